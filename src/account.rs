@@ -0,0 +1,471 @@
+//! 账户保证金状态：以 REST 快照为基准，由账户频道与余额事件频道维护可用保证金、权益与已用保证金，
+//! 可用保证金低于下限或在滚动窗口内跌幅过大时告警
+
+use log::warn;
+use paradex::{
+    structs::{AccountInformation, Balance, BalanceEvent},
+    ws::Message,
+};
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use std::collections::{HashSet, VecDeque};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::markets::decimal;
+use crate::metrics::metrics;
+
+/// 某一时刻的账户保证金
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccountSnapshot {
+    pub free_collateral: Decimal,
+    /// 账户权益（含未实现盈亏）
+    pub equity: Decimal,
+    /// 已用保证金（初始保证金要求）
+    pub margin_used: Decimal,
+    /// 结算资产余额；REST 余额中没有结算资产时为 `None`
+    pub settlement_balance: Option<Decimal>,
+    /// 交易所时间（Unix 毫秒）
+    pub updated_at: u64,
+}
+
+impl AccountSnapshot {
+    /// 由账户信息与余额列表组成的快照
+    pub fn new(account: &AccountInformation, balances: &[Balance]) -> Self {
+        Self {
+            free_collateral: decimal(account.free_collateral),
+            equity: decimal(account.account_value),
+            margin_used: decimal(account.initial_margin_requirement),
+            settlement_balance: balances
+                .iter()
+                .find(|balance| balance.token == account.settlement_asset)
+                .map(|balance| decimal(balance.size)),
+            updated_at: account.updated_at,
+        }
+    }
+}
+
+/// 可用保证金告警
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BalanceAlert {
+    /// 可用保证金低于下限
+    BelowMinimum { free: Decimal, minimum: Decimal },
+    /// 可用保证金相对窗口内最高值的跌幅（百分比）超过阈值
+    Drop {
+        from: Decimal,
+        to: Decimal,
+        pct: Decimal,
+        window: Duration,
+    },
+}
+
+impl fmt::Display for BalanceAlert {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BalanceAlert::BelowMinimum { free, minimum } => write!(
+                f,
+                "free collateral {} is below min_free_collateral {}",
+                free, minimum
+            ),
+            BalanceAlert::Drop {
+                from,
+                to,
+                pct,
+                window,
+            } => write!(
+                f,
+                "free collateral fell {}% from {} to {} within {:?}",
+                pct.round_dp(2),
+                from,
+                to,
+                window
+            ),
+        }
+    }
+}
+
+/// 余额告警回调
+pub type AlertCallback = Box<dyn Fn(&BalanceAlert) + Send + Sync>;
+
+/// 告警阈值
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct AlertThresholds {
+    /// 可用保证金下限；`None` 表示不告警
+    pub min_free_collateral: Option<Decimal>,
+    /// 窗口内跌幅（百分比）阈值；`None` 表示不告警
+    pub max_drop_pct: Option<Decimal>,
+    pub drop_window: Duration,
+}
+
+#[derive(Default)]
+struct State {
+    snapshot: Option<AccountSnapshot>,
+    /// 最近一条账户推送（或 REST 账户信息）的序号，更早的推送被忽略
+    seq_no: Option<u64>,
+    /// 已计入的余额事件，重连后重放的事件不会重复计入
+    applied_events: HashSet<(String, String, u64)>,
+    /// 窗口内的可用保证金（交易所时间，毫秒）
+    history: VecDeque<(u64, Decimal)>,
+    below_minimum: bool,
+    dropped: bool,
+}
+
+struct Inner {
+    state: Mutex<State>,
+    thresholds: AlertThresholds,
+    callback: Mutex<Option<AlertCallback>>,
+}
+
+/// 账户保证金状态，克隆后共享同一份数据
+#[derive(Clone)]
+pub struct AccountState {
+    inner: Arc<Inner>,
+}
+
+impl AccountState {
+    /// 按 `thresholds` 告警，尚无账户数据
+    pub fn new(thresholds: AlertThresholds) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                state: Mutex::new(State::default()),
+                thresholds,
+                callback: Mutex::new(None),
+            }),
+        }
+    }
+
+    /// 告警时额外调用 `callback`（如发送通知）；告警总会记录警告日志并计入指标
+    pub fn on_alert(&self, callback: AlertCallback) {
+        *self.inner.callback.lock().unwrap() = Some(callback);
+    }
+
+    /// 处理账户与余额事件消息，其他消息忽略
+    pub fn on_message(&self, message: &Message) {
+        match message {
+            Message::Account(account) => {
+                self.apply_account(account);
+            }
+            Message::BalanceEvent(event) => {
+                self.apply_balance_event(event);
+            }
+            _ => {}
+        }
+    }
+
+    /// 以 REST 快照为新的基准；快照之前的余额事件不再计入
+    pub fn reconcile(&self, account: &AccountInformation, balances: &[Balance]) {
+        let snapshot = AccountSnapshot::new(account, balances);
+        let mut state = self.inner.state.lock().unwrap();
+        if let Some(ref local) = state.snapshot {
+            if local.free_collateral != snapshot.free_collateral {
+                warn!(
+                    "Free collateral divergence: websocket {} vs REST {}",
+                    local.free_collateral, snapshot.free_collateral
+                );
+            }
+        }
+        state.seq_no = Some(account.seq_no);
+        let alerts = self.update(&mut state, snapshot);
+        drop(state);
+        self.alert(&alerts);
+    }
+
+    /// 应用一条账户推送；`seq_no` 早于当前状态的推送被忽略，返回 `false`
+    pub fn apply_account(&self, account: &AccountInformation) -> bool {
+        let mut state = self.inner.state.lock().unwrap();
+        if state.seq_no.is_some_and(|seq_no| seq_no > account.seq_no) {
+            return false;
+        }
+        state.seq_no = Some(account.seq_no);
+        let settlement_balance = state
+            .snapshot
+            .as_ref()
+            .and_then(|snapshot| snapshot.settlement_balance);
+        let snapshot = AccountSnapshot {
+            settlement_balance,
+            ..AccountSnapshot::new(account, &[])
+        };
+        let alerts = self.update(&mut state, snapshot);
+        drop(state);
+        self.alert(&alerts);
+        true
+    }
+
+    /// 按余额变化调整可用保证金与权益；已计入或早于当前快照的事件被忽略，返回 `false`
+    pub fn apply_balance_event(&self, event: &BalanceEvent) -> bool {
+        let mut state = self.inner.state.lock().unwrap();
+        let Some(mut snapshot) = state.snapshot.clone() else {
+            return false;
+        };
+        if event.created_at <= snapshot.updated_at {
+            return false;
+        }
+        let key = (
+            event.fill_id.clone(),
+            event.market.clone(),
+            event.created_at,
+        );
+        if !state.applied_events.insert(key) {
+            return false;
+        }
+        let delta = decimal(event.settlement_asset_balance_after)
+            - decimal(event.settlement_asset_balance_before);
+        snapshot.free_collateral += delta;
+        snapshot.equity += delta;
+        snapshot.settlement_balance = Some(decimal(event.settlement_asset_balance_after));
+        snapshot.updated_at = event.created_at;
+        let alerts = self.update(&mut state, snapshot);
+        drop(state);
+        self.alert(&alerts);
+        true
+    }
+
+    /// 最近一次账户快照；尚未收到时为 `None`
+    pub fn snapshot(&self) -> Option<AccountSnapshot> {
+        self.inner.state.lock().unwrap().snapshot.clone()
+    }
+
+    /// 可用保证金
+    pub fn free_collateral(&self) -> Option<Decimal> {
+        self.snapshot().map(|snapshot| snapshot.free_collateral)
+    }
+
+    /// 账户权益
+    pub fn equity(&self) -> Option<Decimal> {
+        self.snapshot().map(|snapshot| snapshot.equity)
+    }
+
+    /// 已占用保证金
+    pub fn margin_used(&self) -> Option<Decimal> {
+        self.snapshot().map(|snapshot| snapshot.margin_used)
+    }
+
+    /// 保存新的状态并返回新触发的告警；告警在释放锁之后发出，回调中可以读取状态
+    fn update(&self, state: &mut State, snapshot: AccountSnapshot) -> Vec<BalanceAlert> {
+        let (now, free) = (snapshot.updated_at, snapshot.free_collateral);
+        metrics().set_account(
+            free.to_f64().unwrap_or_default(),
+            snapshot.equity.to_f64().unwrap_or_default(),
+            snapshot.margin_used.to_f64().unwrap_or_default(),
+        );
+        state.snapshot = Some(snapshot);
+
+        let thresholds = &self.inner.thresholds;
+        let mut alerts = Vec::new();
+        if let Some(minimum) = thresholds.min_free_collateral {
+            let below = free < minimum;
+            if below && !state.below_minimum {
+                alerts.push(BalanceAlert::BelowMinimum { free, minimum });
+            }
+            state.below_minimum = below;
+        }
+        if let Some(threshold) = thresholds.max_drop_pct {
+            let window_ms = thresholds.drop_window.as_millis() as u64;
+            state.history.push_back((now, free));
+            while state
+                .history
+                .front()
+                .is_some_and(|(at, _)| *at + window_ms < now)
+            {
+                state.history.pop_front();
+            }
+            let peak = state
+                .history
+                .iter()
+                .map(|(_, free)| *free)
+                .max()
+                .unwrap_or(free);
+            let pct = if peak > Decimal::ZERO {
+                (peak - free) / peak * Decimal::ONE_HUNDRED
+            } else {
+                Decimal::ZERO
+            };
+            let dropped = pct > threshold;
+            if dropped && !state.dropped {
+                alerts.push(BalanceAlert::Drop {
+                    from: peak,
+                    to: free,
+                    pct,
+                    window: thresholds.drop_window,
+                });
+            }
+            state.dropped = dropped;
+        }
+        alerts
+    }
+
+    fn alert(&self, alerts: &[BalanceAlert]) {
+        for alert in alerts {
+            warn!("Low balance: {}", alert);
+            metrics().balance_alert();
+            if let Some(ref callback) = *self.inner.callback.lock().unwrap() {
+                callback(alert);
+            }
+        }
+    }
+}
+
+/// 实时维护的账户状态与 REST 快照的对照表
+pub fn format_comparison(live: Option<&AccountSnapshot>, rest: &AccountSnapshot) -> String {
+    let optional = |value: Option<Decimal>| {
+        value.map_or("-".to_string(), |value| value.normalize().to_string())
+    };
+    let rows = [
+        (
+            "free_collateral",
+            live.map(|live| live.free_collateral),
+            Some(rest.free_collateral),
+        ),
+        ("equity", live.map(|live| live.equity), Some(rest.equity)),
+        (
+            "margin_used",
+            live.map(|live| live.margin_used),
+            Some(rest.margin_used),
+        ),
+        (
+            "settlement_balance",
+            live.and_then(|live| live.settlement_balance),
+            rest.settlement_balance,
+        ),
+    ];
+    let mut out = format!("{:<20}{:>20}{:>20}{:>16}", "", "LIVE", "REST", "DIFF");
+    for (name, live, rest) in rows {
+        let diff = match (live, rest) {
+            (Some(live), Some(rest)) => Some(live - rest),
+            _ => None,
+        };
+        out.push_str(&format!(
+            "\n{:<20}{:>20}{:>20}{:>16}",
+            name,
+            optional(live),
+            optional(rest),
+            optional(diff)
+        ));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use paradex::structs::AccountStatus;
+
+    fn account(free: f64, seq_no: u64, updated_at: u64) -> AccountInformation {
+        AccountInformation {
+            account: "0x1".to_string(),
+            account_value: free + 500.0,
+            free_collateral: free,
+            initial_margin_requirement: 500.0,
+            maintenance_margin_requirement: 250.0,
+            margin_cushion: free,
+            seq_no,
+            settlement_asset: "USDC".to_string(),
+            status: AccountStatus::ACTIVE,
+            total_collateral: free + 500.0,
+            updated_at,
+        }
+    }
+
+    fn event(fill_id: &str, before: f64, after: f64, created_at: u64) -> BalanceEvent {
+        BalanceEvent {
+            fill_id: fill_id.to_string(),
+            market: "BTC-USD-PERP".to_string(),
+            status: "COMPLETED".to_string(),
+            settlement_asset_balance_before: before,
+            settlement_asset_balance_after: after,
+            settlement_asset_price: 1.0,
+            funding_index: 0.0,
+            realized_pnl: after - before,
+            fees: 0.0,
+            realized_funding: 0.0,
+            created_at,
+        }
+    }
+
+    fn balances(size: f64) -> Vec<Balance> {
+        vec![Balance {
+            token: "USDC".to_string(),
+            size,
+            last_updated_at: 0,
+        }]
+    }
+
+    #[test]
+    fn balance_events_are_counted_once() {
+        let state = AccountState::new(AlertThresholds::default());
+        // 没有基准时无法计入增量
+        assert!(!state.apply_balance_event(&event("f0", 1000.0, 990.0, 500)));
+        state.reconcile(&account(1000.0, 1, 1_000), &balances(1500.0));
+
+        // 快照之前的事件已包含在快照中
+        assert!(!state.apply_balance_event(&event("f1", 1510.0, 1500.0, 900)));
+        assert!(state.apply_balance_event(&event("f2", 1500.0, 1480.0, 2_000)));
+        // 重连后重放的同一事件不再计入
+        assert!(!state.apply_balance_event(&event("f2", 1500.0, 1480.0, 2_000)));
+        assert_eq!(state.free_collateral(), Some(Decimal::from(980)));
+        assert_eq!(state.equity(), Some(Decimal::from(1480)));
+        assert_eq!(
+            state.snapshot().unwrap().settlement_balance,
+            Some(Decimal::from(1480))
+        );
+
+        // 账户推送覆盖增量结果，迟到的旧推送被忽略
+        assert!(state.apply_account(&account(975.0, 3, 3_000)));
+        assert!(!state.apply_account(&account(2000.0, 2, 2_500)));
+        assert_eq!(state.free_collateral(), Some(Decimal::from(975)));
+        assert_eq!(state.margin_used(), Some(Decimal::from(500)));
+    }
+
+    #[test]
+    fn alerts_fire_once_until_recovered() {
+        let state = AccountState::new(AlertThresholds {
+            min_free_collateral: Some(Decimal::from(500)),
+            max_drop_pct: Some(Decimal::from(20)),
+            drop_window: Duration::from_secs(60),
+        });
+        let alerts = Arc::new(Mutex::new(Vec::new()));
+        let received = alerts.clone();
+        state.on_alert(Box::new(move |alert| {
+            received.lock().unwrap().push(alert.clone())
+        }));
+
+        state.reconcile(&account(1000.0, 1, 0), &balances(1000.0));
+        // 跌幅 25%，在 60 秒窗口内
+        state.apply_account(&account(750.0, 2, 30_000));
+        state.apply_account(&account(700.0, 3, 40_000));
+        // 低于下限
+        state.apply_account(&account(400.0, 4, 50_000));
+        state.apply_account(&account(350.0, 5, 55_000));
+        // 回到阈值以内后再次越过会重新告警；1000 已移出窗口
+        state.apply_account(&account(600.0, 6, 200_000));
+        state.apply_account(&account(450.0, 7, 210_000));
+
+        let alerts = alerts.lock().unwrap();
+        assert_eq!(
+            *alerts,
+            [
+                BalanceAlert::Drop {
+                    from: Decimal::from(1000),
+                    to: Decimal::from(750),
+                    pct: Decimal::from(25),
+                    window: Duration::from_secs(60),
+                },
+                BalanceAlert::BelowMinimum {
+                    free: Decimal::from(400),
+                    minimum: Decimal::from(500),
+                },
+                BalanceAlert::BelowMinimum {
+                    free: Decimal::from(450),
+                    minimum: Decimal::from(500),
+                },
+                BalanceAlert::Drop {
+                    from: Decimal::from(600),
+                    to: Decimal::from(450),
+                    pct: Decimal::from(25),
+                    window: Duration::from_secs(60),
+                },
+            ]
+        );
+    }
+}
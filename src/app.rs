@@ -0,0 +1,1159 @@
+//! 各子命令共用的启动流程：TLS、日志、环境变量、配置与客户端构建
+
+/// onboarding、认证与账户查询类子命令
+pub mod account;
+/// 行情查询与订阅类子命令
+pub mod market;
+/// 撤单、触发单与平仓子命令
+pub mod orders;
+/// 下单类子命令的会话与任务
+pub mod trade;
+
+use log::{debug, error, info, warn};
+use paradex::{
+    rest::Client,
+    structs::OrderInstruction,
+    url::URL,
+    ws::{Channel, Message},
+};
+use rust_decimal::Decimal;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{mpsc, watch};
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+use trade_lighter_paradex::account::{AccountState, AlertThresholds};
+use trade_lighter_paradex::config::{
+    self, BboLayer, BookLayer, ConfigError, Environment, OrderLayer, RiskLayer, Settings,
+    SettingsLayer, WsChannel, DEFAULT_CONFIG_FILE,
+};
+use trade_lighter_paradex::env::{self, CredentialsError, ETH_ACCOUNT_ENV, PARADEX_ACCOUNT_ENV};
+use trade_lighter_paradex::latency::latency;
+use trade_lighter_paradex::logging;
+use trade_lighter_paradex::market_data::{
+    BboCache, BboDedup, BookAuditor, Dispatching, EventBus, FeedCallback, FeedWatchdog,
+    GuardedSource, LiveConnector, MarketDataSource, OrderBooks, ReconnectPolicy, Reconnecting,
+    Signals, SubscriptionRegistry, TradeTape,
+};
+use trade_lighter_paradex::markets::{base_asset, MarketRegistry};
+use trade_lighter_paradex::metrics::{metrics, MeteredSource};
+use trade_lighter_paradex::onboarding::{
+    derive_stark_key_from_eth, measure_clock_drift, JwtToken, OnboardingError, ParadexConfig,
+    ParadexSigner,
+};
+use trade_lighter_paradex::orders::{OrderError, OrderKind, OrderSpec};
+use trade_lighter_paradex::recorder::{RecordHandle, RecordOptions, Recorder};
+use trade_lighter_paradex::replay::Replay;
+use trade_lighter_paradex::secrets::{
+    EnvSecretProvider, KeySource, KeyringSecretProvider, SecretKey, SecretProvider, PRIVATE_KEY_ENV,
+};
+use trade_lighter_paradex::spread::lighter::LighterFeed;
+use trade_lighter_paradex::spread::{SharedSpreadMonitor, SpreadConfig, SpreadMonitor};
+use trade_lighter_paradex::strategies::{
+    GridConfig, MakerConfig, TrailDistance, TrailingConfig, TwapConfig, TwapError,
+};
+
+use crate::{Args, Command, FundingCommand, TradeArgs, TwapArgs};
+
+/// 初始化 rustls CryptoProvider（必须在任何网络操作之前）
+pub fn install_crypto_provider() {
+    rustls::crypto::aws_lc_rs::default_provider()
+        .install_default()
+        .expect("Failed to install rustls crypto provider");
+}
+
+/// 初始化日志并加载 `.env`；`auth --quiet` 仅保留错误日志
+pub fn init(args: &Args) {
+    let log_level = match args.command {
+        Command::Auth { quiet: true, .. } => log::Level::Error,
+        _ => args.log_level,
+    };
+    logging::init(log_level, args.log_format).unwrap();
+    dotenvy::dotenv().ok();
+}
+
+/// 合并运行配置：命令行 > 配置文件 > 环境变量 > 默认值
+pub fn load_settings(args: &Args) -> Result<Settings, ConfigError> {
+    let file = match args.config {
+        Some(ref path) => SettingsLayer::load(path, true)?,
+        None => SettingsLayer::load(Path::new(DEFAULT_CONFIG_FILE), false)?,
+    };
+    let env = SettingsLayer::from_env(|key| std::env::var(key).ok())?;
+    let mut cli = SettingsLayer {
+        environment: args.production.then_some(Environment::Production),
+        symbols: (!args.symbols.is_empty()).then(|| args.symbols.clone()),
+        channels: args.channels.clone(),
+        run_duration_secs: if args.forever {
+            Some(0)
+        } else {
+            args.run_duration_secs
+        },
+        risk: RiskLayer {
+            max_position: args.max_position,
+            max_notional: args.max_notional,
+            funding_alert_bps: args.funding_alert_bps,
+            max_slippage_bps: args.max_slippage_bps,
+            ..RiskLayer::default()
+        },
+        order_book: BookLayer {
+            refresh_rate: args.book_refresh,
+            price_ticks: (!args.book_price_tick.is_empty()).then(|| args.book_price_tick.clone()),
+        },
+        bbo: BboLayer {
+            ignore_size_changes: args.bbo_ignore_size.then_some(true),
+        },
+        ..SettingsLayer::default()
+    };
+    if let Some(trade) = args.command.trade_args() {
+        cli.trade_symbol = trade.trade_symbol.clone();
+        cli.order = OrderLayer {
+            size: trade.order_size,
+            recv_window_ms: trade.recv_window_ms,
+            stp: trade.stp,
+            ..OrderLayer::default()
+        };
+    }
+    env.merge(file).merge(cli).resolve()
+}
+
+/// 在访问网络前校验 `.env` 中的账户变量，并检查当前命令必需的变量均已设置；
+/// 不使用账户的命令（如 `stream`）不做检查
+///
+/// 使用 `--profile` 时账户来自配置文件，不检查环境变量；设置了 `eth_private_key_hex`
+/// 时私钥与 StarkNet 地址可由以太坊私钥派生，不要求显式设置。
+pub fn check_env_credentials(args: &Args) -> Result<(), CredentialsError> {
+    if args.profile.is_some() {
+        return Ok(());
+    }
+    let mut required = match args.command {
+        Command::Onboard { .. } => vec![PARADEX_ACCOUNT_ENV, ETH_ACCOUNT_ENV],
+        Command::Trade(_)
+        | Command::Twap(_)
+        | Command::Grid(_)
+        | Command::Mm(_)
+        | Command::Trailing(_)
+        | Command::Bracket(_)
+            if trading_on_paper(args) =>
+        {
+            return Ok(())
+        }
+        Command::Auth { .. }
+        | Command::Account { .. }
+        | Command::Balance { .. }
+        | Command::Trade(_)
+        | Command::Twap(_)
+        | Command::Grid(_)
+        | Command::Mm(_)
+        | Command::Trailing(_)
+        | Command::Bracket(_) => {
+            vec![PARADEX_ACCOUNT_ENV]
+        }
+        Command::CancelAll { .. } if !args.dry_run => vec![PARADEX_ACCOUNT_ENV],
+        Command::Cancel { .. }
+        | Command::ClosePosition { .. }
+        | Command::Fills { .. }
+        | Command::Funding {
+            mode: FundingCommand::Payments { .. },
+        } => vec![PARADEX_ACCOUNT_ENV],
+        _ => return Ok(()),
+    };
+    let vars = env::load_credentials()?;
+    if std::env::var("eth_private_key_hex").is_ok() {
+        return Ok(());
+    }
+    if args.key_source == KeySource::Env {
+        required.insert(0, PRIVATE_KEY_ENV);
+    }
+    vars.require(&required)
+}
+
+/// 是否配置了私钥来源（不读取钥匙串、不访问网络），仅用于参数校验
+fn private_key_configured(args: &Args) -> bool {
+    let env_key =
+        || std::env::var(PRIVATE_KEY_ENV).is_ok() || std::env::var("eth_private_key_hex").is_ok();
+    match (args.key_source, &args.profile) {
+        (KeySource::Keyring, _) => true,
+        // profile 读取失败时由 `Credentials::load` 报告具体错误
+        (KeySource::Env, Some(name)) => config::load_profile(&args.accounts_file, name)
+            .map_or(true, |profile| {
+                profile.paradex_account_private_key_hex.is_some() || env_key()
+            }),
+        (KeySource::Env, None) => env_key(),
+    }
+}
+
+/// 检查 `--channels` 中的私有频道：只有 `trade`、`twap`、`grid`、`mm` 与 `bracket` 会订阅，且需要私钥
+pub fn check_channels(args: &Args) -> Result<(), String> {
+    let Some(ref channels) = args.channels else {
+        return Ok(());
+    };
+    let private = channels.private();
+    if private.is_empty() {
+        return Ok(());
+    }
+    let names: Vec<_> = private.iter().map(|c| c.cli_name()).collect();
+    match args.command.trade_args() {
+        Some(trade) if trade.paper => Err(format!(
+            "private channels ({}) are not subscribed in --paper mode; remove them from --channels",
+            names.join(",")
+        )),
+        Some(_) if private_key_configured(args) => Ok(()),
+        Some(_) => Err(format!(
+            "private channels ({}) require a Paradex private key: set {} (or eth_private_key_hex), \
+             use --profile / --key-source keyring, or remove them from --channels",
+            names.join(","),
+            PRIVATE_KEY_ENV
+        )),
+        None => Err(format!(
+            "private channels ({}) are only subscribed by the trade, twap, grid, mm and bracket subcommands; remove them from --channels",
+            names.join(",")
+        )),
+    }
+}
+
+/// `--record` 只对订阅行情的 `stream`、`trade`、`twap`、`grid`、`mm`、`trailing` 与 `bracket` 有效；`--replay` 不下真实订单，
+/// 只用于 `stream`、`summary` 与 `trade` / `twap` / `grid` / `mm` / `bracket` 的 `--paper`
+pub fn check_market_data_args(args: &Args) -> Result<(), String> {
+    match (&args.record, &args.command) {
+        (None, _)
+        | (
+            Some(_),
+            Command::Stream { .. }
+            | Command::Trade(_)
+            | Command::Twap(_)
+            | Command::Grid(_)
+            | Command::Mm(_)
+            | Command::Trailing(_)
+            | Command::Bracket(_),
+        ) => {}
+        (Some(_), _) => {
+            return Err(
+                "--record is only supported by the stream, trade, twap, grid, mm, trailing and bracket subcommands"
+                    .to_string(),
+            )
+        }
+    }
+    match (&args.replay, &args.command) {
+        (None, _) | (Some(_), Command::Stream { .. } | Command::Summary { .. }) => Ok(()),
+        (Some(_), _) if trading_on_paper(args) => Ok(()),
+        (Some(_), _) => Err(
+            "--replay is only supported by stream, summary and the --paper mode of trade, twap, grid, mm and bracket"
+                .to_string(),
+        ),
+    }
+}
+
+/// `trade` / `twap` / `grid` / `mm` / `bracket` 是否为模拟交易
+fn trading_on_paper(args: &Args) -> bool {
+    args.command.trade_args().is_some_and(|trade| trade.paper)
+}
+
+/// 由 `trade` 参数与运行配置得到下单参数，并检查不依赖行情的参数组合
+pub fn order_spec(trade: &TradeArgs, settings: &Settings) -> Result<OrderSpec, OrderError> {
+    let instruction = match (trade.instruction, trade.order_type) {
+        (Some(instruction), _) => instruction.to_instruction(),
+        (None, OrderKind::Market) => OrderInstruction::IOC,
+        (None, OrderKind::Limit) => settings.order.instruction.clone(),
+    };
+    let spec = OrderSpec {
+        side: trade.side.to_side(),
+        order_type: trade.order_type.to_order_type(),
+        size: settings.order.size,
+        price: trade.price,
+        instruction,
+        client_id: trade.client_id.clone(),
+        reduce_only: trade.reduce_only,
+        trigger_price: None,
+    };
+    spec.validate()?;
+    Ok(spec)
+}
+
+/// 未指定 --max-slippage-bps 且未配置 risk.max_slippage_bps 时，TWAP 追赶吃单的滑点上限（基点）
+const DEFAULT_TWAP_SLIPPAGE_BPS: u32 = 50;
+
+/// 由 `twap` 参数与运行配置得到 TWAP 计划；数量步长在查询市场元数据后填入
+pub fn twap_config(twap: &TwapArgs, settings: &Settings) -> Result<TwapConfig, TwapError> {
+    let interval = Duration::from_secs(twap.twap_interval);
+    let config = TwapConfig {
+        symbol: settings.trade_symbol.clone(),
+        side: twap.trade.side.to_side(),
+        total_size: settings.order.size,
+        duration: Duration::from_secs(twap.twap_duration),
+        interval,
+        passive_timeout: twap
+            .twap_passive_timeout
+            .map_or(interval / 2, Duration::from_secs),
+        catchup: twap.twap_catchup,
+        max_slippage_bps: twap
+            .trade
+            .max_slippage_bps
+            .or(settings.risk.max_slippage_bps)
+            .unwrap_or(Decimal::from(DEFAULT_TWAP_SLIPPAGE_BPS)),
+        size_increment: Decimal::ZERO,
+    };
+    config.validate()?;
+    Ok(config)
+}
+
+/// 由 `[grid]` 配置得到网格参数；价格精度在查询市场元数据后填入
+pub fn grid_config(settings: &Settings) -> Result<GridConfig, String> {
+    let spacing = settings
+        .grid
+        .spacing
+        .ok_or("grid.spacing must be set in the [grid] section of the config file")?;
+    let config = GridConfig {
+        symbol: settings.trade_symbol.clone(),
+        center: settings.grid.center_price,
+        spacing,
+        levels: settings.grid.levels,
+        size: settings.grid.size,
+        price_tick: Decimal::ZERO,
+    };
+    config.validate().map_err(|e| e.to_string())?;
+    Ok(config)
+}
+
+/// 由 `[mm]` 配置得到做市参数；做市的市场须已订阅行情，价格精度在查询市场元数据后填入
+pub fn maker_config(settings: &Settings) -> Result<MakerConfig, String> {
+    if let Some(symbol) = settings
+        .mm
+        .symbols
+        .iter()
+        .find(|symbol| !settings.symbols.contains(symbol))
+    {
+        return Err(format!(
+            "mm symbol {} is not in the subscribed symbols",
+            symbol
+        ));
+    }
+    let config = MakerConfig {
+        symbols: settings.mm.symbols.clone(),
+        half_spread_bps: settings.mm.half_spread_bps,
+        size: settings.mm.size,
+        tolerance_ticks: settings.mm.tolerance_ticks,
+        price_ticks: BTreeMap::new(),
+        min_requote_interval: Duration::from_millis(settings.mm.min_requote_ms),
+        skew_bps_per_unit: settings.mm.skew_bps_per_unit,
+        max_inventory: settings.mm.max_inventory,
+    };
+    config.validate().map_err(|e| e.to_string())?;
+    Ok(config)
+}
+
+/// 由 `[trailing]` 配置得到追踪止损参数；止损的市场为 `trade_symbol`，价格精度在查询市场元数据后填入
+pub fn trailing_config(settings: &Settings) -> Result<TrailingConfig, String> {
+    let trailing = &settings.trailing;
+    let side = trailing
+        .side
+        .ok_or("trailing.side must be set in the [trailing] section of the config file")?;
+    let distance =
+        match (trailing.distance, trailing.distance_bps) {
+            (Some(distance), _) => TrailDistance::Absolute(distance),
+            (None, Some(bps)) => TrailDistance::Bps(bps),
+            (None, None) => return Err(
+                "trailing.distance or trailing.distance_bps must be set in the [trailing] section"
+                    .to_string(),
+            ),
+        };
+    let config = TrailingConfig {
+        symbol: settings.trade_symbol.clone(),
+        side,
+        size: trailing.size,
+        distance,
+        mode: trailing.mode,
+        close: trailing.close,
+        max_slippage_bps: trailing.max_slippage_bps,
+        price_tick: Decimal::ZERO,
+        min_step_ticks: trailing.min_step_ticks,
+    };
+    config.validate().map_err(|e| e.to_string())?;
+    Ok(config)
+}
+
+/// 按环境构建网络配置并应用命令行参数（不访问网络）
+pub fn paradex_config(args: &Args, settings: &Settings) -> ParadexConfig {
+    let mut config = match settings.environment {
+        Environment::Production => ParadexConfig::production(),
+        Environment::Testnet => ParadexConfig::testnet(),
+    }
+    .with_env_overrides();
+    if config.has_custom_endpoints() {
+        warn!(
+            "Using custom endpoints {} / {}; the paradex REST/WS clients still connect to the built-in {:?} endpoints",
+            config.base_url, config.ws_url, config.network
+        );
+    }
+    config.log_sensitive = args.log_sensitive;
+    config.expiry_secs = args.jwt_expiry_secs;
+    config.retry.max_attempts = args.http_max_attempts;
+    config.retry.max_rate_limit_retries = args.rate_limit_retries;
+    config.stp = settings.order.stp.to_stp();
+    if let Some(recv_window) = settings.order.recv_window_ms {
+        config.recv_window = Some(recv_window);
+    }
+    config
+}
+
+/// 本地时钟偏差超过 recv_window 的一半时订单容易被拒绝，下单前测量并告警
+pub async fn check_clock_drift(config: &ParadexConfig) {
+    let Some(recv_window) = config.recv_window else {
+        return;
+    };
+    match measure_clock_drift(&reqwest::Client::new(), &config.base_url).await {
+        Ok(drift) if drift_exceeds_window(drift, recv_window) => warn!(
+            "Local clock differs from Paradex server time by {}ms, more than half of recv_window {}ms; \
+             orders may be rejected, sync the system clock (e.g. via NTP)",
+            drift, recv_window
+        ),
+        Ok(drift) => debug!(
+            "Local clock differs from Paradex server time by {}ms (recv_window {}ms)",
+            drift, recv_window
+        ),
+        Err(e) => warn!("Failed to measure clock drift against Paradex server: {}", e),
+    }
+}
+
+fn drift_exceeds_window(drift_ms: i64, recv_window_ms: u64) -> bool {
+    drift_ms.unsigned_abs() > recv_window_ms / 2
+}
+
+/// 优先使用 `/system/config` 返回的链 ID 与账户类哈希，失败时沿用内置配置
+pub async fn with_system_config(config: ParadexConfig) -> ParadexConfig {
+    let config = match ParadexConfig::from_system_config(&reqwest::Client::new(), &config).await {
+        Ok(fetched) => fetched,
+        Err(e) => {
+            warn!(
+                "Failed to fetch Paradex system config, falling back to built-in settings: {}",
+                e
+            );
+            config
+        }
+    };
+    info!("Using StarkNet chain id {}", config.starknet_chain_id);
+    config
+}
+
+/// 钥匙串条目名，按环境与 profile 区分
+pub fn keyring_account(args: &Args, environment: Environment) -> String {
+    format!(
+        "{}:{}",
+        match environment {
+            Environment::Production => "production",
+            Environment::Testnet => "testnet",
+        },
+        args.profile.as_deref().unwrap_or("default")
+    )
+}
+
+/// 账户凭据：Stark 私钥、以太坊与 StarkNet 账户地址，以及可选的子账户
+pub struct Credentials {
+    pub private_key: Option<SecretKey>,
+    pub eth_account: Option<String>,
+    pub starknet_account: Option<String>,
+    /// 子账户的 Stark 私钥与地址
+    pub subaccount: Option<(SecretKey, String)>,
+}
+
+impl Credentials {
+    /// 从选定的 profile、环境变量或钥匙串读取账户信息；出错时直接退出进程
+    ///
+    /// 未提供 Stark 私钥时从以太坊私钥派生，需要 `config` 中的 L1 链 ID。
+    pub fn load(args: &Args, config: &ParadexConfig, environment: Environment) -> Self {
+        let key_provider: Box<dyn SecretProvider> = match args.key_source {
+            KeySource::Env => Box::new(EnvSecretProvider::new(PRIVATE_KEY_ENV)),
+            KeySource::Keyring => Box::new(KeyringSecretProvider::new(&keyring_account(
+                args,
+                environment,
+            ))),
+        };
+        let load_key = || {
+            key_provider.private_key().unwrap_or_else(|e| {
+                error!("Failed to load private key: {}", e);
+                std::process::exit(1);
+            })
+        };
+
+        let (mut private_key, mut eth_account, mut starknet_account) =
+            if let Some(ref name) = args.profile {
+                let profile = config::load_profile(&args.accounts_file, name)
+                    .and_then(|profile| {
+                        profile.check_environment(environment == Environment::Production)?;
+                        Ok(profile)
+                    })
+                    .unwrap_or_else(|e| {
+                        error!("{}", e);
+                        std::process::exit(1);
+                    });
+                info!("Using account profile '{}'", profile.name);
+                let private_key = match args.key_source {
+                    KeySource::Env => profile.paradex_account_private_key_hex.map(SecretKey::new),
+                    KeySource::Keyring => load_key(),
+                };
+                (
+                    private_key,
+                    profile.eth_account_address,
+                    profile.paradex_account_address,
+                )
+            } else {
+                let vars = env::load_credentials().unwrap_or_else(|e| {
+                    error!("{}", e);
+                    std::process::exit(1);
+                });
+                let private_key = match args.key_source {
+                    KeySource::Env => vars.private_key,
+                    KeySource::Keyring => load_key(),
+                };
+                (private_key, vars.eth_account, vars.starknet_account)
+            };
+
+        // 未提供 Stark 私钥时，从以太坊私钥派生
+        if private_key.is_none() {
+            if let Ok(eth_private_key) = std::env::var("eth_private_key_hex") {
+                let derived =
+                    derive_stark_key_from_eth(&eth_private_key, config).unwrap_or_else(|e| {
+                        error!(
+                            "Failed to derive Stark key from Ethereum private key: {}",
+                            e
+                        );
+                        std::process::exit(1);
+                    });
+                info!(
+                    "Derived Paradex account {} (public key 0x{:x}) from Ethereum account {}",
+                    derived.account_address, derived.stark_public_key, derived.eth_address
+                );
+                private_key = Some(derived.stark_private_key);
+                starknet_account = Some(derived.account_address);
+                eth_account.get_or_insert(derived.eth_address);
+            }
+        }
+
+        // 子账户使用自己的 Stark 私钥，REST 客户端据此推导账户地址
+        let subaccount = args.subaccount.as_ref().map(|address| {
+            let key = EnvSecretProvider::new("paradex_subaccount_private_key_hex")
+                .private_key()
+                .ok()
+                .flatten()
+                .unwrap_or_else(|| {
+                    error!("--subaccount requires paradex_subaccount_private_key_hex");
+                    std::process::exit(1);
+                });
+            (key, address.clone())
+        });
+
+        let credentials = Self {
+            private_key,
+            eth_account,
+            starknet_account,
+            subaccount,
+        };
+        if let Some(account) = credentials.account() {
+            logging::set_account(account);
+        }
+        credentials
+    }
+
+    /// 当前会话使用的账户：指定子账户时为子账户地址
+    pub fn account(&self) -> Option<&str> {
+        self.subaccount
+            .as_ref()
+            .map(|(_, address)| address.as_str())
+            .or(self.starknet_account.as_deref())
+    }
+
+    /// 当前会话使用的私钥：指定子账户时为子账户私钥
+    pub fn session_key(&self) -> Option<&SecretKey> {
+        self.subaccount
+            .as_ref()
+            .map(|(key, _)| key)
+            .or(self.private_key.as_ref())
+    }
+
+    /// 由主账户私钥与地址创建签名器；缺少任一项时返回 `None`
+    pub fn signer(&self, config: &ParadexConfig) -> Option<Result<ParadexSigner, OnboardingError>> {
+        let (Some(private_key), Some(account)) = (&self.private_key, &self.starknet_account) else {
+            return None;
+        };
+        Some(ParadexSigner::new(private_key.expose(), account, config))
+    }
+}
+
+/// 以 `key` 创建带私钥的 paradex REST 客户端
+pub async fn private_client(url: URL, key: &SecretKey) -> Client {
+    Client::new(url, Some(key.expose().to_string()))
+        .await
+        .unwrap_or_else(|e| {
+            error!("Failed to create Paradex client: {}", e);
+            std::process::exit(1);
+        })
+}
+
+/// 按 `risk.min_free_collateral` 与 `[account]` 的阈值告警的账户保证金状态
+pub fn account_state(settings: &Settings) -> AccountState {
+    AccountState::new(AlertThresholds {
+        min_free_collateral: settings.risk.min_free_collateral,
+        max_drop_pct: settings.account.max_drop_pct,
+        drop_window: Duration::from_secs(settings.account.drop_window_secs),
+    })
+}
+
+/// 查询市场元数据并校验 `--symbol` 与下单市场；存在未知代码时列出有效市场后退出
+///
+/// 无法获取市场列表时返回空的 registry（跳过校验，价格不对齐）。
+pub async fn validate_markets(url: URL, settings: &Settings) -> MarketRegistry {
+    match MarketRegistry::fetch(url).await {
+        Ok(registry) => {
+            let mut requested = settings.symbols.clone();
+            if !requested.contains(&settings.trade_symbol) {
+                requested.push(settings.trade_symbol.clone());
+            }
+            if let Err(e) = registry.validate(&requested) {
+                error!("{}", e);
+                std::process::exit(1);
+            }
+            registry
+        }
+        Err(e) => {
+            warn!("Skipping market symbol validation: {}", e);
+            MarketRegistry::default()
+        }
+    }
+}
+
+/// Lighter 报价推送给价差监控前缓存的条数
+const SPREAD_FEED_CAPACITY: usize = 256;
+
+/// 按 `[spread]` 建立 Paradex 与 Lighter 的跨所价差监控，并在后台订阅 Lighter 订单簿
+///
+/// 只监控 `spread.markets` 中已订阅的市场；没有这样的市场或重放录制数据时返回 `None`
+/// （录制的 Paradex 行情与实时的 Lighter 报价无法比较）。
+pub fn spread_monitor(args: &Args, settings: &Settings) -> Option<SharedSpreadMonitor> {
+    let spread = &settings.spread;
+    let markets: Vec<_> = settings
+        .symbols
+        .iter()
+        .filter_map(|symbol| Some((symbol, spread.markets.get(symbol)?)))
+        .collect();
+    if markets.is_empty() {
+        return None;
+    }
+    if args.replay.is_some() {
+        warn!("Spread monitoring is not available with --replay");
+        return None;
+    }
+    let mut monitor = SpreadMonitor::new(SpreadConfig {
+        threshold_bps: spread.threshold_bps,
+        max_quote_age: Duration::from_millis(spread.max_quote_age_ms),
+    });
+    for (symbol, lighter) in &markets {
+        monitor.add_market(base_asset(symbol), symbol, &lighter.symbol);
+    }
+    monitor.on_opportunity(Box::new(|opportunity| {
+        info!(
+            "Arbitrage opportunity on {}: buy {:?} @ {}, sell {:?} @ {} ({:.2} bps)",
+            opportunity.market,
+            opportunity.buy_venue,
+            opportunity.buy_price,
+            opportunity.sell_venue,
+            opportunity.sell_price,
+            opportunity.spread_bps
+        )
+    }));
+    let monitor = Arc::new(Mutex::new(monitor));
+    let (sender, feed) = mpsc::channel(SPREAD_FEED_CAPACITY);
+    LighterFeed::new(
+        spread.lighter_url.clone(),
+        markets
+            .iter()
+            .map(|(_, lighter)| (lighter.market_id, lighter.symbol.clone())),
+    )
+    .spawn(sender);
+    tokio::spawn(SpreadMonitor::run_feed(monitor.clone(), feed));
+    Some(monitor)
+}
+
+/// 订阅配置中的公开行情频道（行情摘要、BBO、成交、订单簿与资金费率）
+///
+/// 指定 `spread` 时 BBO 同时推送给跨所价差监控。指定 `events` 时无论配置如何都订阅 BBO 与成交，
+/// 并把已订阅频道的消息转换为 `MarketEvent` 发布到总线上，
+/// 报价缓存、K 线合成与模拟撮合等作为总线的订阅者；
+/// 指定 `recorder` 时所有行情消息同时以原始格式写入录制文件（重放需要原始消息）；订单簿增量维护到 `books` 中对应市场的本地订单簿。
+/// 返回的登记表记录全部订阅 ID，用于退出前取消订阅。
+pub async fn subscribe_market_data(
+    source: &dyn MarketDataSource,
+    settings: &Settings,
+    events: Option<&EventBus>,
+    recorder: Option<&RecordHandle>,
+    books: &OrderBooks,
+    tape: Option<&TradeTape>,
+    spread: Option<&SharedSpreadMonitor>,
+) -> SubscriptionRegistry {
+    let subscriptions = SubscriptionRegistry::new();
+    metrics().watch_subscriptions(subscriptions.clone());
+
+    if settings.subscribes(WsChannel::MarketsSummary) {
+        let summary_events = events.cloned();
+        let summary_id = source
+            .subscribe(
+                Channel::MarketSummary,
+                recording(recorder, WsChannel::MarketsSummary, None, move |message| {
+                    info!(channel = "markets_summary"; "Received MarketSummary message {message:?}");
+                    publish(summary_events.as_ref(), message);
+                }),
+            )
+            .await
+            .unwrap();
+        subscriptions.record(summary_id);
+    }
+
+    // 逐个市场订阅 BBO / Trades / OrderBook / OrderBookDeltas
+    for market_symbol in &settings.symbols {
+        if settings.subscribes(WsChannel::Bbo) || events.is_some() {
+            let bbo_monitor = spread.cloned();
+            let bbo_events = events.cloned();
+            let bbo_id = source
+                .subscribe(
+                    Channel::BBO {
+                        market_symbol: market_symbol.clone(),
+                    },
+                    recording(
+                        recorder,
+                        WsChannel::Bbo,
+                        Some(market_symbol),
+                        move |message| {
+                            // 去重丢弃的未变化报价不再记录日志或更新价差监控
+                            if !publish(bbo_events.as_ref(), message) {
+                                return;
+                            }
+                            info!(channel = "bbo"; "Received BBO message {message:?}");
+                            if let (Message::BBO(bbo), Some(monitor)) = (message, &bbo_monitor) {
+                                monitor.lock().unwrap().on_bbo(bbo);
+                            }
+                        },
+                    ),
+                )
+                .await
+                .unwrap();
+            subscriptions.record(bbo_id);
+        }
+
+        if settings.subscribes(WsChannel::Trades) || events.is_some() || tape.is_some() {
+            let trades_events = events.cloned();
+            let trades_tape = tape.cloned();
+            let trades_id = source
+                .subscribe(
+                    Channel::Trades {
+                        market_symbol: market_symbol.clone(),
+                    },
+                    recording(
+                        recorder,
+                        WsChannel::Trades,
+                        Some(market_symbol),
+                        move |message| {
+                            info!(channel = "trades"; "Received Trades message {message:?}");
+                            publish(trades_events.as_ref(), message);
+                            if let Some(ref tape) = trades_tape {
+                                tape.on_message(message);
+                            }
+                        },
+                    ),
+                )
+                .await
+                .unwrap();
+            subscriptions.record(trades_id);
+        }
+
+        if settings.subscribes(WsChannel::OrderBook) {
+            // 每个价格档位单独订阅，各自维护一本订单簿
+            let ticks: Vec<Option<Decimal>> = if settings.order_book.price_ticks.is_empty() {
+                vec![None]
+            } else {
+                settings
+                    .order_book
+                    .price_ticks
+                    .iter()
+                    .copied()
+                    .map(Some)
+                    .collect()
+            };
+            for tick in ticks {
+                let orderbook_books = books.clone();
+                let orderbook_events = events.cloned();
+                let orderbook_id = source
+                    .subscribe(
+                        Channel::OrderBook {
+                            channel_name: Some("orderbook".into()),
+                            market_symbol: market_symbol.clone(),
+                            refresh_rate: settings.order_book.refresh_rate.as_str().into(),
+                            price_tick: tick.map(|tick| tick.to_string()),
+                        },
+                        recording(recorder, WsChannel::OrderBook, Some(market_symbol), move |message| {
+                            info!(channel = "order_book"; "Received OrderBook message {message:?}");
+                            if let Message::OrderBook(book) = message {
+                                orderbook_books.apply_at(tick, book);
+                            }
+                            publish(orderbook_events.as_ref(), message);
+                        }),
+                    )
+                    .await
+                    .unwrap();
+                subscriptions.record(orderbook_id);
+            }
+        }
+
+        if settings.subscribes(WsChannel::OrderBookDeltas) {
+            let deltas_books = books.clone();
+            let deltas_market = market_symbol.clone();
+            let deltas_events = events.cloned();
+            let orderbook_deltas_id = source
+                .subscribe(
+                    Channel::OrderBookDeltas {
+                        market_symbol: market_symbol.clone(),
+                    },
+                    recording(
+                        recorder,
+                        WsChannel::OrderBookDeltas,
+                        Some(market_symbol),
+                        move |message| {
+                            info!(channel = "order_book_deltas"; "Received OrderBookDeltas message {message:?}");
+                            publish(deltas_events.as_ref(), message);
+                            match message {
+                                Message::OrderBookDeltas(delta) => {
+                                    deltas_books.apply(delta);
+                                }
+                                Message::Disconnected => deltas_books.mark_stale(&deltas_market),
+                                _ => {}
+                            }
+                        },
+                    ),
+                )
+                .await
+                .unwrap();
+            subscriptions.record(orderbook_deltas_id);
+        }
+    }
+
+    if settings.subscribes(WsChannel::FundingData) {
+        let funding_events = events.cloned();
+        let funding_id = source
+            .subscribe(
+                Channel::FundingData {
+                    market_symbol: None,
+                },
+                recording(recorder, WsChannel::FundingData, None, move |message| {
+                    info!(channel = "funding_data"; "Received FundingData message {message:?}");
+                    publish(funding_events.as_ref(), message);
+                }),
+            )
+            .await
+            .unwrap();
+        subscriptions.record(funding_id);
+    }
+
+    subscriptions
+}
+
+/// 把行情消息发布到事件总线（若有）
+fn publish(events: Option<&EventBus>, message: &Message) -> bool {
+    events.is_none_or(|events| events.publish_message(message))
+}
+
+/// 行情事件总线：BBO 经去重后发布，收到的每笔报价写入 `quotes`
+pub fn event_bus(settings: &Settings, quotes: BboCache) -> EventBus {
+    EventBus::new().with_bbo_dedup(
+        BboDedup::new(quotes).with_ignore_size_changes(settings.bbo.ignore_size_changes),
+    )
+}
+
+/// 实时行情延迟汇总日志的间隔
+const LATENCY_LOG_INTERVAL: Duration = Duration::from_secs(60);
+
+/// `--replay` 时重放录制目录（无法打开时退出），否则实时订阅 Paradex WebSocket；
+/// 两者收到的消息都计入指标，订阅回调中的 panic 被捕获并记录
+///
+/// 实时连接断开或长时间没有消息时自动重连并重新订阅，重连后 `books` 重新同步；
+/// `client` 为私有客户端时每次连接都以新的 JWT 认证，`token_updates` 收到轮换的 JWT 时重新认证私有频道。实时行情同时按服务器时间统计延迟，
+/// 并定期输出汇总日志；订阅回调经 `--dispatch-capacity` 大小的队列在独立任务中执行，
+/// 定期输出各频道的消息速率。
+pub async fn market_data_source(
+    args: &Args,
+    config: &ParadexConfig,
+    client: Option<Client>,
+    token_updates: Option<watch::Receiver<JwtToken>>,
+    books: &OrderBooks,
+) -> (Arc<dyn MarketDataSource>, Option<Arc<Replay>>) {
+    let Some(ref dir) = args.replay else {
+        match measure_clock_drift(&reqwest::Client::new(), &config.base_url).await {
+            Ok(offset) => latency().set_clock_offset(offset),
+            Err(e) => warn!(
+                "Failed to measure clock offset, latency uses the local clock: {}",
+                e
+            ),
+        }
+        let connector = LiveConnector::new(config.network, client);
+        let live = match Reconnecting::connect(connector, ReconnectPolicy::default()).await {
+            Ok(live) => live,
+            Err(e) => {
+                error!("Failed to connect to Paradex WebSocket: {}", e);
+                std::process::exit(1);
+            }
+        };
+        let books = books.clone();
+        live.on_reconnect(Box::new(move || books.resync_all()));
+        if let Some(updates) = token_updates {
+            live.reauthenticate_on(updates);
+        }
+        latency().spawn_summary_log(LATENCY_LOG_INTERVAL);
+        let metered = MeteredSource::new(Arc::new(live)).with_latency();
+        let source = Dispatching::new(Arc::new(metered), args.dispatch_capacity);
+        metrics().watch_dispatch(source.counters().clone());
+        source.counters().spawn_rate_log(LATENCY_LOG_INTERVAL);
+        return (guarded(args, Arc::new(source)), None);
+    };
+    match Replay::open(dir, args.speed) {
+        Ok(replay) => {
+            let replay = match args.replay_from {
+                Some(start) => replay.with_start(start),
+                None => replay,
+            };
+            let replay = Arc::new(replay);
+            let metered = Arc::new(MeteredSource::new(replay.clone()));
+            (guarded(args, metered), Some(replay))
+        }
+        Err(e) => {
+            error!("{}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// 捕获订阅回调中的 panic；同一订阅连续 panic `--max-callback-panics` 次后停用该回调
+fn guarded(args: &Args, source: Arc<dyn MarketDataSource>) -> Arc<dyn MarketDataSource> {
+    Arc::new(GuardedSource::new(source).with_max_consecutive_panics(args.max_callback_panics))
+}
+
+/// 检查订阅是否停滞的间隔
+const WATCHDOG_INTERVAL: Duration = Duration::from_secs(1);
+
+/// 按配置的各频道阈值监控 `source` 上的订阅，返回包装后的来源与定期检查的任务（退出前终止）；
+/// 订阅停滞与恢复时调用 `on_status`
+pub fn watch_feeds(
+    settings: &Settings,
+    source: Arc<dyn MarketDataSource>,
+    on_status: Option<FeedCallback>,
+) -> (Arc<dyn MarketDataSource>, JoinHandle<()>) {
+    let thresholds = settings
+        .watchdog
+        .iter()
+        .map(|(channel, secs)| (*channel, Duration::from_secs(*secs)))
+        .collect();
+    let watchdog = FeedWatchdog::new(thresholds);
+    if let Some(callback) = on_status {
+        watchdog.on_status(callback);
+    }
+    let task = watchdog.spawn(WATCHDOG_INTERVAL);
+    (Arc::new(watchdog.watch(source)), task)
+}
+
+/// 每个订阅市场的本地订单簿；实时行情出现序号缺口时按 REST 快照重新同步，重放时只等待录制的快照
+/// 订单簿附带失衡度与微价格信号；指定 `--signal-log-interval` 时定期输出。
+/// 中间价单次变动超过 `--max-price-jump-pct` 时视为异常
+pub fn order_books(args: &Args, settings: &Settings, base_url: &str) -> OrderBooks {
+    let signals = Signals::new(
+        settings.signals.depth,
+        Duration::from_millis(settings.signals.half_life_ms),
+    );
+    if let Some(secs) = args.signal_log_interval {
+        signals.spawn_log(Duration::from_secs(secs));
+    }
+    let books = OrderBooks::new(&settings.symbols)
+        .with_signals(signals)
+        .with_max_price_jump(args.max_price_jump_pct);
+    if args.replay.is_some() {
+        books
+    } else {
+        books.with_rest_resync(base_url)
+    }
+}
+
+/// 指定 `--metrics-port` 时按 `--vwap-windows` 统计成交流水并导出到指标
+pub fn trade_tape(args: &Args) -> Option<TradeTape> {
+    args.metrics_port?;
+    let tape = TradeTape::new(&args.vwap_windows);
+    metrics().watch_trade_tape(tape.clone());
+    Some(tape)
+}
+
+/// 重放时与 `work` 并行推送录制的消息，任一方结束即返回
+pub async fn run_with_replay(replay: Option<&Replay>, work: impl Future<Output = ()>) {
+    let Some(replay) = replay else {
+        return work.await;
+    };
+    tokio::select! {
+        _ = work => {}
+        result = replay.run() => {
+            if let Err(e) = result {
+                error!("Replay failed: {}", e);
+            }
+        }
+    }
+}
+
+/// 订阅回调：指定录制器时先录制消息再执行 `callback`
+fn recording(
+    recorder: Option<&RecordHandle>,
+    channel: WsChannel,
+    market: Option<&str>,
+    callback: impl Fn(&Message) + Send + 'static,
+) -> Box<dyn Fn(&Message) + Send> {
+    let Some(recorder) = recorder.cloned() else {
+        return Box::new(callback);
+    };
+    let market = market.map(str::to_string);
+    Box::new(move |message| {
+        recorder.record(channel, market.as_deref(), message);
+        callback(message);
+    })
+}
+
+/// 启动 `--record` 录制；无法创建目录时退出
+pub fn start_recorder(args: &Args) -> Option<Recorder> {
+    let dir = args.record.as_deref()?;
+    let options = RecordOptions {
+        compression: args.record_compress,
+        max_file_bytes: args.record_max_file_mb * 1024 * 1024,
+    };
+    match Recorder::start_with(dir, options) {
+        Ok(recorder) => Some(recorder),
+        Err(e) => {
+            error!("Failed to create record directory {}: {}", dir.display(), e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// `--record` 时按 `--snapshot-interval` 定期录制各市场本地订单簿的快照
+pub fn spawn_book_snapshots(
+    args: &Args,
+    recorder: Option<&Recorder>,
+    books: &OrderBooks,
+    settings: &Settings,
+) -> Option<JoinHandle<()>> {
+    let recorder = recorder?;
+    Some(recorder.handle().spawn_snapshots(
+        books.clone(),
+        settings.symbols.clone(),
+        Duration::from_secs(args.snapshot_interval),
+        args.snapshot_depth,
+    ))
+}
+
+/// 指定 `--book-audit-interval` 时定期以 REST 快照校验本地订单簿
+pub fn spawn_book_audit(args: &Args, books: &OrderBooks) -> Option<JoinHandle<()>> {
+    let secs = args.book_audit_interval?;
+    if !books.has_rest_snapshots() {
+        warn!("--book-audit-interval needs REST snapshots and is ignored when replaying");
+        return None;
+    }
+    info!(
+        "Auditing order books against REST snapshots every {}s (top {} levels)",
+        secs, args.book_audit_levels
+    );
+    Some(
+        BookAuditor::new(books.clone())
+            .with_levels(args.book_audit_levels)
+            .spawn(Duration::from_secs(secs)),
+    )
+}
+
+/// 退出前写完并关闭录制文件
+pub async fn finish_recorder(recorder: Option<Recorder>) {
+    let Some(recorder) = recorder else {
+        return;
+    };
+    match recorder.finish().await {
+        Ok(lines) => info!("Recorded {} market data messages", lines),
+        Err(e) => error!("Failed to finish market data recording: {}", e),
+    }
+}
+
+/// 等待 Ctrl-C 或（Unix 上的）SIGTERM；无法监听信号时永不返回
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            warn!("Failed to listen for Ctrl-C: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                terminate.recv().await;
+            }
+            Err(e) => {
+                warn!("Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+}
+
+/// 安装退出信号处理：第一次信号取消返回的 token 进入清理，第二次立即退出
+pub fn install_shutdown_handler() -> CancellationToken {
+    let token = CancellationToken::new();
+    let trigger = token.clone();
+    tokio::spawn(async move {
+        shutdown_signal().await;
+        warn!("Shutting down; press Ctrl-C again to force exit");
+        trigger.cancel();
+        shutdown_signal().await;
+        error!("Forced exit");
+        std::process::exit(130);
+    });
+    token
+}
+
+/// 执行 `work` 后继续运行 `run_duration_secs` 秒（0 表示直到收到退出信号）；
+/// `shutdown` 被取消时立即返回，由调用方执行清理
+pub async fn run_until_shutdown(
+    run_duration_secs: u64,
+    shutdown: &CancellationToken,
+    work: impl Future<Output = ()>,
+) {
+    let run = async {
+        work.await;
+        if run_duration_secs == 0 {
+            info!("Running until interrupted (Ctrl-C)");
+            std::future::pending::<()>().await;
+        }
+        tokio::time::sleep(Duration::from_secs(run_duration_secs)).await;
+        info!(
+            "Run duration of {}s elapsed, shutting down",
+            run_duration_secs
+        );
+    };
+    tokio::select! {
+        _ = run => {}
+        _ = shutdown.cancelled() => {}
+    }
+}
+
+/// 取消订阅后等待服务端确认的时间
+const UNSUBSCRIBE_GRACE: Duration = Duration::from_secs(5);
+
+/// 取消全部已登记的订阅并关闭 WebSocket 连接；取消订阅失败时汇总告警，仍会关闭连接
+pub async fn shutdown(source: &dyn MarketDataSource, subscriptions: &SubscriptionRegistry) {
+    subscriptions.shutdown(source, UNSUBSCRIBE_GRACE).await;
+}
@@ -0,0 +1,506 @@
+//! onboarding、认证、账户与历史查询子命令
+
+use log::{error, info, warn};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use paradex::{
+    rest::Client,
+    structs::{AccountInformation, Balance},
+    url::URL,
+    ws::Channel,
+};
+use trade_lighter_paradex::account::{format_comparison, AccountSnapshot};
+use trade_lighter_paradex::config::Settings;
+use trade_lighter_paradex::fills::{fetch_fills, write_csv, write_json, FillFormat};
+use trade_lighter_paradex::funding::{
+    fetch_funding_payments, fetch_funding_rates, format_payments, format_rates, PaymentSummary,
+};
+use trade_lighter_paradex::history::TimeWindow;
+use trade_lighter_paradex::http::AuthedHttpClient;
+use trade_lighter_paradex::market_data::OrderBooks;
+use trade_lighter_paradex::onboarding::{
+    get_jwt_token, is_onboarded, perform_onboarding, JwtManager, OnboardingError,
+    OnboardingOptions, ParadexConfig, ParadexSigner, ServerClock, TokenCache,
+};
+use trade_lighter_paradex::secrets::{self, KeyringSecretProvider, SecretKey};
+
+use super::Credentials;
+use crate::{AccountCommand, Args, Command};
+
+/// 账户未 onboard 时执行 onboarding；已 onboard 视为成功
+pub async fn ensure_onboarded(
+    http_client: &reqwest::Client,
+    signer: &ParadexSigner,
+    eth_account: &str,
+    options: &OnboardingOptions,
+) -> Result<(), OnboardingError> {
+    match is_onboarded(http_client, signer).await {
+        Ok(true) => {
+            info!("Account already onboarded");
+            return Ok(());
+        }
+        Ok(false) => {}
+        Err(e) => warn!("Failed to query onboarding status: {}", e),
+    }
+
+    info!("Performing onboarding...");
+    match perform_onboarding(http_client, signer, eth_account, options).await {
+        Ok(()) => info!("Onboarding completed successfully"),
+        Err(OnboardingError::AlreadyOnboarded) => info!("Account already onboarded"),
+        Err(e) => return Err(e),
+    }
+    Ok(())
+}
+
+/// 启动阶段被限流时，退出前按服务器要求等待，避免崩溃重启循环持续触发限流
+pub async fn back_off_if_rate_limited(error: &OnboardingError) {
+    if let OnboardingError::RateLimited {
+        retry_after: Some(delay),
+    } = error
+    {
+        warn!(
+            "Paradex is rate limiting startup, waiting {:?} before exiting",
+            delay
+        );
+        tokio::time::sleep(*delay).await;
+    }
+}
+
+/// `onboard` 子命令：onboarding（可选获取 JWT）后返回进程退出码
+pub async fn run_onboard(
+    config: &ParadexConfig,
+    private_key: Option<&SecretKey>,
+    starknet_account: Option<&str>,
+    eth_account: Option<&str>,
+    options: &OnboardingOptions,
+    print_jwt: bool,
+) -> i32 {
+    let (Some(private_key), Some(starknet_account), Some(eth_account)) =
+        (private_key, starknet_account, eth_account)
+    else {
+        error!("onboard requires a Paradex private key, StarkNet account and Ethereum account");
+        return 1;
+    };
+    let signer = match ParadexSigner::new(private_key.expose(), starknet_account, config) {
+        Ok(signer) => signer,
+        Err(e) => {
+            error!("{}", e);
+            return 1;
+        }
+    };
+    println!("public_key: 0x{:x}", signer.public_key());
+    println!("account_address: {}", signer.account_address());
+
+    let http_client = reqwest::Client::new();
+    if let Err(e) = ensure_onboarded(&http_client, &signer, eth_account, options).await {
+        error!("Onboarding failed: {}", e);
+        return 1;
+    }
+
+    if print_jwt {
+        match get_jwt_token(&http_client, &signer, &ServerClock::new()).await {
+            Ok(jwt) => println!("jwt: {}", jwt.token()),
+            Err(e) => {
+                error!("Failed to get JWT token: {}", e);
+                return 1;
+            }
+        }
+    }
+    0
+}
+
+/// `auth` 子命令：获取 JWT 并仅输出到 stdout，便于管道给其它工具
+pub async fn run_auth(
+    config: &ParadexConfig,
+    private_key: Option<&SecretKey>,
+    starknet_account: Option<&str>,
+    json: bool,
+) -> i32 {
+    let (Some(private_key), Some(starknet_account)) = (private_key, starknet_account) else {
+        error!("auth requires a Paradex private key and StarkNet account");
+        return 1;
+    };
+    let signer = match ParadexSigner::new(private_key.expose(), starknet_account, config) {
+        Ok(signer) => signer,
+        Err(e) => {
+            error!("{}", e);
+            return 1;
+        }
+    };
+
+    let http_client = reqwest::Client::new();
+    let jwt = match get_jwt_token(&http_client, &signer, &ServerClock::new()).await {
+        Ok(jwt) => jwt,
+        Err(e) => {
+            error!("Failed to get JWT token: {}", e);
+            return 1;
+        }
+    };
+
+    if json {
+        println!(
+            "{}",
+            serde_json::json!({"jwt_token": jwt.token(), "expires_at": jwt.expires_at})
+        );
+    } else {
+        println!("{}", jwt.token());
+        println!("expires_at: {}", jwt.expires_at);
+    }
+    0
+}
+
+/// 认证并创建 `AuthedHttpClient`；返回的 `JwtManager` 须保留到请求结束以持续刷新 token
+async fn authed_client(
+    config: &ParadexConfig,
+    private_key: Option<&SecretKey>,
+    starknet_account: Option<&str>,
+) -> Result<(AuthedHttpClient, JwtManager), i32> {
+    let (Some(private_key), Some(starknet_account)) = (private_key, starknet_account) else {
+        error!("This command requires a Paradex private key and StarkNet account");
+        return Err(1);
+    };
+    let signer = match ParadexSigner::new(private_key.expose(), starknet_account, config) {
+        Ok(signer) => Arc::new(signer),
+        Err(e) => {
+            error!("{}", e);
+            return Err(1);
+        }
+    };
+
+    let http_client = reqwest::Client::new();
+    let token_cache = TokenCache::in_default_dir(config, starknet_account);
+    let jwt_manager =
+        match JwtManager::start(http_client.clone(), signer.clone(), token_cache).await {
+            Ok(manager) => manager,
+            Err(e) => {
+                error!("Failed to get JWT token: {}", e);
+                return Err(1);
+            }
+        };
+    let client = AuthedHttpClient::new(http_client, signer, &jwt_manager);
+    Ok((client, jwt_manager))
+}
+
+/// `account` / `cancel-all` 子命令：通过 `AuthedHttpClient` 调用 paradex crate 未封装的接口
+pub async fn run_rest_command(
+    config: &ParadexConfig,
+    private_key: Option<&SecretKey>,
+    starknet_account: Option<&str>,
+    command: &Command,
+) -> i32 {
+    let (client, _jwt_manager) = match authed_client(config, private_key, starknet_account).await {
+        Ok(authed) => authed,
+        Err(code) => return code,
+    };
+
+    let result = match command {
+        Command::Account {
+            action: Some(AccountCommand::Profile),
+        } => client
+            .get_json::<serde_json::Value>("/account/profile")
+            .await
+            .map(|profile| {
+                println!(
+                    "{}",
+                    serde_json::to_string_pretty(&profile).unwrap_or_default()
+                )
+            }),
+        Command::Account {
+            action: Some(AccountCommand::SetUsername { username }),
+        } => client
+            .post_json::<serde_json::Value, _>(
+                "/account/profile/username",
+                &serde_json::json!({ "username": username }),
+            )
+            .await
+            .map(|_| info!("Username set to {}", username)),
+        Command::CancelAll { market } => {
+            let path = match market {
+                Some(market) => format!("/orders?market={}", market),
+                None => "/orders".to_string(),
+            };
+            client
+                .delete(&path)
+                .await
+                .map(|()| info!("Cancelled all open orders"))
+        }
+        _ => unreachable!("not a REST command"),
+    };
+    match result {
+        Ok(()) => 0,
+        Err(e) => {
+            error!("Request failed: {}", e);
+            1
+        }
+    }
+}
+
+/// `fills` 子命令：按市场分页拉取成交历史，按时间排序后写到 stdout 或 `out`
+pub async fn run_fills(
+    config: &ParadexConfig,
+    credentials: &Credentials,
+    window: TimeWindow,
+    symbols: &[String],
+    format: FillFormat,
+    out: Option<&Path>,
+) -> i32 {
+    let (client, _jwt_manager) = match authed_client(
+        config,
+        credentials.private_key.as_ref(),
+        credentials.starknet_account.as_deref(),
+    )
+    .await
+    {
+        Ok(authed) => authed,
+        Err(code) => return code,
+    };
+
+    let markets: Vec<Option<&str>> = if symbols.is_empty() {
+        vec![None]
+    } else {
+        symbols.iter().map(|symbol| Some(symbol.as_str())).collect()
+    };
+    let mut fills = Vec::new();
+    for market in markets {
+        match fetch_fills(&client, market, &window).await {
+            Ok(page) => fills.extend(page),
+            Err(e) => {
+                error!("Failed to fetch fills: {}", e);
+                return 1;
+            }
+        }
+    }
+    fills.sort_by(|a, b| (a.created_at, &a.id).cmp(&(b.created_at, &b.id)));
+    info!("Exporting {} fills", fills.len());
+
+    let writer: Box<dyn std::io::Write> = match out {
+        Some(path) => match std::fs::File::create(path) {
+            Ok(file) => Box::new(std::io::BufWriter::new(file)),
+            Err(e) => {
+                error!("Failed to create {}: {}", path.display(), e);
+                return 1;
+            }
+        },
+        None => Box::new(std::io::stdout().lock()),
+    };
+    let written = match format {
+        FillFormat::Csv => write_csv(&fills, writer),
+        FillFormat::Json => write_json(&fills, writer),
+    };
+    match written {
+        Ok(()) => 0,
+        Err(e) => {
+            error!("Failed to write fills: {}", e);
+            1
+        }
+    }
+}
+
+/// `funding rates` 子命令：依次查询各市场的资金费率历史
+pub async fn run_funding_rates(
+    config: &ParadexConfig,
+    symbols: &[String],
+    window: TimeWindow,
+    json: bool,
+) -> i32 {
+    let http_client = reqwest::Client::new();
+    let mut rates = Vec::new();
+    for symbol in symbols {
+        match fetch_funding_rates(&http_client, &config.base_url, symbol, &window).await {
+            Ok(page) => rates.extend(page),
+            Err(e) => {
+                error!("Failed to fetch funding rates for {}: {}", symbol, e);
+                return 1;
+            }
+        }
+    }
+    if json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&rates).unwrap_or_default()
+        );
+    } else {
+        println!("{}", format_rates(&rates));
+    }
+    0
+}
+
+/// `funding payments` 子命令：查询账户的资金费支付并按市场汇总
+pub async fn run_funding_payments(
+    config: &ParadexConfig,
+    credentials: &Credentials,
+    symbols: &[String],
+    window: TimeWindow,
+    json: bool,
+) -> i32 {
+    let (client, _jwt_manager) = match authed_client(
+        config,
+        credentials.private_key.as_ref(),
+        credentials.starknet_account.as_deref(),
+    )
+    .await
+    {
+        Ok(authed) => authed,
+        Err(code) => return code,
+    };
+
+    let markets: Vec<Option<&str>> = if symbols.is_empty() {
+        vec![None]
+    } else {
+        symbols.iter().map(|symbol| Some(symbol.as_str())).collect()
+    };
+    let mut payments = Vec::new();
+    for market in markets {
+        match fetch_funding_payments(&client, market, &window).await {
+            Ok(page) => payments.extend(page),
+            Err(e) => {
+                error!("Failed to fetch funding payments: {}", e);
+                return 1;
+            }
+        }
+    }
+    payments.sort_by(|a, b| (a.created_at, &a.id).cmp(&(b.created_at, &b.id)));
+
+    if json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&PaymentSummary::from(payments.as_slice()))
+                .unwrap_or_default()
+        );
+    } else {
+        println!("{}", format_payments(&payments));
+    }
+    0
+}
+
+/// `secrets set` 子命令：交互式输入私钥并写入系统钥匙串
+pub fn run_secrets_set(args: &Args, settings: &Settings) -> i32 {
+    let keyring_account = super::keyring_account(args, settings.environment);
+    let stored = secrets::prompt_secret(&format!("Stark private key for {}: ", keyring_account))
+        .map_err(|e| e.to_string())
+        .and_then(|secret| {
+            KeyringSecretProvider::new(&keyring_account)
+                .store(&secret)
+                .map_err(|e| e.to_string())
+        });
+    match stored {
+        Ok(()) => {
+            info!("Stored private key in OS keyring as {}", keyring_account);
+            0
+        }
+        Err(e) => {
+            error!("Failed to store private key: {}", e);
+            1
+        }
+    }
+}
+
+/// `account` 子命令（无 action）：以 JSON 输出账户信息、余额与持仓
+pub async fn run_account_summary(url: URL, credentials: &Credentials) -> i32 {
+    let Some(key) = credentials.session_key() else {
+        error!("account requires a Paradex private key");
+        return 1;
+    };
+    let client = super::private_client(url, key).await;
+    let summary = tokio::try_join!(
+        client.account_information(),
+        client.balance(),
+        client.positions()
+    );
+    match summary {
+        Ok((account, balances, positions)) => {
+            let summary = serde_json::json!({
+                "account": account,
+                "balances": balances.results,
+                "positions": positions.results,
+            });
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&summary).unwrap_or_default()
+            );
+            0
+        }
+        Err(e) => {
+            error!("Failed to query account: {}", e);
+            1
+        }
+    }
+}
+
+/// 账户信息与余额的 REST 快照
+async fn account_snapshot(
+    client: &Client,
+) -> Result<(AccountInformation, Vec<Balance>), paradex::error::Error> {
+    let (account, balances) = tokio::try_join!(client.account_information(), client.balance())?;
+    Ok((account, balances.results))
+}
+
+/// `balance` 子命令：以 REST 快照为基准，在 `wait` 内由账户与余额事件频道更新可用保证金、
+/// 权益与已用保证金，随后与新的 REST 快照对照输出
+pub async fn run_balance(
+    args: &Args,
+    config: &ParadexConfig,
+    settings: &Settings,
+    credentials: &Credentials,
+    wait: Duration,
+) -> i32 {
+    let Some(key) = credentials.session_key() else {
+        error!("balance requires a Paradex private key");
+        return 1;
+    };
+    let client = super::private_client(config.network, key).await;
+    let account = super::account_state(settings);
+    match account_snapshot(&client).await {
+        Ok((info, balances)) => account.reconcile(&info, &balances),
+        Err(e) => {
+            error!("Failed to query account: {}", e);
+            return 1;
+        }
+    }
+
+    let (source, _) = super::market_data_source(
+        args,
+        config,
+        Some(client.clone()),
+        None,
+        &OrderBooks::default(),
+    )
+    .await;
+    let mut ids = Vec::new();
+    for channel in [Channel::Account, Channel::BalanceEvents] {
+        let tracked = account.clone();
+        match source
+            .subscribe(
+                channel,
+                Box::new(move |message| tracked.on_message(message)),
+            )
+            .await
+        {
+            Ok(id) => ids.push(id),
+            Err(e) => warn!("Failed to subscribe to account updates: {}", e),
+        }
+    }
+    tokio::time::sleep(wait).await;
+    for id in ids {
+        if let Err(e) = source.unsubscribe(id).await {
+            warn!("Failed to unsubscribe account updates: {}", e);
+        }
+    }
+    if let Err(e) = source.stop().await {
+        warn!("Failed to stop market data source: {}", e);
+    }
+
+    match account_snapshot(&client).await {
+        Ok((info, balances)) => {
+            let rest = AccountSnapshot::new(&info, &balances);
+            println!("{}", format_comparison(account.snapshot().as_ref(), &rest));
+            0
+        }
+        Err(e) => {
+            error!("Failed to query account: {}", e);
+            1
+        }
+    }
+}
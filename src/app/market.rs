@@ -0,0 +1,239 @@
+//! 行情订阅、市场列表、行情摘要与订单簿快照子命令
+
+use log::{error, info, warn};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use paradex::ws::Channel;
+use trade_lighter_paradex::config::Settings;
+use trade_lighter_paradex::market_data::{
+    format_summary_table, BboCache, CandleBuilder, CandleInterval, EventBus, MarketSummaryCache,
+    OrderBooks,
+};
+use trade_lighter_paradex::markets::{
+    fetch_market_stats, format_table, MarketListing, MarketRegistry,
+};
+use trade_lighter_paradex::onboarding::ParadexConfig;
+use trade_lighter_paradex::orderbook::{
+    clamp_depth, fetch_orderbook, format_book, OrderBookSummary, DEPTH_RANGE,
+};
+use trade_lighter_paradex::recorder::{RecordHandle, Recorder};
+
+use crate::Args;
+
+/// `stream` 子命令：只订阅公开行情，不需要私钥
+pub async fn run_stream(
+    args: &Args,
+    config: &ParadexConfig,
+    settings: &Settings,
+    candles: &[CandleInterval],
+) -> i32 {
+    let url = config.network;
+    super::validate_markets(url, settings).await;
+    info!("Streaming {}", settings.symbols.join(", "));
+
+    // 建立订阅前安装退出信号处理，保证 Ctrl-C 后仍会取消订阅
+    let shutdown = super::install_shutdown_handler();
+    let recorder = super::start_recorder(args);
+    let books = super::order_books(args, settings, &config.base_url);
+    let (source, replay) = super::market_data_source(args, config, None, None, &books).await;
+    let (source, watchdog) = super::watch_feeds(settings, source, None);
+    let snapshots = super::spawn_book_snapshots(args, recorder.as_ref(), &books, settings);
+    let audit = super::spawn_book_audit(args, &books);
+    let events = (!candles.is_empty()).then(|| super::event_bus(settings, BboCache::new()));
+    if let Some(ref events) = events {
+        build_candles(events, candles, recorder.as_ref().map(Recorder::handle));
+    }
+    let subscriptions = super::subscribe_market_data(
+        source.as_ref(),
+        settings,
+        events.as_ref(),
+        recorder.as_ref().map(Recorder::handle).as_ref(),
+        &books,
+        super::trade_tape(args).as_ref(),
+        super::spread_monitor(args, settings).as_ref(),
+    )
+    .await;
+    super::run_with_replay(
+        replay.as_deref(),
+        super::run_until_shutdown(settings.run_duration_secs, &shutdown, async {}),
+    )
+    .await;
+    log_order_books(&books, &settings.symbols);
+    watchdog.abort();
+    if let Some(snapshots) = snapshots {
+        snapshots.abort();
+    }
+    if let Some(audit) = audit {
+        audit.abort();
+    }
+    super::shutdown(source.as_ref(), &subscriptions).await;
+    super::finish_recorder(recorder).await;
+    0
+}
+
+/// 订阅事件总线上的成交，按 `intervals` 合成 K 线
+fn build_candles(events: &EventBus, intervals: &[CandleInterval], recorder: Option<RecordHandle>) {
+    let mut builder = CandleBuilder::new(intervals);
+    builder.on_candle(Box::new(move |candle| {
+        info!(
+            "Candle {} {} @ {}: O={} H={} L={} C={} V={} ({} trades)",
+            candle.market,
+            candle.interval,
+            candle.start_ts,
+            candle.open,
+            candle.high,
+            candle.low,
+            candle.close,
+            candle.volume,
+            candle.trade_count
+        );
+        if let Some(ref recorder) = recorder {
+            recorder.record_candle(candle);
+        }
+    }));
+    let builder = Mutex::new(builder);
+    events.on_event(Box::new(move |event| {
+        builder.lock().unwrap().on_event(event);
+    }));
+}
+
+/// 输出已建立的本地订单簿的最优价
+pub fn log_order_books(books: &OrderBooks, symbols: &[String]) {
+    for symbol in symbols {
+        let Some(book) = books.get(symbol) else {
+            continue;
+        };
+        let book = book.read().unwrap();
+        if book.is_seeded() {
+            info!(
+                "{} local order book at seq {:?} (synced: {}, last resync {:?}): bid {:?}, ask {:?}, mid {:?}",
+                symbol,
+                book.seq_no(),
+                book.is_synced(),
+                book.last_resync_at(),
+                book.best_bid(),
+                book.best_ask(),
+                book.mid()
+            );
+        }
+    }
+}
+
+/// `markets` 子命令：列出市场元数据与行情统计；`symbols` 非空时只列出这些市场
+pub async fn run_markets(config: &ParadexConfig, symbols: &[String], json: bool) -> i32 {
+    let registry = match MarketRegistry::fetch(config.network).await {
+        Ok(registry) => registry,
+        Err(e) => {
+            error!("{}", e);
+            return 1;
+        }
+    };
+    if let Err(e) = registry.validate(symbols) {
+        error!("{}", e);
+        return 1;
+    }
+    // 统计数据仅用于展示，获取失败时只缺少持仓量与资金费率两列
+    let stats = fetch_market_stats(&reqwest::Client::new(), &config.base_url)
+        .await
+        .unwrap_or_else(|e| {
+            warn!("Failed to fetch market statistics: {}", e);
+            Vec::new()
+        });
+
+    let listings: Vec<MarketListing> = registry
+        .iter()
+        .filter(|market| symbols.is_empty() || symbols.contains(&market.symbol))
+        .map(|market| MarketListing::new(market, stats.iter().find(|s| s.symbol == market.symbol)))
+        .collect();
+    if json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&listings).unwrap_or_default()
+        );
+    } else {
+        println!("{}", format_table(&listings));
+    }
+    0
+}
+
+/// `summary` 子命令：在 `wait` 内收集行情摘要频道的推送，按 24 小时成交量输出
+pub async fn run_summary(
+    args: &Args,
+    config: &ParadexConfig,
+    symbols: &[String],
+    wait: Duration,
+) -> i32 {
+    let summaries = MarketSummaryCache::new();
+    let (source, replay) =
+        super::market_data_source(args, config, None, None, &OrderBooks::default()).await;
+    let cache = summaries.clone();
+    let id = match source
+        .subscribe(
+            Channel::MarketSummary,
+            Box::new(move |message| cache.on_message(message)),
+        )
+        .await
+    {
+        Ok(id) => id,
+        Err(e) => {
+            error!("Failed to subscribe to market summaries: {}", e);
+            return 1;
+        }
+    };
+    super::run_with_replay(replay.as_deref(), tokio::time::sleep(wait)).await;
+    if let Err(e) = source.unsubscribe(id).await {
+        warn!("Failed to unsubscribe market summaries: {}", e);
+    }
+    if let Err(e) = source.stop().await {
+        warn!("Failed to stop market data source: {}", e);
+    }
+
+    let entries: Vec<_> = summaries
+        .by_volume()
+        .into_iter()
+        .filter(|entry| symbols.is_empty() || symbols.contains(&entry.symbol))
+        .collect();
+    if entries.is_empty() {
+        error!("No market summaries received within {}s", wait.as_secs());
+        return 1;
+    }
+    println!("{}", format_summary_table(&entries));
+    0
+}
+
+/// `orderbook` 子命令：依次输出各市场的订单簿快照
+pub async fn run_orderbook(
+    config: &ParadexConfig,
+    symbols: &[String],
+    depth: u32,
+    json: bool,
+) -> i32 {
+    let (depth, clamped) = clamp_depth(depth);
+    if clamped {
+        warn!(
+            "--depth must be within {}..={}, using {}",
+            DEPTH_RANGE.start(),
+            DEPTH_RANGE.end(),
+            depth
+        );
+    }
+
+    let http_client = reqwest::Client::new();
+    let mut code = 0;
+    for symbol in symbols {
+        match fetch_orderbook(&http_client, &config.base_url, symbol, depth).await {
+            Ok(snapshot) if json => println!(
+                "{}",
+                serde_json::to_string_pretty(&OrderBookSummary::from(&snapshot))
+                    .unwrap_or_default()
+            ),
+            Ok(snapshot) => println!("{}\n", format_book(&snapshot)),
+            Err(e) => {
+                error!("Failed to fetch order book for {}: {}", symbol, e);
+                code = 1;
+            }
+        }
+    }
+    code
+}
@@ -0,0 +1,264 @@
+//! 撤单、触发单与平仓子命令：建立客户端与下单出口后交给 [`OrderManager`] 执行
+
+use log::{error, info, warn};
+use std::time::Duration;
+
+use paradex::url::URL;
+use rust_decimal::Decimal;
+use trade_lighter_paradex::client_id::ClientIdGenerator;
+use trade_lighter_paradex::config::RiskLimits;
+use trade_lighter_paradex::gateway::{DryRun, Live, OrderGateway};
+use trade_lighter_paradex::market_data::MarketSummaryCache;
+use trade_lighter_paradex::markets::{fetch_market_stat, MarketRegistry, SharedMarkets};
+use trade_lighter_paradex::metrics::MeteredGateway;
+use trade_lighter_paradex::onboarding::ParadexConfig;
+use trade_lighter_paradex::orders::{describe_order, find_open_order, OrderFactory, OrderTarget};
+use trade_lighter_paradex::positions::{format_summary, open_markets, PositionCache};
+use trade_lighter_paradex::risk::{RestRiskContext, RiskGuard};
+use trade_lighter_paradex::trading::{CloseOptions, OrderManager, OrderTracker, TriggerOrder};
+
+use super::Credentials;
+
+/// 撤单后等待交易所更新挂单列表的时间
+const CANCEL_SETTLE_DELAY: Duration = Duration::from_secs(1);
+
+/// `cancel` 子命令：在挂单中查找目标订单并撤销；未找到（已成交或已撤销）时返回非零退出码
+pub async fn run_cancel(
+    url: URL,
+    credentials: &Credentials,
+    target: OrderTarget,
+    market: Option<&str>,
+    dry_run: bool,
+) -> i32 {
+    let Some(key) = credentials.session_key() else {
+        error!("cancel requires a Paradex private key");
+        return 1;
+    };
+    let client = super::private_client(url, key).await;
+    let open_orders = match client.open_orders().await {
+        Ok(orders) => orders.results,
+        Err(e) => {
+            error!("Failed to list open orders: {}", e);
+            return 1;
+        }
+    };
+    let Some(order) = find_open_order(&open_orders, &target, market) else {
+        error!(
+            "No open order with {}{}; it may already be filled or cancelled",
+            target,
+            market.map_or(String::new(), |market| format!(" on {}", market))
+        );
+        return 1;
+    };
+    info!("Cancelling {}", describe_order(order));
+
+    let gateway: Box<dyn OrderGateway> = if dry_run {
+        Box::new(DryRun::new(credentials.account().unwrap_or_default()))
+    } else {
+        Box::new(Live::new(client.clone()))
+    };
+    if let Err(e) = gateway.cancel_order(order.id.clone()).await {
+        error!("Failed to cancel order {}: {}", order.id, e);
+        return 1;
+    }
+    if dry_run {
+        println!("{}", describe_order(order));
+        return 0;
+    }
+
+    tokio::time::sleep(CANCEL_SETTLE_DELAY).await;
+    let target = OrderTarget::Id(order.id.clone());
+    match client.open_orders().await {
+        Ok(orders) => match find_open_order(&orders.results, &target, None) {
+            Some(order) => {
+                println!("{}", describe_order(order));
+                error!("Order {} is still open after cancellation", order.id);
+                1
+            }
+            None => {
+                println!("{} cancelled", order.id);
+                0
+            }
+        },
+        Err(e) => {
+            warn!("Cancelled order {} but failed to confirm: {}", order.id, e);
+            0
+        }
+    }
+}
+
+/// dry-run 下的 `cancel-all`：只记录撤单请求，不认证也不发送
+pub async fn run_cancel_all_dry_run(credentials: &Credentials, market: Option<String>) -> i32 {
+    let gateway = DryRun::new(credentials.account().unwrap_or_default());
+    let result = match market {
+        Some(market) => gateway.cancel_all_orders_for_market(market).await.map(drop),
+        None => gateway.cancel_all_orders().await.map(drop),
+    };
+    match result {
+        Ok(()) => 0,
+        Err(e) => {
+            error!("Request failed: {}", e);
+            1
+        }
+    }
+}
+
+/// 平仓时轮询持仓的间隔
+pub const CLOSE_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// `stop` 子命令：以 REST 行情统计中的标记价格检查触发价、以 REST 持仓检查只减仓订单后提交触发单；
+/// `clamp` 时数量超过持仓的只减仓订单缩减到持仓数量
+pub async fn run_stop(
+    config: &ParadexConfig,
+    credentials: &Credentials,
+    symbol: &str,
+    order: TriggerOrder,
+    clamp: bool,
+    risk: RiskLimits,
+    dry_run: bool,
+) -> i32 {
+    let Some(key) = credentials.session_key() else {
+        error!("stop requires a Paradex private key");
+        return 1;
+    };
+    let url = config.network;
+    let markets = match MarketRegistry::fetch(url).await {
+        Ok(markets) => markets,
+        Err(e) => {
+            error!("{}", e);
+            return 1;
+        }
+    };
+    if let Err(e) = markets.validate(&[symbol.to_string()]) {
+        error!("{}", e);
+        return 1;
+    }
+    let summaries = MarketSummaryCache::new();
+    match fetch_market_stat(&reqwest::Client::new(), &config.base_url, symbol).await {
+        Ok(Some(stats)) => summaries.seed(&stats),
+        Ok(None) => {}
+        Err(e) => {
+            error!("Failed to fetch the mark price of {}: {}", symbol, e);
+            return 1;
+        }
+    }
+
+    let client = super::private_client(url, key).await;
+    // 止盈与止损单只减仓，发送前按持仓检查方向与数量
+    let positions = match client.positions().await {
+        Ok(positions) => PositionCache::from_snapshot(Decimal::ZERO, &positions.results),
+        Err(e) => {
+            error!("Failed to query positions: {}", e);
+            return 1;
+        }
+    };
+    let sender: Box<dyn OrderGateway> = if dry_run {
+        info!("Dry run: the trigger order is logged, not sent");
+        Box::new(DryRun::new(credentials.account().unwrap_or_default()))
+    } else {
+        Box::new(Live::new(client.clone()))
+    };
+    let risk_context =
+        RestRiskContext::new(client, &config.base_url, None).with_summaries(summaries.clone());
+    let guard = RiskGuard::new(sender.as_ref(), &risk_context, risk);
+    let gateway = MeteredGateway::new(&guard);
+    let orders = OrderManager::new(
+        &gateway,
+        OrderTracker::new(),
+        OrderFactory::new(ClientIdGenerator::new("stop"), config),
+    )
+    .with_markets(SharedMarkets::new(markets))
+    .with_summaries(summaries)
+    .with_positions(positions)
+    .with_reduce_only_clamp(clamp);
+    match orders.submit_trigger_order(symbol, &order).await {
+        Ok(submitted) => {
+            info!(
+                "Submitted {:?} order {} (client id {}) on {}",
+                order.kind,
+                submitted.id(),
+                submitted.client_id(),
+                symbol
+            );
+            0
+        }
+        Err(e) => {
+            error!("Trigger order rejected: {}", e);
+            1
+        }
+    }
+}
+
+/// `close-position` 子命令：依次平掉 `symbols`（或 `all` 时全部）持仓并输出汇总
+pub async fn run_close_position(
+    config: &ParadexConfig,
+    credentials: &Credentials,
+    symbols: &[String],
+    all: bool,
+    risk: RiskLimits,
+    options: CloseOptions,
+) -> i32 {
+    let Some(key) = credentials.session_key() else {
+        error!("close-position requires a Paradex private key");
+        return 1;
+    };
+    let url = config.network;
+    let markets = match MarketRegistry::fetch(url).await {
+        Ok(markets) => markets,
+        Err(e) => {
+            error!("{}", e);
+            return 1;
+        }
+    };
+    if let Err(e) = markets.validate(symbols) {
+        error!("{}", e);
+        return 1;
+    }
+
+    let client = super::private_client(url, key).await;
+    let positions = match client.positions().await {
+        Ok(positions) => positions.results,
+        Err(e) => {
+            error!("Failed to query positions: {}", e);
+            return 1;
+        }
+    };
+    let targets: Vec<String> = if all {
+        open_markets(&positions)
+    } else {
+        symbols.to_vec()
+    };
+    if targets.is_empty() {
+        info!("No open positions");
+        return 0;
+    }
+
+    let sender: Box<dyn OrderGateway> = if options.dry_run {
+        info!("Dry run: close orders are logged, not sent");
+        Box::new(DryRun::new(credentials.account().unwrap_or_default()))
+    } else {
+        Box::new(Live::new(client.clone()))
+    };
+    // 平仓单均为只减仓，风控只检查市价单的滑点（按 REST 订单簿快照估算）；保持所有下单出口一致
+    let risk_context = RestRiskContext::new(client.clone(), &config.base_url, None);
+    let guard = RiskGuard::new(sender.as_ref(), &risk_context, risk);
+    let gateway = MeteredGateway::new(&guard);
+    let orders = OrderManager::new(
+        &gateway,
+        OrderTracker::new(),
+        OrderFactory::new(ClientIdGenerator::new("close"), config),
+    )
+    .with_markets(SharedMarkets::new(markets.clone()))
+    .with_positions(PositionCache::from_snapshot(Decimal::ZERO, &positions))
+    .with_reduce_only_clamp(true);
+    let outcomes = orders
+        .close_positions(&risk_context, &positions, &targets, &options)
+        .await;
+
+    println!("{}", format_summary(&outcomes));
+    if outcomes.iter().all(|(_, outcome)| outcome.is_success()) {
+        0
+    } else {
+        1
+    }
+}
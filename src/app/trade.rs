@@ -0,0 +1,1305 @@
+//! `trade`、`twap`、`grid`、`mm`、`trailing` 与 `bracket`：建立会话、订阅行情与私有频道后执行下单任务
+
+use log::{error, info, warn};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use futures_util::StreamExt;
+use paradex::{
+    rest::Client,
+    structs::OrderType,
+    url::URL,
+    ws::{Channel, Message},
+};
+use rust_decimal::{prelude::FromPrimitive, Decimal};
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+use trade_lighter_paradex::client_id::ClientIdGenerator;
+use trade_lighter_paradex::config::{Settings, WsChannel};
+use trade_lighter_paradex::funding::{FundingPayment, FundingTracker};
+use trade_lighter_paradex::gateway::{DryRun, Live, OrderGateway};
+use trade_lighter_paradex::market_data::{
+    BboCache, ChannelMessage, EventBus, FeedCallback, MarketSummaryCache, OrderBooks, RawChannels,
+    RawMessage, SubscriptionHub, TRADE_BUSTS, TRANSFERS,
+};
+use trade_lighter_paradex::markets::{MarketInfo, MarketRegistry, SharedMarkets};
+use trade_lighter_paradex::metrics::{metrics, MeteredGateway};
+use trade_lighter_paradex::onboarding::{
+    is_onboarded, onboard_subaccount, perform_transfer, perform_withdrawal, FundsTransfer,
+    JwtManager, OnboardingError, OnboardingOptions, ParadexConfig, ParadexSigner, TokenCache,
+};
+use trade_lighter_paradex::orders::{passive_price, OrderFactory, OrderKind, OrderSpec};
+use trade_lighter_paradex::paper::PaperExchange;
+use trade_lighter_paradex::positions::PositionCache;
+use trade_lighter_paradex::recorder::Recorder;
+use trade_lighter_paradex::risk::{self, RestRiskContext, RiskGuard};
+use trade_lighter_paradex::session::AccountSession;
+use trade_lighter_paradex::strategies::{
+    GridConfig, GridTrader, MakerConfig, SimpleMaker, TrailingConfig, TrailingStop, TwapConfig,
+    TwapExecutor,
+};
+use trade_lighter_paradex::trading::{
+    CancelScope, FillLedger, ManagedOrder, NewOrder, OrderManager, OrderManagerError, OrderTracker,
+    TrackedOrder,
+};
+
+use super::account::{back_off_if_rate_limited, ensure_onboarded};
+use super::market::log_order_books;
+use super::Credentials;
+use crate::{Args, Command, TradeArgs};
+
+/// onboarding、获取 JWT 并执行提现 / 划转；返回保持 JWT 刷新的 `JwtManager`
+async fn prepare_trading_account(
+    args: &Args,
+    trade: &TradeArgs,
+    config: &ParadexConfig,
+    credentials: &Credentials,
+) -> Result<Option<JwtManager>, i32> {
+    let (Some(eth_addr), Some(signer)) = (&credentials.eth_account, credentials.signer(config))
+    else {
+        warn!("Ethereum or StarkNet account not provided. Skipping onboarding.");
+        return Ok(None);
+    };
+    let signer = match signer {
+        Ok(signer) => Arc::new(signer),
+        Err(e) => {
+            error!("Invalid Paradex private key or account address: {}", e);
+            return Err(1);
+        }
+    };
+    let http_client = reqwest::Client::new();
+
+    if let Err(e) = ensure_onboarded(
+        &http_client,
+        &signer,
+        eth_addr,
+        &OnboardingOptions::default(),
+    )
+    .await
+    {
+        error!("Onboarding failed: {}", e);
+        back_off_if_rate_limited(&e).await;
+        return Err(1);
+    }
+
+    // 子账户：onboard 后改用子账户签名器完成后续认证
+    let signer = if let Some((ref sub_key, ref sub_addr)) = credentials.subaccount {
+        let sub_signer = match ParadexSigner::new(sub_key.expose(), sub_addr, config) {
+            Ok(signer) => Arc::new(signer),
+            Err(e) => {
+                error!("Invalid Paradex subaccount private key or address: {}", e);
+                return Err(1);
+            }
+        };
+        if !is_onboarded(&http_client, &sub_signer)
+            .await
+            .unwrap_or(false)
+        {
+            info!(
+                "Onboarding subaccount {} under {}...",
+                sub_addr,
+                signer.account_address()
+            );
+            match onboard_subaccount(
+                &http_client,
+                &sub_signer,
+                signer.account_address(),
+                eth_addr,
+            )
+            .await
+            {
+                Ok(()) | Err(OnboardingError::AlreadyOnboarded) => {}
+                Err(e) => {
+                    error!("Subaccount onboarding failed: {}", e);
+                    return Err(1);
+                }
+            }
+        }
+        sub_signer
+    } else {
+        signer
+    };
+
+    // 获取 JWT token 并启动自动刷新
+    info!("Getting JWT token...");
+    let token_cache = TokenCache::in_default_dir(config, signer.account_address())
+        .map(|cache| cache.with_margin(Duration::from_secs(args.jwt_cache_margin_secs)));
+    if args.force_reauth {
+        if let Some(ref cache) = token_cache {
+            cache.invalidate();
+        }
+    }
+    let jwt_manager =
+        match JwtManager::start(http_client.clone(), signer.clone(), token_cache).await {
+            Ok(manager) => {
+                info!(
+                    "JWT token obtained, expires at {}",
+                    manager.current_token().await.expires_at
+                );
+
+                let mut token_updates = manager.subscribe();
+                tokio::spawn(async move {
+                    while token_updates.changed().await.is_ok() {
+                        info!("JWT token refreshed");
+                    }
+                });
+                Some(manager)
+            }
+            Err(e @ OnboardingError::RateLimited { .. }) => {
+                error!("Failed to get JWT token: {}", e);
+                back_off_if_rate_limited(&e).await;
+                return Err(1);
+            }
+            Err(e) => {
+                warn!("Failed to get JWT token: {}", e);
+                None
+            }
+        };
+
+    // 提现与划转（dry-run 时跳过）
+    if args.dry_run && (trade.withdraw.is_some() || trade.transfer.is_some()) {
+        warn!("Dry run: skipping withdrawal and transfer");
+        return Ok(jwt_manager);
+    }
+    if let Some(amount) = trade.withdraw {
+        if let Err(e) = perform_withdrawal(
+            &http_client,
+            &signer,
+            &FundsTransfer {
+                recipient: eth_addr.clone(),
+                token: "USDC".into(),
+                amount,
+            },
+        )
+        .await
+        {
+            warn!("Withdrawal failed: {}", e);
+        }
+    }
+    if let (Some(amount), Some(ref recipient)) = (trade.transfer, &trade.transfer_to) {
+        if let Err(e) = perform_transfer(
+            &http_client,
+            &signer,
+            &FundsTransfer {
+                recipient: recipient.clone(),
+                token: "USDC".into(),
+                amount,
+            },
+        )
+        .await
+        {
+            warn!("Transfer failed: {}", e);
+        }
+    }
+
+    Ok(jwt_manager)
+}
+
+/// 订单频道消息（实盘订阅与模拟撮合共用）
+fn on_order_update(tracker: &OrderTracker, message: &Message) {
+    let strategy = tracker.strategy(message);
+    info!(channel = "orders", strategy = strategy.as_deref().unwrap_or("-"); "Received order update {message:?}");
+}
+
+/// 成交频道消息（实盘订阅与模拟撮合共用）；按订单归属到下单的策略
+fn on_fill(tracker: &OrderTracker, message: &Message) {
+    let strategy = tracker.strategy(message);
+    info!(channel = "fills", strategy = strategy.as_deref().unwrap_or("-"); "Received fill {message:?}");
+}
+
+/// 私有频道的订阅流，由一个任务统一消费
+struct AccountStreams {
+    stop: CancellationToken,
+    task: JoinHandle<()>,
+}
+
+impl AccountStreams {
+    /// 停止消费并取消全部私有频道订阅
+    async fn close(self) {
+        self.stop.cancel();
+        if let Err(e) = self.task.await {
+            warn!("Account stream consumer failed: {}", e);
+        }
+    }
+}
+
+/// 订阅配置中的私有频道（订单、成交、持仓、账户、余额与资金费支付），
+/// 以及资金费估算所需的资金费率与行情摘要（标记价格，同时写入 `summaries`）；
+/// 运行网格时订单频道的断线与重连也转交给 `grid`
+#[allow(clippy::too_many_arguments)]
+async fn subscribe_account_channels(
+    hub: &SubscriptionHub,
+    settings: &Settings,
+    session: &AccountSession,
+    funding: &FundingTracker,
+    summaries: &MarketSummaryCache,
+    ledger: &FillLedger,
+    tracker: &OrderTracker,
+    grid: Option<&GridTrader>,
+) -> AccountStreams {
+    let channels = [
+        (
+            WsChannel::Orders,
+            Channel::Orders {
+                market_symbol: None,
+            },
+        ),
+        (
+            WsChannel::Fills,
+            Channel::Fills {
+                market_symbol: None,
+            },
+        ),
+        (WsChannel::Positions, Channel::Position),
+        (WsChannel::Account, Channel::Account),
+        (WsChannel::BalanceEvents, Channel::BalanceEvents),
+        (
+            WsChannel::FundingPayments,
+            Channel::FundingPayments {
+                market_symbol: None,
+            },
+        ),
+    ];
+    let mut streams = Vec::new();
+    for (ws_channel, channel) in channels {
+        if settings.subscribes(ws_channel) {
+            let (_, stream) = hub.subscribe_stream(channel).await.unwrap();
+            streams.push(stream);
+        }
+    }
+    for channel in [
+        Channel::FundingData {
+            market_symbol: None,
+        },
+        Channel::MarketSummary,
+    ] {
+        let (_, stream) = hub.subscribe_stream(channel).await.unwrap();
+        streams.push(stream);
+    }
+
+    let session = session.clone();
+    let funding = funding.clone();
+    let summaries = summaries.clone();
+    let ledger = ledger.clone();
+    let tracker = tracker.clone();
+    let grid = grid.cloned();
+    let stop = CancellationToken::new();
+    let stopped = stop.clone();
+    let task = tokio::spawn(async move {
+        let mut messages = futures_util::stream::select_all(streams);
+        loop {
+            let next = tokio::select! {
+                next = messages.next() => next,
+                _ = stopped.cancelled() => None,
+            };
+            let Some(ChannelMessage {
+                channel, message, ..
+            }) = next
+            else {
+                break;
+            };
+            funding.on_message(&message);
+            summaries.on_message(&message);
+            ledger.on_message(&message);
+            tracker.on_message(&message);
+            if let (Some(grid), WsChannel::Orders) = (&grid, channel) {
+                grid.on_message(&message);
+            }
+            on_account_message(&session, &tracker, channel, &message);
+        }
+        for stream in messages {
+            if let Err(e) = stream.close().await {
+                warn!("Failed to unsubscribe account stream: {}", e);
+            }
+        }
+    });
+    AccountStreams { stop, task }
+}
+
+/// 按名称订阅配置中 SDK 未覆盖的私有频道：成交撤销将台账中的成交标记为已冲回，转账增减会话余额；
+/// `jwt_manager` 轮换 JWT 时在同一连接上重新认证
+fn subscribe_raw_channels(
+    url: URL,
+    client: &Client,
+    jwt_manager: Option<&JwtManager>,
+    settings: &Settings,
+    session: &AccountSession,
+    ledger: &FillLedger,
+) -> Option<RawChannels> {
+    let channels: Vec<String> = [
+        (WsChannel::TradeBusts, TRADE_BUSTS),
+        (WsChannel::Transfers, TRANSFERS),
+    ]
+    .into_iter()
+    .filter(|(channel, _)| settings.subscribes(*channel))
+    .map(|(_, name)| name.to_string())
+    .collect();
+    if channels.is_empty() {
+        return None;
+    }
+    let session = session.clone();
+    let ledger = ledger.clone();
+    let callback = Box::new(move |message: &RawMessage| match message {
+        RawMessage::TradeBust(bust) => {
+            warn!(channel = "trade_busts"; "Received trade bust {bust:?}");
+            if ledger.bust(bust).is_none() {
+                warn!(
+                    "Busted fill {} is not in the ledger yet",
+                    bust.busted_fill_id
+                );
+            }
+        }
+        RawMessage::Transfer(transfer) => {
+            info!(channel = "transfers"; "Received transfer {transfer:?}");
+            session.apply_transfer(transfer);
+        }
+        RawMessage::Other { channel, data } => {
+            info!("Received {} message {}", channel, data)
+        }
+    });
+    Some(RawChannels::spawn(
+        url,
+        Some(client.clone()),
+        jwt_manager.map(JwtManager::subscribe),
+        channels,
+        callback,
+    ))
+}
+
+/// 私有频道消息：记录日志，持仓、账户与余额同时更新会话状态
+fn on_account_message(
+    session: &AccountSession,
+    tracker: &OrderTracker,
+    channel: WsChannel,
+    message: &Message,
+) {
+    match channel {
+        WsChannel::Orders => on_order_update(tracker, message),
+        WsChannel::Fills => on_fill(tracker, message),
+        WsChannel::Positions => {
+            info!(channel = "positions"; "Received position {message:?}");
+            session.apply(message);
+        }
+        WsChannel::Account => {
+            info!(channel = "account"; "Received account {message:?}");
+            session.apply(message);
+        }
+        WsChannel::BalanceEvents => {
+            info!(channel = "balance_events"; "Received balance event {message:?}");
+            session.apply(message);
+        }
+        WsChannel::FundingPayments => {
+            info!(channel = "funding_payments"; "Received funding payment {message:?}")
+        }
+        _ => {}
+    }
+}
+
+/// 补录 `since` 以来的资金费支付，频道随后推送的同一支付不会重复计入
+async fn backfill_funding(client: &Client, ledger: &FillLedger, since: DateTime<Utc>) {
+    match client.funding_payments(None, Some(since), None).await {
+        Ok(payments) => {
+            let added = payments
+                .iter()
+                .filter(|payment| ledger.record_funding(FundingPayment::from(*payment)))
+                .count();
+            info!("Backfilled {} funding payments since {}", added, since);
+        }
+        Err(e) => {
+            metrics().rest_error();
+            warn!("Failed to backfill funding payments since {}: {}", since, e);
+        }
+    }
+}
+
+/// 会话报告：按市场汇总本次运行的成交、资金费与已实现盈亏，指定 `csv` 时导出成交
+fn report_fills(ledger: &FillLedger, csv: Option<&Path>) {
+    for entry in ledger.reversed() {
+        warn!(
+            "Fill {} on {} was busted: {} {} @ {}",
+            entry.fill.id, entry.fill.market, entry.fill.side, entry.fill.size, entry.fill.price
+        );
+    }
+    println!("{}", ledger.report());
+    if let Some(path) = csv {
+        match ledger.to_csv(path) {
+            Ok(()) => info!("Wrote session fills to {}", path.display()),
+            Err(e) => error!("Failed to write {}: {}", path.display(), e),
+        }
+    }
+}
+
+/// 订阅后等待 WebSocket 连接建立的时间
+const WS_CONNECT_DELAY: Duration = Duration::from_secs(2);
+
+/// 下单、改单与撤单后等待订单结束的最长时间，超时后继续下一步
+const ORDER_STEP_DELAY: Duration = Duration::from_secs(5);
+
+/// 演示下单参考的缓存报价最大时长，超过时改为 REST 查询
+const MAX_QUOTE_AGE: Duration = Duration::from_secs(5);
+
+/// 演示下单的参考价：BBO 缓存中的中间价，缓存中没有新鲜报价时查询 REST
+async fn reference_mid(client: &Client, quotes: &BboCache, symbol: &str) -> Option<Decimal> {
+    if let Some(quote) = quotes.fresh(symbol, MAX_QUOTE_AGE) {
+        return quote.mid();
+    }
+    let bbo = client.bbo(symbol.to_string()).await.ok()?;
+    risk::mid(Decimal::from_f64(bbo.bid)?, Decimal::from_f64(bbo.ask)?)
+}
+
+/// 由下单类子命令的参数与运行配置得到建立会话后执行的任务；其他子命令返回 `None`
+///
+/// 下单类子命令会真实下单，除 `--dry-run` 与 `--paper` 外须以 `--i-know-this-places-orders` 确认
+pub fn trade_job(args: &Args, settings: &Settings) -> Result<Option<TradeJob>, String> {
+    let (name, trade) = match &args.command {
+        Command::Trade(trade) => ("trade", trade.as_ref()),
+        Command::Twap(twap) => ("twap", &twap.trade),
+        Command::Grid(trade) => ("grid", trade.as_ref()),
+        Command::Mm(trade) => ("mm", trade.as_ref()),
+        Command::Trailing(trade) => ("trailing", trade.as_ref()),
+        Command::Bracket(bracket) => ("bracket", &bracket.trade),
+        _ => return Ok(None),
+    };
+    if trade.paper && args.dry_run {
+        return Err("--paper and --dry-run cannot be combined".to_string());
+    }
+    // 模拟账户从空仓开始，没有可保护的持仓
+    if trade.paper && matches!(args.command, Command::Trailing(_)) {
+        return Err(
+            "trailing protects an existing position and does not support --paper".to_string(),
+        );
+    }
+    if matches!(args.command, Command::Trade(_))
+        && trade.max_slippage_bps.is_some()
+        && trade.order_type != OrderKind::Market
+    {
+        return Err("--max-slippage-bps only applies to --order-type market".to_string());
+    }
+    if !trade.confirmed && !args.dry_run && !trade.paper {
+        return Err(format!(
+            "{} places real orders on {:?}; pass --i-know-this-places-orders to continue",
+            name, settings.environment
+        ));
+    }
+    let entry = || {
+        super::order_spec(trade, settings).map_err(|e| format!("Invalid order parameters: {}", e))
+    };
+    let job = match &args.command {
+        Command::Twap(twap) => TradeJob::Twap(
+            super::twap_config(twap, settings)
+                .map_err(|e| format!("Invalid TWAP parameters: {}", e))?,
+        ),
+        Command::Grid(_) => TradeJob::Grid(
+            super::grid_config(settings).map_err(|e| format!("Invalid grid parameters: {}", e))?,
+        ),
+        Command::Mm(_) => TradeJob::Mm(
+            super::maker_config(settings)
+                .map_err(|e| format!("Invalid market making parameters: {}", e))?,
+        ),
+        Command::Trailing(_) => TradeJob::Trailing(
+            super::trailing_config(settings)
+                .map_err(|e| format!("Invalid trailing stop parameters: {}", e))?,
+        ),
+        Command::Bracket(bracket) => TradeJob::Bracket(BracketJob {
+            entry: entry()?,
+            take_profit_offset: bracket.take_profit_offset,
+            stop_loss_offset: bracket.stop_loss_offset,
+        }),
+        Command::Trade(_) => TradeJob::Demo(entry()?),
+        _ => return Ok(None),
+    };
+    Ok(Some(job))
+}
+
+/// `trade`、`twap`、`grid`、`mm`、`trailing` 与 `bracket` 建立会话后执行的任务
+pub enum TradeJob {
+    /// 下单 / 改单 / 撤单演示
+    Demo(OrderSpec),
+    /// 按 TWAP 计划执行母单
+    Twap(TwapConfig),
+    /// 按 `[grid]` 配置运行网格，直到退出
+    Grid(GridConfig),
+    /// 按 `[mm]` 配置做市，直到退出
+    Mm(MakerConfig),
+    /// 按 `[trailing]` 配置追踪止损，直到平仓或退出
+    Trailing(TrailingConfig),
+    /// 提交入场单并挂出止盈与止损
+    Bracket(BracketJob),
+}
+
+/// 括号单的入场单与出场偏移
+pub struct BracketJob {
+    entry: OrderSpec,
+    take_profit_offset: Decimal,
+    stop_loss_offset: Decimal,
+}
+
+impl TradeJob {
+    /// TWAP 子单数量按市场的数量步长对齐，网格中心价、做市报价与止损位按价格精度对齐
+    fn for_market(mut self, markets: &MarketRegistry) -> Self {
+        match self {
+            TradeJob::Twap(ref mut config) => {
+                config.size_increment = markets
+                    .get(&config.symbol)
+                    .map_or(Decimal::ZERO, MarketInfo::min_size);
+            }
+            TradeJob::Grid(ref mut config) => {
+                config.price_tick = markets.price_tick(&config.symbol);
+            }
+            TradeJob::Trailing(ref mut config) => {
+                config.price_tick = markets.price_tick(&config.symbol);
+            }
+            TradeJob::Mm(ref mut config) => {
+                config.price_ticks = config
+                    .symbols
+                    .iter()
+                    .map(|symbol| (symbol.clone(), markets.price_tick(symbol)))
+                    .collect();
+            }
+            TradeJob::Demo(_) | TradeJob::Bracket(_) => {}
+        }
+        self
+    }
+
+    /// 网格任务的网格交易器；持仓上限与风控相同，`position` 为启动时的持仓
+    fn grid(
+        &self,
+        settings: &Settings,
+        quotes: &BboCache,
+        position: Decimal,
+    ) -> Option<GridTrader> {
+        let TradeJob::Grid(config) = self else {
+            return None;
+        };
+        let grid = GridTrader::new(config.clone(), quotes.clone())
+            .expect("grid parameters are validated before dispatch");
+        Some(
+            grid.with_max_position(settings.risk.max_position)
+                .with_position(position),
+        )
+    }
+
+    /// 做市任务的做市商：以订单簿微价格为参考价，按 `positions` 中的持仓调整报价，
+    /// 停滞检测与订单簿异常时撤下报价
+    fn maker(
+        &self,
+        quotes: &BboCache,
+        books: &OrderBooks,
+        positions: &PositionCache,
+    ) -> Option<SimpleMaker> {
+        let TradeJob::Mm(config) = self else {
+            return None;
+        };
+        let maker = SimpleMaker::new(config.clone(), quotes.clone())
+            .expect("market making parameters are validated before dispatch")
+            .with_order_books(books.clone())
+            .with_positions(positions.clone());
+        let on_anomaly = maker.clone();
+        books.on_anomaly(Box::new(move |anomaly| on_anomaly.on_anomaly(anomaly)));
+        Some(maker)
+    }
+
+    /// 追踪止损任务的追踪止损，由 `events` 上的 BBO 更新唤醒
+    fn trailing(&self, quotes: &BboCache, events: &EventBus) -> Option<TrailingStop> {
+        let TradeJob::Trailing(config) = self else {
+            return None;
+        };
+        let trailing = TrailingStop::new(config.clone(), quotes.clone())
+            .expect("trailing stop parameters are validated before dispatch");
+        let wake = trailing.clone();
+        events.on_event(Box::new(move |event| wake.on_event(event)));
+        Some(trailing)
+    }
+}
+
+/// `maker` 的停滞通知回调，交给 [`super::watch_feeds`]
+fn maker_feed_status(maker: Option<&SimpleMaker>) -> Option<FeedCallback> {
+    let maker = maker?.clone();
+    Some(Box::new(move |status| maker.on_feed_status(status)))
+}
+
+/// 执行 TWAP 并输出报告；执行器记录事件总线上的成交，用于计算各周期的 VWAP
+async fn run_twap(
+    orders: &OrderManager<'_>,
+    events: &EventBus,
+    quotes: &BboCache,
+    config: TwapConfig,
+) {
+    let executor = match TwapExecutor::new(config, quotes.clone()) {
+        Ok(executor) => executor,
+        Err(e) => {
+            error!("Invalid TWAP parameters: {}", e);
+            return;
+        }
+    };
+    let tape = executor.clone();
+    events.on_event(Box::new(move |event| tape.on_event(event)));
+    let report = executor.run(orders).await;
+    println!("{}", report);
+}
+
+/// 提交括号单并输出结果；限价入场单未指定价格时按配置偏移参考中间价 `reference`
+async fn run_bracket(
+    orders: &OrderManager<'_>,
+    settings: &Settings,
+    bracket: BracketJob,
+    reference: Option<Decimal>,
+    price_tick: Decimal,
+) {
+    let symbol = &settings.trade_symbol;
+    let BracketJob {
+        mut entry,
+        take_profit_offset,
+        stop_loss_offset,
+    } = bracket;
+    let Some(reference) = entry.price.or(reference) else {
+        error!("Failed to fetch BBO for {}, skipping the bracket", symbol);
+        return;
+    };
+    if entry.order_type == OrderType::LIMIT && entry.price.is_none() {
+        let offset_bps = settings.order.price_offset_bps;
+        entry.price = Some(passive_price(entry.side, reference, offset_bps, price_tick));
+    }
+    if let Err(e) = settings
+        .risk
+        .check(entry.price.unwrap_or(reference), entry.size)
+    {
+        error!("{}, skipping the bracket", e);
+        return;
+    }
+    let entry = NewOrder::new(symbol, entry);
+    match orders
+        .submit_bracket(entry, take_profit_offset, stop_loss_offset)
+        .await
+    {
+        Ok(report) => println!("{}", report),
+        Err(e) => error!("Bracket on {} failed: {}", symbol, e),
+    }
+}
+
+/// 收到退出信号或运行 `run_duration_secs` 秒（0 表示直到中断）后取消的令牌，以及计时任务
+fn stop_after(
+    run_duration_secs: u64,
+    shutdown: &CancellationToken,
+) -> (CancellationToken, Option<JoinHandle<()>>) {
+    let stop = shutdown.child_token();
+    let timer = (run_duration_secs > 0).then(|| {
+        let stop = stop.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_secs(run_duration_secs)).await;
+            info!(
+                "Run duration of {}s elapsed, shutting down",
+                run_duration_secs
+            );
+            stop.cancel();
+        })
+    });
+    (stop, timer)
+}
+
+/// 运行网格直到收到退出信号或运行 `run_duration_secs` 秒（0 表示直到中断），结束前撤销全部网格订单
+async fn run_grid(
+    orders: &OrderManager<'_>,
+    grid: &GridTrader,
+    run_duration_secs: u64,
+    shutdown: &CancellationToken,
+) {
+    let (stop, timer) = stop_after(run_duration_secs, shutdown);
+    if let Err(e) = grid.run(orders, &stop).await {
+        error!("{}", e);
+    }
+    if let Some(timer) = timer {
+        timer.abort();
+    }
+}
+
+/// 追踪止损直到平仓、收到退出信号或运行 `run_duration_secs` 秒（0 表示直到中断），未平仓退出前撤销挂在交易所的止损单
+async fn run_trailing(
+    orders: &OrderManager<'_>,
+    trailing: &TrailingStop,
+    run_duration_secs: u64,
+    shutdown: &CancellationToken,
+) {
+    let (stop, timer) = stop_after(run_duration_secs, shutdown);
+    match trailing.run(orders, &stop).await {
+        Ok(report) => println!("{}", report),
+        Err(e) => error!(
+            "Trailing stop on {} failed: {}",
+            trailing.config().symbol,
+            e
+        ),
+    }
+    if let Some(timer) = timer {
+        timer.abort();
+    }
+}
+
+/// 做市直到收到退出信号或运行 `run_duration_secs` 秒（0 表示直到中断），结束前撤销全部报价
+async fn run_maker(
+    orders: &OrderManager<'_>,
+    maker: &SimpleMaker,
+    run_duration_secs: u64,
+    shutdown: &CancellationToken,
+) {
+    let (stop, timer) = stop_after(run_duration_secs, shutdown);
+    maker.run(orders, &stop).await;
+    if let Some(timer) = timer {
+        timer.abort();
+    }
+}
+
+/// 下单 / 改单 / 撤单演示
+struct OrderDemo<'a> {
+    orders: &'a OrderManager<'a>,
+    settings: &'a Settings,
+    price_tick: Decimal,
+    /// 每一步之后等待订单进入终态的最长时间
+    step_delay: Duration,
+    /// 市价单经 [`OrderManager::submit_market`] 按滑点上限吃单
+    max_slippage_bps: Option<Decimal>,
+    /// 对照吃单的估算与实际成交均价
+    ledger: &'a FillLedger,
+}
+
+impl OrderDemo<'_> {
+    /// 等待订单结束；超时仍未结束时返回 `None`，订单已结束时记录其终态
+    async fn await_terminal(&self, order: &ManagedOrder) -> Option<TrackedOrder> {
+        let order = order.await_filled_or_cancelled(self.step_delay).await?;
+        info!(
+            "Order {} is {:?} (filled {} of {})",
+            order.id,
+            order.state,
+            order.filled_size(),
+            order.size
+        );
+        Some(order)
+    }
+
+    /// 限价单未指定价格时按配置偏移参考中间价 `reference`
+    async fn run(&self, mut spec: OrderSpec, reference: Option<Decimal>) {
+        let orders = self.orders;
+        let symbol = &self.settings.trade_symbol;
+        let offset_bps = self.settings.order.price_offset_bps;
+        let price_tick = self.price_tick;
+
+        let Some(reference) = spec.price.or(reference) else {
+            error!("Failed to fetch BBO for {}, skipping order demo", symbol);
+            return;
+        };
+        if spec.order_type == OrderType::LIMIT && spec.price.is_none() {
+            spec.price = Some(passive_price(spec.side, reference, offset_bps, price_tick));
+        }
+        if let Err(e) = self
+            .settings
+            .risk
+            .check(spec.price.unwrap_or(reference), spec.size)
+        {
+            error!("{}, skipping order demo", e);
+            return;
+        }
+
+        // 创建订单
+        let max_slippage_bps = self
+            .max_slippage_bps
+            .filter(|_| spec.order_type == OrderType::MARKET);
+        let submitted = match max_slippage_bps {
+            Some(cap) => orders
+                .submit_market(symbol, spec.side, spec.size, cap)
+                .await
+                .map(|taken| (taken.order.clone(), Some(taken))),
+            None => orders
+                .submit(NewOrder::new(symbol, spec.clone()))
+                .await
+                .map(|order| (order, None)),
+        };
+        let (order, taken) = match submitted {
+            Ok(submitted) => submitted,
+            Err(e @ (OrderManagerError::Order(_) | OrderManagerError::Risk(_))) => {
+                error!("{}, skipping order demo", e);
+                return;
+            }
+            Err(e) => {
+                error!("Failed to create order: {}", e);
+                return;
+            }
+        };
+
+        // 市价单立即成交或过期，没有可修改 / 取消的挂单；限价单在等待期间结束时同样跳过
+        let terminal = self.await_terminal(&order).await;
+        if let Some(taken) = taken {
+            info!("Slippage: {}", taken.report(self.ledger));
+        }
+        if let (Some(price), None) = (spec.price, terminal) {
+            // 修改订单：在当前挂单价基础上再向远离盘口的方向偏移一次
+            let price = passive_price(spec.side, price, offset_bps, price_tick);
+            let order = match orders.amend(order.id(), price, spec.size).await {
+                Ok(amended) => amended,
+                Err(e) => {
+                    error!("Failed to modify order: {}", e);
+                    order
+                }
+            };
+
+            if self.await_terminal(&order).await.is_none() {
+                // 取消订单
+                info!("Cancel Order Result {:?}", orders.cancel(order.id()).await);
+                if self.await_terminal(&order).await.is_none() {
+                    warn!("Order {} did not close after cancel", order.id());
+                }
+            }
+        }
+
+        info!(
+            "Cancel by market orders Result {:?}",
+            orders.cancel_all(CancelScope::Market(symbol.clone())).await
+        );
+
+        info!(
+            "Cancel All Orders Result {:?}",
+            orders.cancel_all(CancelScope::All).await
+        );
+    }
+}
+
+/// `trade` 子命令：onboarding、认证、订阅行情与私有频道并运行下单演示
+pub async fn run_trade(
+    args: &Args,
+    trade: &TradeArgs,
+    job: TradeJob,
+    settings: &Settings,
+    config: &ParadexConfig,
+    credentials: &Credentials,
+) -> i32 {
+    let Some(key) = credentials.session_key() else {
+        error!("trade requires a Paradex private key");
+        return 1;
+    };
+    let url = config.network;
+    let markets = super::validate_markets(url, settings).await;
+    let price_tick = markets.price_tick(&settings.trade_symbol);
+    let job = job.for_market(&markets);
+    let markets = SharedMarkets::new(markets);
+    let markets_refresh =
+        markets.spawn_refresh(url, Duration::from_secs(trade.markets_refresh_secs));
+    info!(
+        "Subscribing to {}; trading {}",
+        settings.symbols.join(", "),
+        settings.trade_symbol
+    );
+
+    // 保持 JWT 后台刷新直到退出
+    let jwt_manager = match prepare_trading_account(args, trade, config, credentials).await {
+        Ok(manager) => manager,
+        Err(code) => return code,
+    };
+
+    // 创建 Paradex 客户端（指定子账户时以子账户身份）
+    let client = super::private_client(url, key).await;
+    info!(
+        "Account Information {:?}",
+        client.account_information().await
+    );
+
+    // 以 REST 快照建立持仓与余额基准，后续由 WebSocket 事件增量更新
+    let session = AccountSession::new(
+        client.clone(),
+        PositionCache::new(settings.positions.tolerance),
+        super::account_state(settings),
+    );
+    if let Err(e) = session.reconcile().await {
+        warn!("Initial account reconciliation failed: {}", e);
+    }
+    info!("Balance {:?}", session.balance());
+    info!("Position {:?}", session.position(&settings.trade_symbol));
+
+    // 建立订阅前安装退出信号处理，保证 Ctrl-C 后仍会撤单并取消订阅
+    let shutdown = super::install_shutdown_handler();
+    let recorder = super::start_recorder(args);
+    let books = super::order_books(args, settings, &config.base_url);
+    let (manager, _) = super::market_data_source(
+        args,
+        config,
+        Some(client.clone()),
+        jwt_manager.as_ref().map(JwtManager::subscribe),
+        &books,
+    )
+    .await;
+    let quotes = BboCache::new();
+    let maker = job.maker(&quotes, &books, session.positions());
+    let (manager, watchdog) =
+        super::watch_feeds(settings, manager, maker_feed_status(maker.as_ref()));
+    let snapshots = super::spawn_book_snapshots(args, recorder.as_ref(), &books, settings);
+    let audit = super::spawn_book_audit(args, &books);
+    let events = super::event_bus(settings, quotes.clone());
+    if let Some(ref maker) = maker {
+        let maker = maker.clone();
+        events.on_event(Box::new(move |event| maker.on_event(event)));
+    }
+    let trailing = job.trailing(&quotes, &events);
+    let subscriptions = super::subscribe_market_data(
+        manager.as_ref(),
+        settings,
+        Some(&events),
+        recorder.as_ref().map(Recorder::handle).as_ref(),
+        &books,
+        super::trade_tape(args).as_ref(),
+        super::spread_monitor(args, settings).as_ref(),
+    )
+    .await;
+    let hub = SubscriptionHub::new(manager.clone());
+    let funding = FundingTracker::new(settings.risk.funding_alert_bps);
+    for symbol in &settings.symbols {
+        if let Some(position) = session.position(symbol) {
+            funding.set_position(symbol, Decimal::from_f64(position.size).unwrap_or_default());
+        }
+    }
+    let summaries = MarketSummaryCache::new();
+    let ledger = FillLedger::new();
+    let tracker = OrderTracker::new();
+    let position = session
+        .position(&settings.trade_symbol)
+        .and_then(|position| Decimal::from_f64(position.size))
+        .unwrap_or_default();
+    let grid = job.grid(settings, &quotes, position);
+    let account_streams = subscribe_account_channels(
+        &hub,
+        settings,
+        &session,
+        &funding,
+        &summaries,
+        &ledger,
+        &tracker,
+        grid.as_ref(),
+    )
+    .await;
+    let session_start = trade.session_start.unwrap_or_else(|| {
+        Utc::now()
+            .date_naive()
+            .and_time(chrono::NaiveTime::MIN)
+            .and_utc()
+    });
+    backfill_funding(&client, &ledger, session_start).await;
+    let raw_channels = subscribe_raw_channels(
+        url,
+        &client,
+        jwt_manager.as_ref(),
+        settings,
+        &session,
+        &ledger,
+    );
+    let reconciler = (settings.positions.reconcile_interval_secs > 0).then(|| {
+        session.spawn_periodic_reconcile(Duration::from_secs(
+            settings.positions.reconcile_interval_secs,
+        ))
+    });
+
+    let (connect_delay, step_delay) = match trade.settle_delay {
+        Some(secs) => (Duration::from_secs(secs), Duration::from_secs(secs)),
+        None => (WS_CONNECT_DELAY, ORDER_STEP_DELAY),
+    };
+    let sender: Box<dyn OrderGateway> = if args.dry_run {
+        info!("Dry run: orders are logged, not sent");
+        Box::new(DryRun::new(credentials.account().unwrap_or_default()))
+    } else {
+        Box::new(Live::new(client.clone()))
+    };
+    let risk_context =
+        RestRiskContext::new(client.clone(), &config.base_url, Some(session.clone()))
+            .with_funding(funding.clone())
+            .with_order_books(books.clone())
+            .with_summaries(summaries.clone())
+            .with_account(session.account().clone());
+    let guard = RiskGuard::new(sender.as_ref(), &risk_context, settings.risk.clone());
+    let gateway = MeteredGateway::new(&guard);
+    let orders = OrderManager::new(
+        &gateway,
+        tracker.clone(),
+        OrderFactory::new(ClientIdGenerator::new("demo"), config),
+    )
+    .with_markets(markets)
+    .with_quotes(quotes.clone())
+    .with_post_only_retries(trade.post_only_retries)
+    .with_positions(session.positions().clone())
+    .with_reduce_only_clamp(trade.clamp)
+    .with_order_books(books.clone())
+    .with_aggressive_mode(trade.aggressive_mode);
+    // 网格、做市与追踪止损自行处理退出信号，以便退出前撤销自己的订单
+    if let Some(ref grid) = grid {
+        tokio::time::sleep(connect_delay).await;
+        run_grid(&orders, grid, settings.run_duration_secs, &shutdown).await;
+    } else if let Some(ref maker) = maker {
+        tokio::time::sleep(connect_delay).await;
+        run_maker(&orders, maker, settings.run_duration_secs, &shutdown).await;
+    } else if let Some(ref trailing) = trailing {
+        tokio::time::sleep(connect_delay).await;
+        run_trailing(&orders, trailing, settings.run_duration_secs, &shutdown).await;
+    } else {
+        // 演示与接收行情期间收到退出信号时立即进入清理
+        super::run_until_shutdown(settings.run_duration_secs, &shutdown, async {
+            // 等待 WebSocket 连接建立
+            tokio::time::sleep(connect_delay).await;
+            let spec = match job {
+                TradeJob::Demo(spec) => spec,
+                TradeJob::Twap(config) => return run_twap(&orders, &events, &quotes, config).await,
+                TradeJob::Bracket(bracket) => {
+                    let reference = reference_mid(&client, &quotes, &settings.trade_symbol).await;
+                    return run_bracket(&orders, settings, bracket, reference, price_tick).await;
+                }
+                TradeJob::Grid(_) | TradeJob::Mm(_) | TradeJob::Trailing(_) => {
+                    unreachable!("the grid, the maker and the trailing stop run on their own")
+                }
+            };
+            let reference = reference_mid(&client, &quotes, &settings.trade_symbol).await;
+            let demo = OrderDemo {
+                orders: &orders,
+                settings,
+                price_tick,
+                step_delay,
+                max_slippage_bps: trade.max_slippage_bps,
+                ledger: &ledger,
+            };
+            demo.run(spec, reference).await;
+        })
+        .await;
+    }
+    // 撤单失败已逐个记录
+    let _ = orders.cancel_all(CancelScope::Session).await;
+
+    info!(
+        "Reconciled position {:?}",
+        session.position(&settings.trade_symbol)
+    );
+    info!("Reconciled balance {:?}", session.balance());
+    log_order_books(&books, &settings.symbols);
+    report_fills(&ledger, trade.fills_csv.as_deref());
+
+    if let Some(reconciler) = reconciler {
+        reconciler.abort();
+    }
+    account_streams.close().await;
+    if let Some(raw_channels) = raw_channels {
+        raw_channels.close().await;
+    }
+    markets_refresh.abort();
+    watchdog.abort();
+    if let Some(snapshots) = snapshots {
+        snapshots.abort();
+    }
+    if let Some(audit) = audit {
+        audit.abort();
+    }
+    super::shutdown(manager.as_ref(), &subscriptions).await;
+    super::finish_recorder(recorder).await;
+    0
+}
+
+/// `trade --paper`：用实时行情驱动本地模拟撮合运行下单演示，退出时输出会话盈亏
+pub async fn run_paper_trade(
+    args: &Args,
+    trade: &TradeArgs,
+    job: TradeJob,
+    settings: &Settings,
+    config: &ParadexConfig,
+) -> i32 {
+    let url = config.network;
+    let markets = super::validate_markets(url, settings).await;
+    let price_tick = markets.price_tick(&settings.trade_symbol);
+    let job = job.for_market(&markets);
+    let markets = SharedMarkets::new(markets);
+    let markets_refresh =
+        markets.spawn_refresh(url, Duration::from_secs(trade.markets_refresh_secs));
+    info!(
+        "Paper trading {} on {} {} market data",
+        settings.trade_symbol,
+        if args.replay.is_some() {
+            "replayed"
+        } else {
+            "live"
+        },
+        settings.symbols.join(", ")
+    );
+
+    // 公开客户端只用于查询最优价
+    let client = match Client::new(url, None).await {
+        Ok(client) => client,
+        Err(e) => {
+            error!("Failed to create Paradex client: {}", e);
+            return 1;
+        }
+    };
+    let tracker = OrderTracker::new();
+    let ledger = FillLedger::new();
+    let positions = PositionCache::new(settings.positions.tolerance);
+    let (listener, fills, position_cache) = (tracker.clone(), ledger.clone(), positions.clone());
+    let exchange = Arc::new(
+        PaperExchange::new("paper", trade.paper_balance).with_listener(Box::new(move |message| {
+            match message {
+                Message::Fills(_) => {
+                    fills.on_message(message);
+                    on_fill(&listener, message)
+                }
+                Message::Position(_) => position_cache.on_message(message),
+                _ => {
+                    listener.on_message(message);
+                    on_order_update(&listener, message)
+                }
+            }
+        })),
+    );
+
+    let shutdown = super::install_shutdown_handler();
+    let recorder = super::start_recorder(args);
+    let books = super::order_books(args, settings, &config.base_url);
+    let (source, replay) = super::market_data_source(args, config, None, None, &books).await;
+    let quotes = BboCache::new();
+    let maker = job.maker(&quotes, &books, &positions);
+    let (source, watchdog) =
+        super::watch_feeds(settings, source, maker_feed_status(maker.as_ref()));
+    let events = super::event_bus(settings, quotes.clone());
+    let matching = exchange.clone();
+    events.on_event(Box::new(move |event| matching.on_event(event)));
+    if let Some(ref maker) = maker {
+        let maker = maker.clone();
+        events.on_event(Box::new(move |event| maker.on_event(event)));
+    }
+    let subscriptions = super::subscribe_market_data(
+        source.as_ref(),
+        settings,
+        Some(&events),
+        recorder.as_ref().map(Recorder::handle).as_ref(),
+        &books,
+        super::trade_tape(args).as_ref(),
+        super::spread_monitor(args, settings).as_ref(),
+    )
+    .await;
+
+    let (connect_delay, step_delay) = match trade.settle_delay {
+        Some(secs) => (Duration::from_secs(secs), Duration::from_secs(secs)),
+        None => (WS_CONNECT_DELAY, ORDER_STEP_DELAY),
+    };
+    let guard = RiskGuard::new(exchange.as_ref(), exchange.as_ref(), settings.risk.clone());
+    let gateway = MeteredGateway::new(&guard);
+    let orders = OrderManager::new(
+        &gateway,
+        tracker.clone(),
+        OrderFactory::new(ClientIdGenerator::new("paper"), config),
+    )
+    .with_markets(markets)
+    .with_quotes(quotes.clone())
+    .with_post_only_retries(trade.post_only_retries)
+    .with_positions(positions.clone())
+    .with_reduce_only_clamp(trade.clamp)
+    .with_order_books(books.clone())
+    .with_aggressive_mode(trade.aggressive_mode);
+    // 重放结束时模拟会话随之结束
+    let grid = job.grid(settings, &quotes, Decimal::ZERO);
+    let demo = super::run_until_shutdown(settings.run_duration_secs, &shutdown, async {
+        // 等待 BBO 到达后再下单
+        tokio::time::sleep(connect_delay).await;
+        let spec = match job {
+            TradeJob::Demo(spec) => spec,
+            TradeJob::Twap(config) => return run_twap(&orders, &events, &quotes, config).await,
+            TradeJob::Bracket(bracket) => {
+                let reference = reference_mid(&client, &quotes, &settings.trade_symbol).await;
+                return run_bracket(&orders, settings, bracket, reference, price_tick).await;
+            }
+            TradeJob::Grid(_) | TradeJob::Mm(_) | TradeJob::Trailing(_) => {
+                unreachable!("the grid, the maker and the trailing stop run on their own")
+            }
+        };
+        let reference = reference_mid(&client, &quotes, &settings.trade_symbol).await;
+        let demo = OrderDemo {
+            orders: &orders,
+            settings,
+            price_tick,
+            step_delay,
+            max_slippage_bps: trade.max_slippage_bps,
+            ledger: &ledger,
+        };
+        demo.run(spec, reference).await;
+    });
+    match (grid, maker) {
+        (Some(ref grid), _) => {
+            let grid = async {
+                tokio::time::sleep(connect_delay).await;
+                run_grid(&orders, grid, settings.run_duration_secs, &shutdown).await;
+            };
+            super::run_with_replay(replay.as_deref(), grid).await;
+        }
+        (None, Some(ref maker)) => {
+            let maker = async {
+                tokio::time::sleep(connect_delay).await;
+                run_maker(&orders, maker, settings.run_duration_secs, &shutdown).await;
+            };
+            super::run_with_replay(replay.as_deref(), maker).await;
+        }
+        (None, None) => super::run_with_replay(replay.as_deref(), demo).await,
+    }
+    // 撤单失败已逐个记录
+    let _ = orders.cancel_all(CancelScope::Session).await;
+
+    println!("{}", exchange.summary());
+    report_fills(&ledger, trade.fills_csv.as_deref());
+    markets_refresh.abort();
+    watchdog.abort();
+    super::shutdown(source.as_ref(), &subscriptions).await;
+    super::finish_recorder(recorder).await;
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+    use paradex::structs::Side;
+    use trade_lighter_paradex::config::SettingsLayer;
+
+    fn job(argv: &[&str], settings: &Settings) -> Result<Option<TradeJob>, String> {
+        let args = Args::try_parse_from(
+            std::iter::once("trade_lighter_paradex").chain(argv.iter().copied()),
+        )
+        .unwrap();
+        trade_job(&args, settings)
+    }
+
+    #[test]
+    fn order_placing_subcommands_require_confirmation() {
+        let settings = SettingsLayer::default().resolve().unwrap();
+        let error = job(&["trade"], &settings).err().unwrap();
+        assert!(error.starts_with("trade places real orders"), "{error}");
+        assert!(matches!(
+            job(&["trade", "--i-know-this-places-orders"], &settings),
+            Ok(Some(TradeJob::Demo(_)))
+        ));
+        assert!(matches!(
+            job(&["trade", "--dry-run"], &settings),
+            Ok(Some(TradeJob::Demo(_)))
+        ));
+        assert!(matches!(
+            job(&["twap", "--paper"], &settings),
+            Ok(Some(TradeJob::Twap(_)))
+        ));
+        assert!(job(&["trade", "--paper", "--dry-run"], &settings).is_err());
+        // 其他子命令没有下单任务
+        assert!(matches!(job(&["markets"], &settings), Ok(None)));
+    }
+
+    #[test]
+    fn trailing_needs_its_config_section_and_a_live_position() {
+        let mut settings = SettingsLayer::default().resolve().unwrap();
+        let error = job(&["trailing", "--dry-run"], &settings).err().unwrap();
+        assert!(error.contains("trailing.side"), "{error}");
+
+        settings.trailing.side = Some(Side::BUY);
+        settings.trailing.distance = Some(Decimal::from(100));
+        assert!(matches!(
+            job(&["trailing", "--dry-run"], &settings),
+            Ok(Some(TradeJob::Trailing(_)))
+        ));
+        // 模拟账户没有持仓可保护
+        let error = job(&["trailing", "--paper"], &settings).err().unwrap();
+        assert!(error.contains("--paper"), "{error}");
+    }
+}
@@ -0,0 +1,183 @@
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+
+/// 生成的 client_id 的固定前缀
+pub const CLIENT_ID_PREFIX: &str = "tl";
+/// Paradex 接受的 client_id 最大长度
+pub const MAX_CLIENT_ID_LEN: usize = 64;
+/// 策略标识最多保留的字符数：前缀、13 位毫秒时间戳、最长 20 位的序号与三个分隔符之外的余量
+pub const MAX_STRATEGY_LEN: usize = MAX_CLIENT_ID_LEN - CLIENT_ID_PREFIX.len() - 13 - 20 - 3;
+/// 策略标识为空（或全部字符都被过滤）时使用的标识
+const DEFAULT_STRATEGY: &str = "default";
+
+/// client_id 生成或登记的错误
+#[derive(Debug, Error)]
+pub enum ClientIdError {
+    #[error("client_id {0} is already in flight in this session")]
+    InFlight(String),
+    #[error(
+        "client_id {0:?} must be 1..={MAX_CLIENT_ID_LEN} printable ASCII characters without spaces"
+    )]
+    Invalid(String),
+}
+
+/// 校验调用方指定的 client_id：非空、不超过 [`MAX_CLIENT_ID_LEN`] 且只含可见 ASCII 字符
+pub fn validate_client_id(client_id: &str) -> Result<(), ClientIdError> {
+    let valid = (1..=MAX_CLIENT_ID_LEN).contains(&client_id.len())
+        && client_id.bytes().all(|b| b.is_ascii_graphic());
+    if valid {
+        Ok(())
+    } else {
+        Err(ClientIdError::Invalid(client_id.to_string()))
+    }
+}
+
+/// 从生成的 client_id 中取出策略标识；不是本程序生成的 id 返回 `None`
+pub fn strategy_of(client_id: &str) -> Option<&str> {
+    let rest = client_id
+        .strip_prefix(CLIENT_ID_PREFIX)?
+        .strip_prefix('-')?;
+    let mut parts = rest.rsplitn(3, '-');
+    let counter = parts.next()?;
+    let millis = parts.next()?;
+    let strategy = parts.next()?;
+    let digits = |part: &str| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit());
+    (digits(counter) && digits(millis) && !strategy.is_empty()).then_some(strategy)
+}
+
+/// 策略标识只保留小写字母、数字与下划线（`-` 是 client_id 的分隔符），并截断到 [`MAX_STRATEGY_LEN`]
+fn sanitize_strategy(strategy: &str) -> String {
+    let tag: String = strategy
+        .chars()
+        .filter_map(|c| match c {
+            'a'..='z' | '0'..='9' | '_' => Some(c),
+            'A'..='Z' => Some(c.to_ascii_lowercase()),
+            '-' | ' ' | '.' => Some('_'),
+            _ => None,
+        })
+        .take(MAX_STRATEGY_LEN)
+        .collect();
+    if tag.is_empty() {
+        DEFAULT_STRATEGY.to_string()
+    } else {
+        tag
+    }
+}
+
+/// 生成会话内唯一的 client_id，并跟踪尚未结束的订单以防止重复使用
+///
+/// 格式为 `tl-{策略标识}-{会话开始的毫秒时间戳}-{序号}`：时间戳保证重启后生成的 id
+/// 不会与上一次会话冲突，策略标识用于把订单与成交推送归属到下单的策略（见 [`strategy_of`]）。
+pub struct ClientIdGenerator {
+    strategy: String,
+    session: u64,
+    counter: AtomicU64,
+    in_flight: Mutex<HashSet<String>>,
+}
+
+impl ClientIdGenerator {
+    /// `strategy` 为下单策略的标识，不合法的字符会被替换或去除
+    pub fn new(strategy: &str) -> Self {
+        let millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as u64;
+        Self {
+            strategy: sanitize_strategy(strategy),
+            session: millis,
+            counter: AtomicU64::new(0),
+            in_flight: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// 生成的 id 中的策略标识
+    pub fn strategy(&self) -> &str {
+        &self.strategy
+    }
+
+    /// 生成下一个 client_id（不做占用登记）
+    pub fn next_id(&self) -> String {
+        let n = self.counter.fetch_add(1, Ordering::Relaxed);
+        format!(
+            "{}-{}-{}-{}",
+            CLIENT_ID_PREFIX, self.strategy, self.session, n
+        )
+    }
+
+    /// 登记一个 client_id 为在途状态，重复登记或不合法的 id 会返回错误
+    pub fn reserve(&self, client_id: &str) -> Result<(), ClientIdError> {
+        validate_client_id(client_id)?;
+        if self.in_flight.lock().unwrap().insert(client_id.to_string()) {
+            Ok(())
+        } else {
+            Err(ClientIdError::InFlight(client_id.to_string()))
+        }
+    }
+
+    /// 当前在途的全部 client_id
+    pub fn in_flight(&self) -> Vec<String> {
+        self.in_flight.lock().unwrap().iter().cloned().collect()
+    }
+
+    /// 订单结束（成交/撤销/拒绝）后释放 client_id
+    pub fn release(&self, client_id: &str) {
+        self.in_flight.lock().unwrap().remove(client_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn ids_carry_the_strategy_and_stay_within_the_length_limit() {
+        let generator = ClientIdGenerator::new("Grid Maker-v2");
+        let id = generator.next_id();
+        assert!(id.starts_with("tl-grid_maker_v2-"), "{id}");
+        assert_eq!(strategy_of(&id), Some("grid_maker_v2"));
+
+        let long = ClientIdGenerator::new(&"x".repeat(100));
+        assert_eq!(long.strategy().len(), MAX_STRATEGY_LEN);
+        // 序号取最大值时仍不超过上限
+        long.counter.store(u64::MAX, Ordering::Relaxed);
+        let id = long.next_id();
+        assert_eq!(id.len(), MAX_CLIENT_ID_LEN);
+        assert!(validate_client_id(&id).is_ok());
+
+        assert_eq!(ClientIdGenerator::new("!!").strategy(), "default");
+        assert_eq!(strategy_of("my-order-1"), None);
+        assert_eq!(strategy_of("tl-demo-1735689600000-x"), None);
+        assert!(validate_client_id(&"a".repeat(MAX_CLIENT_ID_LEN + 1)).is_err());
+        assert!(validate_client_id("has space").is_err());
+        assert!(validate_client_id("").is_err());
+    }
+
+    #[test]
+    fn concurrent_generation_never_collides() {
+        let generator = Arc::new(ClientIdGenerator::new("demo"));
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                let generator = generator.clone();
+                std::thread::spawn(move || {
+                    (0..1000)
+                        .map(|_| {
+                            let id = generator.next_id();
+                            generator.reserve(&id).unwrap();
+                            id
+                        })
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        let ids: HashSet<_> = handles
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap())
+            .collect();
+        assert_eq!(ids.len(), 8000);
+        assert_eq!(generator.in_flight().len(), 8000);
+    }
+}
@@ -0,0 +1,207 @@
+mod settings;
+
+pub use settings::{
+    AccountLayer, AccountSettings, BboLayer, BboSettings, BookLayer, BookRefresh, BookSettings,
+    ChannelSelection, GridLayer, GridSettings, MakerLayer, MakerSettings, OrderLayer,
+    OrderSettings, PositionLayer, PositionSettings, RiskLayer, RiskLimits, Settings, SettingsLayer,
+    SignalLayer, SignalSettings, WsChannel, DEFAULT_CONFIG_FILE, DEFAULT_STALE_FEED_SECS,
+    DEFAULT_SYMBOL,
+};
+
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+use toml_edit::{DocumentMut, Item};
+
+/// 默认的多账户配置文件
+pub const DEFAULT_ACCOUNTS_FILE: &str = "accounts.toml";
+
+/// 读取 `accounts.toml` 与 `trade_lighter.toml` 的错误
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("Failed to read {path}: {source}")]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("Failed to parse {path}: {message}")]
+    Parse { path: PathBuf, message: String },
+    #[error("Profile '{0}' not found")]
+    ProfileNotFound(String),
+    #[error("Profile '{profile}': {message}")]
+    InvalidProfile { profile: String, message: String },
+    #[error("Profile '{0}' targets production; pass --production to trade on it")]
+    ProductionNotEnabled(String),
+    #[error("Profile '{0}' targets testnet but --production was passed")]
+    TestnetProfileInProduction(String),
+    #[error("Invalid configuration: {0}")]
+    InvalidSettings(String),
+    #[error("Risk limit exceeded: {0}")]
+    RiskLimit(String),
+}
+
+/// Paradex 环境
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Environment {
+    Testnet,
+    Production,
+}
+
+/// `accounts.toml` 中的一个命名账户
+///
+/// ```toml
+/// [profiles.main]
+/// environment = "production"
+/// paradex_account_private_key_hex = "0x..."
+/// paradex_account_address = "0x..."
+/// eth_account_address = "0x..."
+/// ```
+#[derive(Debug, Clone)]
+pub struct AccountProfile {
+    pub name: String,
+    pub environment: Environment,
+    pub paradex_account_private_key_hex: Option<String>,
+    pub paradex_account_address: Option<String>,
+    pub eth_account_address: Option<String>,
+}
+
+impl AccountProfile {
+    /// 生产环境 profile 必须显式传入 `--production`，避免误操作实盘
+    pub fn check_environment(&self, production: bool) -> Result<(), ConfigError> {
+        match (self.environment, production) {
+            (Environment::Production, false) => {
+                Err(ConfigError::ProductionNotEnabled(self.name.clone()))
+            }
+            (Environment::Testnet, true) => {
+                Err(ConfigError::TestnetProfileInProduction(self.name.clone()))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// 从配置文件加载指定 profile
+pub fn load_profile(path: &Path, name: &str) -> Result<AccountProfile, ConfigError> {
+    let contents = std::fs::read_to_string(path).map_err(|source| ConfigError::Io {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    parse_profile(path, &contents, name)
+}
+
+fn parse_profile(path: &Path, contents: &str, name: &str) -> Result<AccountProfile, ConfigError> {
+    let document: DocumentMut =
+        contents
+            .parse()
+            .map_err(|e: toml_edit::TomlError| ConfigError::Parse {
+                path: path.to_path_buf(),
+                message: e.message().to_string(),
+            })?;
+    let profile = document
+        .get("profiles")
+        .and_then(|profiles| profiles.get(name))
+        .filter(|profile| profile.is_table_like())
+        .ok_or_else(|| ConfigError::ProfileNotFound(name.to_string()))?;
+
+    let invalid = |message: String| ConfigError::InvalidProfile {
+        profile: name.to_string(),
+        message,
+    };
+    let field = |key: &str| -> Result<Option<String>, ConfigError> {
+        match profile.get(key) {
+            None => Ok(None),
+            Some(Item::Value(value)) => value
+                .as_str()
+                .map(|s| Some(s.to_string()))
+                .ok_or_else(|| invalid(format!("'{}' must be a string", key))),
+            Some(_) => Err(invalid(format!("'{}' must be a string", key))),
+        }
+    };
+
+    let environment = match field("environment")?.as_deref() {
+        Some("testnet") => Environment::Testnet,
+        Some("production") => Environment::Production,
+        Some(other) => {
+            return Err(invalid(format!(
+                "unknown environment '{}' (expected testnet or production)",
+                other
+            )))
+        }
+        None => return Err(invalid("missing 'environment'".into())),
+    };
+
+    Ok(AccountProfile {
+        name: name.to_string(),
+        environment,
+        paradex_account_private_key_hex: field("paradex_account_private_key_hex")?,
+        paradex_account_address: field("paradex_account_address")?,
+        eth_account_address: field("eth_account_address")?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(contents: &str, name: &str) -> Result<AccountProfile, ConfigError> {
+        parse_profile(Path::new(DEFAULT_ACCOUNTS_FILE), contents, name)
+    }
+
+    const ACCOUNTS: &str = r#"
+[profiles.test]
+environment = "testnet"
+paradex_account_private_key_hex = "0x1"
+paradex_account_address = "0x2"
+
+[profiles.live]
+environment = "production"
+paradex_account_private_key_hex = "0x3"
+paradex_account_address = "0x4"
+eth_account_address = "0x5"
+"#;
+
+    #[test]
+    fn parses_named_profile() {
+        let profile = parse(ACCOUNTS, "live").unwrap();
+        assert_eq!(profile.environment, Environment::Production);
+        assert_eq!(profile.paradex_account_address.as_deref(), Some("0x4"));
+        assert_eq!(profile.eth_account_address.as_deref(), Some("0x5"));
+
+        let profile = parse(ACCOUNTS, "test").unwrap();
+        assert_eq!(profile.environment, Environment::Testnet);
+        assert_eq!(profile.eth_account_address, None);
+
+        assert!(matches!(
+            parse(ACCOUNTS, "missing"),
+            Err(ConfigError::ProfileNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn production_profile_requires_production_flag() {
+        let live = parse(ACCOUNTS, "live").unwrap();
+        assert!(matches!(
+            live.check_environment(false),
+            Err(ConfigError::ProductionNotEnabled(_))
+        ));
+        assert!(live.check_environment(true).is_ok());
+
+        let test = parse(ACCOUNTS, "test").unwrap();
+        assert!(test.check_environment(false).is_ok());
+        assert!(test.check_environment(true).is_err());
+    }
+
+    #[test]
+    fn rejects_missing_or_unknown_environment() {
+        let contents = "[profiles.a]\nparadex_account_address = \"0x1\"\n\n[profiles.b]\nenvironment = \"mainnet\"\n";
+        assert!(matches!(
+            parse(contents, "a"),
+            Err(ConfigError::InvalidProfile { .. })
+        ));
+        assert!(matches!(
+            parse(contents, "b"),
+            Err(ConfigError::InvalidProfile { .. })
+        ));
+    }
+}
@@ -0,0 +1,1333 @@
+use paradex::structs::{OrderInstruction, Side};
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use serde_json::{Map, Value as Json};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+use std::str::FromStr;
+use toml_edit::{DocumentMut, Item, Value};
+
+use super::{ConfigError, Environment};
+use crate::orders::{validate_recv_window, AggressiveMode, StpMode};
+use crate::spread::lighter;
+use crate::strategies::TrailMode;
+
+/// 默认的运行配置文件
+pub const DEFAULT_CONFIG_FILE: &str = "trade_lighter.toml";
+/// 默认订阅与交易的市场
+pub const DEFAULT_SYMBOL: &str = "BTC-USD-PERP";
+
+/// 各频道默认的停滞阈值（秒）；私有频道只在有事件时推送，默认不检查
+pub const DEFAULT_STALE_FEED_SECS: [(WsChannel, u64); 6] = [
+    (WsChannel::MarketsSummary, 30),
+    (WsChannel::Bbo, 10),
+    (WsChannel::Trades, 300),
+    (WsChannel::OrderBook, 30),
+    (WsChannel::OrderBookDeltas, 10),
+    (WsChannel::FundingData, 600),
+];
+
+/// 可订阅的 WebSocket 频道（名称与日志中的 `channel` 字段一致，`--channels` 中的简写为别名）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WsChannel {
+    #[serde(alias = "market_summary")]
+    MarketsSummary,
+    Bbo,
+    Trades,
+    #[serde(alias = "orderbook")]
+    OrderBook,
+    #[serde(alias = "orderbook_deltas")]
+    OrderBookDeltas,
+    #[serde(alias = "funding")]
+    FundingData,
+    Orders,
+    Fills,
+    #[serde(alias = "position")]
+    Positions,
+    Account,
+    #[serde(alias = "balance")]
+    BalanceEvents,
+    FundingPayments,
+    #[serde(alias = "tradebusts")]
+    TradeBusts,
+    Transfers,
+}
+
+impl WsChannel {
+    /// 全部频道
+    pub const ALL: [WsChannel; 14] = [
+        WsChannel::MarketsSummary,
+        WsChannel::Bbo,
+        WsChannel::Trades,
+        WsChannel::OrderBook,
+        WsChannel::OrderBookDeltas,
+        WsChannel::FundingData,
+        WsChannel::Orders,
+        WsChannel::Fills,
+        WsChannel::Positions,
+        WsChannel::Account,
+        WsChannel::BalanceEvents,
+        WsChannel::FundingPayments,
+        WsChannel::TradeBusts,
+        WsChannel::Transfers,
+    ];
+
+    /// `--channels` 中使用的名称
+    pub fn cli_name(self) -> &'static str {
+        match self {
+            WsChannel::MarketsSummary => "market_summary",
+            WsChannel::Bbo => "bbo",
+            WsChannel::Trades => "trades",
+            WsChannel::OrderBook => "orderbook",
+            WsChannel::OrderBookDeltas => "orderbook_deltas",
+            WsChannel::FundingData => "funding",
+            WsChannel::Orders => "orders",
+            WsChannel::Fills => "fills",
+            WsChannel::Positions => "position",
+            WsChannel::Account => "account",
+            WsChannel::BalanceEvents => "balance",
+            WsChannel::FundingPayments => "funding_payments",
+            WsChannel::TradeBusts => "trade_busts",
+            WsChannel::Transfers => "transfers",
+        }
+    }
+
+    /// 私有频道需要认证后的 REST 客户端（即私钥）
+    pub fn is_private(self) -> bool {
+        matches!(
+            self,
+            WsChannel::Orders
+                | WsChannel::Fills
+                | WsChannel::Positions
+                | WsChannel::Account
+                | WsChannel::BalanceEvents
+                | WsChannel::FundingPayments
+                | WsChannel::TradeBusts
+                | WsChannel::Transfers
+        )
+    }
+}
+
+impl FromStr for WsChannel {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        WsChannel::deserialize(Json::String(name.trim().to_string())).map_err(|_| {
+            let valid: Vec<_> = WsChannel::ALL.iter().map(|c| c.cli_name()).collect();
+            format!(
+                "unknown channel {:?}; valid channels: {}",
+                name,
+                valid.join(",")
+            )
+        })
+    }
+}
+
+/// 选定订阅的 WebSocket 频道集合
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(from = "Vec<WsChannel>")]
+pub struct ChannelSelection(BTreeSet<WsChannel>);
+
+impl ChannelSelection {
+    /// 全部频道（默认）
+    pub fn all() -> Self {
+        Self(WsChannel::ALL.into_iter().collect())
+    }
+
+    /// 是否选中 `channel`
+    pub fn contains(&self, channel: WsChannel) -> bool {
+        self.0.contains(&channel)
+    }
+
+    /// 选中的私有频道
+    pub fn private(&self) -> Vec<WsChannel> {
+        self.0.iter().copied().filter(|c| c.is_private()).collect()
+    }
+}
+
+impl From<Vec<WsChannel>> for ChannelSelection {
+    fn from(channels: Vec<WsChannel>) -> Self {
+        Self(channels.into_iter().collect())
+    }
+}
+
+/// 解析逗号分隔的频道列表，如 `bbo,trades,orders`
+impl FromStr for ChannelSelection {
+    type Err = String;
+
+    fn from_str(list: &str) -> Result<Self, Self::Err> {
+        let channels = list
+            .split(',')
+            .filter(|name| !name.trim().is_empty())
+            .map(WsChannel::from_str)
+            .collect::<Result<BTreeSet<_>, _>>()?;
+        if channels.is_empty() {
+            return Err("at least one channel is required".to_string());
+        }
+        Ok(Self(channels))
+    }
+}
+
+/// `order_book` 频道的推送间隔（交易所接受的取值）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum BookRefresh {
+    #[default]
+    #[serde(rename = "50ms")]
+    #[cfg_attr(feature = "cli", value(name = "50ms"))]
+    Ms50,
+    #[serde(rename = "100ms")]
+    #[cfg_attr(feature = "cli", value(name = "100ms"))]
+    Ms100,
+    #[serde(rename = "1s")]
+    #[cfg_attr(feature = "cli", value(name = "1s"))]
+    S1,
+}
+
+impl BookRefresh {
+    /// 订阅频道名中的写法
+    pub fn as_str(self) -> &'static str {
+        match self {
+            BookRefresh::Ms50 => "50ms",
+            BookRefresh::Ms100 => "100ms",
+            BookRefresh::S1 => "1s",
+        }
+    }
+}
+
+/// 下单演示的订单参数
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrderSettings {
+    pub size: Decimal,
+    /// 挂单价相对最优买价的偏移（基点），避免 POST_ONLY 单成交
+    pub price_offset_bps: Decimal,
+    pub instruction: OrderInstruction,
+    /// 订单的 recv_window（毫秒）；`None` 时沿用网络配置的默认值
+    pub recv_window_ms: Option<u64>,
+    pub stp: StpMode,
+}
+
+/// 风控上限，下单前检查
+#[derive(Debug, Clone, PartialEq)]
+pub struct RiskLimits {
+    pub max_order_size: Decimal,
+    /// 下单后单个市场的持仓数量上限（多空取绝对值）；`None` 表示不限制
+    pub max_position: Option<Decimal>,
+    /// 名义价值上限（报价货币）：单笔订单，以及下单后的持仓
+    pub max_notional: Decimal,
+    /// 持仓预计 8 小时资金费成本（相对名义价值的基点）超过该值时告警；`None` 表示不告警
+    pub funding_alert_bps: Option<Decimal>,
+    /// 市价单按本地订单簿估算的成交均价偏离中间价的基点上限；`None` 表示不检查
+    pub max_slippage_bps: Option<Decimal>,
+    /// 可用保证金下限：低于该值时告警，并拦截增加持仓的订单；`None` 表示不检查
+    pub min_free_collateral: Option<Decimal>,
+}
+
+impl RiskLimits {
+    /// 检查订单是否超出风控上限
+    pub fn check(&self, price: Decimal, size: Decimal) -> Result<(), ConfigError> {
+        if size > self.max_order_size {
+            return Err(ConfigError::RiskLimit(format!(
+                "order size {} exceeds max_order_size {}",
+                size, self.max_order_size
+            )));
+        }
+        let notional = price * size;
+        if notional > self.max_notional {
+            return Err(ConfigError::RiskLimit(format!(
+                "order notional {} exceeds max_notional {}",
+                notional, self.max_notional
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// 订单簿信号（失衡度与微价格）的参数
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SignalSettings {
+    /// 计算失衡度的每侧档位数
+    pub depth: usize,
+    /// EWMA 平滑的半衰期（毫秒），0 表示不平滑
+    pub half_life_ms: u64,
+}
+
+/// 持仓缓存与 REST 对账的参数
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PositionSettings {
+    /// 定期以 REST 持仓对账的间隔（秒），0 表示只在启动与重连后对账
+    pub reconcile_interval_secs: u64,
+    /// WebSocket 与 REST 持仓数量的差异超过该值时记为偏差
+    pub tolerance: Decimal,
+}
+
+/// 账户保证金跟踪的告警参数
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AccountSettings {
+    /// 可用保证金在 `drop_window_secs` 内相对最高值的跌幅（百分比）超过该值时告警；`None` 表示不告警
+    pub max_drop_pct: Option<Decimal>,
+    pub drop_window_secs: u64,
+}
+
+/// `order_book` 频道的订阅参数
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BookSettings {
+    pub refresh_rate: BookRefresh,
+    /// 聚合档位的价格间隔；每个档位单独订阅并维护一本订单簿，为空时按交易所默认精度订阅一次
+    pub price_ticks: Vec<Decimal>,
+}
+
+/// BBO 去重的参数
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BboSettings {
+    /// 只有买卖价变化时才传递报价，忽略仅数量变化的推送
+    pub ignore_size_changes: bool,
+}
+
+/// `grid` 子命令的网格参数
+#[derive(Debug, Clone, PartialEq)]
+pub struct GridSettings {
+    /// 网格中心价；`None` 时取启动时的 BBO 中间价
+    pub center_price: Option<Decimal>,
+    /// 相邻两档的价格间隔；运行 `grid` 时必须设置
+    pub spacing: Option<Decimal>,
+    /// 每侧档位数
+    pub levels: u32,
+    /// 每档订单数量，默认与 `order.size` 相同
+    pub size: Decimal,
+}
+
+/// `mm` 子命令的做市参数
+#[derive(Debug, Clone, PartialEq)]
+pub struct MakerSettings {
+    /// 做市的市场，运行 `mm` 时须在 `symbols` 中；默认 `trade_symbol`
+    pub symbols: Vec<String>,
+    /// 买卖报价相对参考价的偏移（基点）
+    pub half_spread_bps: Decimal,
+    /// 每侧报价数量，默认与 `order.size` 相同
+    pub size: Decimal,
+    /// 目标价与挂单价相差超过该数量的价格精度时改价
+    pub tolerance_ticks: u32,
+    /// 同一市场两次改价的最短间隔（毫秒）
+    pub min_requote_ms: u64,
+    /// 每单位持仓使报价整体偏移的基点数，0 表示不按持仓调整
+    pub skew_bps_per_unit: Decimal,
+    /// 持仓（多空取绝对值）达到该值时停止报价积累持仓的一侧；`None` 表示不限制
+    pub max_inventory: Option<Decimal>,
+}
+
+/// `trailing` 子命令的追踪止损参数
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrailingSettings {
+    /// 要保护的持仓方向：`BUY` 为多头、`SELL` 为空头；运行 `trailing` 时必须设置
+    pub side: Option<Side>,
+    /// 止损位与有利极值的绝对价格距离；与 `distance_bps` 二选一
+    pub distance: Option<Decimal>,
+    /// 止损位与有利极值的距离（基点）
+    pub distance_bps: Option<Decimal>,
+    pub mode: TrailMode,
+    /// 本地平仓的方式：市价单，或相对中间价 `max_slippage_bps` 的 IOC 限价单
+    pub close: AggressiveMode,
+    pub max_slippage_bps: Decimal,
+    /// 挂单模式下止损位至少移动该数量的价格精度才撤单重挂
+    pub min_step_ticks: u32,
+    /// 平仓数量，默认与 `order.size` 相同
+    pub size: Decimal,
+}
+
+/// Paradex 市场在 Lighter 中对应的市场
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LighterMarket {
+    /// Lighter 的市场符号，如 `BTC`
+    pub symbol: String,
+    /// Lighter 的市场编号，订阅 `order_book/{market_id}`
+    pub market_id: u32,
+}
+
+/// Paradex 与 Lighter 之间的跨所价差监控参数
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpreadSettings {
+    /// 价差达到该基点数时记录套利机会
+    pub threshold_bps: Decimal,
+    /// 报价超过该毫秒数未更新视为过期，不参与比较
+    pub max_quote_age_ms: u64,
+    /// Lighter 行情的 WebSocket 地址
+    pub lighter_url: String,
+    /// Paradex 市场 -> Lighter 市场；只监控其中已订阅的市场，为空时不启用
+    pub markets: BTreeMap<String, LighterMarket>,
+}
+
+/// 合并文件、环境变量与命令行后的运行配置
+///
+/// 优先级：命令行 > 配置文件 > 环境变量 > 默认值。不包含私钥等敏感信息，可直接记录日志。
+#[derive(Debug, Clone, PartialEq)]
+pub struct Settings {
+    pub environment: Environment,
+    pub symbols: Vec<String>,
+    /// 下单演示使用的市场，默认第一个 `symbols`
+    pub trade_symbol: String,
+    pub order: OrderSettings,
+    pub channels: ChannelSelection,
+    /// 接收行情的时长（秒），0 表示运行到收到中断信号
+    pub run_duration_secs: u64,
+    pub risk: RiskLimits,
+    /// 各频道超过该秒数没有消息视为停滞；未列出的频道不检查
+    pub watchdog: BTreeMap<WsChannel, u64>,
+    pub signals: SignalSettings,
+    pub positions: PositionSettings,
+    pub account: AccountSettings,
+    pub order_book: BookSettings,
+    pub bbo: BboSettings,
+    pub grid: GridSettings,
+    pub mm: MakerSettings,
+    pub trailing: TrailingSettings,
+    pub spread: SpreadSettings,
+}
+
+impl Settings {
+    /// 是否订阅 `channel`
+    pub fn subscribes(&self, channel: WsChannel) -> bool {
+        self.channels.contains(channel)
+    }
+}
+
+/// 配置的一层来源；未设置的字段为 `None`，由更低优先级的层或默认值补齐
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SettingsLayer {
+    pub environment: Option<Environment>,
+    pub symbols: Option<Vec<String>>,
+    pub trade_symbol: Option<String>,
+    pub order: OrderLayer,
+    pub channels: Option<ChannelSelection>,
+    pub run_duration_secs: Option<u64>,
+    pub risk: RiskLayer,
+    /// 按频道覆盖停滞阈值（秒），0 表示不检查
+    pub watchdog: BTreeMap<WsChannel, u64>,
+    pub signals: SignalLayer,
+    pub positions: PositionLayer,
+    pub account: AccountLayer,
+    pub order_book: BookLayer,
+    pub bbo: BboLayer,
+    pub grid: GridLayer,
+    pub mm: MakerLayer,
+    pub trailing: TrailingLayer,
+    pub spread: SpreadLayer,
+}
+
+/// `[order]` 一节
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OrderLayer {
+    pub size: Option<Decimal>,
+    pub price_offset_bps: Option<Decimal>,
+    pub instruction: Option<OrderInstruction>,
+    pub recv_window_ms: Option<u64>,
+    pub stp: Option<StpMode>,
+}
+
+/// `[risk]` 一节
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RiskLayer {
+    pub max_order_size: Option<Decimal>,
+    pub max_position: Option<Decimal>,
+    pub max_notional: Option<Decimal>,
+    pub funding_alert_bps: Option<Decimal>,
+    pub max_slippage_bps: Option<Decimal>,
+    pub min_free_collateral: Option<Decimal>,
+}
+
+/// `[signals]` 一节
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SignalLayer {
+    pub depth: Option<usize>,
+    pub half_life_ms: Option<u64>,
+}
+
+/// `[positions]` 一节
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PositionLayer {
+    pub reconcile_interval_secs: Option<u64>,
+    pub tolerance: Option<Decimal>,
+}
+
+/// `[account]` 一节
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AccountLayer {
+    pub max_drop_pct: Option<Decimal>,
+    pub drop_window_secs: Option<u64>,
+}
+
+/// `[book]` 一节
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BookLayer {
+    pub refresh_rate: Option<BookRefresh>,
+    pub price_ticks: Option<Vec<Decimal>>,
+}
+
+/// `[bbo]` 一节
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BboLayer {
+    pub ignore_size_changes: Option<bool>,
+}
+
+/// `[grid]` 一节
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GridLayer {
+    pub center_price: Option<Decimal>,
+    pub spacing: Option<Decimal>,
+    pub levels: Option<u32>,
+    pub size: Option<Decimal>,
+}
+
+/// `[maker]` 一节
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MakerLayer {
+    pub symbols: Option<Vec<String>>,
+    pub half_spread_bps: Option<Decimal>,
+    pub size: Option<Decimal>,
+    pub tolerance_ticks: Option<u32>,
+    pub min_requote_ms: Option<u64>,
+    pub skew_bps_per_unit: Option<Decimal>,
+    pub max_inventory: Option<Decimal>,
+}
+
+/// `[trailing]` 一节
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TrailingLayer {
+    pub side: Option<Side>,
+    pub distance: Option<Decimal>,
+    pub distance_bps: Option<Decimal>,
+    pub mode: Option<TrailMode>,
+    pub close: Option<AggressiveMode>,
+    pub max_slippage_bps: Option<Decimal>,
+    pub min_step_ticks: Option<u32>,
+    pub size: Option<Decimal>,
+}
+
+/// `[spread]` 一节
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SpreadLayer {
+    pub threshold_bps: Option<Decimal>,
+    pub max_quote_age_ms: Option<u64>,
+    pub lighter_url: Option<String>,
+    /// 按 Paradex 市场覆盖映射
+    pub markets: BTreeMap<String, LighterMarket>,
+}
+
+impl SettingsLayer {
+    /// 读取配置文件；`required` 为 false 时文件不存在视为空配置
+    pub fn load(path: &Path, required: bool) -> Result<Self, ConfigError> {
+        match std::fs::read_to_string(path) {
+            Ok(contents) => Self::parse(path, &contents),
+            Err(e) if !required && e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(source) => Err(ConfigError::Io {
+                path: path.to_path_buf(),
+                source,
+            }),
+        }
+    }
+
+    fn parse(path: &Path, contents: &str) -> Result<Self, ConfigError> {
+        let parse_error = |message: String| ConfigError::Parse {
+            path: path.to_path_buf(),
+            message,
+        };
+        let document: DocumentMut = contents
+            .parse()
+            .map_err(|e: toml_edit::TomlError| parse_error(e.message().to_string()))?;
+        serde_json::from_value(item_to_json(document.as_item()))
+            .map_err(|e| parse_error(e.to_string()))
+    }
+
+    /// 从 `TRADE_LIGHTER_*` 环境变量读取（`lookup` 便于测试替换）
+    pub fn from_env(lookup: impl Fn(&str) -> Option<String>) -> Result<Self, ConfigError> {
+        fn parse<T: serde::de::DeserializeOwned>(
+            lookup: &impl Fn(&str) -> Option<String>,
+            key: &str,
+        ) -> Result<Option<T>, ConfigError> {
+            lookup(key)
+                .map(|value| {
+                    // 数字也以字符串形式尝试，兼容 u64 与 Decimal
+                    let json = value
+                        .parse::<u64>()
+                        .map(Json::from)
+                        .unwrap_or(Json::String(value));
+                    serde_json::from_value(json)
+                        .map_err(|e| ConfigError::InvalidSettings(format!("{}: {}", key, e)))
+                })
+                .transpose()
+        }
+
+        Ok(Self {
+            environment: parse(&lookup, "TRADE_LIGHTER_ENVIRONMENT")?,
+            symbols: lookup("TRADE_LIGHTER_SYMBOLS")
+                .map(|value| value.split(',').map(|s| s.trim().to_string()).collect()),
+            order: OrderLayer {
+                size: parse(&lookup, "TRADE_LIGHTER_ORDER_SIZE")?,
+                ..OrderLayer::default()
+            },
+            run_duration_secs: parse(&lookup, "TRADE_LIGHTER_RUN_DURATION_SECS")?,
+            ..Self::default()
+        })
+    }
+
+    /// 用 `higher` 中已设置的字段覆盖当前层
+    pub fn merge(self, higher: SettingsLayer) -> SettingsLayer {
+        let mut watchdog = self.watchdog;
+        watchdog.extend(higher.watchdog);
+        let mut spread_markets = self.spread.markets;
+        spread_markets.extend(higher.spread.markets);
+        SettingsLayer {
+            environment: higher.environment.or(self.environment),
+            symbols: higher.symbols.or(self.symbols),
+            trade_symbol: higher.trade_symbol.or(self.trade_symbol),
+            order: OrderLayer {
+                size: higher.order.size.or(self.order.size),
+                price_offset_bps: higher
+                    .order
+                    .price_offset_bps
+                    .or(self.order.price_offset_bps),
+                instruction: higher.order.instruction.or(self.order.instruction),
+                recv_window_ms: higher.order.recv_window_ms.or(self.order.recv_window_ms),
+                stp: higher.order.stp.or(self.order.stp),
+            },
+            channels: higher.channels.or(self.channels),
+            run_duration_secs: higher.run_duration_secs.or(self.run_duration_secs),
+            risk: RiskLayer {
+                max_order_size: higher.risk.max_order_size.or(self.risk.max_order_size),
+                max_position: higher.risk.max_position.or(self.risk.max_position),
+                max_notional: higher.risk.max_notional.or(self.risk.max_notional),
+                funding_alert_bps: higher
+                    .risk
+                    .funding_alert_bps
+                    .or(self.risk.funding_alert_bps),
+                max_slippage_bps: higher.risk.max_slippage_bps.or(self.risk.max_slippage_bps),
+                min_free_collateral: higher
+                    .risk
+                    .min_free_collateral
+                    .or(self.risk.min_free_collateral),
+            },
+            watchdog,
+            signals: SignalLayer {
+                depth: higher.signals.depth.or(self.signals.depth),
+                half_life_ms: higher.signals.half_life_ms.or(self.signals.half_life_ms),
+            },
+            positions: PositionLayer {
+                reconcile_interval_secs: higher
+                    .positions
+                    .reconcile_interval_secs
+                    .or(self.positions.reconcile_interval_secs),
+                tolerance: higher.positions.tolerance.or(self.positions.tolerance),
+            },
+            account: AccountLayer {
+                max_drop_pct: higher.account.max_drop_pct.or(self.account.max_drop_pct),
+                drop_window_secs: higher
+                    .account
+                    .drop_window_secs
+                    .or(self.account.drop_window_secs),
+            },
+            order_book: BookLayer {
+                refresh_rate: higher
+                    .order_book
+                    .refresh_rate
+                    .or(self.order_book.refresh_rate),
+                price_ticks: higher
+                    .order_book
+                    .price_ticks
+                    .or(self.order_book.price_ticks),
+            },
+            bbo: BboLayer {
+                ignore_size_changes: higher
+                    .bbo
+                    .ignore_size_changes
+                    .or(self.bbo.ignore_size_changes),
+            },
+            grid: GridLayer {
+                center_price: higher.grid.center_price.or(self.grid.center_price),
+                spacing: higher.grid.spacing.or(self.grid.spacing),
+                levels: higher.grid.levels.or(self.grid.levels),
+                size: higher.grid.size.or(self.grid.size),
+            },
+            mm: MakerLayer {
+                symbols: higher.mm.symbols.or(self.mm.symbols),
+                half_spread_bps: higher.mm.half_spread_bps.or(self.mm.half_spread_bps),
+                size: higher.mm.size.or(self.mm.size),
+                tolerance_ticks: higher.mm.tolerance_ticks.or(self.mm.tolerance_ticks),
+                min_requote_ms: higher.mm.min_requote_ms.or(self.mm.min_requote_ms),
+                skew_bps_per_unit: higher.mm.skew_bps_per_unit.or(self.mm.skew_bps_per_unit),
+                max_inventory: higher.mm.max_inventory.or(self.mm.max_inventory),
+            },
+            trailing: TrailingLayer {
+                side: higher.trailing.side.or(self.trailing.side),
+                distance: higher.trailing.distance.or(self.trailing.distance),
+                distance_bps: higher.trailing.distance_bps.or(self.trailing.distance_bps),
+                mode: higher.trailing.mode.or(self.trailing.mode),
+                close: higher.trailing.close.or(self.trailing.close),
+                max_slippage_bps: higher
+                    .trailing
+                    .max_slippage_bps
+                    .or(self.trailing.max_slippage_bps),
+                min_step_ticks: higher
+                    .trailing
+                    .min_step_ticks
+                    .or(self.trailing.min_step_ticks),
+                size: higher.trailing.size.or(self.trailing.size),
+            },
+            spread: SpreadLayer {
+                threshold_bps: higher.spread.threshold_bps.or(self.spread.threshold_bps),
+                max_quote_age_ms: higher
+                    .spread
+                    .max_quote_age_ms
+                    .or(self.spread.max_quote_age_ms),
+                lighter_url: higher.spread.lighter_url.or(self.spread.lighter_url),
+                markets: spread_markets,
+            },
+        }
+    }
+
+    /// 补齐默认值并校验
+    pub fn resolve(self) -> Result<Settings, ConfigError> {
+        let symbols = self
+            .symbols
+            .unwrap_or_else(|| vec![DEFAULT_SYMBOL.to_string()]);
+        let mut watchdog = BTreeMap::from(DEFAULT_STALE_FEED_SECS);
+        watchdog.extend(self.watchdog);
+        watchdog.retain(|_, secs| *secs > 0);
+        let order_size = self.order.size.unwrap_or(Decimal::new(5, 3));
+        let trade_symbol = self
+            .trade_symbol
+            .or_else(|| symbols.first().cloned())
+            .unwrap_or_default();
+        let mm = MakerSettings {
+            symbols: self
+                .mm
+                .symbols
+                .unwrap_or_else(|| vec![trade_symbol.clone()]),
+            half_spread_bps: self.mm.half_spread_bps.unwrap_or(Decimal::TEN),
+            size: self.mm.size.unwrap_or(order_size),
+            tolerance_ticks: self.mm.tolerance_ticks.unwrap_or(2),
+            min_requote_ms: self.mm.min_requote_ms.unwrap_or(500),
+            skew_bps_per_unit: self.mm.skew_bps_per_unit.unwrap_or(Decimal::ZERO),
+            max_inventory: self.mm.max_inventory,
+        };
+        let settings = Settings {
+            environment: self.environment.unwrap_or(Environment::Testnet),
+            trade_symbol,
+            symbols,
+            order: OrderSettings {
+                size: order_size,
+                price_offset_bps: self.order.price_offset_bps.unwrap_or(Decimal::from(500)),
+                instruction: self
+                    .order
+                    .instruction
+                    .unwrap_or(OrderInstruction::POST_ONLY),
+                recv_window_ms: self.order.recv_window_ms,
+                stp: self.order.stp.unwrap_or(StpMode::ExpireMaker),
+            },
+            channels: self.channels.unwrap_or_else(ChannelSelection::all),
+            run_duration_secs: self.run_duration_secs.unwrap_or(120),
+            risk: RiskLimits {
+                max_order_size: self.risk.max_order_size.unwrap_or(Decimal::new(1, 2)),
+                max_position: self.risk.max_position,
+                max_notional: self.risk.max_notional.unwrap_or(Decimal::from(1000)),
+                funding_alert_bps: self.risk.funding_alert_bps,
+                max_slippage_bps: self.risk.max_slippage_bps,
+                min_free_collateral: self.risk.min_free_collateral,
+            },
+            watchdog,
+            signals: SignalSettings {
+                depth: self.signals.depth.unwrap_or(5),
+                half_life_ms: self.signals.half_life_ms.unwrap_or(1_000),
+            },
+            positions: PositionSettings {
+                reconcile_interval_secs: self.positions.reconcile_interval_secs.unwrap_or(60),
+                tolerance: self.positions.tolerance.unwrap_or(Decimal::new(1, 8)),
+            },
+            account: AccountSettings {
+                max_drop_pct: self.account.max_drop_pct,
+                drop_window_secs: self.account.drop_window_secs.unwrap_or(300),
+            },
+            order_book: BookSettings {
+                refresh_rate: self.order_book.refresh_rate.unwrap_or_default(),
+                price_ticks: {
+                    let mut ticks = self.order_book.price_ticks.unwrap_or_default();
+                    ticks.sort();
+                    ticks.dedup();
+                    ticks
+                },
+            },
+            bbo: BboSettings {
+                ignore_size_changes: self.bbo.ignore_size_changes.unwrap_or(false),
+            },
+            grid: GridSettings {
+                center_price: self.grid.center_price,
+                spacing: self.grid.spacing,
+                levels: self.grid.levels.unwrap_or(5),
+                size: self.grid.size.unwrap_or(order_size),
+            },
+            mm,
+            trailing: TrailingSettings {
+                side: self.trailing.side,
+                distance: self.trailing.distance,
+                distance_bps: self.trailing.distance_bps,
+                mode: self.trailing.mode.unwrap_or_default(),
+                close: self.trailing.close.unwrap_or_default(),
+                max_slippage_bps: self.trailing.max_slippage_bps.unwrap_or(Decimal::from(50)),
+                min_step_ticks: self.trailing.min_step_ticks.unwrap_or(2),
+                size: self.trailing.size.unwrap_or(order_size),
+            },
+            spread: SpreadSettings {
+                threshold_bps: self.spread.threshold_bps.unwrap_or(Decimal::from(5)),
+                max_quote_age_ms: self.spread.max_quote_age_ms.unwrap_or(2_000),
+                lighter_url: self
+                    .spread
+                    .lighter_url
+                    .unwrap_or_else(|| lighter::MAINNET_URL.to_string()),
+                markets: self.spread.markets,
+            },
+        };
+        validate(&settings)?;
+        Ok(settings)
+    }
+}
+
+fn validate(settings: &Settings) -> Result<(), ConfigError> {
+    let invalid = |message: &str| Err(ConfigError::InvalidSettings(message.to_string()));
+    if settings.symbols.is_empty() || settings.symbols.iter().any(|s| s.is_empty()) {
+        return invalid("symbols must be a non-empty list of market symbols");
+    }
+    if settings.order.size <= Decimal::ZERO {
+        return invalid("order.size must be positive");
+    }
+    if settings.order.price_offset_bps < Decimal::ZERO
+        || settings.order.price_offset_bps >= Decimal::from(10_000)
+    {
+        return invalid("order.price_offset_bps must be in [0, 10000)");
+    }
+    if let Some(recv_window) = settings.order.recv_window_ms {
+        validate_recv_window(recv_window)
+            .map_err(|e| ConfigError::InvalidSettings(format!("order.recv_window_ms: {}", e)))?;
+    }
+    if settings.risk.max_order_size <= Decimal::ZERO
+        || settings.risk.max_notional <= Decimal::ZERO
+        || settings
+            .risk
+            .max_position
+            .is_some_and(|max| max <= Decimal::ZERO)
+        || settings
+            .risk
+            .funding_alert_bps
+            .is_some_and(|bps| bps <= Decimal::ZERO)
+        || settings
+            .risk
+            .max_slippage_bps
+            .is_some_and(|bps| bps <= Decimal::ZERO)
+        || settings
+            .risk
+            .min_free_collateral
+            .is_some_and(|min| min <= Decimal::ZERO)
+    {
+        return invalid("risk limits must be positive");
+    }
+    if settings.order.size > settings.risk.max_order_size {
+        return invalid("order.size exceeds risk.max_order_size");
+    }
+    if settings.signals.depth == 0 {
+        return invalid("signals.depth must be positive");
+    }
+    if settings.positions.tolerance < Decimal::ZERO {
+        return invalid("positions.tolerance must not be negative");
+    }
+    if settings
+        .account
+        .max_drop_pct
+        .is_some_and(|pct| pct <= Decimal::ZERO || pct > Decimal::ONE_HUNDRED)
+    {
+        return invalid("account.max_drop_pct must be in (0, 100]");
+    }
+    if settings.account.drop_window_secs == 0 {
+        return invalid("account.drop_window_secs must be positive");
+    }
+    if settings
+        .order_book
+        .price_ticks
+        .iter()
+        .any(|tick| *tick <= Decimal::ZERO)
+    {
+        return invalid("order_book.price_ticks must be positive");
+    }
+    if settings.grid.levels == 0
+        || settings.grid.size <= Decimal::ZERO
+        || settings
+            .grid
+            .spacing
+            .is_some_and(|spacing| spacing <= Decimal::ZERO)
+        || settings
+            .grid
+            .center_price
+            .is_some_and(|price| price <= Decimal::ZERO)
+    {
+        return invalid("grid levels, size, spacing and center_price must be positive");
+    }
+    if settings.grid.size > settings.risk.max_order_size {
+        return invalid("grid.size exceeds risk.max_order_size");
+    }
+    if settings.mm.half_spread_bps <= Decimal::ZERO
+        || settings.mm.half_spread_bps >= Decimal::from(10_000)
+        || settings.mm.size <= Decimal::ZERO
+    {
+        return invalid("mm.half_spread_bps must be in (0, 10000) and mm.size must be positive");
+    }
+    if settings.mm.size > settings.risk.max_order_size {
+        return invalid("mm.size exceeds risk.max_order_size");
+    }
+    if settings.mm.min_requote_ms == 0 {
+        return invalid("mm.min_requote_ms must be positive");
+    }
+    if settings.mm.skew_bps_per_unit < Decimal::ZERO
+        || settings
+            .mm
+            .max_inventory
+            .is_some_and(|max| max <= Decimal::ZERO)
+    {
+        return invalid(
+            "mm.skew_bps_per_unit must not be negative and mm.max_inventory must be positive",
+        );
+    }
+    if settings.mm.symbols.is_empty() || settings.mm.symbols.iter().any(|s| s.is_empty()) {
+        return invalid("mm.symbols must be a non-empty list of market symbols");
+    }
+    let trailing = &settings.trailing;
+    if trailing.distance.is_some() && trailing.distance_bps.is_some() {
+        return invalid("trailing.distance and trailing.distance_bps are mutually exclusive");
+    }
+    if trailing.size <= Decimal::ZERO
+        || trailing.max_slippage_bps <= Decimal::ZERO
+        || trailing
+            .distance
+            .or(trailing.distance_bps)
+            .is_some_and(|distance| distance <= Decimal::ZERO)
+    {
+        return invalid("trailing size, distance and max_slippage_bps must be positive");
+    }
+    if trailing.size > settings.risk.max_order_size {
+        return invalid("trailing.size exceeds risk.max_order_size");
+    }
+    let spread = &settings.spread;
+    if spread.threshold_bps <= Decimal::ZERO || spread.max_quote_age_ms == 0 {
+        return invalid("spread.threshold_bps and spread.max_quote_age_ms must be positive");
+    }
+    if spread
+        .markets
+        .iter()
+        .any(|(paradex, lighter)| paradex.is_empty() || lighter.symbol.is_empty())
+    {
+        return invalid("spread.markets must map market symbols to Lighter symbols");
+    }
+    Ok(())
+}
+
+/// toml_edit 不支持 serde，先转换为 JSON 再反序列化
+fn item_to_json(item: &Item) -> Json {
+    match item {
+        Item::None => Json::Null,
+        Item::Value(value) => value_to_json(value),
+        Item::Table(table) => Json::Object(
+            table
+                .iter()
+                .map(|(key, item)| (key.to_string(), item_to_json(item)))
+                .collect::<Map<_, _>>(),
+        ),
+        Item::ArrayOfTables(tables) => Json::Array(
+            tables
+                .iter()
+                .map(|table| item_to_json(&Item::Table(table.clone())))
+                .collect(),
+        ),
+    }
+}
+
+fn value_to_json(value: &Value) -> Json {
+    match value {
+        Value::String(s) => Json::String(s.value().clone()),
+        Value::Integer(i) => Json::from(*i.value()),
+        // 小数按字符串传递，避免 Decimal 经 f64 损失精度
+        Value::Float(f) => Json::String(f.value().to_string()),
+        Value::Boolean(b) => Json::Bool(*b.value()),
+        Value::Datetime(d) => Json::String(d.value().to_string()),
+        Value::Array(array) => Json::Array(array.iter().map(value_to_json).collect()),
+        Value::InlineTable(table) => Json::Object(
+            table
+                .iter()
+                .map(|(key, value)| (key.to_string(), value_to_json(value)))
+                .collect::<Map<_, _>>(),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(contents: &str) -> Result<SettingsLayer, ConfigError> {
+        SettingsLayer::parse(Path::new(DEFAULT_CONFIG_FILE), contents)
+    }
+
+    const FILE: &str = r#"
+environment = "production"
+symbols = ["BTC-USD-PERP", "ETH-USD-PERP"]
+channels = ["bbo", "trades"]
+run_duration_secs = 30
+
+[order]
+size = 0.002
+price_offset_bps = 250
+instruction = "GTC"
+recv_window_ms = 3000
+stp = "expire_both"
+
+[risk]
+max_position = 0.02
+max_notional = 500
+funding_alert_bps = 5
+max_slippage_bps = 20
+min_free_collateral = 100
+
+[signals]
+depth = 10
+
+[positions]
+reconcile_interval_secs = 30
+
+[account]
+max_drop_pct = 10
+
+[order_book]
+refresh_rate = "100ms"
+price_ticks = [1, 0.5, 1]
+
+[bbo]
+ignore_size_changes = true
+
+[grid]
+spacing = 50
+levels = 3
+
+[mm]
+symbols = ["ETH-USD-PERP"]
+half_spread_bps = 5
+tolerance_ticks = 1
+skew_bps_per_unit = 200
+max_inventory = 0.05
+
+[trailing]
+side = "SELL"
+distance_bps = 150
+mode = "resting"
+min_step_ticks = 5
+
+[spread]
+threshold_bps = 8
+max_quote_age_ms = 500
+
+[spread.markets]
+"BTC-USD-PERP" = { symbol = "BTC", market_id = 1 }
+
+[watchdog]
+bbo = 5
+orderbook_deltas = 0
+orders = 120
+"#;
+
+    #[test]
+    fn missing_file_uses_defaults() {
+        let layer = SettingsLayer::load(Path::new("does-not-exist.toml"), false).unwrap();
+        let settings = layer.resolve().unwrap();
+        assert_eq!(settings.environment, Environment::Testnet);
+        assert_eq!(settings.symbols, vec![DEFAULT_SYMBOL.to_string()]);
+        assert_eq!(settings.trade_symbol, DEFAULT_SYMBOL);
+        assert_eq!(settings.order.size, Decimal::new(5, 3));
+        assert_eq!(settings.channels, ChannelSelection::all());
+        assert_eq!(settings.run_duration_secs, 120);
+        assert_eq!(settings.order.recv_window_ms, None);
+        assert_eq!(settings.order.stp, StpMode::ExpireMaker);
+        assert_eq!(settings.watchdog.get(&WsChannel::Bbo), Some(&10));
+        assert_eq!(settings.watchdog.get(&WsChannel::FundingData), Some(&600));
+        assert_eq!(settings.watchdog.get(&WsChannel::Orders), None);
+        assert_eq!(settings.order_book.refresh_rate, BookRefresh::Ms50);
+        assert!(settings.order_book.price_ticks.is_empty());
+        assert!(!settings.bbo.ignore_size_changes);
+        assert!(settings.spread.markets.is_empty());
+
+        assert!(matches!(
+            SettingsLayer::load(Path::new("does-not-exist.toml"), true),
+            Err(ConfigError::Io { .. })
+        ));
+    }
+
+    #[test]
+    fn cli_overrides_file_overrides_env() {
+        let env = SettingsLayer::from_env(|key| match key {
+            "TRADE_LIGHTER_SYMBOLS" => Some("SOL-USD-PERP".into()),
+            "TRADE_LIGHTER_ORDER_SIZE" => Some("0.001".into()),
+            "TRADE_LIGHTER_RUN_DURATION_SECS" => Some("60".into()),
+            _ => None,
+        })
+        .unwrap();
+        let file = parse(FILE).unwrap();
+        let cli = SettingsLayer {
+            trade_symbol: Some("ETH-USD-PERP".into()),
+            run_duration_secs: Some(10),
+            ..SettingsLayer::default()
+        };
+
+        let settings = env.merge(file).merge(cli).resolve().unwrap();
+        assert_eq!(settings.environment, Environment::Production);
+        // 文件覆盖环境变量
+        assert_eq!(settings.symbols, vec!["BTC-USD-PERP", "ETH-USD-PERP"]);
+        assert_eq!(settings.order.size, Decimal::new(2, 3));
+        assert_eq!(settings.order.price_offset_bps, Decimal::from(250));
+        assert_eq!(settings.order.instruction, OrderInstruction::GTC);
+        assert_eq!(settings.order.recv_window_ms, Some(3000));
+        assert_eq!(settings.order.stp, StpMode::ExpireBoth);
+        // 命令行覆盖文件
+        assert_eq!(settings.trade_symbol, "ETH-USD-PERP");
+        assert_eq!(settings.run_duration_secs, 10);
+        assert_eq!(
+            settings.channels,
+            ChannelSelection::from(vec![WsChannel::Bbo, WsChannel::Trades])
+        );
+        assert_eq!(settings.risk.max_notional, Decimal::from(500));
+        assert_eq!(settings.risk.max_position, Some(Decimal::new(2, 2)));
+        assert_eq!(settings.risk.funding_alert_bps, Some(Decimal::from(5)));
+        assert_eq!(settings.risk.max_slippage_bps, Some(Decimal::from(20)));
+        assert_eq!(settings.risk.min_free_collateral, Some(Decimal::from(100)));
+        assert_eq!(
+            settings.signals,
+            SignalSettings {
+                depth: 10,
+                half_life_ms: 1_000
+            }
+        );
+        assert_eq!(
+            settings.positions,
+            PositionSettings {
+                reconcile_interval_secs: 30,
+                tolerance: Decimal::new(1, 8),
+            }
+        );
+        assert_eq!(
+            settings.account,
+            AccountSettings {
+                max_drop_pct: Some(Decimal::from(10)),
+                drop_window_secs: 300,
+            }
+        );
+        assert_eq!(
+            settings.order_book,
+            BookSettings {
+                refresh_rate: BookRefresh::Ms100,
+                price_ticks: vec![Decimal::new(5, 1), Decimal::ONE],
+            }
+        );
+        assert!(settings.bbo.ignore_size_changes);
+        // 每档数量未设置时沿用 order.size
+        assert_eq!(
+            settings.grid,
+            GridSettings {
+                center_price: None,
+                spacing: Some(Decimal::from(50)),
+                levels: 3,
+                size: Decimal::new(2, 3),
+            }
+        );
+        assert_eq!(
+            settings.mm,
+            MakerSettings {
+                symbols: vec!["ETH-USD-PERP".to_string()],
+                half_spread_bps: Decimal::from(5),
+                size: Decimal::new(2, 3),
+                tolerance_ticks: 1,
+                min_requote_ms: 500,
+                skew_bps_per_unit: Decimal::from(200),
+                max_inventory: Some(Decimal::new(5, 2)),
+            }
+        );
+        assert_eq!(
+            settings.trailing,
+            TrailingSettings {
+                side: Some(Side::SELL),
+                distance: None,
+                distance_bps: Some(Decimal::from(150)),
+                mode: TrailMode::Resting,
+                close: AggressiveMode::Limit,
+                max_slippage_bps: Decimal::from(50),
+                min_step_ticks: 5,
+                size: Decimal::new(2, 3),
+            }
+        );
+        assert_eq!(
+            settings.spread,
+            SpreadSettings {
+                threshold_bps: Decimal::from(8),
+                max_quote_age_ms: 500,
+                lighter_url: lighter::MAINNET_URL.to_string(),
+                markets: BTreeMap::from([(
+                    "BTC-USD-PERP".to_string(),
+                    LighterMarket {
+                        symbol: "BTC".to_string(),
+                        market_id: 1,
+                    },
+                )]),
+            }
+        );
+        // 停滞阈值按频道覆盖默认值，0 关闭检查
+        assert_eq!(settings.watchdog.get(&WsChannel::Bbo), Some(&5));
+        assert_eq!(settings.watchdog.get(&WsChannel::OrderBookDeltas), None);
+        assert_eq!(settings.watchdog.get(&WsChannel::Orders), Some(&120));
+        assert_eq!(settings.watchdog.get(&WsChannel::Trades), Some(&300));
+
+        // 文件未设置的字段沿用环境变量
+        let settings = SettingsLayer::from_env(|key| {
+            (key == "TRADE_LIGHTER_ORDER_SIZE").then(|| "0.001".to_string())
+        })
+        .unwrap()
+        .merge(parse("run_duration_secs = 5").unwrap())
+        .resolve()
+        .unwrap();
+        assert_eq!(settings.order.size, Decimal::new(1, 3));
+
+        // 0 表示运行到中断
+        let settings = parse("run_duration_secs = 0").unwrap().resolve().unwrap();
+        assert_eq!(settings.run_duration_secs, 0);
+    }
+
+    #[test]
+    fn validation_failures() {
+        for contents in [
+            "[order]\nsize = -0.01",
+            "[order]\nsize = 0",
+            "symbols = []",
+            "[order]\nprice_offset_bps = 10000",
+            "[order]\nsize = 1\n[risk]\nmax_order_size = 0.5",
+            "[order]\nrecv_window_ms = 5",
+            "[risk]\nmax_position = 0",
+            "[risk]\nfunding_alert_bps = -1",
+            "[risk]\nmax_slippage_bps = 0",
+            "[signals]\ndepth = 0",
+            "[positions]\ntolerance = -0.1",
+            "[risk]\nmin_free_collateral = 0",
+            "[account]\nmax_drop_pct = 150",
+            "[account]\ndrop_window_secs = 0",
+            "[order_book]\nprice_ticks = [0]",
+            "[order]\nrecv_window_ms = 120000",
+            "[grid]\nlevels = 0",
+            "[grid]\nspacing = -1",
+            "[grid]\nsize = 0.5",
+            "[mm]\nhalf_spread_bps = 0",
+            "[mm]\nsize = 0.5",
+            "[mm]\nsymbols = []",
+            "[mm]\nmin_requote_ms = 0",
+            "[mm]\nskew_bps_per_unit = -1",
+            "[mm]\nmax_inventory = 0",
+            "[trailing]\ndistance = 0",
+            "[trailing]\ndistance = 5\ndistance_bps = 50",
+            "[trailing]\nmax_slippage_bps = 0",
+            "[trailing]\nsize = 0.5",
+            "[spread]\nthreshold_bps = 0",
+            "[spread]\nmax_quote_age_ms = 0",
+            "[spread.markets]\n\"BTC-USD-PERP\" = { symbol = \"\", market_id = 1 }",
+        ] {
+            assert!(
+                matches!(
+                    parse(contents).unwrap().resolve(),
+                    Err(ConfigError::InvalidSettings(_))
+                ),
+                "{contents}"
+            );
+        }
+        // 未知字段与类型错误在解析阶段报错
+        assert!(matches!(parse("sizes = 1"), Err(ConfigError::Parse { .. })));
+        assert!(matches!(
+            parse("channels = [\"tickers\"]"),
+            Err(ConfigError::Parse { .. })
+        ));
+        assert!(matches!(
+            parse("[order]\nstp = \"expire_all\""),
+            Err(ConfigError::Parse { .. })
+        ));
+        assert!(matches!(
+            parse("[trailing]\nmode = \"trailing\""),
+            Err(ConfigError::Parse { .. })
+        ));
+        assert!(matches!(
+            parse("[watchdog]\ntickers = 10"),
+            Err(ConfigError::Parse { .. })
+        ));
+        // 推送间隔只接受交易所支持的取值
+        assert!(matches!(
+            parse("[order_book]\nrefresh_rate = \"10ms\""),
+            Err(ConfigError::Parse { .. })
+        ));
+    }
+
+    #[test]
+    fn risk_limits_reject_large_orders() {
+        let limits = RiskLimits {
+            max_order_size: Decimal::new(1, 2),
+            max_position: None,
+            max_notional: Decimal::from(1000),
+            funding_alert_bps: None,
+            max_slippage_bps: None,
+            min_free_collateral: None,
+        };
+        assert!(limits
+            .check(Decimal::from(90_000), Decimal::new(5, 3))
+            .is_ok());
+        assert!(limits
+            .check(Decimal::from(90_000), Decimal::new(2, 2))
+            .is_err());
+        assert!(limits
+            .check(Decimal::from(300_000), Decimal::new(5, 3))
+            .is_err());
+    }
+
+    #[test]
+    fn channel_selection_parses_cli_names() {
+        let selection: ChannelSelection = "bbo, orderbook,market_summary,balance".parse().unwrap();
+        assert!(selection.contains(WsChannel::Bbo));
+        assert!(selection.contains(WsChannel::OrderBook));
+        assert!(selection.contains(WsChannel::MarketsSummary));
+        assert!(!selection.contains(WsChannel::Trades));
+        assert_eq!(selection.private(), [WsChannel::BalanceEvents]);
+
+        // 配置文件中的完整名称同样可用
+        let selection: ChannelSelection = "order_book_deltas,positions".parse().unwrap();
+        assert!(selection.contains(WsChannel::OrderBookDeltas));
+        assert!(selection.contains(WsChannel::Positions));
+
+        let all = WsChannel::ALL.map(WsChannel::cli_name).join(",");
+        assert_eq!(
+            all.parse::<ChannelSelection>().unwrap(),
+            ChannelSelection::all()
+        );
+
+        assert!("".parse::<ChannelSelection>().is_err());
+        let error = "bbo,tickers".parse::<ChannelSelection>().unwrap_err();
+        assert!(error.contains("\"tickers\""), "{error}");
+        assert!(error.contains("funding_payments"), "{error}");
+    }
+}
@@ -0,0 +1,322 @@
+//! 启动时读取并校验 `.env` 中的账户变量，一次列出全部问题
+
+use num_bigint::BigUint;
+use sha3::{Digest, Keccak256};
+use thiserror::Error;
+
+use crate::secrets::{SecretKey, PRIVATE_KEY_ENV};
+
+/// 以太坊账户地址（用于 onboarding）
+pub const ETH_ACCOUNT_ENV: &str = "eth_account_address";
+/// Paradex StarkNet 账户地址
+pub const PARADEX_ACCOUNT_ENV: &str = "paradex_account_address";
+
+/// StarkNet 域的模数 P = 2^251 + 17·2^192 + 1
+const STARK_PRIME: &str = "800000000000011000000000000000000000000000000000000000000000001";
+
+/// 单个账户变量的问题
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum CredentialProblem {
+    #[error("{0} is not set")]
+    Missing(&'static str),
+    #[error("{var} is not a hex value: {reason}")]
+    InvalidHex { var: &'static str, reason: String },
+    #[error("{0} is outside the StarkNet field (must be below 2^251 + 17*2^192 + 1)")]
+    OutOfRange(&'static str),
+    #[error("{0} must not be zero")]
+    Zero(&'static str),
+    #[error("{var} is not an Ethereum address: {reason}")]
+    InvalidEthAddress { var: &'static str, reason: String },
+    #[error(
+        "{0} has an invalid EIP-55 checksum (use all-lowercase or the correctly checksummed form)"
+    )]
+    BadChecksum(&'static str),
+}
+
+/// 账户变量校验失败，包含全部问题
+#[derive(Debug, Error)]
+#[error("account environment variables need attention (see ENV_SETUP.md):{}", list(.0))]
+pub struct CredentialsError(pub Vec<CredentialProblem>);
+
+fn list(problems: &[CredentialProblem]) -> String {
+    problems
+        .iter()
+        .map(|problem| format!("\n  - {}", problem))
+        .collect()
+}
+
+/// 从环境变量读取的账户信息；未设置的变量为 `None`
+#[derive(Debug, Default)]
+pub struct Credentials {
+    pub private_key: Option<SecretKey>,
+    pub eth_account: Option<String>,
+    pub starknet_account: Option<String>,
+}
+
+impl Credentials {
+    /// 检查 `required` 中的变量均已设置，否则列出全部缺失项
+    pub fn require(&self, required: &[&'static str]) -> Result<(), CredentialsError> {
+        let missing: Vec<_> = required
+            .iter()
+            .filter(|var| match **var {
+                PRIVATE_KEY_ENV => self.private_key.is_none(),
+                ETH_ACCOUNT_ENV => self.eth_account.is_none(),
+                PARADEX_ACCOUNT_ENV => self.starknet_account.is_none(),
+                _ => false,
+            })
+            .map(|var| CredentialProblem::Missing(var))
+            .collect();
+        if missing.is_empty() {
+            Ok(())
+        } else {
+            Err(CredentialsError(missing))
+        }
+    }
+}
+
+/// 读取并校验 Stark 私钥与两个账户地址
+pub fn load_credentials() -> Result<Credentials, CredentialsError> {
+    load_credentials_from(|var| std::env::var(var).ok())
+}
+
+/// 同 [`load_credentials`]，从 `lookup` 读取变量（空值视为未设置）
+pub fn load_credentials_from(
+    lookup: impl Fn(&str) -> Option<String>,
+) -> Result<Credentials, CredentialsError> {
+    let read = |var: &str| lookup(var).filter(|value| !value.trim().is_empty());
+    let mut problems = Vec::new();
+
+    let private_key = read(PRIVATE_KEY_ENV);
+    if let Some(ref key) = private_key {
+        problems.extend(check_felt(PRIVATE_KEY_ENV, key).err());
+    }
+    let starknet_account = read(PARADEX_ACCOUNT_ENV);
+    if let Some(ref address) = starknet_account {
+        problems.extend(check_felt(PARADEX_ACCOUNT_ENV, address).err());
+    }
+    let eth_account = read(ETH_ACCOUNT_ENV);
+    if let Some(ref address) = eth_account {
+        problems.extend(check_eth_address(ETH_ACCOUNT_ENV, address).err());
+    }
+
+    if problems.is_empty() {
+        Ok(Credentials {
+            private_key: private_key.map(SecretKey::new),
+            eth_account,
+            starknet_account,
+        })
+    } else {
+        Err(CredentialsError(problems))
+    }
+}
+
+/// 非零、且小于 StarkNet 域模数的十六进制值（错误信息不包含变量值）
+fn check_felt(var: &'static str, value: &str) -> Result<(), CredentialProblem> {
+    let digits = strip_hex_prefix(value.trim());
+    if digits.is_empty() {
+        return Err(CredentialProblem::InvalidHex {
+            var,
+            reason: "no digits after 0x".to_string(),
+        });
+    }
+    if let Some(c) = digits.chars().find(|c| !c.is_ascii_hexdigit()) {
+        return Err(CredentialProblem::InvalidHex {
+            var,
+            reason: format!("contains non-hex character {:?}", c),
+        });
+    }
+    let value = BigUint::parse_bytes(digits.as_bytes(), 16).expect("validated hex digits");
+    if value >= BigUint::parse_bytes(STARK_PRIME.as_bytes(), 16).unwrap() {
+        return Err(CredentialProblem::OutOfRange(var));
+    }
+    if value == BigUint::ZERO {
+        return Err(CredentialProblem::Zero(var));
+    }
+    Ok(())
+}
+
+/// `0x` 加 40 位十六进制；大小写混合时按 EIP-55 校验
+fn check_eth_address(var: &'static str, value: &str) -> Result<(), CredentialProblem> {
+    let invalid = |reason: &str| CredentialProblem::InvalidEthAddress {
+        var,
+        reason: reason.to_string(),
+    };
+    let value = value.trim();
+    let Some(digits) = value.strip_prefix("0x") else {
+        return Err(invalid("missing 0x prefix"));
+    };
+    if digits.len() != 40 {
+        return Err(invalid(&format!(
+            "expected 40 hex digits, got {}",
+            digits.len()
+        )));
+    }
+    if !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(invalid("contains non-hex characters"));
+    }
+
+    let has_lower = digits.chars().any(|c| c.is_ascii_lowercase());
+    let has_upper = digits.chars().any(|c| c.is_ascii_uppercase());
+    if has_lower && has_upper && checksum_address(digits) != digits {
+        return Err(CredentialProblem::BadChecksum(var));
+    }
+    Ok(())
+}
+
+/// EIP-55：小写地址的 keccak256 中对应半字节 >= 8 的字母大写
+fn checksum_address(digits: &str) -> String {
+    let lower = digits.to_ascii_lowercase();
+    let hash = Keccak256::digest(lower.as_bytes());
+    lower
+        .chars()
+        .enumerate()
+        .map(|(i, c)| {
+            let nibble = (hash[i / 2] >> (if i % 2 == 0 { 4 } else { 0 })) & 0x0f;
+            if nibble >= 8 {
+                c.to_ascii_uppercase()
+            } else {
+                c
+            }
+        })
+        .collect()
+}
+
+fn strip_hex_prefix(value: &str) -> &str {
+    value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+        .unwrap_or(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: &str = "0x4c8b5bcb3a3a4e7a6fa3d3b1d7e5c6b6a2e3f9d8c7b6a5e4d3c2b1a09f8e7d6";
+    const ACCOUNT: &str = "0x129f3dc1b8962d8bb23f7ddc9a0f1c0fcc9fba43a8bc88bc5c1de5b2b6dd2b8";
+    const ETH: &str = "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed";
+
+    fn load(vars: &[(&str, &str)]) -> Result<Credentials, CredentialsError> {
+        load_credentials_from(|var| {
+            vars.iter()
+                .find(|(name, _)| *name == var)
+                .map(|(_, value)| value.to_string())
+        })
+    }
+
+    fn problems(vars: &[(&str, &str)]) -> Vec<CredentialProblem> {
+        load(vars).unwrap_err().0
+    }
+
+    #[test]
+    fn valid_credentials_load() {
+        let credentials = load(&[
+            (PRIVATE_KEY_ENV, KEY),
+            (PARADEX_ACCOUNT_ENV, ACCOUNT),
+            (ETH_ACCOUNT_ENV, ETH),
+        ])
+        .unwrap();
+        assert_eq!(credentials.private_key.unwrap().expose(), KEY);
+        assert_eq!(credentials.starknet_account.as_deref(), Some(ACCOUNT));
+        assert_eq!(credentials.eth_account.as_deref(), Some(ETH));
+
+        // 全小写地址不做校验和检查
+        assert!(load(&[(ETH_ACCOUNT_ENV, &ETH.to_ascii_lowercase())]).is_ok());
+    }
+
+    #[test]
+    fn missing_variables_are_listed_together() {
+        let credentials = load(&[(PARADEX_ACCOUNT_ENV, ACCOUNT), (PRIVATE_KEY_ENV, "  ")]).unwrap();
+        let error = credentials
+            .require(&[PRIVATE_KEY_ENV, PARADEX_ACCOUNT_ENV, ETH_ACCOUNT_ENV])
+            .unwrap_err();
+        assert_eq!(
+            error.0,
+            [
+                CredentialProblem::Missing(PRIVATE_KEY_ENV),
+                CredentialProblem::Missing(ETH_ACCOUNT_ENV)
+            ]
+        );
+        assert!(error
+            .to_string()
+            .contains("\n  - paradex_account_private_key_hex is not set"));
+    }
+
+    #[test]
+    fn malformed_hex_is_rejected() {
+        assert!(matches!(
+            problems(&[(PRIVATE_KEY_ENV, "0xZZ12")]).as_slice(),
+            [CredentialProblem::InvalidHex { var: PRIVATE_KEY_ENV, reason }] if reason.contains("'Z'")
+        ));
+        assert!(matches!(
+            problems(&[(PARADEX_ACCOUNT_ENV, "0x")]).as_slice(),
+            [CredentialProblem::InvalidHex {
+                var: PARADEX_ACCOUNT_ENV,
+                ..
+            }]
+        ));
+    }
+
+    #[test]
+    fn felt_range_is_enforced() {
+        let prime = format!("0x{}", STARK_PRIME);
+        assert_eq!(
+            problems(&[(PARADEX_ACCOUNT_ENV, &prime)]),
+            [CredentialProblem::OutOfRange(PARADEX_ACCOUNT_ENV)]
+        );
+        assert_eq!(
+            problems(&[(PRIVATE_KEY_ENV, &format!("0x{}", "f".repeat(64)))]),
+            [CredentialProblem::OutOfRange(PRIVATE_KEY_ENV)]
+        );
+        assert_eq!(
+            problems(&[(PRIVATE_KEY_ENV, "0x000")]),
+            [CredentialProblem::Zero(PRIVATE_KEY_ENV)]
+        );
+    }
+
+    #[test]
+    fn eth_address_format_and_checksum() {
+        for (value, reason) in [
+            (
+                "5aaeb6053f3e94c9b9a09f33669435e7ef1beaed",
+                "missing 0x prefix",
+            ),
+            (
+                "0x5aaeb6053f3e94c9b9a09f33669435e7ef1bea",
+                "expected 40 hex digits, got 38",
+            ),
+            (
+                "0x5aaeb6053f3e94c9b9a09f33669435e7ef1beagg",
+                "contains non-hex characters",
+            ),
+        ] {
+            assert_eq!(
+                problems(&[(ETH_ACCOUNT_ENV, value)]),
+                [CredentialProblem::InvalidEthAddress {
+                    var: ETH_ACCOUNT_ENV,
+                    reason: reason.to_string()
+                }]
+            );
+        }
+        assert_eq!(
+            problems(&[(
+                ETH_ACCOUNT_ENV,
+                "0x5aaeb6053F3E94C9b9A09f33669435E7Ef1BeAed"
+            )]),
+            [CredentialProblem::BadChecksum(ETH_ACCOUNT_ENV)]
+        );
+    }
+
+    #[test]
+    fn all_problems_are_reported_at_once() {
+        let problems = problems(&[
+            (PRIVATE_KEY_ENV, "not-hex"),
+            (PARADEX_ACCOUNT_ENV, "0x0"),
+            (ETH_ACCOUNT_ENV, "0x1234"),
+        ]);
+        assert_eq!(problems.len(), 3);
+        // 错误信息不泄露私钥内容
+        let message = CredentialsError(problems).to_string();
+        assert!(!message.contains("not-hex"), "{message}");
+    }
+}
@@ -0,0 +1,186 @@
+//! 成交历史（`GET /fills`）的分页拉取与 CSV / JSON 导出
+
+use paradex::structs::{Fill, FillLiquidity};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::io::{self, Write};
+
+use crate::history::{fetch_pages, format_millis, history_path, TimeWindow};
+use crate::http::{AuthedHttpClient, HttpError};
+use crate::markets::decimal;
+
+/// CSV 表头，与 [`write_csv`] 输出的列一一对应
+pub const CSV_HEADER: [&str; 10] = [
+    "timestamp",
+    "market",
+    "side",
+    "price",
+    "size",
+    "fee",
+    "fee_currency",
+    "liquidity",
+    "order_id",
+    "client_id",
+];
+
+/// 单笔成交；数值字段按接口返回的十进制字符串精确解析
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FillRecord {
+    pub id: String,
+    /// 成交时间（毫秒时间戳）
+    pub created_at: u64,
+    pub market: String,
+    pub side: String,
+    pub price: Decimal,
+    pub size: Decimal,
+    pub fee: Decimal,
+    pub fee_currency: String,
+    /// MAKER / TAKER
+    pub liquidity: String,
+    pub order_id: String,
+    #[serde(default)]
+    pub client_id: String,
+}
+
+impl From<&Fill> for FillRecord {
+    /// 成交频道推送的成交；数值由推送中的十进制字符串经 `f64` 转回，保留原始的有效数字
+    fn from(fill: &Fill) -> Self {
+        Self {
+            id: fill.id.clone(),
+            created_at: fill.created_at,
+            market: fill.market.clone(),
+            side: format!("{:?}", fill.side),
+            price: decimal(fill.price),
+            size: decimal(fill.size),
+            fee: decimal(fill.fee),
+            fee_currency: fill.fee_currency.clone(),
+            liquidity: match fill.liquidity {
+                FillLiquidity::MAKER => "MAKER",
+                FillLiquidity::TAKER => "TAKER",
+            }
+            .to_string(),
+            order_id: fill.order_id.clone(),
+            client_id: fill.client_id.clone(),
+        }
+    }
+}
+
+/// 导出格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum FillFormat {
+    Csv,
+    Json,
+}
+
+/// 拉取 `market`（`None` 为全部市场）在时间范围内的全部成交
+pub async fn fetch_fills(
+    client: &AuthedHttpClient,
+    market: Option<&str>,
+    window: &TimeWindow,
+) -> Result<Vec<FillRecord>, HttpError> {
+    let label = match market {
+        Some(market) => format!("fills for {}", market),
+        None => "fills".to_string(),
+    };
+    fetch_pages(&label, |cursor| async move {
+        client
+            .get_json(&history_path("/fills", market, window, cursor.as_deref()))
+            .await
+    })
+    .await
+}
+
+/// 按 [`CSV_HEADER`] 输出 CSV（含表头）
+pub fn write_csv<W: Write>(fills: &[FillRecord], mut writer: W) -> io::Result<()> {
+    writeln!(writer, "{}", CSV_HEADER.join(","))?;
+    for fill in fills {
+        let row = [
+            format_millis(fill.created_at),
+            fill.market.clone(),
+            fill.side.clone(),
+            fill.price.to_string(),
+            fill.size.to_string(),
+            fill.fee.to_string(),
+            fill.fee_currency.clone(),
+            fill.liquidity.clone(),
+            fill.order_id.clone(),
+            fill.client_id.clone(),
+        ];
+        let row: Vec<_> = row.iter().map(|field| csv_field(field)).collect();
+        writeln!(writer, "{}", row.join(","))?;
+    }
+    writer.flush()
+}
+
+/// 输出 JSON 数组；数值保持为十进制字符串
+pub fn write_json<W: Write>(fills: &[FillRecord], mut writer: W) -> io::Result<()> {
+    serde_json::to_writer_pretty(&mut writer, fills)?;
+    writeln!(writer)?;
+    writer.flush()
+}
+
+/// 含逗号、引号或换行的字段加引号，内部引号加倍
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::CursorPage;
+
+    fn fills() -> Vec<FillRecord> {
+        let page: CursorPage<FillRecord> = serde_json::from_str(
+            r#"{
+                "next": null,
+                "prev": null,
+                "results": [{
+                    "id": "1",
+                    "client_id": "tlp-1,retry",
+                    "created_at": 1735689600123,
+                    "fee": "0.01234567890123",
+                    "fee_currency": "USDC",
+                    "liquidity": "MAKER",
+                    "market": "BTC-USD-PERP",
+                    "order_id": "1681462103821101699438490000",
+                    "price": "95000.1",
+                    "side": "BUY",
+                    "size": "0.0001",
+                    "remaining_size": "0",
+                    "fill_type": "FILL"
+                }]
+            }"#,
+        )
+        .unwrap();
+        page.results
+    }
+
+    #[test]
+    fn fills_keep_exact_decimals() {
+        let fills = fills();
+        assert_eq!(fills[0].fee, "0.01234567890123".parse::<Decimal>().unwrap());
+
+        let mut out = Vec::new();
+        write_json(&fills, &mut out).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&out).unwrap();
+        assert_eq!(json[0]["fee"], "0.01234567890123");
+        assert_eq!(json[0]["price"], "95000.1");
+    }
+
+    #[test]
+    fn csv_has_header_and_escapes_fields() {
+        let mut out = Vec::new();
+        write_csv(&fills(), &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "timestamp,market,side,price,size,fee,fee_currency,liquidity,order_id,client_id\n\
+             2025-01-01T00:00:00.123Z,BTC-USD-PERP,BUY,95000.1,0.0001,0.01234567890123,USDC,MAKER,\
+             1681462103821101699438490000,\"tlp-1,retry\"\n"
+        );
+    }
+}
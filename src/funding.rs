@@ -0,0 +1,248 @@
+//! 资金费率历史（`GET /funding/data`）与账户资金费支付（`GET /funding/payments`）；
+//! [`FundingTracker`] 跟踪实时资金费率并估算持仓资金费
+
+mod tracker;
+
+use reqwest::Client as HttpClient;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::history::{fetch_pages, format_millis, history_path, TimeWindow};
+use crate::http::{get_public_json, AuthedHttpClient, HttpError};
+use crate::markets::decimal;
+
+pub use tracker::{
+    funding_cost_bps, FundingProjection, FundingState, FundingTracker, FUNDING_PERIOD,
+};
+
+/// 一条资金费率记录
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FundingRate {
+    pub market: String,
+    /// 毫秒时间戳
+    pub created_at: u64,
+    pub funding_rate: Decimal,
+    pub funding_premium: Decimal,
+    pub funding_index: Decimal,
+}
+
+/// 一笔资金费支付；`payment` 为负表示支出
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FundingPayment {
+    pub id: String,
+    pub market: String,
+    /// 毫秒时间戳
+    pub created_at: u64,
+    pub payment: Decimal,
+    pub index: Decimal,
+    #[serde(default)]
+    pub fill_id: String,
+}
+
+impl From<&paradex::structs::FundingPayment> for FundingPayment {
+    /// 资金费支付频道推送或 SDK 分页查询返回的支付
+    fn from(payment: &paradex::structs::FundingPayment) -> Self {
+        Self {
+            id: payment.id.clone(),
+            market: payment.market.clone(),
+            created_at: payment.created_at,
+            payment: decimal(payment.payment),
+            index: decimal(payment.index),
+            fill_id: payment.fill_id.clone(),
+        }
+    }
+}
+
+/// `funding payments --json` 输出：明细加各市场合计
+#[derive(Debug, Serialize)]
+pub struct PaymentSummary<'a> {
+    pub payments: &'a [FundingPayment],
+    pub totals: BTreeMap<String, Decimal>,
+}
+
+impl<'a> From<&'a [FundingPayment]> for PaymentSummary<'a> {
+    fn from(payments: &'a [FundingPayment]) -> Self {
+        Self {
+            payments,
+            totals: payment_totals(payments),
+        }
+    }
+}
+
+/// 查询 `market` 在时间范围内的资金费率（无需认证）
+pub async fn fetch_funding_rates(
+    http_client: &HttpClient,
+    base_url: &str,
+    market: &str,
+    window: &TimeWindow,
+) -> Result<Vec<FundingRate>, HttpError> {
+    fetch_pages(&format!("funding rates for {}", market), |cursor| {
+        let path = history_path("/funding/data", Some(market), window, cursor.as_deref());
+        async move { get_public_json(http_client, base_url, &path).await }
+    })
+    .await
+}
+
+/// 查询账户在时间范围内的资金费支付；`market` 为 `None` 时查询全部市场
+pub async fn fetch_funding_payments(
+    client: &AuthedHttpClient,
+    market: Option<&str>,
+    window: &TimeWindow,
+) -> Result<Vec<FundingPayment>, HttpError> {
+    let label = match market {
+        Some(market) => format!("funding payments for {}", market),
+        None => "funding payments".to_string(),
+    };
+    fetch_pages(&label, |cursor| {
+        let path = history_path("/funding/payments", market, window, cursor.as_deref());
+        async move { client.get_json(&path).await }
+    })
+    .await
+}
+
+/// 按市场汇总资金费支付（正为收入、负为支出）
+pub fn payment_totals(payments: &[FundingPayment]) -> BTreeMap<String, Decimal> {
+    let mut totals = BTreeMap::new();
+    for payment in payments {
+        *totals
+            .entry(payment.market.clone())
+            .or_insert(Decimal::ZERO) += payment.payment;
+    }
+    totals
+}
+
+/// 渲染资金费率表格
+pub fn format_rates(rates: &[FundingRate]) -> String {
+    let rows: Vec<[String; 4]> = rates
+        .iter()
+        .map(|rate| {
+            [
+                format_millis(rate.created_at),
+                rate.market.clone(),
+                rate.funding_rate.to_string(),
+                rate.funding_premium.to_string(),
+            ]
+        })
+        .collect();
+    render(["TIME", "MARKET", "RATE", "PREMIUM"], &rows)
+}
+
+/// 渲染资金费支付表格，末尾附各市场合计
+pub fn format_payments(payments: &[FundingPayment]) -> String {
+    let mut rows: Vec<[String; 3]> = payments
+        .iter()
+        .map(|payment| {
+            [
+                format_millis(payment.created_at),
+                payment.market.clone(),
+                payment.payment.to_string(),
+            ]
+        })
+        .collect();
+    rows.extend(
+        payment_totals(payments)
+            .into_iter()
+            .map(|(market, total)| ["TOTAL".to_string(), market, total.to_string()]),
+    );
+    render(["TIME", "MARKET", "PAYMENT"], &rows)
+}
+
+/// 左对齐文本列、右对齐数值列（最后 `N - 2` 列）
+fn render<const N: usize>(header: [&str; N], rows: &[[String; N]]) -> String {
+    let mut widths = header.map(str::len);
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.len());
+        }
+    }
+    let line = |cells: [&str; N]| {
+        cells
+            .iter()
+            .zip(widths)
+            .enumerate()
+            .map(|(i, (cell, width))| {
+                if i < 2 {
+                    format!("{:<width$}", cell, width = width)
+                } else {
+                    format!("{:>width$}", cell, width = width)
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("  ")
+            .trim_end()
+            .to_string()
+    };
+    std::iter::once(line(header))
+        .chain(
+            rows.iter()
+                .map(|row| line(row.each_ref().map(String::as_str))),
+        )
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::CursorPage;
+
+    fn payments() -> Vec<FundingPayment> {
+        let page: CursorPage<FundingPayment> = serde_json::from_str(
+            r#"{
+                "next": null,
+                "results": [
+                    {"id": "1", "market": "BTC-USD-PERP", "created_at": 1735689600000,
+                     "payment": "-0.10000000001", "index": "12.5", "fill_id": "f1"},
+                    {"id": "2", "market": "ETH-USD-PERP", "created_at": 1735693200000,
+                     "payment": "0.25", "index": "3.1", "fill_id": "f2"},
+                    {"id": "3", "market": "BTC-USD-PERP", "created_at": 1735696800000,
+                     "payment": "-0.2", "index": "12.6", "fill_id": "f3"}
+                ]
+            }"#,
+        )
+        .unwrap();
+        page.results
+    }
+
+    #[test]
+    fn payments_are_summed_per_market_exactly() {
+        let totals = payment_totals(&payments());
+        assert_eq!(
+            totals.get("BTC-USD-PERP"),
+            Some(&"-0.30000000001".parse().unwrap())
+        );
+        assert_eq!(totals.get("ETH-USD-PERP"), Some(&Decimal::new(25, 2)));
+
+        let json = serde_json::to_value(PaymentSummary::from(payments().as_slice())).unwrap();
+        assert_eq!(json["totals"]["BTC-USD-PERP"], "-0.30000000001");
+        assert_eq!(json["payments"][1]["payment"], "0.25");
+    }
+
+    #[test]
+    fn payments_table_ends_with_totals() {
+        assert_eq!(
+            format_payments(&payments()),
+            "TIME                      MARKET               PAYMENT\n\
+             2025-01-01T00:00:00.000Z  BTC-USD-PERP  -0.10000000001\n\
+             2025-01-01T01:00:00.000Z  ETH-USD-PERP            0.25\n\
+             2025-01-01T02:00:00.000Z  BTC-USD-PERP            -0.2\n\
+             TOTAL                     BTC-USD-PERP  -0.30000000001\n\
+             TOTAL                     ETH-USD-PERP            0.25"
+        );
+    }
+
+    #[test]
+    fn rates_table_lists_each_record() {
+        let rates: Vec<FundingRate> = serde_json::from_str(
+            r#"[{"market": "BTC-USD-PERP", "created_at": 1735689600000,
+                 "funding_rate": "0.0000125", "funding_premium": "1.2", "funding_index": "12.5"}]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            format_rates(&rates),
+            "TIME                      MARKET             RATE  PREMIUM\n\
+             2025-01-01T00:00:00.000Z  BTC-USD-PERP  0.0000125      1.2"
+        );
+    }
+}
@@ -0,0 +1,315 @@
+//! 资金费实时跟踪：按市场保存最新资金费率与溢价，估算持仓在下一周期的资金费，
+//! 收到实际的资金费支付时与估算对账
+
+use log::{info, warn};
+use paradex::ws::Message;
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::markets::decimal;
+use crate::metrics::metrics;
+
+/// 资金费率对应的周期
+pub const FUNDING_PERIOD: Duration = Duration::from_secs(8 * 3_600);
+
+/// 某个市场最新的资金费数据
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FundingState {
+    pub market: String,
+    /// 每个 [`FUNDING_PERIOD`] 的资金费率
+    pub funding_rate: Decimal,
+    pub funding_premium: Decimal,
+    pub funding_index: Decimal,
+    /// 交易所时间（Unix 毫秒）
+    pub updated_at: u64,
+}
+
+/// 持仓在一段时间内的资金费估算
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FundingProjection {
+    pub market: String,
+    /// 多头为正、空头为负
+    pub position: Decimal,
+    pub mark_price: Decimal,
+    pub funding_rate: Decimal,
+    pub horizon: Duration,
+    /// 估算的资金费，正为收入、负为支出（与资金费支付的符号一致）
+    pub payment: Decimal,
+}
+
+impl FundingProjection {
+    /// 支出相对持仓名义价值的基点，收入为负；无持仓时为 0
+    pub fn cost_bps(&self) -> Decimal {
+        let notional = self.position.abs() * self.mark_price;
+        if notional.is_zero() {
+            return Decimal::ZERO;
+        }
+        (-self.payment / notional * Decimal::from(10_000)).round_dp(4)
+    }
+}
+
+/// 持仓 `position` 在资金费率 `rate` 下每周期的成本（基点，收入为负）：多头在正费率时支付
+pub fn funding_cost_bps(position: Decimal, rate: Decimal) -> Decimal {
+    let bps = rate * Decimal::from(10_000);
+    match position.cmp(&Decimal::ZERO) {
+        std::cmp::Ordering::Greater => bps,
+        std::cmp::Ordering::Less => -bps,
+        std::cmp::Ordering::Equal => Decimal::ZERO,
+    }
+}
+
+#[derive(Default)]
+struct State {
+    rates: HashMap<String, FundingState>,
+    marks: HashMap<String, Decimal>,
+    positions: HashMap<String, Decimal>,
+    /// 上一笔资金费支付的时间，估算下一笔支付覆盖的时长
+    last_payment: HashMap<String, u64>,
+    /// 已告警的市场，成本回落到阈值以下后重新告警
+    alerted: HashSet<String>,
+}
+
+/// 各市场的资金费率与持仓资金费估算，克隆后共享同一份数据
+#[derive(Clone, Default)]
+pub struct FundingTracker {
+    state: Arc<Mutex<State>>,
+    alert_bps: Option<Decimal>,
+}
+
+impl FundingTracker {
+    /// `alert_bps`：持仓每 8 小时的资金费成本超过该基点数时告警
+    pub fn new(alert_bps: Option<Decimal>) -> Self {
+        Self {
+            state: Arc::default(),
+            alert_bps,
+        }
+    }
+
+    /// 处理资金费率、行情摘要（标记价格）、持仓与资金费支付消息，其他消息忽略
+    pub fn on_message(&self, message: &Message) {
+        match message {
+            Message::FundingData(data) => self.update_rate(FundingState {
+                market: data.market.clone(),
+                funding_rate: decimal(data.funding_rate),
+                funding_premium: decimal(data.funding_premium),
+                funding_index: decimal(data.funding_index),
+                updated_at: data.created_at,
+            }),
+            Message::MarketSummary(summary) if summary.mark_price > 0.0 => {
+                self.set_mark_price(&summary.symbol, decimal(summary.mark_price))
+            }
+            // 空头持仓的 size 为负数
+            Message::Position(position) => {
+                self.set_position(&position.market, decimal(position.size))
+            }
+            Message::FundingPayments(payment) => {
+                self.reconcile(
+                    &payment.market,
+                    decimal(payment.payment),
+                    payment.created_at,
+                );
+            }
+            _ => {}
+        }
+    }
+
+    /// 更新 `rate.market` 的资金费率
+    pub fn update_rate(&self, rate: FundingState) {
+        let market = rate.market.clone();
+        metrics().set_funding_rate(&market, rate.funding_rate.to_f64().unwrap_or_default());
+        self.state
+            .lock()
+            .unwrap()
+            .rates
+            .insert(market.clone(), rate);
+        self.updated(&market);
+    }
+
+    /// 更新 `market` 的标记价格；价格变化时重新估算资金费
+    pub fn set_mark_price(&self, market: &str, mark_price: Decimal) {
+        let changed = self
+            .state
+            .lock()
+            .unwrap()
+            .marks
+            .insert(market.to_string(), mark_price)
+            != Some(mark_price);
+        if changed {
+            self.updated(market);
+        }
+    }
+
+    /// 持仓数量，多头为正、空头为负
+    pub fn set_position(&self, market: &str, position: Decimal) {
+        self.state
+            .lock()
+            .unwrap()
+            .positions
+            .insert(market.to_string(), position);
+        self.updated(market);
+    }
+
+    /// 最新的资金费数据
+    pub fn rate(&self, market: &str) -> Option<FundingState> {
+        self.state.lock().unwrap().rates.get(market).cloned()
+    }
+
+    /// 当前持仓在 `horizon` 内的资金费估算；缺少资金费率或标记价格时返回 `None`
+    pub fn project(&self, market: &str, horizon: Duration) -> Option<FundingProjection> {
+        let state = self.state.lock().unwrap();
+        project(&state, market, horizon)
+    }
+
+    /// 当前持仓在下一个资金费周期（8 小时）的估算
+    pub fn next_period(&self, market: &str) -> Option<FundingProjection> {
+        self.project(market, FUNDING_PERIOD)
+    }
+
+    /// 收到实际资金费支付：与按上一笔支付以来的时长估算的金额比较并记录误差，返回误差
+    pub fn reconcile(&self, market: &str, payment: Decimal, created_at: u64) -> Option<Decimal> {
+        let mut state = self.state.lock().unwrap();
+        let previous = state.last_payment.insert(market.to_string(), created_at);
+        let elapsed = Duration::from_millis(created_at.saturating_sub(previous?));
+        let estimate = project(&state, market, elapsed)?.payment.round_dp(8);
+        let error = payment - estimate;
+        info!(
+            "Funding payment on {}: {} (estimated {} over {}s, error {})",
+            market,
+            payment,
+            estimate,
+            elapsed.as_secs(),
+            error
+        );
+        Some(error)
+    }
+
+    /// 资金费率、标记价格或持仓变化后更新指标并检查告警
+    fn updated(&self, market: &str) {
+        let Some(projection) = self.next_period(market) else {
+            return;
+        };
+        metrics().set_funding_projection(market, projection.payment.to_f64().unwrap_or_default());
+        let Some(threshold) = self.alert_bps else {
+            return;
+        };
+        let cost_bps = projection.cost_bps();
+        let mut state = self.state.lock().unwrap();
+        if cost_bps > threshold {
+            if state.alerted.insert(market.to_string()) {
+                warn!(
+                    "Projected 8h funding cost on {} position {} is {} bps ({} at rate {}), above --funding-alert-bps {}",
+                    market,
+                    projection.position,
+                    cost_bps,
+                    -projection.payment.round_dp(4),
+                    projection.funding_rate,
+                    threshold
+                );
+            }
+        } else {
+            state.alerted.remove(market);
+        }
+    }
+}
+
+fn project(state: &State, market: &str, horizon: Duration) -> Option<FundingProjection> {
+    let rate = state.rates.get(market)?;
+    let mark_price = *state.marks.get(market)?;
+    let position = state.positions.get(market).copied().unwrap_or_default();
+    let periods = Decimal::from(horizon.as_millis() as u64)
+        / Decimal::from(FUNDING_PERIOD.as_millis() as u64);
+    Some(FundingProjection {
+        market: market.to_string(),
+        position,
+        mark_price,
+        funding_rate: rate.funding_rate,
+        horizon,
+        payment: -position * mark_price * rate.funding_rate * periods,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MARKET: &str = "BTC-USD-PERP";
+
+    fn rate(funding_rate: Decimal) -> FundingState {
+        FundingState {
+            market: MARKET.to_string(),
+            funding_rate,
+            funding_premium: Decimal::ZERO,
+            funding_index: Decimal::ZERO,
+            updated_at: 0,
+        }
+    }
+
+    #[test]
+    fn projects_funding_for_the_position() {
+        let tracker = FundingTracker::new(None);
+        tracker.set_position(MARKET, Decimal::new(5, 1));
+        assert_eq!(tracker.next_period(MARKET), None);
+        tracker.update_rate(rate(Decimal::new(1, 4)));
+        tracker.set_mark_price(MARKET, Decimal::from(100_000));
+
+        // 多头在正费率下支付：0.5 * 100000 * 0.0001 = 5
+        let projection = tracker.next_period(MARKET).unwrap();
+        assert_eq!(projection.payment, Decimal::from(-5));
+        assert_eq!(projection.cost_bps(), Decimal::ONE);
+        let hour = tracker.project(MARKET, Duration::from_secs(3_600)).unwrap();
+        assert_eq!(hour.payment.round_dp(6), Decimal::new(-625, 3));
+
+        // 空头收取
+        tracker.set_position(MARKET, Decimal::new(-5, 1));
+        let projection = tracker.next_period(MARKET).unwrap();
+        assert_eq!(projection.payment, Decimal::from(5));
+        assert_eq!(projection.cost_bps(), Decimal::NEGATIVE_ONE);
+
+        assert_eq!(
+            funding_cost_bps(Decimal::ONE, Decimal::new(3, 4)),
+            Decimal::from(3)
+        );
+        assert_eq!(
+            funding_cost_bps(-Decimal::ONE, Decimal::new(3, 4)),
+            Decimal::from(-3)
+        );
+        assert_eq!(
+            funding_cost_bps(Decimal::ZERO, Decimal::new(3, 4)),
+            Decimal::ZERO
+        );
+    }
+
+    #[test]
+    fn payments_are_reconciled_against_the_estimate() {
+        let tracker = FundingTracker::new(None);
+        tracker.update_rate(rate(Decimal::new(1, 4)));
+        tracker.set_mark_price(MARKET, Decimal::from(100_000));
+        tracker.set_position(MARKET, Decimal::ONE);
+
+        // 第一笔支付只作为起点
+        assert_eq!(tracker.reconcile(MARKET, Decimal::from(-3), 1_000), None);
+        // 1 小时：估算 -1.25，实际 -1.3
+        let error = tracker.reconcile(MARKET, Decimal::new(-13, 1), 1_000 + 3_600_000);
+        assert_eq!(error, Some(Decimal::new(-5, 2)));
+        assert_eq!(tracker.reconcile("ETH-USD-PERP", Decimal::ONE, 1_000), None);
+    }
+
+    #[test]
+    fn alerts_once_until_the_cost_falls_below_the_threshold() {
+        let tracker = FundingTracker::new(Some(Decimal::from(2)));
+        tracker.set_mark_price(MARKET, Decimal::from(100_000));
+        tracker.set_position(MARKET, Decimal::ONE);
+        let alerted = || tracker.state.lock().unwrap().alerted.contains(MARKET);
+
+        tracker.update_rate(rate(Decimal::new(1, 4)));
+        assert!(!alerted());
+        tracker.update_rate(rate(Decimal::new(3, 4)));
+        assert!(alerted());
+        // 空头在正费率下收取资金费，不告警
+        tracker.set_position(MARKET, -Decimal::ONE);
+        assert!(!alerted());
+    }
+}
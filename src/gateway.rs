@@ -0,0 +1,303 @@
+//! 下单出口：真实发送到交易所，或在 dry-run 模式下只记录请求
+
+use async_trait::async_trait;
+use log::info;
+use paradex::{
+    error::Error,
+    rest::Client,
+    structs::{
+        CancelByMarketResponse, ModifyOrderRequest, OrderInstruction, OrderRequest, OrderStatus,
+        OrderType, OrderUpdate, Side,
+    },
+};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use thiserror::Error;
+
+use crate::metrics::metrics;
+use crate::risk::RiskViolation;
+
+/// 下单出口返回的错误：交易所（或模拟撮合）拒绝，或发送前被 [`crate::risk::RiskGuard`] 拦截
+#[derive(Debug, Error)]
+pub enum GatewayError {
+    #[error(transparent)]
+    Exchange(#[from] Error),
+    #[error("blocked by risk guard: {0}")]
+    Risk(#[from] RiskViolation),
+}
+
+/// 下单、改单与撤单操作；行情与账户查询不经过该 trait
+#[async_trait]
+pub trait OrderGateway: Send + Sync {
+    async fn create_order(&self, request: OrderRequest) -> Result<OrderUpdate, GatewayError>;
+
+    async fn modify_order(&self, request: ModifyOrderRequest) -> Result<OrderUpdate, GatewayError>;
+
+    async fn cancel_order(&self, order_id: String) -> Result<(), GatewayError>;
+
+    async fn cancel_order_by_client_id(&self, client_id: String) -> Result<(), GatewayError>;
+
+    async fn cancel_all_orders_for_market(
+        &self,
+        market: String,
+    ) -> Result<CancelByMarketResponse, GatewayError>;
+
+    async fn cancel_all_orders(&self) -> Result<Vec<String>, GatewayError>;
+}
+
+/// 请求失败时计入 REST 错误指标
+fn counted<T>(result: Result<T, Error>) -> Result<T, GatewayError> {
+    Ok(result.inspect_err(|_| metrics().rest_error())?)
+}
+
+/// 通过 Paradex REST 客户端真实下单
+pub struct Live {
+    client: Client,
+}
+
+impl Live {
+    /// 通过 `client` 发送订单
+    pub fn new(client: Client) -> Self {
+        Self { client }
+    }
+}
+
+#[async_trait]
+impl OrderGateway for Live {
+    async fn create_order(&self, request: OrderRequest) -> Result<OrderUpdate, GatewayError> {
+        counted(self.client.create_order(request).await)
+    }
+
+    async fn modify_order(&self, request: ModifyOrderRequest) -> Result<OrderUpdate, GatewayError> {
+        counted(self.client.modify_order(request).await)
+    }
+
+    async fn cancel_order(&self, order_id: String) -> Result<(), GatewayError> {
+        counted(self.client.cancel_order(order_id).await)
+    }
+
+    async fn cancel_order_by_client_id(&self, client_id: String) -> Result<(), GatewayError> {
+        counted(self.client.cancel_order_by_client_id(client_id).await)
+    }
+
+    async fn cancel_all_orders_for_market(
+        &self,
+        market: String,
+    ) -> Result<CancelByMarketResponse, GatewayError> {
+        counted(self.client.cancel_all_orders_for_market(market).await)
+    }
+
+    async fn cancel_all_orders(&self) -> Result<Vec<String>, GatewayError> {
+        counted(self.client.cancel_all_orders().await)
+    }
+}
+
+/// dry-run：把请求序列化为 JSON 记录到日志，并返回合成的结果，不访问交易所
+#[derive(Default)]
+pub struct DryRun {
+    account: String,
+    next_id: AtomicU64,
+    requests: Mutex<Vec<(&'static str, serde_json::Value)>>,
+}
+
+impl DryRun {
+    /// `account` 填入合成订单的 `account` 字段
+    pub fn new(account: impl Into<String>) -> Self {
+        Self {
+            account: account.into(),
+            ..Self::default()
+        }
+    }
+
+    /// 已记录的请求（操作名与 JSON 请求体），按调用顺序
+    pub fn requests(&self) -> Vec<(&'static str, serde_json::Value)> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    fn record<T: Serialize>(&self, operation: &'static str, request: &T) {
+        let json = serde_json::to_value(request).unwrap_or_default();
+        info!(dry_run = true; "Dry run {}: {}", operation, json);
+        self.requests.lock().unwrap().push((operation, json));
+    }
+
+    fn order_update(&self, order: SyntheticOrder) -> OrderUpdate {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as u64;
+        OrderUpdate {
+            account: self.account.clone(),
+            cancel_reason: String::new(),
+            client_id: order.client_id,
+            created_at: now,
+            id: order.id,
+            instruction: order.instruction,
+            last_updated_at: now,
+            market: order.market,
+            price: order.price,
+            remaining_size: order.size,
+            side: order.side,
+            size: order.size,
+            status: OrderStatus::NEW,
+            timestamp: now,
+            order_type: order.order_type,
+            seq_no: 0,
+            avg_fill_price: 0.0,
+            received_at: now,
+            published_at: now,
+            flags: vec![],
+            trigger_price: order.trigger_price,
+        }
+    }
+}
+
+/// 合成 `OrderUpdate` 所需的订单字段
+struct SyntheticOrder {
+    id: String,
+    client_id: String,
+    market: String,
+    instruction: OrderInstruction,
+    price: Option<Decimal>,
+    side: Side,
+    size: Decimal,
+    order_type: OrderType,
+    trigger_price: Option<Decimal>,
+}
+
+#[async_trait]
+impl OrderGateway for DryRun {
+    async fn create_order(&self, request: OrderRequest) -> Result<OrderUpdate, GatewayError> {
+        self.record("create_order", &request);
+        let id = format!(
+            "dry-run-{}",
+            self.next_id.fetch_add(1, Ordering::Relaxed) + 1
+        );
+        Ok(self.order_update(SyntheticOrder {
+            id,
+            client_id: request.client_id.unwrap_or_default(),
+            market: request.market,
+            instruction: request.instruction,
+            price: request.price,
+            side: request.side,
+            size: request.size,
+            order_type: request.order_type,
+            trigger_price: request.trigger_price,
+        }))
+    }
+
+    async fn modify_order(&self, request: ModifyOrderRequest) -> Result<OrderUpdate, GatewayError> {
+        self.record("modify_order", &request);
+        Ok(self.order_update(SyntheticOrder {
+            id: request.id,
+            client_id: String::new(),
+            market: request.market,
+            instruction: OrderInstruction::GTC,
+            price: request.price,
+            side: request.side,
+            size: request.size,
+            order_type: request.order_type,
+            trigger_price: None,
+        }))
+    }
+
+    async fn cancel_order(&self, order_id: String) -> Result<(), GatewayError> {
+        self.record("cancel_order", &serde_json::json!({ "id": order_id }));
+        Ok(())
+    }
+
+    async fn cancel_order_by_client_id(&self, client_id: String) -> Result<(), GatewayError> {
+        self.record(
+            "cancel_order_by_client_id",
+            &serde_json::json!({ "client_id": client_id }),
+        );
+        Ok(())
+    }
+
+    async fn cancel_all_orders_for_market(
+        &self,
+        market: String,
+    ) -> Result<CancelByMarketResponse, GatewayError> {
+        self.record(
+            "cancel_all_orders_for_market",
+            &serde_json::json!({ "market": market }),
+        );
+        Ok(CancelByMarketResponse {
+            market,
+            message: "dry run".to_string(),
+        })
+    }
+
+    async fn cancel_all_orders(&self) -> Result<Vec<String>, GatewayError> {
+        self.record("cancel_all_orders", &serde_json::json!({}));
+        Ok(vec![])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn order_request() -> OrderRequest {
+        OrderRequest {
+            instruction: OrderInstruction::POST_ONLY,
+            market: "BTC-USD-PERP".to_string(),
+            price: Some(Decimal::from(95000)),
+            side: Side::BUY,
+            size: Decimal::new(5, 3),
+            order_type: OrderType::LIMIT,
+            client_id: Some("tlp-1".to_string()),
+            flags: vec![],
+            recv_window: None,
+            stp: None,
+            trigger_price: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn dry_run_records_requests_and_returns_synthetic_orders() {
+        let gateway = DryRun::new("0xabc");
+
+        let created = gateway.create_order(order_request()).await.unwrap();
+        assert_eq!(created.id, "dry-run-1");
+        assert_eq!(created.client_id, "tlp-1");
+        assert_eq!(created.account, "0xabc");
+        assert_eq!(created.status, OrderStatus::NEW);
+        assert_eq!(created.remaining_size, Decimal::new(5, 3));
+
+        let modified = gateway
+            .modify_order(ModifyOrderRequest {
+                id: created.id.clone(),
+                market: created.market.clone(),
+                price: Some(Decimal::from(92000)),
+                side: Side::BUY,
+                size: created.size,
+                order_type: OrderType::LIMIT,
+            })
+            .await
+            .unwrap();
+        assert_eq!(modified.id, "dry-run-1");
+        assert_eq!(modified.price, Some(Decimal::from(92000)));
+
+        gateway.cancel_order(modified.id).await.unwrap();
+        assert!(gateway.cancel_all_orders().await.unwrap().is_empty());
+
+        let requests = gateway.requests();
+        let operations: Vec<_> = requests.iter().map(|(op, _)| *op).collect();
+        assert_eq!(
+            operations,
+            [
+                "create_order",
+                "modify_order",
+                "cancel_order",
+                "cancel_all_orders"
+            ]
+        );
+        assert_eq!(requests[0].1["instruction"], "POST_ONLY");
+        assert_eq!(requests[0].1["market"], "BTC-USD-PERP");
+        assert_eq!(requests[1].1["price"], "92000");
+        assert_eq!(requests[2].1["id"], "dry-run-1");
+    }
+}
@@ -0,0 +1,169 @@
+//! 游标分页的历史查询（成交、资金费）的公共部分：时间范围、分页与时间格式
+
+use chrono::{DateTime, SecondsFormat, TimeZone, Utc};
+use log::info;
+use serde::Deserialize;
+use std::future::Future;
+
+use crate::http::HttpError;
+
+/// 每页请求的条数
+pub const PAGE_SIZE: u32 = 1000;
+
+/// 游标分页响应；`next` 为空表示没有下一页
+#[derive(Debug, Deserialize)]
+pub struct CursorPage<T> {
+    #[serde(default)]
+    pub next: Option<String>,
+    pub results: Vec<T>,
+}
+
+/// 查询的时间范围；未指定的一端不限制
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TimeWindow {
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+}
+
+impl TimeWindow {
+    /// 起始时间不得晚于结束时间
+    pub fn validate(&self) -> Result<(), String> {
+        match (self.from, self.to) {
+            (Some(from), Some(to)) if from > to => Err(format!(
+                "--from ({}) must not be later than --to ({})",
+                from, to
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// 分页查询路径：`page_size`、可选的 `market`、时间范围（毫秒）与游标
+pub fn history_path(
+    path: &str,
+    market: Option<&str>,
+    window: &TimeWindow,
+    cursor: Option<&str>,
+) -> String {
+    let mut params = vec![("page_size", PAGE_SIZE.to_string())];
+    if let Some(market) = market {
+        params.push(("market", market.to_string()));
+    }
+    if let Some(from) = window.from {
+        params.push(("start_at", from.timestamp_millis().to_string()));
+    }
+    if let Some(to) = window.to {
+        params.push(("end_at", to.timestamp_millis().to_string()));
+    }
+    if let Some(cursor) = cursor {
+        params.push(("cursor", cursor.to_string()));
+    }
+    let url = reqwest::Url::parse_with_params(&format!("http://localhost{}", path), &params)
+        .expect("static base URL");
+    format!("{}?{}", url.path(), url.query().unwrap_or_default())
+}
+
+/// 逐页请求直到没有下一页，每页记录一次进度；`fetch_page` 接收上一页的游标
+pub async fn fetch_pages<T, F, Fut>(label: &str, mut fetch_page: F) -> Result<Vec<T>, HttpError>
+where
+    F: FnMut(Option<String>) -> Fut,
+    Fut: Future<Output = Result<CursorPage<T>, HttpError>>,
+{
+    let mut results = Vec::new();
+    let mut cursor = None;
+    for page in 1.. {
+        let response = fetch_page(cursor.take()).await?;
+        results.extend(response.results);
+        info!(
+            "Fetched {} page {}: {} records so far",
+            label,
+            page,
+            results.len()
+        );
+        match response.next {
+            Some(next) if !next.is_empty() => cursor = Some(next),
+            _ => break,
+        }
+    }
+    Ok(results)
+}
+
+/// 解析命令行中的 RFC3339 时间（如 2025-01-01T00:00:00Z）
+pub fn parse_rfc3339(value: &str) -> Result<DateTime<Utc>, String> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|time| time.with_timezone(&Utc))
+        .map_err(|e| format!("invalid RFC3339 time {:?}: {}", value, e))
+}
+
+/// 毫秒时间戳格式化为 RFC3339（毫秒精度）；超出范围时原样输出
+pub fn format_millis(millis: u64) -> String {
+    Utc.timestamp_millis_opt(millis as i64)
+        .single()
+        .map_or_else(
+            || millis.to_string(),
+            |time| time.to_rfc3339_opts(SecondsFormat::Millis, true),
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn path_encodes_filters_and_cursor() {
+        let window = TimeWindow {
+            from: Some(parse_rfc3339("2025-01-01T00:00:00Z").unwrap()),
+            to: Some(parse_rfc3339("2025-01-02T08:00:00+08:00").unwrap()),
+        };
+        assert_eq!(
+            history_path(
+                "/fills",
+                Some("BTC-USD-PERP"),
+                &window,
+                Some("eyJmaWx0ZXIiOiJNVEl6In0=")
+            ),
+            "/fills?page_size=1000&market=BTC-USD-PERP&start_at=1735689600000\
+             &end_at=1735776000000&cursor=eyJmaWx0ZXIiOiJNVEl6In0%3D"
+        );
+        assert_eq!(
+            history_path("/funding/payments", None, &TimeWindow::default(), None),
+            "/funding/payments?page_size=1000"
+        );
+        assert!(parse_rfc3339("2025-01-01").is_err());
+        assert_eq!(format_millis(1735689600123), "2025-01-01T00:00:00.123Z");
+    }
+
+    #[test]
+    fn window_rejects_reversed_range() {
+        let window = TimeWindow {
+            from: Some(parse_rfc3339("2025-02-01T00:00:00Z").unwrap()),
+            to: Some(parse_rfc3339("2025-01-01T00:00:00Z").unwrap()),
+        };
+        assert!(window.validate().is_err());
+        assert!(TimeWindow { to: None, ..window }.validate().is_ok());
+    }
+
+    #[tokio::test]
+    async fn pages_are_fetched_until_cursor_runs_out() {
+        let mut requested = Vec::new();
+        let results = fetch_pages("test", |cursor| {
+            requested.push(cursor.clone());
+            let page = match cursor.as_deref() {
+                None => CursorPage {
+                    next: Some("b".to_string()),
+                    results: vec![1, 2],
+                },
+                Some("b") => CursorPage {
+                    next: Some(String::new()),
+                    results: vec![3],
+                },
+                Some(other) => panic!("unexpected cursor {}", other),
+            };
+            async move { Ok(page) }
+        })
+        .await
+        .unwrap();
+        assert_eq!(results, [1, 2, 3]);
+        assert_eq!(requested, [None, Some("b".to_string())]);
+    }
+}
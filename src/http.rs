@@ -0,0 +1,271 @@
+//! Paradex REST 请求（公开接口与带 JWT 认证的接口），用于 paradex crate 尚未封装的接口
+
+use log::warn;
+use reqwest::{Client as HttpClient, Method, StatusCode};
+use serde::{de::DeserializeOwned, Serialize};
+use std::sync::{Arc, Mutex};
+use thiserror::Error;
+use tokio::sync::watch;
+
+use crate::metrics::metrics;
+use crate::onboarding::{
+    get_jwt_token, JwtManager, JwtToken, OnboardingError, ParadexSigner, ServerClock,
+};
+
+/// `AuthedHttpClient` 请求错误
+#[derive(Debug, Error)]
+pub enum HttpError {
+    #[error("HTTP error: {0}")]
+    Request(#[from] reqwest::Error),
+    #[error("Re-authentication failed: {0}")]
+    Auth(#[from] OnboardingError),
+    #[error("API rejected request: status={status}, body={body}")]
+    Api { status: StatusCode, body: String },
+    #[error("Failed to decode response: {0}")]
+    Decode(#[from] serde_json::Error),
+}
+
+/// 无需认证的 GET 请求（`path` 相对于 `base_url`），解析 JSON 响应
+pub async fn get_public_json<T: DeserializeOwned>(
+    http_client: &HttpClient,
+    base_url: &str,
+    path: &str,
+) -> Result<T, HttpError> {
+    fetch_public_json(http_client, base_url, path)
+        .await
+        .inspect_err(|_| metrics().rest_error())
+}
+
+async fn fetch_public_json<T: DeserializeOwned>(
+    http_client: &HttpClient,
+    base_url: &str,
+    path: &str,
+) -> Result<T, HttpError> {
+    let response = http_client
+        .get(format!("{}{}", base_url, path))
+        .send()
+        .await?;
+    let status = response.status();
+    let text = response.text().await?;
+    if status.is_success() {
+        Ok(serde_json::from_str(&text)?)
+    } else {
+        Err(HttpError::Api { status, body: text })
+    }
+}
+
+struct TokenState {
+    current: JwtToken,
+    updates: watch::Receiver<JwtToken>,
+}
+
+/// 自动附加 `Authorization: Bearer` 的 REST 客户端
+///
+/// token 跟随 `JwtManager` 的后台刷新；收到 401 时重新认证并重试一次。
+pub struct AuthedHttpClient {
+    http_client: HttpClient,
+    signer: Arc<ParadexSigner>,
+    clock: ServerClock,
+    token: Mutex<TokenState>,
+}
+
+impl AuthedHttpClient {
+    /// 使用 `jwt_manager` 的当前 token 并订阅其刷新
+    pub fn new(
+        http_client: HttpClient,
+        signer: Arc<ParadexSigner>,
+        jwt_manager: &JwtManager,
+    ) -> Self {
+        let updates = jwt_manager.subscribe();
+        let current = updates.borrow().clone();
+        Self {
+            http_client,
+            signer,
+            clock: ServerClock::new(),
+            token: Mutex::new(TokenState { current, updates }),
+        }
+    }
+
+    /// GET `path`（相对于 `ParadexConfig::base_url`）并解析 JSON 响应
+    pub async fn get_json<T: DeserializeOwned>(&self, path: &str) -> Result<T, HttpError> {
+        let body = self.send(Method::GET, path, None::<&()>).await?;
+        Ok(serde_json::from_str(&body)?)
+    }
+
+    /// POST JSON 请求体并解析 JSON 响应
+    pub async fn post_json<T: DeserializeOwned, B: Serialize + ?Sized>(
+        &self,
+        path: &str,
+        body: &B,
+    ) -> Result<T, HttpError> {
+        let body = self.send(Method::POST, path, Some(body)).await?;
+        Ok(serde_json::from_str(&body)?)
+    }
+
+    /// DELETE `path`，忽略响应内容
+    pub async fn delete(&self, path: &str) -> Result<(), HttpError> {
+        self.send(Method::DELETE, path, None::<&()>).await?;
+        Ok(())
+    }
+
+    async fn send<B: Serialize + ?Sized>(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<&B>,
+    ) -> Result<String, HttpError> {
+        self.send_with_reauth(method, path, body)
+            .await
+            .inspect_err(|_| metrics().rest_error())
+    }
+
+    async fn send_with_reauth<B: Serialize + ?Sized>(
+        &self,
+        method: Method,
+        path: &str,
+        body: Option<&B>,
+    ) -> Result<String, HttpError> {
+        let url = format!("{}{}", self.signer.config().base_url, path);
+        let mut token = self.token();
+        let mut reauthenticated = false;
+        loop {
+            let mut request = self
+                .http_client
+                .request(method.clone(), &url)
+                .bearer_auth(&token);
+            if let Some(body) = body {
+                request = request.json(body);
+            }
+            let response = request.send().await?;
+            let status = response.status();
+            if status == StatusCode::UNAUTHORIZED && !reauthenticated {
+                warn!("{} {} returned 401, re-authenticating", method, path);
+                token = self.reauthenticate().await?;
+                reauthenticated = true;
+                continue;
+            }
+
+            let text = response.text().await?;
+            return if status.is_success() {
+                Ok(text)
+            } else {
+                Err(HttpError::Api { status, body: text })
+            };
+        }
+    }
+
+    /// 当前 token：刷新任务有新 token 时优先使用
+    fn token(&self) -> String {
+        let mut state = self.token.lock().unwrap();
+        let state = &mut *state;
+        if state.updates.has_changed().unwrap_or(false) {
+            state.current = state.updates.borrow_and_update().clone();
+        }
+        state.current.token().to_string()
+    }
+
+    async fn reauthenticate(&self) -> Result<String, HttpError> {
+        let token = get_jwt_token(&self.http_client, &self.signer, &self.clock).await?;
+        let jwt = token.token().to_string();
+        self.token.lock().unwrap().current = token;
+        Ok(jwt)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::onboarding::{mock_server::MockServer, ParadexConfig};
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    const ACCOUNT: &str = "0x129f3dc1b8962d8bb23f7ddc9a0f1c0fcc9fba43a8bc88bc5c1de5b2b6dd2b8";
+    const PRIVATE_KEY: &str = "0x4c8b5bcb3a3a4e7a6fa3d3b1d7e5c6b6a2e3f9d8c7b6a5e4d3c2b1a09f8e7d6";
+
+    async fn client(server: &MockServer) -> (AuthedHttpClient, JwtManager) {
+        let config = ParadexConfig::custom(&server.url(), "ws://unused", "SN_GOERLI");
+        let signer = Arc::new(ParadexSigner::new(PRIVATE_KEY, ACCOUNT, &config).unwrap());
+        let manager = JwtManager::start(HttpClient::new(), signer.clone(), None)
+            .await
+            .unwrap();
+        let client = AuthedHttpClient::new(HttpClient::new(), signer, &manager);
+        (client, manager)
+    }
+
+    #[tokio::test]
+    async fn reauthenticates_once_on_401() {
+        let auth_calls = Arc::new(AtomicU32::new(0));
+        let calls = auth_calls.clone();
+        let server = MockServer::start(move |request| match request.path.as_str() {
+            "/auth" => {
+                let n = calls.fetch_add(1, Ordering::SeqCst) + 1;
+                (200, format!(r#"{{"jwt_token":"token-{}"}}"#, n))
+            }
+            "/account/profile" if request.header("authorization") == Some("Bearer token-2") => {
+                (200, r#"{"username":"alice"}"#.to_string())
+            }
+            "/system/time" => (503, String::new()),
+            _ => (401, r#"{"error":"INVALID_TOKEN"}"#.to_string()),
+        })
+        .await;
+        let (client, _manager) = client(&server).await;
+
+        let profile: serde_json::Value = client.get_json("/account/profile").await.unwrap();
+        assert_eq!(profile["username"], "alice");
+        assert_eq!(auth_calls.load(Ordering::SeqCst), 2);
+
+        // 新 token 之后的请求直接使用
+        client
+            .get_json::<serde_json::Value>("/account/profile")
+            .await
+            .unwrap();
+        assert_eq!(auth_calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn persistent_401_is_reported_after_one_retry() {
+        let server = MockServer::start(|request| match request.path.as_str() {
+            "/auth" => (200, r#"{"jwt_token":"token"}"#.to_string()),
+            "/system/time" => (503, String::new()),
+            _ => (401, String::new()),
+        })
+        .await;
+        let (client, _manager) = client(&server).await;
+
+        match client.delete("/orders").await {
+            Err(HttpError::Api { status, .. }) => assert_eq!(status, StatusCode::UNAUTHORIZED),
+            other => panic!("unexpected result {other:?}"),
+        }
+        let deletes = server
+            .requests()
+            .into_iter()
+            .filter(|request| request.method == "DELETE")
+            .count();
+        assert_eq!(deletes, 2);
+    }
+
+    #[tokio::test]
+    async fn post_json_sends_body_with_bearer_token() {
+        let server = MockServer::start(|request| match request.path.as_str() {
+            "/auth" => (200, r#"{"jwt_token":"token"}"#.to_string()),
+            "/account/profile/username" => (200, request.body.clone()),
+            _ => (503, String::new()),
+        })
+        .await;
+        let (client, _manager) = client(&server).await;
+
+        let echoed: serde_json::Value = client
+            .post_json(
+                "/account/profile/username",
+                &serde_json::json!({"username": "bob"}),
+            )
+            .await
+            .unwrap();
+        assert_eq!(echoed["username"], "bob");
+        let request = server
+            .requests()
+            .into_iter()
+            .find(|request| request.method == "POST" && request.path.ends_with("username"))
+            .unwrap();
+        assert_eq!(request.header("authorization"), Some("Bearer token"));
+    }
+}
@@ -0,0 +1,259 @@
+//! 端到端行情延迟：本地收到消息的时间减去消息中的交易所时间戳
+//!
+//! 本地时间先按服务器时间同步得到的偏差校正，避免本地时钟偏差混入延迟。每个频道保留最近
+//! [`LATENCY_WINDOW`] 条样本计算 p50 / p95 / p99。
+
+use log::info;
+use paradex::ws::Message;
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::task::JoinHandle;
+
+/// 每个频道保留的样本数
+pub const LATENCY_WINDOW: usize = 1000;
+
+static LATENCY: LatencyTracker = LatencyTracker::new();
+
+/// 进程共享的延迟统计
+pub fn latency() -> &'static LatencyTracker {
+    &LATENCY
+}
+
+/// 某个频道最近样本的延迟分位数（毫秒）
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChannelLatency {
+    pub channel: &'static str,
+    pub samples: usize,
+    pub p50: f64,
+    pub p95: f64,
+    pub p99: f64,
+}
+
+/// 按频道统计的滚动延迟
+pub struct LatencyTracker {
+    /// 服务器时间减去本地时间（毫秒）
+    clock_offset_ms: AtomicI64,
+    samples: Mutex<BTreeMap<&'static str, VecDeque<f64>>>,
+}
+
+impl Default for LatencyTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LatencyTracker {
+    /// 时钟偏差为 0，尚无延迟样本
+    pub const fn new() -> Self {
+        Self {
+            clock_offset_ms: AtomicI64::new(0),
+            samples: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// 记录服务器时间同步得到的偏差（服务器时间减去本地时间，毫秒）
+    pub fn set_clock_offset(&self, offset_ms: i64) {
+        self.clock_offset_ms.store(offset_ms, Ordering::Relaxed);
+    }
+
+    /// 服务器时间减去本地时间（毫秒）
+    pub fn clock_offset_ms(&self) -> i64 {
+        self.clock_offset_ms.load(Ordering::Relaxed)
+    }
+
+    /// 记录带交易所时间戳的消息（BBO、成交与订单簿），其他消息忽略
+    pub fn on_message(&self, channel: &'static str, message: &Message) {
+        if let Some(exchange_ms) = exchange_timestamp(message) {
+            self.record_at(channel, exchange_ms, unix_now_millis());
+        }
+    }
+
+    /// 以本地时间 `local_ms` 收到交易所时间为 `exchange_ms` 的消息
+    pub fn record_at(&self, channel: &'static str, exchange_ms: u64, local_ms: i64) {
+        let latency = local_ms + self.clock_offset_ms() - exchange_ms as i64;
+        let mut samples = self.samples.lock().unwrap();
+        let window = samples.entry(channel).or_default();
+        if window.len() == LATENCY_WINDOW {
+            window.pop_front();
+        }
+        window.push_back(latency as f64);
+    }
+
+    /// 各频道的延迟分位数，按频道名排序
+    pub fn summary(&self) -> Vec<ChannelLatency> {
+        let samples = self.samples.lock().unwrap();
+        samples
+            .iter()
+            .filter(|(_, window)| !window.is_empty())
+            .map(|(channel, window)| {
+                let mut sorted: Vec<f64> = window.iter().copied().collect();
+                sorted.sort_by(f64::total_cmp);
+                ChannelLatency {
+                    channel,
+                    samples: sorted.len(),
+                    p50: percentile(&sorted, 0.50),
+                    p95: percentile(&sorted, 0.95),
+                    p99: percentile(&sorted, 0.99),
+                }
+            })
+            .collect()
+    }
+
+    /// 汇总表，用于 `--latency-report`
+    pub fn report(&self) -> LatencyReport {
+        LatencyReport {
+            clock_offset_ms: self.clock_offset_ms(),
+            channels: self.summary(),
+        }
+    }
+
+    /// 每隔 `interval` 输出一行各频道的延迟，直到任务被终止
+    pub fn spawn_summary_log(&'static self, interval: Duration) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(interval).await;
+                let summary = self.summary();
+                if summary.is_empty() {
+                    continue;
+                }
+                let channels: Vec<_> = summary
+                    .iter()
+                    .map(|c| {
+                        format!(
+                            "{} p50={:.0}ms p95={:.0}ms p99={:.0}ms (n={})",
+                            c.channel, c.p50, c.p95, c.p99, c.samples
+                        )
+                    })
+                    .collect();
+                info!("Market data latency: {}", channels.join(", "));
+            }
+        })
+    }
+}
+
+/// `--latency-report` 输出的汇总表
+#[derive(Debug, Clone, PartialEq)]
+pub struct LatencyReport {
+    pub clock_offset_ms: i64,
+    pub channels: Vec<ChannelLatency>,
+}
+
+impl fmt::Display for LatencyReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Market data latency (clock offset {}ms, last {} samples per channel)",
+            self.clock_offset_ms, LATENCY_WINDOW
+        )?;
+        if self.channels.is_empty() {
+            return write!(f, "  no timestamped messages received");
+        }
+        write!(
+            f,
+            "  {:<18} {:>8} {:>10} {:>10} {:>10}",
+            "channel", "samples", "p50 ms", "p95 ms", "p99 ms"
+        )?;
+        for c in &self.channels {
+            write!(
+                f,
+                "\n  {:<18} {:>8} {:>10.1} {:>10.1} {:>10.1}",
+                c.channel, c.samples, c.p50, c.p95, c.p99
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// 消息中的交易所时间（Unix 毫秒）
+fn exchange_timestamp(message: &Message) -> Option<u64> {
+    match message {
+        Message::BBO(bbo) => Some(bbo.last_updated_at),
+        Message::Trades(trade) => Some(trade.created_at),
+        Message::OrderBook(book) | Message::OrderBookDeltas(book) => Some(book.last_updated_at),
+        _ => None,
+    }
+}
+
+/// 最近秩法：不小于 `q` 比例样本的最小值
+fn percentile(sorted: &[f64], q: f64) -> f64 {
+    let rank = (q * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+fn unix_now_millis() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as i64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use paradex::structs::BBO;
+
+    #[test]
+    fn percentiles_over_the_rolling_window() {
+        let tracker = LatencyTracker::new();
+        // 1..=100 ms
+        for latency in 1..=100 {
+            tracker.record_at("bbo", 1_000, 1_000 + latency);
+        }
+        let summary = tracker.summary();
+        assert_eq!(summary.len(), 1);
+        assert_eq!(summary[0].samples, 100);
+        assert_eq!(summary[0].p50, 50.0);
+        assert_eq!(summary[0].p95, 95.0);
+        assert_eq!(summary[0].p99, 99.0);
+
+        // 窗口满后丢弃最早的样本
+        for _ in 0..LATENCY_WINDOW {
+            tracker.record_at("bbo", 1_000, 1_500);
+        }
+        let summary = tracker.summary();
+        assert_eq!(summary[0].samples, LATENCY_WINDOW);
+        assert_eq!(summary[0].p50, 500.0);
+    }
+
+    #[test]
+    fn clock_offset_corrects_local_skew() {
+        let tracker = LatencyTracker::new();
+        // 本地时钟快 2 秒：服务器时间 = 本地时间 - 2000ms
+        tracker.set_clock_offset(-2_000);
+        tracker.record_at("trades", 10_000, 12_030);
+        assert_eq!(tracker.summary()[0].p99, 30.0);
+
+        let message = Message::BBO(BBO {
+            bid: 95000.0,
+            bid_size: 1.0,
+            ask: 95010.0,
+            ask_size: 1.0,
+            market: "BTC-USD-PERP".to_string(),
+            last_updated_at: unix_now_millis() as u64,
+        });
+        tracker.set_clock_offset(0);
+        tracker.on_message("bbo", &message);
+        tracker.on_message("bbo", &Message::Connected);
+        let bbo = &tracker.report().channels[0];
+        assert_eq!(bbo.channel, "bbo");
+        assert_eq!(bbo.samples, 1);
+        assert!(bbo.p50 >= 0.0 && bbo.p50 < 1_000.0, "{}", bbo.p50);
+    }
+
+    #[test]
+    fn report_lists_channels() {
+        let tracker = LatencyTracker::new();
+        assert!(tracker
+            .report()
+            .to_string()
+            .contains("no timestamped messages"));
+        tracker.record_at("orderbook_deltas", 0, 12);
+        let report = tracker.report().to_string();
+        assert!(report.contains("clock offset 0ms"), "{report}");
+        assert!(report.contains("orderbook_deltas"), "{report}");
+        assert!(report.contains("12.0"), "{report}");
+    }
+}
@@ -0,0 +1,76 @@
+//! Paradex 交易客户端的可复用部分：onboarding、JWT 认证、TypedData 签名与下单辅助
+//!
+//! 命令行程序（`src/main.rs`）只是本库的一个使用者；关闭默认的 `cli` feature
+//! 即可在不引入 clap / dotenvy 的情况下作为库依赖：
+//!
+//! ```no_run
+//! use trade_lighter_paradex::onboarding::{get_jwt_token, ParadexConfig, ParadexSigner, ServerClock};
+//!
+//! # async fn example() -> Result<(), trade_lighter_paradex::onboarding::OnboardingError> {
+//! let config = ParadexConfig::testnet();
+//! let signer = ParadexSigner::new("0x...", "0x...", &config)?;
+//! let jwt = get_jwt_token(&reqwest::Client::new(), &signer, &ServerClock::new()).await?;
+//! println!("expires at {}", jwt.expires_at);
+//! # Ok(())
+//! # }
+//! ```
+
+/// 账户保证金跟踪与低余额告警
+pub mod account;
+/// 订单 client_id 生成与去重
+pub mod client_id;
+/// `accounts.toml` 多账户配置
+pub mod config;
+/// `.env` 账户变量的读取与校验
+pub mod env;
+/// 成交历史导出
+pub mod fills;
+/// 资金费率与资金费支付历史
+pub mod funding;
+/// 下单出口（真实下单或 dry-run）
+pub mod gateway;
+/// 游标分页历史查询的公共部分
+pub mod history;
+/// 公开接口与带 JWT 认证的 REST 请求
+pub mod http;
+/// 行情端到端延迟统计
+pub mod latency;
+/// 文本 / JSON 日志输出
+pub mod logging;
+/// 行情来源：实时 WebSocket 或录制重放
+pub mod market_data;
+/// 市场元数据查询、校验与缓存
+pub mod markets;
+/// Prometheus 指标与 `/metrics` 服务
+pub mod metrics;
+/// onboarding、JWT 认证、提现与划转
+pub mod onboarding;
+/// REST 订单簿快照
+pub mod orderbook;
+/// 下单请求构建
+pub mod orders;
+/// 模拟交易：本地撮合的下单出口
+pub mod paper;
+/// 平仓订单计算与结果汇总
+pub mod positions;
+/// 行情录制为 JSONL 文件
+pub mod recorder;
+/// 重放录制的行情
+pub mod replay;
+/// 下单前风控
+pub mod risk;
+/// 私钥来源与敏感值脱敏
+pub mod secrets;
+/// 账户会话状态（持仓与余额）
+pub mod session;
+/// 跨交易所价差监控
+pub mod spread;
+/// 执行策略（TWAP 与网格）
+pub mod strategies;
+/// 订单状态跟踪
+pub mod trading;
+
+pub use onboarding::{
+    get_jwt_token, is_onboarded, perform_onboarding, JwtToken, LocalSigner, OnboardingError,
+    OnboardingOptions, ParadexConfig, ParadexSigner, SignError, StarkSigner,
+};
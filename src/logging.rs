@@ -0,0 +1,100 @@
+use log::kv::{Key, Value, VisitSource};
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use serde_json::{json, Map};
+use std::io::Write;
+use std::sync::OnceLock;
+
+/// 日志输出格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum LogFormat {
+    Text,
+    Json,
+}
+
+/// 当前会话的账户上下文，会附加到每一条日志
+static ACCOUNT: OnceLock<String> = OnceLock::new();
+
+struct Logger {
+    level: LevelFilter,
+    format: LogFormat,
+}
+
+/// 收集日志记录中的结构化字段（如 `info!(channel = "bbo"; ...)`）
+struct Fields(Vec<(String, String)>);
+
+impl<'kvs> VisitSource<'kvs> for Fields {
+    fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), log::kv::Error> {
+        self.0.push((key.to_string(), value.to_string()));
+        Ok(())
+    }
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let mut fields = Fields(Vec::new());
+        let _ = record.key_values().visit(&mut fields);
+        let timestamp = chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true);
+
+        let line = match self.format {
+            LogFormat::Text => {
+                let mut line = format!(
+                    "{} {:<5} [{}] {}",
+                    timestamp,
+                    record.level(),
+                    record.target(),
+                    record.args()
+                );
+                if let Some(account) = ACCOUNT.get() {
+                    line.push_str(&format!(" account={}", account));
+                }
+                for (key, value) in &fields.0 {
+                    line.push_str(&format!(" {}={}", key, value));
+                }
+                line
+            }
+            LogFormat::Json => {
+                let mut object = Map::new();
+                object.insert("ts".into(), json!(timestamp));
+                object.insert("level".into(), json!(record.level().as_str()));
+                object.insert("target".into(), json!(record.target()));
+                object.insert("msg".into(), json!(record.args().to_string()));
+                if let Some(account) = ACCOUNT.get() {
+                    object.insert("account".into(), json!(account));
+                }
+                for (key, value) in fields.0 {
+                    object.insert(key, json!(value));
+                }
+                serde_json::Value::Object(object).to_string()
+            }
+        };
+
+        let mut stderr = std::io::stderr().lock();
+        let _ = writeln!(stderr, "{}", line);
+    }
+
+    fn flush(&self) {
+        let _ = std::io::stderr().flush();
+    }
+}
+
+/// 初始化全局日志
+pub fn init(level: Level, format: LogFormat) -> Result<(), log::SetLoggerError> {
+    let level = level.to_level_filter();
+    log::set_boxed_logger(Box::new(Logger { level, format }))?;
+    log::set_max_level(level);
+    Ok(())
+}
+
+/// 设置账户上下文（仅首次设置生效）
+pub fn set_account(account: &str) {
+    let _ = ACCOUNT.set(account.to_string());
+}
@@ -1,324 +1,896 @@
-mod onboarding;
+mod app;
 
-use log::{info, warn};
+use log::{error, info};
+use std::path::PathBuf;
 use std::time::Duration;
 
-use clap::Parser;
-use onboarding::{get_jwt_token, perform_onboarding, ParadexConfig};
-use paradex::{
-    rest::Client,
-    structs::{ModifyOrderRequest, OrderRequest, OrderType, Side},
-    url::URL,
+use chrono::{DateTime, Utc};
+use clap::{Parser, Subcommand};
+use rust_decimal::Decimal;
+use trade_lighter_paradex::config::{self, BookRefresh, ChannelSelection};
+use trade_lighter_paradex::fills::FillFormat;
+use trade_lighter_paradex::history::{parse_rfc3339, TimeWindow};
+use trade_lighter_paradex::latency::latency;
+use trade_lighter_paradex::logging::LogFormat;
+use trade_lighter_paradex::market_data::{
+    CandleInterval, DEFAULT_AUDIT_LEVELS, DEFAULT_DISPATCH_CAPACITY, DEFAULT_MAX_CONSECUTIVE_PANICS,
 };
-use rust_decimal::{prelude::FromPrimitive, Decimal};
+use trade_lighter_paradex::metrics::MetricsServer;
+use trade_lighter_paradex::onboarding::{validate_jwt_expiry, OnboardingOptions};
+use trade_lighter_paradex::orders::{
+    parse_positive_decimal, AggressiveMode, InstructionArg, OrderKind, OrderSide, OrderTarget,
+    StpMode, TriggerKind,
+};
+use trade_lighter_paradex::recorder::Compression;
+use trade_lighter_paradex::replay::ReplaySpeed;
+use trade_lighter_paradex::secrets::KeySource;
+use trade_lighter_paradex::trading::{CloseOptions, TriggerOrder, DEFAULT_POST_ONLY_RETRIES};
+
+use app::account::{
+    run_account_summary, run_auth, run_balance, run_fills, run_funding_payments, run_funding_rates,
+    run_onboard, run_rest_command, run_secrets_set,
+};
+use app::market::{run_markets, run_orderbook, run_stream, run_summary};
+use app::orders::{
+    run_cancel, run_cancel_all_dry_run, run_close_position, run_stop, CLOSE_POLL_INTERVAL,
+};
+use app::trade::{run_paper_trade, run_trade};
+use app::Credentials;
 
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 struct Args {
+    #[command(subcommand)]
+    command: Command,
+
     /// 使用生产环境（默认为测试网）
-    #[arg(long, action)]
+    #[arg(long, action, global = true)]
     production: bool,
+
+    /// 日志级别（trace/debug/info/warn/error）
+    #[arg(long, default_value = "info", global = true)]
+    log_level: log::Level,
+
+    /// 日志格式（text/json）
+    #[arg(long, value_enum, default_value = "text", global = true)]
+    log_format: LogFormat,
+
+    /// 输出 TypedData 与各级哈希等敏感调试信息（需配合 --log-level debug/trace）
+    #[arg(long, alias = "debug-signing", action, global = true)]
+    log_sensitive: bool,
+
+    /// 运行配置文件（默认读取当前目录下存在的 trade_lighter.toml）
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
+
+    /// 订阅行情的市场，可重复指定（如 --symbol BTC-USD-PERP --symbol ETH-USD-PERP）
+    #[arg(long = "symbol", global = true)]
+    symbols: Vec<String>,
+
+    /// 订阅的 WebSocket 频道，逗号分隔（默认全部）：
+    /// bbo,trades,orderbook,orderbook_deltas,market_summary,funding,
+    /// orders,fills,position,account,balance,funding_payments
+    #[arg(long, value_name = "LIST", global = true)]
+    channels: Option<ChannelSelection>,
+
+    /// 接收行情的时长（秒），0 表示运行到 Ctrl-C
+    #[arg(
+        long = "duration",
+        alias = "run-duration-secs",
+        value_name = "SECS",
+        global = true
+    )]
+    run_duration_secs: Option<u64>,
+
+    /// 持续运行直到 Ctrl-C（等同 --duration 0）
+    #[arg(long, action, conflicts_with = "run_duration_secs", global = true)]
+    forever: bool,
+
+    /// auth 签名有效期（秒）
+    #[arg(long, default_value_t = 86400, value_parser = parse_jwt_expiry, global = true)]
+    jwt_expiry_secs: u64,
+
+    /// onboarding 与 auth 请求的最大尝试次数
+    #[arg(long, default_value_t = 5, value_parser = clap::value_parser!(u32).range(1..), global = true)]
+    http_max_attempts: u32,
+
+    /// 收到 429 后按 Retry-After 等待重试的最大次数
+    #[arg(long, default_value_t = 3, global = true)]
+    rate_limit_retries: u32,
+
+    /// 以该子账户身份认证、查询与订阅私有频道（需设置 paradex_subaccount_private_key_hex）
+    #[arg(long, global = true)]
+    subaccount: Option<String>,
+
+    /// Stark 私钥来源（env：环境变量或 profile；keyring：系统钥匙串）
+    #[arg(long, value_enum, default_value = "env", global = true)]
+    key_source: KeySource,
+
+    /// 使用多账户配置文件中的指定账户（替代 .env 中的账户变量）
+    #[arg(long, global = true)]
+    profile: Option<String>,
+
+    /// 多账户配置文件路径
+    #[arg(long, default_value = config::DEFAULT_ACCOUNTS_FILE, global = true)]
+    accounts_file: PathBuf,
+
+    /// 只签名并记录下单 / 改单 / 撤单请求，不发送到交易所（行情订阅与账户查询照常）
+    #[arg(long, action, global = true)]
+    dry_run: bool,
+
+    /// 在 127.0.0.1 的该端口上以 Prometheus 文本格式提供 `/metrics`，随程序退出关闭
+    #[arg(long, value_name = "PORT", global = true)]
+    metrics_port: Option<u16>,
+
+    /// 配合 --metrics-port 导出成交 VWAP、主动买卖量与最大成交的滚动窗口，逗号分隔
+    #[arg(long, value_name = "WINDOWS", value_delimiter = ',', default_value = "1m,5m,30m", value_parser = parse_window, global = true)]
+    vwap_windows: Vec<Duration>,
+
+    /// 每隔该秒数输出一次各市场的订单簿失衡度与微价格（仅 stream / trade）
+    #[arg(long, value_name = "SECS", value_parser = clap::value_parser!(u64).range(1..), global = true)]
+    signal_log_interval: Option<u64>,
+
+    /// 实时行情每个订阅缓存的消息数；回调跟不上时行情频道丢弃最旧的消息，私有频道不丢弃
+    #[arg(long, value_name = "N", default_value_t = DEFAULT_DISPATCH_CAPACITY, value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..), global = true)]
+    dispatch_capacity: usize,
+
+    /// 退出时输出实时行情各频道的延迟分位数（交易所时间戳到本地接收，按服务器时间校正）
+    #[arg(long, action, global = true)]
+    latency_report: bool,
+
+    /// 同一订阅的回调连续 panic 达到该次数后停用该回调
+    #[arg(long, value_name = "N", default_value_t = DEFAULT_MAX_CONSECUTIVE_PANICS, value_parser = clap::value_parser!(u32).range(1..), global = true)]
+    max_callback_panics: u32,
+
+    /// 下单后单个市场的持仓数量上限（多空取绝对值），超出的订单在发送前被拦截
+    #[arg(long, value_name = "SIZE", value_parser = parse_positive_decimal, global = true)]
+    max_position: Option<Decimal>,
+
+    /// 单笔订单与下单后持仓的名义价值上限（USD），按标记价格或 BBO 中间价计算
+    #[arg(long, value_name = "USD", value_parser = parse_positive_decimal, global = true)]
+    max_notional: Option<Decimal>,
+
+    /// 市价单按订单簿估算的成交均价偏离中间价超过该基点数时拒绝发送
+    #[arg(long, value_name = "BPS", value_parser = parse_positive_decimal, global = true)]
+    max_slippage_bps: Option<Decimal>,
+
+    /// 持仓按实时资金费率预计的 8 小时资金费成本（相对名义价值的基点）超过该值时告警（仅 trade）
+    #[arg(long, value_name = "BPS", value_parser = parse_positive_decimal, global = true)]
+    funding_alert_bps: Option<Decimal>,
+
+    /// 把订阅的行情消息按频道与市场录制为 JSONL 文件（仅 stream / trade），每小时滚动一个文件
+    #[arg(long, value_name = "DIR", global = true)]
+    record: Option<PathBuf>,
+
+    /// 录制文件的流式压缩格式
+    #[arg(
+        long,
+        value_enum,
+        value_name = "FORMAT",
+        default_value = "none",
+        global = true
+    )]
+    record_compress: Compression,
+
+    /// 录制文件（压缩后）超过该大小（MB）时滚动到新的分段
+    #[arg(long, value_name = "MB", default_value_t = 256, value_parser = clap::value_parser!(u64).range(1..), global = true)]
+    record_max_file_mb: u64,
+
+    /// 配合 --record 每隔该秒数把已同步的本地订单簿快照写入录制目录的 snapshots/
+    #[arg(long, value_name = "SECS", default_value_t = 10, value_parser = clap::value_parser!(u64).range(1..), global = true)]
+    snapshot_interval: u64,
+
+    /// 订单簿快照每侧的档位数
+    #[arg(long, value_name = "N", default_value_t = 50, value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..), global = true)]
+    snapshot_depth: usize,
+
+    /// 订单簿中间价单次变动超过该百分比时视为异常，暂停该市场的下单直到恢复或重新同步
+    #[arg(long, value_name = "PCT", default_value = "5", value_parser = parse_positive_decimal, global = true)]
+    max_price_jump_pct: Decimal,
+
+    /// order_book 频道的推送间隔
+    #[arg(long, value_enum, value_name = "INTERVAL", global = true)]
+    book_refresh: Option<BookRefresh>,
+
+    /// order_book 频道按该价格间隔聚合档位；可逗号分隔多个，每个间隔单独订阅并维护一本订单簿
+    #[arg(long, value_name = "TICK", value_delimiter = ',', value_parser = parse_positive_decimal, global = true)]
+    book_price_tick: Vec<Decimal>,
+
+    /// 每隔该秒数以 REST 快照交叉校验本地订单簿，连续不一致时强制重新同步（重放时不可用）
+    #[arg(long, value_name = "SECS", value_parser = clap::value_parser!(u64).range(1..), global = true)]
+    book_audit_interval: Option<u64>,
+
+    /// 订单簿审计每侧比较的档位数
+    #[arg(long, value_name = "N", default_value_t = DEFAULT_AUDIT_LEVELS, value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..=100), global = true)]
+    book_audit_levels: usize,
+
+    /// BBO 去重时忽略仅数量变化的报价，只有买卖价变化才传递给下游
+    #[arg(long, global = true)]
+    bbo_ignore_size: bool,
+
+    /// 重放 --record 录制的目录代替实时行情（仅 stream / summary / trade --paper），按接收时间合并各文件
+    #[arg(long, value_name = "DIR", conflicts_with = "record", global = true)]
+    replay: Option<PathBuf>,
+
+    /// 重放速度：相对录制时间的倍数（如 1.0、10.0），max 表示不等待
+    #[arg(long, default_value = "1.0", requires = "replay", global = true)]
+    speed: ReplaySpeed,
+
+    /// 从该接收时间（RFC3339）开始重放，索引显示整段更早的分段不再读取
+    #[arg(long, value_name = "TIME", value_parser = parse_rfc3339, requires = "replay", global = true)]
+    replay_from: Option<DateTime<Utc>>,
+
+    /// 忽略本地缓存的 JWT，重新认证
+    #[arg(long, action, global = true)]
+    force_reauth: bool,
+
+    /// 复用缓存 JWT 所需的最小剩余有效期（秒）
+    #[arg(long, default_value_t = 600, global = true)]
+    jwt_cache_margin_secs: u64,
 }
 
-#[tokio::main]
-async fn main() {
-    // 初始化 rustls CryptoProvider（必须在任何网络操作之前）
-    rustls::crypto::aws_lc_rs::default_provider()
-        .install_default()
-        .expect("Failed to install rustls crypto provider");
+/// `trade` 子命令参数
+#[derive(clap::Args, Debug)]
+struct TradeArgs {
+    /// 确认该命令会在交易所下真实订单
+    #[arg(long = "i-know-this-places-orders", action)]
+    confirmed: bool,
 
-    // 初始化日志
-    simple_logger::init_with_level(log::Level::Info).unwrap();
+    /// 运行下单演示的市场（默认第一个 --symbol）
+    #[arg(long)]
+    trade_symbol: Option<String>,
 
-    // 加载 .env 文件
-    dotenvy::dotenv().ok();
+    /// 只减仓：方向须与持仓相反、数量不超过持仓，否则在发送前拒绝
+    #[arg(long, action)]
+    reduce_only: bool,
+
+    /// 只减仓订单的数量超过持仓时缩减到持仓数量，而不是拒绝
+    #[arg(long, action, requires = "reduce_only")]
+    clamp: bool,
+
+    /// 订单方向
+    #[arg(long, value_enum, default_value = "buy")]
+    side: OrderSide,
+
+    /// 订单数量（默认取配置中的 order.size）
+    #[arg(long = "size", alias = "order-size", value_name = "SIZE", value_parser = parse_positive_decimal)]
+    order_size: Option<Decimal>,
+
+    /// 限价；省略时按 BBO 中间价与 order.price_offset_bps 计算（市价单不可指定）
+    #[arg(long, value_parser = parse_positive_decimal)]
+    price: Option<Decimal>,
+
+    /// 订单类型
+    #[arg(long, value_enum, default_value = "limit")]
+    order_type: OrderKind,
+
+    /// 市价单的滑点上限（基点）：按订单簿估算的成交均价相对中间价超出时不下单
+    #[arg(long, value_name = "BPS", value_parser = parse_positive_decimal)]
+    max_slippage_bps: Option<Decimal>,
+
+    /// 指定 --max-slippage-bps 时的吃单方式：市价单，或滑点上限价格的 IOC 限价单
+    #[arg(
+        long,
+        value_enum,
+        default_value = "limit",
+        requires = "max_slippage_bps"
+    )]
+    aggressive_mode: AggressiveMode,
+
+    /// 订单执行方式（默认取配置中的 order.instruction，市价单默认 ioc）
+    #[arg(long, value_enum)]
+    instruction: Option<InstructionArg>,
+
+    /// 订单 client_id（默认自动生成）
+    #[arg(long)]
+    client_id: Option<String>,
+
+    /// 提现到以太坊账户的 USDC 数量
+    #[arg(long)]
+    withdraw: Option<Decimal>,
+
+    /// 划转到其他 Paradex 账户的 USDC 数量（需配合 --transfer-to）
+    #[arg(long, requires = "transfer_to")]
+    transfer: Option<Decimal>,
+
+    /// 划转目标 StarkNet 账户地址
+    #[arg(long)]
+    transfer_to: Option<String>,
+
+    /// 订阅后等待连接建立、以及下单 / 改单 / 撤单后等待订单结束的最长时间（秒），默认分别为 2 与 5
+    #[arg(long, value_name = "SECS")]
+    settle_delay: Option<u64>,
+
+    /// 订单的自成交保护模式（默认取配置中的 order.stp，未配置时为 expire-maker）
+    #[arg(long, value_enum)]
+    stp: Option<StpMode>,
+
+    /// 订单的 recv_window（毫秒，10..=60000），超时未被撮合引擎接收的订单会被拒绝
+    #[arg(long, value_name = "MS")]
+    recv_window_ms: Option<u64>,
+
+    /// 模拟交易：订单由本地按实时成交与 BBO 撮合，不认证、不向交易所发送
+    #[arg(long, action)]
+    paper: bool,
 
-    // 解析命令行参数
-    let args = Args::parse();
-    let url = if args.production {
-        URL::Production
-    } else {
-        URL::Testnet
-    };
+    /// 模拟交易的初始 USDC 余额
+    #[arg(long, default_value = "10000", value_parser = parse_positive_decimal, requires = "paper")]
+    paper_balance: Decimal,
 
-    let symbol: String = "BTC-USD-PERP".into();
-    let base_url = match url {
-        URL::Production => "https://api.prod.paradex.trade/v1",
-        URL::Testnet => "https://api.testnet.paradex.trade/v1",
-    };
+    /// 退出时将本次运行的成交（不含被撤销的成交）写入该 CSV 文件
+    #[arg(long, value_name = "PATH")]
+    fills_csv: Option<PathBuf>,
 
-    // 从环境变量读取账户信息
-    let private_key = std::env::var("paradex_account_private_key_hex").ok();
-    let eth_account = std::env::var("eth_account_address").ok();
-    let starknet_account = std::env::var("paradex_account_address").ok();
-
-    // 根据是否提供私钥决定是否创建认证客户端
-    let client_private = if let Some(private_key) = private_key {
-        let config = if args.production {
-            ParadexConfig::production()
-        } else {
-            ParadexConfig::testnet()
-        };
-
-        // 执行 onboarding（如果提供了以太坊账户和 StarkNet 账户）
-        if let (Some(ref eth_addr), Some(ref starknet_addr)) = (&eth_account, &starknet_account) {
-            info!("Performing onboarding...");
-            let http_client = reqwest::Client::new();
-
-            if let Err(e) = perform_onboarding(
-                &http_client,
-                base_url,
-                starknet_addr,
-                &private_key,
-                eth_addr,
-                &config,
-            )
-            .await
-            {
-                warn!("Onboarding failed (may already be onboarded): {}", e);
-            } else {
-                info!("Onboarding completed successfully");
-            }
+    /// 会话盈亏的起点（RFC3339）：启动时补录此后的资金费支付，程序停止期间的支付也计入（默认当天 UTC 0 点）
+    #[arg(long, value_name = "TIME", value_parser = parse_rfc3339)]
+    session_start: Option<DateTime<Utc>>,
+
+    /// 每隔该秒数重新查询市场元数据（价格精度、数量步长与下单上下限），下单前据此检查订单
+    #[arg(long, value_name = "SECS", default_value_t = 3600, value_parser = clap::value_parser!(u64).range(1..))]
+    markets_refresh_secs: u64,
+
+    /// POST_ONLY 单因穿越盘口被拒绝后，按最新 BBO 重新定价重试的次数（0 表示不重试）
+    #[arg(long, value_name = "N", default_value_t = DEFAULT_POST_ONLY_RETRIES)]
+    post_only_retries: u32,
+}
+
+/// `twap` 子命令参数：母单的市场、方向与数量取 `trade` 的 --trade-symbol、--side 与 --size
+#[derive(clap::Args, Debug)]
+struct TwapArgs {
+    #[command(flatten)]
+    trade: TradeArgs,
+
+    /// 执行母单的总时长（秒）
+    #[arg(long, value_name = "SECS", default_value_t = 300, value_parser = clap::value_parser!(u64).range(1..))]
+    twap_duration: u64,
+
+    /// 子单间隔（秒），每个间隔提交一个子单
+    #[arg(long, value_name = "SECS", default_value_t = 30, value_parser = clap::value_parser!(u64).range(1..))]
+    twap_interval: u64,
+
+    /// 被动子单在己方最优价挂单等待成交的秒数（默认为子单间隔的一半）；越短越激进
+    #[arg(long, value_name = "SECS")]
+    twap_passive_timeout: Option<u64>,
+
+    /// 被动子单超时未全部成交时吃单补齐，滑点上限取 --max-slippage-bps（默认取 risk.max_slippage_bps，未配置时为 50）
+    #[arg(long, action)]
+    twap_catchup: bool,
+}
+
+/// `bracket` 子命令参数：入场单的市场、方向、数量、类型与价格取 `trade` 的参数
+#[derive(clap::Args, Debug)]
+struct BracketArgs {
+    #[command(flatten)]
+    trade: TradeArgs,
+
+    /// 止盈价相对入场成交均价的偏移（价格单位）：多头向上、空头向下
+    #[arg(long, value_name = "PRICE", value_parser = parse_positive_decimal)]
+    take_profit_offset: Decimal,
+
+    /// 止损触发价相对入场成交均价的偏移（价格单位）：多头向下、空头向上
+    #[arg(long, value_name = "PRICE", value_parser = parse_positive_decimal)]
+    stop_loss_offset: Decimal,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// 仅订阅公开行情，无需私钥、不下单
+    Stream {
+        /// 由成交合成 K 线的周期，逗号分隔（如 1s,1m）；收盘的 K 线写入日志，配合 --record 时写入录制目录的 candles/
+        #[arg(long, value_name = "INTERVALS", value_delimiter = ',')]
+        candles: Vec<CandleInterval>,
+    },
+    /// 列出市场及其价格精度、最小数量、持仓量与资金费率（可用 --symbol 过滤），无需私钥
+    Markets {
+        /// 以 JSON 输出
+        #[arg(long, action)]
+        json: bool,
+    },
+    /// 订阅行情摘要频道，按 24 小时成交量输出全部市场（或 --symbol 指定的市场）的标记价格、
+    /// 最新成交价、持仓量与资金费率，无需私钥
+    Summary {
+        /// 等待行情摘要推送的秒数
+        #[arg(long, default_value_t = 5, value_name = "SECS")]
+        wait: u64,
+    },
+    /// 输出 --symbol 市场的订单簿快照（REST），含累计数量、中间价与价差，无需私钥
+    Orderbook {
+        /// 每侧档位数（超出接口支持的 1..=100 时自动调整）
+        #[arg(long, default_value_t = 20)]
+        depth: u32,
+        /// 以 JSON 输出
+        #[arg(long, action)]
+        json: bool,
+    },
+    /// 输出账户信息、余额与持仓；或查询 / 更新账户资料
+    Account {
+        #[command(subcommand)]
+        action: Option<AccountCommand>,
+    },
+    /// 订阅账户与余额事件频道跟踪可用保证金、权益与已用保证金，结束时与新的 REST 快照对照输出
+    Balance {
+        /// 跟踪账户频道推送的秒数
+        #[arg(long, default_value_t = 10, value_name = "SECS")]
+        wait: u64,
+    },
+    /// 运行下单演示：onboarding、行情与私有频道订阅、下单 / 改单 / 撤单
+    Trade(Box<TradeArgs>),
+    /// 按 TWAP 计划把母单切分为子单逐个提交，结束时输出成交均价与各周期 VWAP 的对照（支持 --paper）
+    Twap(Box<TwapArgs>),
+    /// 按配置文件的 [grid] 段在 --trade-symbol 市场运行网格交易（支持 --paper），退出前撤销全部网格订单
+    Grid(Box<TradeArgs>),
+    /// 按配置文件的 [mm] 段在各市场中间价两侧维持一买一卖报价（支持 --paper），行情停滞或异常时撤下报价
+    Mm(Box<TradeArgs>),
+    /// 按配置文件的 [trailing] 段为 --trade-symbol 市场的已有持仓运行追踪止损，价格回撤穿过止损位时只减仓平仓
+    Trailing(Box<TradeArgs>),
+    /// 提交入场单，并按成交均价为已成交数量挂出只减仓的止盈与止损单，一方成交或持仓归零后撤销另一方（支持 --paper）
+    Bracket(Box<BracketArgs>),
+    /// 按订单 id 或 client_id 撤销单个挂单，并输出撤单后的状态
+    Cancel {
+        /// 交易所订单 id
+        #[arg(
+            long,
+            required_unless_present = "client_id",
+            conflicts_with = "client_id"
+        )]
+        id: Option<String>,
+        /// 下单时的 client_id（先在挂单中查找对应的订单 id）
+        #[arg(long)]
+        client_id: Option<String>,
+        /// 只在该市场的挂单中查找，如 BTC-USD-PERP
+        #[arg(long)]
+        market: Option<String>,
+    },
+    /// 撤销全部挂单
+    CancelAll {
+        /// 只撤销该市场的挂单，如 BTC-USD-PERP
+        #[arg(long)]
+        market: Option<String>,
+    },
+    /// 以只减仓订单平掉 --symbol 市场的持仓，并轮询直到持仓归零；已无持仓视为成功
+    ClosePosition {
+        /// 依次平掉全部持仓（不可与 --symbol 同时使用）
+        #[arg(long, action)]
+        all: bool,
+        /// 改用 IOC 限价单：在对手方最优价基础上向成交方向偏移的基点数（默认市价单）
+        #[arg(long, value_name = "BPS", value_parser = parse_positive_decimal)]
+        limit_slippage_bps: Option<Decimal>,
+        /// 下单后等待持仓归零的最长时间（秒）
+        #[arg(long, default_value_t = 30, value_name = "SECS")]
+        timeout: u64,
+    },
+    /// 在 --symbol 市场提交止损或止盈触发单；触发价须位于当前标记价格的正确一侧
+    Stop {
+        /// 触发单类型
+        #[arg(long, value_enum, default_value = "stop")]
+        kind: TriggerKind,
+        /// 订单方向
+        #[arg(long, value_enum)]
+        side: OrderSide,
+        /// 订单数量
+        #[arg(long, value_parser = parse_positive_decimal)]
+        size: Decimal,
+        /// 触发价
+        #[arg(long, value_parser = parse_positive_decimal)]
+        trigger: Decimal,
+        /// 触发后以该价格挂限价单（默认触发后以市价成交）
+        #[arg(long, value_parser = parse_positive_decimal)]
+        limit_price: Option<Decimal>,
+        /// 只减仓订单（止盈、止损）的数量超过持仓时缩减到持仓数量，而不是拒绝
+        #[arg(long, action)]
+        clamp: bool,
+        /// 确认该命令会在交易所下真实订单
+        #[arg(long = "i-know-this-places-orders", action)]
+        confirmed: bool,
+    },
+    /// 导出成交历史（可用 --symbol 过滤），用于对账与报税
+    Fills {
+        #[command(flatten)]
+        window: WindowArgs,
+        /// 输出格式
+        #[arg(long, value_enum, default_value = "csv")]
+        format: FillFormat,
+        /// 写入该文件（默认 stdout）
+        #[arg(long, value_name = "FILE")]
+        out: Option<PathBuf>,
+    },
+    /// 查询资金费率历史或账户的资金费支付
+    Funding {
+        #[command(subcommand)]
+        mode: FundingCommand,
+    },
+    /// 仅执行 onboarding 后退出，不创建 REST 客户端、不订阅、不下单
+    Onboard {
+        /// onboarding 后获取并打印 JWT token
+        #[arg(long, action)]
+        print_jwt: bool,
+        /// onboarding 时附带的推荐码
+        #[arg(long)]
+        referral_code: Option<String>,
+    },
+    /// 仅执行认证流程，将 JWT 及其过期时间输出到 stdout
+    Auth {
+        /// 以 JSON 输出：{"jwt_token": ..., "expires_at": ...}
+        #[arg(long, action)]
+        json: bool,
+        /// 只输出错误日志（屏蔽 TypedData 等调试输出）
+        #[arg(long, action)]
+        quiet: bool,
+    },
+    /// 管理系统钥匙串中的私钥
+    Secrets {
+        #[command(subcommand)]
+        action: SecretsCommand,
+    },
+}
+
+impl Command {
+    /// `trade`、`twap`、`grid`、`mm`、`trailing` 与 `bracket` 共用的下单参数
+    fn trade_args(&self) -> Option<&TradeArgs> {
+        match self {
+            Command::Trade(trade) => Some(trade),
+            Command::Twap(twap) => Some(&twap.trade),
+            Command::Grid(trade) => Some(trade),
+            Command::Mm(trade) => Some(trade),
+            Command::Trailing(trade) => Some(trade),
+            Command::Bracket(bracket) => Some(&bracket.trade),
+            _ => None,
+        }
+    }
+}
+
+/// 历史查询的时间范围
+#[derive(clap::Args, Debug)]
+struct WindowArgs {
+    /// 起始时间（RFC3339，如 2025-01-01T00:00:00Z）
+    #[arg(long, value_name = "TIME", value_parser = parse_rfc3339)]
+    from: Option<DateTime<Utc>>,
+    /// 结束时间（RFC3339）
+    #[arg(long, value_name = "TIME", value_parser = parse_rfc3339)]
+    to: Option<DateTime<Utc>>,
+}
+
+impl WindowArgs {
+    fn window(&self) -> TimeWindow {
+        TimeWindow {
+            from: self.from,
+            to: self.to,
+        }
+    }
+}
+
+#[derive(Subcommand, Debug)]
+enum FundingCommand {
+    /// --symbol 市场的历史资金费率，无需私钥
+    Rates {
+        #[command(flatten)]
+        window: WindowArgs,
+        /// 以 JSON 输出
+        #[arg(long, action)]
+        json: bool,
+    },
+    /// 账户的资金费支付明细与各市场合计（可用 --symbol 过滤）
+    Payments {
+        #[command(flatten)]
+        window: WindowArgs,
+        /// 以 JSON 输出
+        #[arg(long, action)]
+        json: bool,
+    },
+}
 
-            // 获取 JWT token
-            info!("Getting JWT token...");
-            match get_jwt_token(&http_client, base_url, starknet_addr, &private_key, &config).await
-            {
-                Ok(jwt) => info!("JWT token obtained: {}...", &jwt[..jwt.len().min(20)]),
-                Err(e) => warn!("Failed to get JWT token: {}", e),
+impl FundingCommand {
+    fn window(&self) -> TimeWindow {
+        match self {
+            FundingCommand::Rates { window, .. } | FundingCommand::Payments { window, .. } => {
+                window.window()
             }
-        } else {
-            warn!("Ethereum or StarkNet account not provided. Skipping onboarding.");
         }
+    }
+}
 
-        // 创建 Paradex 客户端
-        let client = Client::new(url, Some(private_key.clone())).await.unwrap();
+#[derive(Subcommand, Debug)]
+enum AccountCommand {
+    /// 以 JSON 输出账户资料
+    Profile,
+    /// 设置账户用户名
+    SetUsername { username: String },
+}
 
-        // 查询账户信息
-        info!(
-            "Account Information {:?}",
-            client.account_information().await
-        );
-        info!("Balance {:?}", client.balance().await);
-        info!("Positions {:?}", client.positions().await);
+#[derive(Subcommand, Debug)]
+enum SecretsCommand {
+    /// 交互式输入 Stark 私钥并保存到系统钥匙串（按 --production / --profile 区分）
+    Set,
+}
 
-        Some((client, private_key))
-    } else {
-        None
-    };
+fn parse_window(value: &str) -> Result<Duration, String> {
+    let interval = value.parse::<CandleInterval>()?;
+    Ok(Duration::from_millis(interval.as_millis()))
+}
 
-    // 创建 WebSocket 管理器
-    // 如果有私钥，传入认证客户端；否则使用 None（仅公开数据）
-    let manager = if let Some((ref client, _)) = client_private {
-        paradex::ws::WebsocketManager::new(url, Some(client.clone())).await
-    } else {
-        paradex::ws::WebsocketManager::new(url, None).await
-    };
+fn parse_jwt_expiry(value: &str) -> Result<u64, String> {
+    let secs = value.parse::<u64>().map_err(|e| e.to_string())?;
+    validate_jwt_expiry(secs).map_err(|e| e.to_string())?;
+    Ok(secs)
+}
 
-    // 订阅公开市场数据频道
-    let summary_id = manager
-        .subscribe(
-            paradex::ws::Channel::MarketSummary,
-            Box::new(|message| info!("Received MarketSummary message {message:?}")),
-        )
-        .await
-        .unwrap();
-
-    let bbo_id = manager
-        .subscribe(
-            paradex::ws::Channel::BBO {
-                market_symbol: symbol.clone(),
-            },
-            Box::new(|message| info!("Received BBO message {message:?}")),
-        )
-        .await
-        .unwrap();
-
-    let trades_id = manager
-        .subscribe(
-            paradex::ws::Channel::Trades {
-                market_symbol: symbol.clone(),
-            },
-            Box::new(|message| info!("Received Trades message {message:?}")),
-        )
-        .await
-        .unwrap();
-
-    let orderbook_id = manager
-        .subscribe(
-            paradex::ws::Channel::OrderBook {
-                channel_name: Some("orderbook".into()),
-                market_symbol: symbol.clone(),
-                refresh_rate: "50ms".into(),
-                price_tick: None,
-            },
-            Box::new(|message| info!("Received OrderBook message {message:?}")),
-        )
-        .await
-        .unwrap();
-
-    let orderbook_deltas_id = manager
-        .subscribe(
-            paradex::ws::Channel::OrderBookDeltas {
-                market_symbol: symbol.clone(),
-            },
-            Box::new(|message| info!("Received OrderBookDeltas message {message:?}")),
-        )
-        .await
-        .unwrap();
-
-    let funding_id = manager
-        .subscribe(
-            paradex::ws::Channel::FundingData {
-                market_symbol: None,
-            },
-            Box::new(|message| info!("Received FundingData message {message:?}")),
-        )
-        .await
-        .unwrap();
-
-    // 订阅私有频道（仅在提供私钥时可用）
-    let mut private_channel_ids = Vec::new();
-
-    if client_private.is_some() {
-        let orders_id = manager
-            .subscribe(
-                paradex::ws::Channel::Orders {
-                    market_symbol: None,
-                },
-                Box::new(|message| info!("Received order update {message:?}")),
-            )
-            .await
-            .unwrap();
-        private_channel_ids.push(orders_id);
-
-        let fills_id = manager
-            .subscribe(
-                paradex::ws::Channel::Fills {
-                    market_symbol: None,
-                },
-                Box::new(|message| info!("Received fill {message:?}")),
-            )
-            .await
-            .unwrap();
-        private_channel_ids.push(fills_id);
-
-        let position_id = manager
-            .subscribe(
-                paradex::ws::Channel::Position,
-                Box::new(|message| info!("Received position {message:?}")),
-            )
-            .await
-            .unwrap();
-        private_channel_ids.push(position_id);
-
-        let account_id = manager
-            .subscribe(
-                paradex::ws::Channel::Account,
-                Box::new(|message| info!("Received account {message:?}")),
-            )
-            .await
-            .unwrap();
-        private_channel_ids.push(account_id);
-
-        let balance_id = manager
-            .subscribe(
-                paradex::ws::Channel::BalanceEvents,
-                Box::new(|message| info!("Received balance event {message:?}")),
-            )
-            .await
-            .unwrap();
-        private_channel_ids.push(balance_id);
-
-        let funding_payments_id = manager
-            .subscribe(
-                paradex::ws::Channel::FundingPayments {
-                    market_symbol: None,
-                },
-                Box::new(|message| info!("Received funding payment {message:?}")),
-            )
-            .await
-            .unwrap();
-        private_channel_ids.push(funding_payments_id);
+#[tokio::main]
+async fn main() {
+    app::install_crypto_provider();
+    let args = Args::parse();
+    app::init(&args);
+
+    let settings = app::load_settings(&args).unwrap_or_else(|e| {
+        error!("{}", e);
+        std::process::exit(1);
+    });
+    info!("Effective configuration: {:?}", settings);
+    let config = app::paradex_config(&args, &settings);
+
+    if let Err(e) = app::check_env_credentials(&args) {
+        error!("{}", e);
+        std::process::exit(1);
     }
-
-    // 等待 WebSocket 连接建立
-    tokio::time::sleep(Duration::from_secs(2)).await;
-
-    // 如果有认证客户端，执行订单操作
-    if let Some((ref client, _)) = client_private {
-        // 创建订单
-        let order_request = OrderRequest {
-            instruction: paradex::structs::OrderInstruction::POST_ONLY,
-            market: symbol.clone(),
-            price: Decimal::from_f64(95000.0),
-            side: Side::BUY,
-            size: Decimal::from_f64(0.005).unwrap(),
-            order_type: OrderType::LIMIT,
-            client_id: Some("A".into()),
-            flags: vec![],
-            recv_window: None,
-            stp: None,
-            trigger_price: None,
-        };
-
-        info!("Sending order {order_request:?}");
-        let result = client.create_order(order_request).await.unwrap();
-        info!("Order result {result:?}");
-
-        tokio::time::sleep(Duration::from_secs(5)).await;
-
-        // 修改订单
-        let modify_request = ModifyOrderRequest {
-            id: result.id.clone(),
-            market: symbol.clone(),
-            price: Decimal::from_f64(92000.0),
-            side: Side::BUY,
-            size: Decimal::from_f64(0.005).unwrap(),
-            order_type: OrderType::LIMIT,
-        };
-
-        info!("Sending modify order {modify_request:?}");
-        let modify_result = client.modify_order(modify_request).await.unwrap();
-        info!("Modify order result {modify_result:?}");
-
-        tokio::time::sleep(Duration::from_secs(5)).await;
-
-        // 取消订单
-        info!(
-            "Cancel Order Result {:?}",
-            client.cancel_order(modify_result.id.clone()).await
-        );
-
-        info!(
-            "Cancel by market orders Result {:?}",
-            client.cancel_all_orders_for_market(symbol.clone()).await
-        );
-
-        info!(
-            "Cancel All Orders Result {:?}",
-            client.cancel_all_orders().await
-        );
+    if let Err(e) = app::check_channels(&args) {
+        error!("{}", e);
+        std::process::exit(1);
+    }
+    if let Err(e) = app::check_market_data_args(&args) {
+        error!("{}", e);
+        std::process::exit(1);
     }
 
-    // 等待一段时间接收市场数据
-    tokio::time::sleep(Duration::from_secs(120)).await;
-
-    // 取消所有订阅
-    let mut all_channel_ids = vec![
-        summary_id,
-        bbo_id,
-        trades_id,
-        orderbook_id,
-        orderbook_deltas_id,
-        funding_id,
-    ];
-    all_channel_ids.extend(private_channel_ids);
-
-    for id in all_channel_ids {
-        manager.unsubscribe(id).await.unwrap();
+    // 下单类子命令须显式确认（dry-run 与 --paper 除外）；确认与下单参数都在任何网络请求之前检查
+    let trade_job = app::trade::trade_job(&args, &settings).unwrap_or_else(|e| {
+        error!("{}", e);
+        std::process::exit(1);
+    });
+    match args.command {
+        Command::Fills { ref window, .. } => {
+            if let Err(e) = window.window().validate() {
+                error!("{}", e);
+                std::process::exit(1);
+            }
+        }
+        Command::Funding { ref mode } => {
+            if let Err(e) = mode.window().validate() {
+                error!("{}", e);
+                std::process::exit(1);
+            }
+        }
+        Command::Stop { confirmed, .. } => {
+            if args.symbols.len() != 1 {
+                error!("stop requires exactly one --symbol");
+                std::process::exit(1);
+            }
+            if !confirmed && !args.dry_run {
+                error!(
+                    "stop places real orders on {:?}; pass --i-know-this-places-orders to continue",
+                    settings.environment
+                );
+                std::process::exit(1);
+            }
+        }
+        Command::ClosePosition { all, .. } if all != args.symbols.is_empty() => {
+            error!("close-position requires either --symbol or --all (but not both)");
+            std::process::exit(1);
+        }
+        _ => {}
     }
 
-    tokio::time::sleep(Duration::from_secs(5)).await;
-    manager.stop().await.unwrap();
+    let metrics_server = match args.metrics_port {
+        Some(port) => match MetricsServer::start(port).await {
+            Ok(server) => Some(server),
+            Err(e) => {
+                error!("Failed to start metrics server on port {}: {}", port, e);
+                std::process::exit(1);
+            }
+        },
+        None => None,
+    };
+
+    let code = match args.command {
+        Command::Secrets {
+            action: SecretsCommand::Set,
+        } => run_secrets_set(&args, &settings),
+        Command::Stream { ref candles } => run_stream(&args, &config, &settings, candles).await,
+        Command::Markets { json } => run_markets(&config, &args.symbols, json).await,
+        Command::Summary { wait } => {
+            run_summary(&args, &config, &args.symbols, Duration::from_secs(wait)).await
+        }
+        Command::Orderbook { depth, json } => {
+            run_orderbook(&config, &settings.symbols, depth, json).await
+        }
+        Command::Funding {
+            mode: FundingCommand::Rates { ref window, json },
+        } => run_funding_rates(&config, &settings.symbols, window.window(), json).await,
+        ref command if command.trade_args().is_some_and(|trade| trade.paper) => {
+            let trade = command.trade_args().expect("checked by the guard");
+            let job = trade_job.expect("order parameters are validated before dispatch");
+            run_paper_trade(&args, trade, job, &settings, &config).await
+        }
+        ref command => {
+            let config = app::with_system_config(config).await;
+            let credentials = Credentials::load(&args, &config, settings.environment);
+            match command {
+                Command::Onboard {
+                    print_jwt,
+                    referral_code,
+                } => {
+                    let options = OnboardingOptions {
+                        referral_code: referral_code.clone(),
+                    };
+                    run_onboard(
+                        &config,
+                        credentials.private_key.as_ref(),
+                        credentials.starknet_account.as_deref(),
+                        credentials.eth_account.as_deref(),
+                        &options,
+                        *print_jwt,
+                    )
+                    .await
+                }
+                Command::Auth { json, .. } => {
+                    run_auth(
+                        &config,
+                        credentials.private_key.as_ref(),
+                        credentials.starknet_account.as_deref(),
+                        *json,
+                    )
+                    .await
+                }
+                Command::Account { action: None } => {
+                    run_account_summary(config.network, &credentials).await
+                }
+                Command::Balance { wait } => {
+                    run_balance(
+                        &args,
+                        &config,
+                        &settings,
+                        &credentials,
+                        Duration::from_secs(*wait),
+                    )
+                    .await
+                }
+                Command::Fills {
+                    window,
+                    format,
+                    out,
+                } => {
+                    run_fills(
+                        &config,
+                        &credentials,
+                        window.window(),
+                        &args.symbols,
+                        *format,
+                        out.as_deref(),
+                    )
+                    .await
+                }
+                Command::Funding {
+                    mode: FundingCommand::Payments { window, json },
+                } => {
+                    run_funding_payments(
+                        &config,
+                        &credentials,
+                        &args.symbols,
+                        window.window(),
+                        *json,
+                    )
+                    .await
+                }
+                Command::Cancel {
+                    id,
+                    client_id,
+                    market,
+                } => {
+                    let target = match (id, client_id) {
+                        (Some(id), _) => OrderTarget::Id(id.clone()),
+                        (None, Some(client_id)) => OrderTarget::ClientId(client_id.clone()),
+                        (None, None) => unreachable!("clap requires --id or --client-id"),
+                    };
+                    run_cancel(
+                        config.network,
+                        &credentials,
+                        target,
+                        market.as_deref(),
+                        args.dry_run,
+                    )
+                    .await
+                }
+                Command::CancelAll { market } if args.dry_run => {
+                    run_cancel_all_dry_run(&credentials, market.clone()).await
+                }
+                Command::Account { .. } | Command::CancelAll { .. } => {
+                    run_rest_command(
+                        &config,
+                        credentials.private_key.as_ref(),
+                        credentials.starknet_account.as_deref(),
+                        command,
+                    )
+                    .await
+                }
+                Command::ClosePosition {
+                    all,
+                    limit_slippage_bps,
+                    timeout,
+                } => {
+                    let options = CloseOptions {
+                        slippage_bps: *limit_slippage_bps,
+                        timeout: Duration::from_secs(*timeout),
+                        poll_interval: CLOSE_POLL_INTERVAL,
+                        dry_run: args.dry_run,
+                    };
+                    app::check_clock_drift(&config).await;
+                    run_close_position(
+                        &config,
+                        &credentials,
+                        &args.symbols,
+                        *all,
+                        settings.risk.clone(),
+                        options,
+                    )
+                    .await
+                }
+                Command::Stop {
+                    kind,
+                    side,
+                    size,
+                    trigger,
+                    limit_price,
+                    clamp,
+                    ..
+                } => {
+                    let order = TriggerOrder {
+                        kind: *kind,
+                        side: side.to_side(),
+                        size: *size,
+                        trigger: *trigger,
+                        limit_price: *limit_price,
+                    };
+                    app::check_clock_drift(&config).await;
+                    run_stop(
+                        &config,
+                        &credentials,
+                        &args.symbols[0],
+                        order,
+                        *clamp,
+                        settings.risk.clone(),
+                        args.dry_run,
+                    )
+                    .await
+                }
+                Command::Trade(_)
+                | Command::Twap(_)
+                | Command::Grid(_)
+                | Command::Mm(_)
+                | Command::Trailing(_)
+                | Command::Bracket(_) => {
+                    let trade = command.trade_args().expect(
+                        "trade, twap, grid, mm, trailing and bracket carry order parameters",
+                    );
+                    let job = trade_job.expect("order parameters are validated before dispatch");
+                    app::check_clock_drift(&config).await;
+                    run_trade(&args, trade, job, &settings, &config, &credentials).await
+                }
+                Command::Stream { .. }
+                | Command::Markets { .. }
+                | Command::Summary { .. }
+                | Command::Orderbook { .. }
+                | Command::Funding {
+                    mode: FundingCommand::Rates { .. },
+                }
+                | Command::Secrets { .. } => {
+                    unreachable!()
+                }
+            }
+        }
+    };
+    if let Some(server) = metrics_server {
+        server.stop().await;
+    }
+    if args.latency_report {
+        println!("{}", latency().report());
+    }
+    std::process::exit(code);
 }
@@ -0,0 +1,148 @@
+//! 行情来源：实时 WebSocket 或录制文件重放，对订阅回调提供相同的接口；
+//! [`SubscriptionHub`] 在其上提供流式订阅
+
+mod audit;
+mod bbo_cache;
+mod bbo_dedup;
+mod candles;
+mod dispatch;
+mod events;
+mod guard;
+mod hub;
+mod order_book;
+mod raw;
+mod reconnect;
+mod registry;
+mod signals;
+mod summary_cache;
+mod symbols;
+mod trade_tape;
+mod watchdog;
+
+pub use audit::{
+    compare_with_snapshot, AuditOutcome, BookAuditor, LevelMismatch, AUDIT_STRIKES,
+    DEFAULT_AUDIT_LEVELS,
+};
+pub use bbo_cache::{BboCache, Quote};
+pub use bbo_dedup::BboDedup;
+pub use candles::{Candle, CandleBuilder, CandleCallback, CandleInterval};
+pub use dispatch::{
+    ChannelCounts, DispatchCounters, Dispatching, OverflowPolicy, DEFAULT_DISPATCH_CAPACITY,
+};
+pub use events::{
+    BboEvent, BookEvent, EventBus, EventHandler, EventReceiver, FundingEvent, MarketEvent,
+    SummaryEvent, TradeEvent, DEFAULT_EVENT_CAPACITY,
+};
+pub use guard::{CallbackPanic, GuardedSource, PanicHandler, DEFAULT_MAX_CONSECUTIVE_PANICS};
+pub use hub::{ChannelMessage, MessageStream, SubscriptionHub, DEFAULT_STREAM_CAPACITY};
+pub use order_book::{
+    Anomaly, AnomalyCallback, AnomalyKind, ApplyOutcome, ExecEstimate, LocalOrderBook, OrderBooks,
+    SharedOrderBook,
+};
+pub use raw::{
+    parse_notification, RawCallback, RawChannels, RawMessage, TradeBust, Transfer,
+    TransferDirection, TRADE_BUSTS, TRANSFERS,
+};
+pub use reconnect::{Connector, LiveConnector, ReconnectHook, ReconnectPolicy, Reconnecting};
+pub use registry::{SubscriptionRegistry, UnsubscribeError};
+pub use signals::{imbalance, microprice, BookSignals, Signals};
+pub use summary_cache::{format_summary_table, MarketSummaryCache, SummaryEntry};
+pub use symbols::{CandleSink, SymbolSubscriptions, SYMBOL_CHANNELS};
+pub use trade_tape::{window_label, TapeTrade, TradeTape, WindowStats, DEFAULT_TAPE_WINDOWS};
+pub use watchdog::{Clock, FeedCallback, FeedStatus, FeedWatchdog, WatchedSource};
+
+use async_trait::async_trait;
+use paradex::{
+    error::Error,
+    ws::{Channel, Identifier, Message, WebsocketManager},
+};
+
+use crate::config::WsChannel;
+
+/// 订阅回调，与 `WebsocketManager::subscribe` 的回调类型一致
+pub type Callback = Box<dyn Fn(&Message) + Send + 'static>;
+
+/// 订阅对应的频道与市场；市场为 `None` 表示该频道的全部市场
+pub fn channel_key(channel: &Channel) -> (WsChannel, Option<String>) {
+    match channel {
+        Channel::MarketSummary => (WsChannel::MarketsSummary, None),
+        Channel::BBO { market_symbol } => (WsChannel::Bbo, Some(market_symbol.clone())),
+        Channel::Trades { market_symbol } => (WsChannel::Trades, Some(market_symbol.clone())),
+        Channel::OrderBook { market_symbol, .. } => {
+            (WsChannel::OrderBook, Some(market_symbol.clone()))
+        }
+        Channel::OrderBookDeltas { market_symbol } => {
+            (WsChannel::OrderBookDeltas, Some(market_symbol.clone()))
+        }
+        Channel::FundingData { market_symbol } => (WsChannel::FundingData, market_symbol.clone()),
+        Channel::Orders { market_symbol } => (WsChannel::Orders, market_symbol.clone()),
+        Channel::Fills { market_symbol } => (WsChannel::Fills, market_symbol.clone()),
+        Channel::Position => (WsChannel::Positions, None),
+        Channel::Account => (WsChannel::Account, None),
+        Channel::BalanceEvents => (WsChannel::BalanceEvents, None),
+        Channel::FundingPayments { market_symbol } => {
+            (WsChannel::FundingPayments, market_symbol.clone())
+        }
+    }
+}
+
+/// 订阅 ID，用于取消订阅
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SubscriptionId {
+    Live(Identifier),
+    Replay(u64),
+    /// [`Reconnecting`] 分配的 ID，重连后保持不变
+    Reconnecting(u64),
+}
+
+/// 行情与账户频道的订阅入口；回调无法区分消息来自实时连接还是重放
+#[async_trait]
+pub trait MarketDataSource: Send + Sync {
+    async fn subscribe(
+        &self,
+        channel: Channel,
+        callback: Callback,
+    ) -> Result<SubscriptionId, Error>;
+
+    async fn unsubscribe(&self, id: SubscriptionId) -> Result<(), Error>;
+
+    /// 停止推送消息
+    async fn stop(&self) -> Result<(), Error>;
+}
+
+/// 通过 Paradex WebSocket 实时订阅
+pub struct Live {
+    manager: WebsocketManager,
+}
+
+impl Live {
+    /// 通过 `manager` 订阅
+    pub fn new(manager: WebsocketManager) -> Self {
+        Self { manager }
+    }
+}
+
+#[async_trait]
+impl MarketDataSource for Live {
+    async fn subscribe(
+        &self,
+        channel: Channel,
+        callback: Callback,
+    ) -> Result<SubscriptionId, Error> {
+        self.manager
+            .subscribe(channel, callback)
+            .await
+            .map(SubscriptionId::Live)
+    }
+
+    async fn unsubscribe(&self, id: SubscriptionId) -> Result<(), Error> {
+        match id {
+            SubscriptionId::Live(id) => self.manager.unsubscribe(id).await,
+            _ => Ok(()),
+        }
+    }
+
+    async fn stop(&self) -> Result<(), Error> {
+        self.manager.stop().await
+    }
+}
@@ -0,0 +1,405 @@
+//! 定期以 REST 快照交叉校验本地订单簿
+//!
+//! 对每本已同步的订单簿拉取 REST 深度快照，比较前 K 档的价格与数量。快照序号之后
+//! 已应用的增量改动过的档位视为在途更新，不计入差异；连续多次审计发现差异时强制
+//! 重新同步。审计在独立任务中运行，只在比较时短暂持有读锁，不阻塞增量的应用。
+
+use log::{debug, info, warn};
+use paradex::structs::Side;
+use rust_decimal::Decimal;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+use super::{LocalOrderBook, OrderBooks};
+use crate::metrics::metrics;
+use crate::orderbook::{clamp_depth, Level, OrderBookSnapshot};
+
+/// 默认比较的档位数
+pub const DEFAULT_AUDIT_LEVELS: usize = 10;
+/// 连续发现差异达到该次数时强制重新同步
+pub const AUDIT_STRIKES: u32 = 2;
+/// 本地订单簿落后于快照时最多等待的时间
+const CATCH_UP_TIMEOUT: Duration = Duration::from_secs(2);
+const CATCH_UP_POLL: Duration = Duration::from_millis(20);
+
+/// 一个价格档位上本地与快照的数量不一致；缺失的档位数量记为零
+#[derive(Debug, Clone, PartialEq)]
+pub struct LevelMismatch {
+    pub side: Side,
+    pub price: Decimal,
+    pub local: Decimal,
+    pub snapshot: Decimal,
+}
+
+impl fmt::Display for LevelMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let side = match self.side {
+            Side::BUY => "bid",
+            Side::SELL => "ask",
+        };
+        write!(
+            f,
+            "{} {}: local {} vs snapshot {}",
+            side, self.price, self.local, self.snapshot
+        )
+    }
+}
+
+/// 一次审计的结果
+#[derive(Debug, Clone, PartialEq)]
+pub enum AuditOutcome {
+    Consistent,
+    Diverged(Vec<LevelMismatch>),
+    /// 无法比较（订单簿未同步、落后于快照等）
+    Skipped(&'static str),
+}
+
+/// 比较 `book` 与 `snapshot` 每侧的前 `levels` 档；本地订单簿须已追上快照的序号
+pub fn compare_with_snapshot(
+    book: &LocalOrderBook,
+    snapshot: &OrderBookSnapshot,
+    levels: usize,
+) -> AuditOutcome {
+    if !book.is_synced() {
+        return AuditOutcome::Skipped("order book not synced");
+    }
+    let Some(snapshot_seq) = snapshot.seq_no else {
+        return AuditOutcome::Skipped("snapshot without sequence number");
+    };
+    if book.seq_no().is_some_and(|seq_no| seq_no < snapshot_seq) {
+        return AuditOutcome::Skipped("order book behind the snapshot");
+    }
+    let Some(touched) = book.touched_since(snapshot_seq) else {
+        return AuditOutcome::Skipped("snapshot older than the delta history");
+    };
+    let (bids, asks) = book.depth(levels);
+    let mut snapshot_bids = snapshot.bids.clone();
+    snapshot_bids.sort_by_key(|level| std::cmp::Reverse(level.price()));
+    let mut snapshot_asks = snapshot.asks.clone();
+    snapshot_asks.sort_by_key(|level| level.price());
+
+    let mut mismatches = Vec::new();
+    for (side, local, remote) in [
+        (Side::BUY, bids, snapshot_bids),
+        (Side::SELL, asks, snapshot_asks),
+    ] {
+        let local = nonzero(&local, levels);
+        let remote = nonzero(&remote, levels);
+        // 只有两边都完整覆盖的价格区间可以比较：取两边第 K 档中更靠近盘口的价格为界
+        let bound = [&local, &remote]
+            .into_iter()
+            .filter(|side_levels| side_levels.len() == levels)
+            .filter_map(|side_levels| side_levels.last().map(Level::price))
+            .reduce(|a, b| match side {
+                Side::BUY => a.max(b),
+                Side::SELL => a.min(b),
+            });
+        let within = |price: Decimal| match (side, bound) {
+            (_, None) => true,
+            (Side::BUY, Some(bound)) => price >= bound,
+            (Side::SELL, Some(bound)) => price <= bound,
+        };
+        let prices: BTreeSet<Decimal> = local
+            .iter()
+            .chain(&remote)
+            .map(Level::price)
+            .filter(|price| within(*price))
+            .collect();
+        for price in prices {
+            if touched.contains(&(side, price)) {
+                continue;
+            }
+            let size = |levels: &[Level]| {
+                levels
+                    .iter()
+                    .find(|level| level.price() == price)
+                    .map_or(Decimal::ZERO, Level::size)
+            };
+            let (local, snapshot) = (size(&local), size(&remote));
+            if local != snapshot {
+                mismatches.push(LevelMismatch {
+                    side,
+                    price,
+                    local,
+                    snapshot,
+                });
+            }
+        }
+    }
+    if mismatches.is_empty() {
+        AuditOutcome::Consistent
+    } else {
+        AuditOutcome::Diverged(mismatches)
+    }
+}
+
+/// 前 `levels` 个数量不为零的档位
+fn nonzero(levels: &[Level], n: usize) -> Vec<Level> {
+    levels
+        .iter()
+        .filter(|level| !level.size().is_zero())
+        .take(n)
+        .cloned()
+        .collect()
+}
+
+/// 定期审计 [`OrderBooks`] 管理的订单簿
+#[derive(Debug)]
+pub struct BookAuditor {
+    books: OrderBooks,
+    levels: usize,
+    /// 每个市场连续发现差异的次数
+    strikes: HashMap<String, u32>,
+}
+
+impl BookAuditor {
+    /// 以默认档数核对 `books`
+    pub fn new(books: OrderBooks) -> Self {
+        Self {
+            books,
+            levels: DEFAULT_AUDIT_LEVELS,
+            strikes: HashMap::new(),
+        }
+    }
+
+    /// 每侧比较的档位数
+    pub fn with_levels(mut self, levels: usize) -> Self {
+        self.levels = levels.max(1);
+        self
+    }
+
+    /// 审计 `market` 的订单簿；连续 [`AUDIT_STRIKES`] 次发现差异时强制重新同步
+    pub async fn audit_market(&mut self, market: &str) -> AuditOutcome {
+        let Some(book) = self.books.get(market) else {
+            return AuditOutcome::Skipped("market not maintained");
+        };
+        if !book.read().unwrap().is_synced() {
+            return AuditOutcome::Skipped("order book not synced");
+        }
+        let (depth, _) = clamp_depth(self.levels as u32);
+        let snapshot = match self.books.fetch_snapshot(market, depth).await {
+            Some(Ok(snapshot)) => snapshot,
+            Some(Err(e)) => {
+                warn!(
+                    "Order book audit failed to fetch {} snapshot: {}",
+                    market, e
+                );
+                return AuditOutcome::Skipped("snapshot request failed");
+            }
+            None => return AuditOutcome::Skipped("no REST snapshot source"),
+        };
+        // 快照可能领先于频道推送的增量，等待本地订单簿追上
+        if let Some(snapshot_seq) = snapshot.seq_no {
+            let deadline = tokio::time::Instant::now() + CATCH_UP_TIMEOUT;
+            while book
+                .read()
+                .unwrap()
+                .seq_no()
+                .is_some_and(|seq_no| seq_no < snapshot_seq)
+                && tokio::time::Instant::now() < deadline
+            {
+                tokio::time::sleep(CATCH_UP_POLL).await;
+            }
+        }
+        let outcome = compare_with_snapshot(&book.read().unwrap(), &snapshot, self.levels);
+        match &outcome {
+            AuditOutcome::Consistent => {
+                if self.strikes.remove(market).is_some() {
+                    info!("{} order book matches the REST snapshot again", market);
+                }
+            }
+            AuditOutcome::Diverged(mismatches) => {
+                metrics().orderbook_divergence();
+                let strikes = self.strikes.entry(market.to_string()).or_default();
+                *strikes += 1;
+                let details: Vec<_> = mismatches.iter().map(ToString::to_string).collect();
+                warn!(
+                    "{} order book diverges from the REST snapshot at seq {:?} ({}/{}): {}",
+                    market,
+                    snapshot.seq_no,
+                    strikes,
+                    AUDIT_STRIKES,
+                    details.join("; ")
+                );
+                if *strikes >= AUDIT_STRIKES {
+                    warn!(
+                        "Forcing {} order book resync after persistent divergence",
+                        market
+                    );
+                    self.strikes.remove(market);
+                    self.books.resync(market);
+                }
+            }
+            AuditOutcome::Skipped(reason) => {
+                debug!("Skipped {} order book audit: {}", market, reason)
+            }
+        }
+        outcome
+    }
+
+    /// 依次审计全部市场
+    pub async fn audit_all(&mut self) {
+        for market in self.books.markets() {
+            self.audit_market(&market).await;
+        }
+    }
+
+    /// 每隔 `interval` 审计一次，直到任务被终止
+    pub fn spawn(mut self, interval: Duration) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(interval).await;
+                self.audit_all().await;
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::onboarding::mock_server::MockServer;
+    use paradex::structs::{Level as DeltaLevel, OrderBook, OrderBookUpdateType};
+
+    const MARKET: &str = "BTC-USD-PERP";
+
+    fn levels(levels: &[(i64, i64)]) -> Vec<Level> {
+        levels
+            .iter()
+            .map(|&(price, size)| Level(Decimal::from(price), Decimal::from(size)))
+            .collect()
+    }
+
+    fn snapshot(seq_no: u64, bids: &[(i64, i64)], asks: &[(i64, i64)]) -> OrderBookSnapshot {
+        OrderBookSnapshot {
+            market: MARKET.to_string(),
+            seq_no: Some(seq_no),
+            last_updated_at: None,
+            bids: levels(bids),
+            asks: levels(asks),
+        }
+    }
+
+    fn delta(seq_no: u64, side: Side, price: f64, size: f64) -> OrderBook {
+        OrderBook {
+            seq_no,
+            market: MARKET.to_string(),
+            last_updated_at: 1735689600000 + seq_no,
+            update_type: OrderBookUpdateType::Delta,
+            deletes: vec![],
+            inserts: vec![],
+            updates: vec![DeltaLevel { side, price, size }],
+        }
+    }
+
+    fn seeded(snapshot: &OrderBookSnapshot) -> LocalOrderBook {
+        let mut book = LocalOrderBook::new(MARKET);
+        assert!(book.seed(snapshot));
+        book
+    }
+
+    #[test]
+    fn levels_updated_after_the_snapshot_are_in_flight() {
+        let rest = snapshot(10, &[(100, 2), (99, 1)], &[(101, 1), (102, 3)]);
+        let mut book = seeded(&rest);
+        book.apply(&delta(11, Side::BUY, 100.0, 7.0));
+        book.apply(&delta(12, Side::SELL, 101.5, 4.0));
+        assert_eq!(
+            compare_with_snapshot(&book, &rest, 10),
+            AuditOutcome::Consistent
+        );
+
+        // 快照之前的改动不算在途
+        let newer = snapshot(11, &[(100, 2), (99, 1)], &[(101, 1), (102, 3)]);
+        assert_eq!(
+            compare_with_snapshot(&book, &newer, 10),
+            AuditOutcome::Diverged(vec![LevelMismatch {
+                side: Side::BUY,
+                price: Decimal::from(100),
+                local: Decimal::from(7),
+                snapshot: Decimal::from(2),
+            }])
+        );
+    }
+
+    #[test]
+    fn only_levels_within_both_top_k_are_compared() {
+        let rest = snapshot(10, &[(100, 2), (99, 1), (98, 5)], &[(101, 1), (103, 3)]);
+        let mut local = snapshot(10, &[(100, 2), (99, 1)], &[(101, 1), (102, 9), (103, 3)]);
+        local.bids.push(Level(Decimal::from(97), Decimal::ONE));
+        let book = seeded(&local);
+        // 买盘第 2 档以内一致；卖盘 102 在两边的前 2 档内却只存在于本地
+        assert_eq!(
+            compare_with_snapshot(&book, &rest, 2),
+            AuditOutcome::Diverged(vec![LevelMismatch {
+                side: Side::SELL,
+                price: Decimal::from(102),
+                local: Decimal::from(9),
+                snapshot: Decimal::ZERO,
+            }])
+        );
+        assert_eq!(
+            compare_with_snapshot(&book, &snapshot(9, &[], &[]), 2),
+            AuditOutcome::Skipped("snapshot older than the delta history")
+        );
+        assert_eq!(
+            compare_with_snapshot(&book, &snapshot(11, &[], &[]), 2),
+            AuditOutcome::Skipped("order book behind the snapshot")
+        );
+    }
+
+    #[tokio::test]
+    async fn persistent_divergence_forces_a_resync() {
+        let body = r#"{"market":"BTC-USD-PERP","seq_no":10,"bids":[["100","2"],["99","1"]],"asks":[["101","1"]]}"#;
+        let server = MockServer::start(move |_| (200, body.to_string())).await;
+        let books = OrderBooks::new(&[MARKET.to_string()]).with_rest_resync(&server.url());
+        let shared = books.get(MARKET).unwrap();
+        // 本地订单簿在同一序号上数量被篡改
+        *shared.write().unwrap() = seeded(&snapshot(10, &[(100, 5), (99, 1)], &[(101, 1)]));
+
+        let mut auditor = BookAuditor::new(books.clone()).with_levels(5);
+        let first = auditor.audit_market(MARKET).await;
+        assert!(
+            matches!(first, AuditOutcome::Diverged(ref m) if m.len() == 1),
+            "{first:?}"
+        );
+        assert_eq!(
+            shared.read().unwrap().depth(1).0[0].size(),
+            Decimal::from(5)
+        );
+        assert!(matches!(
+            auditor.audit_market(MARKET).await,
+            AuditOutcome::Diverged(_)
+        ));
+        assert!(metrics()
+            .render()
+            .contains("# TYPE trade_lighter_orderbook_divergences_total counter"));
+
+        for _ in 0..50 {
+            {
+                let book = shared.read().unwrap();
+                if book.is_synced() && book.depth(1).0[0].size() == Decimal::from(2) {
+                    break;
+                }
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert_eq!(
+            shared.read().unwrap().depth(1).0[0].size(),
+            Decimal::from(2)
+        );
+        assert_eq!(auditor.audit_market(MARKET).await, AuditOutcome::Consistent);
+        let paths: Vec<_> = server.requests().into_iter().map(|r| r.path).collect();
+        assert_eq!(
+            paths,
+            [
+                "/orderbook/BTC-USD-PERP?depth=5",
+                "/orderbook/BTC-USD-PERP?depth=5",
+                "/orderbook/BTC-USD-PERP?depth=100",
+                "/orderbook/BTC-USD-PERP?depth=5",
+            ]
+        );
+    }
+}
@@ -0,0 +1,220 @@
+//! 按市场缓存最新 BBO，供策略直接读取最优价而无需解析每条 WebSocket 消息
+
+use chrono::{DateTime, TimeZone, Utc};
+use paradex::{structs::BBO, ws::Message};
+use rust_decimal::Decimal;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use super::{BboEvent, MarketEvent};
+use crate::markets::decimal;
+
+/// 某个市场的最新报价
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Quote {
+    pub bid: Decimal,
+    pub bid_size: Decimal,
+    pub ask: Decimal,
+    pub ask_size: Decimal,
+    /// 交易所更新时间
+    pub exchange_ts: DateTime<Utc>,
+    /// 本地收到的时间
+    pub local_ts: DateTime<Utc>,
+}
+
+impl Quote {
+    /// 买卖价任一缺失（为 0）时返回 `None`
+    pub fn mid(&self) -> Option<Decimal> {
+        crate::risk::mid(self.bid, self.ask)
+    }
+
+    /// 买卖价差（相对中间价的基点）
+    pub fn spread_bps(&self) -> Option<Decimal> {
+        let mid = self.mid()?;
+        Some(((self.ask - self.bid) / mid * Decimal::from(10_000)).round_dp(2))
+    }
+
+    /// 距本地收到报价的时长
+    pub fn age(&self) -> Duration {
+        self.age_at(Utc::now())
+    }
+
+    fn age_at(&self, now: DateTime<Utc>) -> Duration {
+        (now - self.local_ts).to_std().unwrap_or_default()
+    }
+}
+
+/// 各市场最新报价，克隆后共享同一份数据
+#[derive(Debug, Clone, Default)]
+pub struct BboCache {
+    quotes: Arc<RwLock<HashMap<String, Quote>>>,
+}
+
+impl BboCache {
+    /// 空缓存
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 处理 BBO 频道的消息，其他消息忽略
+    pub fn on_message(&self, message: &Message) {
+        if let Message::BBO(bbo) = message {
+            self.update(bbo);
+        }
+    }
+
+    /// 处理行情事件总线上的 BBO 事件，其他事件忽略
+    pub fn on_event(&self, event: &MarketEvent) {
+        if let MarketEvent::Bbo(bbo) = event {
+            self.store(bbo);
+        }
+    }
+
+    /// 缓存 `bbo` 并返回此前的报价
+    pub fn store(&self, bbo: &BboEvent) -> Option<Quote> {
+        let quote = Quote {
+            bid: bbo.bid,
+            bid_size: bbo.bid_size,
+            ask: bbo.ask,
+            ask_size: bbo.ask_size,
+            exchange_ts: bbo.exchange_ts,
+            local_ts: bbo.local_ts,
+        };
+        self.quotes
+            .write()
+            .unwrap()
+            .insert(bbo.symbol.clone(), quote)
+    }
+
+    /// 以本地当前时间缓存 `bbo`
+    pub fn update(&self, bbo: &BBO) {
+        self.update_at(bbo, Utc::now());
+    }
+
+    fn update_at(&self, bbo: &BBO, local_ts: DateTime<Utc>) {
+        let quote = Quote {
+            bid: decimal(bbo.bid),
+            bid_size: decimal(bbo.bid_size),
+            ask: decimal(bbo.ask),
+            ask_size: decimal(bbo.ask_size),
+            exchange_ts: Utc
+                .timestamp_millis_opt(bbo.last_updated_at as i64)
+                .single()
+                .unwrap_or_default(),
+            local_ts,
+        };
+        self.quotes
+            .write()
+            .unwrap()
+            .insert(bbo.market.clone(), quote);
+    }
+
+    /// 丢弃 `symbol` 的报价
+    pub fn remove(&self, symbol: &str) -> Option<Quote> {
+        self.quotes.write().unwrap().remove(symbol)
+    }
+
+    /// `symbol` 的最新报价
+    pub fn get(&self, symbol: &str) -> Option<Quote> {
+        self.quotes.read().unwrap().get(symbol).cloned()
+    }
+
+    /// 收到时间不超过 `max_age` 的报价
+    pub fn fresh(&self, symbol: &str, max_age: Duration) -> Option<Quote> {
+        self.fresh_at(symbol, max_age, Utc::now())
+    }
+
+    fn fresh_at(&self, symbol: &str, max_age: Duration, now: DateTime<Utc>) -> Option<Quote> {
+        self.get(symbol)
+            .filter(|quote| quote.age_at(now) <= max_age)
+    }
+
+    /// `symbol` 的中间价
+    pub fn mid(&self, symbol: &str) -> Option<Decimal> {
+        self.get(symbol)?.mid()
+    }
+
+    /// `symbol` 的买卖价差（基点）
+    pub fn spread_bps(&self, symbol: &str) -> Option<Decimal> {
+        self.get(symbol)?.spread_bps()
+    }
+
+    /// 距最近一次收到 `symbol` 报价的时长；未收到过时返回 `None`
+    pub fn age(&self, symbol: &str) -> Option<Duration> {
+        Some(self.get(symbol)?.age())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeDelta;
+
+    fn bbo(market: &str, bid: f64, ask: f64) -> BBO {
+        BBO {
+            bid,
+            bid_size: 1.5,
+            ask,
+            ask_size: 0.5,
+            market: market.to_string(),
+            last_updated_at: 1735689600000,
+        }
+    }
+
+    #[test]
+    fn caches_latest_quote_per_symbol() {
+        let cache = BboCache::new();
+        let shared = cache.clone();
+        cache.on_message(&Message::BBO(bbo("BTC-USD-PERP", 94990.0, 95010.0)));
+        cache.on_message(&Message::BBO(bbo("ETH-USD-PERP", 3300.0, 3301.0)));
+        cache.on_message(&Message::BBO(bbo("BTC-USD-PERP", 95000.0, 95020.0)));
+        cache.on_message(&Message::Connected);
+
+        let quote = shared.get("BTC-USD-PERP").unwrap();
+        assert_eq!(quote.bid, Decimal::from(95000));
+        assert_eq!(quote.bid_size, Decimal::new(15, 1));
+        assert_eq!(quote.ask, Decimal::from(95020));
+        assert_eq!(quote.ask_size, Decimal::new(5, 1));
+        assert_eq!(quote.exchange_ts.timestamp_millis(), 1735689600000);
+        assert_eq!(shared.mid("BTC-USD-PERP"), Some(Decimal::from(95010)));
+        assert_eq!(
+            shared.spread_bps("ETH-USD-PERP"),
+            Some(Decimal::new(303, 2))
+        );
+        assert!(shared.get("SOL-USD-PERP").is_none());
+        assert!(shared.mid("SOL-USD-PERP").is_none());
+    }
+
+    #[test]
+    fn empty_side_has_no_mid() {
+        let cache = BboCache::new();
+        cache.update(&bbo("BTC-USD-PERP", 0.0, 95010.0));
+        assert!(cache.get("BTC-USD-PERP").is_some());
+        assert_eq!(cache.mid("BTC-USD-PERP"), None);
+        assert_eq!(cache.spread_bps("BTC-USD-PERP"), None);
+    }
+
+    #[test]
+    fn stale_quotes_are_rejected() {
+        let cache = BboCache::new();
+        let received = Utc::now();
+        cache.update_at(&bbo("BTC-USD-PERP", 95000.0, 95010.0), received);
+        let max_age = Duration::from_secs(5);
+
+        let later = received + TimeDelta::seconds(3);
+        assert_eq!(
+            cache.get("BTC-USD-PERP").unwrap().age_at(later),
+            Duration::from_secs(3)
+        );
+        assert!(cache.fresh_at("BTC-USD-PERP", max_age, later).is_some());
+
+        let too_late = received + TimeDelta::seconds(6);
+        assert!(cache.fresh_at("BTC-USD-PERP", max_age, too_late).is_none());
+
+        // 新报价刷新收到时间
+        cache.update_at(&bbo("BTC-USD-PERP", 95001.0, 95011.0), too_late);
+        let quote = cache.fresh_at("BTC-USD-PERP", max_age, too_late).unwrap();
+        assert_eq!(quote.bid, Decimal::from(95001));
+    }
+}
@@ -0,0 +1,133 @@
+//! BBO 去重：交易所在报价未变化时也会重复推送 BBO，与缓存中的上一笔报价比较后
+//! 只传递买卖价或数量确有变化的报价，避免下游反复重新报价与记录日志
+//!
+//! 每笔收到的报价都会写入缓存（包括本地接收时间），因此缓存的新鲜度不受去重影响。
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use super::{BboCache, BboEvent};
+use crate::metrics::metrics;
+
+#[derive(Debug, Default)]
+struct Counts {
+    received: AtomicU64,
+    propagated: AtomicU64,
+}
+
+/// 按市场比较相邻两笔报价；克隆后共享同一份缓存与计数
+#[derive(Debug, Clone, Default)]
+pub struct BboDedup {
+    quotes: BboCache,
+    ignore_size_changes: bool,
+    counts: Arc<Counts>,
+}
+
+impl BboDedup {
+    /// 以 `quotes` 中的报价为比较基准，并把收到的报价写入其中
+    pub fn new(quotes: BboCache) -> Self {
+        Self {
+            quotes,
+            ..Self::default()
+        }
+    }
+
+    /// 为 `true` 时只有买卖价变化才传递，仅数量变化的报价也视为重复
+    pub fn with_ignore_size_changes(mut self, ignore: bool) -> Self {
+        self.ignore_size_changes = ignore;
+        self
+    }
+
+    /// 全部收到的报价（含被过滤的）
+    pub fn quotes(&self) -> &BboCache {
+        &self.quotes
+    }
+
+    /// 缓存 `bbo`，返回是否应传递给下游（该市场的第一笔报价总是传递）
+    pub fn accept(&self, bbo: &BboEvent) -> bool {
+        self.counts.received.fetch_add(1, Ordering::Relaxed);
+        metrics().bbo_received();
+        let changed = match self.quotes.store(bbo) {
+            None => true,
+            Some(previous) => {
+                previous.bid != bbo.bid
+                    || previous.ask != bbo.ask
+                    || (!self.ignore_size_changes
+                        && (previous.bid_size != bbo.bid_size || previous.ask_size != bbo.ask_size))
+            }
+        };
+        if changed {
+            self.counts.propagated.fetch_add(1, Ordering::Relaxed);
+            metrics().bbo_propagated();
+        }
+        changed
+    }
+
+    /// 收到的报价数
+    pub fn received(&self) -> u64 {
+        self.counts.received.load(Ordering::Relaxed)
+    }
+
+    /// 传递给下游的报价数
+    pub fn propagated(&self) -> u64 {
+        self.counts.propagated.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+    use rust_decimal::Decimal;
+
+    fn bbo(symbol: &str, bid: i64, bid_size: i64, ask: i64, ask_size: i64, ts: i64) -> BboEvent {
+        let at = Utc.timestamp_millis_opt(1735689600000 + ts).unwrap();
+        BboEvent {
+            symbol: symbol.to_string(),
+            bid: Decimal::from(bid),
+            bid_size: Decimal::from(bid_size),
+            ask: Decimal::from(ask),
+            ask_size: Decimal::from(ask_size),
+            exchange_ts: at,
+            local_ts: at,
+        }
+    }
+
+    #[test]
+    fn only_changed_quotes_are_propagated() {
+        let dedup = BboDedup::new(BboCache::new());
+        let accepted: Vec<_> = [
+            bbo("BTC-USD-PERP", 100, 1, 101, 1, 0),
+            bbo("BTC-USD-PERP", 100, 1, 101, 1, 10),
+            bbo("ETH-USD-PERP", 100, 1, 101, 1, 20),
+            bbo("BTC-USD-PERP", 100, 2, 101, 1, 30),
+            bbo("BTC-USD-PERP", 100, 2, 102, 1, 40),
+            bbo("BTC-USD-PERP", 100, 2, 102, 1, 50),
+        ]
+        .iter()
+        .map(|quote| dedup.accept(quote))
+        .collect();
+        assert_eq!(accepted, [true, false, true, true, true, false]);
+        assert_eq!((dedup.received(), dedup.propagated()), (6, 4));
+        // 重复的报价仍刷新缓存的接收时间
+        let cached = dedup.quotes().get("BTC-USD-PERP").unwrap();
+        assert_eq!(
+            cached.local_ts,
+            Utc.timestamp_millis_opt(1735689600050).unwrap()
+        );
+    }
+
+    #[test]
+    fn size_only_changes_can_be_suppressed() {
+        let dedup = BboDedup::new(BboCache::new()).with_ignore_size_changes(true);
+        assert!(dedup.accept(&bbo("BTC-USD-PERP", 100, 1, 101, 1, 0)));
+        assert!(!dedup.accept(&bbo("BTC-USD-PERP", 100, 5, 101, 3, 10)));
+        assert!(dedup.accept(&bbo("BTC-USD-PERP", 99, 5, 101, 3, 20)));
+        // 缓存保留最新的数量
+        assert_eq!(
+            dedup.quotes().get("BTC-USD-PERP").unwrap().bid_size,
+            Decimal::from(5)
+        );
+        assert_eq!((dedup.received(), dedup.propagated()), (3, 2));
+    }
+}
@@ -0,0 +1,380 @@
+//! 由成交频道合成 OHLCV K 线
+//!
+//! K 线按成交的交易所时间对齐到周期起点，收到下一周期的成交时输出上一根；中间没有成交的周期
+//! 输出成交量为 0、开高低收均为上一根收盘价的 K 线。
+
+use paradex::{structs::Trade, ws::Message};
+use rust_decimal::Decimal;
+use serde::{Serialize, Serializer};
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+use super::MarketEvent;
+use crate::markets::decimal;
+
+/// K 线周期，如 `1s`、`1m`、`5m`、`1h`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct CandleInterval {
+    millis: u64,
+}
+
+impl CandleInterval {
+    /// 时长为 `secs` 秒的周期
+    pub fn from_secs(secs: u64) -> Self {
+        Self {
+            millis: secs * 1_000,
+        }
+    }
+
+    /// 周期时长（毫秒）
+    pub fn as_millis(&self) -> u64 {
+        self.millis
+    }
+
+    /// `ts` 所在周期的起点（毫秒）
+    fn start_of(&self, ts: u64) -> u64 {
+        ts - ts % self.millis
+    }
+}
+
+impl FromStr for CandleInterval {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            format!(
+                "invalid candle interval {:?}: expected a number followed by s, m or h (e.g. 1s, 1m, 1h)",
+                value
+            )
+        };
+        let value = value.trim();
+        let split = value.len().checked_sub(1).ok_or_else(invalid)?;
+        let (count, unit) = value.split_at(split);
+        let count: u64 = count.parse().map_err(|_| invalid())?;
+        let unit_secs = match unit {
+            "s" => 1,
+            "m" => 60,
+            "h" => 3_600,
+            _ => return Err(invalid()),
+        };
+        if count == 0 {
+            return Err(invalid());
+        }
+        Ok(Self::from_secs(count * unit_secs))
+    }
+}
+
+impl fmt::Display for CandleInterval {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let secs = self.millis / 1_000;
+        if secs.is_multiple_of(3_600) {
+            write!(f, "{}h", secs / 3_600)
+        } else if secs.is_multiple_of(60) {
+            write!(f, "{}m", secs / 60)
+        } else {
+            write!(f, "{}s", secs)
+        }
+    }
+}
+
+impl Serialize for CandleInterval {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// 一根 K 线
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Candle {
+    pub market: String,
+    pub interval: CandleInterval,
+    /// 周期起点（Unix 毫秒）
+    pub start_ts: u64,
+    pub open: Decimal,
+    pub high: Decimal,
+    pub low: Decimal,
+    pub close: Decimal,
+    pub volume: Decimal,
+    pub trade_count: u64,
+}
+
+impl Candle {
+    /// 没有成交的周期：开高低收取上一根的收盘价
+    fn empty(market: &str, interval: CandleInterval, start_ts: u64, close: Decimal) -> Self {
+        Self {
+            market: market.to_string(),
+            interval,
+            start_ts,
+            open: close,
+            high: close,
+            low: close,
+            close,
+            volume: Decimal::ZERO,
+            trade_count: 0,
+        }
+    }
+
+    fn add(&mut self, price: Decimal, size: Decimal) {
+        if self.trade_count == 0 {
+            self.open = price;
+            self.high = price;
+            self.low = price;
+        } else {
+            self.high = self.high.max(price);
+            self.low = self.low.min(price);
+        }
+        self.close = price;
+        self.volume += size;
+        self.trade_count += 1;
+    }
+}
+
+/// 收盘 K 线的回调
+pub type CandleCallback = Box<dyn Fn(&Candle) + Send + Sync>;
+
+/// 按市场与周期合成 K 线
+pub struct CandleBuilder {
+    intervals: Vec<CandleInterval>,
+    /// 各市场、各周期当前未收盘的 K 线
+    open: HashMap<(String, CandleInterval), Candle>,
+    callbacks: Vec<CandleCallback>,
+}
+
+impl CandleBuilder {
+    /// 生成各 `intervals` 的 K 线；重复的周期只保留一个
+    pub fn new(intervals: &[CandleInterval]) -> Self {
+        let mut intervals = intervals.to_vec();
+        intervals.sort();
+        intervals.dedup();
+        Self {
+            intervals,
+            open: HashMap::new(),
+            callbacks: Vec::new(),
+        }
+    }
+
+    /// 按时长排序的周期
+    pub fn intervals(&self) -> &[CandleInterval] {
+        &self.intervals
+    }
+
+    /// 注册收盘回调，每根收盘的 K 线（含空周期）调用一次
+    pub fn on_candle(&mut self, callback: CandleCallback) {
+        self.callbacks.push(callback);
+    }
+
+    /// 处理成交频道的消息，其他消息忽略；返回收盘的 K 线
+    pub fn on_message(&mut self, message: &Message) -> Vec<Candle> {
+        match message {
+            Message::Trades(trade) => self.on_trade(trade),
+            _ => Vec::new(),
+        }
+    }
+
+    /// 处理行情事件总线上的成交事件，其他事件忽略；返回收盘的 K 线
+    pub fn on_event(&mut self, event: &MarketEvent) -> Vec<Candle> {
+        match event {
+            MarketEvent::Trade(trade) => self.add(
+                &trade.symbol,
+                trade.exchange_ts.timestamp_millis().max(0) as u64,
+                trade.price,
+                trade.size,
+            ),
+            _ => Vec::new(),
+        }
+    }
+
+    /// 记入成交推送；返回收盘的 K 线
+    pub fn on_trade(&mut self, trade: &Trade) -> Vec<Candle> {
+        self.add(
+            &trade.market,
+            trade.created_at,
+            decimal(trade.price),
+            decimal(trade.size),
+        )
+    }
+
+    /// 以交易所时间 `ts` 记入一笔成交；早于当前周期的迟到成交计入当前 K 线
+    pub fn add(&mut self, market: &str, ts: u64, price: Decimal, size: Decimal) -> Vec<Candle> {
+        let mut closed = Vec::new();
+        for &interval in &self.intervals {
+            let start = interval.start_of(ts);
+            let key = (market.to_string(), interval);
+            match self.open.get_mut(&key) {
+                Some(candle) => {
+                    if start > candle.start_ts {
+                        roll(candle, start, &mut closed);
+                    }
+                    candle.add(price, size);
+                }
+                None => {
+                    let mut candle = Candle::empty(market, interval, start, price);
+                    candle.add(price, size);
+                    self.open.insert(key, candle);
+                }
+            }
+        }
+        self.emit(&closed);
+        closed
+    }
+
+    /// 把所有市场推进到 `ts`：收盘已结束的周期（无成交的周期输出空 K 线），用于成交稀少的市场
+    pub fn advance_to(&mut self, ts: u64) -> Vec<Candle> {
+        let mut closed = Vec::new();
+        for candle in self.open.values_mut() {
+            let start = candle.interval.start_of(ts);
+            if start > candle.start_ts {
+                roll(candle, start, &mut closed);
+            }
+        }
+        closed.sort_by(|a, b| (a.start_ts, &a.market).cmp(&(b.start_ts, &b.market)));
+        self.emit(&closed);
+        closed
+    }
+
+    fn emit(&self, closed: &[Candle]) {
+        for candle in closed {
+            for callback in &self.callbacks {
+                callback(candle);
+            }
+        }
+    }
+}
+
+/// 收盘 `candle` 及其后到 `start` 之间的空周期，`candle` 变为从 `start` 开始的空 K 线
+fn roll(candle: &mut Candle, start: u64, closed: &mut Vec<Candle>) {
+    let step = candle.interval.as_millis();
+    let mut next = Candle::empty(
+        &candle.market,
+        candle.interval,
+        candle.start_ts + step,
+        candle.close,
+    );
+    closed.push(std::mem::replace(candle, next.clone()));
+    while next.start_ts < start {
+        closed.push(next.clone());
+        next.start_ts += step;
+    }
+    *candle = next;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use paradex::structs::{Side, TradeType};
+    use std::sync::{Arc, Mutex};
+
+    const T0: u64 = 1735689600000;
+
+    fn trade(ts: u64, price: f64, size: f64) -> Message {
+        Message::Trades(Trade {
+            created_at: ts,
+            id: ts.to_string(),
+            market: "BTC-USD-PERP".to_string(),
+            price,
+            side: Side::BUY,
+            size,
+            trade_type: TradeType::FILL,
+        })
+    }
+
+    #[test]
+    fn parses_intervals() {
+        assert_eq!("1s".parse(), Ok(CandleInterval::from_secs(1)));
+        assert_eq!("5m".parse(), Ok(CandleInterval::from_secs(300)));
+        assert_eq!("1h".parse(), Ok(CandleInterval::from_secs(3_600)));
+        assert_eq!(CandleInterval::from_secs(90).to_string(), "90s");
+        assert_eq!(CandleInterval::from_secs(300).to_string(), "5m");
+        for invalid in ["", "m", "0s", "1d", "-1m", "1.5m"] {
+            assert!(invalid.parse::<CandleInterval>().is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn candles_close_when_the_interval_rolls_over() {
+        let second = CandleInterval::from_secs(1);
+        let mut builder = CandleBuilder::new(&[second, CandleInterval::from_secs(60)]);
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let sink = seen.clone();
+        builder.on_candle(Box::new(move |candle| {
+            sink.lock().unwrap().push(candle.clone())
+        }));
+
+        assert!(builder.on_message(&trade(T0 + 100, 100.0, 1.0)).is_empty());
+        assert!(builder.on_message(&trade(T0 + 400, 103.0, 0.5)).is_empty());
+        assert!(builder.on_message(&trade(T0 + 700, 99.5, 2.0)).is_empty());
+        assert!(builder.on_message(&trade(T0 + 900, 101.0, 0.25)).is_empty());
+        assert!(builder.on_message(&Message::Connected).is_empty());
+
+        // 下一秒的成交使上一秒收盘
+        let closed = builder.on_message(&trade(T0 + 1_000, 102.0, 1.0));
+        assert_eq!(
+            closed,
+            vec![Candle {
+                market: "BTC-USD-PERP".to_string(),
+                interval: second,
+                start_ts: T0,
+                open: Decimal::from(100),
+                high: Decimal::from(103),
+                low: Decimal::new(995, 1),
+                close: Decimal::from(101),
+                volume: Decimal::new(375, 2),
+                trade_count: 4,
+            }]
+        );
+        assert_eq!(*seen.lock().unwrap(), closed);
+
+        // 迟到的成交计入当前 K 线
+        assert!(builder.on_message(&trade(T0 + 999, 98.0, 1.0)).is_empty());
+        let closed = builder.on_message(&trade(T0 + 2_000, 100.0, 1.0));
+        assert_eq!(closed[0].start_ts, T0 + 1_000);
+        assert_eq!(closed[0].low, Decimal::from(98));
+        assert_eq!(closed[0].close, Decimal::from(98));
+        assert_eq!(closed[0].trade_count, 2);
+
+        // 1 分钟 K 线尚未收盘
+        assert!(closed.iter().all(|candle| candle.interval == second));
+    }
+
+    #[test]
+    fn empty_intervals_carry_the_close_forward() {
+        let mut builder = CandleBuilder::new(&[CandleInterval::from_secs(1)]);
+        builder.on_message(&trade(T0 + 500, 100.0, 1.0));
+        builder.on_message(&trade(T0 + 600, 101.0, 1.0));
+
+        let closed = builder.on_message(&trade(T0 + 3_200, 105.0, 1.0));
+        let starts: Vec<_> = closed.iter().map(|candle| candle.start_ts).collect();
+        assert_eq!(starts, vec![T0, T0 + 1_000, T0 + 2_000]);
+        for gap in &closed[1..] {
+            assert_eq!(gap.volume, Decimal::ZERO);
+            assert_eq!(gap.trade_count, 0);
+            assert_eq!(
+                (gap.open, gap.high, gap.low, gap.close),
+                (
+                    Decimal::from(101),
+                    Decimal::from(101),
+                    Decimal::from(101),
+                    Decimal::from(101)
+                )
+            );
+        }
+
+        // 无成交时按时间推进
+        let closed = builder.advance_to(T0 + 5_000);
+        assert_eq!(closed.len(), 2);
+        assert_eq!(closed[0].open, Decimal::from(105));
+        assert_eq!(closed[0].trade_count, 1);
+        assert_eq!(closed[1].start_ts, T0 + 4_000);
+        assert_eq!(closed[1].close, Decimal::from(105));
+        assert!(builder.advance_to(T0 + 5_999).is_empty());
+
+        // 空 K 线上的第一笔成交决定开盘价
+        let closed = builder.on_message(&trade(T0 + 6_100, 90.0, 1.0));
+        assert_eq!(closed[0].start_ts, T0 + 5_000);
+        assert_eq!(closed[0].volume, Decimal::ZERO);
+        let closed = builder.advance_to(T0 + 7_000);
+        assert_eq!(closed[0].open, Decimal::from(90));
+        assert_eq!(closed[0].high, Decimal::from(90));
+    }
+}
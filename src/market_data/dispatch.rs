@@ -0,0 +1,454 @@
+//! 订阅回调的分发层：每个订阅一个有界队列，由独立任务执行回调，WebSocket 读取任务只负责入队
+//!
+//! 队列满时按频道的溢出策略处理：行情频道丢弃最旧的消息，私有频道（订单、成交、持仓、账户、
+//! 余额与资金费支付）从不丢弃。各频道收到、处理与丢弃的消息数计入 [`DispatchCounters`]。
+
+use async_trait::async_trait;
+use log::{info, warn};
+use paradex::{
+    error::Error,
+    ws::{Channel, Message},
+};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+
+use super::{channel_key, Callback, MarketDataSource, SubscriptionId};
+use crate::config::WsChannel;
+
+/// 每个订阅默认缓存的消息数
+pub const DEFAULT_DISPATCH_CAPACITY: usize = 4096;
+
+/// 队列已满时新消息的处理方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// 丢弃队列中最旧的消息，保留最新行情
+    DropOldest,
+    /// 从不丢弃；入队在 WebSocket 读取任务中同步执行、无法等待，超出容量时继续缓存并警告
+    NeverDrop,
+}
+
+impl OverflowPolicy {
+    /// 私有频道从不丢弃，其余频道丢弃最旧的消息
+    pub fn for_channel(channel: WsChannel) -> Self {
+        if channel.is_private() {
+            OverflowPolicy::NeverDrop
+        } else {
+            OverflowPolicy::DropOldest
+        }
+    }
+}
+
+/// 某个频道累计的消息数
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ChannelCounts {
+    pub received: u64,
+    pub processed: u64,
+    pub dropped: u64,
+}
+
+/// 各频道的消息计数，克隆后共享同一份数据
+#[derive(Debug, Clone, Default)]
+pub struct DispatchCounters {
+    counts: Arc<Mutex<BTreeMap<WsChannel, ChannelCounts>>>,
+}
+
+impl DispatchCounters {
+    /// `channel` 的计数
+    pub fn get(&self, channel: WsChannel) -> ChannelCounts {
+        self.counts
+            .lock()
+            .unwrap()
+            .get(&channel)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// 全部频道的计数
+    pub fn snapshot(&self) -> BTreeMap<WsChannel, ChannelCounts> {
+        self.counts.lock().unwrap().clone()
+    }
+
+    fn update(&self, channel: WsChannel, f: impl FnOnce(&mut ChannelCounts)) {
+        f(self.counts.lock().unwrap().entry(channel).or_default());
+    }
+
+    /// 每隔 `interval` 输出一行各频道的收到 / 处理速率（条/秒）与丢弃数，直到任务被终止
+    pub fn spawn_rate_log(&self, interval: Duration) -> JoinHandle<()> {
+        let counters = self.clone();
+        tokio::spawn(async move {
+            let mut previous: BTreeMap<WsChannel, ChannelCounts> = BTreeMap::new();
+            loop {
+                tokio::time::sleep(interval).await;
+                let current = counters.snapshot();
+                let secs = interval.as_secs_f64();
+                let channels: Vec<_> = current
+                    .iter()
+                    .filter_map(|(channel, counts)| {
+                        let last = previous.get(channel).copied().unwrap_or_default();
+                        let received = counts.received - last.received;
+                        let processed = counts.processed - last.processed;
+                        let dropped = counts.dropped - last.dropped;
+                        (received > 0 || processed > 0).then(|| {
+                            format!(
+                                "{} in={:.1}/s out={:.1}/s dropped={}",
+                                channel.cli_name(),
+                                received as f64 / secs,
+                                processed as f64 / secs,
+                                dropped
+                            )
+                        })
+                    })
+                    .collect();
+                if !channels.is_empty() {
+                    info!("Market data rates: {}", channels.join(", "));
+                }
+                previous = current;
+            }
+        })
+    }
+}
+
+#[derive(Default)]
+struct QueueState {
+    messages: VecDeque<Message>,
+    closed: bool,
+    /// 超出容量后只警告一次，回落后重置
+    overflowing: bool,
+}
+
+/// 一个订阅的消息队列
+#[derive(Default)]
+struct Queue {
+    state: Mutex<QueueState>,
+    ready: Notify,
+}
+
+impl Queue {
+    fn close(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.closed = true;
+        state.messages.clear();
+        drop(state);
+        self.ready.notify_one();
+    }
+}
+
+/// 以有界队列与独立任务分发 `inner` 的订阅回调
+pub struct Dispatching {
+    inner: Arc<dyn MarketDataSource>,
+    capacity: usize,
+    policies: HashMap<WsChannel, OverflowPolicy>,
+    counters: DispatchCounters,
+    queues: Mutex<HashMap<SubscriptionId, Arc<Queue>>>,
+}
+
+impl Dispatching {
+    /// `capacity` 为每个订阅缓存的消息数
+    pub fn new(inner: Arc<dyn MarketDataSource>, capacity: usize) -> Self {
+        Self {
+            inner,
+            capacity: capacity.max(1),
+            policies: HashMap::new(),
+            counters: DispatchCounters::default(),
+            queues: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 覆盖 `channel` 默认的溢出策略
+    pub fn with_policy(mut self, channel: WsChannel, policy: OverflowPolicy) -> Self {
+        self.policies.insert(channel, policy);
+        self
+    }
+
+    /// `channel` 使用的溢出策略
+    pub fn policy(&self, channel: WsChannel) -> OverflowPolicy {
+        self.policies
+            .get(&channel)
+            .copied()
+            .unwrap_or_else(|| OverflowPolicy::for_channel(channel))
+    }
+
+    /// 各频道的投递计数
+    pub fn counters(&self) -> &DispatchCounters {
+        &self.counters
+    }
+}
+
+fn enqueue(
+    queue: &Queue,
+    counters: &DispatchCounters,
+    channel: WsChannel,
+    policy: OverflowPolicy,
+    capacity: usize,
+    message: &Message,
+) {
+    let mut state = queue.state.lock().unwrap();
+    if state.closed {
+        return;
+    }
+    counters.update(channel, |counts| counts.received += 1);
+    if state.messages.len() < capacity {
+        state.overflowing = false;
+    } else {
+        let first = !state.overflowing;
+        state.overflowing = true;
+        match policy {
+            OverflowPolicy::DropOldest => {
+                state.messages.pop_front();
+                counters.update(channel, |counts| counts.dropped += 1);
+                if first {
+                    warn!(
+                        "{} consumer is lagging, dropping the oldest messages",
+                        channel.cli_name()
+                    );
+                }
+            }
+            OverflowPolicy::NeverDrop if first => warn!(
+                "{} consumer is lagging, queue exceeds {} messages",
+                channel.cli_name(),
+                capacity
+            ),
+            OverflowPolicy::NeverDrop => {}
+        }
+    }
+    state.messages.push_back(message.clone());
+    drop(state);
+    queue.ready.notify_one();
+}
+
+async fn consume(
+    queue: Arc<Queue>,
+    counters: DispatchCounters,
+    channel: WsChannel,
+    callback: Callback,
+) {
+    loop {
+        let next = {
+            let mut state = queue.state.lock().unwrap();
+            if state.closed {
+                return;
+            }
+            state.messages.pop_front()
+        };
+        match next {
+            Some(message) => {
+                callback(&message);
+                counters.update(channel, |counts| counts.processed += 1);
+            }
+            None => queue.ready.notified().await,
+        }
+    }
+}
+
+#[async_trait]
+impl MarketDataSource for Dispatching {
+    async fn subscribe(
+        &self,
+        channel: Channel,
+        callback: Callback,
+    ) -> Result<SubscriptionId, Error> {
+        let name = channel_key(&channel).0;
+        let policy = self.policy(name);
+        let capacity = self.capacity;
+        let queue = Arc::new(Queue::default());
+        let producer = queue.clone();
+        let counters = self.counters.clone();
+        let result = self
+            .inner
+            .subscribe(
+                channel,
+                Box::new(move |message| {
+                    enqueue(&producer, &counters, name, policy, capacity, message)
+                }),
+            )
+            .await;
+        let id = result.inspect_err(|_| queue.close())?;
+        tokio::spawn(consume(
+            queue.clone(),
+            self.counters.clone(),
+            name,
+            callback,
+        ));
+        self.queues.lock().unwrap().insert(id, queue);
+        Ok(id)
+    }
+
+    async fn unsubscribe(&self, id: SubscriptionId) -> Result<(), Error> {
+        if let Some(queue) = self.queues.lock().unwrap().remove(&id) {
+            queue.close();
+        }
+        self.inner.unsubscribe(id).await
+    }
+
+    async fn stop(&self) -> Result<(), Error> {
+        for (_, queue) in self.queues.lock().unwrap().drain() {
+            queue.close();
+        }
+        self.inner.stop().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use paradex::structs::{Side, Trade, TradeType};
+
+    /// 由测试直接调用回调
+    #[derive(Default)]
+    struct ScriptedSource {
+        callbacks: Mutex<Vec<Callback>>,
+    }
+
+    impl ScriptedSource {
+        fn push(&self, message: &Message) {
+            for callback in self.callbacks.lock().unwrap().iter() {
+                callback(message);
+            }
+        }
+    }
+
+    #[async_trait]
+    impl MarketDataSource for ScriptedSource {
+        async fn subscribe(
+            &self,
+            _channel: Channel,
+            callback: Callback,
+        ) -> Result<SubscriptionId, Error> {
+            let mut callbacks = self.callbacks.lock().unwrap();
+            callbacks.push(callback);
+            Ok(SubscriptionId::Replay(callbacks.len() as u64))
+        }
+
+        async fn unsubscribe(&self, _id: SubscriptionId) -> Result<(), Error> {
+            Ok(())
+        }
+
+        async fn stop(&self) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+
+    fn trade(id: u32) -> Message {
+        Message::Trades(Trade {
+            created_at: 1735689600000,
+            id: id.to_string(),
+            market: "BTC-USD-PERP".to_string(),
+            price: 95000.0,
+            side: Side::BUY,
+            size: 0.01,
+            trade_type: TradeType::FILL,
+        })
+    }
+
+    /// 订阅 `channel` 并记录回调收到的成交 id
+    async fn subscribe(source: &Dispatching, channel: Channel) -> Arc<Mutex<Vec<String>>> {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let sink = seen.clone();
+        source
+            .subscribe(
+                channel,
+                Box::new(move |message| {
+                    if let Message::Trades(trade) = message {
+                        sink.lock().unwrap().push(trade.id.clone());
+                    }
+                }),
+            )
+            .await
+            .unwrap();
+        seen
+    }
+
+    async fn drained(source: &Dispatching, channel: WsChannel, processed: u64) {
+        tokio::time::timeout(Duration::from_secs(1), async {
+            while source.counters().get(channel).processed < processed {
+                tokio::task::yield_now().await;
+            }
+        })
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn slow_consumers_drop_market_data_but_not_private_messages() {
+        let scripted = Arc::new(ScriptedSource::default());
+        let source = Dispatching::new(scripted.clone(), 2);
+        assert_eq!(source.policy(WsChannel::Trades), OverflowPolicy::DropOldest);
+        assert_eq!(source.policy(WsChannel::Fills), OverflowPolicy::NeverDrop);
+        let trades = subscribe(
+            &source,
+            Channel::Trades {
+                market_symbol: "BTC-USD-PERP".to_string(),
+            },
+        )
+        .await;
+        let fills = subscribe(
+            &source,
+            Channel::Fills {
+                market_symbol: None,
+            },
+        )
+        .await;
+
+        // 消费任务尚未运行：5 条消息全部在队列中等待
+        for id in 1..=5 {
+            scripted.push(&trade(id));
+        }
+        drained(&source, WsChannel::Trades, 2).await;
+        drained(&source, WsChannel::Fills, 5).await;
+
+        assert_eq!(*trades.lock().unwrap(), ["4", "5"]);
+        assert_eq!(
+            source.counters().get(WsChannel::Trades),
+            ChannelCounts {
+                received: 5,
+                processed: 2,
+                dropped: 3,
+            }
+        );
+        assert_eq!(*fills.lock().unwrap(), ["1", "2", "3", "4", "5"]);
+        assert_eq!(
+            source.counters().get(WsChannel::Fills),
+            ChannelCounts {
+                received: 5,
+                processed: 5,
+                dropped: 0,
+            }
+        );
+
+        // 消费跟上后不再丢弃
+        scripted.push(&trade(6));
+        drained(&source, WsChannel::Trades, 3).await;
+        assert_eq!(*trades.lock().unwrap(), ["4", "5", "6"]);
+        assert_eq!(source.counters().get(WsChannel::Trades).dropped, 3);
+    }
+
+    #[tokio::test]
+    async fn policy_can_be_overridden_and_unsubscribe_stops_delivery() {
+        let scripted = Arc::new(ScriptedSource::default());
+        let source = Dispatching::new(scripted.clone(), 1)
+            .with_policy(WsChannel::Bbo, OverflowPolicy::NeverDrop);
+        let channel = Channel::BBO {
+            market_symbol: "BTC-USD-PERP".to_string(),
+        };
+        let seen = Arc::new(Mutex::new(0));
+        let sink = seen.clone();
+        let id = source
+            .subscribe(channel, Box::new(move |_| *sink.lock().unwrap() += 1))
+            .await
+            .unwrap();
+        for id in 1..=3 {
+            scripted.push(&trade(id));
+        }
+        drained(&source, WsChannel::Bbo, 3).await;
+        assert_eq!(source.counters().get(WsChannel::Bbo).dropped, 0);
+
+        source.unsubscribe(id).await.unwrap();
+        scripted.push(&trade(4));
+        tokio::task::yield_now().await;
+        assert_eq!(*seen.lock().unwrap(), 3);
+        assert_eq!(source.counters().get(WsChannel::Bbo).processed, 3);
+    }
+}
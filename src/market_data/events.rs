@@ -0,0 +1,597 @@
+//! 统一的行情事件：WebSocket 层把公开频道消息转换为 [`MarketEvent`] 发布到 [`EventBus`]，
+//! 报价缓存、K 线合成、模拟撮合与策略各自订阅，不再依赖 `paradex::ws` 的消息类型
+//!
+//! 订阅方式有两种：[`EventBus::on_event`] 注册的处理函数在发布时同步调用，不会漏掉事件，
+//! 适合维护状态的组件；[`EventBus::subscribe`] 返回有界的异步接收端，消费过慢时丢弃最旧的事件并记录。
+//! 设置 [`BboDedup`] 后，报价未变化的 BBO 事件在发布前丢弃，两种订阅者都只收到变化的报价。
+
+use chrono::{DateTime, TimeZone, Utc};
+use log::warn;
+use paradex::structs::{
+    FundingData, MarketSummary, OrderBook, OrderBookUpdateType, Side, Trade, BBO,
+};
+use paradex::ws::Message;
+use rust_decimal::Decimal;
+use serde_json::Value as Json;
+use std::sync::{Arc, RwLock};
+use tokio::sync::broadcast;
+
+use super::BboDedup;
+use crate::config::WsChannel;
+use crate::markets::decimal;
+use crate::orderbook::Level;
+
+/// 异步订阅端默认缓存的事件数
+pub const DEFAULT_EVENT_CAPACITY: usize = 4096;
+
+/// 一条行情事件；各变体均带市场、交易所时间与本地接收时间
+#[derive(Debug, Clone, PartialEq)]
+pub enum MarketEvent {
+    Bbo(BboEvent),
+    Trade(TradeEvent),
+    /// 订单簿增量：数量为 0 的档位表示删除
+    BookDelta(BookEvent),
+    /// 完整订单簿（`order_book` 频道或增量频道的首条快照）
+    BookSnapshot(BookEvent),
+    Funding(FundingEvent),
+    MarketSummary(SummaryEvent),
+}
+
+/// 买卖一档
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BboEvent {
+    pub symbol: String,
+    pub bid: Decimal,
+    pub bid_size: Decimal,
+    pub ask: Decimal,
+    pub ask_size: Decimal,
+    pub exchange_ts: DateTime<Utc>,
+    pub local_ts: DateTime<Utc>,
+}
+
+/// 一笔公开成交
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TradeEvent {
+    pub symbol: String,
+    pub id: String,
+    pub price: Decimal,
+    pub size: Decimal,
+    /// 主动方
+    pub side: Side,
+    pub exchange_ts: DateTime<Utc>,
+    pub local_ts: DateTime<Utc>,
+}
+
+/// 订单簿快照或增量；买盘按价格从高到低、卖盘从低到高排列
+#[derive(Debug, Clone, PartialEq)]
+pub struct BookEvent {
+    pub symbol: String,
+    pub seq_no: u64,
+    pub bids: Vec<Level>,
+    pub asks: Vec<Level>,
+    pub exchange_ts: DateTime<Utc>,
+    pub local_ts: DateTime<Utc>,
+}
+
+/// 资金费率数据
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FundingEvent {
+    pub symbol: String,
+    pub funding_rate: Decimal,
+    pub funding_index: Decimal,
+    pub funding_premium: Decimal,
+    pub exchange_ts: DateTime<Utc>,
+    pub local_ts: DateTime<Utc>,
+}
+
+/// 行情摘要
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SummaryEvent {
+    pub symbol: String,
+    pub mark_price: Decimal,
+    pub last_traded_price: Decimal,
+    pub bid: Decimal,
+    pub ask: Decimal,
+    pub open_interest: Decimal,
+    pub funding_rate: Decimal,
+    /// 24 小时成交量；交易所未提供时为 `None`
+    pub volume_24h: Option<Decimal>,
+    pub exchange_ts: DateTime<Utc>,
+    pub local_ts: DateTime<Utc>,
+}
+
+/// 毫秒时间戳；超出范围时为 Unix 纪元
+fn millis(ts: u64) -> DateTime<Utc> {
+    Utc.timestamp_millis_opt(ts as i64)
+        .single()
+        .unwrap_or_default()
+}
+
+impl MarketEvent {
+    /// 转换公开频道的消息；控制消息与私有频道消息为 `None`
+    pub fn from_message(message: &Message, local_ts: DateTime<Utc>) -> Option<Self> {
+        let event = match message {
+            Message::BBO(bbo) => Self::Bbo(BboEvent::new(bbo, local_ts)),
+            Message::Trades(trade) => Self::Trade(TradeEvent::new(trade, local_ts)),
+            Message::OrderBook(book) => Self::BookSnapshot(BookEvent::new(book, local_ts)),
+            Message::OrderBookDeltas(book) => {
+                let event = BookEvent::new(book, local_ts);
+                match book.update_type {
+                    OrderBookUpdateType::Snapshot => Self::BookSnapshot(event),
+                    _ => Self::BookDelta(event),
+                }
+            }
+            Message::FundingData(funding) => Self::Funding(FundingEvent::new(funding, local_ts)),
+            Message::MarketSummary(summary) => {
+                Self::MarketSummary(SummaryEvent::new(summary, local_ts))
+            }
+            _ => return None,
+        };
+        Some(event)
+    }
+
+    /// 解析 `channel` 频道通知中的 `data`；未知字段忽略，私有频道为 `Ok(None)`
+    pub fn parse(
+        channel: WsChannel,
+        data: Json,
+        local_ts: DateTime<Utc>,
+    ) -> Result<Option<Self>, serde_json::Error> {
+        let message = match channel {
+            WsChannel::Bbo => Message::BBO(serde_json::from_value(data)?),
+            WsChannel::Trades => Message::Trades(serde_json::from_value(data)?),
+            WsChannel::OrderBook => Message::OrderBook(serde_json::from_value(data)?),
+            WsChannel::OrderBookDeltas => Message::OrderBookDeltas(serde_json::from_value(data)?),
+            WsChannel::FundingData => Message::FundingData(serde_json::from_value(data)?),
+            WsChannel::MarketsSummary => Message::MarketSummary(serde_json::from_value(data)?),
+            _ => return Ok(None),
+        };
+        Ok(Self::from_message(&message, local_ts))
+    }
+
+    /// 事件所属市场
+    pub fn symbol(&self) -> &str {
+        match self {
+            Self::Bbo(event) => &event.symbol,
+            Self::Trade(event) => &event.symbol,
+            Self::BookDelta(event) | Self::BookSnapshot(event) => &event.symbol,
+            Self::Funding(event) => &event.symbol,
+            Self::MarketSummary(event) => &event.symbol,
+        }
+    }
+
+    /// 交易所时间
+    pub fn exchange_ts(&self) -> DateTime<Utc> {
+        match self {
+            Self::Bbo(event) => event.exchange_ts,
+            Self::Trade(event) => event.exchange_ts,
+            Self::BookDelta(event) | Self::BookSnapshot(event) => event.exchange_ts,
+            Self::Funding(event) => event.exchange_ts,
+            Self::MarketSummary(event) => event.exchange_ts,
+        }
+    }
+
+    /// 本地收到的时间
+    pub fn local_ts(&self) -> DateTime<Utc> {
+        match self {
+            Self::Bbo(event) => event.local_ts,
+            Self::Trade(event) => event.local_ts,
+            Self::BookDelta(event) | Self::BookSnapshot(event) => event.local_ts,
+            Self::Funding(event) => event.local_ts,
+            Self::MarketSummary(event) => event.local_ts,
+        }
+    }
+}
+
+impl BboEvent {
+    /// 由 BBO 推送转换
+    pub fn new(bbo: &BBO, local_ts: DateTime<Utc>) -> Self {
+        Self {
+            symbol: bbo.market.clone(),
+            bid: decimal(bbo.bid),
+            bid_size: decimal(bbo.bid_size),
+            ask: decimal(bbo.ask),
+            ask_size: decimal(bbo.ask_size),
+            exchange_ts: millis(bbo.last_updated_at),
+            local_ts,
+        }
+    }
+}
+
+impl TradeEvent {
+    /// 由成交推送转换
+    pub fn new(trade: &Trade, local_ts: DateTime<Utc>) -> Self {
+        Self {
+            symbol: trade.market.clone(),
+            id: trade.id.clone(),
+            price: decimal(trade.price),
+            size: decimal(trade.size),
+            side: trade.side,
+            exchange_ts: millis(trade.created_at),
+            local_ts,
+        }
+    }
+}
+
+impl BookEvent {
+    /// 新增、更新的档位取推送的数量，删除的档位数量为 0
+    pub fn new(book: &OrderBook, local_ts: DateTime<Utc>) -> Self {
+        let mut bids = Vec::new();
+        let mut asks = Vec::new();
+        let changed = book.inserts.iter().chain(&book.updates);
+        let levels = changed
+            .map(|level| (level, decimal(level.size)))
+            .chain(book.deletes.iter().map(|level| (level, Decimal::ZERO)));
+        for (level, size) in levels {
+            let entry = Level(decimal(level.price), size);
+            match level.side {
+                Side::BUY => bids.push(entry),
+                Side::SELL => asks.push(entry),
+            }
+        }
+        bids.sort_by_key(|level| std::cmp::Reverse(level.price()));
+        asks.sort_by_key(Level::price);
+        Self {
+            symbol: book.market.clone(),
+            seq_no: book.seq_no,
+            bids,
+            asks,
+            exchange_ts: millis(book.last_updated_at),
+            local_ts,
+        }
+    }
+}
+
+impl FundingEvent {
+    /// 由资金费率推送转换
+    pub fn new(funding: &FundingData, local_ts: DateTime<Utc>) -> Self {
+        Self {
+            symbol: funding.market.clone(),
+            funding_rate: decimal(funding.funding_rate),
+            funding_index: decimal(funding.funding_index),
+            funding_premium: decimal(funding.funding_premium),
+            exchange_ts: millis(funding.created_at),
+            local_ts,
+        }
+    }
+}
+
+impl SummaryEvent {
+    /// 由行情摘要推送转换
+    pub fn new(summary: &MarketSummary, local_ts: DateTime<Utc>) -> Self {
+        Self {
+            symbol: summary.symbol.clone(),
+            mark_price: decimal(summary.mark_price),
+            last_traded_price: decimal(summary.last_traded_price),
+            bid: decimal(summary.bid),
+            ask: decimal(summary.ask),
+            open_interest: decimal(summary.open_interest),
+            funding_rate: decimal(summary.funding_rate),
+            volume_24h: summary.volume_24.filter(|v| v.is_finite()).map(decimal),
+            exchange_ts: millis(summary.created_at),
+            local_ts,
+        }
+    }
+}
+
+/// 同步处理事件的订阅者
+pub type EventHandler = Box<dyn Fn(&MarketEvent) + Send + Sync>;
+
+/// 行情事件总线；克隆后共享同一组订阅者
+#[derive(Clone)]
+pub struct EventBus {
+    handlers: Arc<RwLock<Vec<EventHandler>>>,
+    sender: broadcast::Sender<Arc<MarketEvent>>,
+    bbo_dedup: Option<BboDedup>,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::with_capacity(DEFAULT_EVENT_CAPACITY)
+    }
+}
+
+impl EventBus {
+    /// 没有订阅端的总线
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `capacity` 为每个异步订阅端缓存的事件数
+    pub fn with_capacity(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity.max(1));
+        Self {
+            handlers: Arc::default(),
+            sender,
+            bbo_dedup: None,
+        }
+    }
+
+    /// 发布 BBO 事件前经 `dedup` 过滤，丢弃报价未变化的事件
+    pub fn with_bbo_dedup(mut self, dedup: BboDedup) -> Self {
+        self.bbo_dedup = Some(dedup);
+        self
+    }
+
+    /// 注册同步订阅者：发布时在发布方的任务中依次调用，处理函数须快速返回
+    pub fn on_event(&self, handler: EventHandler) {
+        self.handlers.write().unwrap().push(handler);
+    }
+
+    /// 异步订阅此后发布的事件
+    pub fn subscribe(&self) -> EventReceiver {
+        EventReceiver {
+            receiver: self.sender.subscribe(),
+        }
+    }
+
+    /// 发布事件；BBO 去重判定报价未变化时丢弃并返回 `false`
+    pub fn publish(&self, event: MarketEvent) -> bool {
+        if let (MarketEvent::Bbo(bbo), Some(dedup)) = (&event, &self.bbo_dedup) {
+            if !dedup.accept(bbo) {
+                return false;
+            }
+        }
+        for handler in self.handlers.read().unwrap().iter() {
+            handler(&event);
+        }
+        // 没有异步订阅端时丢弃
+        let _ = self.sender.send(Arc::new(event));
+        true
+    }
+
+    /// 转换并发布一条 WebSocket 消息（本地时间取当前时间）；返回是否发布，
+    /// 不产生事件的消息与被去重丢弃的报价为 `false`
+    pub fn publish_message(&self, message: &Message) -> bool {
+        MarketEvent::from_message(message, Utc::now()).is_some_and(|event| self.publish(event))
+    }
+}
+
+/// 事件总线的异步订阅端
+pub struct EventReceiver {
+    receiver: broadcast::Receiver<Arc<MarketEvent>>,
+}
+
+impl EventReceiver {
+    /// 下一个事件；总线已全部丢弃时为 `None`。跟不上时跳过被覆盖的事件并记录警告
+    pub async fn recv(&mut self) -> Option<Arc<MarketEvent>> {
+        loop {
+            match self.receiver.recv().await {
+                Ok(event) => return Some(event),
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    warn!(
+                        "Market event subscriber is lagging, skipped {} events",
+                        skipped
+                    )
+                }
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::sync::Mutex;
+
+    fn local() -> DateTime<Utc> {
+        millis(1735689600500)
+    }
+
+    fn parse(channel: WsChannel, data: Json) -> MarketEvent {
+        MarketEvent::parse(channel, data, local()).unwrap().unwrap()
+    }
+
+    #[test]
+    fn bbo_and_trade_notifications_tolerate_unknown_fields() {
+        let bbo = parse(
+            WsChannel::Bbo,
+            json!({
+                "market": "BTC-USD-PERP",
+                "bid": "95000.5",
+                "bid_size": "1.2",
+                "ask": "95001",
+                "ask_size": "0.8",
+                "last_updated_at": 1735689600000u64,
+                "seq_no": 42,
+                "venue": "paradex"
+            }),
+        );
+        assert_eq!(
+            bbo,
+            MarketEvent::Bbo(BboEvent {
+                symbol: "BTC-USD-PERP".to_string(),
+                bid: Decimal::new(950005, 1),
+                bid_size: Decimal::new(12, 1),
+                ask: Decimal::from(95001),
+                ask_size: Decimal::new(8, 1),
+                exchange_ts: millis(1735689600000),
+                local_ts: local(),
+            })
+        );
+
+        let trade = parse(
+            WsChannel::Trades,
+            json!({
+                "id": "t-1",
+                "market": "ETH-USD-PERP",
+                "price": "3300.25",
+                "side": "SELL",
+                "size": "0.5",
+                "created_at": 1735689600100u64,
+                "trade_type": "FILL",
+                "unknown": {"nested": true}
+            }),
+        );
+        let MarketEvent::Trade(trade) = trade else {
+            panic!("expected a trade");
+        };
+        assert_eq!(trade.symbol, "ETH-USD-PERP");
+        assert_eq!(trade.price, Decimal::new(330025, 2));
+        assert_eq!(trade.side, Side::SELL);
+        assert_eq!(trade.exchange_ts, millis(1735689600100));
+    }
+
+    #[test]
+    fn book_messages_become_sorted_snapshots_and_deltas() {
+        let data = |update_type: &str| {
+            json!({
+                "seq_no": 7,
+                "market": "BTC-USD-PERP",
+                "last_updated_at": 1735689600000u64,
+                "update_type": update_type,
+                "deletes": [{"side": "BUY", "price": "94998", "size": "0"}],
+                "inserts": [
+                    {"side": "BUY", "price": "94999", "size": "2"},
+                    {"side": "BUY", "price": "95000", "size": "1"},
+                    {"side": "SELL", "price": "95002", "size": "3"},
+                    {"side": "SELL", "price": "95001", "size": "4"}
+                ],
+                "updates": [],
+                "extra": 1
+            })
+        };
+        let MarketEvent::BookDelta(delta) = parse(WsChannel::OrderBookDeltas, data("d")) else {
+            panic!("expected a delta");
+        };
+        let level = |price: i64, size: i64| Level(Decimal::from(price), Decimal::from(size));
+        assert_eq!(delta.seq_no, 7);
+        assert_eq!(
+            delta.bids,
+            [level(95000, 1), level(94999, 2), level(94998, 0)]
+        );
+        assert_eq!(delta.asks, [level(95001, 4), level(95002, 3)]);
+
+        let snapshot = parse(WsChannel::OrderBookDeltas, data("s"));
+        assert!(matches!(snapshot, MarketEvent::BookSnapshot(_)));
+        let snapshot = parse(WsChannel::OrderBook, data("d"));
+        assert!(matches!(snapshot, MarketEvent::BookSnapshot(_)));
+    }
+
+    #[test]
+    fn funding_and_summary_notifications_are_converted() {
+        let funding = parse(
+            WsChannel::FundingData,
+            json!({
+                "market": "BTC-USD-PERP",
+                "funding_index": "12.5",
+                "funding_premium": "0.01",
+                "funding_rate": "0.0001",
+                "created_at": 1735689600000u64,
+                "funding_period_hours": 8
+            }),
+        );
+        let MarketEvent::Funding(funding) = funding else {
+            panic!("expected funding data");
+        };
+        assert_eq!(funding.funding_rate, Decimal::new(1, 4));
+        assert_eq!(funding.funding_index, Decimal::new(125, 1));
+
+        let summary = parse(
+            WsChannel::MarketsSummary,
+            json!({
+                "symbol": "ETH-USD-PERP",
+                "mark_price": "3300.1",
+                "last_traded_price": "3300",
+                "bid": "3299.9",
+                "ask": "3300.2",
+                "total_volume": "1000000",
+                "created_at": 1735689600000u64,
+                "underlying_price": "3300.05",
+                "open_interest": "120.5",
+                "funding_rate": "0.00005",
+                "price_change_rate_24h": "0.01",
+                "delta": "1",
+                "greeks": {"gamma": "0"}
+            }),
+        );
+        assert_eq!(summary.symbol(), "ETH-USD-PERP");
+        let MarketEvent::MarketSummary(summary) = summary else {
+            panic!("expected a market summary");
+        };
+        assert_eq!(summary.mark_price, Decimal::new(33001, 1));
+        assert_eq!(summary.open_interest, Decimal::new(1205, 1));
+        assert_eq!(summary.volume_24h, None);
+    }
+
+    #[test]
+    fn private_and_control_messages_produce_no_events() {
+        assert!(MarketEvent::from_message(&Message::Connected, local()).is_none());
+        assert_eq!(
+            MarketEvent::parse(WsChannel::Fills, json!({}), local()).unwrap(),
+            None
+        );
+        assert!(MarketEvent::parse(WsChannel::Bbo, json!({"market": 1}), local()).is_err());
+    }
+
+    #[tokio::test]
+    async fn bus_delivers_to_handlers_and_receivers() {
+        let bus = EventBus::with_capacity(2);
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let handled = seen.clone();
+        bus.on_event(Box::new(move |event| {
+            handled.lock().unwrap().push(event.symbol().to_string())
+        }));
+        let mut receiver = bus.subscribe();
+        let bbo = |symbol: &str| {
+            MarketEvent::Bbo(BboEvent {
+                symbol: symbol.to_string(),
+                bid: Decimal::ONE,
+                bid_size: Decimal::ONE,
+                ask: Decimal::TWO,
+                ask_size: Decimal::ONE,
+                exchange_ts: local(),
+                local_ts: local(),
+            })
+        };
+        for symbol in ["A", "B", "C"] {
+            bus.publish(bbo(symbol));
+        }
+        // 同步订阅者收到全部事件；容量为 2 的异步订阅端跳过最旧的事件
+        assert_eq!(*seen.lock().unwrap(), ["A", "B", "C"]);
+        assert_eq!(receiver.recv().await.unwrap().symbol(), "B");
+        assert_eq!(receiver.recv().await.unwrap().symbol(), "C");
+        drop(bus);
+        assert!(receiver.recv().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn bbo_dedup_drops_unchanged_quotes_for_all_subscribers() {
+        let bus = EventBus::new().with_bbo_dedup(BboDedup::default());
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let handled = seen.clone();
+        bus.on_event(Box::new(move |event| {
+            if let MarketEvent::Bbo(bbo) = event {
+                handled.lock().unwrap().push(bbo.bid)
+            }
+        }));
+        let mut receiver = bus.subscribe();
+        let quote = |bid: i64| {
+            Message::BBO(BBO {
+                bid: bid as f64,
+                bid_size: 1.0,
+                ask: 101.0,
+                ask_size: 1.0,
+                market: "BTC-USD-PERP".to_string(),
+                last_updated_at: 1735689600000,
+            })
+        };
+        let published: Vec<_> = [100, 100, 99, 99]
+            .into_iter()
+            .map(|bid| bus.publish_message(&quote(bid)))
+            .collect();
+        assert_eq!(published, [true, false, true, false]);
+        assert_eq!(
+            *seen.lock().unwrap(),
+            [Decimal::from(100), Decimal::from(99)]
+        );
+        assert!(!bus.publish_message(&Message::Connected));
+        drop(bus);
+        let mut received = Vec::new();
+        while let Some(event) = receiver.recv().await {
+            received.push(event.symbol().to_string());
+        }
+        assert_eq!(received.len(), 2);
+    }
+}
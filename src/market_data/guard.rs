@@ -0,0 +1,372 @@
+//! 隔离订阅回调中的 panic：panic 被捕获并记录为 [`CallbackPanic`]，订阅继续接收后续消息
+//!
+//! 同一订阅的回调连续 panic 达到上限后停用该回调（此后的消息直接丢弃）并记录错误，
+//! 避免有缺陷的处理逻辑在每条消息上反复失败。
+
+use async_trait::async_trait;
+use log::error;
+use paradex::{
+    error::Error,
+    ws::{Channel, Message},
+};
+use std::any::Any;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::Arc;
+
+use super::{channel_key, Callback, MarketDataSource, SubscriptionId};
+use crate::config::WsChannel;
+use crate::metrics::metrics;
+
+/// 默认停用回调前允许的连续 panic 次数
+pub const DEFAULT_MAX_CONSECUTIVE_PANICS: u32 = 5;
+/// 记录的消息内容最多保留的字符数
+const PAYLOAD_SNIPPET_CHARS: usize = 200;
+
+/// 一次回调 panic
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CallbackPanic {
+    pub channel: WsChannel,
+    pub market: Option<String>,
+    /// panic 信息
+    pub reason: String,
+    /// 引发 panic 的消息（截断）
+    pub payload: String,
+    /// 该订阅连续 panic 的次数
+    pub consecutive: u32,
+    /// 达到上限，回调已停用
+    pub disabled: bool,
+}
+
+/// 回调 panic 的订阅者
+pub type PanicHandler = Box<dyn Fn(&CallbackPanic) + Send + Sync>;
+
+/// 捕获 `inner` 订阅回调中的 panic
+pub struct GuardedSource {
+    inner: Arc<dyn MarketDataSource>,
+    max_consecutive: u32,
+    handlers: Arc<Vec<PanicHandler>>,
+}
+
+impl GuardedSource {
+    /// 同一订阅的回调连续 panic 达到默认次数后停用
+    pub fn new(inner: Arc<dyn MarketDataSource>) -> Self {
+        Self {
+            inner,
+            max_consecutive: DEFAULT_MAX_CONSECUTIVE_PANICS,
+            handlers: Arc::new(Vec::new()),
+        }
+    }
+
+    /// 同一订阅的回调连续 panic `max` 次后停用
+    pub fn with_max_consecutive_panics(mut self, max: u32) -> Self {
+        self.max_consecutive = max.max(1);
+        self
+    }
+
+    /// 每次回调 panic 时调用 `handler`
+    pub fn with_panic_handler(mut self, handler: PanicHandler) -> Self {
+        Arc::get_mut(&mut self.handlers)
+            .expect("handlers are only shared after subscribing")
+            .push(handler);
+        self
+    }
+}
+
+/// panic 信息：`panic!` 的字符串参数，其他类型的负载无法显示
+fn panic_reason(payload: &(dyn Any + Send)) -> String {
+    if let Some(reason) = payload.downcast_ref::<&str>() {
+        reason.to_string()
+    } else if let Some(reason) = payload.downcast_ref::<String>() {
+        reason.clone()
+    } else {
+        "non-string panic payload".to_string()
+    }
+}
+
+fn snippet(message: &Message) -> String {
+    let text = format!("{message:?}");
+    match text.char_indices().nth(PAYLOAD_SNIPPET_CHARS) {
+        Some((end, _)) => format!("{}…", &text[..end]),
+        None => text,
+    }
+}
+
+fn guard(
+    channel: &Channel,
+    callback: Callback,
+    max_consecutive: u32,
+    handlers: Arc<Vec<PanicHandler>>,
+) -> Callback {
+    let (name, market) = channel_key(channel);
+    let consecutive = AtomicU32::new(0);
+    let disabled = AtomicBool::new(false);
+    Box::new(move |message| {
+        if disabled.load(Ordering::Relaxed) {
+            return;
+        }
+        let payload = match catch_unwind(AssertUnwindSafe(|| callback(message))) {
+            Ok(()) => {
+                consecutive.store(0, Ordering::Relaxed);
+                return;
+            }
+            Err(payload) => payload,
+        };
+        let count = consecutive.fetch_add(1, Ordering::Relaxed) + 1;
+        let event = CallbackPanic {
+            channel: name,
+            market: market.clone(),
+            reason: panic_reason(payload.as_ref()),
+            payload: snippet(message),
+            consecutive: count,
+            disabled: count >= max_consecutive,
+        };
+        metrics().callback_panic(name.cli_name());
+        error!(
+            "{} callback panicked ({} in a row): {}; message: {}",
+            name.cli_name(),
+            count,
+            event.reason,
+            event.payload
+        );
+        if event.disabled {
+            disabled.store(true, Ordering::Relaxed);
+            error!(
+                "DISABLED the {} handler{} after {} consecutive panics; its messages are dropped until restart",
+                name.cli_name(),
+                market.as_ref().map_or(String::new(), |market| format!(" for {market}")),
+                count
+            );
+        }
+        for handler in handlers.iter() {
+            handler(&event);
+        }
+    })
+}
+
+#[async_trait]
+impl MarketDataSource for GuardedSource {
+    async fn subscribe(
+        &self,
+        channel: Channel,
+        callback: Callback,
+    ) -> Result<SubscriptionId, Error> {
+        let callback = guard(
+            &channel,
+            callback,
+            self.max_consecutive,
+            self.handlers.clone(),
+        );
+        self.inner.subscribe(channel, callback).await
+    }
+
+    async fn unsubscribe(&self, id: SubscriptionId) -> Result<(), Error> {
+        self.inner.unsubscribe(id).await
+    }
+
+    async fn stop(&self) -> Result<(), Error> {
+        self.inner.stop().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::market_data::Dispatching;
+    use paradex::structs::{Side, Trade, TradeType, BBO};
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    /// 由测试直接调用回调
+    #[derive(Default)]
+    struct ScriptedSource {
+        callbacks: Mutex<Vec<(WsChannel, Callback)>>,
+    }
+
+    impl ScriptedSource {
+        fn push(&self, channel: WsChannel, message: &Message) {
+            for (subscribed, callback) in self.callbacks.lock().unwrap().iter() {
+                if *subscribed == channel {
+                    callback(message);
+                }
+            }
+        }
+    }
+
+    #[async_trait]
+    impl MarketDataSource for ScriptedSource {
+        async fn subscribe(
+            &self,
+            channel: Channel,
+            callback: Callback,
+        ) -> Result<SubscriptionId, Error> {
+            let mut callbacks = self.callbacks.lock().unwrap();
+            callbacks.push((channel_key(&channel).0, callback));
+            Ok(SubscriptionId::Replay(callbacks.len() as u64))
+        }
+
+        async fn unsubscribe(&self, _id: SubscriptionId) -> Result<(), Error> {
+            Ok(())
+        }
+
+        async fn stop(&self) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+
+    fn trade(id: u32) -> Message {
+        Message::Trades(Trade {
+            created_at: 1735689600000,
+            id: id.to_string(),
+            market: "BTC-USD-PERP".to_string(),
+            price: 95000.0,
+            side: Side::BUY,
+            size: 0.01,
+            trade_type: TradeType::FILL,
+        })
+    }
+
+    fn bbo() -> Message {
+        Message::BBO(BBO {
+            bid: 95000.0,
+            bid_size: 1.0,
+            ask: 95001.0,
+            ask_size: 1.0,
+            market: "BTC-USD-PERP".to_string(),
+            last_updated_at: 1735689600000,
+        })
+    }
+
+    fn trades() -> Channel {
+        Channel::Trades {
+            market_symbol: "BTC-USD-PERP".to_string(),
+        }
+    }
+
+    fn bbo_channel() -> Channel {
+        Channel::BBO {
+            market_symbol: "BTC-USD-PERP".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn panicking_handler_is_disabled_while_other_channels_continue() {
+        let scripted = Arc::new(ScriptedSource::default());
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink = events.clone();
+        let source = GuardedSource::new(scripted.clone())
+            .with_max_consecutive_panics(3)
+            .with_panic_handler(Box::new(move |event| {
+                sink.lock().unwrap().push(event.clone())
+            }));
+
+        let calls = Arc::new(AtomicU32::new(0));
+        let counted = calls.clone();
+        source
+            .subscribe(
+                trades(),
+                Box::new(move |_| {
+                    counted.fetch_add(1, Ordering::Relaxed);
+                    panic!("strategy bug");
+                }),
+            )
+            .await
+            .unwrap();
+        let quotes = Arc::new(AtomicU32::new(0));
+        let received = quotes.clone();
+        source
+            .subscribe(
+                bbo_channel(),
+                Box::new(move |_| {
+                    received.fetch_add(1, Ordering::Relaxed);
+                }),
+            )
+            .await
+            .unwrap();
+
+        for id in 1..=5 {
+            scripted.push(WsChannel::Trades, &trade(id));
+            scripted.push(WsChannel::Bbo, &bbo());
+        }
+        // 第 3 次连续 panic 后停用，其余消息不再交给该回调
+        assert_eq!(calls.load(Ordering::Relaxed), 3);
+        assert_eq!(quotes.load(Ordering::Relaxed), 5);
+
+        let events = events.lock().unwrap();
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[0].channel, WsChannel::Trades);
+        assert_eq!(events[0].market.as_deref(), Some("BTC-USD-PERP"));
+        assert_eq!(events[0].reason, "strategy bug");
+        assert!(
+            events[0].payload.starts_with("Trades(Trade {"),
+            "{}",
+            events[0].payload
+        );
+        assert_eq!(
+            events
+                .iter()
+                .map(|e| (e.consecutive, e.disabled))
+                .collect::<Vec<_>>(),
+            [(1, false), (2, false), (3, true)]
+        );
+    }
+
+    #[tokio::test]
+    async fn successful_messages_reset_the_panic_count() {
+        let scripted = Arc::new(ScriptedSource::default());
+        let source = GuardedSource::new(scripted.clone()).with_max_consecutive_panics(2);
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let sink = seen.clone();
+        source
+            .subscribe(
+                trades(),
+                Box::new(move |message| {
+                    let Message::Trades(trade) = message else {
+                        return;
+                    };
+                    if trade.id.parse::<u32>().unwrap() % 2 == 1 {
+                        panic!("odd trade {}", trade.id);
+                    }
+                    sink.lock().unwrap().push(trade.id.clone());
+                }),
+            )
+            .await
+            .unwrap();
+        for id in 1..=6 {
+            scripted.push(WsChannel::Trades, &trade(id));
+        }
+        assert_eq!(*seen.lock().unwrap(), ["2", "4", "6"]);
+    }
+
+    #[tokio::test]
+    async fn dispatch_task_survives_a_panicking_callback() {
+        let scripted = Arc::new(ScriptedSource::default());
+        let dispatching = Arc::new(Dispatching::new(scripted.clone(), 16));
+        let source = GuardedSource::new(dispatching.clone());
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let sink = seen.clone();
+        source
+            .subscribe(
+                trades(),
+                Box::new(move |message| {
+                    if let Message::Trades(trade) = message {
+                        assert_ne!(trade.id, "1", "first trade rejected");
+                        sink.lock().unwrap().push(trade.id.clone());
+                    }
+                }),
+            )
+            .await
+            .unwrap();
+        for id in 1..=3 {
+            scripted.push(WsChannel::Trades, &trade(id));
+        }
+        tokio::time::timeout(Duration::from_secs(1), async {
+            while dispatching.counters().get(WsChannel::Trades).processed < 3 {
+                tokio::task::yield_now().await;
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!(*seen.lock().unwrap(), ["2", "3"]);
+    }
+}
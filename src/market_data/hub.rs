@@ -0,0 +1,259 @@
+//! 以异步流消费订阅：每个订阅对应一个有界通道，流被丢弃时自动取消订阅
+
+use futures_util::Stream;
+use log::{debug, warn};
+use paradex::{
+    error::Error,
+    ws::{Channel, Message},
+};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tokio::sync::mpsc::{self, error::TrySendError};
+
+use super::{channel_key, MarketDataSource, SubscriptionId};
+use crate::config::WsChannel;
+
+/// 每个订阅流默认缓存的消息数
+pub const DEFAULT_STREAM_CAPACITY: usize = 1024;
+
+/// 订阅流中的一条消息，附带所属频道与市场
+#[derive(Debug, Clone)]
+pub struct ChannelMessage {
+    pub channel: WsChannel,
+    /// 按市场订阅的频道对应的市场；全市场订阅为 `None`
+    pub market: Option<String>,
+    pub message: Message,
+}
+
+/// 在 [`MarketDataSource`] 之上提供流式订阅；回调式的 `subscribe` 仍可直接使用
+#[derive(Clone)]
+pub struct SubscriptionHub {
+    source: Arc<dyn MarketDataSource>,
+    capacity: usize,
+}
+
+impl SubscriptionHub {
+    /// 每个订阅流使用默认缓存大小
+    pub fn new(source: Arc<dyn MarketDataSource>) -> Self {
+        Self::with_capacity(source, DEFAULT_STREAM_CAPACITY)
+    }
+
+    /// `capacity` 为每个订阅流缓存的消息数
+    pub fn with_capacity(source: Arc<dyn MarketDataSource>, capacity: usize) -> Self {
+        Self {
+            source,
+            capacity: capacity.max(1),
+        }
+    }
+
+    /// 底层行情源
+    pub fn source(&self) -> &Arc<dyn MarketDataSource> {
+        &self.source
+    }
+
+    /// 订阅 `channel`，返回订阅 ID 与消息流
+    ///
+    /// 回调在 WebSocket 读取任务中同步执行，不能等待消费者：通道已满时丢弃新消息并记录警告，
+    /// 消费者应及时读取。流被丢弃时取消订阅。
+    pub async fn subscribe_stream(
+        &self,
+        channel: Channel,
+    ) -> Result<(SubscriptionId, MessageStream), Error> {
+        let (channel_name, market) = channel_key(&channel);
+        let (sender, receiver) = mpsc::channel(self.capacity);
+        let lagging = AtomicBool::new(false);
+        let id = self
+            .source
+            .subscribe(
+                channel,
+                Box::new(move |message| {
+                    let item = ChannelMessage {
+                        channel: channel_name,
+                        market: market.clone(),
+                        message: message.clone(),
+                    };
+                    match sender.try_send(item) {
+                        Ok(()) => lagging.store(false, Ordering::Relaxed),
+                        // 每段连续丢弃只警告一次
+                        Err(TrySendError::Full(_)) if !lagging.swap(true, Ordering::Relaxed) => {
+                            warn!(
+                                "{} stream consumer is lagging, dropping messages",
+                                channel_name.cli_name()
+                            )
+                        }
+                        Err(_) => {}
+                    }
+                }),
+            )
+            .await?;
+        let stream = MessageStream {
+            receiver,
+            subscription: Some((self.source.clone(), id)),
+        };
+        Ok((id, stream))
+    }
+}
+
+/// 订阅消息流；丢弃时取消对应的订阅
+pub struct MessageStream {
+    receiver: mpsc::Receiver<ChannelMessage>,
+    subscription: Option<(Arc<dyn MarketDataSource>, SubscriptionId)>,
+}
+
+impl MessageStream {
+    /// 取消订阅并等待完成
+    pub async fn close(mut self) -> Result<(), Error> {
+        self.receiver.close();
+        match self.subscription.take() {
+            Some((source, id)) => source.unsubscribe(id).await,
+            None => Ok(()),
+        }
+    }
+}
+
+impl Stream for MessageStream {
+    type Item = ChannelMessage;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.receiver.poll_recv(cx)
+    }
+}
+
+impl Drop for MessageStream {
+    fn drop(&mut self) {
+        let Some((source, id)) = self.subscription.take() else {
+            return;
+        };
+        // 运行时已关闭时连接随之关闭，无需取消订阅
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            return;
+        };
+        runtime.spawn(async move {
+            if let Err(e) = source.unsubscribe(id).await {
+                debug!("Failed to unsubscribe dropped stream {:?}: {}", id, e);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::market_data::Callback;
+    use async_trait::async_trait;
+    use futures_util::StreamExt;
+    use paradex::structs::{Side, Trade, TradeType};
+    use std::sync::Mutex;
+
+    /// 记录订阅与取消订阅，由测试直接调用回调
+    #[derive(Default)]
+    struct ScriptedSource {
+        callbacks: Mutex<Vec<(u64, Callback)>>,
+        unsubscribed: Mutex<Vec<SubscriptionId>>,
+    }
+
+    impl ScriptedSource {
+        fn push(&self, message: &Message) {
+            for (_, callback) in self.callbacks.lock().unwrap().iter() {
+                callback(message);
+            }
+        }
+    }
+
+    #[async_trait]
+    impl MarketDataSource for ScriptedSource {
+        async fn subscribe(
+            &self,
+            _channel: Channel,
+            callback: Callback,
+        ) -> Result<SubscriptionId, Error> {
+            let mut callbacks = self.callbacks.lock().unwrap();
+            let id = callbacks.len() as u64 + 1;
+            callbacks.push((id, callback));
+            Ok(SubscriptionId::Replay(id))
+        }
+
+        async fn unsubscribe(&self, id: SubscriptionId) -> Result<(), Error> {
+            self.unsubscribed.lock().unwrap().push(id);
+            self.callbacks
+                .lock()
+                .unwrap()
+                .retain(|(own, _)| SubscriptionId::Replay(*own) != id);
+            Ok(())
+        }
+
+        async fn stop(&self) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+
+    fn trade(id: &str) -> Message {
+        Message::Trades(Trade {
+            created_at: 1735689600000,
+            id: id.to_string(),
+            market: "BTC-USD-PERP".to_string(),
+            price: 95000.0,
+            side: Side::BUY,
+            size: 0.01,
+            trade_type: TradeType::FILL,
+        })
+    }
+
+    fn trades_channel() -> Channel {
+        Channel::Trades {
+            market_symbol: "BTC-USD-PERP".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn stream_yields_messages_and_drops_when_full() {
+        let source = Arc::new(ScriptedSource::default());
+        let hub = SubscriptionHub::with_capacity(source.clone(), 2);
+        let (_, mut stream) = hub.subscribe_stream(trades_channel()).await.unwrap();
+
+        for id in ["1", "2", "3"] {
+            source.push(&trade(id));
+        }
+        let first = stream.next().await.unwrap();
+        assert_eq!(first.channel, WsChannel::Trades);
+        assert_eq!(first.market.as_deref(), Some("BTC-USD-PERP"));
+        assert!(matches!(first.message, Message::Trades(ref t) if t.id == "1"));
+        assert!(
+            matches!(stream.next().await.unwrap().message, Message::Trades(ref t) if t.id == "2")
+        );
+
+        // 第三条在通道满时被丢弃；消费后继续接收
+        source.push(&trade("4"));
+        assert!(
+            matches!(stream.next().await.unwrap().message, Message::Trades(ref t) if t.id == "4")
+        );
+
+        stream.close().await.unwrap();
+        assert_eq!(
+            *source.unsubscribed.lock().unwrap(),
+            [SubscriptionId::Replay(1)]
+        );
+    }
+
+    #[tokio::test]
+    async fn dropping_a_stream_unsubscribes() {
+        let source = Arc::new(ScriptedSource::default());
+        let hub = SubscriptionHub::new(source.clone());
+        let (kept_id, kept) = hub.subscribe_stream(trades_channel()).await.unwrap();
+        let (dropped_id, dropped) = hub.subscribe_stream(Channel::MarketSummary).await.unwrap();
+        drop(dropped);
+
+        for _ in 0..10 {
+            if !source.unsubscribed.lock().unwrap().is_empty() {
+                break;
+            }
+            tokio::task::yield_now().await;
+        }
+        assert_eq!(*source.unsubscribed.lock().unwrap(), [dropped_id]);
+        assert_ne!(kept_id, dropped_id);
+        assert_eq!(source.callbacks.lock().unwrap().len(), 1);
+        drop(kept);
+    }
+}
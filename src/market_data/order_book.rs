@@ -0,0 +1,1389 @@
+//! 本地订单簿：以快照为基准，按 `seq_no` 顺序应用 `order_book_deltas` 增量
+//!
+//! 序号出现缺口（断线重连等原因丢失增量）时订单簿标记为未同步：缓存此后的增量，
+//! 重新拉取 REST 快照，再应用序号更新的缓存增量。
+//!
+//! 每条增量应用后检查交叉 / 锁定的盘口、负数量与中间价跳变：订单簿标记为可疑并发出 [`Anomaly`]，
+//! 直到后续增量恢复正常或重新建立快照。
+
+use chrono::{DateTime, Utc};
+use log::{debug, info, warn};
+use paradex::structs::{Level as DeltaLevel, OrderBook, OrderBookUpdateType, Side};
+use reqwest::Client as HttpClient;
+use rust_decimal::Decimal;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fmt;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use crate::http::HttpError;
+use crate::markets::decimal;
+use crate::metrics::metrics;
+use crate::orderbook::{fetch_orderbook, Level, OrderBookSnapshot, DEPTH_RANGE};
+
+use super::Signals;
+
+/// 允许乱序到达的增量数：缓存的超前增量达到该数量仍未补齐时视为缺口
+pub const REORDER_WINDOW: usize = 8;
+/// 记录最近若干条增量改动的档位，供审计排除快照前后正在变动的档位
+const RECENT_DELTAS: usize = 256;
+/// 重新同步时最多尝试拉取快照的次数
+const RESYNC_ATTEMPTS: u32 = 3;
+const RESYNC_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// 应用一条订单簿消息的结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApplyOutcome {
+    /// 已应用（快照或连续的增量，以及因此可以继续应用的缓存增量）
+    Applied,
+    /// `seq_no` 不大于当前序号，已忽略
+    Duplicate,
+    /// 尚未建立快照、正在重新同步或序号不连续，暂存等待
+    Buffered,
+    /// 检测到序号缺口，订单簿进入重新同步；调用方需拉取快照并调用 [`LocalOrderBook::seed`]
+    Gap,
+    /// 不属于该市场的消息
+    Ignored,
+}
+
+/// 按订单簿逐档吃单的成交估算
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExecEstimate {
+    /// 可成交部分的成交均价
+    pub avg_price: Decimal,
+    /// 吃到的最远一档价格
+    pub worst_price: Decimal,
+    pub levels_consumed: usize,
+    /// 可成交的数量；流动性不足时小于下单数量
+    pub filled: Decimal,
+    /// 对手方全部档位不足以成交下单数量
+    pub insufficient_liquidity: bool,
+}
+
+impl ExecEstimate {
+    /// 成交均价相对 `mid` 向不利方向偏离的基点数，价格改善时为负
+    pub fn slippage_bps(&self, side: Side, mid: Decimal) -> Decimal {
+        let diff = match side {
+            Side::BUY => self.avg_price - mid,
+            Side::SELL => mid - self.avg_price,
+        };
+        diff / mid * Decimal::from(10_000)
+    }
+}
+
+/// 订单簿异常的类型
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AnomalyKind {
+    /// 最优买价高于最优卖价
+    Crossed { bid: Decimal, ask: Decimal },
+    /// 最优买价等于最优卖价
+    Locked { price: Decimal },
+    /// 增量中数量为负的档位，已忽略
+    NegativeSize {
+        side: Side,
+        price: Decimal,
+        size: Decimal,
+    },
+    /// 中间价相对上一次的变动（百分比）超过阈值
+    PriceJump {
+        from: Decimal,
+        to: Decimal,
+        pct: Decimal,
+    },
+}
+
+impl AnomalyKind {
+    /// 指标标签
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Crossed { .. } => "crossed",
+            Self::Locked { .. } => "locked",
+            Self::NegativeSize { .. } => "negative_size",
+            Self::PriceJump { .. } => "price_jump",
+        }
+    }
+}
+
+/// 应用某条增量后检测到的订单簿异常
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Anomaly {
+    pub market: String,
+    pub seq_no: u64,
+    pub kind: AnomalyKind,
+}
+
+impl fmt::Display for Anomaly {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} order book ", self.market)?;
+        match &self.kind {
+            AnomalyKind::Crossed { bid, ask } => write!(f, "crossed (bid {bid} > ask {ask})")?,
+            AnomalyKind::Locked { price } => write!(f, "locked at {price}")?,
+            AnomalyKind::NegativeSize { side, price, size } => {
+                write!(f, "got negative size {size} for {side:?} {price}")?
+            }
+            AnomalyKind::PriceJump { from, to, pct } => {
+                write!(f, "mid jumped {}% from {from} to {to}", pct.round_dp(2))?
+            }
+        }
+        write!(f, " after delta {}", self.seq_no)
+    }
+}
+
+/// 订单簿异常的回调
+pub type AnomalyCallback = Box<dyn Fn(&Anomaly) + Send + Sync>;
+
+/// 订单簿的同步状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SyncState {
+    /// 尚未收到快照
+    Unseeded,
+    Synced,
+    /// 断线或快照后仍有缺口；下一次缺口立即触发重新同步
+    Stale,
+    /// 等待新快照，期间的增量全部缓存
+    Resyncing,
+}
+
+/// 单个市场的本地订单簿
+#[derive(Debug, Clone)]
+pub struct LocalOrderBook {
+    market: String,
+    bids: BTreeMap<Decimal, Decimal>,
+    asks: BTreeMap<Decimal, Decimal>,
+    state: SyncState,
+    /// 最后应用的序号；REST 快照未带序号时为 `None`，以下一条增量为准
+    seq_no: Option<u64>,
+    /// 序号超前的增量，按序号排列
+    pending: BTreeMap<u64, OrderBook>,
+    last_resync_at: Option<DateTime<Utc>>,
+    /// 中间价单次变动超过该百分比视为异常；为 `None` 时不检查
+    max_price_jump_pct: Option<Decimal>,
+    /// 尚未恢复的异常
+    suspect: Option<Anomaly>,
+    /// 尚未取走的异常
+    anomalies: Vec<Anomaly>,
+    /// 聚合档位的价格间隔；设置时深度与成交估算按档位合并
+    price_tick: Option<Decimal>,
+    /// 最近应用的增量序号及其改动的档位
+    recent: VecDeque<(u64, Vec<(Side, Decimal)>)>,
+}
+
+impl LocalOrderBook {
+    /// `market` 的空订单簿，等待快照
+    pub fn new(market: impl Into<String>) -> Self {
+        Self {
+            market: market.into(),
+            bids: BTreeMap::new(),
+            asks: BTreeMap::new(),
+            state: SyncState::Unseeded,
+            seq_no: None,
+            pending: BTreeMap::new(),
+            last_resync_at: None,
+            max_price_jump_pct: None,
+            suspect: None,
+            anomalies: Vec::new(),
+            price_tick: None,
+            recent: VecDeque::new(),
+        }
+    }
+
+    /// 以 `tick` 为间隔的聚合订单簿：买价向下、卖价向上取整到档位后合并数量，
+    /// 成交估算以档位边界（对吃单方最不利的价格）计价
+    pub fn with_price_tick(mut self, tick: Decimal) -> Self {
+        self.price_tick = Some(tick);
+        self
+    }
+
+    /// 价格最小变动单位；尚未设置时为 `None`
+    pub fn price_tick(&self) -> Option<Decimal> {
+        self.price_tick
+    }
+
+    /// 中间价单次变动超过 `pct`% 时视为异常
+    pub fn with_max_price_jump(mut self, pct: Decimal) -> Self {
+        self.max_price_jump_pct = Some(pct);
+        self
+    }
+
+    /// 订单簿所属市场
+    pub fn market(&self) -> &str {
+        &self.market
+    }
+
+    /// 是否已由快照建立（此后可能因缺口而未同步）
+    pub fn is_seeded(&self) -> bool {
+        self.state != SyncState::Unseeded
+    }
+
+    /// 订单簿是否与交易所一致；为 `false` 时策略应暂停报价
+    pub fn is_synced(&self) -> bool {
+        self.state == SyncState::Synced
+    }
+
+    /// 最近一次由快照（重新）建立订单簿的时间
+    pub fn last_resync_at(&self) -> Option<DateTime<Utc>> {
+        self.last_resync_at
+    }
+
+    /// 最近应用的快照或增量的序号
+    pub fn seq_no(&self) -> Option<u64> {
+        self.seq_no
+    }
+
+    /// 订单簿是否可疑（检测到异常后尚未恢复）；为 `true` 时应暂停下单
+    pub fn is_suspect(&self) -> bool {
+        self.suspect.is_some()
+    }
+
+    /// 使订单簿进入可疑状态的异常
+    pub fn anomaly(&self) -> Option<&Anomaly> {
+        self.suspect.as_ref()
+    }
+
+    /// 序号在 `seq_no` 之后的已应用增量改动的档位；记录不足以追溯到 `seq_no` 时为 `None`
+    pub fn touched_since(&self, seq_no: u64) -> Option<Vec<(Side, Decimal)>> {
+        if self.seq_no? == seq_no {
+            return Some(Vec::new());
+        }
+        let (oldest, _) = self.recent.front()?;
+        if *oldest > seq_no + 1 {
+            return None;
+        }
+        Some(
+            self.recent
+                .iter()
+                .filter(|(applied, _)| *applied > seq_no)
+                .flat_map(|(_, levels)| levels.iter().copied())
+                .collect(),
+        )
+    }
+
+    /// 取走此前检测到的异常
+    pub fn take_anomalies(&mut self) -> Vec<Anomaly> {
+        std::mem::take(&mut self.anomalies)
+    }
+
+    /// 以 REST 快照（`GET /orderbook/{market}`）为基准，随后应用序号更新的缓存增量；
+    /// 返回是否已同步（缓存的增量与快照之间仍有缺口时为 `false`）
+    pub fn seed(&mut self, snapshot: &OrderBookSnapshot) -> bool {
+        let levels = |levels: &[Level]| {
+            levels
+                .iter()
+                .filter(|level| !level.size().is_zero())
+                .map(|level| (level.price(), level.size()))
+                .collect()
+        };
+        self.bids = levels(&snapshot.bids);
+        self.asks = levels(&snapshot.asks);
+        self.reset_to(snapshot.seq_no)
+    }
+
+    /// 应用 `order_book_deltas` 频道的消息（首条为快照，其后为增量）
+    pub fn apply(&mut self, message: &OrderBook) -> ApplyOutcome {
+        if message.market != self.market {
+            return ApplyOutcome::Ignored;
+        }
+        if message.update_type == OrderBookUpdateType::Snapshot {
+            self.bids.clear();
+            self.asks.clear();
+            self.apply_levels(message);
+            self.reset_to(Some(message.seq_no));
+            return ApplyOutcome::Applied;
+        }
+        if self.seq_no.is_some_and(|seq_no| message.seq_no <= seq_no) {
+            debug!(
+                "Dropping {} delta {} (book at {:?})",
+                self.market, message.seq_no, self.seq_no
+            );
+            return ApplyOutcome::Duplicate;
+        }
+        if matches!(self.state, SyncState::Unseeded | SyncState::Resyncing) {
+            self.pending.insert(message.seq_no, message.clone());
+            return ApplyOutcome::Buffered;
+        }
+        if self
+            .seq_no
+            .is_some_and(|seq_no| message.seq_no > seq_no + 1)
+        {
+            self.pending.insert(message.seq_no, message.clone());
+            if self.state == SyncState::Stale || self.pending.len() >= REORDER_WINDOW {
+                self.state = SyncState::Resyncing;
+                return ApplyOutcome::Gap;
+            }
+            return ApplyOutcome::Buffered;
+        }
+        self.apply_delta(message);
+        self.drain_pending();
+        ApplyOutcome::Applied
+    }
+
+    /// 连接断开：此后的第一次缺口立即触发重新同步
+    pub fn mark_stale(&mut self) {
+        if self.state == SyncState::Synced {
+            self.state = SyncState::Stale;
+        }
+    }
+
+    /// 拉取快照失败：保留缓存的增量，下一条增量再次触发重新同步
+    pub fn resync_failed(&mut self) {
+        if self.state == SyncState::Resyncing {
+            self.state = SyncState::Stale;
+        }
+    }
+
+    /// 最优买价
+    pub fn best_bid(&self) -> Option<Decimal> {
+        self.bids.keys().next_back().copied()
+    }
+
+    /// 最优卖价
+    pub fn best_ask(&self) -> Option<Decimal> {
+        self.asks.keys().next().copied()
+    }
+
+    /// 最优买卖价的中间价；任一侧为空时为 `None`
+    pub fn mid(&self) -> Option<Decimal> {
+        Some((self.best_bid()? + self.best_ask()?) / Decimal::TWO)
+    }
+
+    /// 每侧最多 `n` 档，按离盘口由近到远排列：`(bids, asks)`；聚合订单簿按档位合并
+    pub fn depth(&self, n: usize) -> (Vec<Level>, Vec<Level>) {
+        let side = |side| {
+            self.levels(side)
+                .into_iter()
+                .take(n)
+                .map(|(price, size)| Level(price, size))
+                .collect()
+        };
+        (side(Side::BUY), side(Side::SELL))
+    }
+
+    /// 每侧最多 `n` 档的快照，附最后应用的序号，可据此与增量流对齐
+    pub fn snapshot(&self, n: usize) -> OrderBookSnapshot {
+        let (bids, asks) = self.depth(n);
+        OrderBookSnapshot {
+            market: self.market.clone(),
+            seq_no: self.seq_no,
+            last_updated_at: None,
+            bids,
+            asks,
+        }
+    }
+
+    /// 以 `side` 方向吃单 `size` 的成交估算：买单逐档吃卖盘、卖单逐档吃买盘；
+    /// 对手方为空或 `size` 不为正时为 `None`
+    pub fn execution_price(&self, side: Side, size: Decimal) -> Option<ExecEstimate> {
+        if size <= Decimal::ZERO {
+            return None;
+        }
+        let levels = match side {
+            Side::BUY => self.levels(Side::SELL),
+            Side::SELL => self.levels(Side::BUY),
+        };
+        let mut remaining = size;
+        let mut notional = Decimal::ZERO;
+        let mut worst_price = None;
+        let mut levels_consumed = 0;
+        for (price, available) in levels
+            .into_iter()
+            .filter(|(_, available)| !available.is_zero())
+        {
+            if remaining.is_zero() {
+                break;
+            }
+            let take = remaining.min(available);
+            notional += take * price;
+            remaining -= take;
+            worst_price = Some(price);
+            levels_consumed += 1;
+        }
+        let worst_price = worst_price?;
+        let filled = size - remaining;
+        Some(ExecEstimate {
+            avg_price: notional / filled,
+            worst_price,
+            levels_consumed,
+            filled,
+            insufficient_liquidity: !remaining.is_zero(),
+        })
+    }
+
+    /// `side` 一侧的档位，按离盘口由近到远排列；聚合订单簿按档位合并
+    fn levels(&self, side: Side) -> Vec<(Decimal, Decimal)> {
+        let raw: Box<dyn Iterator<Item = (&Decimal, &Decimal)>> = match side {
+            Side::BUY => Box::new(self.bids.iter().rev()),
+            Side::SELL => Box::new(self.asks.iter()),
+        };
+        let Some(tick) = self.price_tick else {
+            return raw.map(|(price, size)| (*price, *size)).collect();
+        };
+        let mut levels: Vec<(Decimal, Decimal)> = Vec::new();
+        for (price, size) in raw {
+            let bucket = match side {
+                Side::BUY => (price / tick).floor() * tick,
+                Side::SELL => (price / tick).ceil() * tick,
+            };
+            match levels.last_mut() {
+                Some((last, total)) if *last == bucket => *total += size,
+                _ => levels.push((bucket, *size)),
+            }
+        }
+        levels
+    }
+
+    /// 建立快照后丢弃过期的缓存增量，并应用紧随快照的增量；返回是否已同步
+    fn reset_to(&mut self, seq_no: Option<u64>) -> bool {
+        self.seq_no = seq_no;
+        self.last_resync_at = Some(Utc::now());
+        self.recent.clear();
+        if let Some(anomaly) = self.suspect.take() {
+            info!(
+                "{} order book rebuilt from a snapshot after: {}",
+                self.market, anomaly
+            );
+        }
+        if let Some(seq_no) = seq_no {
+            self.pending = self.pending.split_off(&(seq_no + 1));
+        }
+        self.drain_pending();
+        self.state = if self.pending.is_empty() {
+            SyncState::Synced
+        } else {
+            SyncState::Stale
+        };
+        self.is_synced()
+    }
+
+    fn drain_pending(&mut self) {
+        while let Some(entry) = self.pending.first_entry() {
+            let next = match self.seq_no {
+                Some(seq_no) => seq_no + 1,
+                None => *entry.key(),
+            };
+            if *entry.key() != next {
+                break;
+            }
+            let delta = entry.remove();
+            self.apply_delta(&delta);
+        }
+    }
+
+    /// 应用一条连续的增量并检查异常；未检测到异常时解除可疑状态
+    fn apply_delta(&mut self, delta: &OrderBook) {
+        let before = self.mid();
+        let mut found: Vec<AnomalyKind> = delta
+            .inserts
+            .iter()
+            .chain(&delta.updates)
+            .map(|level| (level, decimal(level.size)))
+            .filter(|(_, size)| *size < Decimal::ZERO)
+            .map(|(level, size)| AnomalyKind::NegativeSize {
+                side: level.side,
+                price: decimal(level.price),
+                size,
+            })
+            .collect();
+        self.apply_levels(delta);
+        self.seq_no = Some(delta.seq_no);
+        if self.recent.len() == RECENT_DELTAS {
+            self.recent.pop_front();
+        }
+        let touched = delta
+            .deletes
+            .iter()
+            .chain(&delta.inserts)
+            .chain(&delta.updates);
+        self.recent.push_back((
+            delta.seq_no,
+            touched
+                .map(|level| (level.side, decimal(level.price)))
+                .collect(),
+        ));
+
+        match (self.best_bid(), self.best_ask()) {
+            (Some(bid), Some(ask)) if bid > ask => found.push(AnomalyKind::Crossed { bid, ask }),
+            (Some(bid), Some(ask)) if bid == ask => found.push(AnomalyKind::Locked { price: bid }),
+            _ => {}
+        }
+        if let (Some(limit), Some(from), Some(to)) = (self.max_price_jump_pct, before, self.mid()) {
+            if from > Decimal::ZERO {
+                let pct = ((to - from) / from * Decimal::ONE_HUNDRED).abs();
+                if pct > limit {
+                    found.push(AnomalyKind::PriceJump { from, to, pct });
+                }
+            }
+        }
+
+        if found.is_empty() {
+            if let Some(anomaly) = self.suspect.take() {
+                info!("{} order book recovered from: {}", self.market, anomaly);
+            }
+            return;
+        }
+        debug!(
+            "{} anomalous delta {}: {:?}",
+            self.market, delta.seq_no, delta
+        );
+        let anomalies: Vec<_> = found
+            .into_iter()
+            .map(|kind| Anomaly {
+                market: self.market.clone(),
+                seq_no: delta.seq_no,
+                kind,
+            })
+            .collect();
+        self.suspect = Some(anomalies[0].clone());
+        self.anomalies.extend(anomalies);
+    }
+
+    fn apply_levels(&mut self, message: &OrderBook) {
+        for level in &message.deletes {
+            self.side(level.side).remove(&decimal(level.price));
+        }
+        for level in message.inserts.iter().chain(&message.updates) {
+            self.set_level(level);
+        }
+    }
+
+    /// 数量为 0 的档位视为删除，数量为负的档位忽略
+    fn set_level(&mut self, level: &DeltaLevel) {
+        let (price, size) = (decimal(level.price), decimal(level.size));
+        let side = self.side(level.side);
+        if size.is_zero() {
+            side.remove(&price);
+        } else if size > Decimal::ZERO {
+            side.insert(price, size);
+        }
+    }
+
+    fn side(&mut self, side: Side) -> &mut BTreeMap<Decimal, Decimal> {
+        match side {
+            Side::BUY => &mut self.bids,
+            Side::SELL => &mut self.asks,
+        }
+    }
+}
+
+/// 可在多个任务间共享读取的订单簿
+pub type SharedOrderBook = Arc<RwLock<LocalOrderBook>>;
+
+/// 重新同步时拉取 REST 快照的接口地址
+#[derive(Debug, Clone)]
+struct SnapshotSource {
+    http_client: HttpClient,
+    base_url: String,
+}
+
+/// 聚合订单簿的键：市场与价格档位
+type BookKey = (String, Option<Decimal>);
+
+/// 订单簿异常的订阅者，克隆后共享
+#[derive(Clone, Default)]
+struct AnomalyHandlers(Arc<RwLock<Vec<AnomalyCallback>>>);
+
+impl fmt::Debug for AnomalyHandlers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "AnomalyHandlers({})", self.0.read().unwrap().len())
+    }
+}
+
+/// 每个订阅市场一本订单簿；克隆后共享同一组订单簿，可在运行中增删市场
+#[derive(Debug, Clone, Default)]
+pub struct OrderBooks {
+    books: Arc<RwLock<HashMap<String, SharedOrderBook>>>,
+    snapshots: Option<SnapshotSource>,
+    signals: Option<Signals>,
+    max_price_jump_pct: Option<Decimal>,
+    anomaly_handlers: AnomalyHandlers,
+    /// `order_book` 频道按（市场，价格档位）维护的订单簿，档位为 `None` 表示交易所默认精度
+    aggregated: Arc<RwLock<HashMap<BookKey, SharedOrderBook>>>,
+}
+
+impl OrderBooks {
+    /// 维护 `markets` 的订单簿，均等待快照
+    pub fn new(markets: &[String]) -> Self {
+        Self {
+            books: Arc::new(RwLock::new(
+                markets
+                    .iter()
+                    .map(|market| {
+                        let book = LocalOrderBook::new(market.clone());
+                        (market.clone(), Arc::new(RwLock::new(book)))
+                    })
+                    .collect(),
+            )),
+            snapshots: None,
+            signals: None,
+            max_price_jump_pct: None,
+            anomaly_handlers: AnomalyHandlers::default(),
+            aggregated: Arc::default(),
+        }
+    }
+
+    /// 出现缺口时从 `base_url` 拉取 REST 快照重新同步；未设置时等待频道推送新快照
+    pub fn with_rest_resync(mut self, base_url: &str) -> Self {
+        self.snapshots = Some(SnapshotSource {
+            http_client: HttpClient::new(),
+            base_url: base_url.to_string(),
+        });
+        self
+    }
+
+    /// 每次应用更新后重新计算订单簿信号
+    pub fn with_signals(mut self, signals: Signals) -> Self {
+        self.signals = Some(signals);
+        self
+    }
+
+    /// 订单簿信号；未启用时为 `None`
+    pub fn signals(&self) -> Option<&Signals> {
+        self.signals.as_ref()
+    }
+
+    /// 中间价单次变动超过 `pct`% 时视为异常（含此后增加的市场）
+    pub fn with_max_price_jump(mut self, pct: Decimal) -> Self {
+        for book in self.books.read().unwrap().values() {
+            book.write().unwrap().max_price_jump_pct = Some(pct);
+        }
+        self.max_price_jump_pct = Some(pct);
+        self
+    }
+
+    /// 检测到订单簿异常时调用 `callback`（在应用增量的任务中）
+    pub fn on_anomaly(&self, callback: AnomalyCallback) {
+        self.anomaly_handlers.0.write().unwrap().push(callback);
+    }
+
+    /// `market` 的订单簿
+    pub fn get(&self, market: &str) -> Option<SharedOrderBook> {
+        self.books.read().unwrap().get(market).cloned()
+    }
+
+    /// 开始维护 `market` 的订单簿（等待快照）；已存在时保留原订单簿
+    pub fn add_market(&self, market: &str) -> SharedOrderBook {
+        self.books
+            .write()
+            .unwrap()
+            .entry(market.to_string())
+            .or_insert_with(|| {
+                let mut book = LocalOrderBook::new(market);
+                book.max_price_jump_pct = self.max_price_jump_pct;
+                Arc::new(RwLock::new(book))
+            })
+            .clone()
+    }
+
+    /// 以价格档位 `tick` 订阅的 `order_book` 频道维护的订单簿
+    pub fn get_at(&self, market: &str, tick: Option<Decimal>) -> Option<SharedOrderBook> {
+        self.aggregated
+            .read()
+            .unwrap()
+            .get(&(market.to_string(), tick))
+            .cloned()
+    }
+
+    /// 应用以价格档位 `tick` 订阅的 `order_book` 频道消息；同一市场的不同档位各自维护一本订单簿，
+    /// 首次收到时创建。未管理的市场返回 `Ignored`
+    pub fn apply_at(&self, tick: Option<Decimal>, message: &OrderBook) -> ApplyOutcome {
+        if self.get(&message.market).is_none() {
+            return ApplyOutcome::Ignored;
+        }
+        let book = self
+            .aggregated
+            .write()
+            .unwrap()
+            .entry((message.market.clone(), tick))
+            .or_insert_with(|| {
+                let book = LocalOrderBook::new(message.market.clone());
+                let book = match tick {
+                    Some(tick) => book.with_price_tick(tick),
+                    None => book,
+                };
+                Arc::new(RwLock::new(book))
+            })
+            .clone();
+        let outcome = book.write().unwrap().apply(message);
+        outcome
+    }
+
+    /// 丢弃 `market` 的订单簿与信号；此后该市场的消息返回 `Ignored`
+    pub fn remove_market(&self, market: &str) -> Option<SharedOrderBook> {
+        if let Some(ref signals) = self.signals {
+            signals.remove(market);
+        }
+        self.aggregated
+            .write()
+            .unwrap()
+            .retain(|(book_market, _), _| book_market != market);
+        self.books.write().unwrap().remove(market)
+    }
+
+    /// 把消息交给对应市场的订单簿，出现缺口时开始重新同步；未管理的市场返回 `Ignored`
+    pub fn apply(&self, message: &OrderBook) -> ApplyOutcome {
+        let Some(book) = self.get(&message.market) else {
+            return ApplyOutcome::Ignored;
+        };
+        let (outcome, anomalies) = {
+            let mut book = book.write().unwrap();
+            let outcome = book.apply(message);
+            (outcome, book.take_anomalies())
+        };
+        if let (ApplyOutcome::Applied, Some(signals)) = (outcome, &self.signals) {
+            signals.update(&book.read().unwrap());
+        }
+        for anomaly in &anomalies {
+            warn!("{}, marking the book suspect", anomaly);
+            metrics().orderbook_anomaly(anomaly.kind.label());
+            for handler in self.anomaly_handlers.0.read().unwrap().iter() {
+                handler(anomaly);
+            }
+        }
+        if outcome == ApplyOutcome::Gap {
+            warn!(
+                "Sequence gap in {} order book before delta {}, resynchronizing",
+                message.market, message.seq_no
+            );
+            metrics().orderbook_resync();
+            if let Some(ref snapshots) = self.snapshots {
+                tokio::spawn(resync(snapshots.clone(), book.clone()));
+            }
+        }
+        outcome
+    }
+
+    /// 连接断开时标记 `market` 的订单簿
+    pub fn mark_stale(&self, market: &str) {
+        if let Some(book) = self.get(market) {
+            book.write().unwrap().mark_stale();
+        }
+    }
+
+    /// 重新连接后强制已建立的订单簿重新同步；未设置 REST 快照时等待频道推送新快照
+    pub fn resync_all(&self) {
+        let books: Vec<_> = self.books.read().unwrap().values().cloned().collect();
+        for book in books {
+            self.force_resync(&book);
+        }
+    }
+
+    /// 强制 `market` 的订单簿重新同步（尚未建立时忽略）
+    pub fn resync(&self, market: &str) {
+        if let Some(book) = self.get(market) {
+            self.force_resync(&book);
+        }
+    }
+
+    fn force_resync(&self, book: &SharedOrderBook) {
+        {
+            let mut book = book.write().unwrap();
+            if !book.is_seeded() {
+                return;
+            }
+            book.state = SyncState::Resyncing;
+        }
+        metrics().orderbook_resync();
+        if let Some(ref snapshots) = self.snapshots {
+            tokio::spawn(resync(snapshots.clone(), book.clone()));
+        }
+    }
+
+    /// 管理的市场
+    pub fn markets(&self) -> Vec<String> {
+        let mut markets: Vec<_> = self.books.read().unwrap().keys().cloned().collect();
+        markets.sort();
+        markets
+    }
+
+    /// 拉取 `market` 每侧 `depth` 档的 REST 快照；未设置 REST 快照来源时为 `None`
+    pub(super) async fn fetch_snapshot(
+        &self,
+        market: &str,
+        depth: u32,
+    ) -> Option<Result<OrderBookSnapshot, HttpError>> {
+        let snapshots = self.snapshots.as_ref()?;
+        Some(fetch_orderbook(&snapshots.http_client, &snapshots.base_url, market, depth).await)
+    }
+
+    /// 是否设置了 REST 快照来源
+    pub fn has_rest_snapshots(&self) -> bool {
+        self.snapshots.is_some()
+    }
+}
+
+/// 拉取 REST 快照并应用缓存的增量，失败时重试
+async fn resync(snapshots: SnapshotSource, book: SharedOrderBook) {
+    let market = book.read().unwrap().market().to_string();
+    for attempt in 1..=RESYNC_ATTEMPTS {
+        match fetch_orderbook(
+            &snapshots.http_client,
+            &snapshots.base_url,
+            &market,
+            *DEPTH_RANGE.end(),
+        )
+        .await
+        {
+            Ok(snapshot) => {
+                let mut book = book.write().unwrap();
+                // 期间频道已推送新快照
+                if book.state != SyncState::Resyncing {
+                    return;
+                }
+                if book.seed(&snapshot) {
+                    info!(
+                        "{} order book resynchronized at seq {:?}",
+                        market,
+                        book.seq_no()
+                    );
+                    return;
+                }
+                // 快照早于缓存中最早的增量，之间仍有缺口
+                book.state = SyncState::Resyncing;
+                warn!(
+                    "{} snapshot at seq {:?} does not reach buffered deltas (attempt {}/{})",
+                    market, snapshot.seq_no, attempt, RESYNC_ATTEMPTS
+                );
+            }
+            Err(e) => warn!(
+                "Failed to fetch {} order book snapshot (attempt {}/{}): {}",
+                market, attempt, RESYNC_ATTEMPTS, e
+            ),
+        }
+        tokio::time::sleep(RESYNC_RETRY_DELAY).await;
+    }
+    warn!("Giving up resynchronizing {} order book", market);
+    book.write().unwrap().resync_failed();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::onboarding::mock_server::MockServer;
+
+    const MARKET: &str = "BTC-USD-PERP";
+
+    fn level(side: Side, price: f64, size: f64) -> DeltaLevel {
+        DeltaLevel { side, price, size }
+    }
+
+    fn message(
+        seq_no: u64,
+        update_type: OrderBookUpdateType,
+        inserts: Vec<DeltaLevel>,
+        updates: Vec<DeltaLevel>,
+        deletes: Vec<DeltaLevel>,
+    ) -> OrderBook {
+        OrderBook {
+            seq_no,
+            market: MARKET.to_string(),
+            last_updated_at: 1735689600000 + seq_no,
+            update_type,
+            deletes,
+            inserts,
+            updates,
+        }
+    }
+
+    fn delta(seq_no: u64, updates: Vec<DeltaLevel>) -> OrderBook {
+        message(seq_no, OrderBookUpdateType::Delta, vec![], updates, vec![])
+    }
+
+    fn levels(levels: &[(i64, i64)]) -> Vec<Level> {
+        levels
+            .iter()
+            .map(|(price, size)| Level(Decimal::from(*price), Decimal::from(*size)))
+            .collect()
+    }
+
+    #[test]
+    fn scripted_deltas_build_the_expected_book() {
+        let mut book = LocalOrderBook::new(MARKET);
+        // 快照之前的增量先缓存
+        assert_eq!(
+            book.apply(&delta(11, vec![level(Side::BUY, 99.0, 4.0)])),
+            ApplyOutcome::Buffered
+        );
+        assert_eq!(book.best_bid(), None);
+
+        let snapshot = message(
+            10,
+            OrderBookUpdateType::Snapshot,
+            vec![
+                level(Side::BUY, 100.0, 1.0),
+                level(Side::BUY, 99.0, 2.0),
+                level(Side::BUY, 98.0, 3.0),
+                level(Side::SELL, 101.0, 1.0),
+                level(Side::SELL, 102.0, 2.0),
+            ],
+            vec![],
+            vec![],
+        );
+        assert_eq!(book.apply(&snapshot), ApplyOutcome::Applied);
+        // 缓存的 11 紧随快照，已应用：99 的数量更新为 4
+        assert_eq!(book.seq_no(), Some(11));
+
+        // 13 超前：等待 12
+        let thirteen = message(
+            13,
+            OrderBookUpdateType::Delta,
+            vec![level(Side::SELL, 100.5, 5.0)],
+            vec![],
+            vec![level(Side::SELL, 102.0, 0.0)],
+        );
+        assert_eq!(book.apply(&thirteen), ApplyOutcome::Buffered);
+        assert_eq!(book.best_ask(), Some(Decimal::from(101)));
+
+        // 12 删除 100 的买单（数量为 0），随后 13 一并应用
+        assert_eq!(
+            book.apply(&delta(12, vec![level(Side::BUY, 100.0, 0.0)])),
+            ApplyOutcome::Applied
+        );
+        assert_eq!(book.seq_no(), Some(13));
+        // 重复的增量被忽略
+        assert_eq!(
+            book.apply(&delta(12, vec![level(Side::BUY, 100.0, 7.0)])),
+            ApplyOutcome::Duplicate
+        );
+
+        assert_eq!(book.best_bid(), Some(Decimal::from(99)));
+        assert_eq!(book.best_ask(), Some(Decimal::new(1005, 1)));
+        assert_eq!(book.mid(), Some(Decimal::new(9975, 2)));
+        let (bids, asks) = book.depth(5);
+        assert_eq!(bids, levels(&[(99, 4), (98, 3)]));
+        assert_eq!(
+            asks,
+            vec![
+                Level(Decimal::new(1005, 1), Decimal::from(5)),
+                Level(Decimal::from(101), Decimal::ONE),
+            ]
+        );
+        assert_eq!(book.depth(1).0, levels(&[(99, 4)]));
+
+        // 新快照替换全部档位
+        let resnapshot = message(
+            20,
+            OrderBookUpdateType::Snapshot,
+            vec![level(Side::BUY, 90.0, 1.0)],
+            vec![],
+            vec![],
+        );
+        book.apply(&resnapshot);
+        assert_eq!(book.depth(5), (levels(&[(90, 1)]), vec![]));
+        assert_eq!(book.mid(), None);
+    }
+
+    #[test]
+    fn rest_snapshot_seeds_and_drops_stale_deltas() {
+        let books = OrderBooks::new(&[MARKET.to_string()]);
+        assert_eq!(
+            books.apply(&delta(5, vec![level(Side::BUY, 100.0, 9.0)])),
+            ApplyOutcome::Buffered
+        );
+        assert_eq!(
+            books.apply(&delta(7, vec![level(Side::SELL, 101.0, 3.0)])),
+            ApplyOutcome::Buffered
+        );
+        let mut other = delta(1, vec![]);
+        other.market = "ETH-USD-PERP".to_string();
+        assert_eq!(books.apply(&other), ApplyOutcome::Ignored);
+
+        let shared = books.get(MARKET).unwrap();
+        shared.write().unwrap().seed(&OrderBookSnapshot {
+            market: MARKET.to_string(),
+            seq_no: Some(6),
+            last_updated_at: None,
+            bids: levels(&[(100, 1)]),
+            asks: levels(&[(101, 1)]),
+        });
+        let book = shared.read().unwrap();
+        // 5 早于快照被丢弃，7 紧随快照被应用
+        assert_eq!(book.seq_no(), Some(7));
+        assert_eq!(book.depth(5), (levels(&[(100, 1)]), levels(&[(101, 3)])));
+    }
+
+    /// 快照 10 与增量 11 之后丢失 12，随后收到 13..=21
+    fn book_with_gap() -> LocalOrderBook {
+        let mut book = LocalOrderBook::new(MARKET);
+        book.apply(&message(
+            10,
+            OrderBookUpdateType::Snapshot,
+            vec![level(Side::BUY, 100.0, 1.0), level(Side::SELL, 120.0, 1.0)],
+            vec![],
+            vec![],
+        ));
+        book.apply(&delta(11, vec![level(Side::BUY, 11.0, 11.0)]));
+        for seq_no in 13..=21 {
+            let price = seq_no as f64;
+            let outcome = book.apply(&delta(seq_no, vec![level(Side::BUY, price, price)]));
+            let expected = match seq_no - 12 {
+                n if n < REORDER_WINDOW as u64 => ApplyOutcome::Buffered,
+                n if n == REORDER_WINDOW as u64 => ApplyOutcome::Gap,
+                _ => ApplyOutcome::Buffered,
+            };
+            assert_eq!(outcome, expected, "delta {}", seq_no);
+        }
+        assert!(!book.is_synced());
+        assert_eq!(book.seq_no(), Some(11));
+        book
+    }
+
+    fn rest_snapshot(seq_no: u64) -> OrderBookSnapshot {
+        OrderBookSnapshot {
+            market: MARKET.to_string(),
+            seq_no: Some(seq_no),
+            last_updated_at: None,
+            bids: levels(&[(100, 2)]),
+            asks: levels(&[(120, 1)]),
+        }
+    }
+
+    #[test]
+    fn gap_resyncs_from_snapshot_and_replays_newer_deltas() {
+        let mut book = book_with_gap();
+        let before = book.last_resync_at();
+
+        // 快照 11 与缓存的 13 之间仍缺 12
+        assert!(!book.seed(&rest_snapshot(11)));
+        assert!(!book.is_synced());
+
+        // 快照 15 之后重放 16..=21，早于快照的 13..=15 被丢弃
+        assert!(book.seed(&rest_snapshot(15)));
+        assert!(book.is_synced());
+        assert!(book.last_resync_at() > before);
+        assert_eq!(book.seq_no(), Some(21));
+        assert_eq!(
+            book.depth(4),
+            (
+                levels(&[(100, 2), (21, 21), (20, 20), (19, 19)]),
+                levels(&[(120, 1)])
+            )
+        );
+        assert_eq!(book.depth(10).0.len(), 7);
+    }
+
+    #[test]
+    fn disconnect_makes_the_next_gap_resync_immediately() {
+        let mut book = LocalOrderBook::new(MARKET);
+        book.seed(&rest_snapshot(5));
+        book.mark_stale();
+        assert!(!book.is_synced());
+        assert_eq!(book.apply(&delta(6, vec![])), ApplyOutcome::Applied);
+        assert_eq!(book.apply(&delta(8, vec![])), ApplyOutcome::Gap);
+        book.resync_failed();
+        assert_eq!(book.apply(&delta(9, vec![])), ApplyOutcome::Gap);
+    }
+
+    #[test]
+    fn execution_price_walks_the_opposite_side() {
+        let mut book = LocalOrderBook::new(MARKET);
+        assert_eq!(book.execution_price(Side::BUY, Decimal::ONE), None);
+        book.seed(&OrderBookSnapshot {
+            market: MARKET.to_string(),
+            seq_no: Some(1),
+            last_updated_at: None,
+            bids: levels(&[(99, 2), (98, 3)]),
+            asks: levels(&[(101, 1), (102, 2), (104, 4)]),
+        });
+
+        // 恰好吃完前两档：(101 + 102 * 2) / 3
+        let exact = book.execution_price(Side::BUY, Decimal::from(3)).unwrap();
+        assert_eq!(exact.avg_price, Decimal::from(305) / Decimal::from(3));
+        assert_eq!(exact.worst_price, Decimal::from(102));
+        assert_eq!(exact.levels_consumed, 2);
+        assert!(!exact.insufficient_liquidity);
+
+        // 最后一档只吃一部分：(99 * 2 + 98 * 2) / 4
+        let partial = book.execution_price(Side::SELL, Decimal::from(4)).unwrap();
+        assert_eq!(partial.avg_price, Decimal::new(985, 1));
+        assert_eq!(partial.worst_price, Decimal::from(98));
+        assert_eq!(partial.levels_consumed, 2);
+        assert_eq!(partial.filled, Decimal::from(4));
+        // 中间价 100：卖出均价 98.5 偏离 150 基点
+        assert_eq!(
+            partial.slippage_bps(Side::SELL, Decimal::from(100)),
+            Decimal::from(150)
+        );
+
+        // 超出全部卖盘：按可成交的 7 估算并标记流动性不足
+        let short = book.execution_price(Side::BUY, Decimal::from(10)).unwrap();
+        assert!(short.insufficient_liquidity);
+        assert_eq!(short.filled, Decimal::from(7));
+        assert_eq!(short.worst_price, Decimal::from(104));
+        assert_eq!(short.levels_consumed, 3);
+        assert_eq!(book.execution_price(Side::BUY, Decimal::ZERO), None);
+
+        book.seed(&OrderBookSnapshot {
+            market: MARKET.to_string(),
+            seq_no: Some(2),
+            last_updated_at: None,
+            bids: levels(&[(99, 2)]),
+            asks: vec![],
+        });
+        assert_eq!(book.execution_price(Side::BUY, Decimal::ONE), None);
+    }
+
+    /// 快照 10：买 100 × 1，卖 101 × 1
+    fn seeded_book() -> LocalOrderBook {
+        let mut book = LocalOrderBook::new(MARKET).with_max_price_jump(Decimal::from(5));
+        book.apply(&message(
+            10,
+            OrderBookUpdateType::Snapshot,
+            vec![level(Side::BUY, 100.0, 1.0), level(Side::SELL, 101.0, 1.0)],
+            vec![],
+            vec![],
+        ));
+        book
+    }
+
+    #[test]
+    fn anomalous_deltas_mark_the_book_suspect() {
+        let price = |value: i64| Decimal::from(value);
+        let kinds = |book: &mut LocalOrderBook| -> Vec<AnomalyKind> {
+            book.take_anomalies()
+                .into_iter()
+                .map(|anomaly| anomaly.kind)
+                .collect()
+        };
+
+        // 交叉：买 102 高于卖 101；随后删除 102 后恢复
+        let mut book = seeded_book();
+        book.apply(&delta(11, vec![level(Side::BUY, 102.0, 1.0)]));
+        assert_eq!(
+            kinds(&mut book),
+            [AnomalyKind::Crossed {
+                bid: price(102),
+                ask: price(101)
+            }]
+        );
+        assert_eq!(book.anomaly().map(|anomaly| anomaly.seq_no), Some(11));
+        book.apply(&delta(12, vec![level(Side::BUY, 102.0, 0.0)]));
+        assert!(!book.is_suspect());
+        assert!(kinds(&mut book).is_empty());
+
+        // 锁定：买卖价均为 101；新快照解除可疑状态
+        let mut book = seeded_book();
+        book.apply(&delta(11, vec![level(Side::BUY, 101.0, 2.0)]));
+        assert_eq!(
+            kinds(&mut book),
+            [AnomalyKind::Locked { price: price(101) }]
+        );
+        assert!(book.is_suspect());
+        book.seed(&OrderBookSnapshot {
+            market: MARKET.to_string(),
+            seq_no: Some(20),
+            last_updated_at: None,
+            bids: levels(&[(100, 1)]),
+            asks: levels(&[(101, 1)]),
+        });
+        assert!(!book.is_suspect());
+
+        // 负数量的档位被忽略
+        let mut book = seeded_book();
+        book.apply(&delta(11, vec![level(Side::SELL, 101.0, -3.0)]));
+        assert_eq!(
+            kinds(&mut book),
+            [AnomalyKind::NegativeSize {
+                side: Side::SELL,
+                price: price(101),
+                size: price(-3),
+            }]
+        );
+        assert_eq!(book.best_ask(), Some(price(101)));
+        assert_eq!(book.depth(1).1, levels(&[(101, 1)]));
+
+        // 中间价从 100.5 跳到 110.5（约 9.95%），超过 5%
+        let mut book = seeded_book();
+        let jump = message(
+            11,
+            OrderBookUpdateType::Delta,
+            vec![level(Side::BUY, 110.0, 1.0), level(Side::SELL, 111.0, 1.0)],
+            vec![],
+            vec![level(Side::BUY, 100.0, 0.0), level(Side::SELL, 101.0, 0.0)],
+        );
+        book.apply(&jump);
+        let [AnomalyKind::PriceJump { from, to, pct }] = kinds(&mut book)[..] else {
+            panic!("expected a price jump");
+        };
+        assert_eq!((from, to), (Decimal::new(1005, 1), Decimal::new(1105, 1)));
+        assert_eq!(pct.round_dp(2), Decimal::new(995, 2));
+        // 小幅变动不视为异常，可疑状态随之解除
+        book.apply(&delta(12, vec![level(Side::SELL, 110.8, 1.0)]));
+        assert!(!book.is_suspect());
+    }
+
+    #[test]
+    fn order_books_report_anomalies_to_subscribers() {
+        let books = OrderBooks::new(&[MARKET.to_string()]).with_max_price_jump(Decimal::ONE);
+        let seen = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let handler_seen = seen.clone();
+        books.on_anomaly(Box::new(move |anomaly| {
+            handler_seen.lock().unwrap().push(anomaly.kind.label())
+        }));
+        books.apply(&message(
+            10,
+            OrderBookUpdateType::Snapshot,
+            vec![level(Side::BUY, 100.0, 1.0), level(Side::SELL, 101.0, 1.0)],
+            vec![],
+            vec![],
+        ));
+        assert_eq!(
+            books.apply(&delta(11, vec![level(Side::BUY, 101.0, 1.0)])),
+            ApplyOutcome::Applied
+        );
+        assert_eq!(*seen.lock().unwrap(), ["locked"]);
+        assert!(books.get(MARKET).unwrap().read().unwrap().is_suspect());
+
+        // 此后增加的市场使用同一阈值
+        let added = books.add_market("ETH-USD-PERP");
+        assert_eq!(added.read().unwrap().max_price_jump_pct, Some(Decimal::ONE));
+    }
+
+    #[test]
+    fn price_tick_aggregates_levels_into_buckets() {
+        let snapshot = message(
+            1,
+            OrderBookUpdateType::Snapshot,
+            vec![
+                level(Side::BUY, 100.4, 1.0),
+                level(Side::BUY, 100.1, 2.0),
+                level(Side::BUY, 99.9, 3.0),
+                level(Side::SELL, 100.6, 1.0),
+                level(Side::SELL, 100.9, 1.0),
+                level(Side::SELL, 101.2, 4.0),
+            ],
+            vec![],
+            vec![],
+        );
+        let mut book = LocalOrderBook::new(MARKET).with_price_tick(Decimal::new(5, 1));
+        book.apply(&snapshot);
+        let bucket = |price: i64, size: i64| Level(Decimal::new(price, 1), Decimal::from(size));
+        // 买价向下、卖价向上取整到 0.5
+        assert_eq!(
+            book.depth(5),
+            (
+                vec![bucket(1000, 3), bucket(995, 3)],
+                vec![bucket(1010, 2), bucket(1015, 4)]
+            )
+        );
+        assert_eq!(book.depth(1).1, vec![bucket(1010, 2)]);
+        // 成交估算按档位边界计价：2 在 101.0，1 在 101.5
+        let estimate = book.execution_price(Side::BUY, Decimal::from(3)).unwrap();
+        assert_eq!(estimate.worst_price, Decimal::new(1015, 1));
+        assert_eq!(estimate.levels_consumed, 2);
+        assert_eq!(estimate.avg_price, Decimal::new(3035, 1) / Decimal::from(3));
+
+        // 未聚合时保留原始档位
+        let mut raw = LocalOrderBook::new(MARKET);
+        raw.apply(&snapshot);
+        assert_eq!(raw.depth(5).0.len(), 3);
+        assert_eq!(
+            raw.execution_price(Side::BUY, Decimal::ONE)
+                .unwrap()
+                .worst_price,
+            Decimal::new(1006, 1)
+        );
+    }
+
+    #[test]
+    fn each_price_tick_keeps_its_own_book() {
+        let books = OrderBooks::new(&[MARKET.to_string()]);
+        let coarse = Some(Decimal::ONE);
+        let fine = Some(Decimal::new(1, 1));
+        let snapshot = |seq_no, price| {
+            message(
+                seq_no,
+                OrderBookUpdateType::Snapshot,
+                vec![level(Side::BUY, price, 1.0)],
+                vec![],
+                vec![],
+            )
+        };
+        assert_eq!(
+            books.apply_at(coarse, &snapshot(1, 100.0)),
+            ApplyOutcome::Applied
+        );
+        assert_eq!(
+            books.apply_at(fine, &snapshot(1, 100.3)),
+            ApplyOutcome::Applied
+        );
+
+        let best_bid = |tick| {
+            let book = books.get_at(MARKET, tick).unwrap();
+            let book = book.read().unwrap();
+            (book.price_tick(), book.best_bid())
+        };
+        assert_eq!(best_bid(coarse), (coarse, Some(Decimal::from(100))));
+        assert_eq!(best_bid(fine), (fine, Some(Decimal::new(1003, 1))));
+        assert!(books.get_at(MARKET, None).is_none());
+        // 增量维护的订单簿不受影响
+        assert!(!books.get(MARKET).unwrap().read().unwrap().is_seeded());
+
+        let mut other = snapshot(1, 10.0);
+        other.market = "ETH-USD-PERP".to_string();
+        assert_eq!(books.apply_at(coarse, &other), ApplyOutcome::Ignored);
+        books.remove_market(MARKET);
+        assert!(books.get_at(MARKET, coarse).is_none());
+    }
+
+    #[test]
+    fn reconnect_forces_seeded_books_to_resync() {
+        let markets = [MARKET.to_string(), "ETH-USD-PERP".to_string()];
+        let books = OrderBooks::new(&markets);
+        books
+            .get(MARKET)
+            .unwrap()
+            .write()
+            .unwrap()
+            .seed(&rest_snapshot(5));
+        books.resync_all();
+
+        // 已建立的订单簿缓存增量直到新快照到达；尚未建立的不受影响
+        let shared = books.get(MARKET).unwrap();
+        assert!(!shared.read().unwrap().is_synced());
+        assert_eq!(books.apply(&delta(6, vec![])), ApplyOutcome::Buffered);
+        assert!(!books
+            .get("ETH-USD-PERP")
+            .unwrap()
+            .read()
+            .unwrap()
+            .is_seeded());
+
+        let snapshot = message(9, OrderBookUpdateType::Snapshot, vec![], vec![], vec![]);
+        assert_eq!(books.apply(&snapshot), ApplyOutcome::Applied);
+        assert!(shared.read().unwrap().is_synced());
+    }
+
+    #[tokio::test]
+    async fn order_books_fetch_a_snapshot_on_gap() {
+        let server = MockServer::start(|request| {
+            assert_eq!(request.path, "/orderbook/BTC-USD-PERP?depth=100");
+            let body = r#"{"market":"BTC-USD-PERP","seq_no":15,"bids":[["100","2"]],"asks":[["120","1"]]}"#;
+            (200, body.to_string())
+        })
+        .await;
+        let books = OrderBooks::new(&[MARKET.to_string()]).with_rest_resync(&server.url());
+        let shared = books.get(MARKET).unwrap();
+        // 上一次重新同步失败后，下一条超前的增量立即触发重新同步
+        let mut gap = book_with_gap();
+        gap.resync_failed();
+        *shared.write().unwrap() = gap;
+        assert_eq!(
+            books.apply(&delta(22, vec![level(Side::BUY, 22.0, 22.0)])),
+            ApplyOutcome::Gap
+        );
+        assert!(metrics()
+            .render()
+            .contains("# TYPE trade_lighter_orderbook_resyncs_total counter"));
+
+        for _ in 0..50 {
+            if shared.read().unwrap().is_synced() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        let book = shared.read().unwrap();
+        assert!(book.is_synced());
+        assert_eq!(book.seq_no(), Some(22));
+        assert_eq!(book.best_bid(), Some(Decimal::from(100)));
+        assert_eq!(server.requests().len(), 1);
+    }
+}
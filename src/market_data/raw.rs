@@ -0,0 +1,430 @@
+//! 按名称订阅 `paradex::ws::Channel` 未覆盖的频道（成交撤销 `trade_busts`、转账 `transfers`）
+//!
+//! SDK 的频道与消息枚举是封闭的，这里单独维护一条 JSON-RPC WebSocket 连接：
+//! 认证后按频道名订阅，按通知中的 `params.channel` 解析为 [`RawMessage`]；断线后自动重连并重新订阅。
+//! JWT 轮换时在同一连接上重新发送 auth 请求，不断开连接，因此不会漏掉轮换期间的推送。
+
+use async_trait::async_trait;
+use futures_util::{SinkExt, StreamExt};
+use log::{info, warn};
+use paradex::{rest::Client, url::URL};
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tokio_tungstenite::{connect_async, tungstenite::protocol::Message as WsMessage};
+use tokio_util::sync::CancellationToken;
+
+use crate::metrics::metrics;
+use crate::onboarding::JwtToken;
+
+/// 成交撤销频道名
+pub const TRADE_BUSTS: &str = "trade_busts";
+/// 转账频道名
+pub const TRANSFERS: &str = "transfers";
+
+/// 重连等待时间的上限，首次等待 1 秒，此后每次失败翻倍
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// 交易所撤销的成交
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct TradeBust {
+    pub account: String,
+    /// 被撤销成交的 ID，对应 `fills` 频道的 `id`
+    pub busted_fill_id: String,
+    pub created_at: u64,
+}
+
+/// 转账方向：转入或转出本账户
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum TransferDirection {
+    In,
+    Out,
+}
+
+/// 充值、提现与账户间转账
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Transfer {
+    pub id: String,
+    pub account: String,
+    /// DEPOSIT / WITHDRAWAL / ...
+    pub kind: String,
+    pub direction: TransferDirection,
+    /// PENDING / AVAILABLE / COMPLETED / FAILED
+    pub status: String,
+    pub token: String,
+    pub amount: Decimal,
+    pub created_at: u64,
+}
+
+impl Transfer {
+    /// 划转是否已完成
+    pub fn is_completed(&self) -> bool {
+        self.status == "COMPLETED"
+    }
+
+    /// 对余额的影响：转入为正，转出为负
+    pub fn signed_amount(&self) -> Decimal {
+        match self.direction {
+            TransferDirection::In => self.amount,
+            TransferDirection::Out => -self.amount,
+        }
+    }
+}
+
+/// 按名称订阅的频道推送的消息
+#[derive(Debug, Clone, PartialEq)]
+pub enum RawMessage {
+    TradeBust(TradeBust),
+    Transfer(Transfer),
+    /// 没有类型化处理的频道，保留原始数据
+    Other {
+        channel: String,
+        data: Value,
+    },
+}
+
+impl RawMessage {
+    /// 按频道名解析通知中的 `data`；频道名可带 `.` 后缀（如 `transfers.ALL`）
+    pub fn parse(channel: &str, data: Value) -> Result<Self, serde_json::Error> {
+        let base = channel.split('.').next().unwrap_or(channel);
+        Ok(match base {
+            TRADE_BUSTS => RawMessage::TradeBust(serde_json::from_value(data)?),
+            TRANSFERS => RawMessage::Transfer(serde_json::from_value(data)?),
+            _ => RawMessage::Other {
+                channel: channel.to_string(),
+                data,
+            },
+        })
+    }
+}
+
+/// 收到消息时的回调
+pub type RawCallback = Box<dyn Fn(&RawMessage) + Send + Sync + 'static>;
+
+/// 从一帧 JSON-RPC 文本中取出订阅通知的频道名与数据；请求响应等其他帧返回 `None`
+pub fn parse_notification(text: &str) -> Option<(String, Value)> {
+    let mut frame: Value = serde_json::from_str(text).ok()?;
+    let params = frame.get_mut("params")?;
+    let channel = params.get("channel")?.as_str()?.to_string();
+    let data = params.get_mut("data")?.take();
+    Some((channel, data))
+}
+
+fn request(id: u64, method: &str, params: Value) -> WsMessage {
+    WsMessage::text(
+        json!({"jsonrpc": "2.0", "id": id, "method": method, "params": params}).to_string(),
+    )
+}
+
+/// 认证私有频道使用的 JWT
+#[async_trait]
+trait BearerSource: Send + Sync {
+    /// 当前 JWT；`refresh` 为 `true` 时先重新认证
+    async fn bearer(&self, refresh: bool) -> Result<String, String>;
+}
+
+#[async_trait]
+impl BearerSource for Client {
+    async fn bearer(&self, refresh: bool) -> Result<String, String> {
+        if refresh {
+            self.refresh_jwt(true).await.map_err(|e| e.to_string())?;
+        }
+        self.jwt().await.map_err(|e| e.to_string())
+    }
+}
+
+/// 按名称订阅的一组频道，由后台任务维护连接
+pub struct RawChannels {
+    stop: CancellationToken,
+    task: JoinHandle<()>,
+}
+
+impl RawChannels {
+    /// 连接 `url` 并订阅 `channels`；订阅私有频道时传入已认证的 `client`，连接后先用 JWT 认证。
+    /// `token_updates` 每收到一个新 JWT，就以 `client` 重新认证后在当前连接上再次发送 auth
+    pub fn spawn(
+        url: URL,
+        client: Option<Client>,
+        token_updates: Option<watch::Receiver<JwtToken>>,
+        channels: Vec<String>,
+        callback: RawCallback,
+    ) -> Self {
+        let auth = client.map(|client| Arc::new(client) as Arc<dyn BearerSource>);
+        Self::spawn_at(
+            url.websocket().to_string(),
+            auth,
+            token_updates,
+            channels,
+            callback,
+        )
+    }
+
+    fn spawn_at(
+        ws_url: String,
+        auth: Option<Arc<dyn BearerSource>>,
+        mut token_updates: Option<watch::Receiver<JwtToken>>,
+        channels: Vec<String>,
+        callback: RawCallback,
+    ) -> Self {
+        let stop = CancellationToken::new();
+        let stopped = stop.clone();
+        let task = tokio::spawn(async move {
+            let mut backoff = Duration::from_secs(1);
+            loop {
+                let connection = run_connection(
+                    &ws_url,
+                    auth.as_deref(),
+                    &mut token_updates,
+                    &channels,
+                    &callback,
+                );
+                tokio::select! {
+                    result = connection => {
+                        match result {
+                            Ok(()) => {
+                                warn!("Raw channel connection closed, reconnecting");
+                                backoff = Duration::from_secs(1);
+                            }
+                            Err(e) => warn!("Raw channel connection failed: {}", e),
+                        }
+                    }
+                    _ = stopped.cancelled() => break,
+                }
+                tokio::select! {
+                    _ = tokio::time::sleep(backoff) => {}
+                    _ = stopped.cancelled() => break,
+                }
+                backoff = (backoff * 2).min(MAX_BACKOFF);
+            }
+        });
+        Self { stop, task }
+    }
+
+    /// 断开连接并等待后台任务结束
+    pub async fn close(self) {
+        self.stop.cancel();
+        if let Err(e) = self.task.await {
+            warn!("Raw channel task failed: {}", e);
+        }
+    }
+}
+
+/// 等待下一个 JWT；未订阅或刷新任务已结束时永不返回
+async fn next_token(token_updates: &mut Option<watch::Receiver<JwtToken>>) {
+    let Some(updates) = token_updates else {
+        return std::future::pending().await;
+    };
+    if updates.changed().await.is_err() {
+        *token_updates = None;
+        std::future::pending::<()>().await;
+    }
+}
+
+/// 单条连接的生命周期：认证、订阅、转发通知，直到连接关闭
+async fn run_connection(
+    ws_url: &str,
+    auth: Option<&dyn BearerSource>,
+    token_updates: &mut Option<watch::Receiver<JwtToken>>,
+    channels: &[String],
+    callback: &RawCallback,
+) -> Result<(), String> {
+    let (mut connection, _) = connect_async(ws_url).await.map_err(|e| e.to_string())?;
+    if let Some(auth) = auth {
+        // 新连接直接使用最新的 JWT，此前的轮换无需再处理
+        if let Some(updates) = token_updates {
+            updates.borrow_and_update();
+        }
+        let token = auth.bearer(false).await?;
+        connection
+            .send(request(0, "auth", json!({ "bearer": token })))
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+    for (id, channel) in channels.iter().enumerate() {
+        connection
+            .send(request(
+                id as u64 + 1,
+                "subscribe",
+                json!({ "channel": channel }),
+            ))
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+    info!("Subscribed to raw channels {}", channels.join(","));
+
+    let mut next_id = channels.len() as u64 + 1;
+    loop {
+        let frame = tokio::select! {
+            frame = connection.next() => frame,
+            _ = next_token(token_updates), if auth.is_some() => {
+                let token = auth.expect("guarded above").bearer(true).await?;
+                connection
+                    .send(request(next_id, "auth", json!({ "bearer": token })))
+                    .await
+                    .map_err(|e| e.to_string())?;
+                next_id += 1;
+                info!("JWT rotated, re-authenticated raw channel connection");
+                metrics().ws_reauth();
+                continue;
+            }
+        };
+        let Some(frame) = frame else {
+            break;
+        };
+        let text = match frame.map_err(|e| e.to_string())? {
+            WsMessage::Text(text) => text,
+            WsMessage::Close(_) => break,
+            _ => continue,
+        };
+        let Some((channel, data)) = parse_notification(&text) else {
+            continue;
+        };
+        match RawMessage::parse(&channel, data) {
+            Ok(message) => callback(&message),
+            Err(e) => warn!("Failed to parse {} message: {}", channel, e),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Mutex;
+    use tokio::net::TcpListener;
+    use tokio::sync::mpsc;
+
+    /// 每次重新认证返回编号递增的 token
+    #[derive(Default)]
+    struct CountingBearer(AtomicU32);
+
+    #[async_trait]
+    impl BearerSource for CountingBearer {
+        async fn bearer(&self, refresh: bool) -> Result<String, String> {
+            if refresh {
+                self.0.fetch_add(1, Ordering::SeqCst);
+            }
+            Ok(format!("token-{}", self.0.load(Ordering::SeqCst)))
+        }
+    }
+
+    /// 单连接的模拟 WebSocket 服务器：记录收到的请求，推送 `outgoing` 中的帧
+    async fn mock_server() -> (
+        String,
+        Arc<Mutex<Vec<Value>>>,
+        mpsc::UnboundedSender<String>,
+    ) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let (outgoing, mut pending) = mpsc::unbounded_channel::<String>();
+        let log = received.clone();
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut connection = tokio_tungstenite::accept_async(stream).await.unwrap();
+            loop {
+                tokio::select! {
+                    Some(Ok(WsMessage::Text(text))) = connection.next() => {
+                        log.lock().unwrap().push(serde_json::from_str(&text).unwrap());
+                    }
+                    Some(text) = pending.recv() => {
+                        connection.send(WsMessage::text(text)).await.unwrap();
+                    }
+                    else => break,
+                }
+            }
+        });
+        (url, received, outgoing)
+    }
+
+    async fn wait_until(condition: impl Fn() -> bool) {
+        for _ in 0..400 {
+            if condition() {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        panic!("condition was never met");
+    }
+
+    fn bearers(received: &Mutex<Vec<Value>>) -> Vec<String> {
+        received
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|request| request["method"] == "auth")
+            .map(|request| request["params"]["bearer"].as_str().unwrap().to_string())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn rotated_jwt_is_sent_on_the_open_connection() {
+        let (url, received, outgoing) = mock_server().await;
+        let (tokens, updates) = watch::channel(JwtToken::new("jwt-0".to_string(), 0, 0, "0x1"));
+        let busts = Arc::new(Mutex::new(Vec::new()));
+        let seen = busts.clone();
+        let channels = RawChannels::spawn_at(
+            url,
+            Some(Arc::new(CountingBearer::default())),
+            Some(updates),
+            vec![TRADE_BUSTS.to_string()],
+            Box::new(move |message| {
+                if let RawMessage::TradeBust(bust) = message {
+                    seen.lock().unwrap().push(bust.busted_fill_id.clone());
+                }
+            }),
+        );
+        wait_until(|| received.lock().unwrap().len() == 2).await;
+        assert_eq!(bearers(&received), ["token-0"]);
+        assert_eq!(
+            received.lock().unwrap()[1]["params"]["channel"],
+            TRADE_BUSTS
+        );
+
+        tokens
+            .send(JwtToken::new("jwt-1".to_string(), 1, 1, "0x1"))
+            .unwrap();
+        wait_until(|| bearers(&received).len() == 2).await;
+        assert_eq!(bearers(&received), ["token-0", "token-1"]);
+
+        // 重新认证后同一连接上的推送照常送达
+        let bust = r#"{"jsonrpc":"2.0","method":"subscription","params":{"channel":"trade_busts","data":{"account":"0x1","busted_fill_id":"f-1","created_at":1735689600000}}}"#;
+        outgoing.send(bust.to_string()).unwrap();
+        wait_until(|| busts.lock().unwrap().len() == 1).await;
+        assert_eq!(*busts.lock().unwrap(), ["f-1"]);
+        channels.close().await;
+    }
+
+    #[test]
+    fn parses_bust_and_transfer_notifications() {
+        let bust = r#"{"jsonrpc":"2.0","method":"subscription","params":{"channel":"trade_busts","data":{"account":"0x1","busted_fill_id":"f-1","created_at":1735689600000}}}"#;
+        let (channel, data) = parse_notification(bust).unwrap();
+        assert_eq!(
+            RawMessage::parse(&channel, data).unwrap(),
+            RawMessage::TradeBust(TradeBust {
+                account: "0x1".to_string(),
+                busted_fill_id: "f-1".to_string(),
+                created_at: 1735689600000,
+            })
+        );
+
+        let transfer = r#"{"jsonrpc":"2.0","method":"subscription","params":{"channel":"transfers","data":{"id":"t-1","account":"0x1","kind":"WITHDRAWAL","direction":"OUT","status":"COMPLETED","token":"USDC","amount":"12.5","created_at":1735689600000,"txn_hash":"0xabc"}}}"#;
+        let (channel, data) = parse_notification(transfer).unwrap();
+        let RawMessage::Transfer(transfer) = RawMessage::parse(&channel, data).unwrap() else {
+            panic!("expected a transfer");
+        };
+        assert!(transfer.is_completed());
+        assert_eq!(transfer.signed_amount(), Decimal::new(-125, 1));
+
+        // 请求响应不是订阅通知
+        assert!(parse_notification(r#"{"jsonrpc":"2.0","id":1,"result":{}}"#).is_none());
+        let other = RawMessage::parse("tradebusts.ALL", json!({"x": 1})).unwrap();
+        assert!(matches!(other, RawMessage::Other { .. }));
+    }
+}
@@ -0,0 +1,880 @@
+//! 断线自动重连：记住每个订阅的原始频道，连接断开或长时间没有消息时重建连接并重新订阅
+//!
+//! 订阅 ID 在重连前后保持不变；旧连接停止前推送的消息不再转发给回调。
+//!
+//! JWT 轮换后已认证的连接仍使用旧 token：[`Reconnecting::reauthenticate_on`] 收到新 token 时
+//! 在新连接上重新认证并订阅私有频道，确认后才取消旧连接上的私有订阅，行情订阅不受影响；
+//! 两条连接同时推送期间私有消息按 ID 去重。
+
+use async_trait::async_trait;
+use log::{debug, info, warn};
+use paradex::{
+    error::Error,
+    rest::Client,
+    url::URL,
+    ws::{Channel, Message, WebsocketManager},
+};
+use std::collections::{BTreeMap, HashSet};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use tokio::sync::{watch, Semaphore};
+use tokio_util::sync::CancellationToken;
+
+use super::{channel_key, Callback, Live, MarketDataSource, SubscriptionId};
+use crate::metrics::metrics;
+use crate::onboarding::JwtToken;
+
+/// JWT 轮换后等待新连接确认私有频道订阅的时长
+const REAUTH_CONFIRM_TIMEOUT: Duration = Duration::from_secs(10);
+/// 取消旧连接上的私有订阅后继续去重的时长，覆盖仍在途的消息
+const REAUTH_GRACE: Duration = Duration::from_secs(2);
+
+/// 建立一条新连接
+#[async_trait]
+pub trait Connector: Send + Sync {
+    async fn connect(&self) -> Result<Arc<dyn MarketDataSource>, Error>;
+}
+
+/// 每次连接创建新的 `WebsocketManager`；`client` 须为私有客户端，私有频道以新取得的 JWT 认证
+pub struct LiveConnector {
+    url: URL,
+    client: Option<Client>,
+}
+
+impl LiveConnector {
+    /// 连接 `url`；`client` 用于私有频道的鉴权
+    pub fn new(url: URL, client: Option<Client>) -> Self {
+        Self { url, client }
+    }
+}
+
+#[async_trait]
+impl Connector for LiveConnector {
+    async fn connect(&self) -> Result<Arc<dyn MarketDataSource>, Error> {
+        if let Some(ref client) = self.client {
+            client.refresh_jwt(true).await?;
+        }
+        let manager = WebsocketManager::new(self.url, self.client.clone()).await;
+        Ok(Arc::new(Live::new(manager)))
+    }
+}
+
+/// 重连策略
+#[derive(Debug, Clone)]
+pub struct ReconnectPolicy {
+    /// 首次重连前的等待时间，此后每次失败翻倍
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    /// 有订阅时超过该时长没有任何消息视为连接失效；`None` 不检查
+    pub silence_timeout: Option<Duration>,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+            silence_timeout: Some(Duration::from_secs(60)),
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    /// 第 `attempt` 次（从 0 开始）连续重连前的等待时间
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        self.initial_backoff
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_backoff)
+    }
+}
+
+/// 重连成功后调用，例如强制订单簿重新同步
+pub type ReconnectHook = Box<dyn Fn() + Send + Sync>;
+
+/// 断线后自动重建连接并重新订阅的行情来源
+pub struct Reconnecting {
+    shared: Arc<Shared>,
+    stop: CancellationToken,
+}
+
+struct Shared {
+    connector: Box<dyn Connector>,
+    policy: ReconnectPolicy,
+    connection: tokio::sync::Mutex<Connection>,
+    signals: Arc<Signals>,
+    next_id: AtomicU64,
+    hooks: Mutex<Vec<ReconnectHook>>,
+}
+
+/// 当前连接与其上的订阅
+struct Connection {
+    source: Arc<dyn MarketDataSource>,
+    /// JWT 轮换后承载私有频道订阅的连接；为 `None` 时私有频道与行情在同一连接上
+    private_source: Option<Arc<dyn MarketDataSource>>,
+    subscriptions: BTreeMap<u64, Subscription>,
+}
+
+impl Connection {
+    /// 订阅所在的连接
+    fn source_of(&self, subscription: &Subscription) -> &Arc<dyn MarketDataSource> {
+        match self.private_source {
+            Some(ref private) if subscription.private => private,
+            _ => &self.source,
+        }
+    }
+}
+
+struct Subscription {
+    channel: Channel,
+    /// 私有频道，JWT 轮换时迁移到新连接
+    private: bool,
+    callback: Arc<Mutex<Callback>>,
+    inner: SubscriptionId,
+    /// 置位后该内层订阅只转发数据消息（迁移期间），不再转发连接状态
+    retired: Arc<AtomicBool>,
+}
+
+/// 回调向监控任务报告的连接状态；不持有连接，避免循环引用
+struct Signals {
+    /// 当前连接的代数，每次重连加一
+    generation: AtomicU64,
+    last_message: Mutex<Instant>,
+    /// 自上次收到行情以来的连续重连次数
+    attempts: AtomicU32,
+    disconnects: UnboundedSender<u64>,
+    transition: Mutex<Transition>,
+}
+
+/// JWT 轮换迁移私有订阅的过程
+#[derive(Default)]
+struct Transition {
+    /// 每次迁移加一，过期的结束请求不影响之后的迁移
+    epoch: u64,
+    /// 迁移期间已转发的私有消息（订阅 ID 与消息 ID）；不在迁移中为 `None`
+    seen: Option<HashSet<(u64, String)>>,
+}
+
+impl Reconnecting {
+    /// 建立首个连接并开始监控
+    pub async fn connect(
+        connector: impl Connector + 'static,
+        policy: ReconnectPolicy,
+    ) -> Result<Self, Error> {
+        let source = connector.connect().await?;
+        let (disconnects, receiver) = mpsc::unbounded_channel();
+        let shared = Arc::new(Shared {
+            connector: Box::new(connector),
+            policy,
+            connection: tokio::sync::Mutex::new(Connection {
+                source,
+                private_source: None,
+                subscriptions: BTreeMap::new(),
+            }),
+            signals: Arc::new(Signals {
+                generation: AtomicU64::new(0),
+                last_message: Mutex::new(Instant::now()),
+                attempts: AtomicU32::new(0),
+                disconnects,
+                transition: Mutex::new(Transition::default()),
+            }),
+            next_id: AtomicU64::new(0),
+            hooks: Mutex::new(Vec::new()),
+        });
+        let stop = CancellationToken::new();
+        tokio::spawn(supervise(shared.clone(), receiver, stop.clone()));
+        Ok(Self { shared, stop })
+    }
+
+    /// 注册重连成功后的回调
+    pub fn on_reconnect(&self, hook: ReconnectHook) {
+        self.shared.hooks.lock().unwrap().push(hook);
+    }
+
+    /// `updates` 每收到一个新 JWT 就以新连接重新认证私有频道，直到停止
+    pub fn reauthenticate_on(&self, mut updates: watch::Receiver<JwtToken>) {
+        let shared = self.shared.clone();
+        let stop = self.stop.clone();
+        tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    _ = stop.cancelled() => return,
+                    changed = updates.changed() => {
+                        if changed.is_err() {
+                            return;
+                        }
+                    }
+                }
+                match shared.reauthenticate().await {
+                    Ok(0) => {}
+                    Ok(count) => info!(
+                        "JWT rotated, re-authenticated {} private channel subscriptions",
+                        count
+                    ),
+                    Err(e) => warn!(
+                        "Failed to re-authenticate private channels after JWT rotation: {}",
+                        e
+                    ),
+                }
+            }
+        });
+    }
+}
+
+impl Drop for Reconnecting {
+    fn drop(&mut self) {
+        self.stop.cancel();
+    }
+}
+
+#[async_trait]
+impl MarketDataSource for Reconnecting {
+    async fn subscribe(
+        &self,
+        channel: Channel,
+        callback: Callback,
+    ) -> Result<SubscriptionId, Error> {
+        let mut connection = self.shared.connection.lock().await;
+        let callback = Arc::new(Mutex::new(callback));
+        let generation = self.shared.signals.generation.load(Ordering::SeqCst);
+        let id = self.shared.next_id.fetch_add(1, Ordering::Relaxed);
+        let private = channel_key(&channel).0.is_private();
+        let retired = Arc::new(AtomicBool::new(false));
+        let source = match connection.private_source {
+            Some(ref private_source) if private => private_source,
+            _ => &connection.source,
+        };
+        let inner = source
+            .subscribe(
+                channel.clone(),
+                forward(
+                    self.shared.signals.clone(),
+                    generation,
+                    id,
+                    retired.clone(),
+                    callback.clone(),
+                ),
+            )
+            .await?;
+        connection.subscriptions.insert(
+            id,
+            Subscription {
+                channel,
+                private,
+                callback,
+                inner,
+                retired,
+            },
+        );
+        Ok(SubscriptionId::Reconnecting(id))
+    }
+
+    async fn unsubscribe(&self, id: SubscriptionId) -> Result<(), Error> {
+        let SubscriptionId::Reconnecting(id) = id else {
+            return Ok(());
+        };
+        let mut connection = self.shared.connection.lock().await;
+        match connection.subscriptions.remove(&id) {
+            Some(subscription) => {
+                connection
+                    .source_of(&subscription)
+                    .unsubscribe(subscription.inner)
+                    .await
+            }
+            None => Ok(()),
+        }
+    }
+
+    async fn stop(&self) -> Result<(), Error> {
+        self.stop.cancel();
+        let connection = self.shared.connection.lock().await;
+        if let Some(ref private) = connection.private_source {
+            private.stop().await?;
+        }
+        connection.source.stop().await
+    }
+}
+
+/// 包装用户回调：只转发当前连接的消息，并记录活动与断线；迁移期间丢弃另一条连接已转发的私有消息
+fn forward(
+    signals: Arc<Signals>,
+    generation: u64,
+    id: u64,
+    retired: Arc<AtomicBool>,
+    callback: Arc<Mutex<Callback>>,
+) -> Callback {
+    Box::new(move |message| {
+        if signals.generation.load(Ordering::SeqCst) != generation {
+            return;
+        }
+        if retired.load(Ordering::SeqCst)
+            && matches!(
+                message,
+                Message::Connected | Message::Disconnected | Message::Unsubscribed
+            )
+        {
+            return;
+        }
+        if signals.is_duplicate(id, message) {
+            return;
+        }
+        match message {
+            Message::Disconnected => {
+                let _ = signals.disconnects.send(generation);
+            }
+            Message::Connected | Message::Unsubscribed => signals.touch(),
+            _ => {
+                signals.touch();
+                signals.attempts.store(0, Ordering::Relaxed);
+            }
+        }
+        (callback.lock().unwrap())(message);
+    })
+}
+
+impl Signals {
+    fn touch(&self) {
+        *self.last_message.lock().unwrap() = Instant::now();
+    }
+
+    fn silent_for(&self) -> Duration {
+        self.last_message.lock().unwrap().elapsed()
+    }
+
+    /// 开始迁移并返回其编号
+    fn begin_transition(&self) -> u64 {
+        let mut transition = self.transition.lock().unwrap();
+        transition.epoch += 1;
+        transition.seen = Some(HashSet::new());
+        transition.epoch
+    }
+
+    /// 结束编号为 `epoch` 的迁移；之后已开始新的迁移时不做处理
+    fn end_transition(&self, epoch: u64) {
+        let mut transition = self.transition.lock().unwrap();
+        if transition.epoch == epoch {
+            transition.seen = None;
+        }
+    }
+
+    /// 迁移期间订阅 `id` 是否已转发过同一条私有消息
+    fn is_duplicate(&self, id: u64, message: &Message) -> bool {
+        let mut transition = self.transition.lock().unwrap();
+        let (Some(seen), Some(key)) = (transition.seen.as_mut(), private_message_id(message))
+        else {
+            return false;
+        };
+        !seen.insert((id, key))
+    }
+}
+
+/// 私有频道消息的去重 ID；行情与控制消息为 `None`
+fn private_message_id(message: &Message) -> Option<String> {
+    let id = match message {
+        Message::Orders(order) => format!("order:{}:{}", order.id, order.seq_no),
+        Message::Fills(fill) => format!("fill:{}", fill.id),
+        Message::Position(position) => {
+            format!("position:{}:{}", position.id, position.last_updated_at)
+        }
+        Message::Account(account) => format!("account:{}", account.seq_no),
+        Message::BalanceEvent(event) => format!(
+            "balance:{}:{}:{}",
+            event.fill_id, event.market, event.created_at
+        ),
+        Message::FundingPayments(payment) => format!("funding:{}", payment.id),
+        _ => return None,
+    };
+    Some(id)
+}
+
+/// 内层订阅确认（收到 `Connected`）时释放一个许可
+fn confirming(callback: Callback, confirmed: Arc<Semaphore>) -> Callback {
+    Box::new(move |message| {
+        if matches!(message, Message::Connected) {
+            confirmed.add_permits(1);
+        }
+        callback(message);
+    })
+}
+
+/// 等待断线或静默，然后重连
+async fn supervise(
+    shared: Arc<Shared>,
+    mut disconnects: UnboundedReceiver<u64>,
+    stop: CancellationToken,
+) {
+    let check_interval = shared
+        .policy
+        .silence_timeout
+        .map_or(Duration::from_secs(1), |timeout| timeout / 4);
+    loop {
+        let reason = tokio::select! {
+            _ = stop.cancelled() => return,
+            Some(generation) = disconnects.recv() => {
+                if generation != shared.signals.generation.load(Ordering::SeqCst) {
+                    continue;
+                }
+                "connection lost".to_string()
+            }
+            _ = tokio::time::sleep(check_interval) => {
+                let Some(timeout) = shared.policy.silence_timeout else {
+                    continue;
+                };
+                let silent_for = shared.signals.silent_for();
+                if silent_for < timeout || shared.connection.lock().await.subscriptions.is_empty() {
+                    continue;
+                }
+                format!("silent for {:?}", silent_for)
+            }
+        };
+        warn!("WebSocket {}, reconnecting", reason);
+        tokio::select! {
+            _ = stop.cancelled() => return,
+            _ = shared.reconnect() => {}
+        }
+    }
+}
+
+impl Shared {
+    /// 按退避间隔重试直到重连成功
+    async fn reconnect(&self) {
+        loop {
+            let attempt = self.signals.attempts.fetch_add(1, Ordering::Relaxed);
+            tokio::time::sleep(self.policy.backoff(attempt)).await;
+            metrics().ws_reconnect_attempt();
+            match self.try_reconnect().await {
+                Ok(count) => {
+                    info!("WebSocket reconnected, resubscribed {} channels", count);
+                    for hook in self.hooks.lock().unwrap().iter() {
+                        hook();
+                    }
+                    return;
+                }
+                Err(e) => warn!("WebSocket reconnect attempt {} failed: {}", attempt + 1, e),
+            }
+        }
+    }
+
+    /// 建立新连接并重新订阅全部频道，成功后停止旧连接；返回订阅数
+    async fn try_reconnect(&self) -> Result<usize, Error> {
+        let source = self.connector.connect().await?;
+        let mut connection = self.connection.lock().await;
+        // 此后旧连接的消息不再转发
+        let generation = self.signals.generation.fetch_add(1, Ordering::SeqCst) + 1;
+        let mut resubscribed = Vec::with_capacity(connection.subscriptions.len());
+        for (id, subscription) in &connection.subscriptions {
+            let retired = Arc::new(AtomicBool::new(false));
+            let callback = forward(
+                self.signals.clone(),
+                generation,
+                *id,
+                retired.clone(),
+                subscription.callback.clone(),
+            );
+            match source
+                .subscribe(subscription.channel.clone(), callback)
+                .await
+            {
+                Ok(inner) => resubscribed.push((*id, inner, retired)),
+                Err(e) => {
+                    let _ = source.stop().await;
+                    return Err(e);
+                }
+            }
+        }
+        for (id, inner, retired) in resubscribed {
+            if let Some(subscription) = connection.subscriptions.get_mut(&id) {
+                subscription.inner = inner;
+                subscription.retired = retired;
+            }
+        }
+        let previous = std::mem::replace(&mut connection.source, source);
+        let previous_private = connection.private_source.take();
+        self.signals.touch();
+        let count = connection.subscriptions.len();
+        drop(connection);
+        for previous in std::iter::once(previous).chain(previous_private) {
+            if let Err(e) = previous.stop().await {
+                debug!("Failed to stop previous WebSocket connection: {}", e);
+            }
+        }
+        Ok(count)
+    }
+
+    /// 以新连接（新 JWT）订阅全部私有频道，全部确认后取消旧连接上的私有订阅；返回迁移的订阅数
+    async fn reauthenticate(&self) -> Result<usize, Error> {
+        let has_private = {
+            let connection = self.connection.lock().await;
+            connection.subscriptions.values().any(|s| s.private)
+        };
+        if !has_private {
+            return Ok(0);
+        }
+        let source = self.connector.connect().await?;
+        let mut connection = self.connection.lock().await;
+        let generation = self.signals.generation.load(Ordering::SeqCst);
+        let confirmed = Arc::new(Semaphore::new(0));
+        let epoch = self.signals.begin_transition();
+        let mut moved = Vec::new();
+        for (id, subscription) in connection.subscriptions.iter().filter(|(_, s)| s.private) {
+            let retired = Arc::new(AtomicBool::new(false));
+            let callback = forward(
+                self.signals.clone(),
+                generation,
+                *id,
+                retired.clone(),
+                subscription.callback.clone(),
+            );
+            let callback = confirming(callback, confirmed.clone());
+            match source
+                .subscribe(subscription.channel.clone(), callback)
+                .await
+            {
+                Ok(inner) => moved.push((*id, inner, retired)),
+                Err(e) => {
+                    self.signals.end_transition(epoch);
+                    let _ = source.stop().await;
+                    return Err(e);
+                }
+            }
+        }
+        let confirmation = confirmed.acquire_many(moved.len() as u32);
+        if tokio::time::timeout(REAUTH_CONFIRM_TIMEOUT, confirmation)
+            .await
+            .is_err()
+        {
+            // 新连接未确认：保留旧订阅，新连接上的订阅不再转发
+            for (_, _, retired) in &moved {
+                retired.store(true, Ordering::SeqCst);
+            }
+            self.signals.end_transition(epoch);
+            let _ = source.stop().await;
+            return Err(Error::WebSocketSend(format!(
+                "private channels were not confirmed within {:?}",
+                REAUTH_CONFIRM_TIMEOUT
+            )));
+        }
+        let previous_private = connection.private_source.replace(source);
+        let previous = previous_private
+            .clone()
+            .unwrap_or(connection.source.clone());
+        for (id, inner, retired) in &moved {
+            let Some(subscription) = connection.subscriptions.get_mut(id) else {
+                continue;
+            };
+            let old_inner = std::mem::replace(&mut subscription.inner, *inner);
+            let old_retired = std::mem::replace(&mut subscription.retired, retired.clone());
+            old_retired.store(true, Ordering::SeqCst);
+            if let Err(e) = previous.unsubscribe(old_inner).await {
+                debug!("Failed to unsubscribe retired private channel: {}", e);
+            }
+        }
+        let count = moved.len();
+        drop(connection);
+        metrics().ws_reauth();
+        if let Some(previous) = previous_private {
+            if let Err(e) = previous.stop().await {
+                debug!(
+                    "Failed to stop previous private WebSocket connection: {}",
+                    e
+                );
+            }
+        }
+        let signals = self.signals.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(REAUTH_GRACE).await;
+            signals.end_transition(epoch);
+        });
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use paradex::structs::{FundingPayment, Side, Trade, TradeType};
+
+    /// 一条模拟连接：测试直接推送消息或断开
+    #[derive(Default)]
+    struct FakeConnection {
+        callbacks: Mutex<Vec<(u64, Channel, Callback)>>,
+        stopped: std::sync::atomic::AtomicBool,
+    }
+
+    impl FakeConnection {
+        fn push(&self, message: &Message) {
+            for (_, _, callback) in self.callbacks.lock().unwrap().iter() {
+                callback(message);
+            }
+        }
+
+        fn channels(&self) -> Vec<Channel> {
+            let callbacks = self.callbacks.lock().unwrap();
+            callbacks.iter().map(|(_, c, _)| c.clone()).collect()
+        }
+    }
+
+    #[async_trait]
+    impl MarketDataSource for FakeConnection {
+        async fn subscribe(
+            &self,
+            channel: Channel,
+            callback: Callback,
+        ) -> Result<SubscriptionId, Error> {
+            let mut callbacks = self.callbacks.lock().unwrap();
+            let id = callbacks.len() as u64;
+            callbacks.push((id, channel, callback));
+            Ok(SubscriptionId::Replay(id))
+        }
+
+        async fn unsubscribe(&self, id: SubscriptionId) -> Result<(), Error> {
+            self.callbacks
+                .lock()
+                .unwrap()
+                .retain(|(own, _, _)| SubscriptionId::Replay(*own) != id);
+            Ok(())
+        }
+
+        async fn stop(&self) -> Result<(), Error> {
+            self.stopped.store(true, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    /// 依次返回新连接；`failures` 次之前的连接尝试失败
+    #[derive(Clone, Default)]
+    struct FakeConnector {
+        connections: Arc<Mutex<Vec<Arc<FakeConnection>>>>,
+        failures: Arc<AtomicU32>,
+    }
+
+    impl FakeConnector {
+        fn connection(&self, index: usize) -> Option<Arc<FakeConnection>> {
+            self.connections.lock().unwrap().get(index).cloned()
+        }
+
+        async fn wait_for_connection(&self, index: usize) -> Arc<FakeConnection> {
+            wait_until(|| {
+                self.connection(index)
+                    .is_some_and(|connection| !connection.callbacks.lock().unwrap().is_empty())
+            })
+            .await;
+            self.connection(index).unwrap()
+        }
+    }
+
+    #[async_trait]
+    impl Connector for FakeConnector {
+        async fn connect(&self) -> Result<Arc<dyn MarketDataSource>, Error> {
+            if self
+                .failures
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+                .is_ok()
+            {
+                return Err(Error::WebSocketSend("connection refused".to_string()));
+            }
+            let connection = Arc::new(FakeConnection::default());
+            self.connections.lock().unwrap().push(connection.clone());
+            Ok(connection)
+        }
+    }
+
+    async fn wait_until(condition: impl Fn() -> bool) {
+        for _ in 0..200 {
+            if condition() {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        panic!("condition was never met");
+    }
+
+    fn policy(silence_timeout: Option<Duration>) -> ReconnectPolicy {
+        ReconnectPolicy {
+            initial_backoff: Duration::from_millis(5),
+            max_backoff: Duration::from_millis(20),
+            silence_timeout,
+        }
+    }
+
+    fn trade(id: &str) -> Message {
+        Message::Trades(Trade {
+            created_at: 1735689600000,
+            id: id.to_string(),
+            market: "BTC-USD-PERP".to_string(),
+            price: 95000.0,
+            side: Side::BUY,
+            size: 0.01,
+            trade_type: TradeType::FILL,
+        })
+    }
+
+    fn trades_channel() -> Channel {
+        Channel::Trades {
+            market_symbol: "BTC-USD-PERP".to_string(),
+        }
+    }
+
+    fn recording_callback(received: &Arc<Mutex<Vec<String>>>) -> Callback {
+        let received = received.clone();
+        Box::new(move |message| {
+            if let Message::Trades(trade) = message {
+                received.lock().unwrap().push(trade.id.clone());
+            }
+        })
+    }
+
+    fn payment(id: &str) -> Message {
+        Message::FundingPayments(FundingPayment {
+            id: id.to_string(),
+            market: "BTC-USD-PERP".to_string(),
+            payment: -0.1,
+            index: 1.0,
+            fill_id: String::new(),
+            created_at: 1735689600000,
+        })
+    }
+
+    fn payments_channel() -> Channel {
+        Channel::FundingPayments {
+            market_symbol: None,
+        }
+    }
+
+    fn token(jwt: &str) -> JwtToken {
+        JwtToken::new(jwt.to_string(), 0, 0, "0x1")
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_the_limit() {
+        let policy = ReconnectPolicy::default();
+        assert_eq!(policy.backoff(0), Duration::from_secs(1));
+        assert_eq!(policy.backoff(1), Duration::from_secs(2));
+        assert_eq!(policy.backoff(5), Duration::from_secs(32));
+        assert_eq!(policy.backoff(6), Duration::from_secs(60));
+        assert_eq!(policy.backoff(100), Duration::from_secs(60));
+    }
+
+    #[tokio::test]
+    async fn dropped_connection_is_rebuilt_and_resubscribed() {
+        let connector = FakeConnector::default();
+        let source = Reconnecting::connect(connector.clone(), policy(None))
+            .await
+            .unwrap();
+        let reconnects = Arc::new(AtomicU32::new(0));
+        let hook_count = reconnects.clone();
+        source.on_reconnect(Box::new(move || {
+            hook_count.fetch_add(1, Ordering::SeqCst);
+        }));
+
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let id = source
+            .subscribe(trades_channel(), recording_callback(&received))
+            .await
+            .unwrap();
+        let first = connector.wait_for_connection(0).await;
+        first.push(&trade("1"));
+
+        // 断线后第一次重连失败，按退避重试
+        connector.failures.store(1, Ordering::SeqCst);
+        first.push(&Message::Disconnected);
+        let second = connector.wait_for_connection(1).await;
+        assert_eq!(second.channels(), [trades_channel()]);
+        wait_until(|| reconnects.load(Ordering::SeqCst) == 1).await;
+        assert!(first.stopped.load(Ordering::SeqCst));
+
+        // 旧连接停止前的消息不再转发
+        first.push(&trade("stale"));
+        second.push(&trade("2"));
+        assert_eq!(*received.lock().unwrap(), ["1", "2"]);
+        assert_eq!(reconnects.load(Ordering::SeqCst), 1);
+
+        // 订阅 ID 在重连后仍然有效
+        source.unsubscribe(id).await.unwrap();
+        assert!(second.channels().is_empty());
+        source.stop().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn silent_connection_is_replaced() {
+        let connector = FakeConnector::default();
+        let source =
+            Reconnecting::connect(connector.clone(), policy(Some(Duration::from_millis(100))))
+                .await
+                .unwrap();
+        let received = Arc::new(Mutex::new(Vec::new()));
+        source
+            .subscribe(trades_channel(), recording_callback(&received))
+            .await
+            .unwrap();
+
+        let second = connector.wait_for_connection(1).await;
+        second.push(&trade("after silence"));
+        assert_eq!(*received.lock().unwrap(), ["after silence"]);
+        source.stop().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn rotated_jwt_moves_private_channels_without_gaps() {
+        let connector = FakeConnector::default();
+        let source = Reconnecting::connect(connector.clone(), policy(None))
+            .await
+            .unwrap();
+        let trades = Arc::new(Mutex::new(Vec::new()));
+        source
+            .subscribe(trades_channel(), recording_callback(&trades))
+            .await
+            .unwrap();
+        let payments = Arc::new(Mutex::new(Vec::new()));
+        let recorded = payments.clone();
+        source
+            .subscribe(
+                payments_channel(),
+                Box::new(move |message| match message {
+                    Message::FundingPayments(payment) => {
+                        recorded.lock().unwrap().push(payment.id.clone())
+                    }
+                    Message::Unsubscribed | Message::Disconnected => {
+                        recorded.lock().unwrap().push(format!("{message:?}"))
+                    }
+                    _ => {}
+                }),
+            )
+            .await
+            .unwrap();
+        let first = connector.wait_for_connection(0).await;
+
+        let (tokens, updates) = watch::channel(token("jwt-0"));
+        source.reauthenticate_on(updates);
+        tokens.send(token("jwt-1")).unwrap();
+        let second = connector.wait_for_connection(1).await;
+        assert_eq!(second.channels(), [payments_channel()]);
+
+        // 确认前两条连接都在推送：同一条消息只转发一次
+        first.push(&payment("1"));
+        second.push(&payment("1"));
+        second.push(&payment("2"));
+        first.push(&payment("2"));
+        second.push(&Message::Connected);
+        wait_until(|| first.channels() == [trades_channel()]).await;
+
+        // 行情订阅留在原连接上，私有频道改由新连接推送
+        first.push(&trade("t1"));
+        second.push(&payment("3"));
+        assert_eq!(*trades.lock().unwrap(), ["t1"]);
+        assert_eq!(*payments.lock().unwrap(), ["1", "2", "3"]);
+        assert!(!first.stopped.load(Ordering::SeqCst));
+
+        // 再次轮换后停止上一条私有连接
+        tokens.send(token("jwt-2")).unwrap();
+        let third = connector.wait_for_connection(2).await;
+        third.push(&Message::Connected);
+        wait_until(|| second.stopped.load(Ordering::SeqCst)).await;
+        third.push(&payment("4"));
+        assert_eq!(*payments.lock().unwrap(), ["1", "2", "3", "4"]);
+        source.stop().await.unwrap();
+        assert!(first.stopped.load(Ordering::SeqCst));
+        assert!(third.stopped.load(Ordering::SeqCst));
+    }
+}
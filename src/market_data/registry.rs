@@ -0,0 +1,211 @@
+//! 订阅登记：记录每个订阅 ID，退出时逐个取消订阅，失败汇总后仍会停止行情来源
+
+use log::{info, warn};
+use paradex::{error::Error, ws::Channel};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use thiserror::Error;
+
+use super::{Callback, MarketDataSource, SubscriptionId};
+
+/// 部分订阅取消失败
+#[derive(Debug, Error)]
+#[error("failed to unsubscribe {} of {total} subscriptions: {}", failures.len(), describe(failures))]
+pub struct UnsubscribeError {
+    pub total: usize,
+    pub failures: Vec<(SubscriptionId, Error)>,
+}
+
+fn describe(failures: &[(SubscriptionId, Error)]) -> String {
+    failures
+        .iter()
+        .map(|(id, e)| format!("{:?}: {}", id, e))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// 活跃订阅的登记表，克隆后共享同一份数据
+#[derive(Debug, Clone, Default)]
+pub struct SubscriptionRegistry {
+    ids: Arc<Mutex<Vec<SubscriptionId>>>,
+}
+
+impl SubscriptionRegistry {
+    /// 空注册表
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 登记订阅 ID；重复登记忽略
+    pub fn record(&self, id: SubscriptionId) {
+        let mut ids = self.ids.lock().unwrap();
+        if !ids.contains(&id) {
+            ids.push(id);
+        }
+    }
+
+    /// 订阅并登记返回的 ID
+    pub async fn subscribe(
+        &self,
+        source: &dyn MarketDataSource,
+        channel: Channel,
+        callback: Callback,
+    ) -> Result<SubscriptionId, Error> {
+        let id = source.subscribe(channel, callback).await?;
+        self.record(id);
+        Ok(id)
+    }
+
+    /// 取消单个订阅；未登记或已取消的 ID 不会重复取消，返回 `Ok(false)`
+    pub async fn unsubscribe(
+        &self,
+        source: &dyn MarketDataSource,
+        id: SubscriptionId,
+    ) -> Result<bool, Error> {
+        if !self.take(id) {
+            return Ok(false);
+        }
+        source.unsubscribe(id).await?;
+        Ok(true)
+    }
+
+    fn take(&self, id: SubscriptionId) -> bool {
+        let mut ids = self.ids.lock().unwrap();
+        let before = ids.len();
+        ids.retain(|own| *own != id);
+        ids.len() != before
+    }
+
+    /// 当前登记的订阅数
+    pub fn active_count(&self) -> usize {
+        self.ids.lock().unwrap().len()
+    }
+
+    /// 取消全部已登记的订阅；单个失败不影响其余订阅，失败汇总为一个错误
+    ///
+    /// 调用后登记表清空，失败的订阅也不会再次尝试。
+    pub async fn unsubscribe_all(
+        &self,
+        source: &dyn MarketDataSource,
+    ) -> Result<(), UnsubscribeError> {
+        let ids = std::mem::take(&mut *self.ids.lock().unwrap());
+        let total = ids.len();
+        let mut failures = Vec::new();
+        for id in ids {
+            if let Err(e) = source.unsubscribe(id).await {
+                failures.push((id, e));
+            }
+        }
+        if failures.is_empty() {
+            info!("Unsubscribed {} subscriptions", total);
+            Ok(())
+        } else {
+            Err(UnsubscribeError { total, failures })
+        }
+    }
+
+    /// 取消全部订阅，等待 `grace` 后停止行情来源；取消订阅失败时仍会停止
+    pub async fn shutdown(&self, source: &dyn MarketDataSource, grace: Duration) {
+        if let Err(e) = self.unsubscribe_all(source).await {
+            warn!("{}", e);
+        }
+        tokio::time::sleep(grace).await;
+        if let Err(e) = source.stop().await {
+            warn!("Failed to stop market data source: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use paradex::ws::Message;
+
+    /// 取消 ID 为 2 的订阅时失败，记录调用顺序
+    #[derive(Default)]
+    struct FlakySource {
+        next: Mutex<u64>,
+        unsubscribed: Mutex<Vec<SubscriptionId>>,
+        stopped: Mutex<bool>,
+    }
+
+    #[async_trait]
+    impl MarketDataSource for FlakySource {
+        async fn subscribe(
+            &self,
+            _channel: Channel,
+            _callback: Callback,
+        ) -> Result<SubscriptionId, Error> {
+            let mut next = self.next.lock().unwrap();
+            *next += 1;
+            Ok(SubscriptionId::Replay(*next))
+        }
+
+        async fn unsubscribe(&self, id: SubscriptionId) -> Result<(), Error> {
+            self.unsubscribed.lock().unwrap().push(id);
+            if id == SubscriptionId::Replay(2) {
+                return Err(Error::WebSocketSend("connection closed".to_string()));
+            }
+            Ok(())
+        }
+
+        async fn stop(&self) -> Result<(), Error> {
+            *self.stopped.lock().unwrap() = true;
+            Ok(())
+        }
+    }
+
+    fn callback() -> Callback {
+        Box::new(|_: &Message| {})
+    }
+
+    #[tokio::test]
+    async fn unsubscribe_all_attempts_every_id_and_still_stops() {
+        let source = FlakySource::default();
+        let registry = SubscriptionRegistry::new();
+        for _ in 0..4 {
+            registry
+                .subscribe(&source, Channel::MarketSummary, callback())
+                .await
+                .unwrap();
+        }
+        registry.record(SubscriptionId::Replay(1));
+        assert_eq!(registry.active_count(), 4);
+
+        // 单独取消后不会再次取消
+        assert!(registry
+            .unsubscribe(&source, SubscriptionId::Replay(4))
+            .await
+            .unwrap());
+        assert!(!registry
+            .unsubscribe(&source, SubscriptionId::Replay(4))
+            .await
+            .unwrap());
+        assert_eq!(registry.active_count(), 3);
+
+        let error = registry.unsubscribe_all(&source).await.unwrap_err();
+        assert_eq!(error.total, 3);
+        assert_eq!(error.failures.len(), 1);
+        assert_eq!(error.failures[0].0, SubscriptionId::Replay(2));
+        assert!(error.to_string().contains("1 of 3"), "{error}");
+        assert_eq!(
+            *source.unsubscribed.lock().unwrap(),
+            [4, 1, 2, 3].map(SubscriptionId::Replay)
+        );
+        assert_eq!(registry.active_count(), 0);
+
+        // 取消订阅失败时仍会停止行情来源
+        let source = FlakySource::default();
+        for _ in 0..2 {
+            registry
+                .subscribe(&source, Channel::MarketSummary, callback())
+                .await
+                .unwrap();
+        }
+        registry.shutdown(&source, Duration::ZERO).await;
+        assert!(*source.stopped.lock().unwrap());
+        assert_eq!(source.unsubscribed.lock().unwrap().len(), 2);
+        assert_eq!(registry.active_count(), 0);
+    }
+}
@@ -0,0 +1,229 @@
+//! 订单簿信号：前 N 档的买卖量失衡度与按盘口数量加权的微价格
+//!
+//! 每次订单簿更新后重新计算，并按半衰期做 EWMA 平滑；与 [`BboCache`](super::BboCache) 一样
+//! 克隆后共享同一份数据，同时写入指标。
+
+use log::info;
+use rust_decimal::prelude::{FromPrimitive, ToPrimitive};
+use rust_decimal::Decimal;
+use std::collections::BTreeMap;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+
+use super::LocalOrderBook;
+use crate::metrics::metrics;
+
+/// 前 `depth` 档的 (买量 - 卖量) / (买量 + 卖量)；两侧均为空时为 `None`
+pub fn imbalance(book: &LocalOrderBook, depth: usize) -> Option<Decimal> {
+    let (bids, asks) = book.depth(depth);
+    let bid_volume: Decimal = bids.iter().map(|level| level.size()).sum();
+    let ask_volume: Decimal = asks.iter().map(|level| level.size()).sum();
+    let total = bid_volume + ask_volume;
+    (!total.is_zero()).then(|| (bid_volume - ask_volume) / total)
+}
+
+/// (买一价 × 卖一量 + 卖一价 × 买一量) / (买一量 + 卖一量)；任一侧为空时为 `None`
+pub fn microprice(book: &LocalOrderBook) -> Option<Decimal> {
+    let (bids, asks) = book.depth(1);
+    let (bid, ask) = (bids.first()?, asks.first()?);
+    let total = bid.size() + ask.size();
+    (!total.is_zero()).then(|| (bid.price() * ask.size() + ask.price() * bid.size()) / total)
+}
+
+/// 某个市场最新的信号值
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BookSignals {
+    pub imbalance: Decimal,
+    pub microprice: Decimal,
+    /// EWMA 平滑后的失衡度
+    pub imbalance_ewma: Decimal,
+    /// EWMA 平滑后的微价格
+    pub microprice_ewma: Decimal,
+    pub updated_at: Instant,
+}
+
+/// 各市场的订单簿信号
+#[derive(Debug, Clone)]
+pub struct Signals {
+    depth: usize,
+    half_life: Duration,
+    values: Arc<RwLock<BTreeMap<String, BookSignals>>>,
+}
+
+impl Signals {
+    /// `depth`：失衡度的每侧档位数；`half_life`：EWMA 半衰期，为 0 时不平滑
+    pub fn new(depth: usize, half_life: Duration) -> Self {
+        Self {
+            depth: depth.max(1),
+            half_life,
+            values: Arc::default(),
+        }
+    }
+
+    /// 订单簿更新后重新计算；未同步或任一侧为空的订单簿不更新
+    pub fn update(&self, book: &LocalOrderBook) -> Option<BookSignals> {
+        self.update_at(book, Instant::now())
+    }
+
+    fn update_at(&self, book: &LocalOrderBook, now: Instant) -> Option<BookSignals> {
+        if !book.is_synced() {
+            return None;
+        }
+        let imbalance = imbalance(book, self.depth)?;
+        let microprice = microprice(book)?;
+        let mut values = self.values.write().unwrap();
+        let signals = match values.get(book.market()) {
+            Some(previous) => {
+                let alpha = self.alpha(now.saturating_duration_since(previous.updated_at));
+                BookSignals {
+                    imbalance,
+                    microprice,
+                    imbalance_ewma: ewma(previous.imbalance_ewma, imbalance, alpha),
+                    microprice_ewma: ewma(previous.microprice_ewma, microprice, alpha),
+                    updated_at: now,
+                }
+            }
+            None => BookSignals {
+                imbalance,
+                microprice,
+                imbalance_ewma: imbalance,
+                microprice_ewma: microprice,
+                updated_at: now,
+            },
+        };
+        values.insert(book.market().to_string(), signals);
+        metrics().set_book_signals(
+            book.market(),
+            [
+                signals.imbalance,
+                signals.imbalance_ewma,
+                signals.microprice,
+                signals.microprice_ewma,
+            ]
+            .map(|value| value.to_f64().unwrap_or_default()),
+        );
+        Some(signals)
+    }
+
+    /// `market` 的最新信号
+    pub fn get(&self, market: &str) -> Option<BookSignals> {
+        self.values.read().unwrap().get(market).copied()
+    }
+
+    /// 停止维护 `market` 时丢弃其信号
+    pub fn remove(&self, market: &str) {
+        self.values.write().unwrap().remove(market);
+    }
+
+    /// 距上次更新 `elapsed` 时新值的权重：1 - 2^(-elapsed / half_life)
+    fn alpha(&self, elapsed: Duration) -> Decimal {
+        if self.half_life.is_zero() {
+            return Decimal::ONE;
+        }
+        let alpha = 1.0 - 0.5f64.powf(elapsed.as_secs_f64() / self.half_life.as_secs_f64());
+        Decimal::from_f64(alpha).unwrap_or(Decimal::ONE)
+    }
+
+    /// 每隔 `interval` 输出一行各市场的信号，直到任务被终止
+    pub fn spawn_log(&self, interval: Duration) -> JoinHandle<()> {
+        let signals = self.clone();
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(interval).await;
+                let values = signals.values.read().unwrap().clone();
+                for (market, s) in values {
+                    info!(
+                        "Signals {}: imbalance={:.4} (ewma {:.4}) microprice={:.4} (ewma {:.4})",
+                        market, s.imbalance, s.imbalance_ewma, s.microprice, s.microprice_ewma
+                    );
+                }
+            }
+        })
+    }
+}
+
+fn ewma(previous: Decimal, value: Decimal, alpha: Decimal) -> Decimal {
+    previous + alpha * (value - previous)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::orderbook::{Level, OrderBookSnapshot};
+
+    fn book(bids: &[(i64, i64)], asks: &[(i64, i64)]) -> LocalOrderBook {
+        let levels = |levels: &[(i64, i64)]| {
+            levels
+                .iter()
+                .map(|&(price, size)| Level(Decimal::from(price), Decimal::from(size)))
+                .collect()
+        };
+        let mut book = LocalOrderBook::new("BTC-USD-PERP");
+        book.seed(&OrderBookSnapshot {
+            market: "BTC-USD-PERP".to_string(),
+            seq_no: Some(1),
+            last_updated_at: None,
+            bids: levels(bids),
+            asks: levels(asks),
+        });
+        book
+    }
+
+    #[test]
+    fn imbalance_and_microprice_from_the_book() {
+        let book = book(
+            &[(100, 3), (99, 5), (98, 10)],
+            &[(101, 1), (102, 2), (103, 4)],
+        );
+        // 前 2 档：(8 - 3) / 11
+        assert_eq!(
+            imbalance(&book, 2),
+            Some(Decimal::from(5) / Decimal::from(11))
+        );
+        // 全部档位：(18 - 7) / 25
+        assert_eq!(imbalance(&book, 10), Some(Decimal::new(44, 2)));
+        // (100 * 1 + 101 * 3) / 4：买一量大，微价格靠近卖一
+        assert_eq!(microprice(&book), Some(Decimal::new(10075, 2)));
+
+        let one_sided = self::book(&[(100, 3)], &[]);
+        assert_eq!(imbalance(&one_sided, 5), Some(Decimal::ONE));
+        assert_eq!(microprice(&one_sided), None);
+        assert_eq!(imbalance(&LocalOrderBook::new("BTC-USD-PERP"), 5), None);
+    }
+
+    #[test]
+    fn signals_are_smoothed_by_half_life() {
+        let signals = Signals::new(1, Duration::from_secs(1));
+        let start = Instant::now();
+        let first = signals
+            .update_at(&book(&[(100, 3)], &[(101, 1)]), start)
+            .unwrap();
+        assert_eq!(first.imbalance, Decimal::new(5, 1));
+        assert_eq!(first.imbalance_ewma, Decimal::new(5, 1));
+
+        // 经过一个半衰期，新值权重为 1/2
+        let second = signals
+            .update_at(
+                &book(&[(100, 1)], &[(101, 3)]),
+                start + Duration::from_secs(1),
+            )
+            .unwrap();
+        assert_eq!(second.imbalance, Decimal::new(-5, 1));
+        assert_eq!(second.imbalance_ewma.round_dp(6), Decimal::ZERO);
+        assert_eq!(second.microprice, Decimal::new(10025, 2));
+        assert_eq!(second.microprice_ewma.round_dp(6), Decimal::new(1005, 1));
+        assert_eq!(signals.get("BTC-USD-PERP"), Some(second));
+
+        // 不平滑时直接取最新值；未同步的订单簿不更新
+        let raw = Signals::new(1, Duration::ZERO);
+        raw.update_at(&book(&[(100, 3)], &[(101, 1)]), start);
+        let latest = raw
+            .update_at(&book(&[(100, 1)], &[(101, 3)]), start)
+            .unwrap();
+        assert_eq!(latest.imbalance_ewma, latest.imbalance);
+        let mut stale = book(&[(100, 3)], &[(101, 1)]);
+        stale.mark_stale();
+        assert_eq!(raw.update_at(&stale, start), None);
+    }
+}
@@ -0,0 +1,303 @@
+//! 按市场缓存行情摘要频道的最新数据（标记价格、最新成交价、24 小时成交量、持仓量与资金费率）
+
+use chrono::{DateTime, TimeZone, Utc};
+use paradex::{structs::MarketSummary, ws::Message};
+use rust_decimal::Decimal;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use crate::markets::{decimal, MarketStats};
+
+/// 某个市场最新的行情摘要
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SummaryEntry {
+    pub symbol: String,
+    pub mark_price: Decimal,
+    pub last_price: Decimal,
+    /// 交易所未提供时为 0
+    pub volume_24h: Decimal,
+    pub open_interest: Decimal,
+    pub funding_rate: Decimal,
+    /// 交易所更新时间
+    pub exchange_ts: DateTime<Utc>,
+    /// 本地收到的时间
+    pub local_ts: DateTime<Utc>,
+}
+
+impl SummaryEntry {
+    /// 距本地收到摘要的时长
+    pub fn age(&self) -> Duration {
+        self.age_at(Utc::now())
+    }
+
+    fn age_at(&self, now: DateTime<Utc>) -> Duration {
+        (now - self.local_ts).to_std().unwrap_or_default()
+    }
+}
+
+/// 各市场最新行情摘要，克隆后共享同一份数据
+#[derive(Debug, Clone, Default)]
+pub struct MarketSummaryCache {
+    entries: Arc<RwLock<HashMap<String, SummaryEntry>>>,
+}
+
+impl MarketSummaryCache {
+    /// 空缓存
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 处理行情摘要频道的消息，其他消息忽略
+    pub fn on_message(&self, message: &Message) {
+        if let Message::MarketSummary(summary) = message {
+            self.update(summary);
+        }
+    }
+
+    /// 以本地当前时间缓存 `summary`
+    pub fn update(&self, summary: &MarketSummary) {
+        self.update_at(summary, Utc::now());
+    }
+
+    fn update_at(&self, summary: &MarketSummary, local_ts: DateTime<Utc>) {
+        let entry = SummaryEntry {
+            symbol: summary.symbol.clone(),
+            mark_price: decimal(summary.mark_price),
+            last_price: decimal(summary.last_traded_price),
+            volume_24h: summary.volume_24.map(decimal).unwrap_or_default(),
+            open_interest: decimal(summary.open_interest),
+            funding_rate: decimal(summary.funding_rate),
+            exchange_ts: Utc
+                .timestamp_millis_opt(summary.created_at as i64)
+                .single()
+                .unwrap_or_default(),
+            local_ts,
+        };
+        self.entries
+            .write()
+            .unwrap()
+            .insert(summary.symbol.clone(), entry);
+    }
+
+    /// 以 REST 行情统计补充尚未收到推送的市场（如未订阅行情摘要频道的一次性命令）；
+    /// 已有摘要的市场保持不变
+    pub fn seed(&self, stats: &MarketStats) {
+        let now = Utc::now();
+        self.entries
+            .write()
+            .unwrap()
+            .entry(stats.symbol.clone())
+            .or_insert_with(|| SummaryEntry {
+                symbol: stats.symbol.clone(),
+                mark_price: stats.mark_price.unwrap_or_default(),
+                last_price: Decimal::ZERO,
+                volume_24h: Decimal::ZERO,
+                open_interest: stats.open_interest.unwrap_or_default(),
+                funding_rate: stats.funding_rate.unwrap_or_default(),
+                exchange_ts: now,
+                local_ts: now,
+            });
+    }
+
+    /// `symbol` 的最新摘要
+    pub fn get(&self, symbol: &str) -> Option<SummaryEntry> {
+        self.entries.read().unwrap().get(symbol).cloned()
+    }
+
+    /// 收到时间不超过 `max_age` 的摘要
+    pub fn fresh(&self, symbol: &str, max_age: Duration) -> Option<SummaryEntry> {
+        self.fresh_at(symbol, max_age, Utc::now())
+    }
+
+    fn fresh_at(
+        &self,
+        symbol: &str,
+        max_age: Duration,
+        now: DateTime<Utc>,
+    ) -> Option<SummaryEntry> {
+        self.get(symbol)
+            .filter(|entry| entry.age_at(now) <= max_age)
+    }
+
+    /// 收到时间不超过 `max_age` 的有效（大于 0）标记价格
+    pub fn mark_price(&self, symbol: &str, max_age: Duration) -> Option<Decimal> {
+        self.fresh(symbol, max_age)
+            .map(|entry| entry.mark_price)
+            .filter(|price| *price > Decimal::ZERO)
+    }
+
+    /// 距最近一次收到 `symbol` 摘要的时长；未收到过时返回 `None`
+    pub fn age(&self, symbol: &str) -> Option<Duration> {
+        Some(self.get(symbol)?.age())
+    }
+
+    /// 全部市场的摘要，按 24 小时成交量从大到小排列
+    pub fn by_volume(&self) -> Vec<SummaryEntry> {
+        let mut entries: Vec<SummaryEntry> =
+            self.entries.read().unwrap().values().cloned().collect();
+        entries.sort_by(|a, b| {
+            b.volume_24h
+                .cmp(&a.volume_24h)
+                .then_with(|| a.symbol.cmp(&b.symbol))
+        });
+        entries
+    }
+}
+
+/// `summary` 子命令的文本表格：市场列左对齐，数值列右对齐
+pub fn format_summary_table(entries: &[SummaryEntry]) -> String {
+    let header = [
+        "SYMBOL",
+        "MARK",
+        "LAST",
+        "VOLUME 24H",
+        "OPEN INTEREST",
+        "FUNDING RATE",
+        "AGE",
+    ];
+    let rows: Vec<[String; 7]> = entries
+        .iter()
+        .map(|entry| {
+            [
+                entry.symbol.clone(),
+                entry.mark_price.normalize().to_string(),
+                entry.last_price.normalize().to_string(),
+                entry.volume_24h.round_dp(2).normalize().to_string(),
+                entry.open_interest.normalize().to_string(),
+                entry.funding_rate.normalize().to_string(),
+                format!("{}s", entry.age().as_secs()),
+            ]
+        })
+        .collect();
+
+    let mut widths = header.map(str::len);
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.len());
+        }
+    }
+    let line = |cells: Vec<&str>| {
+        cells
+            .iter()
+            .zip(widths)
+            .enumerate()
+            .map(|(i, (cell, width))| {
+                if i == 0 {
+                    format!("{:<width$}", cell, width = width)
+                } else {
+                    format!("{:>width$}", cell, width = width)
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("  ")
+            .trim_end()
+            .to_string()
+    };
+
+    let mut out = line(header.to_vec());
+    for row in &rows {
+        out.push('\n');
+        out.push_str(&line(row.iter().map(String::as_str).collect()));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeDelta;
+
+    fn summary(symbol: &str, mark_price: f64, volume_24: Option<f64>) -> MarketSummary {
+        serde_json::from_value(serde_json::json!({
+            "symbol": symbol,
+            "mark_price": mark_price.to_string(),
+            "last_traded_price": (mark_price + 1.0).to_string(),
+            "bid": "0",
+            "ask": "0",
+            "volume_24": volume_24.map(|volume| volume.to_string()),
+            "total_volume": "0",
+            "created_at": 1735689600000u64,
+            "underlying_price": "0",
+            "open_interest": "12.5",
+            "funding_rate": "0.0001",
+            "price_change_rate_24h": "0",
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn caches_latest_summary_sorted_by_volume() {
+        let cache = MarketSummaryCache::new();
+        let shared = cache.clone();
+        cache.on_message(&Message::MarketSummary(summary(
+            "ETH-USD-PERP",
+            3300.0,
+            Some(5_000.0),
+        )));
+        cache.on_message(&Message::MarketSummary(summary(
+            "BTC-USD-PERP",
+            95000.0,
+            Some(1_000.0),
+        )));
+        cache.on_message(&Message::MarketSummary(summary("SOL-USD-PERP", 0.0, None)));
+        cache.on_message(&Message::MarketSummary(summary(
+            "BTC-USD-PERP",
+            95010.0,
+            Some(9_000.0),
+        )));
+        cache.on_message(&Message::Connected);
+
+        let btc = shared.get("BTC-USD-PERP").unwrap();
+        assert_eq!(btc.mark_price, Decimal::from(95010));
+        assert_eq!(btc.last_price, Decimal::from(95011));
+        assert_eq!(btc.open_interest, Decimal::new(125, 1));
+        assert_eq!(btc.funding_rate, Decimal::new(1, 4));
+        assert_eq!(btc.exchange_ts.timestamp_millis(), 1735689600000);
+        let symbols: Vec<String> = shared
+            .by_volume()
+            .into_iter()
+            .map(|entry| entry.symbol)
+            .collect();
+        assert_eq!(symbols, ["BTC-USD-PERP", "ETH-USD-PERP", "SOL-USD-PERP"]);
+
+        let table = format_summary_table(&shared.by_volume()[..1]);
+        let mut lines = table.lines();
+        assert!(lines.next().unwrap().starts_with("SYMBOL        "));
+        assert!(lines
+            .next()
+            .unwrap()
+            .starts_with("BTC-USD-PERP  95010  95011"));
+
+        // 标记价格为 0 时视为缺失
+        let max_age = Duration::from_secs(30);
+        assert_eq!(
+            shared.mark_price("BTC-USD-PERP", max_age),
+            Some(Decimal::from(95010))
+        );
+        assert_eq!(shared.mark_price("SOL-USD-PERP", max_age), None);
+        assert_eq!(shared.mark_price("DOGE-USD-PERP", max_age), None);
+    }
+
+    #[test]
+    fn stale_summaries_are_rejected() {
+        let cache = MarketSummaryCache::new();
+        let received = Utc::now() - TimeDelta::hours(2);
+        cache.update_at(&summary("BTC-USD-PERP", 95000.0, None), received);
+        let max_age = Duration::from_secs(30);
+
+        assert!(cache.get("BTC-USD-PERP").is_some());
+        assert!(cache.age("BTC-USD-PERP").unwrap() >= Duration::from_secs(7_200));
+        assert!(cache.fresh("BTC-USD-PERP", max_age).is_none());
+        assert_eq!(cache.mark_price("BTC-USD-PERP", max_age), None);
+        assert!(cache
+            .fresh_at("BTC-USD-PERP", max_age, received + TimeDelta::seconds(10))
+            .is_some());
+
+        cache.update(&summary("BTC-USD-PERP", 95001.0, None));
+        assert_eq!(
+            cache.mark_price("BTC-USD-PERP", max_age),
+            Some(Decimal::from(95001))
+        );
+    }
+}
@@ -0,0 +1,446 @@
+//! 多市场订阅：按市场与频道组合订阅，并把消息路由到该市场的订单簿、最新报价与 K 线
+//!
+//! 每个市场的组件登记在同一张表中，回调按订阅所属的市场查表后处理；市场可在运行中增删，
+//! 移除时取消该市场的全部订阅并丢弃其状态，之后迟到的消息不再处理。
+
+use log::{info, warn};
+use paradex::{
+    error::Error,
+    ws::{Channel, Message},
+};
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+
+use super::{
+    BboCache, Callback, Candle, CandleBuilder, CandleInterval, MarketDataSource, OrderBooks,
+    SubscriptionId,
+};
+use crate::config::{ChannelSelection, WsChannel};
+
+/// 按市场订阅的频道中有对应组件的频道
+pub const SYMBOL_CHANNELS: [WsChannel; 3] = [
+    WsChannel::Bbo,
+    WsChannel::Trades,
+    WsChannel::OrderBookDeltas,
+];
+
+/// 收盘 K 线的回调，所有市场共用
+pub type CandleSink = Arc<dyn Fn(&Candle) + Send + Sync>;
+
+/// 单个市场的订阅与状态
+struct SymbolEntry {
+    ids: Vec<SubscriptionId>,
+    candles: Option<Arc<Mutex<CandleBuilder>>>,
+}
+
+type Registry = Arc<Mutex<BTreeMap<String, SymbolEntry>>>;
+
+/// 按市场管理订阅；克隆后共享同一组订阅
+#[derive(Clone)]
+pub struct SymbolSubscriptions {
+    source: Arc<dyn MarketDataSource>,
+    channels: Vec<WsChannel>,
+    books: OrderBooks,
+    quotes: BboCache,
+    candles: Option<(Vec<CandleInterval>, CandleSink)>,
+    symbols: Registry,
+}
+
+impl SymbolSubscriptions {
+    /// 订阅 `channels` 中按市场订阅的 BBO、成交与订单簿增量频道，其他频道忽略
+    pub fn new(source: Arc<dyn MarketDataSource>, channels: &ChannelSelection) -> Self {
+        Self {
+            source,
+            channels: SYMBOL_CHANNELS
+                .into_iter()
+                .filter(|channel| channels.contains(*channel))
+                .collect(),
+            books: OrderBooks::default(),
+            quotes: BboCache::new(),
+            candles: None,
+            symbols: Arc::default(),
+        }
+    }
+
+    /// 订单簿增量维护到 `books`（例如已设置 REST 重新同步与信号的订单簿）
+    pub fn with_order_books(mut self, books: OrderBooks) -> Self {
+        self.books = books;
+        self
+    }
+
+    /// BBO 写入 `quotes`
+    pub fn with_quotes(mut self, quotes: BboCache) -> Self {
+        self.quotes = quotes;
+        self
+    }
+
+    /// 每个市场由成交合成 `intervals` 周期的 K 线，收盘时调用 `sink`
+    pub fn with_candles(mut self, intervals: &[CandleInterval], sink: CandleSink) -> Self {
+        self.candles = Some((intervals.to_vec(), sink));
+        self
+    }
+
+    /// 订阅市场的订单簿
+    pub fn order_books(&self) -> &OrderBooks {
+        &self.books
+    }
+
+    /// 订阅市场的买卖一档
+    pub fn quotes(&self) -> &BboCache {
+        &self.quotes
+    }
+
+    /// 当前订阅的市场
+    pub fn symbols(&self) -> Vec<String> {
+        self.symbols.lock().unwrap().keys().cloned().collect()
+    }
+
+    /// 依次订阅 `symbols`，遇到失败即返回
+    pub async fn add_symbols(&self, symbols: &[String]) -> Result<(), Error> {
+        for symbol in symbols {
+            self.add_symbol(symbol).await?;
+        }
+        Ok(())
+    }
+
+    /// 订阅 `symbol` 的全部频道；已订阅时返回 `false`。任一频道订阅失败时撤销已完成的订阅
+    pub async fn add_symbol(&self, symbol: &str) -> Result<bool, Error> {
+        {
+            let mut symbols = self.symbols.lock().unwrap();
+            if symbols.contains_key(symbol) {
+                return Ok(false);
+            }
+            let candles = self.candles.as_ref().map(|(intervals, sink)| {
+                let mut builder = CandleBuilder::new(intervals);
+                let sink = sink.clone();
+                builder.on_candle(Box::new(move |candle| sink(candle)));
+                Arc::new(Mutex::new(builder))
+            });
+            symbols.insert(
+                symbol.to_string(),
+                SymbolEntry {
+                    ids: Vec::new(),
+                    candles,
+                },
+            );
+        }
+        self.books.add_market(symbol);
+
+        let mut ids = Vec::with_capacity(self.channels.len());
+        for &channel in &self.channels {
+            let callback = self.route(symbol, channel);
+            match self
+                .source
+                .subscribe(symbol_channel(channel, symbol), callback)
+                .await
+            {
+                Ok(id) => ids.push(id),
+                Err(e) => {
+                    self.symbols.lock().unwrap().remove(symbol);
+                    self.books.remove_market(symbol);
+                    self.unsubscribe(symbol, ids).await;
+                    return Err(e);
+                }
+            }
+        }
+        let removed = match self.symbols.lock().unwrap().get_mut(symbol) {
+            Some(entry) => {
+                entry.ids = ids;
+                None
+            }
+            // 订阅期间已被移除：remove_symbol 看不到这些 ID
+            None => Some(ids),
+        };
+        if let Some(ids) = removed {
+            self.unsubscribe(symbol, ids).await;
+            return Ok(false);
+        }
+        info!("Subscribed {} to {} channels", symbol, self.channels.len());
+        Ok(true)
+    }
+
+    /// 取消 `symbol` 的全部订阅并丢弃其订单簿、报价与 K 线；未订阅时返回 `false`
+    pub async fn remove_symbol(&self, symbol: &str) -> Result<bool, Error> {
+        let Some(entry) = self.symbols.lock().unwrap().remove(symbol) else {
+            return Ok(false);
+        };
+        self.books.remove_market(symbol);
+        self.quotes.remove(symbol);
+        let mut result = Ok(true);
+        for id in entry.ids {
+            if let Err(e) = self.source.unsubscribe(id).await {
+                warn!("Failed to unsubscribe {} channel: {}", symbol, e);
+                result = Err(e);
+            }
+        }
+        info!("Unsubscribed {}", symbol);
+        result
+    }
+
+    /// 取消全部市场的订阅
+    pub async fn close(&self) -> Result<(), Error> {
+        let mut result = Ok(());
+        for symbol in self.symbols() {
+            if let Err(e) = self.remove_symbol(&symbol).await {
+                result = Err(e);
+            }
+        }
+        result
+    }
+
+    /// 取消未登记到表中的订阅
+    async fn unsubscribe(&self, symbol: &str, ids: Vec<SubscriptionId>) {
+        for id in ids {
+            if let Err(e) = self.source.unsubscribe(id).await {
+                warn!("Failed to unsubscribe {} channel: {}", symbol, e);
+            }
+        }
+    }
+
+    /// `symbol` 的 `channel` 订阅回调：查表取得该市场的组件，市场已移除时丢弃消息
+    fn route(&self, symbol: &str, channel: WsChannel) -> Callback {
+        let symbol = symbol.to_string();
+        let symbols = self.symbols.clone();
+        let books = self.books.clone();
+        let quotes = self.quotes.clone();
+        Box::new(move |message| {
+            let candles = {
+                let symbols = symbols.lock().unwrap();
+                let Some(entry) = symbols.get(&symbol) else {
+                    return;
+                };
+                entry.candles.clone()
+            };
+            match (channel, message) {
+                (WsChannel::Bbo, Message::BBO(bbo)) if bbo.market == symbol => quotes.update(bbo),
+                (WsChannel::Trades, Message::Trades(trade)) if trade.market == symbol => {
+                    if let Some(candles) = candles {
+                        candles.lock().unwrap().on_trade(trade);
+                    }
+                }
+                (WsChannel::OrderBookDeltas, Message::OrderBookDeltas(delta))
+                    if delta.market == symbol =>
+                {
+                    books.apply(delta);
+                }
+                (WsChannel::OrderBookDeltas, Message::Disconnected) => books.mark_stale(&symbol),
+                _ => {}
+            }
+        })
+    }
+}
+
+/// `symbol` 的 `channel` 频道；`channel` 须为 [`SYMBOL_CHANNELS`] 之一
+fn symbol_channel(channel: WsChannel, symbol: &str) -> Channel {
+    let market_symbol = symbol.to_string();
+    match channel {
+        WsChannel::Bbo => Channel::BBO { market_symbol },
+        WsChannel::Trades => Channel::Trades { market_symbol },
+        WsChannel::OrderBookDeltas => Channel::OrderBookDeltas { market_symbol },
+        other => unreachable!("{} is not a per-symbol channel", other.cli_name()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::market_data::channel_key;
+    use async_trait::async_trait;
+    use paradex::structs::{
+        Level as DeltaLevel, OrderBook, OrderBookUpdateType, Side, Trade, TradeType, BBO,
+    };
+    use rust_decimal::Decimal;
+
+    /// 记录订阅的来源，测试按频道与市场推送消息
+    #[derive(Default)]
+    struct FakeSource {
+        subscriptions: Mutex<Vec<(u64, Channel, Callback)>>,
+        next_id: std::sync::atomic::AtomicU64,
+    }
+
+    impl FakeSource {
+        fn push(&self, channel: WsChannel, market: &str, message: &Message) {
+            for (_, subscribed, callback) in self.subscriptions.lock().unwrap().iter() {
+                if channel_key(subscribed) == (channel, Some(market.to_string())) {
+                    callback(message);
+                }
+            }
+        }
+
+        fn markets(&self) -> Vec<String> {
+            let subscriptions = self.subscriptions.lock().unwrap();
+            let mut markets: Vec<_> = subscriptions
+                .iter()
+                .filter_map(|(_, channel, _)| channel_key(channel).1)
+                .collect();
+            markets.dedup();
+            markets
+        }
+    }
+
+    #[async_trait]
+    impl MarketDataSource for FakeSource {
+        async fn subscribe(
+            &self,
+            channel: Channel,
+            callback: Callback,
+        ) -> Result<SubscriptionId, Error> {
+            let id = self
+                .next_id
+                .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            self.subscriptions
+                .lock()
+                .unwrap()
+                .push((id, channel, callback));
+            Ok(SubscriptionId::Replay(id))
+        }
+
+        async fn unsubscribe(&self, id: SubscriptionId) -> Result<(), Error> {
+            self.subscriptions
+                .lock()
+                .unwrap()
+                .retain(|(own, _, _)| SubscriptionId::Replay(*own) != id);
+            Ok(())
+        }
+
+        async fn stop(&self) -> Result<(), Error> {
+            Ok(())
+        }
+    }
+
+    fn snapshot(market: &str, seq_no: u64, bid: f64, ask: f64) -> Message {
+        Message::OrderBookDeltas(OrderBook {
+            market: market.to_string(),
+            seq_no,
+            last_updated_at: 1735689600000,
+            update_type: OrderBookUpdateType::Snapshot,
+            deletes: vec![],
+            inserts: vec![
+                DeltaLevel {
+                    price: bid,
+                    side: Side::BUY,
+                    size: 1.0,
+                },
+                DeltaLevel {
+                    price: ask,
+                    side: Side::SELL,
+                    size: 1.0,
+                },
+            ],
+            updates: vec![],
+        })
+    }
+
+    fn trade(market: &str, price: f64, created_at: u64) -> Message {
+        Message::Trades(Trade {
+            created_at,
+            id: created_at.to_string(),
+            market: market.to_string(),
+            price,
+            side: Side::BUY,
+            size: 1.0,
+            trade_type: TradeType::FILL,
+        })
+    }
+
+    fn subscriptions(source: &Arc<FakeSource>) -> SymbolSubscriptions {
+        let channels = ChannelSelection::from(vec![
+            WsChannel::Bbo,
+            WsChannel::Trades,
+            WsChannel::OrderBookDeltas,
+            WsChannel::Fills,
+        ]);
+        SymbolSubscriptions::new(source.clone(), &channels)
+    }
+
+    #[tokio::test]
+    async fn messages_are_routed_to_their_own_symbol() {
+        let source = Arc::new(FakeSource::default());
+        let closed = Arc::new(Mutex::new(Vec::new()));
+        let sink = closed.clone();
+        let symbols = subscriptions(&source).with_candles(
+            &["1m".parse().unwrap()],
+            Arc::new(move |candle: &Candle| sink.lock().unwrap().push(candle.market.clone())),
+        );
+        symbols
+            .add_symbols(&["BTC-USD-PERP".to_string(), "ETH-USD-PERP".to_string()])
+            .await
+            .unwrap();
+        assert!(!symbols.add_symbol("BTC-USD-PERP").await.unwrap());
+        // 私有频道不按市场订阅
+        assert_eq!(source.subscriptions.lock().unwrap().len(), 6);
+
+        source.push(
+            WsChannel::OrderBookDeltas,
+            "BTC-USD-PERP",
+            &snapshot("BTC-USD-PERP", 1, 95000.0, 95001.0),
+        );
+        source.push(
+            WsChannel::OrderBookDeltas,
+            "ETH-USD-PERP",
+            &snapshot("ETH-USD-PERP", 7, 3300.0, 3301.0),
+        );
+        let books = symbols.order_books();
+        let btc = books.get("BTC-USD-PERP").unwrap();
+        assert_eq!(btc.read().unwrap().seq_no(), Some(1));
+        assert_eq!(btc.read().unwrap().best_bid(), Some(Decimal::from(95000)));
+        let eth = books.get("ETH-USD-PERP").unwrap();
+        assert_eq!(eth.read().unwrap().seq_no(), Some(7));
+        assert_eq!(eth.read().unwrap().best_ask(), Some(Decimal::from(3301)));
+
+        source.push(
+            WsChannel::Bbo,
+            "ETH-USD-PERP",
+            &Message::BBO(BBO {
+                bid: 3300.0,
+                bid_size: 1.0,
+                ask: 3301.0,
+                ask_size: 1.0,
+                market: "ETH-USD-PERP".to_string(),
+                last_updated_at: 1735689600000,
+            }),
+        );
+        assert!(symbols.quotes().get("ETH-USD-PERP").is_some());
+        assert!(symbols.quotes().get("BTC-USD-PERP").is_none());
+
+        // 各市场的 K 线互不影响：只有 ETH 的周期被下一周期的成交关闭
+        source.push(
+            WsChannel::Trades,
+            "BTC-USD-PERP",
+            &trade("BTC-USD-PERP", 95000.0, 1735689600000),
+        );
+        source.push(
+            WsChannel::Trades,
+            "ETH-USD-PERP",
+            &trade("ETH-USD-PERP", 3300.0, 1735689600000),
+        );
+        source.push(
+            WsChannel::Trades,
+            "ETH-USD-PERP",
+            &trade("ETH-USD-PERP", 3301.0, 1735689660000),
+        );
+        assert_eq!(*closed.lock().unwrap(), ["ETH-USD-PERP"]);
+    }
+
+    #[tokio::test]
+    async fn removed_symbol_is_unsubscribed_and_forgotten() {
+        let source = Arc::new(FakeSource::default());
+        let symbols = subscriptions(&source);
+        symbols
+            .add_symbols(&["BTC-USD-PERP".to_string(), "ETH-USD-PERP".to_string()])
+            .await
+            .unwrap();
+        assert!(symbols.remove_symbol("ETH-USD-PERP").await.unwrap());
+        assert!(!symbols.remove_symbol("ETH-USD-PERP").await.unwrap());
+        assert_eq!(source.markets(), ["BTC-USD-PERP"]);
+        assert_eq!(symbols.symbols(), ["BTC-USD-PERP"]);
+        assert!(symbols.order_books().get("ETH-USD-PERP").is_none());
+
+        // 重新添加后从空订单簿开始
+        assert!(symbols.add_symbol("ETH-USD-PERP").await.unwrap());
+        let eth = symbols.order_books().get("ETH-USD-PERP").unwrap();
+        assert!(!eth.read().unwrap().is_seeded());
+        symbols.close().await.unwrap();
+        assert!(source.subscriptions.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,405 @@
+//! 成交流水的滚动统计：按市场与时间窗口计算 VWAP、主动买卖量与窗口内最大成交
+//!
+//! 窗口按成交的交易所时间滑动，每笔成交只进出队列一次；最大成交用单调队列维护，
+//! 因此每次更新的均摊开销与窗口内的成交数无关。
+
+use paradex::{
+    structs::{Side, Trade},
+    ws::Message,
+};
+use rust_decimal::Decimal;
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::market_data::CandleInterval;
+use crate::markets::decimal;
+
+/// 默认的统计窗口：1 分钟、5 分钟与 30 分钟
+pub const DEFAULT_TAPE_WINDOWS: [Duration; 3] = [
+    Duration::from_secs(60),
+    Duration::from_secs(300),
+    Duration::from_secs(1_800),
+];
+
+/// 一笔成交
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TapeTrade {
+    /// 交易所时间（Unix 毫秒）
+    pub ts: u64,
+    pub price: Decimal,
+    pub size: Decimal,
+    /// 主动方向
+    pub side: Side,
+}
+
+/// 某个市场在一个窗口内的统计
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WindowStats {
+    pub market: String,
+    pub window: Duration,
+    pub trade_count: usize,
+    pub volume: Decimal,
+    pub buy_volume: Decimal,
+    pub sell_volume: Decimal,
+    /// 窗口内没有成交时为 `None`
+    pub vwap: Option<Decimal>,
+    /// 窗口内数量最大的成交
+    pub largest: Option<TapeTrade>,
+}
+
+impl WindowStats {
+    /// (主动买量 - 主动卖量) / 总量，取值 -1..=1
+    pub fn imbalance(&self) -> Option<Decimal> {
+        if self.volume.is_zero() {
+            return None;
+        }
+        Some((self.buy_volume - self.sell_volume) / self.volume)
+    }
+}
+
+/// 窗口标签，如 `1m`、`30m`
+pub fn window_label(window: Duration) -> String {
+    CandleInterval::from_secs(window.as_secs()).to_string()
+}
+
+/// 单个窗口的队列与累计量
+struct RollingWindow {
+    span_ms: u64,
+    trades: VecDeque<(u64, TapeTrade)>,
+    /// 数量单调递减的成交，队首为窗口内最大成交
+    largest: VecDeque<(u64, TapeTrade)>,
+    notional: Decimal,
+    volume: Decimal,
+    buy_volume: Decimal,
+    sell_volume: Decimal,
+}
+
+impl RollingWindow {
+    fn new(window: Duration) -> Self {
+        Self {
+            span_ms: window.as_millis() as u64,
+            trades: VecDeque::new(),
+            largest: VecDeque::new(),
+            notional: Decimal::ZERO,
+            volume: Decimal::ZERO,
+            buy_volume: Decimal::ZERO,
+            sell_volume: Decimal::ZERO,
+        }
+    }
+
+    fn push(&mut self, seq: u64, trade: TapeTrade) {
+        self.notional += trade.price * trade.size;
+        self.volume += trade.size;
+        match trade.side {
+            Side::BUY => self.buy_volume += trade.size,
+            Side::SELL => self.sell_volume += trade.size,
+        }
+        while self
+            .largest
+            .back()
+            .is_some_and(|(_, back)| back.size <= trade.size)
+        {
+            self.largest.pop_back();
+        }
+        self.largest.push_back((seq, trade));
+        self.trades.push_back((seq, trade));
+    }
+
+    /// 移出交易所时间不晚于 `latest - span` 的成交
+    fn evict(&mut self, latest: u64) {
+        let cutoff = latest.saturating_sub(self.span_ms);
+        while let Some(&(seq, trade)) = self.trades.front() {
+            if trade.ts > cutoff {
+                break;
+            }
+            self.trades.pop_front();
+            self.notional -= trade.price * trade.size;
+            self.volume -= trade.size;
+            match trade.side {
+                Side::BUY => self.buy_volume -= trade.size,
+                Side::SELL => self.sell_volume -= trade.size,
+            }
+            if self.largest.front().is_some_and(|(front, _)| *front == seq) {
+                self.largest.pop_front();
+            }
+        }
+    }
+
+    fn stats(&self, market: &str) -> WindowStats {
+        WindowStats {
+            market: market.to_string(),
+            window: Duration::from_millis(self.span_ms),
+            trade_count: self.trades.len(),
+            volume: self.volume,
+            buy_volume: self.buy_volume,
+            sell_volume: self.sell_volume,
+            vwap: (!self.volume.is_zero()).then(|| self.notional / self.volume),
+            largest: self.largest.front().map(|(_, trade)| *trade),
+        }
+    }
+}
+
+/// 单个市场的全部窗口
+struct SymbolTape {
+    windows: Vec<RollingWindow>,
+    next_seq: u64,
+    latest: u64,
+    total_buy: Decimal,
+    total_sell: Decimal,
+}
+
+#[derive(Default)]
+struct Tape {
+    windows: Vec<Duration>,
+    symbols: BTreeMap<String, SymbolTape>,
+}
+
+/// 各市场的成交统计，克隆后共享同一份数据
+#[derive(Clone)]
+pub struct TradeTape {
+    inner: Arc<Mutex<Tape>>,
+}
+
+impl Default for TradeTape {
+    fn default() -> Self {
+        Self::new(&DEFAULT_TAPE_WINDOWS)
+    }
+}
+
+impl TradeTape {
+    /// 统计各 `windows` 内的成交；重复的窗口只保留一个
+    pub fn new(windows: &[Duration]) -> Self {
+        let mut windows = windows.to_vec();
+        windows.sort();
+        windows.dedup();
+        Self {
+            inner: Arc::new(Mutex::new(Tape {
+                windows,
+                symbols: BTreeMap::new(),
+            })),
+        }
+    }
+
+    /// 按时长排序的统计窗口
+    pub fn windows(&self) -> Vec<Duration> {
+        self.inner.lock().unwrap().windows.clone()
+    }
+
+    /// 处理成交频道的消息，其他消息忽略
+    pub fn on_message(&self, message: &Message) {
+        if let Message::Trades(trade) = message {
+            self.record(trade);
+        }
+    }
+
+    /// 记入一笔成交
+    pub fn record(&self, trade: &Trade) {
+        self.add(
+            &trade.market,
+            TapeTrade {
+                ts: trade.created_at,
+                price: decimal(trade.price),
+                size: decimal(trade.size),
+                side: trade.side,
+            },
+        );
+    }
+
+    /// 记入一笔成交；已滑出窗口的迟到成交只计入累计买卖量，窗口内的迟到成交按到达顺序移出
+    pub fn add(&self, market: &str, trade: TapeTrade) {
+        let mut tape = self.inner.lock().unwrap();
+        let Tape { windows, symbols } = &mut *tape;
+        let symbol = symbols
+            .entry(market.to_string())
+            .or_insert_with(|| SymbolTape {
+                windows: windows.iter().map(|w| RollingWindow::new(*w)).collect(),
+                next_seq: 0,
+                latest: 0,
+                total_buy: Decimal::ZERO,
+                total_sell: Decimal::ZERO,
+            });
+        match trade.side {
+            Side::BUY => symbol.total_buy += trade.size,
+            Side::SELL => symbol.total_sell += trade.size,
+        }
+        symbol.latest = symbol.latest.max(trade.ts);
+        let seq = symbol.next_seq;
+        symbol.next_seq += 1;
+        for window in &mut symbol.windows {
+            if trade.ts > symbol.latest.saturating_sub(window.span_ms) {
+                window.push(seq, trade);
+            }
+            window.evict(symbol.latest);
+        }
+    }
+
+    /// 窗口内的成交量加权均价；未配置该窗口或窗口内没有成交时返回 `None`
+    pub fn vwap(&self, market: &str, window: Duration) -> Option<Decimal> {
+        self.stats(market, window)?.vwap
+    }
+
+    /// 窗口内 (主动买量 - 主动卖量) / 总量
+    pub fn buy_sell_imbalance(&self, market: &str, window: Duration) -> Option<Decimal> {
+        self.stats(market, window)?.imbalance()
+    }
+
+    /// 窗口内数量最大的成交
+    pub fn largest_trade(&self, market: &str, window: Duration) -> Option<TapeTrade> {
+        self.stats(market, window)?.largest
+    }
+
+    /// 自启动以来的主动买量与主动卖量
+    pub fn cumulative_volume(&self, market: &str) -> Option<(Decimal, Decimal)> {
+        let tape = self.inner.lock().unwrap();
+        let symbol = tape.symbols.get(market)?;
+        Some((symbol.total_buy, symbol.total_sell))
+    }
+
+    /// 截至该市场最近一笔成交时的窗口统计
+    pub fn stats(&self, market: &str, window: Duration) -> Option<WindowStats> {
+        let tape = self.inner.lock().unwrap();
+        let index = tape.windows.iter().position(|w| *w == window)?;
+        Some(tape.symbols.get(market)?.windows[index].stats(market))
+    }
+
+    /// 全部市场与窗口的统计，按市场、窗口排序
+    pub fn snapshot(&self) -> Vec<WindowStats> {
+        let tape = self.inner.lock().unwrap();
+        tape.symbols
+            .iter()
+            .flat_map(|(market, symbol)| symbol.windows.iter().map(|w| w.stats(market)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use paradex::structs::TradeType;
+
+    const T0: u64 = 1735689600000;
+    const MINUTE: Duration = Duration::from_secs(60);
+    const FIVE_MINUTES: Duration = Duration::from_secs(300);
+
+    fn trade(ts: u64, price: Decimal, size: Decimal, side: Side) -> TapeTrade {
+        TapeTrade {
+            ts,
+            price,
+            size,
+            side,
+        }
+    }
+
+    #[test]
+    fn vwap_uses_exact_decimal_math() {
+        let tape = TradeTape::new(&[MINUTE, FIVE_MINUTES]);
+        let market = "BTC-USD-PERP";
+        // 0.1 @ 95000.1 + 0.2 @ 95000.2 + 0.3 @ 95000.3
+        for (i, step) in [1, 2, 3].into_iter().enumerate() {
+            let price = Decimal::new(950_000 + step, 1);
+            let size = Decimal::new(step, 1);
+            let side = if i == 1 { Side::SELL } else { Side::BUY };
+            tape.add(market, trade(T0 + i as u64 * 1_000, price, size, side));
+        }
+        // (9500.01 + 19000.04 + 28500.09) / 0.6 = 57000.14 / 0.6
+        let expected = Decimal::new(5_700_014, 2) / Decimal::new(6, 1);
+        assert_eq!(tape.vwap(market, MINUTE), Some(expected));
+        assert_eq!(
+            expected.round_dp(10),
+            "95000.2333333333".parse::<Decimal>().unwrap()
+        );
+        // (0.4 - 0.2) / 0.6
+        assert_eq!(
+            tape.buy_sell_imbalance(market, MINUTE),
+            Some(Decimal::new(2, 1) / Decimal::new(6, 1))
+        );
+        assert_eq!(
+            tape.largest_trade(market, MINUTE).unwrap().size,
+            Decimal::new(3, 1)
+        );
+        assert_eq!(tape.vwap(market, Duration::from_secs(30)), None);
+        assert_eq!(tape.vwap("ETH-USD-PERP", MINUTE), None);
+
+        let message = Message::Trades(Trade {
+            created_at: T0 + 3_000,
+            id: "4".to_string(),
+            market: market.to_string(),
+            price: 95000.5,
+            side: Side::BUY,
+            size: 0.4,
+            trade_type: TradeType::FILL,
+        });
+        tape.on_message(&message);
+        tape.on_message(&Message::Connected);
+        let stats = tape.stats(market, MINUTE).unwrap();
+        assert_eq!(stats.trade_count, 4);
+        assert_eq!(stats.volume, Decimal::ONE);
+        // (57000.14 + 38000.2) / 1.0
+        assert_eq!(stats.vwap, Some(Decimal::new(9_500_034, 2)));
+    }
+
+    #[test]
+    fn windows_evict_trades_by_exchange_time() {
+        let tape = TradeTape::new(&[MINUTE, FIVE_MINUTES]);
+        let market = "BTC-USD-PERP";
+        tape.add(
+            market,
+            trade(T0, Decimal::from(100), Decimal::from(5), Side::BUY),
+        );
+        tape.add(
+            market,
+            trade(T0 + 30_000, Decimal::from(110), Decimal::ONE, Side::SELL),
+        );
+        tape.add(
+            market,
+            trade(
+                T0 + 61_000,
+                Decimal::from(120),
+                Decimal::from(2),
+                Side::SELL,
+            ),
+        );
+
+        // 1 分钟窗口只剩后两笔，最大成交随之更新
+        let minute = tape.stats(market, MINUTE).unwrap();
+        assert_eq!(minute.trade_count, 2);
+        assert_eq!(minute.vwap, Some(Decimal::from(350) / Decimal::from(3)));
+        assert_eq!(minute.largest.unwrap().price, Decimal::from(120));
+        assert_eq!(minute.imbalance(), Some(Decimal::NEGATIVE_ONE));
+
+        let five = tape.stats(market, FIVE_MINUTES).unwrap();
+        assert_eq!(five.trade_count, 3);
+        assert_eq!(five.largest.unwrap().size, Decimal::from(5));
+        assert_eq!(five.vwap, Some(Decimal::from(850) / Decimal::from(8)));
+
+        // 迟到且已滑出 1 分钟窗口的成交只计入较长的窗口与累计量
+        tape.add(
+            market,
+            trade(T0 + 1_000, Decimal::from(90), Decimal::ONE, Side::BUY),
+        );
+        assert_eq!(tape.stats(market, MINUTE).unwrap().trade_count, 2);
+        assert_eq!(tape.stats(market, FIVE_MINUTES).unwrap().trade_count, 4);
+        assert_eq!(
+            tape.cumulative_volume(market),
+            Some((Decimal::from(6), Decimal::from(3)))
+        );
+
+        // 窗口清空后没有 VWAP，累计量保留
+        tape.add(
+            market,
+            trade(T0 + 400_000, Decimal::from(130), Decimal::ONE, Side::BUY),
+        );
+        let five = tape.stats(market, FIVE_MINUTES).unwrap();
+        assert_eq!(five.trade_count, 1);
+        assert_eq!(five.vwap, Some(Decimal::from(130)));
+        assert_eq!(five.buy_volume, Decimal::ONE);
+        assert_eq!(five.sell_volume, Decimal::ZERO);
+        assert_eq!(
+            tape.cumulative_volume(market),
+            Some((Decimal::from(7), Decimal::from(3)))
+        );
+        assert_eq!(tape.snapshot().len(), 2);
+        assert_eq!(window_label(FIVE_MINUTES), "5m");
+    }
+}
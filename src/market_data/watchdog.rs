@@ -0,0 +1,412 @@
+//! 停滞行情检测：记录每个订阅最后一次收到数据的时间，按频道阈值定期检查
+//!
+//! 交易所维护或连接静默断开时订阅可能不再推送数据却不报错；停滞时记录警告、计入指标并通知回调。
+
+use async_trait::async_trait;
+use log::{info, warn};
+use paradex::{
+    error::Error,
+    ws::{Channel, Message},
+};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+
+use super::{channel_key, Callback, MarketDataSource, SubscriptionId};
+use crate::config::WsChannel;
+use crate::metrics::metrics;
+
+/// 当前时间的来源，测试中可替换
+pub type Clock = Arc<dyn Fn() -> Instant + Send + Sync>;
+
+/// 订阅停滞或恢复时的通知
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FeedStatus {
+    pub id: SubscriptionId,
+    pub channel: WsChannel,
+    pub market: Option<String>,
+    /// 距最后一次收到数据的时长
+    pub silent_for: Duration,
+    /// `true` 为进入停滞，`false` 为恢复
+    pub stale: bool,
+}
+
+/// 停滞状态变化时的回调，例如暂停报价或触发重连
+pub type FeedCallback = Box<dyn Fn(&FeedStatus) + Send + Sync>;
+
+struct Feed {
+    channel: WsChannel,
+    market: Option<String>,
+    last_message: Instant,
+    stale: bool,
+}
+
+struct Inner {
+    thresholds: BTreeMap<WsChannel, Duration>,
+    feeds: Mutex<HashMap<SubscriptionId, Feed>>,
+    callback: Mutex<Option<FeedCallback>>,
+    clock: Clock,
+}
+
+/// 按频道阈值检查各订阅是否停滞；克隆后共享同一份状态
+#[derive(Clone)]
+pub struct FeedWatchdog {
+    inner: Arc<Inner>,
+}
+
+impl FeedWatchdog {
+    /// `thresholds` 中没有的频道不检查
+    pub fn new(thresholds: BTreeMap<WsChannel, Duration>) -> Self {
+        Self::with_clock(thresholds, Arc::new(Instant::now))
+    }
+
+    /// 以 `clock` 计时，便于测试
+    pub fn with_clock(thresholds: BTreeMap<WsChannel, Duration>, clock: Clock) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                thresholds,
+                feeds: Mutex::new(HashMap::new()),
+                callback: Mutex::new(None),
+                clock,
+            }),
+        }
+    }
+
+    /// 设置停滞与恢复时的回调
+    pub fn on_status(&self, callback: FeedCallback) {
+        *self.inner.callback.lock().unwrap() = Some(callback);
+    }
+
+    /// 开始跟踪订阅 `id`，以当前时间为最后一次收到数据的时间
+    pub fn register(&self, id: SubscriptionId, channel: WsChannel, market: Option<String>) {
+        let feed = Feed {
+            channel,
+            market,
+            last_message: (self.inner.clock)(),
+            stale: false,
+        };
+        self.inner.feeds.lock().unwrap().insert(id, feed);
+    }
+
+    /// 停止检查订阅 `id`
+    pub fn unregister(&self, id: SubscriptionId) {
+        self.inner.feeds.lock().unwrap().remove(&id);
+    }
+
+    /// 订阅 `id` 收到数据；停滞中的订阅随即恢复
+    pub fn record(&self, id: SubscriptionId) {
+        let now = (self.inner.clock)();
+        let recovered = {
+            let mut feeds = self.inner.feeds.lock().unwrap();
+            let Some(feed) = feeds.get_mut(&id) else {
+                return;
+            };
+            let silent_for = now.saturating_duration_since(feed.last_message);
+            feed.last_message = now;
+            if !feed.stale {
+                return;
+            }
+            feed.stale = false;
+            status(id, feed, silent_for)
+        };
+        info!(
+            "{} feed{} recovered after {:?}",
+            recovered.channel.cli_name(),
+            market_suffix(&recovered.market),
+            recovered.silent_for
+        );
+        self.notify(&recovered);
+    }
+
+    /// 检查全部订阅，返回本次新进入停滞的订阅
+    pub fn check(&self) -> Vec<FeedStatus> {
+        let now = (self.inner.clock)();
+        let stale: Vec<_> = {
+            let mut feeds = self.inner.feeds.lock().unwrap();
+            feeds
+                .iter_mut()
+                .filter_map(|(id, feed)| {
+                    let threshold = self.inner.thresholds.get(&feed.channel)?;
+                    let silent_for = now.saturating_duration_since(feed.last_message);
+                    if feed.stale || silent_for <= *threshold {
+                        return None;
+                    }
+                    feed.stale = true;
+                    Some(status(*id, feed, silent_for))
+                })
+                .collect()
+        };
+        for feed in &stale {
+            warn!(
+                "{} feed{} is stale: no data for {:?}",
+                feed.channel.cli_name(),
+                market_suffix(&feed.market),
+                feed.silent_for
+            );
+            metrics().stale_feed(feed.channel.cli_name());
+            self.notify(feed);
+        }
+        stale
+    }
+
+    /// 是否有订阅处于停滞状态
+    pub fn any_stale(&self) -> bool {
+        self.inner.feeds.lock().unwrap().values().any(|f| f.stale)
+    }
+
+    /// 每隔 `interval` 检查一次，直到任务被终止
+    pub fn spawn(&self, interval: Duration) -> JoinHandle<()> {
+        let watchdog = self.clone();
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(interval).await;
+                watchdog.check();
+            }
+        })
+    }
+
+    /// 包装行情来源：订阅自动登记，收到数据时记录时间
+    pub fn watch(&self, inner: Arc<dyn MarketDataSource>) -> WatchedSource {
+        WatchedSource {
+            inner,
+            watchdog: self.clone(),
+        }
+    }
+
+    fn notify(&self, status: &FeedStatus) {
+        if let Some(ref callback) = *self.inner.callback.lock().unwrap() {
+            callback(status);
+        }
+    }
+}
+
+fn status(id: SubscriptionId, feed: &Feed, silent_for: Duration) -> FeedStatus {
+    FeedStatus {
+        id,
+        channel: feed.channel,
+        market: feed.market.clone(),
+        silent_for,
+        stale: feed.stale,
+    }
+}
+
+fn market_suffix(market: &Option<String>) -> String {
+    market
+        .as_ref()
+        .map(|market| format!(" for {}", market))
+        .unwrap_or_default()
+}
+
+/// 由 [`FeedWatchdog::watch`] 创建；订阅返回 ID 之前收到的数据不计入
+pub struct WatchedSource {
+    inner: Arc<dyn MarketDataSource>,
+    watchdog: FeedWatchdog,
+}
+
+#[async_trait]
+impl MarketDataSource for WatchedSource {
+    async fn subscribe(
+        &self,
+        channel: Channel,
+        callback: Callback,
+    ) -> Result<SubscriptionId, Error> {
+        let (name, market) = channel_key(&channel);
+        let id = Arc::new(Mutex::new(None::<SubscriptionId>));
+        let own_id = id.clone();
+        let watchdog = self.watchdog.clone();
+        let subscription = self
+            .inner
+            .subscribe(
+                channel,
+                Box::new(move |message| {
+                    let is_data = !matches!(
+                        message,
+                        Message::Connected | Message::Disconnected | Message::Unsubscribed
+                    );
+                    if is_data {
+                        if let Some(id) = *own_id.lock().unwrap() {
+                            watchdog.record(id);
+                        }
+                    }
+                    callback(message);
+                }),
+            )
+            .await?;
+        self.watchdog.register(subscription, name, market);
+        *id.lock().unwrap() = Some(subscription);
+        Ok(subscription)
+    }
+
+    async fn unsubscribe(&self, id: SubscriptionId) -> Result<(), Error> {
+        self.watchdog.unregister(id);
+        self.inner.unsubscribe(id).await
+    }
+
+    async fn stop(&self) -> Result<(), Error> {
+        self.inner.stop().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// 手动推进的时钟
+    #[derive(Clone)]
+    struct ManualClock(Arc<Mutex<Instant>>);
+
+    impl ManualClock {
+        fn new() -> Self {
+            Self(Arc::new(Mutex::new(Instant::now())))
+        }
+
+        fn advance(&self, secs: u64) {
+            *self.0.lock().unwrap() += Duration::from_secs(secs);
+        }
+
+        fn clock(&self) -> Clock {
+            let now = self.0.clone();
+            Arc::new(move || *now.lock().unwrap())
+        }
+    }
+
+    fn watchdog(clock: &ManualClock) -> FeedWatchdog {
+        let thresholds = BTreeMap::from([
+            (WsChannel::Bbo, Duration::from_secs(10)),
+            (WsChannel::FundingData, Duration::from_secs(600)),
+        ]);
+        FeedWatchdog::with_clock(thresholds, clock.clock())
+    }
+
+    const BBO: SubscriptionId = SubscriptionId::Replay(1);
+    const FUNDING: SubscriptionId = SubscriptionId::Replay(2);
+    const ORDERS: SubscriptionId = SubscriptionId::Replay(3);
+
+    #[test]
+    fn stale_feeds_are_flagged_once_and_cleared_by_new_data() {
+        let clock = ManualClock::new();
+        let watchdog = watchdog(&clock);
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let recorded = events.clone();
+        watchdog.on_status(Box::new(move |status| {
+            recorded.lock().unwrap().push(status.clone());
+        }));
+        watchdog.register(BBO, WsChannel::Bbo, Some("BTC-USD-PERP".to_string()));
+        watchdog.register(FUNDING, WsChannel::FundingData, None);
+        // 没有阈值的频道不检查
+        watchdog.register(ORDERS, WsChannel::Orders, None);
+
+        clock.advance(8);
+        watchdog.record(BBO);
+        clock.advance(8);
+        assert!(watchdog.check().is_empty());
+
+        clock.advance(5);
+        let stale = watchdog.check();
+        assert_eq!(stale.len(), 1);
+        assert_eq!(stale[0].id, BBO);
+        assert_eq!(stale[0].market.as_deref(), Some("BTC-USD-PERP"));
+        assert_eq!(stale[0].silent_for, Duration::from_secs(13));
+        assert!(watchdog.any_stale());
+        // 持续停滞不重复通知
+        clock.advance(60);
+        assert!(watchdog.check().is_empty());
+
+        clock.advance(1);
+        watchdog.record(BBO);
+        assert!(!watchdog.any_stale());
+        assert!(watchdog.check().is_empty());
+
+        let events = events.lock().unwrap();
+        assert_eq!(events.len(), 2);
+        assert!(events[0].stale);
+        assert!(!events[1].stale);
+        assert_eq!(events[1].silent_for, Duration::from_secs(74));
+    }
+
+    #[test]
+    fn long_thresholds_tolerate_quiet_channels() {
+        let clock = ManualClock::new();
+        let watchdog = watchdog(&clock);
+        watchdog.register(FUNDING, WsChannel::FundingData, None);
+        clock.advance(599);
+        assert!(watchdog.check().is_empty());
+        clock.advance(2);
+        assert_eq!(watchdog.check()[0].id, FUNDING);
+
+        // 取消订阅后不再检查
+        watchdog.unregister(FUNDING);
+        assert!(!watchdog.any_stale());
+    }
+
+    #[tokio::test]
+    async fn watched_source_records_data_messages() {
+        struct Immediate(Mutex<Vec<Callback>>);
+
+        #[async_trait]
+        impl MarketDataSource for Immediate {
+            async fn subscribe(
+                &self,
+                _channel: Channel,
+                callback: Callback,
+            ) -> Result<SubscriptionId, Error> {
+                self.0.lock().unwrap().push(callback);
+                Ok(BBO)
+            }
+
+            async fn unsubscribe(&self, _id: SubscriptionId) -> Result<(), Error> {
+                Ok(())
+            }
+
+            async fn stop(&self) -> Result<(), Error> {
+                Ok(())
+            }
+        }
+
+        let clock = ManualClock::new();
+        let watchdog = watchdog(&clock);
+        let inner = Arc::new(Immediate(Mutex::new(Vec::new())));
+        let source = watchdog.watch(inner.clone());
+        let delivered = Arc::new(AtomicUsize::new(0));
+        let counter = delivered.clone();
+        let channel = Channel::BBO {
+            market_symbol: "BTC-USD-PERP".to_string(),
+        };
+        let id = source
+            .subscribe(
+                channel,
+                Box::new(move |_| {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                }),
+            )
+            .await
+            .unwrap();
+        let push = |message: &Message| {
+            for callback in inner.0.lock().unwrap().iter() {
+                callback(message);
+            }
+        };
+
+        clock.advance(11);
+        assert_eq!(watchdog.check()[0].channel, WsChannel::Bbo);
+        // 连接事件不算数据
+        push(&Message::Connected);
+        assert!(watchdog.any_stale());
+        push(&Message::BBO(paradex::structs::BBO {
+            bid: 95000.0,
+            bid_size: 1.0,
+            ask: 95010.0,
+            ask_size: 1.0,
+            market: "BTC-USD-PERP".to_string(),
+            last_updated_at: 1735689600000,
+        }));
+        assert!(!watchdog.any_stale());
+        assert_eq!(delivered.load(Ordering::SeqCst), 2);
+
+        source.unsubscribe(id).await.unwrap();
+        clock.advance(100);
+        assert!(watchdog.check().is_empty());
+    }
+}
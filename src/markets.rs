@@ -0,0 +1,633 @@
+use log::{info, warn};
+use paradex::{
+    rest::Client,
+    structs::{MarketSummaryStatic, Side},
+    url::URL,
+};
+use reqwest::Client as HttpClient;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use thiserror::Error;
+use tokio::task::JoinHandle;
+
+use crate::http::{get_public_json, HttpError};
+use crate::metrics::metrics;
+
+/// 命令行指定的市场校验错误
+#[derive(Debug, Error)]
+pub enum MarketError {
+    #[error("Failed to fetch markets: {0}")]
+    Fetch(#[from] paradex::error::Error),
+    #[error(
+        "Unknown market symbol(s): {}. Valid markets: {}",
+        unknown.join(", "),
+        valid.join(", ")
+    )]
+    UnknownSymbols {
+        unknown: Vec<String>,
+        valid: Vec<String>,
+    },
+}
+
+/// 订单价格或数量不符合市场规则，在发送前拦截
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum MarketRuleError {
+    #[error("{market}: price {price} is not a multiple of the tick size {tick}")]
+    OffTick {
+        market: String,
+        price: Decimal,
+        tick: Decimal,
+    },
+    #[error("{market}: size {size} is not a multiple of the size increment {increment}")]
+    OffIncrement {
+        market: String,
+        size: Decimal,
+        increment: Decimal,
+    },
+    #[error("{market}: size {size} is below the minimum order size {min}")]
+    SizeBelowMinimum {
+        market: String,
+        size: Decimal,
+        min: Decimal,
+    },
+    #[error("{market}: size {size} exceeds the maximum order size {max}")]
+    SizeAboveMaximum {
+        market: String,
+        size: Decimal,
+        max: Decimal,
+    },
+    #[error("{market}: notional {notional} is below the minimum {min}")]
+    NotionalBelowMinimum {
+        market: String,
+        notional: Decimal,
+        min: Decimal,
+    },
+}
+
+/// 查询交易所当前上线的全部市场（按代码排序）
+pub async fn fetch_markets(url: URL) -> Result<Vec<MarketSummaryStatic>, MarketError> {
+    let client = Client::new(url, None).await?;
+    let mut markets = client.markets().await?;
+    markets.sort_by(|a, b| a.symbol.cmp(&b.symbol));
+    Ok(markets)
+}
+
+/// 检查 `requested` 均在 `available` 中，否则列出未知代码与全部有效市场
+pub fn validate_symbols(requested: &[String], available: &[String]) -> Result<(), MarketError> {
+    let unknown: Vec<String> = requested
+        .iter()
+        .filter(|symbol| !available.contains(symbol))
+        .cloned()
+        .collect();
+    if unknown.is_empty() {
+        Ok(())
+    } else {
+        Err(MarketError::UnknownSymbols {
+            unknown,
+            valid: available.to_vec(),
+        })
+    }
+}
+
+/// 下单相关的市场元数据
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MarketInfo {
+    pub symbol: String,
+    pub base_currency: String,
+    pub quote_currency: String,
+    pub price_tick_size: Decimal,
+    /// 数量步长，也是最小下单数量
+    pub order_size_increment: Decimal,
+    pub min_notional: Decimal,
+    pub max_order_size: Decimal,
+}
+
+impl From<&MarketSummaryStatic> for MarketInfo {
+    fn from(market: &MarketSummaryStatic) -> Self {
+        Self {
+            symbol: market.symbol.clone(),
+            base_currency: market.base_currency.clone(),
+            quote_currency: market.quote_currency.clone(),
+            price_tick_size: decimal(market.price_tick_size),
+            order_size_increment: decimal(market.order_size_increment),
+            min_notional: decimal(market.min_notional),
+            max_order_size: decimal(market.max_order_size),
+        }
+    }
+}
+
+impl MarketInfo {
+    /// 最小下单数量（Paradex 以数量步长作为最小数量）
+    pub fn min_size(&self) -> Decimal {
+        self.order_size_increment
+    }
+}
+
+/// 经十进制字符串转换，避免 0.1 之类的步长变成 0.1000000000000000055…
+pub(crate) fn decimal(value: f64) -> Decimal {
+    value.to_string().parse().unwrap_or_default()
+}
+
+/// 按代码缓存的市场元数据，供下单时对齐价格与数量
+#[derive(Debug, Clone, Default)]
+pub struct MarketRegistry {
+    markets: BTreeMap<String, MarketInfo>,
+}
+
+impl MarketRegistry {
+    /// 以市场代码为键建立索引
+    pub fn new(markets: impl IntoIterator<Item = MarketInfo>) -> Self {
+        Self {
+            markets: markets
+                .into_iter()
+                .map(|market| (market.symbol.clone(), market))
+                .collect(),
+        }
+    }
+
+    /// 查询交易所当前上线的全部市场（无需认证）
+    pub async fn fetch(url: URL) -> Result<Self, MarketError> {
+        let markets = fetch_markets(url).await?;
+        Ok(Self::new(markets.iter().map(MarketInfo::from)))
+    }
+
+    /// `symbol` 的市场信息
+    pub fn get(&self, symbol: &str) -> Option<&MarketInfo> {
+        self.markets.get(symbol)
+    }
+
+    /// 按代码排序的全部市场
+    pub fn iter(&self) -> impl Iterator<Item = &MarketInfo> {
+        self.markets.values()
+    }
+
+    /// 按代码排序的全部市场代码
+    pub fn symbols(&self) -> Vec<String> {
+        self.markets.keys().cloned().collect()
+    }
+
+    /// 检查 `requested` 均为已上线的市场
+    pub fn validate(&self, requested: &[String]) -> Result<(), MarketError> {
+        validate_symbols(requested, &self.symbols())
+    }
+
+    /// 价格精度；未知市场为 0（不对齐）
+    pub fn price_tick(&self, symbol: &str) -> Decimal {
+        self.get(symbol)
+            .map(|market| market.price_tick_size)
+            .unwrap_or_default()
+    }
+
+    /// 价格对齐到精度，向远离盘口的方向取整（买单向下、卖单向上）；未知市场不对齐
+    pub fn round_price(&self, symbol: &str, price: Decimal, side: Side) -> Decimal {
+        let tick = self.price_tick(symbol);
+        match side {
+            Side::BUY => round_down(price, tick),
+            Side::SELL => round_up(price, tick),
+        }
+    }
+
+    /// 数量向下对齐到步长；对齐后低于最小数量时返回错误而不是下调为 0。未知市场不对齐
+    pub fn round_size(&self, symbol: &str, size: Decimal) -> Result<Decimal, MarketRuleError> {
+        let Some(market) = self.get(symbol) else {
+            return Ok(size);
+        };
+        let rounded = round_down(size, market.order_size_increment);
+        if rounded < market.min_size() {
+            return Err(MarketRuleError::SizeBelowMinimum {
+                market: symbol.to_string(),
+                size,
+                min: market.min_size(),
+            });
+        }
+        Ok(rounded)
+    }
+
+    /// 发送前检查订单：价格在精度上、数量在步长上且不超出上下限，限价单的名义价值不低于下限；
+    /// 未知市场不检查
+    pub fn validate_order(
+        &self,
+        symbol: &str,
+        price: Option<Decimal>,
+        size: Decimal,
+    ) -> Result<(), MarketRuleError> {
+        let Some(market) = self.get(symbol) else {
+            return Ok(());
+        };
+        let market_name = || symbol.to_string();
+        if let Some(price) = price {
+            let tick = market.price_tick_size;
+            if !is_multiple(price, tick) {
+                return Err(MarketRuleError::OffTick {
+                    market: market_name(),
+                    price,
+                    tick,
+                });
+            }
+        }
+        if size < market.min_size() {
+            return Err(MarketRuleError::SizeBelowMinimum {
+                market: market_name(),
+                size,
+                min: market.min_size(),
+            });
+        }
+        if !is_multiple(size, market.order_size_increment) {
+            return Err(MarketRuleError::OffIncrement {
+                market: market_name(),
+                size,
+                increment: market.order_size_increment,
+            });
+        }
+        if !market.max_order_size.is_zero() && size > market.max_order_size {
+            return Err(MarketRuleError::SizeAboveMaximum {
+                market: market_name(),
+                size,
+                max: market.max_order_size,
+            });
+        }
+        if let Some(price) = price {
+            let notional = price * size;
+            if notional < market.min_notional {
+                return Err(MarketRuleError::NotionalBelowMinimum {
+                    market: market_name(),
+                    notional,
+                    min: market.min_notional,
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+fn round_down(value: Decimal, step: Decimal) -> Decimal {
+    if step.is_zero() {
+        value
+    } else {
+        (value / step).floor() * step
+    }
+}
+
+fn round_up(value: Decimal, step: Decimal) -> Decimal {
+    if step.is_zero() {
+        value
+    } else {
+        (value / step).ceil() * step
+    }
+}
+
+fn is_multiple(value: Decimal, step: Decimal) -> bool {
+    step.is_zero() || (value % step).is_zero()
+}
+
+/// 定期从交易所刷新的市场元数据，克隆后共享同一份数据
+#[derive(Debug, Clone, Default)]
+pub struct SharedMarkets {
+    registry: Arc<RwLock<MarketRegistry>>,
+}
+
+impl SharedMarkets {
+    /// 共享 `registry`
+    pub fn new(registry: MarketRegistry) -> Self {
+        Self {
+            registry: Arc::new(RwLock::new(registry)),
+        }
+    }
+
+    /// 当前的市场元数据
+    pub fn registry(&self) -> MarketRegistry {
+        self.registry.read().unwrap().clone()
+    }
+
+    /// 用 `f` 读取当前的市场元数据，避免整体克隆
+    pub fn with<T>(&self, f: impl FnOnce(&MarketRegistry) -> T) -> T {
+        f(&self.registry.read().unwrap())
+    }
+
+    /// 以新查询的市场列表替换原有数据
+    pub fn replace(&self, registry: MarketRegistry) {
+        *self.registry.write().unwrap() = registry;
+    }
+
+    /// 每隔 `interval` 重新查询市场列表；查询失败时保留原有数据
+    pub fn spawn_refresh(&self, url: URL, interval: Duration) -> JoinHandle<()> {
+        let markets = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            // 第一次 tick 立即完成，启动时已查询过
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                match MarketRegistry::fetch(url).await {
+                    Ok(registry) => {
+                        info!("Refreshed metadata for {} markets", registry.markets.len());
+                        markets.replace(registry);
+                    }
+                    Err(e) => {
+                        metrics().rest_error();
+                        warn!(
+                            "Failed to refresh market metadata, keeping the previous data: {}",
+                            e
+                        );
+                    }
+                }
+            }
+        })
+    }
+}
+
+/// 市场行情统计（`/markets/summary`）中列表展示用到的字段
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct MarketStats {
+    pub symbol: String,
+    pub mark_price: Option<Decimal>,
+    pub open_interest: Option<Decimal>,
+    pub funding_rate: Option<Decimal>,
+}
+
+#[derive(Deserialize)]
+struct StatsResponse {
+    results: Vec<MarketStats>,
+}
+
+/// 查询全部市场的行情统计（无需认证）
+pub async fn fetch_market_stats(
+    http_client: &HttpClient,
+    base_url: &str,
+) -> Result<Vec<MarketStats>, HttpError> {
+    fetch_stats(http_client, base_url, "ALL").await
+}
+
+/// 查询单个市场的行情统计（无需认证）
+pub async fn fetch_market_stat(
+    http_client: &HttpClient,
+    base_url: &str,
+    market: &str,
+) -> Result<Option<MarketStats>, HttpError> {
+    let stats = fetch_stats(http_client, base_url, market).await?;
+    Ok(stats.into_iter().find(|stat| stat.symbol == market))
+}
+
+async fn fetch_stats(
+    http_client: &HttpClient,
+    base_url: &str,
+    market: &str,
+) -> Result<Vec<MarketStats>, HttpError> {
+    let path = format!("/markets/summary?market={}", market);
+    let response: StatsResponse = get_public_json(http_client, base_url, &path).await?;
+    Ok(response.results)
+}
+
+/// `markets` 子命令输出的一行
+#[derive(Debug, Clone, Serialize)]
+pub struct MarketListing {
+    #[serde(flatten)]
+    pub info: MarketInfo,
+    pub open_interest: Option<Decimal>,
+    pub funding_rate: Option<Decimal>,
+}
+
+impl MarketListing {
+    /// 合并市场信息与统计数据；没有统计数据时持仓量与资金费率为 `None`
+    pub fn new(info: &MarketInfo, stats: Option<&MarketStats>) -> Self {
+        Self {
+            info: info.clone(),
+            open_interest: stats.and_then(|stats| stats.open_interest),
+            funding_rate: stats.and_then(|stats| stats.funding_rate),
+        }
+    }
+}
+
+/// 渲染为对齐的文本表格；缺失的统计值显示为 `-`
+pub fn format_table(listings: &[MarketListing]) -> String {
+    let header = [
+        "SYMBOL",
+        "BASE/QUOTE",
+        "TICK",
+        "MIN SIZE",
+        "OPEN INTEREST",
+        "FUNDING RATE",
+    ];
+    let optional =
+        |value: Option<Decimal>| value.map_or("-".to_string(), |v| v.normalize().to_string());
+    let rows: Vec<[String; 6]> = listings
+        .iter()
+        .map(|listing| {
+            let info = &listing.info;
+            [
+                info.symbol.clone(),
+                format!("{}/{}", info.base_currency, info.quote_currency),
+                info.price_tick_size.normalize().to_string(),
+                info.order_size_increment.normalize().to_string(),
+                optional(listing.open_interest),
+                optional(listing.funding_rate),
+            ]
+        })
+        .collect();
+
+    let mut widths = header.map(str::len);
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.len());
+        }
+    }
+    let line = |cells: Vec<&str>| {
+        cells
+            .iter()
+            .zip(widths)
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect::<Vec<_>>()
+            .join("  ")
+            .trim_end()
+            .to_string()
+    };
+
+    let mut out = line(header.to_vec());
+    for row in &rows {
+        out.push('\n');
+        out.push_str(&line(row.iter().map(String::as_str).collect()));
+    }
+    out
+}
+
+/// 市场代码的基础资产，如 `BTC-USD-PERP` -> `BTC`
+pub fn base_asset(symbol: &str) -> &str {
+    symbol.split('-').next().unwrap_or(symbol)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn symbols(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    #[test]
+    fn unknown_symbols_list_valid_markets() {
+        let available = symbols(&["BTC-USD-PERP", "ETH-USD-PERP"]);
+        assert!(validate_symbols(&symbols(&["ETH-USD-PERP"]), &available).is_ok());
+
+        let error =
+            validate_symbols(&symbols(&["BTC-USD-PERP", "DOGE-USD"]), &available).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Unknown market symbol(s): DOGE-USD. Valid markets: BTC-USD-PERP, ETH-USD-PERP"
+        );
+    }
+
+    #[test]
+    fn base_asset_is_first_component() {
+        assert_eq!(base_asset("ETH-USD-PERP"), "ETH");
+        assert_eq!(base_asset("BTC"), "BTC");
+    }
+
+    fn market(symbol: &str, tick: Decimal, increment: Decimal) -> MarketInfo {
+        MarketInfo {
+            symbol: symbol.to_string(),
+            base_currency: base_asset(symbol).to_string(),
+            quote_currency: "USD".to_string(),
+            price_tick_size: tick,
+            order_size_increment: increment,
+            min_notional: Decimal::from(10),
+            max_order_size: Decimal::from(100),
+        }
+    }
+
+    #[test]
+    fn registry_rounds_to_market_increments() {
+        let registry = MarketRegistry::new([
+            market("ETH-USD-PERP", Decimal::new(1, 2), Decimal::new(1, 3)),
+            market("BTC-USD-PERP", Decimal::new(1, 1), Decimal::new(1, 4)),
+        ]);
+        assert_eq!(registry.symbols(), ["BTC-USD-PERP", "ETH-USD-PERP"]);
+        assert!(registry.validate(&symbols(&["ETH-USD-PERP"])).is_ok());
+        assert_eq!(
+            registry.round_price("BTC-USD-PERP", Decimal::new(9512345, 2), Side::BUY),
+            Decimal::new(951234, 1)
+        );
+        assert_eq!(
+            registry.round_price("BTC-USD-PERP", Decimal::new(9512345, 2), Side::SELL),
+            Decimal::new(951235, 1)
+        );
+        assert_eq!(
+            registry.round_size("ETH-USD-PERP", Decimal::new(12345, 4)),
+            Ok(Decimal::new(1234, 3))
+        );
+        // 未知市场不对齐
+        assert_eq!(registry.price_tick("DOGE-USD-PERP"), Decimal::ZERO);
+        assert_eq!(
+            registry.round_price("DOGE-USD-PERP", Decimal::new(12345, 4), Side::SELL),
+            Decimal::new(12345, 4)
+        );
+        assert_eq!(decimal(0.1), Decimal::new(1, 1));
+    }
+
+    #[test]
+    fn orders_are_checked_against_market_rules() {
+        let registry = MarketRegistry::new([market(
+            "BTC-USD-PERP",
+            Decimal::new(1, 1),
+            Decimal::new(1, 4),
+        )]);
+        let check = |price: Option<&str>, size: &str| {
+            registry.validate_order(
+                "BTC-USD-PERP",
+                price.map(|price| price.parse().unwrap()),
+                size.parse().unwrap(),
+            )
+        };
+        assert_eq!(check(Some("95000.1"), "0.001"), Ok(()));
+        assert_eq!(check(None, "0.001"), Ok(()));
+        assert!(matches!(
+            check(Some("95000.15"), "0.001"),
+            Err(MarketRuleError::OffTick { .. })
+        ));
+        assert!(matches!(
+            check(Some("95000"), "0.00015"),
+            Err(MarketRuleError::OffIncrement { .. })
+        ));
+        assert!(matches!(
+            check(Some("95000"), "0.00001"),
+            Err(MarketRuleError::SizeBelowMinimum { .. })
+        ));
+        assert!(matches!(
+            check(Some("95000"), "101"),
+            Err(MarketRuleError::SizeAboveMaximum { .. })
+        ));
+        // 0.0001 * 95000 = 9.5 低于名义价值下限 10
+        assert_eq!(
+            check(Some("95000"), "0.0001").unwrap_err().to_string(),
+            "BTC-USD-PERP: notional 9.5000 is below the minimum 10"
+        );
+        assert_eq!(
+            registry.validate_order("DOGE-USD-PERP", None, Decimal::ZERO),
+            Ok(())
+        );
+    }
+
+    /// 随机抽样：对齐后的价格与数量在一个步长以内、不越过原值的被动一侧，数量不会被静默下调到最小值以下
+    #[test]
+    fn rounding_properties_hold_for_random_inputs() {
+        use rand::{rngs::StdRng, Rng, SeedableRng};
+
+        let mut rng = StdRng::seed_from_u64(832);
+        for _ in 0..2000 {
+            let tick = Decimal::new(rng.random_range(1..=50), rng.random_range(0..=4));
+            let increment = Decimal::new(rng.random_range(1..=50), rng.random_range(0..=5));
+            let registry = MarketRegistry::new([market("X-USD-PERP", tick, increment)]);
+            let price = Decimal::new(rng.random_range(1..=10_000_000), rng.random_range(0..=6));
+            let size = Decimal::new(rng.random_range(1..=1_000_000), rng.random_range(0..=7));
+
+            let bid = registry.round_price("X-USD-PERP", price, Side::BUY);
+            let ask = registry.round_price("X-USD-PERP", price, Side::SELL);
+            assert!(
+                bid <= price && price - bid < tick,
+                "{price} -> {bid} ({tick})"
+            );
+            assert!(
+                ask >= price && ask - price < tick,
+                "{price} -> {ask} ({tick})"
+            );
+            assert!(is_multiple(bid, tick) && is_multiple(ask, tick));
+
+            match registry.round_size("X-USD-PERP", size) {
+                Ok(rounded) => {
+                    assert!(rounded <= size && size - rounded < increment);
+                    assert!(rounded >= increment && is_multiple(rounded, increment));
+                }
+                Err(MarketRuleError::SizeBelowMinimum { min, .. }) => {
+                    assert_eq!(min, increment);
+                    assert!(size < increment, "{size} rejected ({increment})");
+                }
+                Err(e) => panic!("unexpected error {e}"),
+            }
+        }
+    }
+
+    #[test]
+    fn table_aligns_columns_and_marks_missing_stats() {
+        let btc = market("BTC-USD-PERP", Decimal::new(1, 1), Decimal::new(1, 4));
+        let stats = MarketStats {
+            symbol: btc.symbol.clone(),
+            open_interest: Some(Decimal::new(12345, 1)),
+            funding_rate: Some(Decimal::new(-125, 7)),
+            ..MarketStats::default()
+        };
+        let eth = market("ETH-USD-PERP", Decimal::new(1, 2), Decimal::new(1, 3));
+        let table = format_table(&[
+            MarketListing::new(&btc, Some(&stats)),
+            MarketListing::new(&eth, None),
+        ]);
+        assert_eq!(
+            table,
+            "SYMBOL        BASE/QUOTE  TICK  MIN SIZE  OPEN INTEREST  FUNDING RATE\n\
+             BTC-USD-PERP  BTC/USD     0.1   0.0001    1234.5         -0.0000125\n\
+             ETH-USD-PERP  ETH/USD     0.01  0.001     -              -"
+        );
+    }
+}
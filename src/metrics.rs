@@ -0,0 +1,1066 @@
+//! Prometheus 指标：进程内计数器与仪表，由 `--metrics-port` 启动的 HTTP 服务以文本格式导出
+//!
+//! 指标在整个进程共享（[`metrics`]），订阅回调、下单出口、账户会话与 REST 请求直接更新；
+//! 未启动 HTTP 服务时只是不被读取。
+
+use async_trait::async_trait;
+use log::{debug, info, warn};
+use paradex::{
+    error::Error,
+    structs::{CancelByMarketResponse, ModifyOrderRequest, OrderRequest, OrderUpdate},
+    ws::{Channel, Message},
+};
+use rust_decimal::prelude::ToPrimitive;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+use crate::gateway::{GatewayError, OrderGateway};
+use crate::latency::latency;
+use crate::market_data::{
+    channel_key, window_label, Callback, ChannelCounts, DispatchCounters, MarketDataSource,
+    SubscriptionId, SubscriptionRegistry, TradeTape,
+};
+
+/// 指标名前缀
+pub const PREFIX: &str = "trade_lighter_";
+/// 请求头的最大长度，超出时直接断开
+const MAX_REQUEST_BYTES: usize = 8192;
+
+/// WebSocket 连接状态，用于从各订阅回调收到的重复连接事件中识别重连
+const NEVER_CONNECTED: u8 = 0;
+const CONNECTED: u8 = 1;
+const DISCONNECTED: u8 = 2;
+
+/// 进程内的全部指标
+pub struct Metrics {
+    ws_messages: Mutex<BTreeMap<&'static str, u64>>,
+    ws_state: AtomicU8,
+    reconnects: AtomicU64,
+    reconnect_attempts: AtomicU64,
+    reauths: AtomicU64,
+    orders_submitted: AtomicU64,
+    orders_accepted: AtomicU64,
+    orders_rejected: AtomicU64,
+    orders_cancelled: AtomicU64,
+    rest_errors: AtomicU64,
+    orderbook_resyncs: AtomicU64,
+    orderbook_divergences: AtomicU64,
+    bbo_received: AtomicU64,
+    bbo_propagated: AtomicU64,
+    balance_alerts: AtomicU64,
+    stale_feeds: Mutex<BTreeMap<&'static str, u64>>,
+    orderbook_anomalies: Mutex<BTreeMap<&'static str, u64>>,
+    callback_panics: Mutex<BTreeMap<&'static str, u64>>,
+    position_drifts: Mutex<BTreeMap<String, u64>>,
+    quotes: Mutex<BTreeMap<String, (f64, f64)>>,
+    positions: Mutex<BTreeMap<String, f64>>,
+    balances: Mutex<BTreeMap<String, f64>>,
+    /// 可用保证金、权益与已用保证金
+    account: Mutex<Option<[f64; 3]>>,
+    funding_rates: Mutex<BTreeMap<String, f64>>,
+    funding_projections: Mutex<BTreeMap<String, f64>>,
+    /// 失衡度、平滑失衡度、微价格、平滑微价格
+    book_signals: Mutex<BTreeMap<String, [f64; 4]>>,
+    maker_skews: Mutex<BTreeMap<String, f64>>,
+    trade_tape: Mutex<Option<TradeTape>>,
+    dispatch: Mutex<Option<DispatchCounters>>,
+    subscriptions: Mutex<Option<SubscriptionRegistry>>,
+}
+
+static METRICS: Metrics = Metrics::new();
+
+/// 进程共享的指标
+pub fn metrics() -> &'static Metrics {
+    &METRICS
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Metrics {
+    /// 全部计数为 0
+    pub const fn new() -> Self {
+        Self {
+            ws_messages: Mutex::new(BTreeMap::new()),
+            ws_state: AtomicU8::new(NEVER_CONNECTED),
+            reconnects: AtomicU64::new(0),
+            reconnect_attempts: AtomicU64::new(0),
+            reauths: AtomicU64::new(0),
+            orders_submitted: AtomicU64::new(0),
+            orders_accepted: AtomicU64::new(0),
+            orders_rejected: AtomicU64::new(0),
+            orders_cancelled: AtomicU64::new(0),
+            rest_errors: AtomicU64::new(0),
+            orderbook_resyncs: AtomicU64::new(0),
+            orderbook_divergences: AtomicU64::new(0),
+            bbo_received: AtomicU64::new(0),
+            bbo_propagated: AtomicU64::new(0),
+            balance_alerts: AtomicU64::new(0),
+            stale_feeds: Mutex::new(BTreeMap::new()),
+            orderbook_anomalies: Mutex::new(BTreeMap::new()),
+            callback_panics: Mutex::new(BTreeMap::new()),
+            position_drifts: Mutex::new(BTreeMap::new()),
+            quotes: Mutex::new(BTreeMap::new()),
+            positions: Mutex::new(BTreeMap::new()),
+            balances: Mutex::new(BTreeMap::new()),
+            account: Mutex::new(None),
+            funding_rates: Mutex::new(BTreeMap::new()),
+            funding_projections: Mutex::new(BTreeMap::new()),
+            book_signals: Mutex::new(BTreeMap::new()),
+            maker_skews: Mutex::new(BTreeMap::new()),
+            trade_tape: Mutex::new(None),
+            dispatch: Mutex::new(None),
+            subscriptions: Mutex::new(None),
+        }
+    }
+
+    /// 记录 `channel` 频道收到的一条消息；连接事件只用于统计重连，BBO 同时更新买一卖一
+    pub fn ws_message(&self, channel: &'static str, message: &Message) {
+        match message {
+            Message::Connected => {
+                // 每个订阅回调都会收到连接事件，只有断线后的第一次计为重连
+                if self.ws_state.swap(CONNECTED, Ordering::SeqCst) == DISCONNECTED {
+                    self.reconnects.fetch_add(1, Ordering::Relaxed);
+                }
+                return;
+            }
+            Message::Disconnected => {
+                let _ = self.ws_state.compare_exchange(
+                    CONNECTED,
+                    DISCONNECTED,
+                    Ordering::SeqCst,
+                    Ordering::SeqCst,
+                );
+                return;
+            }
+            Message::BBO(bbo) => {
+                self.quotes
+                    .lock()
+                    .unwrap()
+                    .insert(bbo.market.clone(), (bbo.bid, bbo.ask));
+            }
+            _ => {}
+        }
+        *self.ws_messages.lock().unwrap().entry(channel).or_default() += 1;
+    }
+
+    /// 发出一笔订单
+    pub fn order_submitted(&self) {
+        self.orders_submitted.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// 订单被交易所接受
+    pub fn order_accepted(&self) {
+        self.orders_accepted.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// 订单被交易所拒绝
+    pub fn order_rejected(&self) {
+        self.orders_rejected.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// 撤销了 `count` 笔订单
+    pub fn orders_cancelled(&self, count: u64) {
+        self.orders_cancelled.fetch_add(count, Ordering::Relaxed);
+    }
+
+    /// REST 请求失败
+    pub fn rest_error(&self) {
+        self.rest_errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// 重建 WebSocket 连接的一次尝试
+    pub fn ws_reconnect_attempt(&self) {
+        self.reconnect_attempts.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// JWT 轮换后以新连接重新认证私有频道
+    pub fn ws_reauth(&self) {
+        self.reauths.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// 订单簿因缺口或异常重新同步
+    pub fn orderbook_resync(&self) {
+        self.orderbook_resyncs.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// 审计发现本地订单簿与 REST 快照不一致
+    pub fn orderbook_divergence(&self) {
+        self.orderbook_divergences.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// BBO 去重收到一笔报价
+    pub fn bbo_received(&self) {
+        self.bbo_received.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// BBO 去重把一笔变化的报价传递给下游
+    pub fn bbo_propagated(&self) {
+        self.bbo_propagated.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// 订单簿检测到 `kind` 类异常（交叉、锁定、负数量或价格跳变）
+    pub fn orderbook_anomaly(&self, kind: &'static str) {
+        *self
+            .orderbook_anomalies
+            .lock()
+            .unwrap()
+            .entry(kind)
+            .or_default() += 1;
+    }
+
+    /// `channel` 频道的订阅回调 panic
+    pub fn callback_panic(&self, channel: &'static str) {
+        *self
+            .callback_panics
+            .lock()
+            .unwrap()
+            .entry(channel)
+            .or_default() += 1;
+    }
+
+    /// `channel` 频道的一个订阅进入停滞
+    pub fn stale_feed(&self, channel: &'static str) {
+        *self.stale_feeds.lock().unwrap().entry(channel).or_default() += 1;
+    }
+
+    /// `market` 的 WebSocket 持仓与 REST 对账结果不一致
+    pub fn position_drift(&self, market: &str) {
+        *self
+            .position_drifts
+            .lock()
+            .unwrap()
+            .entry(market.to_string())
+            .or_default() += 1;
+    }
+
+    /// 持仓数量（多头为正、空头为负）
+    pub fn set_position(&self, market: &str, size: f64) {
+        self.positions
+            .lock()
+            .unwrap()
+            .insert(market.to_string(), size);
+    }
+
+    /// 记录 `asset` 的余额
+    pub fn set_balance(&self, asset: &str, balance: f64) {
+        self.balances
+            .lock()
+            .unwrap()
+            .insert(asset.to_string(), balance);
+    }
+
+    /// 账户的可用保证金、权益与已用保证金
+    pub fn set_account(&self, free_collateral: f64, equity: f64, margin_used: f64) {
+        *self.account.lock().unwrap() = Some([free_collateral, equity, margin_used]);
+    }
+
+    /// 可用保证金过低或跌幅过大的告警
+    pub fn balance_alert(&self) {
+        self.balance_alerts.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// 最新资金费率（每 8 小时）
+    pub fn set_funding_rate(&self, market: &str, rate: f64) {
+        self.funding_rates
+            .lock()
+            .unwrap()
+            .insert(market.to_string(), rate);
+    }
+
+    /// 持仓在下一个资金费周期的估算资金费，正为收入、负为支出
+    pub fn set_funding_projection(&self, market: &str, payment: f64) {
+        self.funding_projections
+            .lock()
+            .unwrap()
+            .insert(market.to_string(), payment);
+    }
+
+    /// 做市报价按持仓施加的偏移（基点），多头为正
+    pub fn set_maker_skew(&self, market: &str, skew_bps: f64) {
+        self.maker_skews
+            .lock()
+            .unwrap()
+            .insert(market.to_string(), skew_bps);
+    }
+
+    /// 订单簿信号：失衡度、平滑失衡度、微价格与平滑微价格
+    pub fn set_book_signals(&self, market: &str, values: [f64; 4]) {
+        self.book_signals
+            .lock()
+            .unwrap()
+            .insert(market.to_string(), values);
+    }
+
+    /// 导出该成交统计的 VWAP、买卖量与最大成交
+    pub fn watch_trade_tape(&self, tape: TradeTape) {
+        *self.trade_tape.lock().unwrap() = Some(tape);
+    }
+
+    /// 导出订阅分发层各频道收到、处理与丢弃的消息数
+    pub fn watch_dispatch(&self, counters: DispatchCounters) {
+        *self.dispatch.lock().unwrap() = Some(counters);
+    }
+
+    /// 导出登记表中的活跃订阅数
+    pub fn watch_subscriptions(&self, subscriptions: SubscriptionRegistry) {
+        *self.subscriptions.lock().unwrap() = Some(subscriptions);
+    }
+
+    /// Prometheus 文本格式（version 0.0.4）
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        let ws_messages: Vec<_> = self
+            .ws_messages
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(channel, count)| (vec![("channel", channel.to_string())], *count as f64))
+            .collect();
+        family(
+            &mut out,
+            "ws_messages_total",
+            "counter",
+            "WebSocket messages received per channel",
+            &ws_messages,
+        );
+        for (name, help, value) in [
+            (
+                "ws_reconnects_total",
+                "WebSocket reconnections",
+                &self.reconnects,
+            ),
+            (
+                "ws_reconnect_attempts_total",
+                "Attempts to rebuild the WebSocket connection",
+                &self.reconnect_attempts,
+            ),
+            (
+                "ws_reauths_total",
+                "Private channel re-authentications after JWT rotation",
+                &self.reauths,
+            ),
+            (
+                "orders_submitted_total",
+                "Orders submitted",
+                &self.orders_submitted,
+            ),
+            (
+                "orders_accepted_total",
+                "Orders accepted by the exchange",
+                &self.orders_accepted,
+            ),
+            (
+                "orders_rejected_total",
+                "Orders rejected by the exchange or the risk guard",
+                &self.orders_rejected,
+            ),
+            (
+                "orders_cancelled_total",
+                "Orders cancelled",
+                &self.orders_cancelled,
+            ),
+            (
+                "rest_errors_total",
+                "Failed REST requests",
+                &self.rest_errors,
+            ),
+            (
+                "orderbook_resyncs_total",
+                "Order book resynchronizations after a sequence gap",
+                &self.orderbook_resyncs,
+            ),
+            (
+                "orderbook_divergences_total",
+                "Order book audits that found the local book diverging from the REST snapshot",
+                &self.orderbook_divergences,
+            ),
+            (
+                "bbo_received_total",
+                "BBO quotes received by the deduplication layer",
+                &self.bbo_received,
+            ),
+            (
+                "bbo_propagated_total",
+                "BBO quotes propagated downstream after dropping unchanged ones",
+                &self.bbo_propagated,
+            ),
+            (
+                "balance_alerts_total",
+                "Free collateral alerts (below the minimum or a sharp drop)",
+                &self.balance_alerts,
+            ),
+        ] {
+            let value = value.load(Ordering::Relaxed) as f64;
+            family(&mut out, name, "counter", help, &[(vec![], value)]);
+        }
+        let stale_feeds: Vec<_> = self
+            .stale_feeds
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(channel, count)| (vec![("channel", channel.to_string())], *count as f64))
+            .collect();
+        family(
+            &mut out,
+            "stale_feed_total",
+            "counter",
+            "Subscriptions that stopped delivering data per channel",
+            &stale_feeds,
+        );
+        let anomalies: Vec<_> = self
+            .orderbook_anomalies
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(kind, count)| (vec![("kind", kind.to_string())], *count as f64))
+            .collect();
+        family(
+            &mut out,
+            "orderbook_anomalies_total",
+            "counter",
+            "Crossed, locked or otherwise anomalous order book updates per kind",
+            &anomalies,
+        );
+        let panics: Vec<_> = self
+            .callback_panics
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(channel, count)| (vec![("channel", channel.to_string())], *count as f64))
+            .collect();
+        family(
+            &mut out,
+            "callback_panics_total",
+            "counter",
+            "Panics caught in subscription callbacks per channel",
+            &panics,
+        );
+        let position_drifts: Vec<_> = self
+            .position_drifts
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(market, count)| (vec![("symbol", market.clone())], *count as f64))
+            .collect();
+        family(
+            &mut out,
+            "position_drift_total",
+            "counter",
+            "Position reconciliations where the websocket state diverged from REST per market",
+            &position_drifts,
+        );
+        self.render_dispatch(&mut out);
+        if let Some(subscriptions) = self.subscriptions.lock().unwrap().clone() {
+            family(
+                &mut out,
+                "subscriptions_active",
+                "gauge",
+                "Registered market data subscriptions",
+                &[(vec![], subscriptions.active_count() as f64)],
+            );
+        }
+
+        let tracker = latency();
+        let latencies: Vec<Sample> = tracker
+            .summary()
+            .into_iter()
+            .flat_map(|c| {
+                [("0.5", c.p50), ("0.95", c.p95), ("0.99", c.p99)].map(|(quantile, value)| {
+                    let labels = vec![
+                        ("channel", c.channel.to_string()),
+                        ("quantile", quantile.to_string()),
+                    ];
+                    (labels, value)
+                })
+            })
+            .collect();
+        family(
+            &mut out,
+            "ws_latency_ms",
+            "gauge",
+            "Exchange-to-local market data latency over recent messages",
+            &latencies,
+        );
+        family(
+            &mut out,
+            "clock_offset_ms",
+            "gauge",
+            "Paradex server time minus local time",
+            &[(vec![], tracker.clock_offset_ms() as f64)],
+        );
+
+        let quotes = self.quotes.lock().unwrap().clone();
+        let (bids, asks): (Vec<Sample>, Vec<Sample>) = quotes
+            .into_iter()
+            .map(|(symbol, (bid, ask))| {
+                let labels = vec![("symbol", symbol)];
+                ((labels.clone(), bid), (labels, ask))
+            })
+            .unzip();
+        family(&mut out, "best_bid", "gauge", "Best bid price", &bids);
+        family(&mut out, "best_ask", "gauge", "Best ask price", &asks);
+        let positions = labelled(&self.positions, "symbol");
+        family(
+            &mut out,
+            "position_size",
+            "gauge",
+            "Position size, negative for shorts",
+            &positions,
+        );
+        let balances = labelled(&self.balances, "asset");
+        family(
+            &mut out,
+            "account_balance",
+            "gauge",
+            "Account balance per asset",
+            &balances,
+        );
+        if let Some(account) = *self.account.lock().unwrap() {
+            for (value, (name, help)) in account.into_iter().zip([
+                ("free_collateral", "Free collateral"),
+                ("account_equity", "Account equity including unrealized PnL"),
+                (
+                    "margin_used",
+                    "Initial margin requirement of open positions",
+                ),
+            ]) {
+                family(&mut out, name, "gauge", help, &[(vec![], value)]);
+            }
+        }
+        let funding_rates = labelled(&self.funding_rates, "symbol");
+        family(
+            &mut out,
+            "funding_rate",
+            "gauge",
+            "Latest funding rate per 8h period",
+            &funding_rates,
+        );
+        let funding_projections = labelled(&self.funding_projections, "symbol");
+        family(
+            &mut out,
+            "funding_projected_payment",
+            "gauge",
+            "Estimated funding on the open position over the next 8h, negative when paying",
+            &funding_projections,
+        );
+        let book_signals = self.book_signals.lock().unwrap().clone();
+        for (index, (name, help)) in [
+            ("book_imbalance", "Top-N order book volume imbalance"),
+            ("book_imbalance_ewma", "EWMA-smoothed order book imbalance"),
+            ("microprice", "Top-of-book size-weighted microprice"),
+            ("microprice_ewma", "EWMA-smoothed microprice"),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let samples: Vec<Sample> = book_signals
+                .iter()
+                .map(|(symbol, values)| (vec![("symbol", symbol.clone())], values[index]))
+                .collect();
+            family(&mut out, name, "gauge", help, &samples);
+        }
+        let maker_skews = labelled(&self.maker_skews, "symbol");
+        family(
+            &mut out,
+            "maker_skew_bps",
+            "gauge",
+            "Inventory skew applied to market-making quotes, positive when long",
+            &maker_skews,
+        );
+        self.render_trade_tape(&mut out);
+        out
+    }
+
+    fn render_dispatch(&self, out: &mut String) {
+        let Some(counters) = self.dispatch.lock().unwrap().clone() else {
+            return;
+        };
+        let counts = counters.snapshot();
+        for (name, help, value) in [
+            (
+                "dispatch_received_total",
+                "Messages queued for subscription callbacks per channel",
+                (|c: &ChannelCounts| c.received) as fn(&ChannelCounts) -> u64,
+            ),
+            (
+                "dispatch_processed_total",
+                "Messages handled by subscription callbacks per channel",
+                |c| c.processed,
+            ),
+            (
+                "dispatch_dropped_total",
+                "Messages dropped because the subscription queue was full per channel",
+                |c| c.dropped,
+            ),
+        ] {
+            let samples: Vec<Sample> = counts
+                .iter()
+                .map(|(channel, c)| {
+                    (
+                        vec![("channel", channel.cli_name().to_string())],
+                        value(c) as f64,
+                    )
+                })
+                .collect();
+            family(out, name, "counter", help, &samples);
+        }
+    }
+
+    fn render_trade_tape(&self, out: &mut String) {
+        let Some(tape) = self.trade_tape.lock().unwrap().clone() else {
+            return;
+        };
+        let (mut vwaps, mut volumes, mut imbalances, mut largest) =
+            (Vec::new(), Vec::new(), Vec::new(), Vec::new());
+        for stats in tape.snapshot() {
+            let labels = vec![
+                ("symbol", stats.market.clone()),
+                ("window", window_label(stats.window)),
+            ];
+            let value = |d: rust_decimal::Decimal| d.to_f64().unwrap_or_default();
+            if let Some(vwap) = stats.vwap {
+                vwaps.push((labels.clone(), value(vwap)));
+            }
+            if let Some(imbalance) = stats.imbalance() {
+                imbalances.push((labels.clone(), value(imbalance)));
+            }
+            if let Some(trade) = stats.largest {
+                largest.push((labels.clone(), value(trade.size)));
+            }
+            for (side, volume) in [("buy", stats.buy_volume), ("sell", stats.sell_volume)] {
+                let mut labels = labels.clone();
+                labels.push(("side", side.to_string()));
+                volumes.push((labels, value(volume)));
+            }
+        }
+        family(
+            out,
+            "trade_vwap",
+            "gauge",
+            "Volume-weighted average trade price over the window",
+            &vwaps,
+        );
+        family(
+            out,
+            "trade_volume",
+            "gauge",
+            "Taker buy and sell volume over the window",
+            &volumes,
+        );
+        family(
+            out,
+            "trade_imbalance",
+            "gauge",
+            "(buy - sell) / total taker volume over the window",
+            &imbalances,
+        );
+        family(
+            out,
+            "largest_trade_size",
+            "gauge",
+            "Largest trade size over the window",
+            &largest,
+        );
+    }
+}
+
+type Sample = (Vec<(&'static str, String)>, f64);
+
+fn labelled(values: &Mutex<BTreeMap<String, f64>>, label: &'static str) -> Vec<Sample> {
+    values
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(key, value)| (vec![(label, key.clone())], *value))
+        .collect()
+}
+
+/// 输出一个指标族；没有样本的带标签指标只输出 HELP / TYPE
+fn family(out: &mut String, name: &str, kind: &str, help: &str, samples: &[Sample]) {
+    let _ = writeln!(out, "# HELP {}{} {}", PREFIX, name, help);
+    let _ = writeln!(out, "# TYPE {}{} {}", PREFIX, name, kind);
+    for (labels, value) in samples {
+        let labels: Vec<_> = labels
+            .iter()
+            .map(|(key, value)| format!("{}=\"{}\"", key, escape(value)))
+            .collect();
+        if labels.is_empty() {
+            let _ = writeln!(out, "{}{} {}", PREFIX, name, value);
+        } else {
+            let _ = writeln!(out, "{}{}{{{}}} {}", PREFIX, name, labels.join(","), value);
+        }
+    }
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// 统计每个订阅频道收到的消息
+pub struct MeteredSource {
+    inner: Arc<dyn MarketDataSource>,
+    track_latency: bool,
+}
+
+impl MeteredSource {
+    /// 统计 `inner` 各频道的消息数，不统计延迟
+    pub fn new(inner: Arc<dyn MarketDataSource>) -> Self {
+        Self {
+            inner,
+            track_latency: false,
+        }
+    }
+
+    /// 同时统计消息相对交易所时间戳的延迟（只适用于实时行情）
+    pub fn with_latency(mut self) -> Self {
+        self.track_latency = true;
+        self
+    }
+}
+
+#[async_trait]
+impl MarketDataSource for MeteredSource {
+    async fn subscribe(
+        &self,
+        channel: Channel,
+        callback: Callback,
+    ) -> Result<SubscriptionId, Error> {
+        let name = channel_key(&channel).0.cli_name();
+        let track_latency = self.track_latency;
+        self.inner
+            .subscribe(
+                channel,
+                Box::new(move |message| {
+                    metrics().ws_message(name, message);
+                    if track_latency {
+                        latency().on_message(name, message);
+                    }
+                    callback(message);
+                }),
+            )
+            .await
+    }
+
+    async fn unsubscribe(&self, id: SubscriptionId) -> Result<(), Error> {
+        self.inner.unsubscribe(id).await
+    }
+
+    async fn stop(&self) -> Result<(), Error> {
+        self.inner.stop().await
+    }
+}
+
+/// 统计下单、成交确认、拒单与撤单数量
+pub struct MeteredGateway<'a> {
+    inner: &'a dyn OrderGateway,
+}
+
+impl<'a> MeteredGateway<'a> {
+    /// 统计经 `inner` 发出的订单与撤单
+    pub fn new(inner: &'a dyn OrderGateway) -> Self {
+        Self { inner }
+    }
+}
+
+#[async_trait]
+impl OrderGateway for MeteredGateway<'_> {
+    async fn create_order(&self, request: OrderRequest) -> Result<OrderUpdate, GatewayError> {
+        metrics().order_submitted();
+        let result = self.inner.create_order(request).await;
+        match result {
+            Ok(_) => metrics().order_accepted(),
+            Err(_) => metrics().order_rejected(),
+        }
+        result
+    }
+
+    async fn modify_order(&self, request: ModifyOrderRequest) -> Result<OrderUpdate, GatewayError> {
+        self.inner.modify_order(request).await
+    }
+
+    async fn cancel_order(&self, order_id: String) -> Result<(), GatewayError> {
+        let result = self.inner.cancel_order(order_id).await;
+        if result.is_ok() {
+            metrics().orders_cancelled(1);
+        }
+        result
+    }
+
+    async fn cancel_order_by_client_id(&self, client_id: String) -> Result<(), GatewayError> {
+        let result = self.inner.cancel_order_by_client_id(client_id).await;
+        if result.is_ok() {
+            metrics().orders_cancelled(1);
+        }
+        result
+    }
+
+    async fn cancel_all_orders_for_market(
+        &self,
+        market: String,
+    ) -> Result<CancelByMarketResponse, GatewayError> {
+        // 接口不返回撤销的数量
+        self.inner.cancel_all_orders_for_market(market).await
+    }
+
+    async fn cancel_all_orders(&self) -> Result<Vec<String>, GatewayError> {
+        let result = self.inner.cancel_all_orders().await;
+        if let Ok(ref ids) = result {
+            metrics().orders_cancelled(ids.len() as u64);
+        }
+        result
+    }
+}
+
+/// `/metrics` HTTP 服务；`stop` 后停止监听
+pub struct MetricsServer {
+    address: SocketAddr,
+    cancel: CancellationToken,
+    task: JoinHandle<()>,
+}
+
+impl MetricsServer {
+    /// 在 `127.0.0.1:port` 上监听（port 为 0 时由系统分配）
+    pub async fn start(port: u16) -> io::Result<Self> {
+        let listener = TcpListener::bind(("127.0.0.1", port)).await?;
+        let address = listener.local_addr()?;
+        let cancel = CancellationToken::new();
+        let stopped = cancel.clone();
+        let task = tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    accepted = listener.accept() => match accepted {
+                        Ok((stream, _)) => {
+                            tokio::spawn(serve(stream, metrics()));
+                        }
+                        Err(e) => warn!("Metrics server accept failed: {}", e),
+                    },
+                    _ = stopped.cancelled() => break,
+                }
+            }
+        });
+        info!("Serving metrics on http://{}/metrics", address);
+        Ok(Self {
+            address,
+            cancel,
+            task,
+        })
+    }
+
+    /// 实际监听的地址（端口为 0 时由系统分配）
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    /// 停止接受连接并等待服务任务退出
+    pub async fn stop(self) {
+        self.cancel.cancel();
+        let _ = self.task.await;
+    }
+}
+
+/// 处理单个连接：`GET /metrics` 返回指标，其余路径 404
+async fn serve(mut stream: TcpStream, metrics: &Metrics) {
+    let mut request = Vec::new();
+    let mut buffer = [0u8; 1024];
+    while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+        match stream.read(&mut buffer).await {
+            Ok(0) | Err(_) => return,
+            Ok(n) => request.extend_from_slice(&buffer[..n]),
+        }
+        if request.len() > MAX_REQUEST_BYTES {
+            return;
+        }
+    }
+    let request = String::from_utf8_lossy(&request);
+    let mut parts = request.split_whitespace();
+    let (method, path) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+    debug!("Metrics request {} {}", method, path);
+    let (status, content_type, body) = match (method, path.split('?').next()) {
+        ("GET", Some("/metrics")) => (
+            "200 OK",
+            "text/plain; version=0.0.4; charset=utf-8",
+            metrics.render(),
+        ),
+        _ => ("404 Not Found", "text/plain", "not found\n".to_string()),
+    };
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    );
+    let _ = stream.write_all(response.as_bytes()).await;
+    let _ = stream.shutdown().await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gateway::DryRun;
+    use paradex::structs::{OrderInstruction, OrderType, Side, BBO};
+    use rust_decimal::Decimal;
+
+    fn bbo(market: &str, bid: f64, ask: f64) -> Message {
+        Message::BBO(BBO {
+            bid,
+            bid_size: 1.0,
+            ask,
+            ask_size: 1.0,
+            market: market.to_string(),
+            last_updated_at: 0,
+        })
+    }
+
+    #[test]
+    fn render_prometheus_text() {
+        let metrics = Metrics::new();
+        metrics.ws_message("bbo", &bbo("BTC-USD-PERP", 95000.0, 95000.5));
+        metrics.ws_message("bbo", &bbo("BTC-USD-PERP", 95001.0, 95001.5));
+        metrics.ws_message("trades", &Message::Connected);
+        metrics.set_position("BTC-USD-PERP", -0.01);
+        metrics.set_balance("USDC", 1000.5);
+        metrics.set_funding_rate("BTC-USD-PERP", 0.0001);
+        metrics.set_funding_projection("BTC-USD-PERP", -5.0);
+        metrics.set_book_signals("BTC-USD-PERP", [0.25, 0.2, 95000.75, 95000.5]);
+        metrics.set_maker_skew("BTC-USD-PERP", -2.5);
+        metrics.rest_error();
+
+        let text = metrics.render();
+        assert!(text.contains("# TYPE trade_lighter_ws_messages_total counter\n"));
+        assert!(text.contains("trade_lighter_ws_messages_total{channel=\"bbo\"} 2\n"));
+        assert!(!text.contains("channel=\"trades\""));
+        assert!(text.contains("trade_lighter_best_bid{symbol=\"BTC-USD-PERP\"} 95001\n"));
+        assert!(text.contains("trade_lighter_best_ask{symbol=\"BTC-USD-PERP\"} 95001.5\n"));
+        assert!(text.contains("trade_lighter_position_size{symbol=\"BTC-USD-PERP\"} -0.01\n"));
+        assert!(text.contains("trade_lighter_account_balance{asset=\"USDC\"} 1000.5\n"));
+        assert!(text.contains("trade_lighter_rest_errors_total 1\n"));
+        assert!(text.contains("trade_lighter_funding_rate{symbol=\"BTC-USD-PERP\"} 0.0001\n"));
+        assert!(text.contains("trade_lighter_book_imbalance_ewma{symbol=\"BTC-USD-PERP\"} 0.2\n"));
+        assert!(text.contains("trade_lighter_microprice{symbol=\"BTC-USD-PERP\"} 95000.75\n"));
+        assert!(text.contains("trade_lighter_maker_skew_bps{symbol=\"BTC-USD-PERP\"} -2.5\n"));
+        assert!(
+            text.contains("trade_lighter_funding_projected_payment{symbol=\"BTC-USD-PERP\"} -5\n")
+        );
+        assert!(text.contains("trade_lighter_orders_submitted_total 0\n"));
+        // 每个指标行都有前缀
+        assert!(text
+            .lines()
+            .filter(|line| !line.starts_with('#'))
+            .all(|line| line.starts_with(PREFIX)));
+    }
+
+    #[test]
+    fn render_trade_tape() {
+        use crate::market_data::TapeTrade;
+        use std::time::Duration;
+
+        let metrics = Metrics::new();
+        assert!(!metrics.render().contains("trade_vwap"));
+        let tape = TradeTape::new(&[Duration::from_secs(60)]);
+        metrics.watch_trade_tape(tape.clone());
+        for (price, size, side) in [(100, 3, Side::BUY), (104, 1, Side::SELL)] {
+            tape.add(
+                "BTC-USD-PERP",
+                TapeTrade {
+                    ts: 1735689600000,
+                    price: Decimal::from(price),
+                    size: Decimal::from(size),
+                    side,
+                },
+            );
+        }
+        let text = metrics.render();
+        let labels = "symbol=\"BTC-USD-PERP\",window=\"1m\"";
+        assert!(
+            text.contains(&format!("trade_lighter_trade_vwap{{{labels}}} 101\n")),
+            "{text}"
+        );
+        assert!(text.contains(&format!("trade_lighter_trade_imbalance{{{labels}}} 0.5\n")));
+        assert!(text.contains(&format!("trade_lighter_largest_trade_size{{{labels}}} 3\n")));
+        assert!(text.contains(&format!(
+            "trade_lighter_trade_volume{{{labels},side=\"sell\"}} 1\n"
+        )));
+    }
+
+    #[test]
+    fn reconnects_are_counted_once_per_disconnect() {
+        let metrics = Metrics::new();
+        // 初次连接时每个订阅各收到一次 Connected
+        for channel in ["bbo", "trades"] {
+            metrics.ws_message(channel, &Message::Connected);
+        }
+        for channel in ["bbo", "trades"] {
+            metrics.ws_message(channel, &Message::Disconnected);
+        }
+        for channel in ["bbo", "trades"] {
+            metrics.ws_message(channel, &Message::Connected);
+        }
+        assert_eq!(metrics.reconnects.load(Ordering::Relaxed), 1);
+    }
+
+    #[tokio::test]
+    async fn server_exposes_metrics_until_stopped() {
+        // 全局指标可能被其他测试更新，只检查固定的部分
+        let gateway = DryRun::new("0xabc");
+        let metered = MeteredGateway::new(&gateway);
+        let before = metrics().orders_submitted.load(Ordering::Relaxed);
+        metered
+            .create_order(OrderRequest {
+                instruction: OrderInstruction::GTC,
+                market: "BTC-USD-PERP".to_string(),
+                price: Some(Decimal::from(95000)),
+                side: Side::BUY,
+                size: Decimal::new(1, 3),
+                order_type: OrderType::LIMIT,
+                client_id: None,
+                flags: vec![],
+                recv_window: None,
+                stp: None,
+                trigger_price: None,
+            })
+            .await
+            .unwrap();
+        assert!(metrics().orders_submitted.load(Ordering::Relaxed) > before);
+
+        let server = MetricsServer::start(0).await.unwrap();
+        let address = server.address();
+        let client = reqwest::Client::new();
+        let response = client
+            .get(format!("http://{}/metrics", address))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+        let text = response.text().await.unwrap();
+        assert!(text.contains("# TYPE trade_lighter_orders_accepted_total counter"));
+        let missing = client
+            .get(format!("http://{}/other", address))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(missing.status(), reqwest::StatusCode::NOT_FOUND);
+
+        server.stop().await;
+        assert!(client
+            .get(format!("http://{}/metrics", address))
+            .send()
+            .await
+            .is_err());
+    }
+}
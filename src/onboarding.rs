@@ -1,174 +1,528 @@
-use log::info;
+mod error;
+mod key_derivation;
+#[cfg(test)]
+pub(crate) mod mock_server;
+pub mod retry;
+mod server_time;
+mod signer;
+mod token_cache;
+pub mod typed_data;
+
+pub use error::OnboardingError;
+pub use key_derivation::derive_stark_key_from_eth;
+pub use retry::RetryPolicy;
+pub use server_time::{measure_clock_drift, ServerClock};
+pub use signer::{LocalSigner, SignError, StarkSigner};
+pub use token_cache::{JwtToken, TokenCache};
+
+use crate::secrets::Redacted;
+use log::{debug, info, warn};
+use paradex::{structs::STPType, url::URL};
 use reqwest::Client as HttpClient;
+use rust_decimal::Decimal;
 use serde::Deserialize;
 use serde_json::json;
-use starknet::core::{crypto::compute_hash_on_elements, types::TypedData, utils::starknet_keccak};
-use starknet_crypto::Felt;
-use starknet_signers::SigningKey;
-use std::time::{SystemTime, UNIX_EPOCH};
+use starknet::core::types::TypedData;
+use starknet_crypto::{Felt, Signature};
+use starknet_signers::VerifyingKey;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use typed_data::{
+    auth_message_hash, build_auth_typed_data, build_onboarding_typed_data,
+    build_transfer_typed_data, build_withdraw_typed_data, log_typed_data_hashes, message_hash,
+    onboarding_message_hash,
+};
+use zeroize::Zeroizing;
+
+/// 内置测试网配置使用的 StarkNet 链 ID（Sepolia），仅在无法获取 `/system/config` 时使用
+pub const TESTNET_CHAIN_ID: &str = "PRIVATE_SN_POTC_SEPOLIA";
+
+#[derive(Debug, Deserialize)]
+struct SystemConfigResponse {
+    starknet_chain_id: String,
+    #[serde(deserialize_with = "server_time::deserialize_u64")]
+    l1_chain_id: u64,
+    paraclear_account_proxy_hash: String,
+    paraclear_account_hash: String,
+}
 
+/// Paradex 网络配置：REST / WS 端点、链 ID 与签名、下单默认值
 #[derive(Debug, Clone)]
 pub struct ParadexConfig {
+    /// StarkNet 链 ID，用作 TypedData domain 的 `chainId`
     pub starknet_chain_id: String,
+    /// REST API 地址（含 `/v1`），onboarding、auth 等请求均基于该地址
+    pub base_url: String,
+    /// WebSocket 地址
+    pub ws_url: String,
+    /// paradex crate 的 REST/WS 客户端所用网络；该 crate 只支持内置地址
+    pub network: URL,
+    /// 输出 TypedData 及各级哈希等敏感信息，用于排查签名问题
+    pub log_sensitive: bool,
+    /// 订单默认的 recv_window（毫秒），防止因时钟偏差或延迟导致过期订单被执行
+    pub recv_window: Option<u64>,
+    /// 订单默认的自成交保护模式
+    pub stp: Option<STPType>,
+    /// L1 以太坊链 ID，用于派生 Stark 密钥的 EIP-712 域
+    pub l1_chain_id: u64,
+    /// Paraclear 账户代理合约类哈希（来自 `/system/config`）
+    pub paraclear_account_proxy_hash: Option<String>,
+    /// Paraclear 账户合约类哈希（来自 `/system/config`）
+    pub paraclear_account_hash: Option<String>,
+    /// auth 签名的有效期（秒），决定 JWT 认证请求的 expiration
+    pub expiry_secs: u64,
+    /// onboarding 与 auth 请求的重试策略
+    pub retry: RetryPolicy,
+}
+
+/// auth 签名默认有效期（秒）
+const DEFAULT_JWT_EXPIRY_SECS: u64 = 24 * 60 * 60;
+/// Paradex 接受的 auth 签名有效期范围（秒）
+pub(crate) const JWT_EXPIRY_RANGE: std::ops::RangeInclusive<u64> = 60..=7 * 24 * 60 * 60;
+
+/// 校验 auth 签名有效期是否在交易所接受的范围内
+pub fn validate_jwt_expiry(expiry_secs: u64) -> Result<(), OnboardingError> {
+    if JWT_EXPIRY_RANGE.contains(&expiry_secs) {
+        Ok(())
+    } else {
+        Err(OnboardingError::InvalidJwtExpiry(expiry_secs))
+    }
 }
 
 impl ParadexConfig {
+    /// 内置测试网配置（离线回退，链 ID 以 `/system/config` 为准）
     pub fn testnet() -> Self {
-        Self {
-            starknet_chain_id: "SN_GOERLI".to_string(),
-        }
+        Self::for_network(URL::Testnet, TESTNET_CHAIN_ID, 11155111)
     }
 
+    /// 内置生产环境配置
     pub fn production() -> Self {
+        Self::for_network(URL::Production, "SN_MAIN", 1)
+    }
+
+    /// 自定义端点（如预发布环境或代理）；`SN_MAIN` 链视为生产网络
+    pub fn custom(base_url: &str, ws_url: &str, chain_id: &str) -> Self {
+        let mut config = if chain_id == "SN_MAIN" {
+            Self::production()
+        } else {
+            Self::testnet()
+        };
+        config.base_url = base_url.trim_end_matches('/').to_string();
+        config.ws_url = ws_url.to_string();
+        config.starknet_chain_id = chain_id.to_string();
+        config
+    }
+
+    /// 应用 `PARADEX_HTTP_URL` / `PARADEX_WS_URL` 环境变量覆盖，未设置时原样返回
+    pub fn with_env_overrides(self) -> Self {
+        let base_url = std::env::var("PARADEX_HTTP_URL").ok();
+        let ws_url = std::env::var("PARADEX_WS_URL").ok();
+        if base_url.is_none() && ws_url.is_none() {
+            return self;
+        }
+        Self::custom(
+            base_url.as_deref().unwrap_or(&self.base_url),
+            ws_url.as_deref().unwrap_or(&self.ws_url),
+            &self.starknet_chain_id,
+        )
+    }
+
+    /// 是否使用了与 `network` 内置地址不同的端点
+    pub fn has_custom_endpoints(&self) -> bool {
+        self.base_url != format!("{}/v1", self.network.rest())
+            || self.ws_url != self.network.websocket()
+    }
+
+    /// 从 `/system/config` 读取链 ID 与 Paraclear 账户类哈希，其余设置沿用 `base`
+    ///
+    /// 请求失败时返回错误，调用方可继续使用静态配置作为离线回退。
+    pub async fn from_system_config(
+        http_client: &HttpClient,
+        base: &ParadexConfig,
+    ) -> Result<Self, OnboardingError> {
+        let url = format!("{}/system/config", base.base_url);
+        let response = retry::with_backoff(&base.retry, "System config", || async {
+            Ok(http_client.get(&url).send().await?)
+        })
+        .await?;
+        if !response.status().is_success() {
+            return Err(OnboardingError::from_http_response(response).await);
+        }
+
+        let system_config: SystemConfigResponse = response.json().await?;
+        let mut config = base.clone();
+        config.starknet_chain_id = system_config.starknet_chain_id;
+        config.l1_chain_id = system_config.l1_chain_id;
+        config.paraclear_account_proxy_hash = Some(system_config.paraclear_account_proxy_hash);
+        config.paraclear_account_hash = Some(system_config.paraclear_account_hash);
+        Ok(config)
+    }
+
+    fn for_network(network: URL, chain_id: &str, l1_chain_id: u64) -> Self {
         Self {
-            starknet_chain_id: "SN_MAIN".to_string(),
+            starknet_chain_id: chain_id.to_string(),
+            base_url: format!("{}/v1", network.rest()),
+            ws_url: network.websocket().to_string(),
+            network,
+            log_sensitive: false,
+            recv_window: Some(5000),
+            stp: Some(STPType::EXPIRE_MAKER),
+            l1_chain_id,
+            paraclear_account_proxy_hash: None,
+            paraclear_account_hash: None,
+            expiry_secs: DEFAULT_JWT_EXPIRY_SECS,
+            retry: RetryPolicy::default(),
         }
     }
 }
 
-/// 将字符串转换为 felt（0x 前缀的十六进制表示）
-fn string_to_felt_hex(s: &str) -> String {
-    if s.is_empty() {
-        return "0x0".to_string();
-    }
+/// Paraclear 中结算资产的链上精度（USDC 为 8 位小数）
+const PARACLEAR_DECIMALS: u32 = 8;
 
-    let mut result = String::from("0x");
-    for byte in s.as_bytes() {
-        result.push_str(&format!("{:02x}", byte));
+/// 将金额按链上精度缩放为整数字符串
+fn scale_amount(amount: Decimal) -> Result<String, OnboardingError> {
+    if amount <= Decimal::ZERO {
+        return Err(OnboardingError::InvalidAmount(format!(
+            "Amount must be positive: {}",
+            amount
+        )));
+    }
+    let scaled = amount * Decimal::from(10u64.pow(PARACLEAR_DECIMALS));
+    if scaled.fract() != Decimal::ZERO {
+        return Err(OnboardingError::InvalidAmount(format!(
+            "Amount {} has more than {} decimal places",
+            amount, PARACLEAR_DECIMALS
+        )));
     }
-    result
+    Ok(scaled.trunc().to_string())
 }
 
-/// 构建 Paradex onboarding TypedData (完全匹配 Python 实现)
-fn build_onboarding_typed_data(chain_id: &str) -> TypedData {
-    let typed_data_json = json!({
-        "types": {
-            "StarkNetDomain": [
-                { "name": "name", "type": "felt" },
-                { "name": "version", "type": "felt" },
-                { "name": "chainId", "type": "felt" }
-            ],
-            "Constant": [
-                { "name": "action", "type": "felt" }
-            ]
-        },
-        "primaryType": "Constant",
-        "domain": {
-            "name": string_to_felt_hex("Paradex"),
-            "chainId": string_to_felt_hex(chain_id),
-            "version": "1"
-        },
-        "message": {
-            "action": "Onboarding"
-        }
-    });
+/// 资金转出请求（提现时 `recipient` 为 L1 以太坊地址，划转时为 Paradex 账户地址）
+#[derive(Debug, Clone)]
+pub struct FundsTransfer {
+    pub recipient: String,
+    pub token: String,
+    pub amount: Decimal,
+}
 
-    serde_json::from_value(typed_data_json).expect("Failed to parse TypedData")
+/// Paradex 签名器
+///
+/// 通过 `StarkSigner` 完成 onboarding / auth / 提现 / 划转签名，本身不持有私钥；
+/// `new` 使用本地私钥（`LocalSigner`），`with_signer` 可接入外部签名服务。
+pub struct ParadexSigner {
+    signer: Box<dyn StarkSigner>,
+    account: Felt,
+    account_address: String,
+    config: ParadexConfig,
 }
 
-/// 构建 Paradex auth TypedData (完全匹配 Python 实现)
-fn build_auth_typed_data(chain_id: &str, timestamp: u64, expiry: u64) -> TypedData {
-    let typed_data_json = json!({
-        "types": {
-            "StarkNetDomain": [
-                { "name": "name", "type": "felt" },
-                { "name": "version", "type": "felt" },
-                { "name": "chainId", "type": "felt" }
-            ],
-            "Request": [
-                { "name": "method", "type": "felt" },
-                { "name": "path", "type": "felt" },
-                { "name": "body", "type": "felt" },
-                { "name": "timestamp", "type": "felt" },
-                { "name": "expiration", "type": "felt" }
-            ]
-        },
-        "primaryType": "Request",
-        "domain": {
-            "name": string_to_felt_hex("Paradex"),
-            "chainId": string_to_felt_hex(chain_id),
-            "version": "1"
-        },
-        "message": {
-            "method": "POST",
-            "path": "/v1/auth",
-            "body": "",
-            "timestamp": timestamp,
-            "expiration": expiry
+impl ParadexSigner {
+    /// 由十六进制 Stark 私钥与账户地址创建签名器，同时校验 JWT 有效期配置
+    pub fn new(
+        private_key: &str,
+        account_address: &str,
+        config: &ParadexConfig,
+    ) -> Result<Self, OnboardingError> {
+        let private_key = Zeroizing::new(
+            Felt::from_hex(private_key)
+                .map_err(|e| OnboardingError::InvalidPrivateKey(e.to_string()))?,
+        );
+        Self::with_signer(
+            Box::new(LocalSigner::new(Zeroizing::new(private_key.to_bytes_be()))),
+            account_address,
+            config,
+        )
+    }
+
+    /// 使用任意 `StarkSigner`（如 HSM、远程签名服务）创建签名器
+    pub fn with_signer(
+        signer: Box<dyn StarkSigner>,
+        account_address: &str,
+        config: &ParadexConfig,
+    ) -> Result<Self, OnboardingError> {
+        validate_jwt_expiry(config.expiry_secs)?;
+        let account = Felt::from_hex(account_address)
+            .map_err(|e| OnboardingError::InvalidAccountAddress(e.to_string()))?;
+
+        Ok(Self {
+            signer,
+            account,
+            account_address: account_address.to_string(),
+            config: config.clone(),
+        })
+    }
+
+    /// 账户地址（构造时传入的原始字符串）
+    pub fn account_address(&self) -> &str {
+        &self.account_address
+    }
+
+    /// 签名器使用的网络配置
+    pub fn config(&self) -> &ParadexConfig {
+        &self.config
+    }
+
+    /// 签名密钥对应的 Stark 公钥
+    pub fn public_key(&self) -> Felt {
+        self.signer.public_key()
+    }
+
+    /// 签名 onboarding 消息，返回 `PARADEX-STARKNET-SIGNATURE` 头
+    pub async fn sign_onboarding(&self) -> Result<Redacted<String>, OnboardingError> {
+        let chain_id = &self.config.starknet_chain_id;
+        let message_hash = onboarding_message_hash(chain_id, self.account)?;
+        if self.config.log_sensitive {
+            let typed_data = build_onboarding_typed_data(chain_id)?;
+            log_typed_data_hashes("Onboarding", &typed_data, message_hash);
         }
-    });
+        self.sign_hash(message_hash).await
+    }
+
+    /// 签名 auth 消息，返回 `PARADEX-STARKNET-SIGNATURE` 头
+    pub async fn sign_auth(
+        &self,
+        timestamp: u64,
+        expiry: u64,
+    ) -> Result<Redacted<String>, OnboardingError> {
+        let chain_id = &self.config.starknet_chain_id;
+        let message_hash = auth_message_hash(chain_id, self.account, timestamp, expiry)?;
+        if self.config.log_sensitive {
+            let typed_data = build_auth_typed_data(chain_id, timestamp, expiry)?;
+            log_typed_data_hashes("Auth", &typed_data, message_hash);
+        }
+        self.sign_hash(message_hash).await
+    }
+
+    /// 签名提现消息，返回 `PARADEX-STARKNET-SIGNATURE` 头
+    pub async fn sign_withdrawal(
+        &self,
+        request: &FundsTransfer,
+        timestamp: u64,
+    ) -> Result<Redacted<String>, OnboardingError> {
+        let typed_data = build_withdraw_typed_data(
+            &self.config.starknet_chain_id,
+            &request.token,
+            &scale_amount(request.amount)?,
+            &request.recipient,
+            timestamp,
+        )?;
+        self.sign_typed_data("Withdraw", &typed_data).await
+    }
+
+    /// 签名划转消息，返回 `PARADEX-STARKNET-SIGNATURE` 头
+    pub async fn sign_transfer(
+        &self,
+        request: &FundsTransfer,
+        timestamp: u64,
+    ) -> Result<Redacted<String>, OnboardingError> {
+        let typed_data = build_transfer_typed_data(
+            &self.config.starknet_chain_id,
+            &request.token,
+            &scale_amount(request.amount)?,
+            &request.recipient,
+            timestamp,
+        )?;
+        self.sign_typed_data("Transfer", &typed_data).await
+    }
+
+    async fn sign_typed_data(
+        &self,
+        label: &str,
+        typed_data: &TypedData,
+    ) -> Result<Redacted<String>, OnboardingError> {
+        let message_hash = message_hash(typed_data, self.account)?;
+        if self.config.log_sensitive {
+            log_typed_data_hashes(label, typed_data, message_hash);
+        }
+        self.sign_hash(message_hash).await
+    }
+
+    /// 校验私钥派生出的账户地址与配置的账户地址一致
+    ///
+    /// 需要 `config` 中已填充 Paraclear 账户类哈希；未填充时跳过。
+    pub fn verify_account_address(&self) -> Result<(), OnboardingError> {
+        let (Some(proxy_hash), Some(account_hash)) = (
+            self.config.paraclear_account_proxy_hash.as_deref(),
+            self.config.paraclear_account_hash.as_deref(),
+        ) else {
+            debug!("Paraclear account hashes unknown, skipping account address check");
+            return Ok(());
+        };
+        let parse = |hash: &str| {
+            Felt::from_hex(hash).map_err(|e| OnboardingError::InvalidAccountAddress(e.to_string()))
+        };
+        let derived = paradex::message::account_address(
+            self.public_key(),
+            parse(proxy_hash)?,
+            parse(account_hash)?,
+        )
+        .map_err(|e| OnboardingError::InvalidAccountAddress(e.to_string()))?;
+        if derived != self.account {
+            return Err(OnboardingError::KeyMismatch {
+                account: self.account_address.clone(),
+                derived: format!("0x{:x}", derived),
+            });
+        }
+        Ok(())
+    }
 
-    serde_json::from_value(typed_data_json).expect("Failed to parse TypedData")
+    async fn sign_hash(&self, message_hash: Felt) -> Result<Redacted<String>, OnboardingError> {
+        let signature = self.signer.sign(message_hash).await?;
+        verify_signature(self.public_key(), message_hash, &signature)?;
+        Ok(Redacted::new(format!(
+            r#"["{}","{}"]"#,
+            signature.r, signature.s
+        )))
+    }
+}
+
+/// 发送前用公钥在本地验证签名，区分签名错误与账户 / 公钥不匹配
+fn verify_signature(
+    public_key: Felt,
+    message_hash: Felt,
+    signature: &Signature,
+) -> Result<(), OnboardingError> {
+    match VerifyingKey::from_scalar(public_key).verify(&message_hash, signature) {
+        Ok(true) => Ok(()),
+        Ok(false) => Err(OnboardingError::SignatureSelfCheckFailed(format!(
+            "signature does not verify for public key 0x{:x}",
+            public_key
+        ))),
+        Err(e) => Err(OnboardingError::SignatureSelfCheckFailed(e.to_string())),
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+#[derive(Debug, Deserialize)]
+struct OnboardingStatus {
+    exists: bool,
+}
+
+/// 查询签名器公钥对应的账户是否已完成 onboarding
+pub async fn is_onboarded(
+    http_client: &HttpClient,
+    signer: &ParadexSigner,
+) -> Result<bool, OnboardingError> {
+    let url = format!("{}/onboarding", signer.config.base_url);
+    let public_key = format!("0x{:x}", signer.public_key());
+    let response = retry::with_backoff(&signer.config.retry, "Onboarding status", || async {
+        Ok(http_client
+            .get(&url)
+            .query(&[("public_key", public_key.as_str())])
+            .send()
+            .await?)
+    })
+    .await?;
+
+    if response.status().is_success() {
+        let status: OnboardingStatus = response.json().await?;
+        debug!(
+            "Onboarding status for {}: exists={}",
+            signer.account_address(),
+            status.exists
+        );
+        Ok(status.exists)
+    } else {
+        Err(OnboardingError::from_http_response(response).await)
+    }
+}
+
+/// onboarding 请求体中的可选字段
+#[derive(Debug, Clone, Default)]
+pub struct OnboardingOptions {
+    /// 推荐码
+    pub referral_code: Option<String>,
 }
 
-/// 执行 onboarding
+fn onboarding_body(
+    public_key: Felt,
+    parent_account: Option<&str>,
+    options: &OnboardingOptions,
+) -> serde_json::Value {
+    let mut body = json!({"public_key": format!("0x{:x}", public_key)});
+    if let Some(parent) = parent_account {
+        body["parent_account"] = json!(parent);
+    }
+    if let Some(ref referral_code) = options.referral_code {
+        body["referral_code"] = json!(referral_code);
+    }
+    body
+}
+
+/// 执行 onboarding，暂时性失败时按配置的策略重试
 pub async fn perform_onboarding(
     http_client: &HttpClient,
-    base_url: &str,
-    account_address: &str,
-    private_key: &str,
+    signer: &ParadexSigner,
     ethereum_account: &str,
-    config: &ParadexConfig,
-) -> Result<(), Box<dyn std::error::Error>> {
-    // 解析私钥
-    let private_key_felt =
-        Felt::from_hex(private_key).map_err(|e| format!("Failed to parse private key: {}", e))?;
-    let signing_key = SigningKey::from_secret_scalar(private_key_felt);
-
-    // 获取公钥并构建签名
-    let public_key = signing_key.verifying_key().scalar();
-    let typed_data = build_onboarding_typed_data(&config.starknet_chain_id);
-    let account_felt = Felt::from_hex(account_address)
-        .map_err(|e| format!("Failed to parse account address: {}", e))?;
-    let domain_hash = typed_data.encoder().domain().encoded_hash();
-    let message_struct_hash = typed_data
-        .encoder()
-        .encode_value(typed_data.primary_type(), typed_data.message())
-        .map_err(|e| format!("Failed to encode message struct: {}", e))?;
-    let domain = typed_data.encoder().domain();
-    let domain_type_hash = starknet_keccak(b"StarkNetDomain(name:felt,version:felt,chainId:felt)");
-    let manual_domain_hash = compute_hash_on_elements(&[
-        domain_type_hash,
-        domain.name,
-        domain.version,
-        domain.chain_id,
-    ]);
-    info!(
-        "Onboarding typed data JSON: {}",
-        serde_json::to_string(&typed_data).unwrap_or_default()
-    );
-    info!("Onboarding domain type hash: 0x{:x}", domain_type_hash);
-    info!(
-        "Onboarding domain fields name=0x{:x}, version=0x{:x}, chain_id=0x{:x}",
-        domain.name, domain.version, domain.chain_id
-    );
-    info!(
-        "Onboarding domain_hash=0x{:x}, manual_domain_hash=0x{:x}, message_struct_hash=0x{:x}",
-        domain_hash, manual_domain_hash, message_struct_hash
-    );
-    let message_hash = typed_data
-        .message_hash(account_felt)
-        .map_err(|e| format!("Failed to encode TypedData: {}", e))?;
-    info!(
-        "Onboarding typed data revision {:?}, message hash: 0x{:x}",
-        typed_data.revision(),
-        message_hash
-    );
-    let signature = signing_key.sign(&message_hash)?;
+    options: &OnboardingOptions,
+) -> Result<(), OnboardingError> {
+    signer.verify_account_address()?;
+    retry::with_backoff(&signer.config.retry, "Onboarding", || {
+        request_onboarding(http_client, signer, ethereum_account, None, options)
+    })
+    .await
+}
+
+/// 在已 onboard 的主账户下 onboard 子账户
+///
+/// `signer` 为子账户自己的密钥与地址，请求体中携带父账户地址。
+pub async fn onboard_subaccount(
+    http_client: &HttpClient,
+    signer: &ParadexSigner,
+    parent_account: &str,
+    ethereum_account: &str,
+) -> Result<(), OnboardingError> {
+    signer.verify_account_address()?;
+    let options = OnboardingOptions::default();
+    retry::with_backoff(&signer.config.retry, "Subaccount onboarding", || {
+        request_onboarding(
+            http_client,
+            signer,
+            ethereum_account,
+            Some(parent_account),
+            &options,
+        )
+    })
+    .await
+}
+
+async fn request_onboarding(
+    http_client: &HttpClient,
+    signer: &ParadexSigner,
+    ethereum_account: &str,
+    parent_account: Option<&str>,
+    options: &OnboardingOptions,
+) -> Result<(), OnboardingError> {
+    let signature_header = signer.sign_onboarding().await?;
 
     // 发送 onboarding 请求
-    let signature_header = format!(r#"["{}","{}"]"#, signature.r, signature.s);
-    let url = format!("{}/onboarding", base_url);
+    let url = format!("{}/onboarding", signer.config.base_url);
 
-    info!("POST {} with StarkNet account: {}", url, account_address);
+    debug!(
+        "POST {} with StarkNet account: {}",
+        url,
+        signer.account_address()
+    );
 
     let response = http_client
         .post(&url)
         .header("Content-Type", "application/json")
         .header("PARADEX-ETHEREUM-ACCOUNT", ethereum_account)
-        .header("PARADEX-STARKNET-ACCOUNT", account_address)
-        .header("PARADEX-STARKNET-SIGNATURE", &signature_header)
-        .json(&json!({"public_key": format!("0x{:x}", public_key)}))
+        .header("PARADEX-STARKNET-ACCOUNT", signer.account_address())
+        .header("PARADEX-STARKNET-SIGNATURE", signature_header.expose())
+        .json(&onboarding_body(
+            signer.public_key(),
+            parent_account,
+            options,
+        ))
         .send()
         .await?;
 
@@ -176,85 +530,52 @@ pub async fn perform_onboarding(
         info!("Onboarding successful");
         Ok(())
     } else {
-        let error_text = response.text().await.unwrap_or_default();
-        Err(format!("Onboarding failed: {}", error_text).into())
+        Err(OnboardingError::from_http_response(response).await)
     }
 }
 
 #[derive(Debug, Deserialize)]
 struct AuthResponse {
-    jwt_token: String,
+    jwt_token: Redacted<String>,
 }
 
-/// 获取 JWT token
+/// 获取 JWT token，签名时间戳以 Paradex 服务器时间为准；暂时性失败时重试
 pub async fn get_jwt_token(
     http_client: &HttpClient,
-    base_url: &str,
-    account_address: &str,
-    private_key: &str,
-    config: &ParadexConfig,
-) -> Result<String, Box<dyn std::error::Error>> {
-    let private_key_felt =
-        Felt::from_hex(private_key).map_err(|e| format!("Failed to parse private key: {}", e))?;
-    let signing_key = SigningKey::from_secret_scalar(private_key_felt);
-
-    let now = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap()
-        .as_secs();
-    let expiry = now + 24 * 60 * 60;
-
-    // 构建并签名 TypedData
-    let typed_data = build_auth_typed_data(&config.starknet_chain_id, now, expiry);
-    let account_felt = Felt::from_hex(account_address)
-        .map_err(|e| format!("Failed to parse account address: {}", e))?;
-    let domain_hash = typed_data.encoder().domain().encoded_hash();
-    let message_struct_hash = typed_data
-        .encoder()
-        .encode_value(typed_data.primary_type(), typed_data.message())
-        .map_err(|e| format!("Failed to encode message struct: {}", e))?;
-    let domain = typed_data.encoder().domain();
-    let domain_type_hash = starknet_keccak(b"StarkNetDomain(name:felt,version:felt,chainId:felt)");
-    let manual_domain_hash = compute_hash_on_elements(&[
-        domain_type_hash,
-        domain.name,
-        domain.version,
-        domain.chain_id,
-    ]);
-    info!(
-        "Auth typed data JSON: {}",
-        serde_json::to_string(&typed_data).unwrap_or_default()
-    );
-    info!("Auth domain type hash: 0x{:x}", domain_type_hash);
-    info!(
-        "Auth domain fields name=0x{:x}, version=0x{:x}, chain_id=0x{:x}",
-        domain.name, domain.version, domain.chain_id
-    );
-    info!(
-        "Auth domain_hash=0x{:x}, manual_domain_hash=0x{:x}, message_struct_hash=0x{:x}",
-        domain_hash, manual_domain_hash, message_struct_hash
-    );
-    let message_hash = typed_data
-        .message_hash(account_felt)
-        .map_err(|e| format!("Failed to encode TypedData: {}", e))?;
-    info!(
-        "Auth typed data revision {:?}, message hash: 0x{:x}",
-        typed_data.revision(),
-        message_hash
-    );
-    let signature = signing_key.sign(&message_hash)?;
+    signer: &ParadexSigner,
+    clock: &ServerClock,
+) -> Result<JwtToken, OnboardingError> {
+    signer.verify_account_address()?;
+    retry::with_backoff(&signer.config.retry, "Auth", || {
+        request_jwt_token(http_client, signer, clock)
+    })
+    .await
+}
+
+async fn request_jwt_token(
+    http_client: &HttpClient,
+    signer: &ParadexSigner,
+    clock: &ServerClock,
+) -> Result<JwtToken, OnboardingError> {
+    let now = clock.now(http_client, &signer.config.base_url).await;
+    let expiry = now + signer.config.expiry_secs;
+
+    let signature_header = signer.sign_auth(now, expiry).await?;
 
     // 发送认证请求
-    let signature_header = format!(r#"["{}","{}"]"#, signature.r, signature.s);
-    let url = format!("{}/auth", base_url);
+    let url = format!("{}/auth", signer.config.base_url);
 
-    info!("POST {} with StarkNet account: {}", url, account_address);
+    debug!(
+        "POST {} with StarkNet account: {}",
+        url,
+        signer.account_address()
+    );
 
     let response = http_client
         .post(&url)
         .header("Content-Type", "application/json")
-        .header("PARADEX-STARKNET-ACCOUNT", account_address)
-        .header("PARADEX-STARKNET-SIGNATURE", &signature_header)
+        .header("PARADEX-STARKNET-ACCOUNT", signer.account_address())
+        .header("PARADEX-STARKNET-SIGNATURE", signature_header.expose())
         .header("PARADEX-TIMESTAMP", now.to_string())
         .header("PARADEX-SIGNATURE-EXPIRATION", expiry.to_string())
         .send()
@@ -262,10 +583,705 @@ pub async fn get_jwt_token(
 
     if response.status().is_success() {
         let auth_response: AuthResponse = response.json().await?;
-        info!("JWT token obtained successfully");
-        Ok(auth_response.jwt_token)
+        info!("JWT token obtained");
+        Ok(JwtToken::new(
+            auth_response.jwt_token.into_inner(),
+            now,
+            expiry,
+            signer.account_address(),
+        ))
+    } else {
+        Err(OnboardingError::from_http_response(response).await)
+    }
+}
+
+/// JWT 刷新间隔（Paradex 的 JWT 有效期约 5 分钟，提前刷新）
+const JWT_REFRESH_INTERVAL: Duration = Duration::from_secs(180);
+/// 刷新失败后的最大退避时间
+const JWT_MAX_BACKOFF: Duration = Duration::from_secs(60);
+/// 连续失败达到该次数后输出告警
+const JWT_FAILURE_WARN_THRESHOLD: u32 = 3;
+
+/// JWT 管理器
+///
+/// 持有签名器并在后台任务中定期重新签名 auth TypedData、调用 `/auth`，
+/// 通过 watch 通道向 WebSocket 与其它需要认证的组件广播最新 token。
+pub struct JwtManager {
+    receiver: watch::Receiver<JwtToken>,
+    task: JoinHandle<()>,
+}
+
+impl JwtManager {
+    /// 同步获取首个 token 后启动后台刷新任务
+    ///
+    /// 提供 `cache` 时优先复用其中仍有效的 token，并在每次获取新 token 后写回。
+    pub async fn start(
+        http_client: HttpClient,
+        signer: Arc<ParadexSigner>,
+        cache: Option<TokenCache>,
+    ) -> Result<Self, OnboardingError> {
+        let clock = ServerClock::new();
+        let cached = cache.as_ref().and_then(|cache| cache.load(unix_now()));
+        let token = match cached {
+            Some(token) => token,
+            None => {
+                let token = get_jwt_token(&http_client, &signer, &clock).await?;
+                if let Some(ref cache) = cache {
+                    cache.store(&token);
+                }
+                token
+            }
+        };
+        let (sender, receiver) = watch::channel(token);
+        let task = tokio::spawn(Self::refresh_loop(
+            http_client,
+            signer,
+            clock,
+            cache,
+            sender,
+        ));
+        Ok(Self { receiver, task })
+    }
+
+    /// 当前有效的 token
+    pub async fn current_token(&self) -> JwtToken {
+        self.receiver.borrow().clone()
+    }
+
+    /// 订阅 token 变更
+    pub fn subscribe(&self) -> watch::Receiver<JwtToken> {
+        self.receiver.clone()
+    }
+
+    async fn refresh_loop(
+        http_client: HttpClient,
+        signer: Arc<ParadexSigner>,
+        clock: ServerClock,
+        cache: Option<TokenCache>,
+        sender: watch::Sender<JwtToken>,
+    ) {
+        let mut failures: u32 = 0;
+        let mut delay = JWT_REFRESH_INTERVAL;
+        loop {
+            tokio::time::sleep(delay).await;
+
+            let result = get_jwt_token(&http_client, &signer, &clock)
+                .await
+                .map_err(|e| e.to_string());
+            match result {
+                Ok(token) => {
+                    debug!("JWT token refreshed");
+                    failures = 0;
+                    delay = JWT_REFRESH_INTERVAL;
+                    if let Some(ref cache) = cache {
+                        cache.store(&token);
+                    }
+                    if sender.send(token).is_err() {
+                        break;
+                    }
+                }
+                Err(e) => {
+                    failures += 1;
+                    delay = Duration::from_secs(1 << failures.min(6)).min(JWT_MAX_BACKOFF);
+                    if failures >= JWT_FAILURE_WARN_THRESHOLD {
+                        warn!(
+                            "JWT refresh failed {} times in a row, retrying in {:?}: {}",
+                            failures, delay, e
+                        );
+                    } else {
+                        debug!("JWT refresh failed, retrying in {:?}: {}", delay, e);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Drop for JwtManager {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// 提现到 L1 以太坊地址
+pub async fn perform_withdrawal(
+    http_client: &HttpClient,
+    signer: &ParadexSigner,
+    request: &FundsTransfer,
+) -> Result<(), OnboardingError> {
+    let now = unix_now();
+    let signature_header = signer.sign_withdrawal(request, now).await?;
+
+    // 发送提现请求
+    let url = format!("{}/withdrawals", signer.config.base_url);
+
+    debug!(
+        "POST {} with StarkNet account: {}",
+        url,
+        signer.account_address()
+    );
+
+    let response = http_client
+        .post(&url)
+        .header("Content-Type", "application/json")
+        .header("PARADEX-STARKNET-ACCOUNT", signer.account_address())
+        .header("PARADEX-STARKNET-SIGNATURE", signature_header.expose())
+        .header("PARADEX-TIMESTAMP", now.to_string())
+        .json(&json!({
+            "token": request.token,
+            "amount": request.amount.to_string(),
+            "recipient": request.recipient,
+        }))
+        .send()
+        .await?;
+
+    if response.status().is_success() {
+        info!(
+            "Withdrawal of {} {} submitted",
+            request.amount, request.token
+        );
+        Ok(())
     } else {
-        let error_text = response.text().await.unwrap_or_default();
-        Err(format!("JWT auth failed: {}", error_text).into())
+        Err(OnboardingError::from_http_response(response).await)
+    }
+}
+
+/// 在 Paradex 账户之间划转（如主账户与子账户）
+pub async fn perform_transfer(
+    http_client: &HttpClient,
+    signer: &ParadexSigner,
+    request: &FundsTransfer,
+) -> Result<(), OnboardingError> {
+    let now = unix_now();
+    let signature_header = signer.sign_transfer(request, now).await?;
+
+    // 发送划转请求
+    let url = format!("{}/transfers", signer.config.base_url);
+
+    debug!(
+        "POST {} with StarkNet account: {}",
+        url,
+        signer.account_address()
+    );
+
+    let response = http_client
+        .post(&url)
+        .header("Content-Type", "application/json")
+        .header("PARADEX-STARKNET-ACCOUNT", signer.account_address())
+        .header("PARADEX-STARKNET-SIGNATURE", signature_header.expose())
+        .header("PARADEX-TIMESTAMP", now.to_string())
+        .json(&json!({
+            "token": request.token,
+            "amount": request.amount.to_string(),
+            "recipient": request.recipient,
+        }))
+        .send()
+        .await?;
+
+    if response.status().is_success() {
+        info!(
+            "Transfer of {} {} to {} submitted",
+            request.amount, request.token, request.recipient
+        );
+        Ok(())
+    } else {
+        Err(OnboardingError::from_http_response(response).await)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use starknet_signers::SigningKey;
+    use std::str::FromStr;
+    use std::sync::Mutex;
+
+    const ACCOUNT: &str = "0x129f3dc1b8962d8bb23f7ddc9a0f1c0fcc9fba43a8bc88bc5c1de5b2b6dd2b8";
+    const PRIVATE_KEY: &str = "0x4c8b5bcb3a3a4e7a6fa3d3b1d7e5c6b6a2e3f9d8c7b6a5e4d3c2b1a09f8e7d6";
+
+    fn message_hash(typed_data: &TypedData) -> Felt {
+        typed_data
+            .message_hash(Felt::from_hex(ACCOUNT).unwrap())
+            .unwrap()
+    }
+
+    #[test]
+    fn scale_amount_rejects_excess_precision() {
+        assert_eq!(
+            scale_amount(Decimal::from_str("12.5").unwrap()).unwrap(),
+            "1250000000"
+        );
+        assert!(scale_amount(Decimal::from_str("0.000000001").unwrap()).is_err());
+        assert!(scale_amount(Decimal::ZERO).is_err());
+    }
+
+    #[tokio::test]
+    async fn is_onboarded_reads_exists_flag() {
+        let public_key = format!(
+            "public_key=0x{:x}",
+            starknet_crypto::get_public_key(&Felt::from_hex(PRIVATE_KEY).unwrap())
+        );
+        let server = mock_server::MockServer::start(move |request| {
+            if request.path.ends_with(&public_key) {
+                (200, r#"{"exists":true}"#.to_string())
+            } else {
+                (200, r#"{"exists":false}"#.to_string())
+            }
+        })
+        .await;
+
+        let config = ParadexConfig::custom(&server.url(), "ws://unused", "SN_GOERLI");
+        let http_client = HttpClient::new();
+        let signer = ParadexSigner::new(PRIVATE_KEY, ACCOUNT, &config).unwrap();
+        assert!(is_onboarded(&http_client, &signer).await.unwrap());
+
+        let other = ParadexSigner::new("0x1", ACCOUNT, &config).unwrap();
+        assert!(!is_onboarded(&http_client, &other).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn subaccount_onboarding_sends_parent_account() {
+        const PARENT: &str = "0x445afd19fe1b2c3d4e5f60718293a4b5c6d7e8f9";
+        let server = mock_server::MockServer::start(|_| (200, "{}".to_string())).await;
+        let config = ParadexConfig::custom(&server.url(), "ws://unused", "SN_GOERLI");
+        let signer = ParadexSigner::new(PRIVATE_KEY, ACCOUNT, &config).unwrap();
+
+        onboard_subaccount(&HttpClient::new(), &signer, PARENT, "0xeth")
+            .await
+            .unwrap();
+
+        let request = &server.requests()[0];
+        assert_eq!(request.path, "/onboarding");
+        assert_eq!(request.header("paradex-starknet-account"), Some(ACCOUNT));
+        let body: serde_json::Value = serde_json::from_str(&request.body).unwrap();
+        assert_eq!(body["parent_account"], PARENT);
+        assert_eq!(body["public_key"], format!("0x{:x}", signer.public_key()));
+    }
+
+    #[tokio::test]
+    async fn referral_code_is_sent_only_when_set() {
+        let server = mock_server::MockServer::start(|_| (200, "{}".to_string())).await;
+        let config = ParadexConfig::custom(&server.url(), "ws://unused", "SN_GOERLI");
+        let signer = ParadexSigner::new(PRIVATE_KEY, ACCOUNT, &config).unwrap();
+        let http_client = HttpClient::new();
+
+        let options = OnboardingOptions {
+            referral_code: Some("cryptofriend".into()),
+        };
+        perform_onboarding(&http_client, &signer, "0xeth", &options)
+            .await
+            .unwrap();
+        perform_onboarding(
+            &http_client,
+            &signer,
+            "0xeth",
+            &OnboardingOptions::default(),
+        )
+        .await
+        .unwrap();
+
+        let bodies: Vec<serde_json::Value> = server
+            .requests()
+            .iter()
+            .map(|request| serde_json::from_str(&request.body).unwrap())
+            .collect();
+        assert_eq!(bodies[0]["referral_code"], "cryptofriend");
+        assert!(bodies[1].get("referral_code").is_none());
+    }
+
+    #[test]
+    fn builtin_configs_use_network_endpoints() {
+        let testnet = ParadexConfig::testnet();
+        assert_eq!(testnet.base_url, "https://api.testnet.paradex.trade/v1");
+        assert!(!testnet.has_custom_endpoints());
+        assert!(!ParadexConfig::production().has_custom_endpoints());
+    }
+
+    #[tokio::test]
+    async fn system_config_overrides_chain_id() {
+        let server = mock_server::MockServer::start(|request| match request.path.as_str() {
+            "/system/config" => (
+                200,
+                r#"{"starknet_chain_id":"PRIVATE_SN_POTC_SEPOLIA","l1_chain_id":"11155111",
+                    "paraclear_account_proxy_hash":"0x1","paraclear_account_hash":"0x2"}"#
+                    .to_string(),
+            ),
+            _ => (404, String::new()),
+        })
+        .await;
+        let base = ParadexConfig::custom(&server.url(), "ws://unused", "SN_GOERLI");
+
+        let config = ParadexConfig::from_system_config(&HttpClient::new(), &base)
+            .await
+            .unwrap();
+        assert_eq!(config.starknet_chain_id, "PRIVATE_SN_POTC_SEPOLIA");
+        assert_eq!(config.l1_chain_id, 11155111);
+        assert_eq!(config.paraclear_account_hash.as_deref(), Some("0x2"));
+        assert_eq!(config.base_url, base.base_url);
+    }
+
+    #[tokio::test]
+    async fn system_config_failure_leaves_fallback() {
+        let server = mock_server::MockServer::start(|_| (404, String::new())).await;
+        let base = ParadexConfig::custom(&server.url(), "ws://unused", "SN_GOERLI");
+        assert!(ParadexConfig::from_system_config(&HttpClient::new(), &base)
+            .await
+            .is_err());
+        assert_eq!(ParadexConfig::testnet().starknet_chain_id, TESTNET_CHAIN_ID);
+    }
+
+    #[tokio::test]
+    async fn custom_config_is_honored_end_to_end() {
+        let server = mock_server::MockServer::start(|request| match request.path.as_str() {
+            "/v1/system/time" => (503, String::new()),
+            "/v1/onboarding" => (200, "{}".to_string()),
+            "/v1/auth" => (200, r#"{"jwt_token":"staging-token"}"#.to_string()),
+            _ => (404, String::new()),
+        })
+        .await;
+        let config = ParadexConfig::custom(
+            &format!("{}/v1/", server.url()),
+            "ws://staging/v1",
+            "SN_GOERLI",
+        );
+        assert!(config.has_custom_endpoints());
+        let signer = ParadexSigner::new(PRIVATE_KEY, ACCOUNT, &config).unwrap();
+        let http_client = HttpClient::new();
+
+        perform_onboarding(
+            &http_client,
+            &signer,
+            "0xeth",
+            &OnboardingOptions::default(),
+        )
+        .await
+        .unwrap();
+        let token = get_jwt_token(&http_client, &signer, &ServerClock::new())
+            .await
+            .unwrap();
+
+        assert_eq!(token.token(), "staging-token");
+        assert_eq!(token.account, ACCOUNT);
+        assert_eq!(token.expires_at, token.issued_at + config.expiry_secs);
+        let paths: Vec<_> = server.requests().into_iter().map(|r| r.path).collect();
+        assert_eq!(paths, ["/v1/onboarding", "/v1/system/time", "/v1/auth"]);
+    }
+
+    #[test]
+    fn jwt_expiry_out_of_range_is_rejected() {
+        assert!(validate_jwt_expiry(86_400).is_ok());
+        assert!(matches!(
+            validate_jwt_expiry(10),
+            Err(OnboardingError::InvalidJwtExpiry(10))
+        ));
+
+        let mut config = ParadexConfig::testnet();
+        config.expiry_secs = 30 * 24 * 60 * 60;
+        assert!(matches!(
+            ParadexSigner::new(PRIVATE_KEY, ACCOUNT, &config),
+            Err(OnboardingError::InvalidJwtExpiry(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn corrupted_key_fails_preflight_without_network_call() {
+        let server = mock_server::MockServer::start(|_| (200, "{}".to_string())).await;
+        let mut config = ParadexConfig::custom(&server.url(), "ws://unused", "SN_GOERLI");
+        config.paraclear_account_proxy_hash =
+            Some("0x3530cc4759d78042f1b543bf797f5f3d647cde0388c33734cf91b7f7b9314a9".into());
+        config.paraclear_account_hash =
+            Some("0x41cb0280ebadaa75f996d8d92c6f265f6d040bb3ba442e5f86a554f1765244e".into());
+        let public_key = starknet_crypto::get_public_key(&Felt::from_hex(PRIVATE_KEY).unwrap());
+        let account = paradex::message::account_address(
+            public_key,
+            Felt::from_hex(config.paraclear_account_proxy_hash.as_deref().unwrap()).unwrap(),
+            Felt::from_hex(config.paraclear_account_hash.as_deref().unwrap()).unwrap(),
+        )
+        .unwrap();
+        let account = format!("0x{:x}", account);
+
+        let corrupted = PRIVATE_KEY.replace("d6", "d7");
+        let signer = ParadexSigner::new(&corrupted, &account, &config).unwrap();
+        let http_client = HttpClient::new();
+        assert!(matches!(
+            perform_onboarding(
+                &http_client,
+                &signer,
+                "0xeth",
+                &OnboardingOptions::default()
+            )
+            .await,
+            Err(OnboardingError::KeyMismatch { .. })
+        ));
+        assert!(matches!(
+            get_jwt_token(&http_client, &signer, &ServerClock::new()).await,
+            Err(OnboardingError::KeyMismatch { .. })
+        ));
+        assert!(server.requests().is_empty());
+
+        let signer = ParadexSigner::new(PRIVATE_KEY, &account, &config).unwrap();
+        perform_onboarding(
+            &http_client,
+            &signer,
+            "0xeth",
+            &OnboardingOptions::default(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(server.requests().len(), 1);
+    }
+
+    #[test]
+    fn signature_self_check_rejects_wrong_public_key() {
+        let signing_key = SigningKey::from_secret_scalar(Felt::from_hex(PRIVATE_KEY).unwrap());
+        let hash = onboarding_message_hash("SN_GOERLI", Felt::from_hex(ACCOUNT).unwrap()).unwrap();
+        let signature = signing_key.sign(&hash).unwrap();
+        assert!(verify_signature(signing_key.verifying_key().scalar(), hash, &signature).is_ok());
+
+        let corrupted = starknet_crypto::get_public_key(&Felt::from_hex("0x1234").unwrap());
+        assert!(matches!(
+            verify_signature(corrupted, hash, &signature),
+            Err(OnboardingError::SignatureSelfCheckFailed(_))
+        ));
+    }
+
+    #[test]
+    fn signature_is_deterministic_for_fixed_key() {
+        let typed_data = build_transfer_typed_data(
+            "SN_SEPOLIA",
+            "USDC",
+            "1250000000",
+            "0x445afd19fe1b2c3d4e5f60718293a4b5c6d7e8f9",
+            1_700_000_000,
+        )
+        .unwrap();
+        let signing_key = SigningKey::from_secret_scalar(Felt::from_hex(PRIVATE_KEY).unwrap());
+        let hash = message_hash(&typed_data);
+        let first = signing_key.sign(&hash).unwrap();
+        let second = signing_key.sign(&hash).unwrap();
+        assert_eq!((first.r, first.s), (second.r, second.s));
+        assert!(signing_key.verifying_key().verify(&hash, &first).unwrap());
+    }
+
+    const ETH_ACCOUNT: &str = "0x36Fb7eFD2b0F4c4C5f5bd6e5A0B1c0a5bB8e2e11";
+    const FIXED_NOW: u64 = 1_700_000_000;
+
+    fn fixed_signer(server: &mock_server::MockServer) -> ParadexSigner {
+        let config = ParadexConfig::custom(&server.url(), "ws://unused", "SN_SEPOLIA");
+        ParadexSigner::new(PRIVATE_KEY, ACCOUNT, &config).unwrap()
+    }
+
+    #[tokio::test]
+    async fn onboarding_request_matches_snapshot() {
+        let server = mock_server::MockServer::start(|_| (200, "{}".to_string())).await;
+        let signer = fixed_signer(&server);
+
+        perform_onboarding(
+            &HttpClient::new(),
+            &signer,
+            ETH_ACCOUNT,
+            &OnboardingOptions::default(),
+        )
+        .await
+        .unwrap();
+
+        let request = &server.requests()[0];
+        assert_eq!(
+            (request.method.as_str(), request.path.as_str()),
+            ("POST", "/onboarding")
+        );
+        assert_eq!(request.header("content-type"), Some("application/json"));
+        assert_eq!(
+            request.header("paradex-ethereum-account"),
+            Some(ETH_ACCOUNT)
+        );
+        assert_eq!(request.header("paradex-starknet-account"), Some(ACCOUNT));
+        assert_eq!(
+            request.header("paradex-starknet-signature"),
+            Some(
+                r#"["2613213752719319423700390259635989723175531121857951900326889687524171423781","2689673957860442744144952605842684537966146879569969326698657853684871883354"]"#
+            )
+        );
+        assert_eq!(
+            request.body,
+            format!(r#"{{"public_key":"0x{:x}"}}"#, signer.public_key())
+        );
+    }
+
+    #[tokio::test]
+    async fn auth_request_matches_snapshot() {
+        let server = mock_server::MockServer::start(|_| {
+            (200, r#"{"jwt_token":"snapshot-token"}"#.to_string())
+        })
+        .await;
+        let signer = fixed_signer(&server);
+
+        let token = get_jwt_token(&HttpClient::new(), &signer, &ServerClock::fixed(FIXED_NOW))
+            .await
+            .unwrap();
+        assert_eq!(token.token(), "snapshot-token");
+        assert_eq!(token.issued_at, FIXED_NOW);
+        assert_eq!(token.expires_at, FIXED_NOW + DEFAULT_JWT_EXPIRY_SECS);
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 1, "fixed clock must not query /system/time");
+        let request = &requests[0];
+        assert_eq!(
+            (request.method.as_str(), request.path.as_str()),
+            ("POST", "/auth")
+        );
+        assert_eq!(request.header("paradex-starknet-account"), Some(ACCOUNT));
+        assert_eq!(request.header("paradex-timestamp"), Some("1700000000"));
+        assert_eq!(
+            request.header("paradex-signature-expiration"),
+            Some("1700086400")
+        );
+        assert_eq!(
+            request.header("paradex-starknet-signature"),
+            Some(
+                r#"["2064742703787400881091419004512414615323809848652711539278754373839278833126","987728810691207312954004475509653348780070022414794884296919426842842434451"]"#
+            )
+        );
+    }
+
+    /// 内存中的签名器：记录收到的哈希，用于确认调用方只依赖 `StarkSigner`
+    struct FakeSigner {
+        inner: LocalSigner,
+        hashes: Arc<Mutex<Vec<Felt>>>,
+        fail: bool,
+    }
+
+    #[async_trait::async_trait]
+    impl StarkSigner for FakeSigner {
+        async fn sign(&self, hash: Felt) -> Result<Signature, SignError> {
+            self.hashes.lock().unwrap().push(hash);
+            if self.fail {
+                return Err(SignError::Failed("device locked".to_string()));
+            }
+            self.inner.sign(hash).await
+        }
+
+        fn public_key(&self) -> Felt {
+            self.inner.public_key()
+        }
+    }
+
+    fn fake_signer(
+        server: &mock_server::MockServer,
+        fail: bool,
+    ) -> (ParadexSigner, Arc<Mutex<Vec<Felt>>>) {
+        let hashes = Arc::new(Mutex::new(Vec::new()));
+        let fake = FakeSigner {
+            inner: LocalSigner::new(Zeroizing::new(
+                Felt::from_hex(PRIVATE_KEY).unwrap().to_bytes_be(),
+            )),
+            hashes: hashes.clone(),
+            fail,
+        };
+        let config = ParadexConfig::custom(&server.url(), "ws://unused", "SN_SEPOLIA");
+        let signer = ParadexSigner::with_signer(Box::new(fake), ACCOUNT, &config).unwrap();
+        (signer, hashes)
+    }
+
+    #[tokio::test]
+    async fn auth_signs_through_stark_signer_trait() {
+        let server = mock_server::MockServer::start(|_| {
+            (200, r#"{"jwt_token":"snapshot-token"}"#.to_string())
+        })
+        .await;
+        let (signer, hashes) = fake_signer(&server, false);
+
+        get_jwt_token(&HttpClient::new(), &signer, &ServerClock::fixed(FIXED_NOW))
+            .await
+            .unwrap();
+
+        let expected = auth_message_hash(
+            "SN_SEPOLIA",
+            Felt::from_hex(ACCOUNT).unwrap(),
+            FIXED_NOW,
+            FIXED_NOW + DEFAULT_JWT_EXPIRY_SECS,
+        )
+        .unwrap();
+        assert_eq!(*hashes.lock().unwrap(), vec![expected]);
+        // 与本地私钥路径的快照签名一致
+        assert_eq!(
+            server.requests()[0].header("paradex-starknet-signature"),
+            Some(
+                r#"["2064742703787400881091419004512414615323809848652711539278754373839278833126","987728810691207312954004475509653348780070022414794884296919426842842434451"]"#
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn signer_error_aborts_before_request() {
+        let server = mock_server::MockServer::start(|_| (200, "{}".to_string())).await;
+        let (signer, hashes) = fake_signer(&server, true);
+
+        let result = perform_onboarding(
+            &HttpClient::new(),
+            &signer,
+            ETH_ACCOUNT,
+            &OnboardingOptions::default(),
+        )
+        .await;
+        assert!(matches!(
+            result,
+            Err(OnboardingError::Signer(SignError::Failed(_)))
+        ));
+        assert_eq!(hashes.lock().unwrap().len(), 1);
+        assert!(server.requests().is_empty());
+    }
+
+    #[tokio::test]
+    async fn api_error_body_is_surfaced() {
+        let body = r#"{"error":"INVALID_STARKNET_SIGNATURE","message":"invalid signature"}"#;
+        let server = mock_server::MockServer::start(move |_| (400, body.to_string())).await;
+        let signer = fixed_signer(&server);
+        let http_client = HttpClient::new();
+
+        for result in [
+            perform_onboarding(
+                &http_client,
+                &signer,
+                ETH_ACCOUNT,
+                &OnboardingOptions::default(),
+            )
+            .await
+            .err(),
+            get_jwt_token(&http_client, &signer, &ServerClock::fixed(FIXED_NOW))
+                .await
+                .err(),
+        ] {
+            match result {
+                Some(OnboardingError::ApiRejected { status, body: text }) => {
+                    assert_eq!(status, reqwest::StatusCode::BAD_REQUEST);
+                    assert_eq!(text, body);
+                }
+                other => panic!("unexpected result {other:?}"),
+            }
+        }
+        // 4xx 不重试
+        assert_eq!(server.requests().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn malformed_json_response() {
+        let server = mock_server::MockServer::start(|_| (200, "<html>oops".to_string())).await;
+        let signer = fixed_signer(&server);
+        let http_client = HttpClient::new();
+
+        // onboarding 成功时不解析响应体
+        perform_onboarding(
+            &http_client,
+            &signer,
+            ETH_ACCOUNT,
+            &OnboardingOptions::default(),
+        )
+        .await
+        .unwrap();
+        assert!(matches!(
+            get_jwt_token(&http_client, &signer, &ServerClock::fixed(FIXED_NOW)).await,
+            Err(OnboardingError::Http(e)) if e.is_decode()
+        ));
     }
 }
@@ -0,0 +1,159 @@
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::{Response, StatusCode};
+use serde::Deserialize;
+use std::time::Duration;
+use thiserror::Error;
+
+/// onboarding、认证、提现与划转流程的错误
+#[derive(Debug, Error)]
+pub enum OnboardingError {
+    #[error("Invalid private key: {0}")]
+    InvalidPrivateKey(String),
+    #[error("Invalid account address: {0}")]
+    InvalidAccountAddress(String),
+    #[error(
+        "JWT expiry {0}s is outside the accepted range {min}..={max}s",
+        min = super::JWT_EXPIRY_RANGE.start(),
+        max = super::JWT_EXPIRY_RANGE.end()
+    )]
+    InvalidJwtExpiry(u64),
+    #[error("String {0:?} does not fit in a single felt (max 31 bytes)")]
+    StringTooLong(String),
+    #[error("Invalid amount: {0}")]
+    InvalidAmount(String),
+    #[error("Signing failed: {0}")]
+    SigningFailed(String),
+    #[error(transparent)]
+    Signer(#[from] super::SignError),
+    #[error("Private key does not belong to account {account} (key derives {derived})")]
+    KeyMismatch { account: String, derived: String },
+    #[error("Signature failed local verification: {0}")]
+    SignatureSelfCheckFailed(String),
+    #[error("HTTP error: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("API rejected request: status={status}, body={body}")]
+    ApiRejected { status: StatusCode, body: String },
+    #[error("Account is already onboarded")]
+    AlreadyOnboarded,
+    #[error("API does not accept a request field: {0}")]
+    UnsupportedField(String),
+    /// HTTP 429；`retry_after` 为服务器 `Retry-After` 提示，缺失时为 `None`
+    #[error("Rate limited by Paradex (retry after {retry_after:?})")]
+    RateLimited { retry_after: Option<Duration> },
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiErrorBody {
+    #[serde(default)]
+    error: Option<String>,
+    #[serde(default)]
+    message: Option<String>,
+}
+
+impl OnboardingError {
+    /// 根据 API 返回的状态码与错误内容归类错误
+    pub(crate) fn from_response(status: StatusCode, body: String) -> Self {
+        if let Ok(parsed) = serde_json::from_str::<ApiErrorBody>(&body) {
+            let code = parsed.error.unwrap_or_default().to_ascii_uppercase();
+            let original_message = parsed.message.unwrap_or_default();
+            let message = original_message.to_ascii_lowercase();
+            if code.contains("ALREADY_ONBOARDED") || message.contains("already onboarded") {
+                return OnboardingError::AlreadyOnboarded;
+            }
+            if code.contains("UNKNOWN_FIELD") || message.contains("unknown field") {
+                return OnboardingError::UnsupportedField(original_message);
+            }
+        }
+        OnboardingError::ApiRejected { status, body }
+    }
+
+    /// 将非 2xx 响应转换为错误；429 时读取 `Retry-After` 头
+    pub(crate) async fn from_http_response(response: Response) -> Self {
+        let status = response.status();
+        if status == StatusCode::TOO_MANY_REQUESTS {
+            return OnboardingError::RateLimited {
+                retry_after: parse_retry_after(response.headers()),
+            };
+        }
+        let body = response.text().await.unwrap_or_default();
+        Self::from_response(status, body)
+    }
+
+    /// 连接失败、超时与 5xx 视为暂时性错误，可以重试；4xx（如签名错误）不重试
+    pub fn is_transient(&self) -> bool {
+        match self {
+            OnboardingError::Http(e) => e.is_connect() || e.is_timeout(),
+            OnboardingError::ApiRejected { status, .. } => status.is_server_error(),
+            _ => false,
+        }
+    }
+}
+
+/// 解析 `Retry-After`：秒数或 HTTP 日期
+fn parse_retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let at = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let secs = (at.timestamp() - chrono::Utc::now().timestamp()).max(0);
+    Some(Duration::from_secs(secs as u64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn already_onboarded_error_code() {
+        let body = r#"{"error":"ACCOUNT_ALREADY_ONBOARDED","message":"account exists"}"#;
+        assert!(matches!(
+            OnboardingError::from_response(StatusCode::BAD_REQUEST, body.into()),
+            OnboardingError::AlreadyOnboarded
+        ));
+    }
+
+    #[test]
+    fn already_onboarded_message() {
+        let body = r#"{"message":"Account already onboarded"}"#;
+        assert!(matches!(
+            OnboardingError::from_response(StatusCode::CONFLICT, body.into()),
+            OnboardingError::AlreadyOnboarded
+        ));
+    }
+
+    #[test]
+    fn signature_failure_is_rejected() {
+        let body =
+            r#"{"error":"STARKNET_SIGNATURE_VERIFICATION_FAILED","message":"invalid signature"}"#;
+        match OnboardingError::from_response(StatusCode::UNAUTHORIZED, body.into()) {
+            OnboardingError::ApiRejected { status, body: text } => {
+                assert_eq!(status, StatusCode::UNAUTHORIZED);
+                assert!(text.contains("STARKNET_SIGNATURE_VERIFICATION_FAILED"));
+            }
+            other => panic!("unexpected error {other:?}"),
+        }
+    }
+
+    #[test]
+    fn unknown_field_maps_to_readable_error() {
+        let body = r#"{"error":"INVALID_REQUEST_PARAMETER","message":"json: unknown field \"referral_code\""}"#;
+        match OnboardingError::from_response(StatusCode::BAD_REQUEST, body.into()) {
+            OnboardingError::UnsupportedField(message) => {
+                assert_eq!(message, r#"json: unknown field "referral_code""#)
+            }
+            other => panic!("unexpected error {other:?}"),
+        }
+    }
+
+    #[test]
+    fn non_json_body_is_rejected() {
+        assert!(matches!(
+            OnboardingError::from_response(StatusCode::BAD_GATEWAY, "Bad Gateway".into()),
+            OnboardingError::ApiRejected {
+                status: StatusCode::BAD_GATEWAY,
+                ..
+            }
+        ));
+    }
+}
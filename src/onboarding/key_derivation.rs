@@ -0,0 +1,254 @@
+use k256::ecdsa::signature::hazmat::PrehashSigner;
+use k256::ecdsa::{Signature, SigningKey};
+use num_bigint::BigUint;
+use sha2::{Digest, Sha256};
+use sha3::Keccak256;
+use starknet_crypto::{get_public_key, Felt};
+use std::str::FromStr;
+use zeroize::Zeroizing;
+
+use super::{OnboardingError, ParadexConfig};
+use crate::secrets::SecretKey;
+
+/// StarkNet 曲线阶，派生出的私钥必须小于该值
+const STARK_EC_ORDER: &str = "800000000000010ffffffffffffffffb781126dcae7b2321e66a241adc64d2f";
+
+/// 由以太坊私钥派生出的 Paradex 账户
+pub struct DerivedAccount {
+    pub eth_address: String,
+    pub stark_private_key: SecretKey,
+    pub stark_public_key: Felt,
+    pub account_address: String,
+}
+
+impl std::fmt::Debug for DerivedAccount {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DerivedAccount")
+            .field("eth_address", &self.eth_address)
+            .field("stark_private_key", &self.stark_private_key)
+            .field("stark_public_key", &self.stark_public_key)
+            .field("account_address", &self.account_address)
+            .finish()
+    }
+}
+
+/// 使用以太坊私钥派生 Paradex StarkNet 私钥与账户地址（与 Python SDK 一致）
+///
+/// 对 EIP-712 `STARK Key` 消息签名，取签名的 r 分量经 grind 得到 Stark 私钥，
+/// 再按 Paraclear 账户代理合约计算账户地址。需要 `config` 中已填充
+/// `l1_chain_id` 与账户类哈希（来自 `/system/config`）。
+pub fn derive_stark_key_from_eth(
+    eth_private_key: &str,
+    config: &ParadexConfig,
+) -> Result<DerivedAccount, OnboardingError> {
+    let (Some(proxy_hash), Some(account_hash)) = (
+        config.paraclear_account_proxy_hash.as_deref(),
+        config.paraclear_account_hash.as_deref(),
+    ) else {
+        return Err(OnboardingError::InvalidAccountAddress(
+            "Paraclear account class hashes are not configured".into(),
+        ));
+    };
+
+    let secret = parse_eth_key(eth_private_key)
+        .and_then(|bytes| SigningKey::from_slice(bytes.as_slice()).ok())
+        .ok_or_else(|| OnboardingError::InvalidPrivateKey("Invalid Ethereum private key".into()))?;
+
+    let digest = stark_key_message_hash(config.l1_chain_id);
+    let r = secp256k1_signature_r(&secret, &digest);
+    let stark_private_key = SecretKey::new(format!("0x{:x}", grind_key(&r)));
+
+    let private_felt = Felt::from_hex(stark_private_key.expose())
+        .map_err(|e| OnboardingError::InvalidPrivateKey(e.to_string()))?;
+    let stark_public_key = get_public_key(&private_felt);
+    let account = paradex::message::account_address(
+        stark_public_key,
+        Felt::from_str(proxy_hash)
+            .map_err(|e| OnboardingError::InvalidAccountAddress(e.to_string()))?,
+        Felt::from_str(account_hash)
+            .map_err(|e| OnboardingError::InvalidAccountAddress(e.to_string()))?,
+    )
+    .map_err(|e| OnboardingError::InvalidAccountAddress(e.to_string()))?;
+
+    Ok(DerivedAccount {
+        eth_address: eth_address(&secret),
+        stark_private_key,
+        stark_public_key,
+        account_address: format!("0x{:x}", account),
+    })
+}
+
+/// 解析十六进制私钥为 32 字节（不足时左侧补零）
+fn parse_eth_key(s: &str) -> Option<Zeroizing<[u8; 32]>> {
+    let hex = s.trim().trim_start_matches("0x");
+    if hex.is_empty() || hex.len() > 64 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    let padded = Zeroizing::new(format!("{:0>64}", hex));
+    let mut bytes = Zeroizing::new([0u8; 32]);
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&padded[2 * i..2 * i + 2], 16).ok()?;
+    }
+    Some(bytes)
+}
+
+fn hex_int(s: &str) -> BigUint {
+    BigUint::parse_bytes(s.as_bytes(), 16).expect("valid hex constant")
+}
+
+fn keccak(parts: &[&[u8]]) -> [u8; 32] {
+    let mut hasher = Keccak256::new();
+    for part in parts {
+        hasher.update(part);
+    }
+    hasher.finalize().into()
+}
+
+fn to_bytes32(value: &BigUint) -> [u8; 32] {
+    let bytes = value.to_bytes_be();
+    let mut out = [0u8; 32];
+    out[32 - bytes.len()..].copy_from_slice(&bytes);
+    out
+}
+
+/// Paradex `STARK Key` EIP-712 消息的签名摘要
+fn stark_key_message_hash(l1_chain_id: u64) -> [u8; 32] {
+    let domain_type_hash = keccak(&[b"EIP712Domain(string name,string version,uint256 chainId)"]);
+    let domain_separator = keccak(&[
+        &domain_type_hash,
+        &keccak(&[b"Paradex"]),
+        &keccak(&[b"1"]),
+        &to_bytes32(&BigUint::from(l1_chain_id)),
+    ]);
+    let struct_hash = keccak(&[
+        &keccak(&[b"Constant(string action)"]),
+        &keccak(&[b"STARK Key"]),
+    ]);
+    keccak(&[b"\x19\x01", &domain_separator, &struct_hash])
+}
+
+/// secp256k1 ECDSA 签名（RFC 6979 确定性 k）的 r 分量
+///
+/// 签名由 `k256` 以常数时间完成；密钥派生只依赖 r。
+fn secp256k1_signature_r(secret: &SigningKey, digest: &[u8; 32]) -> BigUint {
+    let signature: Signature = secret
+        .sign_prehash(digest)
+        .expect("32-byte prehash is always accepted");
+    BigUint::from_bytes_be(&signature.r().to_bytes())
+}
+
+/// 以太坊私钥对应的地址
+fn eth_address(secret: &SigningKey) -> String {
+    let point = secret.verifying_key().to_encoded_point(false);
+    let hash = keccak(&[&point.as_bytes()[1..]]);
+    let hex: String = hash[12..].iter().map(|b| format!("{:02x}", b)).collect();
+    format!("0x{}", hex)
+}
+
+/// StarkWare `grind_key`：对种子做带序号的 SHA-256，直到结果落入无偏区间
+fn grind_key(seed: &BigUint) -> BigUint {
+    fn padded_hex(value: &BigUint) -> String {
+        let hex = format!("{:x}", value);
+        if hex.len() % 2 == 0 {
+            hex
+        } else {
+            format!("0{}", hex)
+        }
+    }
+
+    let order = hex_int(STARK_EC_ORDER);
+    let two_256 = BigUint::from(1u32) << 256;
+    let max_allowed = &two_256 - (&two_256 % &order);
+
+    let mut index = BigUint::ZERO;
+    loop {
+        let input = format!("{}{}", padded_hex(seed), padded_hex(&index));
+        let bytes = (0..input.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&input[i..i + 2], 16).unwrap())
+            .collect::<Vec<_>>();
+        let key = BigUint::from_bytes_be(&Sha256::digest(&bytes));
+        if key < max_allowed {
+            return key % order;
+        }
+        index += 1u32;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key_one() -> SigningKey {
+        SigningKey::from_slice(parse_eth_key("0x1").unwrap().as_slice()).unwrap()
+    }
+
+    #[test]
+    fn secp256k1_rfc6979_vector() {
+        // 私钥 1，消息 "Satoshi Nakamoto"
+        let digest: [u8; 32] = Sha256::digest(b"Satoshi Nakamoto").into();
+        let r = secp256k1_signature_r(&key_one(), &digest);
+        assert_eq!(
+            format!("{:x}", r),
+            "934b1ea10a4b3c1757e2b0c017d0b6143ce3c9a7e6a4a49860d7a6ab210ee3d8"
+        );
+    }
+
+    #[test]
+    fn eth_address_vector() {
+        // 私钥 1 对应的众所周知的地址
+        assert_eq!(
+            eth_address(&key_one()),
+            "0x7e5f4552091a69125d5dfcb7b8c2659029395bdf"
+        );
+    }
+
+    #[test]
+    fn grind_key_matches_starkware_vector() {
+        let signature = "21fbf0696d5e0aa2ef41a2b4ffb623bcaf070461d61cf7251c74161f82fec3a4370854bc0a34b3ab487c1bc021cd318c734c51ae29374f2beb0e6f2dd49b4bf41c";
+        let r = hex_int(&signature[..64]);
+        assert_eq!(
+            format!("{:x}", grind_key(&r)),
+            "766f11e90cd7c7b43085b56da35c781f8c067ac0d578eabdceebc4886435bda"
+        );
+    }
+
+    #[test]
+    fn derive_stark_key_end_to_end() {
+        // 参考值按 paradex-py 的流程独立计算：eth_account 对 `STARK Key` 消息做
+        // EIP-712 签名，取 r 经 `grind_key` 得到 Stark 私钥
+        let mut config = ParadexConfig::testnet();
+        config.paraclear_account_proxy_hash = Some("0x1".into());
+        config.paraclear_account_hash = Some("0x2".into());
+
+        let derived = derive_stark_key_from_eth(
+            "0x4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318",
+            &config,
+        )
+        .unwrap();
+        assert_eq!(
+            derived.eth_address,
+            "0x2c7536e3605d9c16a7a3d7b1898e529396a65c23"
+        );
+        assert_eq!(
+            derived.stark_private_key.expose(),
+            "0x15aa66c3c7959ac3ab7fca87c88742bb33a17ff151579a24d43e372ebd89a9d"
+        );
+        assert_eq!(
+            derived.stark_public_key,
+            Felt::from_hex("0x1c5c6f2916fa792008236cc14d10c139b90ce55d6c6d65b059a92fe0e7e8a63")
+                .unwrap()
+        );
+        assert!(!format!("{:?}", derived).contains("15aa66c3"));
+
+        config.l1_chain_id = 1;
+        let derived = derive_stark_key_from_eth("0x1", &config).unwrap();
+        assert_eq!(
+            derived.stark_private_key.expose(),
+            "0x105f2dca6db3b2731ec4352fe72e445b9b6ddedee3da6e57b14472d58a9ca3"
+        );
+
+        assert!(derive_stark_key_from_eth("0x0", &config).is_err());
+        assert!(derive_stark_key_from_eth("0xzz", &config).is_err());
+    }
+}
@@ -0,0 +1,240 @@
+use log::{debug, warn};
+use std::future::Future;
+use std::time::Duration;
+
+use super::OnboardingError;
+
+/// REST 请求的重试策略：指数退避加随机抖动
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// 最大尝试次数（含首次请求）
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    /// 收到 429 后最多按 `Retry-After` 等待重试的次数
+    pub max_rate_limit_retries: u32,
+    /// 429 响应未携带 `Retry-After` 时的等待时间
+    pub rate_limit_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(10),
+            max_rate_limit_retries: 3,
+            rate_limit_delay: Duration::from_secs(5),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// 第 `attempt` 次失败后的等待时间：在退避上限的 [1/2, 1] 区间内随机取值
+    fn delay(&self, attempt: u32) -> Duration {
+        let backoff = self
+            .base_delay
+            .saturating_mul(1 << (attempt - 1).min(16))
+            .min(self.max_delay);
+        let half = backoff / 2;
+        half + half.mul_f64(rand::random::<f64>())
+    }
+}
+
+/// 执行 `operation`，遇到暂时性错误时按 `policy` 退避重试
+///
+/// 每次重试都会重新调用 `operation`，因此签名与时间戳会重新生成。
+/// 429 按 `Retry-After` 单独计数重试，用尽后返回带实际等待时间的 `RateLimited`。
+pub async fn with_backoff<T, F, Fut>(
+    policy: &RetryPolicy,
+    label: &str,
+    mut operation: F,
+) -> Result<T, OnboardingError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, OnboardingError>>,
+{
+    let mut attempt = 1;
+    let mut rate_limited = 0;
+    loop {
+        match operation().await {
+            Err(OnboardingError::RateLimited { retry_after }) => {
+                let delay = retry_after.unwrap_or(policy.rate_limit_delay);
+                if rate_limited >= policy.max_rate_limit_retries {
+                    return Err(OnboardingError::RateLimited {
+                        retry_after: Some(delay),
+                    });
+                }
+                // 只在首次限流时告警，避免崩溃重启循环中刷屏
+                if rate_limited == 0 {
+                    warn!(
+                        endpoint = label,
+                        retry_after_secs = delay.as_secs(),
+                        max_retries = policy.max_rate_limit_retries;
+                        "{} rate limited by Paradex, backing off", label
+                    );
+                } else {
+                    debug!("{} still rate limited, waiting {:?}", label, delay);
+                }
+                tokio::time::sleep(delay).await;
+                rate_limited += 1;
+            }
+            Err(e) if e.is_transient() && attempt < policy.max_attempts => {
+                let delay = policy.delay(attempt);
+                warn!(
+                    "{} failed (attempt {}/{}), retrying in {:?}: {}",
+                    label, attempt, policy.max_attempts, delay, e
+                );
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{
+        get_jwt_token, mock_server::MockServer, perform_onboarding, OnboardingOptions,
+        ParadexConfig, ParadexSigner, ServerClock,
+    };
+    use super::*;
+    use reqwest::Client as HttpClient;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Instant;
+
+    const ACCOUNT: &str = "0x129f3dc1b8962d8bb23f7ddc9a0f1c0fcc9fba43a8bc88bc5c1de5b2b6dd2b8";
+    const PRIVATE_KEY: &str = "0x4c8b5bcb3a3a4e7a6fa3d3b1d7e5c6b6a2e3f9d8c7b6a5e4d3c2b1a09f8e7d6";
+
+    fn signer(server: &MockServer, max_attempts: u32) -> ParadexSigner {
+        let mut config = ParadexConfig::custom(&server.url(), "ws://unused", "SN_GOERLI");
+        config.retry = RetryPolicy {
+            max_attempts,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(1),
+            max_rate_limit_retries: 2,
+            rate_limit_delay: Duration::from_millis(200),
+        };
+        ParadexSigner::new(PRIVATE_KEY, ACCOUNT, &config).unwrap()
+    }
+
+    /// `/auth` 前 `failures` 次返回 `status`，之后成功
+    async fn flaky_server(failures: usize, status: u16) -> MockServer {
+        let calls = AtomicUsize::new(0);
+        MockServer::start(move |request| match request.path.as_str() {
+            "/auth" if calls.fetch_add(1, Ordering::SeqCst) < failures => {
+                (status, r#"{"error":"UNAVAILABLE"}"#.to_string())
+            }
+            "/auth" => (200, r#"{"jwt_token":"token"}"#.to_string()),
+            _ => (404, String::new()),
+        })
+        .await
+    }
+
+    fn auth_requests(server: &MockServer) -> usize {
+        server
+            .requests()
+            .iter()
+            .filter(|request| request.path == "/auth")
+            .count()
+    }
+
+    #[tokio::test]
+    async fn retries_server_errors_with_backoff() {
+        let server = flaky_server(2, 502).await;
+        let started = Instant::now();
+        let token = get_jwt_token(&HttpClient::new(), &signer(&server, 5), &ServerClock::new())
+            .await
+            .unwrap();
+
+        assert_eq!(token.token(), "token");
+        assert_eq!(auth_requests(&server), 3);
+        // 两次退避至少为 50ms + 100ms
+        assert!(started.elapsed() >= Duration::from_millis(150));
+    }
+
+    #[tokio::test]
+    async fn does_not_retry_client_errors() {
+        let server = flaky_server(1, 401).await;
+        let result =
+            get_jwt_token(&HttpClient::new(), &signer(&server, 5), &ServerClock::new()).await;
+
+        assert!(matches!(result, Err(OnboardingError::ApiRejected { .. })));
+        assert_eq!(auth_requests(&server), 1);
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_max_attempts() {
+        let server = flaky_server(10, 503).await;
+        let result =
+            get_jwt_token(&HttpClient::new(), &signer(&server, 2), &ServerClock::new()).await;
+
+        assert!(result.is_err());
+        assert_eq!(auth_requests(&server), 2);
+    }
+
+    /// 所有请求都返回 429，`retry_after` 为 `Retry-After` 头（None 表示不携带）
+    async fn rate_limited_server(retry_after: Option<&'static str>) -> MockServer {
+        MockServer::start_with_headers(move |_| {
+            let headers = retry_after
+                .map(|value| vec![("Retry-After".to_string(), value.to_string())])
+                .unwrap_or_default();
+            (429, headers, r#"{"error":"RATE_LIMITED"}"#.to_string())
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn rate_limit_honours_retry_after_header() {
+        let server = rate_limited_server(Some("1")).await;
+        let started = Instant::now();
+        let result =
+            get_jwt_token(&HttpClient::new(), &signer(&server, 5), &ServerClock::new()).await;
+
+        match result {
+            Err(OnboardingError::RateLimited { retry_after }) => {
+                assert_eq!(retry_after, Some(Duration::from_secs(1)))
+            }
+            other => panic!("unexpected result {other:?}"),
+        }
+        // 首次请求加 2 次限流重试，每次等待 1s
+        assert_eq!(auth_requests(&server), 3);
+        assert!(started.elapsed() >= Duration::from_secs(2));
+    }
+
+    #[tokio::test]
+    async fn rate_limit_without_header_uses_default_delay() {
+        let server = rate_limited_server(None).await;
+        let started = Instant::now();
+        let result = perform_onboarding(
+            &HttpClient::new(),
+            &signer(&server, 5),
+            "0x36Fb7eFD2b0F4c4C5f5bd6e5A0B1c0a5bB8e2e11",
+            &OnboardingOptions::default(),
+        )
+        .await;
+
+        match result {
+            Err(OnboardingError::RateLimited { retry_after }) => {
+                assert_eq!(retry_after, Some(Duration::from_millis(200)))
+            }
+            other => panic!("unexpected result {other:?}"),
+        }
+        assert_eq!(server.requests().len(), 3);
+        assert!(started.elapsed() >= Duration::from_millis(400));
+    }
+
+    #[test]
+    fn delay_grows_exponentially_within_jitter_bounds() {
+        let policy = RetryPolicy::default();
+        for attempt in 1..=8 {
+            let cap = policy
+                .base_delay
+                .saturating_mul(1 << (attempt - 1))
+                .min(policy.max_delay);
+            let delay = policy.delay(attempt);
+            assert!(delay >= cap / 2 && delay <= cap);
+        }
+    }
+}
@@ -0,0 +1,191 @@
+use log::{debug, warn};
+use reqwest::Client as HttpClient;
+use serde::Deserialize;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use super::{unix_now, OnboardingError};
+
+/// 重新同步服务器时间的间隔
+const CLOCK_SYNC_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+#[derive(Debug, Deserialize)]
+struct SystemTimeResponse {
+    #[serde(deserialize_with = "deserialize_u64")]
+    server_time: u64,
+}
+
+/// Paradex 的整数字段（如 `server_time`、`l1_chain_id`）可能以字符串或数字返回
+pub(super) fn deserialize_u64<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<u64, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Integer {
+        Number(u64),
+        Text(String),
+    }
+
+    match Integer::deserialize(deserializer)? {
+        Integer::Number(value) => Ok(value),
+        Integer::Text(text) => text.parse().map_err(serde::de::Error::custom),
+    }
+}
+
+/// 查询 Paradex 服务器时间（Unix 秒）
+pub async fn fetch_server_time(
+    http_client: &HttpClient,
+    base_url: &str,
+) -> Result<u64, OnboardingError> {
+    Ok(fetch_server_time_millis(http_client, base_url).await? / 1000)
+}
+
+async fn fetch_server_time_millis(
+    http_client: &HttpClient,
+    base_url: &str,
+) -> Result<u64, OnboardingError> {
+    let url = format!("{}/system/time", base_url);
+    let response = http_client.get(&url).send().await?;
+
+    if response.status().is_success() {
+        let body: SystemTimeResponse = response.json().await?;
+        Ok(body.server_time)
+    } else {
+        Err(OnboardingError::from_http_response(response).await)
+    }
+}
+
+/// 测量服务器时间减去本地时间的偏差（毫秒），以请求往返的中点作为本地参考时间
+pub async fn measure_clock_drift(
+    http_client: &HttpClient,
+    base_url: &str,
+) -> Result<i64, OnboardingError> {
+    let sent_at = unix_now_millis();
+    let server_time = fetch_server_time_millis(http_client, base_url).await?;
+    let midpoint = sent_at + (unix_now_millis() - sent_at) / 2;
+    Ok(server_time as i64 - midpoint)
+}
+
+fn unix_now_millis() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as i64
+}
+
+/// 以服务器时间为准的时钟：缓存本地时钟与服务器的偏差并定期刷新
+///
+/// 服务器时间不可用时退回本地时间（沿用上一次成功同步的偏差）。
+#[derive(Debug, Default)]
+pub struct ServerClock {
+    offset_secs: AtomicI64,
+    synced_at: Mutex<Option<Instant>>,
+    fixed: Option<u64>,
+}
+
+impl ServerClock {
+    /// 创建尚未同步的时钟，首次调用 `now` 时同步
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 始终返回 `now` 的时钟，不访问服务器（用于测试或重放固定时间戳）
+    pub fn fixed(now: u64) -> Self {
+        Self {
+            fixed: Some(now),
+            ..Self::default()
+        }
+    }
+
+    /// 按服务器时间校正后的当前 Unix 秒；偏差过期时先重新同步
+    pub async fn now(&self, http_client: &HttpClient, base_url: &str) -> u64 {
+        if let Some(now) = self.fixed {
+            return now;
+        }
+        let stale = self
+            .synced_at
+            .lock()
+            .unwrap()
+            .is_none_or(|at| at.elapsed() >= CLOCK_SYNC_INTERVAL);
+        if stale {
+            self.sync(http_client, base_url).await;
+        }
+        unix_now().saturating_add_signed(self.offset_secs.load(Ordering::Relaxed))
+    }
+
+    async fn sync(&self, http_client: &HttpClient, base_url: &str) {
+        // 无论成功与否都记录同步时间，避免服务器不可用时每次请求都重试
+        *self.synced_at.lock().unwrap() = Some(Instant::now());
+        match fetch_server_time(http_client, base_url).await {
+            Ok(server_time) => {
+                let offset = server_time as i64 - unix_now() as i64;
+                if offset != 0 {
+                    debug!("Local clock differs from Paradex server by {}s", offset);
+                }
+                self.offset_secs.store(offset, Ordering::Relaxed);
+            }
+            Err(e) => warn!(
+                "Failed to fetch Paradex server time, using local clock: {}",
+                e
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{get_jwt_token, mock_server::MockServer, ParadexConfig, ParadexSigner};
+    use super::*;
+
+    const ACCOUNT: &str = "0x129f3dc1b8962d8bb23f7ddc9a0f1c0fcc9fba43a8bc88bc5c1de5b2b6dd2b8";
+    const PRIVATE_KEY: &str = "0x4c8b5bcb3a3a4e7a6fa3d3b1d7e5c6b6a2e3f9d8c7b6a5e4d3c2b1a09f8e7d6";
+    const SKEW_SECS: u64 = 3600;
+
+    #[tokio::test]
+    async fn auth_timestamp_follows_server_clock() {
+        let server_time = (unix_now() + SKEW_SECS) * 1000;
+        let server = MockServer::start(move |request| match request.path.as_str() {
+            "/system/time" => (200, format!(r#"{{"server_time":"{}"}}"#, server_time)),
+            "/auth" => (200, r#"{"jwt_token":"token"}"#.to_string()),
+            _ => (404, String::new()),
+        })
+        .await;
+
+        let config = ParadexConfig::custom(&server.url(), "ws://unused", "SN_GOERLI");
+        let signer = ParadexSigner::new(PRIVATE_KEY, ACCOUNT, &config).unwrap();
+        let clock = ServerClock::new();
+        let http_client = HttpClient::new();
+        let token = get_jwt_token(&http_client, &signer, &clock).await.unwrap();
+        assert_eq!(token.token(), "token");
+
+        let auth = server
+            .requests()
+            .into_iter()
+            .find(|request| request.path == "/auth")
+            .unwrap();
+        assert_eq!(auth.method, "POST");
+        let timestamp: u64 = auth.header("paradex-timestamp").unwrap().parse().unwrap();
+        assert!(timestamp.abs_diff(unix_now() + SKEW_SECS) <= 5);
+    }
+
+    #[tokio::test]
+    async fn falls_back_to_local_clock_when_time_endpoint_fails() {
+        let server = MockServer::start(|_| (503, String::new())).await;
+        let clock = ServerClock::new();
+        let now = clock.now(&HttpClient::new(), &server.url()).await;
+        assert!(now.abs_diff(unix_now()) <= 5);
+    }
+
+    #[tokio::test]
+    async fn drift_is_measured_in_millis() {
+        let server_time = (unix_now() + SKEW_SECS) * 1000;
+        let server =
+            MockServer::start(move |_| (200, format!(r#"{{"server_time":{}}}"#, server_time)))
+                .await;
+        let drift = measure_clock_drift(&HttpClient::new(), &server.url())
+            .await
+            .unwrap();
+        assert!(drift.abs_diff(SKEW_SECS as i64 * 1000) <= 5000);
+    }
+}
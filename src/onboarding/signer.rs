@@ -0,0 +1,60 @@
+//! Stark 签名抽象：签名可以来自本地私钥，也可以委托给 HSM / 远程签名服务
+
+use async_trait::async_trait;
+use starknet_core::crypto::ecdsa_sign;
+use starknet_crypto::{get_public_key, Felt, Signature};
+use thiserror::Error;
+use zeroize::Zeroizing;
+
+/// `StarkSigner` 签名错误
+#[derive(Debug, Error)]
+pub enum SignError {
+    #[error("Signing failed: {0}")]
+    Failed(String),
+    #[error("Signer backend error: {0}")]
+    Backend(#[source] Box<dyn std::error::Error + Send + Sync>),
+}
+
+/// 对消息哈希进行 Stark 签名
+///
+/// onboarding、auth、提现与划转只依赖该 trait，不接触私钥本身。
+#[async_trait]
+pub trait StarkSigner: Send + Sync {
+    /// 签名消息哈希
+    async fn sign(&self, hash: Felt) -> Result<Signature, SignError>;
+
+    /// 签名密钥对应的 Stark 公钥
+    fn public_key(&self) -> Felt;
+}
+
+/// 在进程内持有私钥的签名器；私钥保存在 `Zeroizing` 中，销毁时清零
+pub struct LocalSigner {
+    secret: Zeroizing<[u8; 32]>,
+    public_key: Felt,
+}
+
+impl LocalSigner {
+    /// 以大端字节的 `private_key` 签名；私钥由签名器接管，释放时清零
+    pub fn new(private_key: Zeroizing<[u8; 32]>) -> Self {
+        let public_key = get_public_key(&Zeroizing::new(Felt::from_bytes_be(&private_key)));
+        Self {
+            secret: private_key,
+            public_key,
+        }
+    }
+}
+
+#[async_trait]
+impl StarkSigner for LocalSigner {
+    async fn sign(&self, hash: Felt) -> Result<Signature, SignError> {
+        // 还原的私钥只在本次签名内存在，离开作用域时清零
+        let secret = Zeroizing::new(Felt::from_bytes_be(&self.secret));
+        ecdsa_sign(&secret, &hash)
+            .map(Signature::from)
+            .map_err(|e| SignError::Failed(e.to_string()))
+    }
+
+    fn public_key(&self) -> Felt {
+        self.public_key
+    }
+}
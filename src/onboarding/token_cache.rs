@@ -0,0 +1,231 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use super::ParadexConfig;
+use crate::secrets::{serialize_exposed, Redacted};
+
+/// 复用缓存 token 时要求的最小剩余有效期
+pub const DEFAULT_TOKEN_MARGIN: Duration = Duration::from_secs(10 * 60);
+
+/// `/auth` 返回的 JWT 及其元数据，可直接持久化
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JwtToken {
+    #[serde(alias = "jwt_token", serialize_with = "serialize_exposed")]
+    pub token: Redacted<String>,
+    /// 签名 auth 消息时使用的时间戳（Unix 秒）
+    #[serde(default)]
+    pub issued_at: u64,
+    /// 过期时间（Unix 秒）
+    pub expires_at: u64,
+    /// 认证的账户地址
+    #[serde(default)]
+    pub account: String,
+}
+
+impl JwtToken {
+    /// `expires_at` 取签名过期时间与 JWT `exp` 声明中较早者
+    pub fn new(token: String, issued_at: u64, expires_at: u64, account: &str) -> Self {
+        let expires_at = jwt_expiry(&token).map_or(expires_at, |exp| exp.min(expires_at));
+        Self {
+            token: Redacted::new(token),
+            issued_at,
+            expires_at,
+            account: account.to_string(),
+        }
+    }
+
+    /// JWT 原文，用于 `Authorization: Bearer` 头
+    pub fn token(&self) -> &str {
+        self.token.expose()
+    }
+}
+
+impl std::ops::Deref for JwtToken {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.token()
+    }
+}
+
+/// JWT 本地缓存，按环境（StarkNet 链 ID）与账户地址区分文件
+///
+/// 默认位置为 `~/.cache/trade_lighter_paradex/jwt_<chain>_<account>.json`。
+#[derive(Debug, Clone)]
+pub struct TokenCache {
+    path: PathBuf,
+    margin: Duration,
+}
+
+impl TokenCache {
+    /// 在 `dir` 下为指定环境与账户创建缓存
+    pub fn new(dir: impl AsRef<Path>, config: &ParadexConfig, account_address: &str) -> Self {
+        let file_name = format!(
+            "jwt_{}_{}.json",
+            config.starknet_chain_id.to_ascii_lowercase(),
+            account_address.to_ascii_lowercase()
+        );
+        Self {
+            path: dir.as_ref().join(file_name),
+            margin: DEFAULT_TOKEN_MARGIN,
+        }
+    }
+
+    /// 使用默认缓存目录；无法确定用户目录时返回 `None`
+    pub fn in_default_dir(config: &ParadexConfig, account_address: &str) -> Option<Self> {
+        let base = std::env::var_os("XDG_CACHE_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))?;
+        Some(Self::new(
+            base.join("trade_lighter_paradex"),
+            config,
+            account_address,
+        ))
+    }
+
+    /// 设置复用 token 所需的最小剩余有效期
+    pub fn with_margin(mut self, margin: Duration) -> Self {
+        self.margin = margin;
+        self
+    }
+
+    /// 读取仍有足够剩余有效期的 token
+    pub fn load(&self, now: u64) -> Option<JwtToken> {
+        let contents = std::fs::read_to_string(&self.path).ok()?;
+        let cached: JwtToken = match serde_json::from_str(&contents) {
+            Ok(cached) => cached,
+            Err(e) => {
+                warn!("Ignoring corrupt JWT cache {}: {}", self.path.display(), e);
+                return None;
+            }
+        };
+        if cached.expires_at > now + self.margin.as_secs() {
+            debug!(
+                "Reusing cached JWT token, {}s of validity left",
+                cached.expires_at - now
+            );
+            Some(cached)
+        } else {
+            debug!("Cached JWT token expires too soon, re-authenticating");
+            None
+        }
+    }
+
+    /// 写入 token；失败仅记录日志，不影响认证流程
+    pub fn store(&self, token: &JwtToken) {
+        if let Err(e) = self.write(token) {
+            warn!("Failed to write JWT cache {}: {}", self.path.display(), e);
+        }
+    }
+
+    /// 删除缓存文件（`--force-reauth`）
+    pub fn invalidate(&self) {
+        match std::fs::remove_file(&self.path) {
+            Ok(()) => debug!("Removed JWT cache {}", self.path.display()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => warn!("Failed to remove JWT cache {}: {}", self.path.display(), e),
+        }
+    }
+
+    fn write(&self, token: &JwtToken) -> std::io::Result<()> {
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let contents = serde_json::to_string(token)?;
+
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        std::io::Write::write_all(&mut options.open(&self.path)?, contents.as_bytes())
+    }
+}
+
+/// 解析 JWT payload 中的 `exp` 声明（不校验签名）
+pub fn jwt_expiry(jwt_token: &str) -> Option<u64> {
+    #[derive(Deserialize)]
+    struct Claims {
+        exp: u64,
+    }
+
+    let payload = jwt_token.split('.').nth(1)?;
+    let bytes = URL_SAFE_NO_PAD.decode(payload.trim_end_matches('=')).ok()?;
+    serde_json::from_slice::<Claims>(&bytes)
+        .ok()
+        .map(|claims| claims.exp)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ACCOUNT: &str = "0x129f3dc1b8962d8bb23f7ddc9a0f1c0fcc9fba43a8bc88bc5c1de5b2b6dd2b8";
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("tlp_token_cache_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn token_is_reused_only_outside_margin() {
+        let dir = temp_dir("margin");
+        let cache = TokenCache::new(&dir, &ParadexConfig::testnet(), ACCOUNT)
+            .with_margin(Duration::from_secs(600));
+        cache.store(&JwtToken::new("token".into(), 0, 10_000, ACCOUNT));
+
+        assert_eq!(cache.load(9_000).as_deref(), Some("token"));
+        assert_eq!(cache.load(9_400), None);
+        assert_eq!(cache.load(11_000), None);
+
+        cache.invalidate();
+        assert_eq!(cache.load(0), None);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn cache_is_keyed_by_environment_and_account() {
+        let dir = temp_dir("keying");
+        let testnet = TokenCache::new(&dir, &ParadexConfig::testnet(), ACCOUNT);
+        let production = TokenCache::new(&dir, &ParadexConfig::production(), ACCOUNT);
+        let other_account = TokenCache::new(&dir, &ParadexConfig::testnet(), "0x1234");
+        testnet.store(&JwtToken::new("testnet-token".into(), 0, u64::MAX, ACCOUNT));
+
+        assert_eq!(testnet.load(0).as_deref(), Some("testnet-token"));
+        assert_eq!(production.load(0), None);
+        assert_eq!(other_account.load(0), None);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn token_round_trips_and_reads_legacy_cache() {
+        let payload = URL_SAFE_NO_PAD.encode(br#"{"exp":1700000300}"#);
+        let jwt = format!("eyJhbGciOiJIUzI1NiJ9.{}.signature", payload);
+        let token = JwtToken::new(jwt.clone(), 1_700_000_000, 1_700_086_400, ACCOUNT);
+        assert_eq!(token.expires_at, 1_700_000_300);
+        assert_eq!(&*token, jwt);
+        assert!(!format!("{:?}", token).contains("signature"));
+
+        let json = serde_json::to_string(&token).unwrap();
+        assert_eq!(serde_json::from_str::<JwtToken>(&json).unwrap(), token);
+
+        let legacy: JwtToken =
+            serde_json::from_str(r#"{"jwt_token":"old","expires_at":42}"#).unwrap();
+        assert_eq!((legacy.token(), legacy.expires_at), ("old", 42));
+    }
+
+    #[test]
+    fn jwt_expiry_reads_exp_claim() {
+        let payload = URL_SAFE_NO_PAD.encode(br#"{"sub":"0x1","exp":1700000300}"#);
+        let token = format!("eyJhbGciOiJIUzI1NiJ9.{}.signature", payload);
+        assert_eq!(jwt_expiry(&token), Some(1_700_000_300));
+        assert_eq!(jwt_expiry("not-a-jwt"), None);
+    }
+}
@@ -0,0 +1,382 @@
+//! Paradex 签名所用的 StarkNet TypedData 构建与哈希（与 Python SDK 一致）
+
+use log::{debug, trace};
+use serde_json::json;
+use starknet::core::types::TypedData;
+use starknet_crypto::Felt;
+
+use super::OnboardingError;
+
+/// 单个 felt 可容纳的最大字节数（Cairo short string）
+pub const MAX_SHORT_STRING_BYTES: usize = 31;
+
+/// 将字符串转换为 felt（0x 前缀的十六进制表示），超过 31 字节时报错
+pub fn string_to_felt_hex(s: &str) -> Result<String, OnboardingError> {
+    let mut chunks = string_to_felt_chunks(s);
+    if chunks.len() > 1 {
+        return Err(OnboardingError::StringTooLong(s.to_string()));
+    }
+    Ok(chunks.remove(0))
+}
+
+/// 将任意长度字符串按 31 字节切分为多个 felt（用于请求 body 等长字段的哈希）
+///
+/// 按 UTF-8 字节切分，空字符串返回单个 `0x0`。
+pub fn string_to_felt_chunks(s: &str) -> Vec<String> {
+    if s.is_empty() {
+        return vec!["0x0".to_string()];
+    }
+    s.as_bytes()
+        .chunks(MAX_SHORT_STRING_BYTES)
+        .map(|chunk| {
+            let mut result = String::from("0x");
+            for byte in chunk {
+                result.push_str(&format!("{:02x}", byte));
+            }
+            result
+        })
+        .collect()
+}
+
+/// 构建 Paradex onboarding TypedData (完全匹配 Python 实现)
+pub fn build_onboarding_typed_data(chain_id: &str) -> Result<TypedData, OnboardingError> {
+    let typed_data_json = json!({
+        "types": {
+            "StarkNetDomain": [
+                { "name": "name", "type": "felt" },
+                { "name": "version", "type": "felt" },
+                { "name": "chainId", "type": "felt" }
+            ],
+            "Constant": [
+                { "name": "action", "type": "felt" }
+            ]
+        },
+        "primaryType": "Constant",
+        "domain": {
+            "name": string_to_felt_hex("Paradex")?,
+            "chainId": string_to_felt_hex(chain_id)?,
+            "version": "1"
+        },
+        "message": {
+            "action": "Onboarding"
+        }
+    });
+
+    serde_json::from_value(typed_data_json)
+        .map_err(|e| OnboardingError::SigningFailed(e.to_string()))
+}
+
+/// 构建 Paradex auth TypedData (完全匹配 Python 实现)
+pub fn build_auth_typed_data(
+    chain_id: &str,
+    timestamp: u64,
+    expiry: u64,
+) -> Result<TypedData, OnboardingError> {
+    let typed_data_json = json!({
+        "types": {
+            "StarkNetDomain": [
+                { "name": "name", "type": "felt" },
+                { "name": "version", "type": "felt" },
+                { "name": "chainId", "type": "felt" }
+            ],
+            "Request": [
+                { "name": "method", "type": "felt" },
+                { "name": "path", "type": "felt" },
+                { "name": "body", "type": "felt" },
+                { "name": "timestamp", "type": "felt" },
+                { "name": "expiration", "type": "felt" }
+            ]
+        },
+        "primaryType": "Request",
+        "domain": {
+            "name": string_to_felt_hex("Paradex")?,
+            "chainId": string_to_felt_hex(chain_id)?,
+            "version": "1"
+        },
+        "message": {
+            "method": "POST",
+            "path": "/v1/auth",
+            "body": "",
+            "timestamp": timestamp,
+            "expiration": expiry
+        }
+    });
+
+    serde_json::from_value(typed_data_json)
+        .map_err(|e| OnboardingError::SigningFailed(e.to_string()))
+}
+
+/// onboarding 消息哈希
+pub fn onboarding_message_hash(chain_id: &str, account: Felt) -> Result<Felt, OnboardingError> {
+    message_hash(&build_onboarding_typed_data(chain_id)?, account)
+}
+
+/// auth 消息哈希
+pub fn auth_message_hash(
+    chain_id: &str,
+    account: Felt,
+    timestamp: u64,
+    expiry: u64,
+) -> Result<Felt, OnboardingError> {
+    message_hash(
+        &build_auth_typed_data(chain_id, timestamp, expiry)?,
+        account,
+    )
+}
+
+/// 计算 TypedData 针对账户的 message hash
+pub fn message_hash(typed_data: &TypedData, account: Felt) -> Result<Felt, OnboardingError> {
+    typed_data
+        .message_hash(account)
+        .map_err(|e| OnboardingError::SigningFailed(e.to_string()))
+}
+
+/// 构建 Paradex 提现 TypedData
+pub fn build_withdraw_typed_data(
+    chain_id: &str,
+    token: &str,
+    amount: &str,
+    recipient: &str,
+    timestamp: u64,
+) -> Result<TypedData, OnboardingError> {
+    let typed_data_json = json!({
+        "types": {
+            "StarkNetDomain": [
+                { "name": "name", "type": "felt" },
+                { "name": "version", "type": "felt" },
+                { "name": "chainId", "type": "felt" }
+            ],
+            "Withdraw": [
+                { "name": "token", "type": "felt" },
+                { "name": "amount", "type": "felt" },
+                { "name": "recipient", "type": "felt" },
+                { "name": "timestamp", "type": "felt" }
+            ]
+        },
+        "primaryType": "Withdraw",
+        "domain": {
+            "name": string_to_felt_hex("Paradex")?,
+            "chainId": string_to_felt_hex(chain_id)?,
+            "version": "1"
+        },
+        "message": {
+            "token": string_to_felt_hex(token)?,
+            "amount": amount,
+            "recipient": recipient,
+            "timestamp": timestamp
+        }
+    });
+
+    serde_json::from_value(typed_data_json)
+        .map_err(|e| OnboardingError::SigningFailed(e.to_string()))
+}
+
+/// 构建 Paradex 子账户划转 TypedData
+pub fn build_transfer_typed_data(
+    chain_id: &str,
+    token: &str,
+    amount: &str,
+    recipient: &str,
+    timestamp: u64,
+) -> Result<TypedData, OnboardingError> {
+    let typed_data_json = json!({
+        "types": {
+            "StarkNetDomain": [
+                { "name": "name", "type": "felt" },
+                { "name": "version", "type": "felt" },
+                { "name": "chainId", "type": "felt" }
+            ],
+            "Transfer": [
+                { "name": "recipient", "type": "felt" },
+                { "name": "token", "type": "felt" },
+                { "name": "amount", "type": "felt" },
+                { "name": "timestamp", "type": "felt" }
+            ]
+        },
+        "primaryType": "Transfer",
+        "domain": {
+            "name": string_to_felt_hex("Paradex")?,
+            "chainId": string_to_felt_hex(chain_id)?,
+            "version": "1"
+        },
+        "message": {
+            "recipient": recipient,
+            "token": string_to_felt_hex(token)?,
+            "amount": amount,
+            "timestamp": timestamp
+        }
+    });
+
+    serde_json::from_value(typed_data_json)
+        .map_err(|e| OnboardingError::SigningFailed(e.to_string()))
+}
+
+/// 输出签名相关的调试信息（TypedData、domain 哈希、message 哈希）
+pub(crate) fn log_typed_data_hashes(label: &str, typed_data: &TypedData, message_hash: Felt) {
+    let encoder = typed_data.encoder();
+    let domain = encoder.domain();
+    let message_struct_hash = encoder
+        .encode_value(typed_data.primary_type(), typed_data.message())
+        .map(|hash| format!("0x{:x}", hash))
+        .unwrap_or_else(|e| format!("<{}>", e));
+
+    trace!(
+        "{} typed data JSON: {}",
+        label,
+        serde_json::to_string(typed_data).unwrap_or_default()
+    );
+    debug!(
+        "{} domain fields name=0x{:x}, version=0x{:x}, chain_id=0x{:x}",
+        label, domain.name, domain.version, domain.chain_id
+    );
+    debug!(
+        "{} domain_hash=0x{:x}, message_struct_hash={}",
+        label,
+        domain.encoded_hash(),
+        message_struct_hash
+    );
+    debug!(
+        "{} typed data revision {:?}, message hash: 0x{:x}",
+        label,
+        typed_data.revision(),
+        message_hash
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use starknet::core::crypto::compute_hash_on_elements;
+    use starknet::core::utils::{cairo_short_string_to_felt, starknet_keccak};
+
+    const ACCOUNT: &str = "0x129f3dc1b8962d8bb23f7ddc9a0f1c0fcc9fba43a8bc88bc5c1de5b2b6dd2b8";
+    const CHAIN_ID: &str = "SN_SEPOLIA";
+
+    fn account() -> Felt {
+        Felt::from_hex(ACCOUNT).unwrap()
+    }
+
+    fn short(s: &str) -> Felt {
+        cairo_short_string_to_felt(s).unwrap()
+    }
+
+    /// 按 SNIP-12 revision 0 独立计算 message hash，用于交叉校验
+    fn reference_hash(struct_hash: Felt) -> Felt {
+        let domain_hash = compute_hash_on_elements(&[
+            starknet_keccak(b"StarkNetDomain(name:felt,version:felt,chainId:felt)"),
+            short("Paradex"),
+            Felt::ONE,
+            short(CHAIN_ID),
+        ]);
+        compute_hash_on_elements(&[
+            short("StarkNet Message"),
+            domain_hash,
+            account(),
+            struct_hash,
+        ])
+    }
+
+    fn hex(felt: Felt) -> String {
+        format!("0x{:x}", felt)
+    }
+
+    #[test]
+    fn string_to_felt_hex_boundaries() {
+        assert_eq!(string_to_felt_hex("").unwrap(), "0x0");
+        assert_eq!(string_to_felt_hex("USDC").unwrap(), "0x55534443");
+
+        let max = "a".repeat(MAX_SHORT_STRING_BYTES);
+        assert_eq!(
+            string_to_felt_hex(&max).unwrap(),
+            format!("0x{}", "61".repeat(31))
+        );
+        assert!(matches!(
+            string_to_felt_hex(&"a".repeat(32)),
+            Err(OnboardingError::StringTooLong(_))
+        ));
+
+        // 按 UTF-8 字节计数："é" 占 2 字节
+        assert_eq!(string_to_felt_hex("é").unwrap(), "0xc3a9");
+        assert!(string_to_felt_hex(&"é".repeat(15)).is_ok());
+        assert!(string_to_felt_hex(&"é".repeat(16)).is_err());
+    }
+
+    #[test]
+    fn string_to_felt_chunks_splits_every_31_bytes() {
+        assert_eq!(string_to_felt_chunks(""), vec!["0x0"]);
+        assert_eq!(string_to_felt_chunks(&"a".repeat(31)).len(), 1);
+
+        let chunks = string_to_felt_chunks(&"a".repeat(32));
+        assert_eq!(
+            chunks,
+            vec![format!("0x{}", "61".repeat(31)), "0x61".to_string()]
+        );
+        assert_eq!(string_to_felt_chunks(&"b".repeat(62)).len(), 2);
+    }
+
+    #[test]
+    fn onboarding_message_hash_matches_golden() {
+        let hash = onboarding_message_hash(CHAIN_ID, account()).unwrap();
+        let struct_hash = compute_hash_on_elements(&[
+            starknet_keccak(b"Constant(action:felt)"),
+            short("Onboarding"),
+        ]);
+        assert_eq!(hash, reference_hash(struct_hash));
+        assert_eq!(
+            hex(hash),
+            "0x34afb42a3bf2d8dfd4ba335295aa4821d1d02e3deb8a768a6dcc759a48c98c3"
+        );
+    }
+
+    #[test]
+    fn auth_message_hash_matches_golden() {
+        let hash = auth_message_hash(CHAIN_ID, account(), 1_700_000_000, 1_700_086_400).unwrap();
+        let struct_hash = compute_hash_on_elements(&[
+            starknet_keccak(
+                b"Request(method:felt,path:felt,body:felt,timestamp:felt,expiration:felt)",
+            ),
+            short("POST"),
+            short("/v1/auth"),
+            Felt::ZERO,
+            Felt::from(1_700_000_000u64),
+            Felt::from(1_700_086_400u64),
+        ]);
+        assert_eq!(hash, reference_hash(struct_hash));
+        assert_eq!(
+            hex(hash),
+            "0x5f345eeecaa0ed6948d7b1943840774e21474d05db958f937933b42cc0beb9f"
+        );
+    }
+
+    #[test]
+    fn withdraw_message_hash_matches_golden() {
+        let typed_data = build_withdraw_typed_data(
+            CHAIN_ID,
+            "USDC",
+            "1250000000",
+            "0x36Fb7eFD2b0F4c4C5f5bd6e5A0B1c0a5bB8e2e11",
+            1_700_000_000,
+        )
+        .unwrap();
+        assert_eq!(
+            hex(typed_data.message_hash(account()).unwrap()),
+            "0x6b8838f1b377881b665d0b612ad841a042fc41abc840af1ed434085b4e49a5d"
+        );
+    }
+
+    #[test]
+    fn transfer_message_hash_matches_golden() {
+        let typed_data = build_transfer_typed_data(
+            CHAIN_ID,
+            "USDC",
+            "1250000000",
+            "0x445afd19fe1b2c3d4e5f60718293a4b5c6d7e8f9",
+            1_700_000_000,
+        )
+        .unwrap();
+        assert_eq!(
+            hex(typed_data.message_hash(account()).unwrap()),
+            "0x6a89f3fc8adf269923652e749b49b6c6c653ec860a71d81d2b001b947d018f2"
+        );
+    }
+}
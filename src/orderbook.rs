@@ -0,0 +1,209 @@
+//! REST 订单簿快照（`GET /orderbook/{market}`）
+
+use reqwest::Client as HttpClient;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::ops::RangeInclusive;
+
+use crate::http::{get_public_json, HttpError};
+
+/// 接口接受的档位数范围
+pub const DEPTH_RANGE: RangeInclusive<u32> = 1..=100;
+
+/// 将档位数限制在接口接受的范围内；返回限制后的值以及是否发生了调整
+pub fn clamp_depth(depth: u32) -> (u32, bool) {
+    let clamped = depth.clamp(*DEPTH_RANGE.start(), *DEPTH_RANGE.end());
+    (clamped, clamped != depth)
+}
+
+/// 一档报价：接口返回 `["价格", "数量"]`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Level(pub Decimal, pub Decimal);
+
+impl Level {
+    /// 价格
+    pub fn price(&self) -> Decimal {
+        self.0
+    }
+
+    /// 数量
+    pub fn size(&self) -> Decimal {
+        self.1
+    }
+}
+
+/// 订单簿快照；买卖盘均按离盘口由近到远排列
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrderBookSnapshot {
+    pub market: String,
+    #[serde(default)]
+    pub seq_no: Option<u64>,
+    #[serde(default)]
+    pub last_updated_at: Option<u64>,
+    pub bids: Vec<Level>,
+    pub asks: Vec<Level>,
+}
+
+impl OrderBookSnapshot {
+    /// 最优买卖价的中间价；任一侧为空时为 `None`
+    pub fn mid(&self) -> Option<Decimal> {
+        let (bid, ask) = (self.bids.first()?, self.asks.first()?);
+        Some((bid.price() + ask.price()) / Decimal::TWO)
+    }
+
+    /// 买卖价差（相对中间价的基点）
+    pub fn spread_bps(&self) -> Option<Decimal> {
+        let (bid, ask) = (self.bids.first()?, self.asks.first()?);
+        let mid = self.mid().filter(|mid| !mid.is_zero())?;
+        Some(((ask.price() - bid.price()) / mid * Decimal::from(10_000)).round_dp(2))
+    }
+}
+
+/// `--json` 输出：快照加中间价与价差
+#[derive(Debug, Serialize)]
+pub struct OrderBookSummary<'a> {
+    #[serde(flatten)]
+    pub snapshot: &'a OrderBookSnapshot,
+    pub mid: Option<Decimal>,
+    pub spread_bps: Option<Decimal>,
+}
+
+impl<'a> From<&'a OrderBookSnapshot> for OrderBookSummary<'a> {
+    fn from(snapshot: &'a OrderBookSnapshot) -> Self {
+        Self {
+            snapshot,
+            mid: snapshot.mid(),
+            spread_bps: snapshot.spread_bps(),
+        }
+    }
+}
+
+/// 查询 `market` 的订单簿快照（无需认证）
+pub async fn fetch_orderbook(
+    http_client: &HttpClient,
+    base_url: &str,
+    market: &str,
+    depth: u32,
+) -> Result<OrderBookSnapshot, HttpError> {
+    get_public_json(
+        http_client,
+        base_url,
+        &format!("/orderbook/{}?depth={}", market, depth),
+    )
+    .await
+}
+
+/// 渲染为文本：卖盘在上（远到近）、买盘在下，附累计数量
+pub fn format_book(snapshot: &OrderBookSnapshot) -> String {
+    let optional = |value: Option<Decimal>| value.map_or("-".to_string(), |v| v.to_string());
+    let cumulative = |levels: &[Level]| -> Vec<[String; 3]> {
+        let mut total = Decimal::ZERO;
+        levels
+            .iter()
+            .map(|level| {
+                total += level.size();
+                [
+                    level.price().to_string(),
+                    level.size().to_string(),
+                    total.to_string(),
+                ]
+            })
+            .collect()
+    };
+    let mut asks = cumulative(&snapshot.asks);
+    asks.reverse();
+    let bids = cumulative(&snapshot.bids);
+
+    let header = ["PRICE", "SIZE", "CUMULATIVE"];
+    let mut widths = header.map(str::len);
+    for row in asks.iter().chain(&bids) {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.len());
+        }
+    }
+    let line = |side: &str, cells: [&str; 3]| {
+        format!(
+            "{:<3}  {:>w0$}  {:>w1$}  {:>w2$}",
+            side,
+            cells[0],
+            cells[1],
+            cells[2],
+            w0 = widths[0],
+            w1 = widths[1],
+            w2 = widths[2]
+        )
+    };
+
+    let mut out = vec![
+        format!(
+            "{}  mid {}  spread {} bps",
+            snapshot.market,
+            optional(snapshot.mid()),
+            optional(snapshot.spread_bps())
+        ),
+        line("", header),
+    ];
+    out.extend(
+        asks.iter()
+            .map(|row| line("ASK", [&row[0], &row[1], &row[2]])),
+    );
+    out.push("-".repeat(widths.iter().sum::<usize>() + 9));
+    out.extend(
+        bids.iter()
+            .map(|row| line("BID", [&row[0], &row[1], &row[2]])),
+    );
+    out.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot() -> OrderBookSnapshot {
+        serde_json::from_str(
+            r#"{
+                "market": "BTC-USD-PERP",
+                "seq_no": 42,
+                "last_updated_at": 1700000000000,
+                "bids": [["95000", "0.3"], ["94990.5", "1.25"]],
+                "asks": [["95010", "0.5"], ["95020", "0.7"]]
+            }"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn mid_and_spread_from_top_of_book() {
+        let book = snapshot();
+        assert_eq!(book.mid(), Some(Decimal::from(95005)));
+        assert_eq!(book.spread_bps(), Some(Decimal::new(105, 2)));
+
+        let empty = OrderBookSnapshot {
+            asks: vec![],
+            ..book
+        };
+        assert_eq!(empty.mid(), None);
+        assert_eq!(empty.spread_bps(), None);
+    }
+
+    #[test]
+    fn depth_is_clamped_to_api_range() {
+        assert_eq!(clamp_depth(20), (20, false));
+        assert_eq!(clamp_depth(0), (1, true));
+        assert_eq!(clamp_depth(500), (100, true));
+    }
+
+    #[test]
+    fn book_renders_with_cumulative_size() {
+        assert_eq!(
+            format_book(&snapshot()),
+            "BTC-USD-PERP  mid 95005  spread 1.05 bps\n\
+             \x20      PRICE  SIZE  CUMULATIVE\n\
+             ASK    95020   0.7         1.2\n\
+             ASK    95010   0.5         0.5\n\
+             ------------------------------\n\
+             BID    95000   0.3         0.3\n\
+             BID  94990.5  1.25        1.55"
+        );
+    }
+}
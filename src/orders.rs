@@ -0,0 +1,1011 @@
+use paradex::structs::{
+    OrderFlags, OrderInstruction, OrderRequest, OrderType, OrderUpdate, STPType, Side,
+};
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use std::fmt;
+use thiserror::Error;
+
+use crate::client_id::{ClientIdError, ClientIdGenerator};
+use crate::onboarding::ParadexConfig;
+
+/// Paradex 接受的 recv_window 范围（毫秒）
+const RECV_WINDOW_RANGE: std::ops::RangeInclusive<u64> = 10..=60_000;
+
+/// Paradex 拒绝会立即成交的 POST_ONLY 单时返回的错误码（`ParadexError` 的 `error` 字段）
+pub const POST_ONLY_WOULD_CROSS: &str = "POST_ONLY_WOULD_CROSS";
+
+/// 构建订单失败的原因
+#[derive(Debug, Error)]
+pub enum OrderError {
+    #[error(transparent)]
+    ClientId(#[from] ClientIdError),
+    #[error(
+        "recv_window {0}ms is outside the accepted range {min}..={max}ms",
+        min = RECV_WINDOW_RANGE.start(),
+        max = RECV_WINDOW_RANGE.end()
+    )]
+    InvalidRecvWindow(u64),
+    #[error("Order size must be positive, got {0}")]
+    InvalidSize(Decimal),
+    #[error("Limit orders require a price")]
+    MissingPrice,
+    #[error("Market orders must not specify a price")]
+    UnexpectedPrice,
+    #[error("POST_ONLY is only valid for limit orders")]
+    PostOnlyMarket,
+    #[error("POST_ONLY order on {market} would cross the book after {attempts} attempt(s)")]
+    PostOnlyWouldCross { market: String, attempts: u32 },
+    #[error("{0:?} orders require a trigger price")]
+    MissingTrigger(OrderType),
+    #[error("{0:?} orders must not specify a trigger price")]
+    UnexpectedTrigger(OrderType),
+    #[error(
+        "{side:?} {order_type:?} trigger {trigger} must be {} the mark price {mark}",
+        if *.above { "above" } else { "below" }
+    )]
+    TriggerOnWrongSide {
+        order_type: OrderType,
+        side: Side,
+        trigger: Decimal,
+        mark: Decimal,
+        above: bool,
+    },
+    #[error("Bracket take-profit and stop-loss offsets must be positive, got {0}")]
+    InvalidBracketOffset(Decimal),
+    #[error("No recent mark price for {0} to check the trigger against")]
+    NoMarkPrice(String),
+    #[error("Reduce-only order on {0} has no position to reduce")]
+    NoPositionToReduce(String),
+    #[error("Reduce-only {side:?} order on {market} would increase the {position} position")]
+    ReduceOnlyWrongSide {
+        market: String,
+        side: Side,
+        position: Decimal,
+    },
+    #[error("Reduce-only order of {size} on {market} exceeds the open position of {position}")]
+    ReduceOnlyExceedsPosition {
+        market: String,
+        size: Decimal,
+        position: Decimal,
+    },
+}
+
+/// 校验 recv_window 是否在交易所接受的范围内
+pub fn validate_recv_window(recv_window: u64) -> Result<(), OrderError> {
+    if RECV_WINDOW_RANGE.contains(&recv_window) {
+        Ok(())
+    } else {
+        Err(OrderError::InvalidRecvWindow(recv_window))
+    }
+}
+
+/// 在 `reference` 基础上下调 `offset_bps` 个基点，并向下对齐到 `tick`（为 0 时不对齐）
+pub fn offset_price(reference: Decimal, offset_bps: Decimal, tick: Decimal) -> Decimal {
+    let price = reference * (Decimal::ONE - offset_bps / Decimal::from(10_000));
+    if tick.is_zero() {
+        price
+    } else {
+        (price / tick).floor() * tick
+    }
+}
+
+/// 在 `reference` 基础上按方向向远离成交的一侧偏移 `offset_bps` 个基点：
+/// 买单向下取整、卖单向上取整到 `tick`
+pub fn passive_price(
+    side: Side,
+    reference: Decimal,
+    offset_bps: Decimal,
+    tick: Decimal,
+) -> Decimal {
+    match side {
+        Side::BUY => offset_price(reference, offset_bps, tick),
+        Side::SELL => {
+            let price = reference * (Decimal::ONE + offset_bps / Decimal::from(10_000));
+            if tick.is_zero() {
+                price
+            } else {
+                (price / tick).ceil() * tick
+            }
+        }
+    }
+}
+
+/// 在 `reference` 基础上按方向向易于成交的一侧偏移 `slippage_bps` 个基点：
+/// 买单向上取整、卖单向下取整到 `tick`
+pub fn aggressive_price(
+    side: Side,
+    reference: Decimal,
+    slippage_bps: Decimal,
+    tick: Decimal,
+) -> Decimal {
+    let opposite = match side {
+        Side::BUY => Side::SELL,
+        Side::SELL => Side::BUY,
+    };
+    passive_price(opposite, reference, slippage_bps, tick)
+}
+
+/// 触发单的触发条件：止损单（`STOP_*`、`STOP_LOSS_*`）在价格向不利方向突破时触发，
+/// 买入止损在上穿触发价时、卖出止损在下穿时；止盈单（`TAKE_PROFIT_*`）方向相反。
+/// 非触发单返回 `None`
+pub fn stop_condition(order_type: OrderType, side: Side) -> Option<OrderFlags> {
+    let stop = match order_type {
+        OrderType::STOP_MARKET
+        | OrderType::STOP_LIMIT
+        | OrderType::STOP_LOSS_MARKET
+        | OrderType::STOP_LOSS_LIMIT => true,
+        OrderType::TAKE_PROFIT_MARKET | OrderType::TAKE_PROFIT_LIMIT => false,
+        OrderType::MARKET | OrderType::LIMIT => return None,
+    };
+    Some(if stop == (side == Side::BUY) {
+        OrderFlags::STOP_CONDITION_ABOVE_TRIGGER
+    } else {
+        OrderFlags::STOP_CONDITION_BELOW_TRIGGER
+    })
+}
+
+/// 需要限价的订单类型
+fn takes_price(order_type: OrderType) -> bool {
+    matches!(
+        order_type,
+        OrderType::LIMIT
+            | OrderType::STOP_LIMIT
+            | OrderType::TAKE_PROFIT_LIMIT
+            | OrderType::STOP_LOSS_LIMIT
+    )
+}
+
+/// 止盈与止损单只用于平仓，交易所要求带 `REDUCE_ONLY`
+fn closes_position(order_type: OrderType) -> bool {
+    matches!(
+        order_type,
+        OrderType::TAKE_PROFIT_MARKET
+            | OrderType::TAKE_PROFIT_LIMIT
+            | OrderType::STOP_LOSS_MARKET
+            | OrderType::STOP_LOSS_LIMIT
+    )
+}
+
+/// 检查触发价位于当前标记价格的正确一侧，否则订单会在提交后立即触发
+pub fn validate_trigger(
+    order_type: OrderType,
+    side: Side,
+    trigger: Decimal,
+    mark: Decimal,
+) -> Result<(), OrderError> {
+    let Some(condition) = stop_condition(order_type, side) else {
+        return Err(OrderError::UnexpectedTrigger(order_type));
+    };
+    let above = condition == OrderFlags::STOP_CONDITION_ABOVE_TRIGGER;
+    if (above && trigger > mark) || (!above && trigger < mark) {
+        Ok(())
+    } else {
+        Err(OrderError::TriggerOnWrongSide {
+            order_type,
+            side,
+            trigger,
+            mark,
+            above,
+        })
+    }
+}
+
+/// 检查只减仓订单不会增加或反转持仓，`position` 为当前持仓数量（空头为负）；
+/// 数量超过持仓时 `clamp` 为 `true` 则缩减到持仓数量，否则拒绝。返回可下单的数量
+pub fn check_reduce_only(
+    market: &str,
+    side: Side,
+    size: Decimal,
+    position: Decimal,
+    clamp: bool,
+) -> Result<Decimal, OrderError> {
+    if position.is_zero() {
+        return Err(OrderError::NoPositionToReduce(market.to_string()));
+    }
+    // 多头只能卖出减仓，空头只能买入
+    if (side == Side::BUY) == position.is_sign_positive() {
+        return Err(OrderError::ReduceOnlyWrongSide {
+            market: market.to_string(),
+            side,
+            position,
+        });
+    }
+    let open = position.abs();
+    if size <= open {
+        Ok(size)
+    } else if clamp {
+        Ok(open)
+    } else {
+        Err(OrderError::ReduceOnlyExceedsPosition {
+            market: market.to_string(),
+            size,
+            position: open,
+        })
+    }
+}
+
+/// 相对中间价 `mid` 向成交方向偏移 `max_slippage_bps` 个基点的吃单限价：
+/// 买单向下、卖单向上取整到 `tick`（为 0 时不对齐），保证不超出滑点上限
+pub fn capped_price(side: Side, mid: Decimal, max_slippage_bps: Decimal, tick: Decimal) -> Decimal {
+    let offset = max_slippage_bps / Decimal::from(10_000);
+    let price = match side {
+        Side::BUY => mid * (Decimal::ONE + offset),
+        Side::SELL => mid * (Decimal::ONE - offset),
+    };
+    if tick.is_zero() {
+        return price;
+    }
+    match side {
+        Side::BUY => (price / tick).floor() * tick,
+        Side::SELL => (price / tick).ceil() * tick,
+    }
+}
+
+/// 解析命令行中的正数（数量、价格），拒绝 0 与负数
+pub fn parse_positive_decimal(value: &str) -> Result<Decimal, String> {
+    let decimal = value
+        .parse::<Decimal>()
+        .map_err(|e| format!("invalid decimal {:?}: {}", value, e))?;
+    if decimal > Decimal::ZERO {
+        Ok(decimal)
+    } else {
+        Err(format!("must be positive, got {}", decimal))
+    }
+}
+
+/// 命令行可选的订单方向
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum OrderSide {
+    Buy,
+    Sell,
+}
+
+impl OrderSide {
+    /// 对应的 SDK 订单方向
+    pub fn to_side(self) -> Side {
+        match self {
+            OrderSide::Buy => Side::BUY,
+            OrderSide::Sell => Side::SELL,
+        }
+    }
+}
+
+/// 命令行可选的订单类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum OrderKind {
+    Limit,
+    Market,
+}
+
+impl OrderKind {
+    /// 对应的 SDK 订单类型
+    pub fn to_order_type(self) -> OrderType {
+        match self {
+            OrderKind::Limit => OrderType::LIMIT,
+            OrderKind::Market => OrderType::MARKET,
+        }
+    }
+}
+
+/// 带滑点上限的吃单方式（配置文件中写作 `market` / `limit`）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum AggressiveMode {
+    /// 市价单，成交价只在下单前按订单簿估算检查
+    Market,
+    /// 以滑点上限对应价格的 IOC 限价单吃单，超出上限的部分不成交
+    #[default]
+    Limit,
+}
+
+/// `stop` 子命令的触发单类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum TriggerKind {
+    /// 止损单
+    Stop,
+    /// 只减仓的止盈单
+    TakeProfit,
+    /// 只减仓的止损单
+    StopLoss,
+}
+
+/// 命令行可选的订单执行方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum InstructionArg {
+    Gtc,
+    #[cfg_attr(feature = "cli", value(name = "post_only"))]
+    PostOnly,
+    Ioc,
+}
+
+impl InstructionArg {
+    /// 对应的 SDK 订单指令
+    pub fn to_instruction(self) -> OrderInstruction {
+        match self {
+            InstructionArg::Gtc => OrderInstruction::GTC,
+            InstructionArg::PostOnly => OrderInstruction::POST_ONLY,
+            InstructionArg::Ioc => OrderInstruction::IOC,
+        }
+    }
+}
+
+/// 下单参数；限价单的 `price` 可在下单前再填入（如按最优价计算）
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrderSpec {
+    pub side: Side,
+    pub order_type: OrderType,
+    pub size: Decimal,
+    pub price: Option<Decimal>,
+    pub instruction: OrderInstruction,
+    pub client_id: Option<String>,
+    /// 只减仓（`REDUCE_ONLY`），用于平仓
+    pub reduce_only: bool,
+    /// 止损 / 止盈单的触发价
+    pub trigger_price: Option<Decimal>,
+}
+
+impl OrderSpec {
+    /// 检查不依赖行情即可判断的参数组合
+    pub fn validate(&self) -> Result<(), OrderError> {
+        if self.size <= Decimal::ZERO {
+            return Err(OrderError::InvalidSize(self.size));
+        }
+        let triggered = stop_condition(self.order_type, self.side).is_some();
+        match self.trigger_price {
+            None if triggered => return Err(OrderError::MissingTrigger(self.order_type)),
+            Some(_) if !triggered => return Err(OrderError::UnexpectedTrigger(self.order_type)),
+            _ => {}
+        }
+        if !takes_price(self.order_type) {
+            if self.price.is_some() {
+                return Err(OrderError::UnexpectedPrice);
+            }
+            if self.instruction == OrderInstruction::POST_ONLY {
+                return Err(OrderError::PostOnlyMarket);
+            }
+        }
+        Ok(())
+    }
+
+    /// 订单是否带 `REDUCE_ONLY`：显式指定，或止盈 / 止损单
+    pub fn is_reduce_only(&self) -> bool {
+        self.reduce_only || closes_position(self.order_type)
+    }
+}
+
+/// 要撤销的订单：交易所订单 id 或下单时的 client_id
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OrderTarget {
+    Id(String),
+    ClientId(String),
+}
+
+impl OrderTarget {
+    /// `order` 是否为此目标指向的订单
+    pub fn matches(&self, order: &OrderUpdate) -> bool {
+        match self {
+            OrderTarget::Id(id) => order.id == *id,
+            OrderTarget::ClientId(client_id) => order.client_id == *client_id,
+        }
+    }
+}
+
+impl fmt::Display for OrderTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OrderTarget::Id(id) => write!(f, "order id {}", id),
+            OrderTarget::ClientId(client_id) => write!(f, "client id {}", client_id),
+        }
+    }
+}
+
+/// 在挂单中查找 `target`；指定 `market` 时只查找该市场的挂单
+pub fn find_open_order<'a>(
+    orders: &'a [OrderUpdate],
+    target: &OrderTarget,
+    market: Option<&str>,
+) -> Option<&'a OrderUpdate> {
+    orders
+        .iter()
+        .filter(|order| market.is_none_or(|market| order.market == market))
+        .find(|order| target.matches(order))
+}
+
+/// 订单的单行描述：id、client_id、市场、方向、剩余 / 总数量、价格与状态
+pub fn describe_order(order: &OrderUpdate) -> String {
+    format!(
+        "{} (client id {}) {} {:?} {}/{} @ {} {:?}",
+        order.id,
+        if order.client_id.is_empty() {
+            "-"
+        } else {
+            &order.client_id
+        },
+        order.market,
+        order.side,
+        order.remaining_size,
+        order.size,
+        order
+            .price
+            .map_or("market".to_string(), |price| price.to_string()),
+        order.status
+    )
+}
+
+/// 命令行与配置文件可选的自成交保护模式（配置文件中写作 `expire_maker` 等）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum StpMode {
+    None,
+    #[cfg_attr(feature = "cli", value(alias = "expire_maker"))]
+    ExpireMaker,
+    #[cfg_attr(feature = "cli", value(alias = "expire_taker"))]
+    ExpireTaker,
+    #[cfg_attr(feature = "cli", value(alias = "expire_both"))]
+    ExpireBoth,
+}
+
+impl StpMode {
+    /// 对应的 SDK 自成交保护类型；`None` 表示不设置
+    pub fn to_stp(self) -> Option<STPType> {
+        match self {
+            StpMode::None => None,
+            StpMode::ExpireMaker => Some(STPType::EXPIRE_MAKER),
+            StpMode::ExpireTaker => Some(STPType::EXPIRE_TAKER),
+            StpMode::ExpireBoth => Some(STPType::EXPIRE_BOTH),
+        }
+    }
+}
+
+/// 构建订单请求，保证每个订单都带有会话内唯一的 client_id，并应用配置中的默认参数
+pub struct OrderFactory {
+    client_ids: ClientIdGenerator,
+    recv_window: Option<u64>,
+    stp: Option<STPType>,
+}
+
+impl OrderFactory {
+    /// 使用 `config` 中的 recv_window 与自成交保护设置
+    pub fn new(client_ids: ClientIdGenerator, config: &ParadexConfig) -> Self {
+        Self {
+            client_ids,
+            recv_window: config.recv_window,
+            stp: config.stp.clone(),
+        }
+    }
+
+    /// 构建限价单；`client_id` 为 `None` 时自动生成
+    pub fn limit(
+        &self,
+        market: &str,
+        side: Side,
+        price: Decimal,
+        size: Decimal,
+        instruction: OrderInstruction,
+        client_id: Option<String>,
+    ) -> Result<OrderRequest, OrderError> {
+        self.order(
+            market,
+            &OrderSpec {
+                side,
+                order_type: OrderType::LIMIT,
+                size,
+                price: Some(price),
+                instruction,
+                client_id,
+                reduce_only: false,
+                trigger_price: None,
+            },
+        )
+    }
+
+    /// 按 `spec` 构建订单；`client_id` 为 `None` 时自动生成
+    pub fn order(&self, market: &str, spec: &OrderSpec) -> Result<OrderRequest, OrderError> {
+        spec.validate()?;
+        if takes_price(spec.order_type) && spec.price.is_none() {
+            return Err(OrderError::MissingPrice);
+        }
+        if let Some(recv_window) = self.recv_window {
+            validate_recv_window(recv_window)?;
+        }
+
+        let client_id = spec
+            .client_id
+            .clone()
+            .unwrap_or_else(|| self.client_ids.next_id());
+        self.client_ids.reserve(&client_id)?;
+
+        let mut flags = Vec::new();
+        if spec.is_reduce_only() {
+            flags.push(OrderFlags::REDUCE_ONLY);
+        }
+        flags.extend(stop_condition(spec.order_type, spec.side));
+        Ok(OrderRequest {
+            instruction: spec.instruction.clone(),
+            market: market.to_string(),
+            price: spec.price,
+            side: spec.side,
+            size: spec.size,
+            order_type: spec.order_type,
+            client_id: Some(client_id),
+            flags,
+            recv_window: self.recv_window,
+            stp: self.stp.clone(),
+            trigger_price: spec.trigger_price,
+        })
+    }
+
+    /// 本会话创建且尚未释放的订单 client_id
+    pub fn outstanding(&self) -> Vec<String> {
+        self.client_ids.in_flight()
+    }
+
+    /// 订单结束后释放其 client_id
+    pub fn release(&self, client_id: &str) {
+        self.client_ids.release(client_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn factory(recv_window: Option<u64>) -> OrderFactory {
+        let mut config = ParadexConfig::testnet();
+        config.recv_window = recv_window;
+        OrderFactory::new(ClientIdGenerator::new("test"), &config)
+    }
+
+    fn factory_with_stp(stp: StpMode) -> OrderFactory {
+        let mut config = ParadexConfig::testnet();
+        config.stp = stp.to_stp();
+        OrderFactory::new(ClientIdGenerator::new("test"), &config)
+    }
+
+    fn order(factory: &OrderFactory) -> Result<OrderRequest, OrderError> {
+        factory.limit(
+            "BTC-USD-PERP",
+            Side::BUY,
+            Decimal::new(95000, 0),
+            Decimal::new(5, 3),
+            OrderInstruction::POST_ONLY,
+            None,
+        )
+    }
+
+    #[test]
+    fn recv_window_is_serialized_when_set() {
+        let request = order(&factory(Some(5000))).unwrap();
+        let json = serde_json::to_value(&request).unwrap();
+        assert_eq!(json["recv_window"], 5000);
+    }
+
+    #[test]
+    fn recv_window_is_omitted_when_unset() {
+        let request = order(&factory(None)).unwrap();
+        let json = serde_json::to_value(&request).unwrap();
+        assert!(json.get("recv_window").is_none());
+    }
+
+    #[test]
+    fn recv_window_out_of_range_is_rejected() {
+        assert!(matches!(
+            order(&factory(Some(5))),
+            Err(OrderError::InvalidRecvWindow(5))
+        ));
+        assert!(matches!(
+            order(&factory(Some(120_000))),
+            Err(OrderError::InvalidRecvWindow(120_000))
+        ));
+    }
+
+    #[test]
+    fn stp_defaults_to_expire_maker() {
+        let request = order(&factory(Some(5000))).unwrap();
+        let json = serde_json::to_value(&request).unwrap();
+        assert_eq!(json["stp"], "EXPIRE_MAKER");
+    }
+
+    #[test]
+    fn stp_mode_is_serialized() {
+        let request = order(&factory_with_stp(StpMode::ExpireBoth)).unwrap();
+        let json = serde_json::to_value(&request).unwrap();
+        assert_eq!(json["stp"], "EXPIRE_BOTH");
+
+        let request = order(&factory_with_stp(StpMode::None)).unwrap();
+        let json = serde_json::to_value(&request).unwrap();
+        assert!(json.get("stp").is_none());
+    }
+
+    #[test]
+    fn offset_price_floors_to_tick() {
+        let bid = Decimal::new(951234, 1);
+        assert_eq!(
+            offset_price(bid, Decimal::from(500), Decimal::ONE),
+            Decimal::from(90367)
+        );
+        assert_eq!(
+            offset_price(bid, Decimal::ZERO, Decimal::new(5, 1)),
+            Decimal::from(95123)
+        );
+        assert_eq!(offset_price(bid, Decimal::ZERO, Decimal::ZERO), bid);
+    }
+
+    #[test]
+    fn outstanding_tracks_unreleased_orders() {
+        let factory = factory(None);
+        let client_id = order(&factory).unwrap().client_id.unwrap();
+        assert_eq!(factory.outstanding(), std::slice::from_ref(&client_id));
+        factory.release(&client_id);
+        assert!(factory.outstanding().is_empty());
+    }
+
+    #[test]
+    fn positive_decimal_parser_rejects_zero_and_negative() {
+        assert_eq!(parse_positive_decimal("0.005"), Ok(Decimal::new(5, 3)));
+        assert_eq!(parse_positive_decimal("95000"), Ok(Decimal::from(95000)));
+        assert!(parse_positive_decimal("0").is_err());
+        assert!(parse_positive_decimal("-1").is_err());
+        assert!(parse_positive_decimal("abc").is_err());
+    }
+
+    #[cfg(feature = "cli")]
+    #[test]
+    fn order_enums_parse_from_cli_names() {
+        use clap::ValueEnum;
+
+        let side = OrderSide::from_str("sell", false).unwrap();
+        assert_eq!(side.to_side(), Side::SELL);
+        let kind = OrderKind::from_str("market", false).unwrap();
+        assert_eq!(kind.to_order_type(), OrderType::MARKET);
+        let instruction = InstructionArg::from_str("post_only", false).unwrap();
+        assert_eq!(instruction.to_instruction(), OrderInstruction::POST_ONLY);
+        assert!(InstructionArg::from_str("fok", false).is_err());
+    }
+
+    #[test]
+    fn order_spec_enforces_price_rules() {
+        let factory = factory(None);
+        let limit = OrderSpec {
+            side: Side::SELL,
+            order_type: OrderType::LIMIT,
+            size: Decimal::new(5, 3),
+            price: None,
+            instruction: OrderInstruction::GTC,
+            client_id: Some("manual-1".to_string()),
+            reduce_only: false,
+            trigger_price: None,
+        };
+        assert!(limit.validate().is_ok());
+        assert!(matches!(
+            factory.order("BTC-USD-PERP", &limit),
+            Err(OrderError::MissingPrice)
+        ));
+
+        let market = OrderSpec {
+            order_type: OrderType::MARKET,
+            price: Some(Decimal::from(95000)),
+            instruction: OrderInstruction::IOC,
+            ..limit.clone()
+        };
+        assert!(matches!(
+            market.validate(),
+            Err(OrderError::UnexpectedPrice)
+        ));
+
+        let market = OrderSpec {
+            price: None,
+            ..market
+        };
+        let request = factory.order("BTC-USD-PERP", &market).unwrap();
+        assert_eq!(request.client_id.as_deref(), Some("manual-1"));
+        assert!(request.price.is_none());
+        assert!(request.flags.is_empty());
+
+        let close = OrderSpec {
+            reduce_only: true,
+            client_id: None,
+            ..market.clone()
+        };
+        let request = factory.order("BTC-USD-PERP", &close).unwrap();
+        let json = serde_json::to_value(&request).unwrap();
+        assert_eq!(json["flags"], serde_json::json!(["REDUCE_ONLY"]));
+
+        let post_only_market = OrderSpec {
+            instruction: OrderInstruction::POST_ONLY,
+            ..market
+        };
+        assert!(matches!(
+            post_only_market.validate(),
+            Err(OrderError::PostOnlyMarket)
+        ));
+
+        let empty = OrderSpec {
+            size: Decimal::ZERO,
+            ..limit
+        };
+        assert!(matches!(empty.validate(), Err(OrderError::InvalidSize(_))));
+    }
+
+    #[test]
+    fn trigger_side_matrix() {
+        let mark = Decimal::from(95000);
+        let above = Decimal::from(96000);
+        let below = Decimal::from(94000);
+        // (订单类型, 方向, 触发价须高于标记价格)
+        let cases = [
+            (OrderType::STOP_MARKET, Side::BUY, true),
+            (OrderType::STOP_MARKET, Side::SELL, false),
+            (OrderType::STOP_LIMIT, Side::BUY, true),
+            (OrderType::STOP_LIMIT, Side::SELL, false),
+            (OrderType::STOP_LOSS_MARKET, Side::BUY, true),
+            (OrderType::STOP_LOSS_LIMIT, Side::SELL, false),
+            (OrderType::TAKE_PROFIT_MARKET, Side::BUY, false),
+            (OrderType::TAKE_PROFIT_MARKET, Side::SELL, true),
+            (OrderType::TAKE_PROFIT_LIMIT, Side::BUY, false),
+            (OrderType::TAKE_PROFIT_LIMIT, Side::SELL, true),
+        ];
+        for (order_type, side, trigger_above) in cases {
+            let (valid, invalid) = if trigger_above {
+                (above, below)
+            } else {
+                (below, above)
+            };
+            assert!(
+                validate_trigger(order_type, side, valid, mark).is_ok(),
+                "{order_type:?} {side:?}"
+            );
+            assert!(matches!(
+                validate_trigger(order_type, side, invalid, mark),
+                Err(OrderError::TriggerOnWrongSide { above, .. }) if above == trigger_above
+            ));
+            // 触发价等于标记价格会立即触发
+            assert!(validate_trigger(order_type, side, mark, mark).is_err());
+            let expected = if trigger_above {
+                OrderFlags::STOP_CONDITION_ABOVE_TRIGGER
+            } else {
+                OrderFlags::STOP_CONDITION_BELOW_TRIGGER
+            };
+            assert_eq!(stop_condition(order_type, side), Some(expected));
+        }
+        assert!(matches!(
+            validate_trigger(OrderType::LIMIT, Side::BUY, above, mark),
+            Err(OrderError::UnexpectedTrigger(OrderType::LIMIT))
+        ));
+    }
+
+    #[test]
+    fn trigger_orders_set_type_trigger_and_flags() {
+        let factory = factory(None);
+        let stop = OrderSpec {
+            side: Side::SELL,
+            order_type: OrderType::STOP_MARKET,
+            size: Decimal::new(5, 3),
+            price: None,
+            instruction: OrderInstruction::GTC,
+            client_id: None,
+            reduce_only: false,
+            trigger_price: Some(Decimal::from(94000)),
+        };
+        let json = serde_json::to_value(factory.order("BTC-USD-PERP", &stop).unwrap()).unwrap();
+        assert_eq!(json["type"], "STOP_MARKET");
+        assert_eq!(json["trigger_price"], "94000");
+        assert_eq!(
+            json["flags"],
+            serde_json::json!(["STOP_CONDITION_BELOW_TRIGGER"])
+        );
+
+        let take_profit = OrderSpec {
+            order_type: OrderType::TAKE_PROFIT_LIMIT,
+            price: Some(Decimal::from(96900)),
+            trigger_price: Some(Decimal::from(97000)),
+            ..stop.clone()
+        };
+        let json =
+            serde_json::to_value(factory.order("BTC-USD-PERP", &take_profit).unwrap()).unwrap();
+        assert_eq!(json["type"], "TAKE_PROFIT_LIMIT");
+        assert_eq!(json["price"], "96900");
+        assert_eq!(
+            json["flags"],
+            serde_json::json!(["REDUCE_ONLY", "STOP_CONDITION_ABOVE_TRIGGER"])
+        );
+
+        // 非触发单不带触发价
+        let json = serde_json::to_value(order(&factory).unwrap()).unwrap();
+        assert!(json.get("trigger_price").is_none());
+
+        assert!(matches!(
+            OrderSpec {
+                trigger_price: None,
+                ..stop.clone()
+            }
+            .validate(),
+            Err(OrderError::MissingTrigger(OrderType::STOP_MARKET))
+        ));
+        assert!(matches!(
+            OrderSpec {
+                order_type: OrderType::LIMIT,
+                price: Some(Decimal::from(94000)),
+                ..stop.clone()
+            }
+            .validate(),
+            Err(OrderError::UnexpectedTrigger(OrderType::LIMIT))
+        ));
+        assert!(matches!(
+            OrderSpec {
+                price: Some(Decimal::from(94000)),
+                ..stop.clone()
+            }
+            .validate(),
+            Err(OrderError::UnexpectedPrice)
+        ));
+        assert!(matches!(
+            factory.order(
+                "BTC-USD-PERP",
+                &OrderSpec {
+                    order_type: OrderType::STOP_LIMIT,
+                    ..stop
+                }
+            ),
+            Err(OrderError::MissingPrice)
+        ));
+    }
+
+    #[test]
+    fn reduce_only_orders_never_increase_the_position() {
+        let market = "BTC-USD-PERP";
+        let size = Decimal::new(5, 3);
+        let long = Decimal::new(3, 3);
+        let short = -Decimal::new(8, 3);
+        // 没有持仓时无论方向都拒绝，即使允许缩减
+        for side in [Side::BUY, Side::SELL] {
+            assert!(matches!(
+                check_reduce_only(market, side, size, Decimal::ZERO, true),
+                Err(OrderError::NoPositionToReduce(_))
+            ));
+        }
+        // 多头只能卖出、空头只能买入
+        assert!(matches!(
+            check_reduce_only(market, Side::BUY, size, long, true),
+            Err(OrderError::ReduceOnlyWrongSide {
+                side: Side::BUY,
+                ..
+            })
+        ));
+        assert!(matches!(
+            check_reduce_only(market, Side::SELL, size, short, true),
+            Err(OrderError::ReduceOnlyWrongSide {
+                side: Side::SELL,
+                ..
+            })
+        ));
+        assert_eq!(
+            check_reduce_only(market, Side::BUY, size, short, false).unwrap(),
+            size
+        );
+        assert_eq!(
+            check_reduce_only(market, Side::SELL, long, long, false).unwrap(),
+            long
+        );
+        // 超过持仓：默认拒绝，允许缩减时缩减到持仓数量
+        assert!(matches!(
+            check_reduce_only(market, Side::SELL, size, long, false),
+            Err(OrderError::ReduceOnlyExceedsPosition { position, .. }) if position == long
+        ));
+        assert_eq!(
+            check_reduce_only(market, Side::SELL, size, long, true).unwrap(),
+            long
+        );
+        assert_eq!(
+            check_reduce_only(market, Side::BUY, Decimal::ONE, short, true).unwrap(),
+            short.abs()
+        );
+    }
+
+    #[test]
+    fn capped_price_stays_within_the_slippage_cap() {
+        let mid = Decimal::new(950005, 1);
+        // 95000.5 * 1.002 = 95190.501，买单向下取整；95000.5 * 0.998 = 94810.499，卖单向上取整
+        assert_eq!(
+            capped_price(Side::BUY, mid, Decimal::from(20), Decimal::ONE),
+            Decimal::from(95190)
+        );
+        assert_eq!(
+            capped_price(Side::SELL, mid, Decimal::from(20), Decimal::ONE),
+            Decimal::from(94811)
+        );
+        assert_eq!(
+            capped_price(Side::BUY, mid, Decimal::from(20), Decimal::ZERO),
+            Decimal::new(95190501, 3)
+        );
+        assert_eq!(
+            capped_price(Side::SELL, mid, Decimal::ZERO, Decimal::ZERO),
+            mid
+        );
+    }
+
+    #[test]
+    fn passive_price_moves_away_from_the_book() {
+        let reference = Decimal::new(951234, 1);
+        assert_eq!(
+            passive_price(Side::BUY, reference, Decimal::from(500), Decimal::ONE),
+            Decimal::from(90367)
+        );
+        assert_eq!(
+            passive_price(Side::SELL, reference, Decimal::from(500), Decimal::ONE),
+            Decimal::from(99880)
+        );
+    }
+
+    fn open_order(id: &str, client_id: &str, market: &str) -> OrderUpdate {
+        OrderUpdate {
+            account: "0xabc".to_string(),
+            cancel_reason: String::new(),
+            client_id: client_id.to_string(),
+            created_at: 0,
+            id: id.to_string(),
+            instruction: OrderInstruction::GTC,
+            last_updated_at: 0,
+            market: market.to_string(),
+            price: Some(Decimal::from(95000)),
+            remaining_size: Decimal::new(3, 3),
+            side: Side::BUY,
+            size: Decimal::new(5, 3),
+            status: paradex::structs::OrderStatus::OPEN,
+            timestamp: 0,
+            order_type: OrderType::LIMIT,
+            seq_no: 0,
+            avg_fill_price: 0.0,
+            received_at: 0,
+            published_at: 0,
+            flags: vec![],
+            trigger_price: None,
+        }
+    }
+
+    #[test]
+    fn open_orders_are_found_by_id_or_client_id() {
+        let orders = [
+            open_order("101", "tlp-1", "BTC-USD-PERP"),
+            open_order("102", "tlp-2", "ETH-USD-PERP"),
+        ];
+        let by_client_id = OrderTarget::ClientId("tlp-2".to_string());
+        assert_eq!(
+            find_open_order(&orders, &by_client_id, None).map(|o| o.id.as_str()),
+            Some("102")
+        );
+        assert!(find_open_order(&orders, &by_client_id, Some("BTC-USD-PERP")).is_none());
+
+        let by_id = OrderTarget::Id("101".to_string());
+        assert_eq!(
+            find_open_order(&orders, &by_id, Some("BTC-USD-PERP")).map(|o| o.id.as_str()),
+            Some("101")
+        );
+        assert!(find_open_order(&orders, &OrderTarget::Id("999".to_string()), None).is_none());
+
+        assert_eq!(
+            describe_order(&orders[0]),
+            "101 (client id tlp-1) BTC-USD-PERP BUY 0.003/0.005 @ 95000 OPEN"
+        );
+    }
+
+    #[test]
+    fn aggressive_price_crosses_the_book() {
+        let reference = Decimal::new(951234, 1);
+        assert_eq!(
+            aggressive_price(Side::BUY, reference, Decimal::from(50), Decimal::ONE),
+            Decimal::from(95600)
+        );
+        assert_eq!(
+            aggressive_price(Side::SELL, reference, Decimal::from(50), Decimal::ONE),
+            Decimal::from(94647)
+        );
+    }
+}
@@ -0,0 +1,1042 @@
+//! 模拟交易（paper trading）：订单保存在本地，由实时成交与 BBO 驱动模拟成交，不访问交易所
+//!
+//! 撮合规则：
+//! - 市价单与穿越盘口的限价单立即按对手方最优价全部成交（taker）；
+//! - POST_ONLY 限价单穿越盘口时被拒绝，IOC 限价单未穿越时直接过期；
+//! - 挂单在市场成交价穿过挂单价（买单成交价低于挂单价、卖单高于挂单价）时按挂单价成交（maker），
+//!   成交数量不超过该笔市场成交的数量；
+//! - 触发单（止损 / 止盈）在市场成交价穿过触发价时触发：市价类按对手方最优价成交，
+//!   限价类转为普通挂单；
+//! - 只减仓订单的成交数量不超过持仓，持仓平掉（或反向）后剩余部分与其他只减仓挂单一并撤销。
+
+use async_trait::async_trait;
+use paradex::{
+    error::Error,
+    structs::{
+        CancelByMarketResponse, Fill, FillLiquidity, FillType, ModifyOrderRequest, OrderFlags,
+        OrderInstruction, OrderRequest, OrderStatus, OrderType, OrderUpdate, Position,
+        PositionSide, PositionStatus, Side, Trade, BBO,
+    },
+    ws::Message,
+};
+use rust_decimal::{prelude::ToPrimitive, Decimal};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::gateway::{GatewayError, OrderGateway};
+use crate::market_data::MarketEvent;
+use crate::markets::decimal;
+use crate::orders::{stop_condition, POST_ONLY_WOULD_CROSS};
+use crate::risk::RiskContext;
+
+#[cfg(test)]
+pub(crate) mod test_support;
+
+/// 只减仓订单因没有可减的持仓而被撤销时的原因
+const REDUCE_ONLY_CANCEL_REASON: &str = "REDUCE_ONLY";
+
+/// 不带限价、按对手方最优价成交的订单类型
+fn is_market(order_type: OrderType) -> bool {
+    matches!(
+        order_type,
+        OrderType::MARKET
+            | OrderType::STOP_MARKET
+            | OrderType::STOP_LOSS_MARKET
+            | OrderType::TAKE_PROFIT_MARKET
+    )
+}
+
+/// 接收模拟的订单更新（`Message::Orders`）、成交（`Message::Fills`）与成交后的持仓（`Message::Position`）
+pub type PaperListener = Box<dyn Fn(&Message) + Send + Sync>;
+
+/// 单个市场的模拟持仓；`size` 为正表示多头、为负表示空头
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PaperPosition {
+    pub size: Decimal,
+    pub average_entry: Decimal,
+    pub realized_pnl: Decimal,
+}
+
+impl PaperPosition {
+    fn apply_fill(&mut self, side: Side, price: Decimal, size: Decimal) {
+        let signed = match side {
+            Side::BUY => size,
+            Side::SELL => -size,
+        };
+        let total = self.size + signed;
+        if self.size.is_zero() || self.size.is_sign_positive() == signed.is_sign_positive() {
+            // 开仓或加仓：按数量加权更新均价
+            self.average_entry =
+                (self.average_entry * self.size.abs() + price * size) / total.abs();
+        } else {
+            // 减仓：按均价结算已实现盈亏；反手时剩余部分以成交价开仓
+            let closed = size.min(self.size.abs());
+            let direction = if self.size.is_sign_positive() {
+                Decimal::ONE
+            } else {
+                -Decimal::ONE
+            };
+            self.realized_pnl += (price - self.average_entry) * closed * direction;
+            if total.is_zero() {
+                self.average_entry = Decimal::ZERO;
+            } else if total.is_sign_positive() != self.size.is_sign_positive() {
+                self.average_entry = price;
+            }
+        }
+        self.size = total;
+    }
+
+    /// 按 `mark` 计算的未实现盈亏
+    pub fn unrealized_pnl(&self, mark: Decimal) -> Decimal {
+        (mark - self.average_entry) * self.size
+    }
+
+    /// 持仓频道格式的持仓，数量带符号（与交易所一致）
+    fn to_position(&self, market: &str, seq_no: u64) -> Position {
+        let entry = self.average_entry.to_f64().unwrap_or_default();
+        let cost = (self.average_entry * self.size)
+            .to_f64()
+            .unwrap_or_default();
+        Position {
+            average_entry_price: entry,
+            average_entry_price_usd: entry,
+            cached_funding_index: 0.0,
+            cost,
+            cost_usd: cost,
+            id: format!("paper-{}", market),
+            last_fill_id: String::new(),
+            last_updated_at: now_millis(),
+            leverage: String::new(),
+            liquidation_price: 0.0,
+            market: market.to_string(),
+            seq_no,
+            side: if self.size.is_sign_negative() {
+                PositionSide::SHORT
+            } else {
+                PositionSide::LONG
+            },
+            size: self.size.to_f64().unwrap_or_default(),
+            status: if self.size.is_zero() {
+                PositionStatus::CLOSED
+            } else {
+                PositionStatus::OPEN
+            },
+            unrealized_funding_pnl: 0.0,
+            unrealized_pnl: 0.0,
+        }
+    }
+}
+
+/// 订单最多可成交的数量：只减仓订单不超过反方向的持仓，其他订单为 `size`
+fn reducible(
+    positions: &BTreeMap<String, PaperPosition>,
+    order: &OrderUpdate,
+    size: Decimal,
+) -> Decimal {
+    if !order.flags.contains(&OrderFlags::REDUCE_ONLY) {
+        return size;
+    }
+    let position = positions
+        .get(&order.market)
+        .map_or(Decimal::ZERO, |position| position.size);
+    match order.side {
+        Side::BUY if position.is_sign_negative() => size.min(-position),
+        Side::SELL if position.is_sign_positive() => size.min(position),
+        _ => Decimal::ZERO,
+    }
+}
+
+#[derive(Default)]
+struct State {
+    /// 挂单，按提交顺序撮合
+    resting: Vec<OrderUpdate>,
+    /// 尚未触发的触发单
+    triggers: Vec<OrderUpdate>,
+    /// 最优买价与卖价
+    quotes: HashMap<String, (Decimal, Decimal)>,
+    last_trade: HashMap<String, Decimal>,
+    positions: BTreeMap<String, PaperPosition>,
+    next_order_id: u64,
+    next_fill_id: u64,
+    fill_count: u64,
+}
+
+impl State {
+    /// 盯市价格：最近成交价，其次为中间价
+    fn mark(&self, market: &str) -> Option<Decimal> {
+        self.last_trade.get(market).copied().or_else(|| {
+            self.quotes
+                .get(market)
+                .map(|(bid, ask)| (*bid + *ask) / Decimal::TWO)
+        })
+    }
+
+    /// 对手方最优价：买单取卖一、卖单取买一
+    fn opposite_quote(&self, market: &str, side: Side) -> Option<Decimal> {
+        self.quotes.get(market).map(|(bid, ask)| match side {
+            Side::BUY => *ask,
+            Side::SELL => *bid,
+        })
+    }
+
+    fn reducible(&self, order: &OrderUpdate, size: Decimal) -> Decimal {
+        reducible(&self.positions, order, size)
+    }
+
+    /// 以 `price` 吃单成交；只减仓订单超过持仓的部分撤销
+    fn take(&mut self, mut order: OrderUpdate, price: Decimal) -> (OrderUpdate, Vec<Fill>) {
+        let size = self.reducible(&order, order.remaining_size);
+        let mut fills = Vec::new();
+        if !size.is_zero() {
+            fills.push(self.fill(&mut order, price, size, FillLiquidity::TAKER));
+        }
+        if !order.remaining_size.is_zero() {
+            order.status = OrderStatus::CLOSED;
+            order.cancel_reason = REDUCE_ONLY_CANCEL_REASON.to_string();
+        }
+        (order, fills)
+    }
+
+    /// 记录一笔成交并更新订单与持仓
+    fn fill(
+        &mut self,
+        order: &mut OrderUpdate,
+        price: Decimal,
+        size: Decimal,
+        liquidity: FillLiquidity,
+    ) -> Fill {
+        let filled_before = order.size - order.remaining_size;
+        let average = if filled_before.is_zero() {
+            price
+        } else {
+            (decimal(order.avg_fill_price) * filled_before + price * size) / (filled_before + size)
+        };
+        order.remaining_size -= size;
+        order.avg_fill_price = average.to_f64().unwrap_or_default();
+        order.last_updated_at = now_millis();
+        if order.remaining_size.is_zero() {
+            order.status = OrderStatus::CLOSED;
+        }
+
+        let position = self.positions.entry(order.market.clone()).or_default();
+        let realized_before = position.realized_pnl;
+        position.apply_fill(order.side, price, size);
+        let realized = position.realized_pnl - realized_before;
+        self.fill_count += 1;
+        self.next_fill_id += 1;
+
+        Fill {
+            client_id: order.client_id.clone(),
+            created_at: order.last_updated_at,
+            fee: 0.0,
+            fee_currency: "USDC".to_string(),
+            id: format!("paper-fill-{}", self.next_fill_id),
+            liquidity,
+            market: order.market.clone(),
+            order_id: order.id.clone(),
+            price: price.to_f64().unwrap_or_default(),
+            side: order.side,
+            size: size.to_f64().unwrap_or_default(),
+            remaining_size: order.remaining_size.to_f64().unwrap_or_default(),
+            fill_type: FillType::FILL,
+            realized_pnl: realized.to_f64().unwrap_or_default(),
+        }
+    }
+
+    /// 撮合新订单或改单后的订单：没有可减持仓的只减仓订单直接撤销，
+    /// 触发单等待触发，其他订单按 [`Self::execute`] 撮合
+    fn submit(&mut self, mut order: OrderUpdate) -> Result<(OrderUpdate, Vec<Fill>), Error> {
+        if self.reducible(&order, order.remaining_size).is_zero() {
+            order.status = OrderStatus::CLOSED;
+            order.cancel_reason = REDUCE_ONLY_CANCEL_REASON.to_string();
+            return Ok((order, vec![]));
+        }
+        if order.trigger_price.is_some() && stop_condition(order.order_type, order.side).is_some() {
+            order.status = OrderStatus::OPEN;
+            self.triggers.push(order.clone());
+            return Ok((order, vec![]));
+        }
+        self.execute(order)
+    }
+
+    /// 穿越盘口则立即成交，否则挂单（IOC 过期）
+    fn execute(&mut self, mut order: OrderUpdate) -> Result<(OrderUpdate, Vec<Fill>), Error> {
+        let quote = self.opposite_quote(&order.market, order.side);
+        let crosses = match (order.order_type, order.price, quote) {
+            (order_type, _, _) if is_market(order_type) => true,
+            (_, Some(price), Some(quote)) => match order.side {
+                Side::BUY => price >= quote,
+                Side::SELL => price <= quote,
+            },
+            _ => false,
+        };
+
+        if crosses {
+            // 与交易所相同的结构化错误码，订单管理器据此重新定价
+            if order.instruction == OrderInstruction::POST_ONLY {
+                return Err(Error::ParadexError {
+                    status_code: reqwest::StatusCode::BAD_REQUEST,
+                    error: Some(POST_ONLY_WOULD_CROSS.to_string()),
+                    message: format!(
+                        "paper: POST_ONLY {:?} order at {} would cross the book on {}",
+                        order.side,
+                        order.price.unwrap_or_default(),
+                        order.market
+                    ),
+                });
+            }
+            let Some(quote) = quote else {
+                return Err(Error::RestError(format!(
+                    "paper: no BBO for {} to fill the market order",
+                    order.market
+                )));
+            };
+            return Ok(self.take(order, quote));
+        }
+
+        if order.instruction == OrderInstruction::IOC {
+            order.status = OrderStatus::CLOSED;
+            order.cancel_reason = "IOC_NOT_FILLED".to_string();
+        } else {
+            order.status = OrderStatus::OPEN;
+            self.resting.push(order.clone());
+        }
+        Ok((order, vec![]))
+    }
+
+    /// 市场 `market` 以 `price` 成交时触发的触发单：市价类立即成交，限价类转为挂单
+    fn trigger(&mut self, market: &str, price: Decimal) -> (Vec<OrderUpdate>, Vec<Fill>) {
+        let triggered = |order: &OrderUpdate| {
+            let (Some(trigger), Some(condition)) = (
+                order.trigger_price,
+                stop_condition(order.order_type, order.side),
+            ) else {
+                return false;
+            };
+            order.market == market
+                && match condition {
+                    OrderFlags::STOP_CONDITION_ABOVE_TRIGGER => price >= trigger,
+                    _ => price <= trigger,
+                }
+        };
+        let (triggered, waiting): (Vec<_>, _) = std::mem::take(&mut self.triggers)
+            .into_iter()
+            .partition(|order| triggered(order));
+        self.triggers = waiting;
+
+        let mut updates = Vec::new();
+        let mut fills = Vec::new();
+        for order in triggered {
+            let result = if is_market(order.order_type) {
+                let quote = self.opposite_quote(market, order.side).unwrap_or(price);
+                Ok(self.take(order, quote))
+            } else {
+                self.execute(order)
+            };
+            if let Ok((order, filled)) = result {
+                updates.push(order);
+                fills.extend(filled);
+            }
+        }
+        (updates, fills)
+    }
+
+    /// 撤销没有可减持仓的只减仓挂单与触发单
+    fn expire_reduce_only(&mut self) -> Vec<OrderUpdate> {
+        let mut expired = Vec::new();
+        for orders in [&mut self.resting, &mut self.triggers] {
+            let (gone, kept): (Vec<_>, _) = std::mem::take(orders).into_iter().partition(|order| {
+                reducible(&self.positions, order, order.remaining_size).is_zero()
+            });
+            *orders = kept;
+            expired.extend(gone);
+        }
+        for order in expired.iter_mut() {
+            order.status = OrderStatus::CLOSED;
+            order.cancel_reason = REDUCE_ONLY_CANCEL_REASON.to_string();
+            order.last_updated_at = now_millis();
+        }
+        expired
+    }
+
+    /// 移除匹配的挂单与未触发的触发单
+    fn remove_resting(&mut self, matches: impl Fn(&OrderUpdate) -> bool) -> Vec<OrderUpdate> {
+        let (mut removed, kept): (Vec<_>, _) = std::mem::take(&mut self.resting)
+            .into_iter()
+            .partition(|order| matches(order));
+        self.resting = kept;
+        let (triggers, kept): (Vec<_>, _) = std::mem::take(&mut self.triggers)
+            .into_iter()
+            .partition(|order| matches(order));
+        self.triggers = kept;
+        removed.extend(triggers);
+        removed
+    }
+}
+
+/// 本地模拟撮合的下单出口
+pub struct PaperExchange {
+    account: String,
+    initial_balance: Decimal,
+    state: Mutex<State>,
+    listener: Option<PaperListener>,
+    /// 推送的订单更新的序号，与交易所的 `seq_no` 一样单调递增
+    seq_no: AtomicU64,
+}
+
+impl PaperExchange {
+    /// `initial_balance` 为模拟的初始 USDC 余额
+    pub fn new(account: impl Into<String>, initial_balance: Decimal) -> Self {
+        Self {
+            account: account.into(),
+            initial_balance,
+            state: Mutex::new(State::default()),
+            listener: None,
+            seq_no: AtomicU64::new(0),
+        }
+    }
+
+    /// 订单状态变化、成交与持仓变化时回调 `listener`
+    pub fn with_listener(mut self, listener: PaperListener) -> Self {
+        self.listener = Some(listener);
+        self
+    }
+
+    /// 处理行情消息：BBO 更新盘口，成交驱动挂单撮合；其他消息忽略
+    pub fn on_message(&self, message: &Message) {
+        match message {
+            Message::BBO(bbo) => self.on_bbo(bbo),
+            Message::Trades(trade) => self.on_trade(trade),
+            _ => {}
+        }
+    }
+
+    /// 处理行情事件总线上的 BBO 与成交事件，其他事件忽略
+    pub fn on_event(&self, event: &MarketEvent) {
+        match event {
+            MarketEvent::Bbo(bbo) => self.quote(&bbo.symbol, bbo.bid, bbo.ask),
+            MarketEvent::Trade(trade) => self.trade(&trade.symbol, trade.price, trade.size),
+            _ => {}
+        }
+    }
+
+    /// 以 BBO 更新报价并撮合挂单
+    pub fn on_bbo(&self, bbo: &BBO) {
+        self.quote(&bbo.market, decimal(bbo.bid), decimal(bbo.ask));
+    }
+
+    /// 以成交价撮合挂单
+    pub fn on_trade(&self, trade: &Trade) {
+        self.trade(&trade.market, decimal(trade.price), decimal(trade.size));
+    }
+
+    fn quote(&self, market: &str, bid: Decimal, ask: Decimal) {
+        self.state
+            .lock()
+            .unwrap()
+            .quotes
+            .insert(market.to_string(), (bid, ask));
+    }
+
+    /// 市场 `market` 以 `price` 成交 `size`：穿价的挂单按挂单价成交，合计不超过成交量；
+    /// 随后检查触发单并撤销已没有可减持仓的只减仓订单
+    fn trade(&self, market: &str, price: Decimal, size: Decimal) {
+        let mut available = size;
+        let mut updates = Vec::new();
+        let mut fills = Vec::new();
+        {
+            let mut state = self.state.lock().unwrap();
+            state.last_trade.insert(market.to_string(), price);
+            let mut resting = std::mem::take(&mut state.resting);
+            for order in resting.iter_mut() {
+                if available.is_zero() {
+                    break;
+                }
+                let Some(limit) = order.price else { continue };
+                let through = order.market == market
+                    && match order.side {
+                        Side::BUY => price < limit,
+                        Side::SELL => price > limit,
+                    };
+                if !through {
+                    continue;
+                }
+                let size = state.reducible(order, order.remaining_size.min(available));
+                if size.is_zero() {
+                    continue;
+                }
+                available -= size;
+                fills.push(state.fill(order, limit, size, FillLiquidity::MAKER));
+                updates.push(order.clone());
+            }
+            resting.retain(|order| order.status != OrderStatus::CLOSED);
+            state.resting = resting;
+            let (triggered, triggered_fills) = state.trigger(market, price);
+            updates.extend(triggered);
+            fills.extend(triggered_fills);
+            updates.extend(state.expire_reduce_only());
+        }
+        self.emit(updates, fills);
+    }
+
+    /// 当前挂单，包括尚未触发的触发单
+    pub fn open_orders(&self) -> Vec<OrderUpdate> {
+        let state = self.state.lock().unwrap();
+        state
+            .resting
+            .iter()
+            .chain(&state.triggers)
+            .cloned()
+            .collect()
+    }
+
+    /// `market` 的模拟持仓
+    pub fn position(&self, market: &str) -> Option<PaperPosition> {
+        self.state.lock().unwrap().positions.get(market).cloned()
+    }
+
+    /// 会话盈亏汇总
+    pub fn summary(&self) -> PaperSummary {
+        let state = self.state.lock().unwrap();
+        let positions: Vec<_> = state
+            .positions
+            .iter()
+            .map(|(market, position)| {
+                let mark = state.mark(market);
+                (market.clone(), position.clone(), mark)
+            })
+            .collect();
+        PaperSummary {
+            initial_balance: self.initial_balance,
+            fills: state.fill_count,
+            positions,
+        }
+    }
+
+    fn emit(&self, updates: Vec<OrderUpdate>, fills: Vec<Fill>) {
+        let Some(ref listener) = self.listener else {
+            return;
+        };
+        let markets: BTreeSet<_> = fills.iter().map(|fill| fill.market.clone()).collect();
+        for fill in fills {
+            listener(&Message::Fills(fill));
+        }
+        for mut update in updates {
+            update.seq_no = self.seq_no.fetch_add(1, Ordering::Relaxed) + 1;
+            listener(&Message::Orders(update));
+        }
+        for market in markets {
+            let Some(position) = self.position(&market) else {
+                continue;
+            };
+            let seq_no = self.seq_no.fetch_add(1, Ordering::Relaxed) + 1;
+            listener(&Message::Position(position.to_position(&market, seq_no)));
+        }
+    }
+
+    fn new_order(&self, state: &mut State, request: OrderRequest) -> OrderUpdate {
+        state.next_order_id += 1;
+        let now = now_millis();
+        OrderUpdate {
+            account: self.account.clone(),
+            cancel_reason: String::new(),
+            client_id: request.client_id.unwrap_or_default(),
+            created_at: now,
+            id: format!("paper-{}", state.next_order_id),
+            instruction: request.instruction,
+            last_updated_at: now,
+            market: request.market,
+            price: request.price,
+            remaining_size: request.size,
+            side: request.side,
+            size: request.size,
+            status: OrderStatus::NEW,
+            timestamp: now,
+            order_type: request.order_type,
+            seq_no: 0,
+            avg_fill_price: 0.0,
+            received_at: now,
+            published_at: now,
+            flags: request.flags,
+            trigger_price: request.trigger_price,
+        }
+    }
+
+    fn cancel_where(&self, matches: impl Fn(&OrderUpdate) -> bool) -> Vec<OrderUpdate> {
+        let mut cancelled = self.state.lock().unwrap().remove_resting(matches);
+        for order in cancelled.iter_mut() {
+            order.status = OrderStatus::CLOSED;
+            order.cancel_reason = "USER_CANCELED".to_string();
+            order.last_updated_at = now_millis();
+        }
+        self.emit(cancelled.clone(), vec![]);
+        cancelled
+    }
+}
+
+#[async_trait]
+impl OrderGateway for PaperExchange {
+    async fn create_order(&self, request: OrderRequest) -> Result<OrderUpdate, GatewayError> {
+        let (order, fills, expired) = {
+            let mut state = self.state.lock().unwrap();
+            let order = self.new_order(&mut state, request);
+            let (order, fills) = state.submit(order)?;
+            (order, fills, state.expire_reduce_only())
+        };
+        let mut updates = vec![order.clone()];
+        updates.extend(expired);
+        self.emit(updates, fills);
+        Ok(order)
+    }
+
+    async fn modify_order(&self, request: ModifyOrderRequest) -> Result<OrderUpdate, GatewayError> {
+        let (order, fills) = {
+            let mut state = self.state.lock().unwrap();
+            let Some(index) = state.resting.iter().position(|o| o.id == request.id) else {
+                return Err(
+                    Error::RestError(format!("paper: order {} is not open", request.id)).into(),
+                );
+            };
+            let mut order = state.resting[index].clone();
+            let filled = order.size - order.remaining_size;
+            if request.size <= filled {
+                return Err(Error::RestError(format!(
+                    "paper: new size {} does not exceed filled size {}",
+                    request.size, filled
+                ))
+                .into());
+            }
+            order.price = request.price;
+            order.size = request.size;
+            order.remaining_size = request.size - filled;
+            order.last_updated_at = now_millis();
+            // 改单后重新撮合；被拒绝时保留原挂单
+            let resubmitted = state.submit(order)?;
+            state.resting.remove(index);
+            resubmitted
+        };
+        let expired = self.state.lock().unwrap().expire_reduce_only();
+        let mut updates = vec![order.clone()];
+        updates.extend(expired);
+        self.emit(updates, fills);
+        Ok(order)
+    }
+
+    async fn cancel_order(&self, order_id: String) -> Result<(), GatewayError> {
+        if self.cancel_where(|order| order.id == order_id).is_empty() {
+            return Err(Error::RestError(format!("paper: order {} is not open", order_id)).into());
+        }
+        Ok(())
+    }
+
+    async fn cancel_order_by_client_id(&self, client_id: String) -> Result<(), GatewayError> {
+        if self
+            .cancel_where(|order| order.client_id == client_id)
+            .is_empty()
+        {
+            return Err(Error::RestError(format!(
+                "paper: no open order with client id {}",
+                client_id
+            ))
+            .into());
+        }
+        Ok(())
+    }
+
+    async fn cancel_all_orders_for_market(
+        &self,
+        market: String,
+    ) -> Result<CancelByMarketResponse, GatewayError> {
+        self.cancel_where(|order| order.market == market);
+        Ok(CancelByMarketResponse {
+            market,
+            message: "paper".to_string(),
+        })
+    }
+
+    async fn cancel_all_orders(&self) -> Result<Vec<String>, GatewayError> {
+        Ok(self
+            .cancel_where(|_| true)
+            .into_iter()
+            .map(|order| order.id)
+            .collect())
+    }
+}
+
+/// 风控以模拟持仓与盯市价格为依据
+#[async_trait]
+impl RiskContext for PaperExchange {
+    async fn position(&self, market: &str) -> Result<Decimal, String> {
+        Ok(PaperExchange::position(self, market).map_or(Decimal::ZERO, |position| position.size))
+    }
+
+    async fn reference_price(&self, market: &str) -> Option<Decimal> {
+        self.state.lock().unwrap().mark(market)
+    }
+
+    async fn open_order(&self, id: &str) -> Option<OrderUpdate> {
+        self.open_orders().into_iter().find(|order| order.id == id)
+    }
+}
+
+/// 模拟会话的盈亏汇总
+#[derive(Debug, Clone, PartialEq)]
+pub struct PaperSummary {
+    pub initial_balance: Decimal,
+    pub fills: u64,
+    /// 市场、持仓与盯市价格（无行情时为 `None`）
+    pub positions: Vec<(String, PaperPosition, Option<Decimal>)>,
+}
+
+impl PaperSummary {
+    /// 各市场已实现盈亏之和
+    pub fn realized_pnl(&self) -> Decimal {
+        self.positions.iter().map(|(_, p, _)| p.realized_pnl).sum()
+    }
+
+    /// 按标记价格计算的未实现盈亏之和；没有标记价格的市场不计入
+    pub fn unrealized_pnl(&self) -> Decimal {
+        self.positions
+            .iter()
+            .filter_map(|(_, position, mark)| mark.map(|mark| position.unrealized_pnl(mark)))
+            .sum()
+    }
+
+    /// 初始余额加已实现盈亏（模拟不收手续费）
+    pub fn balance(&self) -> Decimal {
+        self.initial_balance + self.realized_pnl()
+    }
+}
+
+impl fmt::Display for PaperSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Paper trading summary ({} fills)", self.fills)?;
+        for (market, position, mark) in &self.positions {
+            writeln!(
+                f,
+                "  {}: position {} @ {}, realized {}, unrealized {}",
+                market,
+                position.size,
+                position.average_entry,
+                position.realized_pnl,
+                mark.map_or("-".to_string(), |mark| position
+                    .unrealized_pnl(mark)
+                    .to_string())
+            )?;
+        }
+        write!(
+            f,
+            "  balance {} USDC (initial {}), realized {}, unrealized {}",
+            self.balance(),
+            self.initial_balance,
+            self.realized_pnl(),
+            self.unrealized_pnl()
+        )
+    }
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::positions::PositionCache;
+    use std::sync::Arc;
+
+    const MARKET: &str = "BTC-USD-PERP";
+
+    fn request(
+        side: Side,
+        price: Option<i64>,
+        size: Decimal,
+        instruction: OrderInstruction,
+    ) -> OrderRequest {
+        OrderRequest {
+            instruction,
+            market: MARKET.to_string(),
+            price: price.map(Decimal::from),
+            side,
+            size,
+            order_type: if price.is_some() {
+                OrderType::LIMIT
+            } else {
+                OrderType::MARKET
+            },
+            client_id: Some(format!("paper-test-{:?}-{:?}", side, price)),
+            flags: vec![],
+            recv_window: None,
+            stp: None,
+            trigger_price: None,
+        }
+    }
+
+    fn bbo(bid: &str, ask: &str) -> Message {
+        Message::BBO(
+            serde_json::from_value(serde_json::json!({
+                "bid": bid, "bid_size": "1", "ask": ask, "ask_size": "1",
+                "market": MARKET, "last_updated_at": 0, "seq_no": 0
+            }))
+            .unwrap(),
+        )
+    }
+
+    fn trade(price: &str, size: &str) -> Message {
+        Message::Trades(
+            serde_json::from_value(serde_json::json!({
+                "created_at": 0, "id": "t", "market": MARKET, "price": price,
+                "side": "SELL", "size": size, "trade_type": "FILL"
+            }))
+            .unwrap(),
+        )
+    }
+
+    #[tokio::test]
+    async fn resting_orders_fill_when_trades_go_through_the_price() {
+        let fills = Arc::new(Mutex::new(Vec::new()));
+        let recorded = fills.clone();
+        let exchange = PaperExchange::new("0xabc", Decimal::from(10_000)).with_listener(Box::new(
+            move |message| {
+                if let Message::Fills(fill) = message {
+                    recorded
+                        .lock()
+                        .unwrap()
+                        .push((fill.price, fill.size, fill.remaining_size));
+                }
+            },
+        ));
+        exchange.on_message(&bbo("100.5", "101"));
+
+        let order = exchange
+            .create_order(request(
+                Side::BUY,
+                Some(100),
+                Decimal::ONE,
+                OrderInstruction::GTC,
+            ))
+            .await
+            .unwrap();
+        assert_eq!(order.status, OrderStatus::OPEN);
+
+        // 成交价等于挂单价不算穿过
+        exchange.on_message(&trade("100", "5"));
+        assert!(fills.lock().unwrap().is_empty());
+
+        exchange.on_message(&trade("99.5", "0.4"));
+        assert_eq!(exchange.open_orders()[0].remaining_size, Decimal::new(6, 1));
+
+        exchange.on_message(&trade("99", "2"));
+        assert!(exchange.open_orders().is_empty());
+        assert_eq!(
+            *fills.lock().unwrap(),
+            [(100.0, 0.4, 0.6), (100.0, 0.6, 0.0)]
+        );
+
+        let position = exchange.position(MARKET).unwrap();
+        assert_eq!(position.size, Decimal::ONE);
+        assert_eq!(position.average_entry, Decimal::from(100));
+    }
+
+    #[tokio::test]
+    async fn fills_push_the_updated_position() {
+        let positions = PositionCache::new(Decimal::ZERO);
+        let cache = positions.clone();
+        let exchange = PaperExchange::new("0xabc", Decimal::from(10_000))
+            .with_listener(Box::new(move |message| cache.on_message(message)));
+        exchange.on_message(&bbo("100", "101"));
+
+        exchange
+            .create_order(request(
+                Side::SELL,
+                None,
+                Decimal::ONE,
+                OrderInstruction::IOC,
+            ))
+            .await
+            .unwrap();
+        assert_eq!(positions.size(MARKET), -Decimal::ONE);
+        assert_eq!(positions.entry_price(MARKET), Some(Decimal::from(100)));
+
+        exchange
+            .create_order(request(
+                Side::BUY,
+                None,
+                Decimal::ONE,
+                OrderInstruction::IOC,
+            ))
+            .await
+            .unwrap();
+        assert_eq!(positions.size(MARKET), Decimal::ZERO);
+        assert_eq!(
+            positions.get(MARKET).unwrap().status,
+            PositionStatus::CLOSED
+        );
+    }
+
+    #[tokio::test]
+    async fn post_only_orders_that_cross_are_rejected() {
+        let exchange = PaperExchange::new("0xabc", Decimal::from(10_000));
+        exchange.on_message(&bbo("100", "101"));
+
+        let rejected = exchange
+            .create_order(request(
+                Side::BUY,
+                Some(101),
+                Decimal::ONE,
+                OrderInstruction::POST_ONLY,
+            ))
+            .await;
+        assert!(matches!(
+            rejected,
+            Err(GatewayError::Exchange(Error::ParadexError { error: Some(ref code), .. }))
+                if code == POST_ONLY_WOULD_CROSS
+        ));
+        assert!(exchange.open_orders().is_empty());
+
+        let resting = exchange
+            .create_order(request(
+                Side::SELL,
+                Some(102),
+                Decimal::ONE,
+                OrderInstruction::POST_ONLY,
+            ))
+            .await
+            .unwrap();
+        assert_eq!(resting.status, OrderStatus::OPEN);
+
+        // 改单穿越盘口被拒绝时原挂单保留
+        let modify = ModifyOrderRequest {
+            id: resting.id.clone(),
+            market: MARKET.to_string(),
+            price: Some(Decimal::from(99)),
+            side: Side::SELL,
+            size: Decimal::ONE,
+            order_type: OrderType::LIMIT,
+        };
+        assert!(exchange.modify_order(modify).await.is_err());
+        assert_eq!(exchange.open_orders()[0].price, Some(Decimal::from(102)));
+
+        exchange.cancel_order(resting.id).await.unwrap();
+        assert!(exchange.open_orders().is_empty());
+    }
+
+    #[tokio::test]
+    async fn taker_fills_realize_pnl() {
+        let exchange = PaperExchange::new("0xabc", Decimal::from(10_000));
+        exchange.on_message(&bbo("100", "101"));
+
+        let bought = exchange
+            .create_order(request(
+                Side::BUY,
+                None,
+                Decimal::TWO,
+                OrderInstruction::IOC,
+            ))
+            .await
+            .unwrap();
+        assert_eq!(bought.status, OrderStatus::CLOSED);
+        assert_eq!(bought.avg_fill_price, 101.0);
+
+        // 未穿越盘口的 IOC 限价单直接过期
+        let expired = exchange
+            .create_order(request(
+                Side::SELL,
+                Some(105),
+                Decimal::ONE,
+                OrderInstruction::IOC,
+            ))
+            .await
+            .unwrap();
+        assert_eq!(expired.status, OrderStatus::CLOSED);
+        assert_eq!(expired.remaining_size, Decimal::ONE);
+
+        exchange.on_message(&bbo("104", "105"));
+        exchange
+            .create_order(request(
+                Side::SELL,
+                Some(103),
+                Decimal::ONE,
+                OrderInstruction::GTC,
+            ))
+            .await
+            .unwrap();
+        exchange.on_message(&trade("104.5", "0.1"));
+
+        let summary = exchange.summary();
+        assert_eq!(summary.fills, 2);
+        assert_eq!(summary.realized_pnl(), Decimal::from(3));
+        assert_eq!(summary.unrealized_pnl(), Decimal::new(35, 1));
+        assert_eq!(summary.balance(), Decimal::from(10_003));
+        assert_eq!(
+            summary.to_string(),
+            "Paper trading summary (2 fills)\n  \
+             BTC-USD-PERP: position 1 @ 101, realized 3, unrealized 3.5\n  \
+             balance 10003 USDC (initial 10000), realized 3, unrealized 3.5"
+        );
+    }
+
+    #[tokio::test]
+    async fn stops_trigger_and_reduce_only_orders_never_flip_the_position() {
+        let exchange = PaperExchange::new("0xabc", Decimal::from(10_000));
+        exchange.on_message(&bbo("100", "101"));
+        exchange
+            .create_order(request(
+                Side::BUY,
+                None,
+                Decimal::ONE,
+                OrderInstruction::IOC,
+            ))
+            .await
+            .unwrap();
+
+        let mut stop = request(Side::SELL, None, Decimal::TWO, OrderInstruction::GTC);
+        stop.order_type = OrderType::STOP_LOSS_MARKET;
+        stop.trigger_price = Some(Decimal::from(98));
+        stop.flags = vec![
+            OrderFlags::REDUCE_ONLY,
+            OrderFlags::STOP_CONDITION_BELOW_TRIGGER,
+        ];
+        let mut take_profit = request(Side::SELL, Some(105), Decimal::ONE, OrderInstruction::GTC);
+        take_profit.flags = vec![OrderFlags::REDUCE_ONLY];
+        exchange.create_order(stop).await.unwrap();
+        exchange.create_order(take_profit).await.unwrap();
+        assert_eq!(exchange.open_orders().len(), 2);
+
+        // 未穿过触发价不触发
+        exchange.on_message(&trade("98.5", "1"));
+        assert_eq!(exchange.open_orders().len(), 2);
+
+        // 触发后按买一成交，数量不超过持仓；持仓平掉后止盈单随之撤销
+        exchange.on_message(&bbo("97", "98"));
+        exchange.on_message(&trade("97.5", "1"));
+        assert!(exchange.open_orders().is_empty());
+        let position = exchange.position(MARKET).unwrap();
+        assert_eq!(position.size, Decimal::ZERO);
+        assert_eq!(position.realized_pnl, Decimal::from(-4));
+
+        // 没有持仓时只减仓订单直接撤销
+        let mut reduce = request(Side::SELL, Some(99), Decimal::ONE, OrderInstruction::GTC);
+        reduce.flags = vec![OrderFlags::REDUCE_ONLY];
+        let rejected = exchange.create_order(reduce).await.unwrap();
+        assert_eq!(rejected.status, OrderStatus::CLOSED);
+        assert_eq!(rejected.cancel_reason, REDUCE_ONLY_CANCEL_REASON);
+    }
+
+    #[test]
+    fn position_flips_through_zero() {
+        let mut position = PaperPosition::default();
+        position.apply_fill(Side::SELL, Decimal::from(100), Decimal::ONE);
+        assert_eq!(position.size, -Decimal::ONE);
+        position.apply_fill(Side::BUY, Decimal::from(90), Decimal::from(3));
+        assert_eq!(position.realized_pnl, Decimal::from(10));
+        assert_eq!(position.size, Decimal::TWO);
+        assert_eq!(position.average_entry, Decimal::from(90));
+    }
+}
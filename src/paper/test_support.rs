@@ -0,0 +1,141 @@
+//! 策略与订单管理测试共用的模拟撮合环境
+//!
+//! 订单推送写入 [`OrderTracker`]、持仓推送写入 [`PositionCache`]，并对推送计数；
+//! 测试以 `tokio::join!` 在同一任务中运行策略与脚本，脚本通过 [`PaperMarket::wait_for`] /
+//! [`PaperMarket::settle`] 让出执行权等待策略作出反应，不按墙钟时间轮询。
+
+use chrono::Utc;
+use paradex::structs::Side;
+use rust_decimal::Decimal;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use super::PaperExchange;
+use crate::client_id::ClientIdGenerator;
+use crate::market_data::{BboCache, BboEvent, MarketEvent, TradeEvent};
+use crate::onboarding::ParadexConfig;
+use crate::orders::OrderFactory;
+use crate::positions::PositionCache;
+use crate::trading::{OrderManager, OrderTracker};
+
+/// 测试使用的市场
+pub(crate) const MARKET: &str = "BTC-USD-PERP";
+
+/// 等待的上限：让出执行权的轮数；每轮同一任务中的策略都会被轮询一次
+const WAIT_ROUNDS: usize = 1_000;
+
+/// `strategy` 前缀的订单工厂
+pub(crate) fn factory(strategy: &str) -> OrderFactory {
+    OrderFactory::new(ClientIdGenerator::new(strategy), &ParadexConfig::testnet())
+}
+
+/// 模拟撮合、订单状态、持仓与行情的组合；克隆后共享同一个模拟交易所
+#[derive(Clone)]
+pub(crate) struct PaperMarket {
+    pub exchange: Arc<PaperExchange>,
+    pub tracker: OrderTracker,
+    pub positions: PositionCache,
+    pub quotes: BboCache,
+    /// 模拟交易所推送的消息数
+    updates: Arc<AtomicU64>,
+}
+
+impl PaperMarket {
+    pub fn new() -> Self {
+        let tracker = OrderTracker::new();
+        let positions = PositionCache::new(Decimal::ZERO);
+        let updates = Arc::new(AtomicU64::new(0));
+        let (listener, cache, counter) = (tracker.clone(), positions.clone(), updates.clone());
+        let exchange = PaperExchange::new("0xabc", Decimal::from(10_000)).with_listener(Box::new(
+            move |message| {
+                listener.on_message(message);
+                cache.on_message(message);
+                counter.fetch_add(1, Ordering::SeqCst);
+            },
+        ));
+        Self {
+            exchange: Arc::new(exchange),
+            tracker,
+            positions,
+            quotes: BboCache::new(),
+            updates,
+        }
+    }
+
+    /// 直接经模拟交易所下单的订单管理器，订单 client_id 以 `strategy` 为前缀
+    pub fn manager(&self, strategy: &str) -> OrderManager<'_> {
+        OrderManager::new(
+            self.exchange.as_ref(),
+            self.tracker.clone(),
+            factory(strategy),
+        )
+    }
+
+    /// 以 `bid` / `ask` 更新报价缓存与模拟交易所，返回对应的行情事件供策略处理
+    pub fn bbo(&self, bid: Decimal, ask: Decimal) -> MarketEvent {
+        let event = MarketEvent::Bbo(BboEvent {
+            symbol: MARKET.to_string(),
+            bid,
+            bid_size: Decimal::ONE,
+            ask,
+            ask_size: Decimal::ONE,
+            exchange_ts: Utc::now(),
+            local_ts: Utc::now(),
+        });
+        self.quotes.on_event(&event);
+        self.exchange.on_event(&event);
+        event
+    }
+
+    /// 以 `price` 成交 `size` 的市场卖单撮合挂单，返回对应的行情事件供策略处理
+    pub fn trade(&self, price: Decimal, size: Decimal) -> MarketEvent {
+        let event = MarketEvent::Trade(TradeEvent {
+            symbol: MARKET.to_string(),
+            id: "t".to_string(),
+            price,
+            size,
+            side: Side::SELL,
+            exchange_ts: Utc::now(),
+            local_ts: Utc::now(),
+        });
+        self.exchange.on_event(&event);
+        event
+    }
+
+    /// 挂出中的限价单（方向, 价格），按价格排序
+    pub fn resting(&self) -> Vec<(Side, Decimal)> {
+        let mut orders: Vec<_> = self
+            .exchange
+            .open_orders()
+            .into_iter()
+            .filter_map(|order| Some((order.side, order.price?)))
+            .collect();
+        orders.sort_by_key(|(_, price)| *price);
+        orders
+    }
+
+    /// 等待 `condition` 成立：每轮让出一次执行权，使策略处理已到达的推送与唤醒；
+    /// `WAIT_ROUNDS` 轮后仍不成立则失败
+    pub async fn wait_for(&self, what: &str, condition: impl Fn() -> bool) {
+        for _ in 0..WAIT_ROUNDS {
+            if condition() {
+                return;
+            }
+            tokio::task::yield_now().await;
+        }
+        panic!("timed out waiting for {what}");
+    }
+
+    /// 让出执行权直到一轮中没有新的推送，即策略已处理完唤醒及其引发的订单变化；
+    /// 用于断言某个事件不会引起挂单变化
+    pub async fn settle(&self) {
+        for _ in 0..WAIT_ROUNDS {
+            let seen = self.updates.load(Ordering::SeqCst);
+            tokio::task::yield_now().await;
+            if self.updates.load(Ordering::SeqCst) == seen {
+                return;
+            }
+        }
+        panic!("paper exchange kept pushing updates");
+    }
+}
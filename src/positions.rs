@@ -0,0 +1,450 @@
+//! 持仓缓存（WebSocket 实时更新、定期以 REST 对账）与平仓：由持仓计算反向的只减仓订单
+
+use log::error;
+use paradex::{
+    structs::{Position, PositionSide, PositionStatus, Side},
+    ws::Message,
+};
+use rust_decimal::Decimal;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use std::sync::{Arc, RwLock};
+
+use crate::markets::decimal;
+use crate::metrics::metrics;
+
+/// 对账时 WebSocket 持仓与 REST 持仓的一处偏差
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PositionDrift {
+    pub market: String,
+    /// 对账前缓存中的数量（空头为负）
+    pub cached: Decimal,
+    pub rest: Decimal,
+}
+
+/// 各市场的最新持仓：持仓频道实时更新，对账时以 REST 快照为准；克隆后共享同一份数据
+#[derive(Debug, Clone)]
+pub struct PositionCache {
+    positions: Arc<RwLock<HashMap<String, Position>>>,
+    /// 数量差异超过该值时视为偏差
+    tolerance: Decimal,
+}
+
+impl PositionCache {
+    /// 数量绝对值不超过 `tolerance` 的持仓视为已平仓
+    pub fn new(tolerance: Decimal) -> Self {
+        Self {
+            positions: Arc::default(),
+            tolerance,
+        }
+    }
+
+    /// 由 REST 快照建立的缓存
+    pub fn from_snapshot(tolerance: Decimal, snapshot: &[Position]) -> Self {
+        let cache = Self::new(tolerance);
+        *cache.positions.write().unwrap() = snapshot
+            .iter()
+            .map(|position| (position.market.clone(), position.clone()))
+            .collect();
+        cache
+    }
+
+    /// 处理持仓频道的消息，其他消息忽略
+    pub fn on_message(&self, message: &Message) {
+        if let Message::Position(position) = message {
+            self.update(position);
+        }
+    }
+
+    /// 应用一条持仓推送；`seq_no` 早于缓存（含 REST 快照）的推送被忽略，返回 `false`
+    pub fn update(&self, position: &Position) -> bool {
+        let mut positions = self.positions.write().unwrap();
+        if positions
+            .get(&position.market)
+            .is_some_and(|cached| cached.seq_no > position.seq_no)
+        {
+            return false;
+        }
+        metrics().set_position(&position.market, position.size);
+        positions.insert(position.market.clone(), position.clone());
+        true
+    }
+
+    /// `symbol` 的持仓（可能已平仓）
+    pub fn get(&self, symbol: &str) -> Option<Position> {
+        self.positions.read().unwrap().get(symbol).cloned()
+    }
+
+    /// 未平仓的持仓
+    fn open(&self, symbol: &str) -> Option<Position> {
+        self.get(symbol)
+            .filter(|position| position.status != PositionStatus::CLOSED)
+    }
+
+    /// 持仓数量，空头为负；没有持仓时为 0
+    pub fn size(&self, symbol: &str) -> Decimal {
+        self.open(symbol)
+            .map_or(Decimal::ZERO, |position| decimal(position.size))
+    }
+
+    /// 持仓均价；没有持仓时为 `None`
+    pub fn entry_price(&self, symbol: &str) -> Option<Decimal> {
+        self.open(symbol)
+            .filter(|position| position.size != 0.0)
+            .map(|position| decimal(position.average_entry_price))
+    }
+
+    /// 按标记价格 `mark` 计算的未实现盈亏；没有持仓时为 0
+    pub fn unrealized_pnl(&self, symbol: &str, mark: Decimal) -> Decimal {
+        match self.entry_price(symbol) {
+            Some(entry) => self.size(symbol) * (mark - entry),
+            None => Decimal::ZERO,
+        }
+    }
+
+    /// 以 REST 快照替换缓存，返回数量差异超过容差的市场；偏差记录错误日志并计入指标
+    pub fn reconcile(&self, snapshot: &[Position]) -> Vec<PositionDrift> {
+        let rest: HashMap<&str, &Position> = snapshot
+            .iter()
+            .map(|position| (position.market.as_str(), position))
+            .collect();
+        let size = |position: Option<&Position>| {
+            position
+                .filter(|position| position.status != PositionStatus::CLOSED)
+                .map_or(Decimal::ZERO, |position| decimal(position.size))
+        };
+
+        let mut positions = self.positions.write().unwrap();
+        let markets: BTreeSet<&str> = positions
+            .keys()
+            .map(String::as_str)
+            .chain(rest.keys().copied())
+            .collect();
+        let drifts: Vec<PositionDrift> = markets
+            .into_iter()
+            .filter_map(|market| {
+                let cached = size(positions.get(market));
+                let rest = size(rest.get(market).copied());
+                ((cached - rest).abs() > self.tolerance).then(|| PositionDrift {
+                    market: market.to_string(),
+                    cached,
+                    rest,
+                })
+            })
+            .collect();
+        for drift in &drifts {
+            error!(
+                "Position drift on {}: websocket {} vs REST {}, using REST",
+                drift.market, drift.cached, drift.rest
+            );
+            metrics().position_drift(&drift.market);
+            if !rest.contains_key(drift.market.as_str()) {
+                metrics().set_position(&drift.market, 0.0);
+            }
+        }
+
+        *positions = snapshot
+            .iter()
+            .map(|position| (position.market.clone(), position.clone()))
+            .collect();
+        for position in snapshot {
+            metrics().set_position(&position.market, position.size);
+        }
+        drifts
+    }
+
+    /// 缓存的持仓数（含已平仓）
+    pub fn len(&self) -> usize {
+        self.positions.read().unwrap().len()
+    }
+
+    /// 是否没有缓存任何持仓
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// 平仓所需的反向订单方向与数量
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClosingOrder {
+    pub market: String,
+    pub side: Side,
+    pub size: Decimal,
+}
+
+/// 持仓的反向订单；已平仓或数量为 0 时为 `None`
+///
+/// Paradex 空头持仓的 `size` 为负数，这里取绝对值，方向以 `side` 为准。
+pub fn closing_order(position: &Position) -> Option<ClosingOrder> {
+    let size = decimal(position.size).abs();
+    if position.status == PositionStatus::CLOSED || size.is_zero() {
+        return None;
+    }
+    let side = match position.side {
+        PositionSide::LONG => Side::SELL,
+        PositionSide::SHORT => Side::BUY,
+    };
+    Some(ClosingOrder {
+        market: position.market.clone(),
+        side,
+        size,
+    })
+}
+
+/// `market` 的未平仓数量；没有持仓时为 0
+pub fn open_size(positions: &[Position], market: &str) -> Decimal {
+    positions
+        .iter()
+        .filter(|position| position.market == market)
+        .filter_map(closing_order)
+        .map(|order| order.size)
+        .sum()
+}
+
+/// 有未平持仓的市场，按快照中的顺序
+pub fn open_markets(positions: &[Position]) -> Vec<String> {
+    positions
+        .iter()
+        .filter_map(closing_order)
+        .map(|order| order.market)
+        .collect()
+}
+
+/// 单个市场的平仓结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CloseOutcome {
+    /// 没有持仓，视为成功
+    AlreadyFlat,
+    /// 已下单且持仓归零
+    Closed {
+        side: Side,
+        size: Decimal,
+    },
+    /// dry-run：只记录了平仓请求
+    DryRun {
+        side: Side,
+        size: Decimal,
+    },
+    /// 超时后仍有剩余持仓
+    TimedOut {
+        remaining: Decimal,
+    },
+    Failed(String),
+}
+
+impl CloseOutcome {
+    /// 是否已平仓（或无需平仓）
+    pub fn is_success(&self) -> bool {
+        matches!(
+            self,
+            CloseOutcome::AlreadyFlat | CloseOutcome::Closed { .. } | CloseOutcome::DryRun { .. }
+        )
+    }
+}
+
+impl fmt::Display for CloseOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CloseOutcome::AlreadyFlat => write!(f, "already flat"),
+            CloseOutcome::Closed { side, size } => write!(f, "closed ({:?} {})", side, size),
+            CloseOutcome::DryRun { side, size } => {
+                write!(f, "dry run ({:?} {} not sent)", side, size)
+            }
+            CloseOutcome::TimedOut { remaining } => {
+                write!(f, "timed out, {} still open", remaining)
+            }
+            CloseOutcome::Failed(reason) => write!(f, "failed: {}", reason),
+        }
+    }
+}
+
+/// 每个市场一行的平仓汇总
+pub fn format_summary(outcomes: &[(String, CloseOutcome)]) -> String {
+    let width = outcomes
+        .iter()
+        .map(|(market, _)| market.len())
+        .max()
+        .unwrap_or_default();
+    outcomes
+        .iter()
+        .map(|(market, outcome)| format!("{:<width$}  {}", market, outcome, width = width))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn position(market: &str, side: &str, size: &str, status: &str) -> Position {
+        serde_json::from_value(serde_json::json!({
+            "average_entry_price": "95000",
+            "average_entry_price_usd": "95000",
+            "cached_funding_index": "0",
+            "cost": "0",
+            "cost_usd": "0",
+            "id": "1",
+            "last_fill_id": "1",
+            "last_updated_at": 0,
+            "leverage": "",
+            "liquidation_price": "",
+            "market": market,
+            "seq_no": 1,
+            "side": side,
+            "size": size,
+            "status": status,
+            "unrealized_funding_pnl": "0",
+            "unrealized_pnl": "0"
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn closing_order_is_opposite_of_position() {
+        assert_eq!(
+            closing_order(&position("BTC-USD-PERP", "LONG", "0.015", "OPEN")),
+            Some(ClosingOrder {
+                market: "BTC-USD-PERP".to_string(),
+                side: Side::SELL,
+                size: Decimal::new(15, 3),
+            })
+        );
+        // 空头数量为负
+        assert_eq!(
+            closing_order(&position("ETH-USD-PERP", "SHORT", "-0.3", "OPEN")),
+            Some(ClosingOrder {
+                market: "ETH-USD-PERP".to_string(),
+                side: Side::BUY,
+                size: Decimal::new(3, 1),
+            })
+        );
+    }
+
+    #[test]
+    fn flat_positions_need_no_order() {
+        assert_eq!(
+            closing_order(&position("BTC-USD-PERP", "LONG", "0", "OPEN")),
+            None
+        );
+        assert_eq!(
+            closing_order(&position("BTC-USD-PERP", "LONG", "0.1", "CLOSED")),
+            None
+        );
+
+        let positions = [
+            position("BTC-USD-PERP", "LONG", "0.1", "OPEN"),
+            position("ETH-USD-PERP", "SHORT", "0", "OPEN"),
+        ];
+        assert_eq!(open_size(&positions, "BTC-USD-PERP"), Decimal::new(1, 1));
+        assert_eq!(open_size(&positions, "ETH-USD-PERP"), Decimal::ZERO);
+        assert_eq!(open_size(&positions, "SOL-USD-PERP"), Decimal::ZERO);
+        assert_eq!(open_markets(&positions), ["BTC-USD-PERP"]);
+    }
+
+    #[test]
+    fn summary_lists_each_market() {
+        let outcomes = [
+            (
+                "BTC-USD-PERP".to_string(),
+                CloseOutcome::Closed {
+                    side: Side::SELL,
+                    size: Decimal::new(15, 3),
+                },
+            ),
+            ("ETH-USD-PERP".to_string(), CloseOutcome::AlreadyFlat),
+            (
+                "SOL-USD-PERP".to_string(),
+                CloseOutcome::TimedOut {
+                    remaining: Decimal::ONE,
+                },
+            ),
+        ];
+        assert_eq!(
+            format_summary(&outcomes),
+            "BTC-USD-PERP  closed (SELL 0.015)\n\
+             ETH-USD-PERP  already flat\n\
+             SOL-USD-PERP  timed out, 1 still open"
+        );
+        assert!(outcomes[1].1.is_success());
+        assert!(!outcomes[2].1.is_success());
+    }
+
+    fn update(size: &str, seq_no: u64) -> Position {
+        let side = if size.starts_with('-') {
+            "SHORT"
+        } else {
+            "LONG"
+        };
+        let mut position = position("BTC-USD-PERP", side, size, "OPEN");
+        position.seq_no = seq_no;
+        position
+    }
+
+    #[test]
+    fn cache_exposes_size_entry_and_unrealized_pnl() {
+        let cache = PositionCache::new(Decimal::new(1, 8));
+        cache.on_message(&Message::Position(update("0.2", 2)));
+        // 迟到的旧推送被忽略
+        assert!(!cache.update(&update("0.1", 1)));
+
+        assert_eq!(cache.size("BTC-USD-PERP"), Decimal::new(2, 1));
+        assert_eq!(
+            cache.entry_price("BTC-USD-PERP"),
+            Some(Decimal::from(95000))
+        );
+        assert_eq!(
+            cache.unrealized_pnl("BTC-USD-PERP", Decimal::from(96000)),
+            Decimal::from(200)
+        );
+
+        cache.update(&update("-0.1", 3));
+        assert_eq!(
+            cache.unrealized_pnl("BTC-USD-PERP", Decimal::from(96000)),
+            Decimal::from(-100)
+        );
+        assert_eq!(cache.size("ETH-USD-PERP"), Decimal::ZERO);
+        assert_eq!(cache.entry_price("ETH-USD-PERP"), None);
+        assert_eq!(
+            cache.unrealized_pnl("ETH-USD-PERP", Decimal::from(3300)),
+            Decimal::ZERO
+        );
+    }
+
+    #[test]
+    fn reconcile_corrects_missed_websocket_updates() {
+        let cache = PositionCache::new(Decimal::new(1, 8));
+        let shared = cache.clone();
+        cache.update(&update("0.2", 2));
+        // 漏掉了 seq_no 3 的减仓推送；ETH 持仓只出现在 REST 中
+        let snapshot = [
+            update("0.05", 3),
+            position("ETH-USD-PERP", "SHORT", "-1.5", "OPEN"),
+        ];
+        let drifts = cache.reconcile(&snapshot);
+        assert_eq!(
+            drifts,
+            [
+                PositionDrift {
+                    market: "BTC-USD-PERP".to_string(),
+                    cached: Decimal::new(2, 1),
+                    rest: Decimal::new(5, 2),
+                },
+                PositionDrift {
+                    market: "ETH-USD-PERP".to_string(),
+                    cached: Decimal::ZERO,
+                    rest: Decimal::new(-15, 1),
+                },
+            ]
+        );
+        assert_eq!(shared.size("BTC-USD-PERP"), Decimal::new(5, 2));
+        assert_eq!(shared.size("ETH-USD-PERP"), Decimal::new(-15, 1));
+        assert!(metrics()
+            .render()
+            .contains("position_drift_total{symbol=\"BTC-USD-PERP\"}"));
+
+        // 对账后早于快照的推送不会覆盖 REST 结果，一致时没有偏差
+        assert!(!cache.update(&update("0.2", 2)));
+        assert!(cache.reconcile(&snapshot).is_empty());
+        assert_eq!(cache.len(), 2);
+    }
+}
@@ -0,0 +1,837 @@
+//! 行情录制：公开频道消息按频道与市场写入 JSONL 文件，每行附本地接收时间
+//!
+//! WS 回调只把消息放入通道，由独立的写入任务序列化并缓冲写盘；文件按小时（UTC）滚动，
+//! 如 `trades_BTC-USD-PERP.2025-01-01T00.jsonl`，同一小时内超过大小上限时再滚动出分段
+//! `trades_BTC-USD-PERP.2025-01-01T00.part001.jsonl`。可选以 gzip 或 zstd 流式压缩（扩展名追加 `.gz` / `.zst`）；
+//! 每个分段关闭时在目录下的 `index.jsonl` 追加一行，记录其接收时间范围，重放时据此跳过起始时间之前的分段。合成的 K 线写入 `candles/` 子目录（重放时不读取），
+//! 如 `candles/candles_1m_BTC-USD-PERP.2025-01-01T00.jsonl`；本地订单簿的定期快照写入 `snapshots/` 子目录，
+//! 如 `snapshots/snapshots_BTC-USD-PERP.2025-01-01T00.jsonl`，每行附最后应用的序号以便与增量流对齐。
+
+use async_compression::tokio::write::{GzipEncoder, ZstdEncoder};
+use chrono::{TimeZone, Utc};
+use log::{error, info};
+use paradex::ws::Message;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as Json;
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::fs::OpenOptions;
+use tokio::io::{AsyncWrite, AsyncWriteExt, BufWriter};
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+
+use crate::config::WsChannel;
+use crate::market_data::{Candle, OrderBooks};
+use crate::orderbook::OrderBookSnapshot;
+
+/// 录制文件的扩展名
+pub const RECORD_EXTENSION: &str = "jsonl";
+/// 分段索引的文件名（重放时不作为录制文件读取）
+pub const INDEX_FILE: &str = "index.jsonl";
+/// 单个录制文件的默认大小上限
+pub const DEFAULT_MAX_FILE_BYTES: u64 = 256 * 1024 * 1024;
+/// 写入任务定期刷盘的间隔
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+const HOUR_MILLIS: u64 = 3_600_000;
+/// K 线文件所在的子目录
+const CANDLE_DIR: &str = "candles";
+/// 订单簿快照文件所在的子目录
+const SNAPSHOT_DIR: &str = "snapshots";
+/// 订单簿快照的频道名
+const SNAPSHOT_CHANNEL: &str = "snapshots";
+
+/// 录制文件的压缩格式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum Compression {
+    #[default]
+    None,
+    Gzip,
+    Zstd,
+}
+
+impl Compression {
+    /// 追加在 `.jsonl` 之后的扩展名
+    pub fn suffix(self) -> &'static str {
+        match self {
+            Compression::None => "",
+            Compression::Gzip => ".gz",
+            Compression::Zstd => ".zst",
+        }
+    }
+
+    /// 按文件名判断录制文件的压缩格式；不是录制文件（包括分段索引）时为 `None`
+    pub fn of_file(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_str()?;
+        if name == INDEX_FILE {
+            return None;
+        }
+        [Compression::None, Compression::Gzip, Compression::Zstd]
+            .into_iter()
+            .find(|compression| {
+                name.ends_with(&format!(".{}{}", RECORD_EXTENSION, compression.suffix()))
+            })
+    }
+}
+
+/// 录制文件的压缩与滚动参数
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RecordOptions {
+    pub compression: Compression,
+    /// 文件（压缩后）达到该字节数时滚动到下一个分段
+    pub max_file_bytes: u64,
+}
+
+impl Default for RecordOptions {
+    fn default() -> Self {
+        Self {
+            compression: Compression::None,
+            max_file_bytes: DEFAULT_MAX_FILE_BYTES,
+        }
+    }
+}
+
+/// 分段索引中的一行：一个已关闭的录制分段
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SegmentEntry {
+    /// 相对录制目录的路径
+    pub file: String,
+    /// 分段中第一行与最后一行的接收时间（毫秒时间戳）
+    pub first_received_at: u64,
+    pub last_received_at: u64,
+    pub lines: u64,
+}
+
+/// 读取录制目录的分段索引；同一文件多次出现（重启后追加写入）时合并时间范围。
+/// 索引不存在时为空
+pub fn read_index(dir: &Path) -> io::Result<HashMap<String, SegmentEntry>> {
+    let contents = match std::fs::read_to_string(dir.join(INDEX_FILE)) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(HashMap::new()),
+        Err(e) => return Err(e),
+    };
+    let mut segments: HashMap<String, SegmentEntry> = HashMap::new();
+    for line in contents.lines().filter(|line| !line.trim().is_empty()) {
+        let entry: SegmentEntry = serde_json::from_str(line)?;
+        match segments.get_mut(&entry.file) {
+            Some(merged) => {
+                merged.first_received_at = merged.first_received_at.min(entry.first_received_at);
+                merged.last_received_at = merged.last_received_at.max(entry.last_received_at);
+                merged.lines += entry.lines;
+            }
+            None => {
+                segments.insert(entry.file.clone(), entry);
+            }
+        }
+    }
+    Ok(segments)
+}
+
+/// 录制文件中的一行
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordedLine {
+    /// 本地接收时间（毫秒时间戳）
+    pub received_at: u64,
+    /// 频道名，与 `--channels` 中的名称一致
+    pub channel: String,
+    /// 按市场订阅的频道对应的市场；行情摘要等全市场频道为 `None`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub market: Option<String>,
+    /// 消息内容，即 paradex 对应结构体的 JSON
+    pub data: Json,
+}
+
+/// 公开行情消息的内容；控制消息与私有频道消息不录制
+pub fn message_payload(message: &Message) -> Option<Json> {
+    let payload = match message {
+        Message::BBO(bbo) => serde_json::to_value(bbo),
+        Message::MarketSummary(summary) => serde_json::to_value(summary),
+        Message::OrderBook(book) | Message::OrderBookDeltas(book) => serde_json::to_value(book),
+        Message::Trades(trade) => serde_json::to_value(trade),
+        Message::FundingData(funding) => serde_json::to_value(funding),
+        _ => return None,
+    };
+    payload.ok()
+}
+
+/// 频道与市场对应的文件名前缀，如 `trades_BTC-USD-PERP`
+fn file_stem(channel: &str, market: Option<&str>) -> String {
+    match market {
+        Some(market) => format!("{}_{}", channel, market),
+        None => channel.to_string(),
+    }
+}
+
+/// `received_at` 所在的小时，如 `2025-01-01T00`
+fn hour_label(received_at: u64) -> String {
+    Utc.timestamp_millis_opt(received_at as i64)
+        .single()
+        .map_or_else(
+            || (received_at / HOUR_MILLIS).to_string(),
+            |time| time.format("%Y-%m-%dT%H").to_string(),
+        )
+}
+
+/// 分段的文件名；第一个分段不带序号，按文件名排序时分段保持先后顺序
+fn file_name(stem: &str, hour: &str, segment: u32, compression: Compression) -> String {
+    let part = match segment {
+        0 => String::new(),
+        n => format!(".part{:03}", n),
+    };
+    format!(
+        "{}.{}{}.{}{}",
+        stem,
+        hour,
+        part,
+        RECORD_EXTENSION,
+        compression.suffix()
+    )
+}
+
+fn unix_now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64
+}
+
+struct Pending {
+    received_at: u64,
+    record: Record,
+}
+
+enum Record {
+    Message {
+        channel: WsChannel,
+        market: Option<String>,
+        message: Message,
+    },
+    Candle(Candle),
+    Snapshot(OrderBookSnapshot),
+}
+
+/// WS 回调中使用的录制入口，可随回调克隆
+#[derive(Clone)]
+pub struct RecordHandle {
+    sender: mpsc::UnboundedSender<Pending>,
+}
+
+impl RecordHandle {
+    /// 记录一条消息（附当前时间）；不阻塞，写入任务结束后静默丢弃
+    pub fn record(&self, channel: WsChannel, market: Option<&str>, message: &Message) {
+        self.record_at(unix_now_millis(), channel, market, message);
+    }
+
+    pub(crate) fn record_at(
+        &self,
+        received_at: u64,
+        channel: WsChannel,
+        market: Option<&str>,
+        message: &Message,
+    ) {
+        let _ = self.sender.send(Pending {
+            received_at,
+            record: Record::Message {
+                channel,
+                market: market.map(str::to_string),
+                message: message.clone(),
+            },
+        });
+    }
+
+    /// 记录一根收盘的 K 线，频道名为 `candles_<周期>`
+    pub fn record_candle(&self, candle: &Candle) {
+        self.record_candle_at(unix_now_millis(), candle);
+    }
+
+    fn record_candle_at(&self, received_at: u64, candle: &Candle) {
+        let _ = self.sender.send(Pending {
+            received_at,
+            record: Record::Candle(candle.clone()),
+        });
+    }
+
+    /// 记录一份订单簿快照，频道名为 `snapshots`
+    pub fn record_snapshot(&self, snapshot: OrderBookSnapshot) {
+        self.record_snapshot_at(unix_now_millis(), snapshot);
+    }
+
+    fn record_snapshot_at(&self, received_at: u64, snapshot: OrderBookSnapshot) {
+        let _ = self.sender.send(Pending {
+            received_at,
+            record: Record::Snapshot(snapshot),
+        });
+    }
+
+    /// 每隔 `interval` 为 `markets` 的本地订单簿各记录一份每侧 `depth` 档的快照
+    pub fn spawn_snapshots(
+        &self,
+        books: OrderBooks,
+        markets: Vec<String>,
+        interval: Duration,
+        depth: usize,
+    ) -> JoinHandle<()> {
+        let handle = self.clone();
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(interval).await;
+                handle.snapshot_books(&books, &markets, depth);
+            }
+        })
+    }
+
+    /// 在一次读锁内取快照，避免与增量交错；未同步的订单簿跳过
+    fn snapshot_books(&self, books: &OrderBooks, markets: &[String], depth: usize) {
+        for market in markets {
+            let Some(book) = books.get(market) else {
+                continue;
+            };
+            let snapshot = {
+                let book = book.read().unwrap();
+                if !book.is_synced() {
+                    info!(
+                        "Skipping {} order book snapshot: book is not synced",
+                        market
+                    );
+                    continue;
+                }
+                book.snapshot(depth)
+            };
+            self.record_snapshot(snapshot);
+        }
+    }
+}
+
+/// 录制器：持有写入任务，退出前调用 [`Recorder::finish`] 刷盘
+pub struct Recorder {
+    handle: RecordHandle,
+    stop: oneshot::Sender<()>,
+    task: JoinHandle<io::Result<u64>>,
+}
+
+impl Recorder {
+    /// 创建目录并以默认参数（不压缩）启动写入任务
+    pub fn start(dir: &Path) -> io::Result<Self> {
+        Self::start_with(dir, RecordOptions::default())
+    }
+
+    /// 创建目录并启动写入任务
+    pub fn start_with(dir: &Path, options: RecordOptions) -> io::Result<Self> {
+        std::fs::create_dir_all(dir)?;
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let (stop, stopped) = oneshot::channel();
+        let task = tokio::spawn(write_records(dir.to_path_buf(), options, receiver, stopped));
+        info!(
+            "Recording market data to {} (compression {:?}, {} MB per file)",
+            dir.display(),
+            options.compression,
+            options.max_file_bytes / (1024 * 1024)
+        );
+        Ok(Self {
+            handle: RecordHandle { sender },
+            stop,
+            task,
+        })
+    }
+
+    /// 写入记录的句柄，可克隆后交给多个订阅
+    pub fn handle(&self) -> RecordHandle {
+        self.handle.clone()
+    }
+
+    /// 停止接收，写完已收到的消息并刷盘关闭文件；返回写入的行数
+    pub async fn finish(self) -> io::Result<u64> {
+        let _ = self.stop.send(());
+        self.task.await.map_err(io::Error::other)?
+    }
+}
+
+/// 统计写入底层文件的（压缩后）字节数
+struct Counting<W> {
+    inner: W,
+    written: Arc<AtomicU64>,
+}
+
+impl<W: AsyncWrite + Unpin> AsyncWrite for Counting<W> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let poll = Pin::new(&mut self.inner).poll_write(cx, buf);
+        if let Poll::Ready(Ok(written)) = poll {
+            self.written.fetch_add(written as u64, Ordering::Relaxed);
+        }
+        poll
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
+/// 单个频道 / 市场当前打开的分段
+struct OpenFile {
+    /// 相对录制目录的文件名
+    name: String,
+    hour: String,
+    segment: u32,
+    writer: Box<dyn AsyncWrite + Send + Sync + Unpin>,
+    /// 文件的字节数（含追加写入前已有的内容）
+    written: Arc<AtomicU64>,
+    first_received_at: u64,
+    last_received_at: u64,
+    lines: u64,
+}
+
+impl OpenFile {
+    /// 刷盘关闭，并在分段索引中记录该分段
+    async fn close(mut self, dir: &Path) -> io::Result<()> {
+        self.writer.shutdown().await?;
+        let entry = SegmentEntry {
+            file: self.name,
+            first_received_at: self.first_received_at,
+            last_received_at: self.last_received_at,
+            lines: self.lines,
+        };
+        let mut line = serde_json::to_vec(&entry)?;
+        line.push(b'\n');
+        let mut index = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(dir.join(INDEX_FILE))
+            .await?;
+        index.write_all(&line).await?;
+        // tokio 的文件写入在后台完成，刷盘后索引才对随后的读取可见
+        index.flush().await
+    }
+}
+
+struct Writer {
+    dir: PathBuf,
+    options: RecordOptions,
+    files: HashMap<String, OpenFile>,
+    lines: u64,
+}
+
+impl Writer {
+    /// 打开 `stem` 在 `hour` 内从 `segment` 起第一个未写满的分段（重启后追加写入）
+    async fn open(&self, stem: &str, hour: String, mut segment: u32) -> io::Result<OpenFile> {
+        loop {
+            let name = file_name(stem, &hour, segment, self.options.compression);
+            let path = self.dir.join(&name);
+            let existing = match tokio::fs::metadata(&path).await {
+                Ok(metadata) => metadata.len(),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => 0,
+                Err(e) => return Err(e),
+            };
+            if existing >= self.options.max_file_bytes {
+                segment += 1;
+                continue;
+            }
+            if let Some(parent) = path.parent() {
+                tokio::fs::create_dir_all(parent).await?;
+            }
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .await?;
+            let written = Arc::new(AtomicU64::new(existing));
+            let counting = Counting {
+                inner: BufWriter::new(file),
+                written: written.clone(),
+            };
+            let writer: Box<dyn AsyncWrite + Send + Sync + Unpin> = match self.options.compression {
+                Compression::None => Box::new(counting),
+                Compression::Gzip => Box::new(GzipEncoder::new(counting)),
+                Compression::Zstd => Box::new(ZstdEncoder::new(counting)),
+            };
+            return Ok(OpenFile {
+                name,
+                hour,
+                segment,
+                writer,
+                written,
+                first_received_at: 0,
+                last_received_at: 0,
+                lines: 0,
+            });
+        }
+    }
+
+    async fn write(&mut self, pending: Pending) -> io::Result<()> {
+        let (line, stem) = match pending.record {
+            Record::Message {
+                channel,
+                market,
+                message,
+            } => {
+                let Some(data) = message_payload(&message) else {
+                    return Ok(());
+                };
+                let channel = channel.cli_name().to_string();
+                let stem = file_stem(&channel, market.as_deref());
+                (
+                    RecordedLine {
+                        received_at: pending.received_at,
+                        channel,
+                        market,
+                        data,
+                    },
+                    stem,
+                )
+            }
+            Record::Candle(candle) => {
+                let channel = format!("candles_{}", candle.interval);
+                let stem = format!(
+                    "{}/{}",
+                    CANDLE_DIR,
+                    file_stem(&channel, Some(&candle.market))
+                );
+                (
+                    RecordedLine {
+                        received_at: pending.received_at,
+                        channel,
+                        market: Some(candle.market.clone()),
+                        data: serde_json::to_value(&candle)?,
+                    },
+                    stem,
+                )
+            }
+            Record::Snapshot(snapshot) => {
+                let stem = format!(
+                    "{}/{}",
+                    SNAPSHOT_DIR,
+                    file_stem(SNAPSHOT_CHANNEL, Some(&snapshot.market))
+                );
+                (
+                    RecordedLine {
+                        received_at: pending.received_at,
+                        channel: SNAPSHOT_CHANNEL.to_string(),
+                        market: Some(snapshot.market.clone()),
+                        data: serde_json::to_value(&snapshot)?,
+                    },
+                    stem,
+                )
+            }
+        };
+        let hour = hour_label(line.received_at);
+        let next_segment = match self.files.get(&stem) {
+            None => Some(0),
+            // 跨小时：从新小时的第一个分段开始
+            Some(file) if file.hour != hour => Some(0),
+            // 超过大小上限：滚动到下一个分段
+            Some(file) if file.written.load(Ordering::Relaxed) >= self.options.max_file_bytes => {
+                Some(file.segment + 1)
+            }
+            Some(_) => None,
+        };
+        if let Some(segment) = next_segment {
+            if let Some(previous) = self.files.remove(&stem) {
+                previous.close(&self.dir).await?;
+            }
+            let file = self.open(&stem, hour, segment).await?;
+            self.files.insert(stem.clone(), file);
+        }
+        let mut bytes = serde_json::to_vec(&line)?;
+        bytes.push(b'\n');
+        let file = self.files.get_mut(&stem).expect("file opened above");
+        file.writer.write_all(&bytes).await?;
+        if file.lines == 0 {
+            file.first_received_at = line.received_at;
+        }
+        file.last_received_at = line.received_at;
+        file.lines += 1;
+        self.lines += 1;
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> io::Result<()> {
+        for file in self.files.values_mut() {
+            file.writer.flush().await?;
+        }
+        Ok(())
+    }
+
+    async fn close(&mut self) -> io::Result<()> {
+        for (_, file) in self.files.drain() {
+            file.close(&self.dir).await?;
+        }
+        Ok(())
+    }
+}
+
+async fn write_records(
+    dir: PathBuf,
+    options: RecordOptions,
+    mut receiver: mpsc::UnboundedReceiver<Pending>,
+    mut stopped: oneshot::Receiver<()>,
+) -> io::Result<u64> {
+    let mut writer = Writer {
+        dir,
+        options,
+        files: HashMap::new(),
+        lines: 0,
+    };
+    let mut flush = tokio::time::interval(FLUSH_INTERVAL);
+    let result = async {
+        loop {
+            tokio::select! {
+                pending = receiver.recv() => match pending {
+                    Some(pending) => writer.write(pending).await?,
+                    None => break,
+                },
+                _ = flush.tick() => writer.flush().await?,
+                _ = &mut stopped => break,
+            }
+        }
+        // 回调可能仍持有发送端：关闭通道后写完已排队的消息
+        receiver.close();
+        while let Some(pending) = receiver.recv().await {
+            writer.write(pending).await?;
+        }
+        Ok(())
+    }
+    .await;
+    let closed = writer.close().await;
+    match result.and(closed) {
+        Ok(()) => Ok(writer.lines),
+        Err(e) => {
+            error!("Market data recording stopped: {}", e);
+            Err(e)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::market_data::CandleBuilder;
+    use crate::orderbook::Level;
+    use paradex::structs::{Side, Trade, TradeType, BBO};
+    use rust_decimal::Decimal;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("tlp_recorder_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn trade(id: &str) -> Message {
+        Message::Trades(Trade {
+            created_at: 1735689600000,
+            id: id.to_string(),
+            market: "BTC-USD-PERP".to_string(),
+            price: 95000.5,
+            side: Side::BUY,
+            size: 0.01,
+            trade_type: TradeType::FILL,
+        })
+    }
+
+    fn read_lines(path: &Path) -> Vec<RecordedLine> {
+        std::fs::read_to_string(path)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn messages_are_written_per_channel_and_market() {
+        let dir = temp_dir("channels");
+        let recorder = Recorder::start(&dir).unwrap();
+        let handle = recorder.handle();
+        let at = 1735689600123;
+        handle.record_at(at, WsChannel::Trades, Some("BTC-USD-PERP"), &trade("1"));
+        handle.record_at(at + 1, WsChannel::Trades, Some("BTC-USD-PERP"), &trade("2"));
+        handle.record_at(
+            at + 2,
+            WsChannel::Bbo,
+            Some("BTC-USD-PERP"),
+            &Message::BBO(BBO {
+                bid: 95000.0,
+                bid_size: 1.0,
+                ask: 95001.0,
+                ask_size: 2.0,
+                market: "BTC-USD-PERP".to_string(),
+                last_updated_at: at,
+            }),
+        );
+        handle.record_at(at + 3, WsChannel::Trades, None, &Message::Connected);
+        assert_eq!(recorder.finish().await.unwrap(), 3);
+
+        let trades = read_lines(&dir.join("trades_BTC-USD-PERP.2025-01-01T00.jsonl"));
+        assert_eq!(trades.len(), 2);
+        assert_eq!(trades[0].received_at, at);
+        assert_eq!(trades[0].channel, "trades");
+        assert_eq!(trades[1].data["id"], "2");
+        assert_eq!(trades[1].data["price"], "95000.5");
+
+        let bbo = read_lines(&dir.join("bbo_BTC-USD-PERP.2025-01-01T00.jsonl"));
+        assert_eq!(bbo[0].data["ask"], "95001");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn candles_are_written_to_a_subdirectory() {
+        let dir = temp_dir("candles");
+        let recorder = Recorder::start(&dir).unwrap();
+        let handle = recorder.handle();
+        let mut builder = CandleBuilder::new(&["1m".parse().unwrap()]);
+        let at = 1735689600000;
+        for message in [trade("1"), trade("2")] {
+            builder.on_message(&message);
+        }
+        let closed = builder.advance_to(at + 60_000);
+        handle.record_candle_at(at + 60_001, &closed[0]);
+        assert_eq!(recorder.finish().await.unwrap(), 1);
+
+        let candles = read_lines(&dir.join("candles/candles_1m_BTC-USD-PERP.2025-01-01T00.jsonl"));
+        assert_eq!(candles[0].channel, "candles_1m");
+        assert_eq!(candles[0].market.as_deref(), Some("BTC-USD-PERP"));
+        assert_eq!(candles[0].data["interval"], "1m");
+        assert_eq!(candles[0].data["start_ts"], at);
+        assert_eq!(candles[0].data["close"], "95000.5");
+        assert_eq!(candles[0].data["volume"], "0.02");
+        assert_eq!(candles[0].data["trade_count"], 2);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn snapshots_skip_unsynced_books() {
+        let dir = temp_dir("snapshots");
+        let recorder = Recorder::start(&dir).unwrap();
+        let handle = recorder.handle();
+        let markets = vec!["BTC-USD-PERP".to_string(), "ETH-USD-PERP".to_string()];
+        let books = OrderBooks::new(&markets);
+        let level = |price: i64, size: i64| Level(Decimal::from(price), Decimal::from(size));
+        books
+            .get("BTC-USD-PERP")
+            .unwrap()
+            .write()
+            .unwrap()
+            .seed(&OrderBookSnapshot {
+                market: "BTC-USD-PERP".to_string(),
+                seq_no: Some(42),
+                last_updated_at: None,
+                bids: vec![level(95000, 1), level(94999, 2), level(94998, 3)],
+                asks: vec![level(95001, 1), level(95002, 2)],
+            });
+        // ETH 的订单簿尚未建立，不写快照
+        handle.snapshot_books(&books, &markets, 2);
+        assert_eq!(recorder.finish().await.unwrap(), 1);
+
+        let files: Vec<_> = std::fs::read_dir(dir.join("snapshots"))
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        assert_eq!(files.len(), 1);
+        assert!(files[0]
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .starts_with("snapshots_BTC-USD-PERP."));
+        let snapshots = read_lines(&files[0]);
+        assert_eq!(snapshots[0].channel, "snapshots");
+        assert_eq!(snapshots[0].data["seq_no"], 42);
+        assert_eq!(snapshots[0].data["bids"].as_array().unwrap().len(), 2);
+        assert_eq!(snapshots[0].data["asks"][1][0], "95002");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn files_roll_over_each_hour() {
+        let dir = temp_dir("roll");
+        let recorder = Recorder::start(&dir).unwrap();
+        let handle = recorder.handle();
+        let at = 1735689600000 + HOUR_MILLIS - 1;
+        handle.record_at(at, WsChannel::Trades, Some("BTC-USD-PERP"), &trade("1"));
+        handle.record_at(at + 1, WsChannel::Trades, Some("BTC-USD-PERP"), &trade("2"));
+        // 发送端仍存活时 finish 也能写完已排队的消息
+        assert_eq!(recorder.finish().await.unwrap(), 2);
+        drop(handle);
+
+        let first = read_lines(&dir.join("trades_BTC-USD-PERP.2025-01-01T00.jsonl"));
+        let second = read_lines(&dir.join("trades_BTC-USD-PERP.2025-01-01T01.jsonl"));
+        assert_eq!(first[0].data["id"], "1");
+        assert_eq!(second[0].data["id"], "2");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn size_limit_rolls_segments_and_indexes_them() {
+        let dir = temp_dir("segments");
+        let options = RecordOptions {
+            compression: Compression::None,
+            max_file_bytes: 1,
+        };
+        let recorder = Recorder::start_with(&dir, options).unwrap();
+        let handle = recorder.handle();
+        let at = 1735689600000;
+        for (offset, id) in ["1", "2", "3"].into_iter().enumerate() {
+            handle.record_at(
+                at + offset as u64,
+                WsChannel::Trades,
+                Some("BTC-USD-PERP"),
+                &trade(id),
+            );
+        }
+        assert_eq!(recorder.finish().await.unwrap(), 3);
+
+        let names = [
+            "trades_BTC-USD-PERP.2025-01-01T00.jsonl",
+            "trades_BTC-USD-PERP.2025-01-01T00.part001.jsonl",
+            "trades_BTC-USD-PERP.2025-01-01T00.part002.jsonl",
+        ];
+        for (name, id) in names.iter().zip(["1", "2", "3"]) {
+            assert_eq!(read_lines(&dir.join(name))[0].data["id"], id);
+        }
+        let index = read_index(&dir).unwrap();
+        assert_eq!(index.len(), 3);
+        assert_eq!(
+            index[names[1]],
+            SegmentEntry {
+                file: names[1].to_string(),
+                first_received_at: at + 1,
+                last_received_at: at + 1,
+                lines: 1,
+            }
+        );
+
+        // 重启后追加到未写满的分段，索引合并同一文件的时间范围
+        let options = RecordOptions {
+            max_file_bytes: DEFAULT_MAX_FILE_BYTES,
+            ..options
+        };
+        let recorder = Recorder::start_with(&dir, options).unwrap();
+        recorder.handle().record_at(
+            at + 10,
+            WsChannel::Trades,
+            Some("BTC-USD-PERP"),
+            &trade("4"),
+        );
+        recorder.finish().await.unwrap();
+        let index = read_index(&dir).unwrap();
+        assert_eq!(index[names[0]].last_received_at, at + 10);
+        assert_eq!(index[names[0]].lines, 2);
+        assert_eq!(Compression::of_file(&dir.join(INDEX_FILE)), None);
+        assert_eq!(
+            Compression::of_file(Path::new("bbo_BTC-USD-PERP.2025-01-01T00.jsonl.zst")),
+            Some(Compression::Zstd)
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
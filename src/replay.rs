@@ -0,0 +1,623 @@
+//! 重放 `--record` 录制的 JSONL 文件：按接收时间合并各文件，并按原始节奏（可加速）推送给订阅回调
+//!
+//! 压缩（`.jsonl.gz` / `.jsonl.zst`）与未压缩的分段可以混合，读取时按扩展名透明解压。设置起始时间后，
+//! 分段索引中结束时间早于起始时间的分段直接跳过，不再打开与解压。
+
+use async_compression::tokio::bufread::{GzipDecoder, ZstdDecoder};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use log::{debug, info};
+use paradex::{
+    error::Error,
+    ws::{Channel, Message},
+};
+use serde_json::Value as Json;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use thiserror::Error;
+use tokio::fs::File;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, BufReader, Lines};
+use tokio::time::Instant;
+use tokio_util::sync::CancellationToken;
+
+use crate::config::WsChannel;
+use crate::market_data::{channel_key, Callback, MarketDataSource, SubscriptionId};
+use crate::recorder::{
+    read_index, Compression, RecordedLine, SegmentEntry, INDEX_FILE, RECORD_EXTENSION,
+};
+
+/// 回放录制文件失败的原因
+#[derive(Debug, Error)]
+pub enum ReplayError {
+    #[error("Failed to read {path}: {source}")]
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    #[error("{path}:{line}: {message}")]
+    Parse {
+        path: PathBuf,
+        line: usize,
+        message: String,
+    },
+    #[error("No .{ext} files (plain or compressed) found in {0}", ext = RECORD_EXTENSION)]
+    Empty(PathBuf),
+}
+
+/// 重放速度：相对录制时间的倍数，或不等待尽快推送
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReplaySpeed {
+    Factor(f64),
+    Max,
+}
+
+impl FromStr for ReplaySpeed {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        if value.eq_ignore_ascii_case("max") {
+            return Ok(ReplaySpeed::Max);
+        }
+        match value.parse::<f64>() {
+            Ok(factor) if factor.is_finite() && factor > 0.0 => Ok(ReplaySpeed::Factor(factor)),
+            _ => Err(format!(
+                "invalid speed {:?}: expected a positive number (e.g. 1.0, 10.0) or \"max\"",
+                value
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ReplaySpeed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplaySpeed::Factor(factor) => write!(f, "{}x", factor),
+            ReplaySpeed::Max => write!(f, "max"),
+        }
+    }
+}
+
+/// 录制行还原为 WebSocket 消息
+pub fn decode(line: &RecordedLine) -> Result<Message, String> {
+    let channel = WsChannel::from_str(&line.channel)?;
+    let data = line.data.clone();
+    let message = match channel {
+        WsChannel::Bbo => serde_json::from_value(data).map(Message::BBO),
+        WsChannel::Trades => serde_json::from_value(data).map(Message::Trades),
+        WsChannel::OrderBook => serde_json::from_value(data).map(Message::OrderBook),
+        WsChannel::OrderBookDeltas => serde_json::from_value(data).map(Message::OrderBookDeltas),
+        WsChannel::FundingData => serde_json::from_value(data).map(Message::FundingData),
+        WsChannel::MarketsSummary => {
+            serde_json::from_value(market_summary_fields(data)).map(Message::MarketSummary)
+        }
+        _ => return Err(format!("channel {:?} is not replayable", line.channel)),
+    };
+    message.map_err(|e| e.to_string())
+}
+
+/// 行情摘要的部分 f64 字段只能从字符串反序列化，但序列化（录制）时写成了数字或 null
+fn market_summary_fields(mut data: Json) -> Json {
+    if let Json::Object(ref mut fields) = data {
+        for (key, value) in fields.iter_mut() {
+            if key == "created_at" {
+                continue;
+            }
+            match value {
+                Json::Number(number) => *value = Json::String(number.to_string()),
+                // NaN 序列化为 null；空字符串会还原为 NaN（可选字段为 None）
+                Json::Null => *value = Json::String(String::new()),
+                _ => {}
+            }
+        }
+    }
+    data
+}
+
+struct Subscription {
+    id: u64,
+    channel: WsChannel,
+    market: Option<String>,
+    callback: Callback,
+}
+
+impl Subscription {
+    fn matches(&self, channel: WsChannel, line: &RecordedLine) -> bool {
+        self.channel == channel
+            && self
+                .market
+                .as_deref()
+                .is_none_or(|market| line.market.as_deref() == Some(market))
+    }
+}
+
+/// 单个录制文件的逐行读取器
+struct RecordFile {
+    path: PathBuf,
+    lines: Lines<Box<dyn AsyncBufRead + Send + Unpin>>,
+    line_no: usize,
+}
+
+impl RecordFile {
+    async fn open(path: PathBuf) -> Result<Self, ReplayError> {
+        let file = File::open(&path).await.map_err(|source| ReplayError::Io {
+            path: path.clone(),
+            source,
+        })?;
+        let file = BufReader::new(file);
+        // 重启后追加写入的压缩文件包含多个帧 / 成员
+        let reader: Box<dyn AsyncBufRead + Send + Unpin> = match Compression::of_file(&path) {
+            Some(Compression::Gzip) => {
+                let mut decoder = GzipDecoder::new(file);
+                decoder.multiple_members(true);
+                Box::new(BufReader::new(decoder))
+            }
+            Some(Compression::Zstd) => {
+                let mut decoder = ZstdDecoder::new(file);
+                decoder.multiple_members(true);
+                Box::new(BufReader::new(decoder))
+            }
+            _ => Box::new(file),
+        };
+        Ok(Self {
+            path,
+            lines: reader.lines(),
+            line_no: 0,
+        })
+    }
+
+    async fn next(&mut self) -> Result<Option<RecordedLine>, ReplayError> {
+        loop {
+            let line = self
+                .lines
+                .next_line()
+                .await
+                .map_err(|source| ReplayError::Io {
+                    path: self.path.clone(),
+                    source,
+                })?;
+            self.line_no += 1;
+            match line {
+                None => return Ok(None),
+                Some(line) if line.trim().is_empty() => continue,
+                Some(line) => {
+                    return serde_json::from_str(&line)
+                        .map(Some)
+                        .map_err(|e| ReplayError::Parse {
+                            path: self.path.clone(),
+                            line: self.line_no,
+                            message: e.to_string(),
+                        })
+                }
+            }
+        }
+    }
+}
+
+/// 录制目录的重放来源；订阅后调用 [`Replay::run`] 开始推送
+pub struct Replay {
+    files: Vec<PathBuf>,
+    /// 分段索引，按文件名
+    index: HashMap<String, SegmentEntry>,
+    speed: ReplaySpeed,
+    /// 从该接收时间（毫秒时间戳）开始推送
+    start: Option<u64>,
+    subscriptions: Mutex<Vec<Subscription>>,
+    next_id: AtomicU64,
+    stopped: CancellationToken,
+}
+
+impl Replay {
+    /// 列出目录中的录制文件（按文件名排序，同一时间戳的消息按此顺序推送）
+    pub fn open(dir: &Path, speed: ReplaySpeed) -> Result<Self, ReplayError> {
+        let io_error = |source| ReplayError::Io {
+            path: dir.to_path_buf(),
+            source,
+        };
+        let mut files = Vec::new();
+        for entry in std::fs::read_dir(dir).map_err(io_error)? {
+            let path = entry.map_err(io_error)?.path();
+            if path.is_file() && Compression::of_file(&path).is_some() {
+                files.push(path);
+            }
+        }
+        if files.is_empty() {
+            return Err(ReplayError::Empty(dir.to_path_buf()));
+        }
+        files.sort();
+        let index = read_index(dir).map_err(|source| ReplayError::Io {
+            path: dir.join(INDEX_FILE),
+            source,
+        })?;
+        Ok(Self {
+            files,
+            index,
+            speed,
+            start: None,
+            subscriptions: Mutex::new(Vec::new()),
+            next_id: AtomicU64::new(0),
+            stopped: CancellationToken::new(),
+        })
+    }
+
+    /// 跳过接收时间早于 `start` 的消息；索引显示整段早于 `start` 的分段不再读取
+    pub fn with_start(mut self, start: DateTime<Utc>) -> Self {
+        self.start = Some(start.timestamp_millis().max(0) as u64);
+        self
+    }
+
+    /// 需要读取的文件：不在索引中的分段（如进程异常退出时未关闭的）总是读取
+    fn files_from_start(&self) -> Vec<&PathBuf> {
+        let Some(start) = self.start else {
+            return self.files.iter().collect();
+        };
+        self.files
+            .iter()
+            .filter(|path| {
+                let name = path.file_name().and_then(|name| name.to_str());
+                name.and_then(|name| self.index.get(name))
+                    .is_none_or(|segment| segment.last_received_at >= start)
+            })
+            .collect()
+    }
+
+    /// 按接收时间合并全部文件并推送给匹配的订阅，直到读完或被 `stop`；返回推送的消息数
+    pub async fn run(&self) -> Result<u64, ReplayError> {
+        let paths = self.files_from_start();
+        info!(
+            "Replaying {} recorded files at {} speed",
+            paths.len(),
+            self.speed
+        );
+        if paths.len() < self.files.len() {
+            info!(
+                "Skipped {} segments recorded before the start time",
+                self.files.len() - paths.len()
+            );
+        }
+        let mut files = Vec::with_capacity(paths.len());
+        let mut heads = Vec::with_capacity(paths.len());
+        let mut queue = BinaryHeap::new();
+        for (index, path) in paths.into_iter().enumerate() {
+            let mut file = RecordFile::open(path.clone()).await?;
+            let mut head = file.next().await?;
+            // 跳过分段中起始时间之前的行
+            while let (Some(line), Some(start)) = (&head, self.start) {
+                if line.received_at >= start {
+                    break;
+                }
+                head = file.next().await?;
+            }
+            if let Some(ref line) = head {
+                queue.push(Reverse((line.received_at, index)));
+            }
+            files.push(file);
+            heads.push(head);
+        }
+
+        let mut clock: Option<(u64, Instant)> = None;
+        let mut dispatched = 0;
+        while let Some(Reverse((received_at, index))) = queue.pop() {
+            let line = heads[index].take().expect("queued file has a head line");
+            heads[index] = files[index].next().await?;
+            if let Some(ref next) = heads[index] {
+                queue.push(Reverse((next.received_at, index)));
+            }
+
+            if let ReplaySpeed::Factor(factor) = self.speed {
+                let (first, started) = *clock.get_or_insert((received_at, Instant::now()));
+                let offset = (received_at.saturating_sub(first)) as f64 / 1000.0 / factor;
+                tokio::select! {
+                    _ = tokio::time::sleep_until(started + Duration::from_secs_f64(offset)) => {}
+                    _ = self.stopped.cancelled() => break,
+                }
+            } else if self.stopped.is_cancelled() {
+                break;
+            }
+
+            let message = decode(&line).map_err(|message| ReplayError::Parse {
+                path: files[index].path.clone(),
+                line: files[index].line_no,
+                message,
+            })?;
+            let channel = WsChannel::from_str(&line.channel).expect("decoded channel is valid");
+            for subscription in self.subscriptions.lock().unwrap().iter() {
+                if subscription.matches(channel, &line) {
+                    (subscription.callback)(&message);
+                }
+            }
+            dispatched += 1;
+        }
+        info!("Replay finished after {} messages", dispatched);
+        Ok(dispatched)
+    }
+}
+
+#[async_trait]
+impl MarketDataSource for Replay {
+    async fn subscribe(
+        &self,
+        channel: Channel,
+        callback: Callback,
+    ) -> Result<SubscriptionId, Error> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (channel, market) = channel_key(&channel);
+        if channel.is_private() {
+            debug!(
+                "Private channel {} has no recorded data to replay",
+                channel.cli_name()
+            );
+        }
+        self.subscriptions.lock().unwrap().push(Subscription {
+            id,
+            channel,
+            market,
+            callback,
+        });
+        Ok(SubscriptionId::Replay(id))
+    }
+
+    async fn unsubscribe(&self, id: SubscriptionId) -> Result<(), Error> {
+        if let SubscriptionId::Replay(id) = id {
+            self.subscriptions
+                .lock()
+                .unwrap()
+                .retain(|subscription| subscription.id != id);
+        }
+        Ok(())
+    }
+
+    async fn stop(&self) -> Result<(), Error> {
+        self.stopped.cancel();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recorder::{RecordOptions, Recorder};
+    use chrono::TimeZone;
+    use std::io::Write;
+    use std::sync::Arc;
+
+    fn fixtures() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/replay")
+    }
+
+    /// 把回调收到的消息记录为 `频道:标识`
+    fn logger(log: &Arc<Mutex<Vec<String>>>) -> Callback {
+        let log = log.clone();
+        Box::new(move |message| {
+            let entry = match message {
+                Message::BBO(bbo) => format!("bbo:{}@{}", bbo.market, bbo.bid),
+                Message::Trades(trade) => format!("trades:{}", trade.id),
+                Message::MarketSummary(summary) => {
+                    format!("summary:{}@{}", summary.symbol, summary.mark_price)
+                }
+                other => format!("{:?}", other),
+            };
+            log.lock().unwrap().push(entry);
+        })
+    }
+
+    async fn subscribe(replay: &Replay, channel: Channel, log: &Arc<Mutex<Vec<String>>>) {
+        replay.subscribe(channel, logger(log)).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn messages_are_dispatched_in_timestamp_order() {
+        let replay = Replay::open(&fixtures(), ReplaySpeed::Max).unwrap();
+        let log = Arc::new(Mutex::new(Vec::new()));
+        subscribe(&replay, Channel::MarketSummary, &log).await;
+        subscribe(
+            &replay,
+            Channel::BBO {
+                market_symbol: "BTC-USD-PERP".into(),
+            },
+            &log,
+        )
+        .await;
+        subscribe(
+            &replay,
+            Channel::Trades {
+                market_symbol: "BTC-USD-PERP".into(),
+            },
+            &log,
+        )
+        .await;
+        // 没有录制数据的市场不会收到消息
+        subscribe(
+            &replay,
+            Channel::Trades {
+                market_symbol: "ETH-USD-PERP".into(),
+            },
+            &log,
+        )
+        .await;
+
+        assert_eq!(replay.run().await.unwrap(), 6);
+        assert_eq!(
+            *log.lock().unwrap(),
+            [
+                "bbo:BTC-USD-PERP@95000",
+                "summary:BTC-USD-PERP@95000.5",
+                "trades:1",
+                "bbo:BTC-USD-PERP@95001",
+                "trades:2",
+                "trades:3",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn unsubscribed_callbacks_are_not_invoked() {
+        let replay = Replay::open(&fixtures(), ReplaySpeed::Max).unwrap();
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let id = replay
+            .subscribe(
+                Channel::BBO {
+                    market_symbol: "BTC-USD-PERP".into(),
+                },
+                logger(&log),
+            )
+            .await
+            .unwrap();
+        subscribe(
+            &replay,
+            Channel::Trades {
+                market_symbol: "BTC-USD-PERP".into(),
+            },
+            &log,
+        )
+        .await;
+        replay.unsubscribe(id).await.unwrap();
+
+        replay.run().await.unwrap();
+        assert_eq!(*log.lock().unwrap(), ["trades:1", "trades:2", "trades:3"]);
+    }
+
+    #[tokio::test]
+    async fn pacing_follows_recorded_timestamps() {
+        let replay = Replay::open(&fixtures(), ReplaySpeed::Factor(10.0)).unwrap();
+        let started = Instant::now();
+        replay.run().await.unwrap();
+        // 录制跨度 2 秒，10 倍速约 200 毫秒
+        let elapsed = started.elapsed();
+        assert!(
+            elapsed >= Duration::from_millis(200) && elapsed < Duration::from_secs(1),
+            "{elapsed:?}"
+        );
+    }
+
+    #[test]
+    fn speed_parses_factor_or_max() {
+        assert_eq!("10".parse(), Ok(ReplaySpeed::Factor(10.0)));
+        assert_eq!("MAX".parse(), Ok(ReplaySpeed::Max));
+        assert!("0".parse::<ReplaySpeed>().is_err());
+        assert!("fast".parse::<ReplaySpeed>().is_err());
+        assert!(matches!(
+            Replay::open(&fixtures().join("missing"), ReplaySpeed::Max),
+            Err(ReplayError::Io { .. })
+        ));
+    }
+
+    /// 把夹具按频道以不同压缩格式重新录制到临时目录
+    async fn record_fixtures(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("tlp_replay_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        for (prefix, compression) in [
+            ("bbo_", Compression::Zstd),
+            ("trades_", Compression::Gzip),
+            ("market_summary", Compression::None),
+        ] {
+            let options = RecordOptions {
+                compression,
+                ..RecordOptions::default()
+            };
+            let recorder = Recorder::start_with(&dir, options).unwrap();
+            let handle = recorder.handle();
+            let mut fixture = RecordFile::open(
+                std::fs::read_dir(fixtures())
+                    .unwrap()
+                    .map(|entry| entry.unwrap().path())
+                    .find(|path| {
+                        path.file_name()
+                            .unwrap()
+                            .to_string_lossy()
+                            .starts_with(prefix)
+                    })
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+            while let Some(line) = fixture.next().await.unwrap() {
+                let channel = WsChannel::from_str(&line.channel).unwrap();
+                let message = decode(&line).unwrap();
+                handle.record_at(line.received_at, channel, line.market.as_deref(), &message);
+            }
+            recorder.finish().await.unwrap();
+        }
+        dir
+    }
+
+    async fn subscribe_btc(replay: &Replay, log: &Arc<Mutex<Vec<String>>>) {
+        subscribe(replay, Channel::MarketSummary, log).await;
+        let market_symbol = "BTC-USD-PERP".to_string();
+        subscribe(
+            replay,
+            Channel::BBO {
+                market_symbol: market_symbol.clone(),
+            },
+            log,
+        )
+        .await;
+        subscribe(replay, Channel::Trades { market_symbol }, log).await;
+    }
+
+    #[tokio::test]
+    async fn compressed_recording_round_trips_through_replay() {
+        let dir = record_fixtures("compressed").await;
+        assert!(dir
+            .join("bbo_BTC-USD-PERP.2025-01-01T00.jsonl.zst")
+            .exists());
+        assert!(dir
+            .join("trades_BTC-USD-PERP.2025-01-01T00.jsonl.gz")
+            .exists());
+        assert!(dir.join("market_summary.2025-01-01T00.jsonl").exists());
+
+        let replay = Replay::open(&dir, ReplaySpeed::Max).unwrap();
+        let log = Arc::new(Mutex::new(Vec::new()));
+        subscribe_btc(&replay, &log).await;
+        assert_eq!(replay.run().await.unwrap(), 6);
+        assert_eq!(
+            *log.lock().unwrap(),
+            [
+                "bbo:BTC-USD-PERP@95000",
+                "summary:BTC-USD-PERP@95000.5",
+                "trades:1",
+                "bbo:BTC-USD-PERP@95001",
+                "trades:2",
+                "trades:3",
+            ]
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn start_time_skips_indexed_segments_without_reading_them() {
+        let dir = record_fixtures("seek").await;
+        // 索引显示整段早于起始时间的分段不会被打开：内容损坏也不影响重放
+        let stale = "bbo_BTC-USD-PERP.2024-12-31T23.jsonl.zst";
+        std::fs::write(dir.join(stale), b"not zstd").unwrap();
+        let entry = SegmentEntry {
+            file: stale.to_string(),
+            first_received_at: 1735686000000,
+            last_received_at: 1735689599999,
+            lines: 1,
+        };
+        let mut index = std::fs::OpenOptions::new()
+            .append(true)
+            .open(dir.join(INDEX_FILE))
+            .unwrap();
+        writeln!(index, "{}", serde_json::to_string(&entry).unwrap()).unwrap();
+
+        let start = Utc.timestamp_millis_opt(1735689601000).unwrap();
+        let replay = Replay::open(&dir, ReplaySpeed::Max)
+            .unwrap()
+            .with_start(start);
+        let log = Arc::new(Mutex::new(Vec::new()));
+        subscribe_btc(&replay, &log).await;
+        assert_eq!(replay.run().await.unwrap(), 3);
+        assert_eq!(
+            *log.lock().unwrap(),
+            ["bbo:BTC-USD-PERP@95001", "trades:2", "trades:3"]
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,1012 @@
+//! 下单前风控：所有下单 / 改单请求经 [`RiskGuard`] 检查单笔数量、下单后持仓与名义价值，
+//! 市价单另按订单簿估算滑点
+
+use async_trait::async_trait;
+use log::{error, info, warn};
+use paradex::{
+    rest::Client,
+    structs::{
+        CancelByMarketResponse, ModifyOrderRequest, OrderFlags, OrderRequest, OrderType,
+        OrderUpdate, PositionStatus, Side,
+    },
+};
+use reqwest::Client as HttpClient;
+use rust_decimal::Decimal;
+use std::time::Duration;
+use thiserror::Error;
+
+use crate::account::AccountState;
+use crate::config::RiskLimits;
+use crate::funding::{funding_cost_bps, FundingTracker};
+use crate::gateway::{GatewayError, OrderGateway};
+use crate::market_data::{Anomaly, ExecEstimate, LocalOrderBook, MarketSummaryCache, OrderBooks};
+use crate::markets::{decimal, fetch_market_stat};
+use crate::orderbook::{fetch_orderbook, DEPTH_RANGE};
+use crate::session::AccountSession;
+
+/// 违反风控限制的原因
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum RiskViolation {
+    #[error("order size {size} exceeds max_order_size {limit} by {}", size - limit)]
+    OrderSize { size: Decimal, limit: Decimal },
+    #[error(
+        "resulting {market} position {} exceeds max_position {limit} by {}",
+        position,
+        position.abs() - limit
+    )]
+    Position {
+        market: String,
+        position: Decimal,
+        limit: Decimal,
+    },
+    #[error(
+        "{kind} notional {notional} on {market} exceeds max_notional {limit} by {}",
+        notional - limit
+    )]
+    Notional {
+        market: String,
+        kind: &'static str,
+        notional: Decimal,
+        limit: Decimal,
+    },
+    #[error("no mark, BBO or order price for {0}; cannot check max_notional")]
+    MissingPrice(String),
+    #[error("failed to read {market} position: {reason}")]
+    PositionUnavailable { market: String, reason: String },
+    #[error(
+        "estimated {market} slippage {} bps exceeds max_slippage_bps {limit}",
+        slippage_bps.round_dp(2)
+    )]
+    Slippage {
+        market: String,
+        slippage_bps: Decimal,
+        limit: Decimal,
+    },
+    #[error("{market} order book can only fill {available} of {size}")]
+    InsufficientLiquidity {
+        market: String,
+        size: Decimal,
+        available: Decimal,
+    },
+    #[error("no order book for {0}; cannot check max_slippage_bps")]
+    MissingBook(String),
+    #[error("free collateral {free} is below min_free_collateral {limit}")]
+    FreeCollateral { free: Decimal, limit: Decimal },
+    #[error("{0}; order placement paused until the book recovers or resyncs")]
+    SuspectBook(Anomaly),
+}
+
+/// 行情摘要缓存中的标记价格超过该时长视为过期，改为查询 REST
+pub const MAX_MARK_AGE: Duration = Duration::from_secs(30);
+
+/// 风控检查所需的持仓与参考价
+#[async_trait]
+pub trait RiskContext: Send + Sync {
+    /// 当前持仓，多头为正、空头为负，无持仓为 0
+    async fn position(&self, market: &str) -> Result<Decimal, String>;
+
+    /// 计算名义价值的参考价：标记价格，其次 BBO 中间价
+    async fn reference_price(&self, market: &str) -> Option<Decimal>;
+
+    /// 最新资金费率（每 8 小时），用于提示加仓后的资金费成本；未知时为 `None`
+    async fn funding_rate(&self, _market: &str) -> Option<Decimal> {
+        None
+    }
+
+    /// 估算市价单成交价的订单簿；不可用时为 `None`
+    async fn order_book(&self, _market: &str) -> Option<LocalOrderBook> {
+        None
+    }
+
+    /// 账户的可用保证金；未知时为 `None`，不检查 `min_free_collateral`
+    async fn free_collateral(&self) -> Option<Decimal> {
+        None
+    }
+
+    /// 本地订单簿尚未恢复的异常；存在时暂停该市场的下单
+    async fn book_anomaly(&self, _market: &str) -> Option<Anomaly> {
+        None
+    }
+
+    /// 交易所订单 ID 为 `id` 的挂单，改单时据此只检查新增的数量；未知时为 `None`
+    async fn open_order(&self, _id: &str) -> Option<OrderUpdate> {
+        None
+    }
+}
+
+/// 以持仓缓存（或 REST 持仓）与 REST 行情作为风控依据
+pub struct RestRiskContext {
+    client: Client,
+    http_client: HttpClient,
+    base_url: String,
+    session: Option<AccountSession>,
+    funding: Option<FundingTracker>,
+    books: Option<OrderBooks>,
+    summaries: Option<MarketSummaryCache>,
+    account: Option<AccountState>,
+}
+
+impl RestRiskContext {
+    /// `session` 为 `None` 时每次检查都查询 `client.positions()`
+    pub fn new(client: Client, base_url: &str, session: Option<AccountSession>) -> Self {
+        Self {
+            client,
+            http_client: HttpClient::new(),
+            base_url: base_url.to_string(),
+            session,
+            funding: None,
+            books: None,
+            summaries: None,
+            account: None,
+        }
+    }
+
+    /// 以实时资金费率提示加仓后的资金费成本
+    pub fn with_funding(mut self, funding: FundingTracker) -> Self {
+        self.funding = Some(funding);
+        self
+    }
+
+    /// 优先以已同步的本地订单簿估算市价单成交价，否则拉取 REST 快照；
+    /// 订单簿出现异常（交叉、锁定等）时暂停该市场的下单
+    pub fn with_order_books(mut self, books: OrderBooks) -> Self {
+        books.on_anomaly(Box::new(|anomaly| {
+            warn!(
+                "Pausing order placement on {} until the book recovers: {}",
+                anomaly.market, anomaly
+            )
+        }));
+        self.books = Some(books);
+        self
+    }
+
+    /// 优先使用行情摘要频道推送的标记价格（不超过 [`MAX_MARK_AGE`]）
+    pub fn with_summaries(mut self, summaries: MarketSummaryCache) -> Self {
+        self.summaries = Some(summaries);
+        self
+    }
+
+    /// 以账户频道维护的可用保证金检查 `min_free_collateral`
+    pub fn with_account(mut self, account: AccountState) -> Self {
+        self.account = Some(account);
+        self
+    }
+}
+
+#[async_trait]
+impl RiskContext for RestRiskContext {
+    async fn position(&self, market: &str) -> Result<Decimal, String> {
+        if let Some(ref session) = self.session {
+            return Ok(session.positions().size(market));
+        }
+        let position = self
+            .client
+            .positions()
+            .await
+            .map_err(|e| e.to_string())?
+            .results
+            .into_iter()
+            .find(|position| position.market == market);
+        // 空头持仓的 size 为负数
+        Ok(position
+            .filter(|position| position.status != PositionStatus::CLOSED)
+            .map_or(Decimal::ZERO, |position| decimal(position.size)))
+    }
+
+    async fn reference_price(&self, market: &str) -> Option<Decimal> {
+        let cached = self
+            .summaries
+            .as_ref()
+            .and_then(|summaries| summaries.mark_price(market, MAX_MARK_AGE));
+        if cached.is_some() {
+            return cached;
+        }
+        let mark = fetch_market_stat(&self.http_client, &self.base_url, market)
+            .await
+            .ok()
+            .flatten()
+            .and_then(|stat| stat.mark_price)
+            .filter(|price| price.is_sign_positive() && !price.is_zero());
+        if mark.is_some() {
+            return mark;
+        }
+        let bbo = self.client.bbo(market.to_string()).await.ok()?;
+        mid(decimal(bbo.bid), decimal(bbo.ask))
+    }
+
+    async fn funding_rate(&self, market: &str) -> Option<Decimal> {
+        Some(self.funding.as_ref()?.rate(market)?.funding_rate)
+    }
+
+    async fn order_book(&self, market: &str) -> Option<LocalOrderBook> {
+        let local = self
+            .books
+            .as_ref()
+            .and_then(|books| books.get(market))
+            .map(|book| book.read().unwrap().clone())
+            .filter(LocalOrderBook::is_synced);
+        if local.is_some() {
+            return local;
+        }
+        let snapshot = fetch_orderbook(
+            &self.http_client,
+            &self.base_url,
+            market,
+            *DEPTH_RANGE.end(),
+        )
+        .await
+        .ok()?;
+        let mut book = LocalOrderBook::new(market);
+        book.seed(&snapshot);
+        Some(book)
+    }
+
+    async fn free_collateral(&self) -> Option<Decimal> {
+        self.account.as_ref()?.free_collateral()
+    }
+
+    async fn book_anomaly(&self, market: &str) -> Option<Anomaly> {
+        let book = self.books.as_ref()?.get(market)?;
+        let anomaly = book.read().unwrap().anomaly().cloned();
+        anomaly
+    }
+
+    async fn open_order(&self, id: &str) -> Option<OrderUpdate> {
+        let orders = self.client.open_orders().await.ok()?;
+        orders.results.into_iter().find(|order| order.id == id)
+    }
+}
+
+/// 买一卖一均有效时的中间价
+pub fn mid(bid: Decimal, ask: Decimal) -> Option<Decimal> {
+    (bid > Decimal::ZERO && ask > Decimal::ZERO).then(|| (bid + ask) / Decimal::TWO)
+}
+
+/// 检查 `position` 上再下 `side` `size` 的订单是否超限
+///
+/// 同方向减仓、平仓的订单总是放行；反手订单只按开出新方向的部分检查单笔数量与名义价值。
+/// 名义价值使用 `reference_price`，缺失时退回订单价格 `order_price`。
+pub fn check_order(
+    limits: &RiskLimits,
+    market: &str,
+    side: Side,
+    size: Decimal,
+    position: Decimal,
+    reference_price: Option<Decimal>,
+    order_price: Option<Decimal>,
+) -> Result<(), RiskViolation> {
+    let resulting = match side {
+        Side::BUY => position + size,
+        Side::SELL => position - size,
+    };
+    let same_side =
+        resulting.is_zero() || resulting.is_sign_negative() == position.is_sign_negative();
+    if same_side && resulting.abs() <= position.abs() {
+        return Ok(());
+    }
+    // 反手时先平掉原持仓，只有超出部分是新开仓
+    let opening = if same_side || position.is_zero() {
+        size
+    } else {
+        resulting.abs()
+    };
+    if opening > limits.max_order_size {
+        return Err(RiskViolation::OrderSize {
+            size: opening,
+            limit: limits.max_order_size,
+        });
+    }
+    if let Some(limit) = limits.max_position {
+        if resulting.abs() > limit {
+            return Err(RiskViolation::Position {
+                market: market.to_string(),
+                position: resulting,
+                limit,
+            });
+        }
+    }
+    let price = reference_price
+        .or(order_price)
+        .ok_or_else(|| RiskViolation::MissingPrice(market.to_string()))?;
+    for (kind, notional) in [
+        ("order", opening * price),
+        ("position", resulting.abs() * price),
+    ] {
+        if notional > limits.max_notional {
+            return Err(RiskViolation::Notional {
+                market: market.to_string(),
+                kind,
+                notional,
+                limit: limits.max_notional,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// 按 `book` 估算 `side` `size` 市价单的成交价，滑点超过 `limit` 基点或深度不足时拒绝
+pub fn check_slippage(
+    limit: Decimal,
+    market: &str,
+    side: Side,
+    size: Decimal,
+    book: &LocalOrderBook,
+) -> Result<ExecEstimate, RiskViolation> {
+    let missing = || RiskViolation::MissingBook(market.to_string());
+    let mid = book.mid().ok_or_else(missing)?;
+    let estimate = book.execution_price(side, size).ok_or_else(missing)?;
+    if estimate.insufficient_liquidity {
+        return Err(RiskViolation::InsufficientLiquidity {
+            market: market.to_string(),
+            size,
+            available: estimate.filled,
+        });
+    }
+    let slippage_bps = estimate.slippage_bps(side, mid);
+    if slippage_bps > limit {
+        return Err(RiskViolation::Slippage {
+            market: market.to_string(),
+            slippage_bps,
+            limit,
+        });
+    }
+    Ok(estimate)
+}
+
+/// 在任意下单出口前执行风控；撤单直接放行
+pub struct RiskGuard<'a> {
+    inner: &'a dyn OrderGateway,
+    context: &'a dyn RiskContext,
+    limits: RiskLimits,
+}
+
+impl<'a> RiskGuard<'a> {
+    /// 按 `limits` 检查后再交给 `inner` 下单；`context` 提供持仓与价格
+    pub fn new(
+        inner: &'a dyn OrderGateway,
+        context: &'a dyn RiskContext,
+        limits: RiskLimits,
+    ) -> Self {
+        Self {
+            inner,
+            context,
+            limits,
+        }
+    }
+
+    /// 检查一笔订单；违规时记录命中的限额与超出量
+    pub async fn check(
+        &self,
+        market: &str,
+        side: Side,
+        size: Decimal,
+        order_price: Option<Decimal>,
+    ) -> Result<(), RiskViolation> {
+        let position = self.context.position(market).await.map_err(|reason| {
+            RiskViolation::PositionUnavailable {
+                market: market.to_string(),
+                reason,
+            }
+        })?;
+        let reference_price = self.context.reference_price(market).await;
+        check_order(
+            &self.limits,
+            market,
+            side,
+            size,
+            position,
+            reference_price,
+            order_price,
+        )
+        .inspect_err(|violation| {
+            error!("Order on {} blocked by risk guard: {}", market, violation)
+        })?;
+        let resulting = match side {
+            Side::BUY => position + size,
+            Side::SELL => position - size,
+        };
+        if let Some(limit) = self.limits.min_free_collateral {
+            if let Some(free) = self.context.free_collateral().await {
+                if resulting.abs() > position.abs() && free < limit {
+                    let violation = RiskViolation::FreeCollateral { free, limit };
+                    error!("Order on {} blocked by risk guard: {}", market, violation);
+                    return Err(violation);
+                }
+            }
+        }
+        if let Some(threshold) = self.limits.funding_alert_bps {
+            if let Some(rate) = self.context.funding_rate(market).await {
+                let cost_bps = funding_cost_bps(resulting, rate);
+                if resulting.abs() > position.abs() && cost_bps > threshold {
+                    warn!(
+                        "Order on {} grows the position to {}, paying {} bps per 8h funding (above --funding-alert-bps {})",
+                        market, resulting, cost_bps, threshold
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// 订单簿可疑时拒绝下单与改单
+    pub async fn check_book(&self, market: &str) -> Result<(), RiskViolation> {
+        match self.context.book_anomaly(market).await {
+            Some(anomaly) => {
+                let violation = RiskViolation::SuspectBook(anomaly);
+                error!("Order on {} blocked by risk guard: {}", market, violation);
+                Err(violation)
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// 设置了 `max_slippage_bps` 时估算市价单的成交价；违规时记录命中的限额
+    pub async fn check_market_order(
+        &self,
+        market: &str,
+        side: Side,
+        size: Decimal,
+    ) -> Result<(), RiskViolation> {
+        let Some(limit) = self.limits.max_slippage_bps else {
+            return Ok(());
+        };
+        let result = match self.context.order_book(market).await {
+            Some(book) => check_slippage(limit, market, side, size, &book),
+            None => Err(RiskViolation::MissingBook(market.to_string())),
+        };
+        let estimate = result.inspect_err(|violation| {
+            error!("Order on {} blocked by risk guard: {}", market, violation)
+        })?;
+        info!(
+            "Estimated {:?} {} on {}: avg {} (worst {}, {} levels)",
+            side,
+            size,
+            market,
+            estimate.avg_price.round_dp(8),
+            estimate.worst_price,
+            estimate.levels_consumed
+        );
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl OrderGateway for RiskGuard<'_> {
+    async fn create_order(&self, request: OrderRequest) -> Result<OrderUpdate, GatewayError> {
+        self.check_book(&request.market).await?;
+        // 市价单（包括平仓）按订单簿深度检查滑点
+        if request.order_type == OrderType::MARKET {
+            self.check_market_order(&request.market, request.side, request.size)
+                .await?;
+        }
+        // 只减仓订单由交易所保证不会增加持仓
+        if !request.flags.contains(&OrderFlags::REDUCE_ONLY) {
+            self.check(
+                &request.market,
+                request.side,
+                request.size,
+                request.price.filter(|price| !price.is_zero()),
+            )
+            .await?;
+        }
+        self.inner.create_order(request).await
+    }
+
+    async fn modify_order(&self, request: ModifyOrderRequest) -> Result<OrderUpdate, GatewayError> {
+        self.check_book(&request.market).await?;
+        match self.context.open_order(&request.id).await {
+            // 只减仓订单由交易所保证不会增加持仓
+            Some(order) if order.flags.contains(&OrderFlags::REDUCE_ONLY) => {}
+            // 原挂单的数量已在下单时检查过，只检查改单增加的部分
+            Some(order) => {
+                let added = request.size - order.size;
+                if added > Decimal::ZERO {
+                    self.check(&request.market, request.side, added, request.price)
+                        .await?;
+                }
+            }
+            // 找不到原挂单时按新数量整单检查
+            None => {
+                self.check(&request.market, request.side, request.size, request.price)
+                    .await?;
+            }
+        }
+        self.inner.modify_order(request).await
+    }
+
+    async fn cancel_order(&self, order_id: String) -> Result<(), GatewayError> {
+        self.inner.cancel_order(order_id).await
+    }
+
+    async fn cancel_order_by_client_id(&self, client_id: String) -> Result<(), GatewayError> {
+        self.inner.cancel_order_by_client_id(client_id).await
+    }
+
+    async fn cancel_all_orders_for_market(
+        &self,
+        market: String,
+    ) -> Result<CancelByMarketResponse, GatewayError> {
+        self.inner.cancel_all_orders_for_market(market).await
+    }
+
+    async fn cancel_all_orders(&self) -> Result<Vec<String>, GatewayError> {
+        self.inner.cancel_all_orders().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gateway::DryRun;
+    use crate::market_data::AnomalyKind;
+    use crate::orderbook::{Level, OrderBookSnapshot};
+    use crate::paper::test_support::{PaperMarket, MARKET};
+    use paradex::structs::OrderInstruction;
+
+    fn limits() -> RiskLimits {
+        RiskLimits {
+            max_order_size: Decimal::new(1, 2),
+            max_position: Some(Decimal::new(15, 3)),
+            max_notional: Decimal::from(2000),
+            funding_alert_bps: None,
+            max_slippage_bps: None,
+            min_free_collateral: None,
+        }
+    }
+
+    fn check(side: Side, size: &str, position: &str) -> Result<(), RiskViolation> {
+        check_order(
+            &limits(),
+            "BTC-USD-PERP",
+            side,
+            size.parse().unwrap(),
+            position.parse().unwrap(),
+            Some(Decimal::from(100_000)),
+            None,
+        )
+    }
+
+    #[test]
+    fn long_and_short_positions_are_netted() {
+        // 多头 0.008 再买 0.01 -> 0.018，超出 0.003
+        let violation = check(Side::BUY, "0.01", "0.008").unwrap_err();
+        assert_eq!(
+            violation.to_string(),
+            "resulting BTC-USD-PERP position 0.018 exceeds max_position 0.015 by 0.003"
+        );
+        // 卖出只减仓，或空头买入平仓，均放行
+        assert!(check(Side::SELL, "0.01", "0.008").is_ok());
+        assert!(check(Side::BUY, "0.01", "-0.012").is_ok());
+        // 空头 -0.012 再卖 0.005 -> -0.017
+        assert!(matches!(
+            check(Side::SELL, "0.005", "-0.012"),
+            Err(RiskViolation::Position { .. })
+        ));
+        // 空头 -0.008 买 0.01 反手为多头 0.002，未超限
+        assert!(check(Side::BUY, "0.01", "-0.008").is_ok());
+        // 单笔数量先于持仓检查
+        assert_eq!(
+            check(Side::BUY, "0.02", "0").unwrap_err(),
+            RiskViolation::OrderSize {
+                size: Decimal::new(2, 2),
+                limit: Decimal::new(1, 2),
+            }
+        );
+    }
+
+    #[test]
+    fn position_flip_checks_the_opening_size() {
+        let mut limits = limits();
+        limits.max_position = None;
+        let check = |side, size: &str, position: &str| {
+            check_order(
+                &limits,
+                "BTC-USD-PERP",
+                side,
+                size.parse().unwrap(),
+                position.parse().unwrap(),
+                Some(Decimal::from(100)),
+                None,
+            )
+        };
+        // 多头 1 卖出 1.9 -> 空头 0.9，持仓绝对值变小但新开空 0.9 超过单笔上限
+        assert_eq!(
+            check(Side::SELL, "1.9", "1").unwrap_err(),
+            RiskViolation::OrderSize {
+                size: "0.9".parse().unwrap(),
+                limit: Decimal::new(1, 2),
+            }
+        );
+        // 空头 -1 买入 1.005 -> 多头 0.005，新开部分在上限内
+        assert!(check(Side::BUY, "1.005", "-1").is_ok());
+        // 恰好平仓不算反手
+        assert!(check(Side::SELL, "1", "1").is_ok());
+    }
+
+    #[test]
+    fn notional_uses_resulting_position() {
+        // 0.012 * 100000 = 1200 < 2000，但持仓 0.012 + 0.009 = 0.021 > 0.015
+        let mut limits = limits();
+        limits.max_position = None;
+        let violation = check_order(
+            &limits,
+            "BTC-USD-PERP",
+            Side::BUY,
+            Decimal::new(9, 3),
+            Decimal::new(12, 3),
+            Some(Decimal::from(100_000)),
+            None,
+        )
+        .unwrap_err();
+        assert_eq!(
+            violation.to_string(),
+            "position notional 2100.000 on BTC-USD-PERP exceeds max_notional 2000 by 100.000"
+        );
+    }
+
+    #[test]
+    fn missing_reference_price_falls_back_to_order_price() {
+        let check = |order_price| {
+            check_order(
+                &limits(),
+                "BTC-USD-PERP",
+                Side::BUY,
+                Decimal::new(1, 2),
+                Decimal::ZERO,
+                None,
+                order_price,
+            )
+        };
+        assert!(check(Some(Decimal::from(150_000))).is_ok());
+        assert!(matches!(
+            check(Some(Decimal::from(250_000))),
+            Err(RiskViolation::Notional { kind: "order", .. })
+        ));
+        assert_eq!(
+            check(None),
+            Err(RiskViolation::MissingPrice("BTC-USD-PERP".to_string()))
+        );
+        // 减仓订单无需价格
+        assert!(check_order(
+            &limits(),
+            "BTC-USD-PERP",
+            Side::SELL,
+            Decimal::new(1, 2),
+            Decimal::new(1, 2),
+            None,
+            None,
+        )
+        .is_ok());
+    }
+
+    struct FixedContext {
+        position: Result<Decimal, String>,
+        book: Option<LocalOrderBook>,
+        free_collateral: Option<Decimal>,
+        anomaly: Option<Anomaly>,
+    }
+
+    #[async_trait]
+    impl RiskContext for FixedContext {
+        async fn position(&self, _market: &str) -> Result<Decimal, String> {
+            self.position.clone()
+        }
+
+        async fn reference_price(&self, _market: &str) -> Option<Decimal> {
+            Some(Decimal::from(100_000))
+        }
+
+        async fn order_book(&self, _market: &str) -> Option<LocalOrderBook> {
+            self.book.clone()
+        }
+
+        async fn free_collateral(&self) -> Option<Decimal> {
+            self.free_collateral
+        }
+
+        async fn book_anomaly(&self, _market: &str) -> Option<Anomaly> {
+            self.anomaly.clone()
+        }
+    }
+
+    /// 中间价 100_000：卖盘 100_010 × 0.01、100_100 × 0.01
+    fn book() -> LocalOrderBook {
+        let level = |price: i64, size: i64| Level(Decimal::from(price), Decimal::new(size, 2));
+        let mut book = LocalOrderBook::new("BTC-USD-PERP");
+        book.seed(&OrderBookSnapshot {
+            market: "BTC-USD-PERP".to_string(),
+            seq_no: Some(1),
+            last_updated_at: None,
+            bids: vec![level(99_990, 1)],
+            asks: vec![level(100_010, 1), level(100_100, 1)],
+        });
+        book
+    }
+
+    fn request(size: Decimal, flags: Vec<OrderFlags>) -> OrderRequest {
+        OrderRequest {
+            instruction: OrderInstruction::GTC,
+            market: "BTC-USD-PERP".to_string(),
+            price: Some(Decimal::from(99_000)),
+            side: Side::BUY,
+            size,
+            order_type: OrderType::LIMIT,
+            client_id: None,
+            flags,
+            recv_window: None,
+            stp: None,
+            trigger_price: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn guard_blocks_before_reaching_gateway() {
+        let gateway = DryRun::new("0xabc".to_string());
+        let context = FixedContext {
+            position: Ok(Decimal::new(1, 2)),
+            book: None,
+            free_collateral: None,
+            anomaly: None,
+        };
+        let guard = RiskGuard::new(&gateway, &context, limits());
+
+        let error = guard
+            .create_order(request(Decimal::new(1, 2), vec![]))
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(error, GatewayError::Risk(RiskViolation::Position { .. })),
+            "{error}"
+        );
+        assert!(error.to_string().contains("max_position"), "{error}");
+        assert!(guard
+            .create_order(request(Decimal::new(5, 3), vec![]))
+            .await
+            .is_ok());
+        // 只减仓订单不检查
+        assert!(guard
+            .create_order(request(Decimal::ONE, vec![OrderFlags::REDUCE_ONLY]))
+            .await
+            .is_ok());
+
+        // 无法获取持仓时拒绝下单
+        let context = FixedContext {
+            position: Err("timeout".to_string()),
+            book: None,
+            free_collateral: None,
+            anomaly: None,
+        };
+        let guard = RiskGuard::new(&gateway, &context, limits());
+        assert!(guard
+            .create_order(request(Decimal::new(5, 3), vec![]))
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn modify_checks_only_the_added_size_and_skips_reduce_only_orders() {
+        let market = PaperMarket::new();
+        market.bbo(Decimal::from(100), Decimal::from(101));
+        let exchange = market.exchange.as_ref();
+        let guard = RiskGuard::new(exchange, exchange, limits());
+        let order = |side, size, price: i64, flags| OrderRequest {
+            instruction: OrderInstruction::GTC,
+            market: MARKET.to_string(),
+            price: Some(Decimal::from(price)),
+            side,
+            size,
+            order_type: OrderType::LIMIT,
+            client_id: None,
+            flags,
+            recv_window: None,
+            stp: None,
+            trigger_price: None,
+        };
+        let modify = |order: &OrderUpdate, size, price: i64| ModifyOrderRequest {
+            id: order.id.clone(),
+            market: MARKET.to_string(),
+            price: Some(Decimal::from(price)),
+            side: order.side,
+            size,
+            order_type: OrderType::LIMIT,
+        };
+
+        // 多头 0.01，另挂 0.005 的买单
+        guard
+            .create_order(order(Side::BUY, Decimal::new(1, 2), 101, vec![]))
+            .await
+            .unwrap();
+        let bid = guard
+            .create_order(order(Side::BUY, Decimal::new(5, 3), 99, vec![]))
+            .await
+            .unwrap();
+        // 挂单增加到 0.006 只新增 0.001：持仓最多 0.011，原挂单的数量不重复计入
+        assert!(guard
+            .modify_order(modify(&bid, Decimal::new(6, 3), 98))
+            .await
+            .is_ok());
+        // 再增加 0.01 会使持仓达到 0.02
+        let error = guard
+            .modify_order(modify(&bid, Decimal::new(16, 3), 98))
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(error, GatewayError::Risk(RiskViolation::Position { .. })),
+            "{error}"
+        );
+
+        // 只减仓挂单的改单不检查
+        let ask = guard
+            .create_order(order(
+                Side::SELL,
+                Decimal::new(1, 2),
+                110,
+                vec![OrderFlags::REDUCE_ONLY],
+            ))
+            .await
+            .unwrap();
+        assert!(guard
+            .modify_order(modify(&ask, Decimal::new(5, 2), 111))
+            .await
+            .is_ok());
+    }
+
+    #[test]
+    fn slippage_is_estimated_from_book_depth() {
+        let limit = Decimal::from(5);
+        // 0.01 全部在 100_010 成交：偏离 1 基点
+        let estimate = check_slippage(
+            limit,
+            "BTC-USD-PERP",
+            Side::BUY,
+            Decimal::new(1, 2),
+            &book(),
+        )
+        .unwrap();
+        assert_eq!(estimate.avg_price, Decimal::from(100_010));
+        // 0.02 均价 100_055：偏离 5.5 基点
+        assert_eq!(
+            check_slippage(
+                limit,
+                "BTC-USD-PERP",
+                Side::BUY,
+                Decimal::new(2, 2),
+                &book()
+            )
+            .unwrap_err()
+            .to_string(),
+            "estimated BTC-USD-PERP slippage 5.50 bps exceeds max_slippage_bps 5"
+        );
+        assert_eq!(
+            check_slippage(
+                limit,
+                "BTC-USD-PERP",
+                Side::SELL,
+                Decimal::new(2, 2),
+                &book()
+            ),
+            Err(RiskViolation::InsufficientLiquidity {
+                market: "BTC-USD-PERP".to_string(),
+                size: Decimal::new(2, 2),
+                available: Decimal::new(1, 2),
+            })
+        );
+        assert_eq!(
+            check_slippage(
+                limit,
+                "BTC-USD-PERP",
+                Side::BUY,
+                Decimal::ONE,
+                &LocalOrderBook::new("BTC-USD-PERP")
+            ),
+            Err(RiskViolation::MissingBook("BTC-USD-PERP".to_string()))
+        );
+    }
+
+    #[tokio::test]
+    async fn guard_checks_slippage_of_market_orders() {
+        let gateway = DryRun::new("0xabc".to_string());
+        let mut limits = limits();
+        limits.max_slippage_bps = Some(Decimal::from(5));
+        let market = |size, flags| OrderRequest {
+            order_type: OrderType::MARKET,
+            price: None,
+            ..request(size, flags)
+        };
+
+        let context = FixedContext {
+            position: Ok(Decimal::ZERO),
+            book: Some(book()),
+            free_collateral: None,
+            anomaly: None,
+        };
+        let guard = RiskGuard::new(&gateway, &context, limits.clone());
+        assert!(guard
+            .create_order(market(Decimal::new(1, 2), vec![]))
+            .await
+            .is_ok());
+        // 只减仓的市价单同样检查滑点
+        let error = guard
+            .create_order(market(Decimal::new(2, 2), vec![OrderFlags::REDUCE_ONLY]))
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("max_slippage_bps"), "{error}");
+        // 限价单不估算
+        assert!(guard
+            .create_order(request(Decimal::new(1, 2), vec![]))
+            .await
+            .is_ok());
+
+        // 没有订单簿时无法估算，拒绝市价单
+        let context = FixedContext {
+            position: Ok(Decimal::ZERO),
+            book: None,
+            free_collateral: None,
+            anomaly: None,
+        };
+        let guard = RiskGuard::new(&gateway, &context, limits);
+        assert!(guard
+            .create_order(market(Decimal::new(1, 2), vec![]))
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn guard_blocks_growing_positions_on_low_free_collateral() {
+        let gateway = DryRun::new("0xabc".to_string());
+        let mut limits = limits();
+        limits.min_free_collateral = Some(Decimal::from(100));
+        let context = FixedContext {
+            position: Ok(Decimal::new(5, 3)),
+            book: None,
+            free_collateral: Some(Decimal::from(50)),
+            anomaly: None,
+        };
+        let guard = RiskGuard::new(&gateway, &context, limits);
+
+        let error = guard
+            .create_order(request(Decimal::new(5, 3), vec![]))
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("min_free_collateral"), "{error}");
+        // 减仓订单不受影响
+        let sell = OrderRequest {
+            side: Side::SELL,
+            ..request(Decimal::new(5, 3), vec![])
+        };
+        assert!(guard.create_order(sell).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn guard_pauses_orders_on_suspect_books() {
+        let gateway = DryRun::new("0xabc".to_string());
+        let context = FixedContext {
+            position: Ok(Decimal::ZERO),
+            book: None,
+            free_collateral: None,
+            anomaly: Some(Anomaly {
+                market: "BTC-USD-PERP".to_string(),
+                seq_no: 42,
+                kind: AnomalyKind::Crossed {
+                    bid: Decimal::from(100_010),
+                    ask: Decimal::from(100_000),
+                },
+            }),
+        };
+        let guard = RiskGuard::new(&gateway, &context, limits());
+
+        // 只减仓订单同样暂停
+        let error = guard
+            .create_order(request(Decimal::new(1, 3), vec![OrderFlags::REDUCE_ONLY]))
+            .await
+            .unwrap_err();
+        assert!(
+            error.to_string().contains(
+                "BTC-USD-PERP order book crossed (bid 100010 > ask 100000) after delta 42"
+            ),
+            "{error}"
+        );
+        // 撤单不受影响
+        assert!(guard.cancel_all_orders().await.is_ok());
+    }
+}
@@ -0,0 +1,226 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::io::Write;
+use std::process::Command;
+use thiserror::Error;
+use zeroize::Zeroizing;
+
+/// 系统钥匙串中使用的服务名
+pub const KEYRING_SERVICE: &str = "trade_lighter_paradex";
+/// 默认从该环境变量读取 Stark 私钥
+pub const PRIVATE_KEY_ENV: &str = "paradex_account_private_key_hex";
+
+/// 读取或写入私钥的错误
+#[derive(Debug, Error)]
+pub enum SecretError {
+    #[error("OS keyring error: {0}")]
+    Keyring(#[from] keyring::Error),
+}
+
+/// 私钥来源（`--key-source`）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum KeySource {
+    /// 环境变量 / .env（或 profile 中的明文字段）
+    Env,
+    /// 系统钥匙串（macOS Keychain / Windows Credential Manager / Linux Secret Service）
+    Keyring,
+}
+
+/// 私钥材料，drop 时清零内存
+pub struct SecretKey(Zeroizing<String>);
+
+impl SecretKey {
+    /// 包装私钥字符串（去除首尾空白）
+    pub fn new(value: String) -> Self {
+        let value = Zeroizing::new(value);
+        Self(Zeroizing::new(value.trim().to_string()))
+    }
+
+    /// 读取私钥原文
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Debug for SecretKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("SecretKey(..)")
+    }
+}
+
+/// 敏感值（JWT、签名等）包装，`Debug` / `Display` / `Serialize` 只输出 `***`
+///
+/// 确需持久化原值的字段使用 `#[serde(serialize_with = "serialize_exposed")]` 显式声明。
+#[derive(Clone, PartialEq, Eq)]
+pub struct Redacted<T>(T);
+
+impl<T> Redacted<T> {
+    /// 包装敏感值
+    pub fn new(value: T) -> Self {
+        Self(value)
+    }
+
+    /// 读取原值（仅用于发送请求或显式输出）
+    pub fn expose(&self) -> &T {
+        &self.0
+    }
+
+    /// 取出原值
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> std::fmt::Debug for Redacted<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("***")
+    }
+}
+
+impl<T> std::fmt::Display for Redacted<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("***")
+    }
+}
+
+impl<T> Serialize for Redacted<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str("***")
+    }
+}
+
+/// 序列化 `Redacted` 的原值，仅用于需要落盘的字段（如 JWT 缓存）
+pub fn serialize_exposed<T: Serialize, S: Serializer>(
+    value: &Redacted<T>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    value.expose().serialize(serializer)
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Redacted<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        T::deserialize(deserializer).map(Self)
+    }
+}
+
+/// Stark 私钥提供者
+pub trait SecretProvider {
+    /// 读取私钥；未配置时返回 `Ok(None)`
+    fn private_key(&self) -> Result<Option<SecretKey>, SecretError>;
+}
+
+/// 从环境变量读取私钥（原有行为）
+pub struct EnvSecretProvider {
+    var: String,
+}
+
+impl EnvSecretProvider {
+    /// 从环境变量 `var` 读取密钥
+    pub fn new(var: &str) -> Self {
+        Self {
+            var: var.to_string(),
+        }
+    }
+}
+
+impl SecretProvider for EnvSecretProvider {
+    fn private_key(&self) -> Result<Option<SecretKey>, SecretError> {
+        Ok(std::env::var(&self.var).ok().map(SecretKey::new))
+    }
+}
+
+/// 从系统钥匙串读取私钥
+///
+/// 通过 `keyring` 访问平台原生存储：macOS Keychain、Windows Credential Manager、
+/// Linux Secret Service。私钥只经由系统 API 传递，不会出现在任何进程的命令行参数中。
+pub struct KeyringSecretProvider {
+    account: String,
+}
+
+impl KeyringSecretProvider {
+    /// `account` 用于区分不同环境与 profile 的私钥，如 `testnet:default`
+    pub fn new(account: &str) -> Self {
+        Self {
+            account: account.to_string(),
+        }
+    }
+
+    fn entry(&self) -> Result<keyring::Entry, SecretError> {
+        Ok(keyring::Entry::new(KEYRING_SERVICE, &self.account)?)
+    }
+
+    /// 将私钥写入系统钥匙串（已存在时覆盖）
+    pub fn store(&self, secret: &SecretKey) -> Result<(), SecretError> {
+        self.entry()?.set_password(secret.expose())?;
+        Ok(())
+    }
+}
+
+impl SecretProvider for KeyringSecretProvider {
+    fn private_key(&self) -> Result<Option<SecretKey>, SecretError> {
+        match self.entry()?.get_password() {
+            Ok(password) => {
+                let secret = SecretKey::new(password);
+                Ok((!secret.expose().is_empty()).then_some(secret))
+            }
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+/// 从终端读取私钥（输入时关闭回显）
+pub fn prompt_secret(prompt: &str) -> std::io::Result<SecretKey> {
+    use std::io::IsTerminal;
+
+    let interactive = std::io::stdin().is_terminal();
+    eprint!("{}", prompt);
+    std::io::stderr().flush()?;
+    if interactive {
+        let _ = Command::new("stty").arg("-echo").status();
+    }
+    let mut line = Zeroizing::new(String::new());
+    let result = std::io::stdin().read_line(&mut line);
+    if interactive {
+        let _ = Command::new("stty").arg("echo").status();
+        eprintln!();
+    }
+    result?;
+    Ok(SecretKey::new(std::mem::take(&mut *line)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn env_provider_reads_variable() {
+        let var = "TLP_TEST_SECRET_KEY";
+        assert!(EnvSecretProvider::new(var).private_key().unwrap().is_none());
+
+        std::env::set_var(var, " 0x1234\n");
+        let key = EnvSecretProvider::new(var).private_key().unwrap().unwrap();
+        assert_eq!(key.expose(), "0x1234");
+        assert_eq!(format!("{:?}", key), "SecretKey(..)");
+        std::env::remove_var(var);
+    }
+
+    #[test]
+    fn redacted_never_formats_inner_value() {
+        let secret = "0x4c8b5bcb3a3a4e7a6fa3d3b1d7e5c6b6";
+        let redacted = Redacted::new(secret.to_string());
+        assert_eq!(format!("{:?}", redacted), "***");
+        assert_eq!(format!("{}", redacted), "***");
+        assert!(!format!("{:?}", Some(&redacted)).contains("4c8b"));
+        assert_eq!(redacted.expose(), secret);
+
+        let key = SecretKey::new(secret.to_string());
+        assert!(!format!("{:?}", key).contains("4c8b"));
+
+        let json = serde_json::to_string(&redacted).unwrap();
+        assert_eq!(json, r#""***""#);
+
+        let parsed: Redacted<String> = serde_json::from_str(r#""token""#).unwrap();
+        assert_eq!(parsed.into_inner(), "token");
+    }
+}
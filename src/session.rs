@@ -0,0 +1,191 @@
+use log::{info, warn};
+use paradex::{rest::Client, structs::Position, ws::Message};
+use rust_decimal::prelude::ToPrimitive;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+use crate::account::AccountState;
+use crate::market_data::Transfer;
+use crate::metrics::metrics;
+use crate::positions::PositionCache;
+
+/// 浮点比较容差
+const EPSILON: f64 = 1e-9;
+
+#[derive(Default)]
+struct State {
+    balances: HashMap<String, f64>,
+    settlement_asset: Option<String>,
+    /// 已计入余额的转账 ID
+    applied_transfers: HashSet<String>,
+}
+
+/// 账户会话：以 REST 快照为基准，叠加 WebSocket 增量事件维护持仓与余额
+///
+/// 启动时、每次断线重连后以及 [`spawn_periodic_reconcile`](Self::spawn_periodic_reconcile)
+/// 的每个周期都会重新拉取 REST `positions()` / `balance()`，并将快照与 WebSocket 累积状态的差异记录到日志；
+/// 持仓保存在 [`PositionCache`] 中，可用保证金与权益保存在 [`AccountState`] 中，偏差以 REST 为准。
+#[derive(Clone)]
+pub struct AccountSession {
+    client: Client,
+    positions: PositionCache,
+    account: AccountState,
+    state: Arc<Mutex<State>>,
+    needs_reconcile: Arc<AtomicBool>,
+}
+
+impl AccountSession {
+    /// `positions` 与 `account` 为持仓缓存与账户保证金状态，克隆后与会话共享
+    pub fn new(client: Client, positions: PositionCache, account: AccountState) -> Self {
+        Self {
+            client,
+            positions,
+            account,
+            state: Arc::new(Mutex::new(State::default())),
+            needs_reconcile: Arc::new(AtomicBool::new(true)),
+        }
+    }
+
+    /// 拉取 REST 快照作为新的基准，并报告与当前 WebSocket 状态的差异
+    pub async fn reconcile(&self) -> Result<(), paradex::error::Error> {
+        self.needs_reconcile.store(false, Ordering::SeqCst);
+        let positions = self
+            .client
+            .positions()
+            .await
+            .inspect_err(|_| metrics().rest_error())?;
+        let balances = self
+            .client
+            .balance()
+            .await
+            .inspect_err(|_| metrics().rest_error())?;
+        let account = self
+            .client
+            .account_information()
+            .await
+            .inspect_err(|_| metrics().rest_error())?;
+
+        self.positions.reconcile(&positions.results);
+        self.account.reconcile(&account, &balances.results);
+        let mut state = self.state.lock().unwrap();
+        for balance in &balances.results {
+            if let Some(local) = state.balances.get(&balance.token) {
+                if (local - balance.size).abs() > EPSILON {
+                    warn!(
+                        "Balance divergence on {}: websocket {} vs REST {}",
+                        balance.token, local, balance.size
+                    );
+                }
+            }
+        }
+
+        state.balances = balances
+            .results
+            .into_iter()
+            .map(|balance| (balance.token, balance.size))
+            .collect();
+        for (token, balance) in &state.balances {
+            metrics().set_balance(token, *balance);
+        }
+
+        info!(
+            "Reconciled account state: {} positions, {} balances",
+            self.positions.len(),
+            state.balances.len()
+        );
+        Ok(())
+    }
+
+    /// 处理私有频道消息；断线后的首次重连会触发重新对账
+    pub fn apply(&self, message: &Message) {
+        match message {
+            Message::Disconnected => {
+                self.needs_reconcile.store(true, Ordering::SeqCst);
+            }
+            Message::Connected if self.needs_reconcile.swap(false, Ordering::SeqCst) => {
+                let session = self.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = session.reconcile().await {
+                        session.needs_reconcile.store(true, Ordering::SeqCst);
+                        warn!("Account reconciliation failed: {}", e);
+                    }
+                });
+            }
+            Message::Position(_) => self.positions.on_message(message),
+            Message::Account(account) => {
+                self.state.lock().unwrap().settlement_asset =
+                    Some(account.settlement_asset.clone());
+                self.account.on_message(message);
+            }
+            Message::BalanceEvent(event) => {
+                self.account.on_message(message);
+                let mut state = self.state.lock().unwrap();
+                let asset = state
+                    .settlement_asset
+                    .clone()
+                    .unwrap_or_else(|| "USDC".to_string());
+                metrics().set_balance(&asset, event.settlement_asset_balance_after);
+                state
+                    .balances
+                    .insert(asset, event.settlement_asset_balance_after);
+            }
+            _ => {}
+        }
+    }
+
+    /// 已完成的转账按方向增减对应代币的余额；未完成或已计入的转账忽略
+    pub fn apply_transfer(&self, transfer: &Transfer) {
+        if !transfer.is_completed() {
+            return;
+        }
+        let Some(amount) = transfer.signed_amount().to_f64() else {
+            return;
+        };
+        let mut state = self.state.lock().unwrap();
+        if !state.applied_transfers.insert(transfer.id.clone()) {
+            return;
+        }
+        let balance = state.balances.entry(transfer.token.clone()).or_default();
+        *balance += amount;
+        metrics().set_balance(&transfer.token, *balance);
+    }
+
+    /// 对账后的持仓视图
+    pub fn position(&self, symbol: &str) -> Option<Position> {
+        self.positions.get(symbol)
+    }
+
+    /// 会话维护的持仓
+    pub fn positions(&self) -> &PositionCache {
+        &self.positions
+    }
+
+    /// 会话维护的账户保证金状态
+    pub fn account(&self) -> &AccountState {
+        &self.account
+    }
+
+    /// 每隔 `interval` 以 REST 快照对账一次，直到返回的任务被中止
+    pub fn spawn_periodic_reconcile(&self, interval: Duration) -> JoinHandle<()> {
+        let session = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            // 第一次立即触发，启动时已经对账过
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                if let Err(e) = session.reconcile().await {
+                    warn!("Periodic account reconciliation failed: {}", e);
+                }
+            }
+        })
+    }
+
+    /// 对账后的余额视图（token -> 数量）
+    pub fn balance(&self) -> HashMap<String, f64> {
+        self.state.lock().unwrap().balances.clone()
+    }
+}
@@ -0,0 +1,389 @@
+use log::{debug, warn};
+use paradex::structs::BBO;
+use rust_decimal::Decimal;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+
+use crate::markets::decimal;
+
+pub mod lighter;
+
+/// 交易所
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Venue {
+    Paradex,
+    Lighter,
+}
+
+impl Venue {
+    fn other(&self) -> Venue {
+        match self {
+            Venue::Paradex => Venue::Lighter,
+            Venue::Lighter => Venue::Paradex,
+        }
+    }
+}
+
+/// 单个交易所的最优买卖报价
+#[derive(Debug, Clone, Copy)]
+pub struct Quote {
+    pub bid: Decimal,
+    pub ask: Decimal,
+    pub received_at: Instant,
+}
+
+/// 外部行情源推送的一次 BBO，`symbol` 为交易所内符号
+#[derive(Debug, Clone, PartialEq)]
+pub struct VenueQuote {
+    pub venue: Venue,
+    pub symbol: String,
+    pub bid: Decimal,
+    pub ask: Decimal,
+}
+
+/// 跨所套利机会：在 `buy_venue` 以卖一价买入，在 `sell_venue` 以买一价卖出
+#[derive(Debug, Clone)]
+pub struct SpreadOpportunity {
+    pub market: String,
+    pub buy_venue: Venue,
+    pub sell_venue: Venue,
+    pub buy_price: Decimal,
+    pub sell_price: Decimal,
+    pub spread_bps: Decimal,
+}
+
+/// 价差监控参数
+#[derive(Debug, Clone)]
+pub struct SpreadConfig {
+    /// 触发阈值（基点）
+    pub threshold_bps: Decimal,
+    /// 报价最大允许时长，超过则视为过期
+    pub max_quote_age: Duration,
+}
+
+type OpportunityCallback = Box<dyn Fn(&SpreadOpportunity) + Send + 'static>;
+
+/// 多个行情源共同推送报价的价差监控
+pub type SharedSpreadMonitor = Arc<Mutex<SpreadMonitor>>;
+
+/// Lighter 与 Paradex 之间的跨所价差监控
+///
+/// 两个交易所对同一市场的命名不同（如 Paradex 的 `BTC-USD-PERP` 与 Lighter 的 `BTC`），
+/// 通过映射表统一到逻辑市场名后再比较报价。
+pub struct SpreadMonitor {
+    config: SpreadConfig,
+    /// (交易所, 交易所内符号) -> 逻辑市场名
+    symbols: HashMap<(Venue, String), String>,
+    /// 逻辑市场名 -> (Paradex 符号, Lighter 符号)
+    markets: HashMap<String, (String, String)>,
+    quotes: HashMap<(String, Venue), Quote>,
+    callback: Option<OpportunityCallback>,
+}
+
+impl SpreadMonitor {
+    /// 按 `config` 监控，尚未登记任何市场
+    pub fn new(config: SpreadConfig) -> Self {
+        Self {
+            config,
+            symbols: HashMap::new(),
+            markets: HashMap::new(),
+            quotes: HashMap::new(),
+            callback: None,
+        }
+    }
+
+    /// 注册逻辑市场及其在两个交易所中的符号
+    pub fn add_market(&mut self, market: &str, paradex_symbol: &str, lighter_symbol: &str) {
+        self.symbols.insert(
+            (Venue::Paradex, paradex_symbol.to_string()),
+            market.to_string(),
+        );
+        self.symbols.insert(
+            (Venue::Lighter, lighter_symbol.to_string()),
+            market.to_string(),
+        );
+        self.markets.insert(
+            market.to_string(),
+            (paradex_symbol.to_string(), lighter_symbol.to_string()),
+        );
+    }
+
+    /// 设置发现套利机会时的回调
+    pub fn on_opportunity(&mut self, callback: OpportunityCallback) {
+        self.callback = Some(callback);
+    }
+
+    /// 获取逻辑市场在指定交易所中的符号，用于订阅行情
+    pub fn venue_symbol(&self, venue: Venue, market: &str) -> Option<&str> {
+        self.markets
+            .get(market)
+            .map(|(paradex, lighter)| match venue {
+                Venue::Paradex => paradex.as_str(),
+                Venue::Lighter => lighter.as_str(),
+            })
+    }
+
+    /// 推送一个交易所的最新 BBO，并检查两个方向的跨所价差
+    pub fn on_quote(
+        &mut self,
+        venue: Venue,
+        venue_symbol: &str,
+        bid: Decimal,
+        ask: Decimal,
+    ) -> Option<SpreadOpportunity> {
+        let Some(market) = self
+            .symbols
+            .get(&(venue, venue_symbol.to_string()))
+            .cloned()
+        else {
+            warn!("No spread mapping for {:?} symbol {}", venue, venue_symbol);
+            return None;
+        };
+
+        let now = Instant::now();
+        self.quotes.insert(
+            (market.clone(), venue),
+            Quote {
+                bid,
+                ask,
+                received_at: now,
+            },
+        );
+
+        let opportunity = self.evaluate(&market, venue, now)?;
+        debug!(
+            "Spread opportunity on {}: buy {:?} @ {} / sell {:?} @ {} ({:.2} bps)",
+            opportunity.market,
+            opportunity.buy_venue,
+            opportunity.buy_price,
+            opportunity.sell_venue,
+            opportunity.sell_price,
+            opportunity.spread_bps
+        );
+        if let Some(callback) = &self.callback {
+            callback(&opportunity);
+        }
+        Some(opportunity)
+    }
+
+    /// 推送 Paradex 的 BBO
+    pub fn on_bbo(&mut self, bbo: &BBO) -> Option<SpreadOpportunity> {
+        self.on_quote(
+            Venue::Paradex,
+            &bbo.market,
+            decimal(bbo.bid),
+            decimal(bbo.ask),
+        )
+    }
+
+    /// 将 `feed` 中的报价逐条推送给 `monitor`，直到所有发送端关闭
+    ///
+    /// Lighter 等外部交易所的行情客户端只需把 BBO 发送到 `feed` 即可接入监控。
+    pub async fn run_feed(monitor: SharedSpreadMonitor, mut feed: mpsc::Receiver<VenueQuote>) {
+        while let Some(quote) = feed.recv().await {
+            monitor
+                .lock()
+                .unwrap()
+                .on_quote(quote.venue, &quote.symbol, quote.bid, quote.ask);
+        }
+        debug!("Spread quote feed closed");
+    }
+
+    fn fresh_quote(&self, market: &str, venue: Venue, now: Instant) -> Option<Quote> {
+        let quote = self.quotes.get(&(market.to_string(), venue))?;
+        if now.duration_since(quote.received_at) > self.config.max_quote_age {
+            return None;
+        }
+        if quote.bid <= Decimal::ZERO || quote.ask <= Decimal::ZERO {
+            return None;
+        }
+        Some(*quote)
+    }
+
+    fn evaluate(&self, market: &str, venue: Venue, now: Instant) -> Option<SpreadOpportunity> {
+        let this = self.fresh_quote(market, venue, now)?;
+        let other_venue = venue.other();
+        let other = self.fresh_quote(market, other_venue, now)?;
+
+        // 两个方向：在对面买入、在本所卖出；在本所买入、在对面卖出
+        let candidates = [
+            (other_venue, other.ask, venue, this.bid),
+            (venue, this.ask, other_venue, other.bid),
+        ];
+
+        candidates
+            .into_iter()
+            .map(
+                |(buy_venue, buy_price, sell_venue, sell_price)| SpreadOpportunity {
+                    market: market.to_string(),
+                    buy_venue,
+                    sell_venue,
+                    buy_price,
+                    sell_price,
+                    spread_bps: (sell_price - buy_price) / buy_price * Decimal::from(10_000),
+                },
+            )
+            .filter(|o| o.spread_bps >= self.config.threshold_bps)
+            .max_by_key(|o| o.spread_bps)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_monitor(threshold_bps: i64, max_quote_age: Duration) -> SpreadMonitor {
+        let mut monitor = SpreadMonitor::new(SpreadConfig {
+            threshold_bps: Decimal::from(threshold_bps),
+            max_quote_age,
+        });
+        monitor.add_market("BTC", "BTC-USD-PERP", "BTC");
+        monitor
+    }
+
+    /// 价格以 0.001 为单位
+    fn quote(
+        monitor: &mut SpreadMonitor,
+        venue: Venue,
+        symbol: &str,
+        bid: i64,
+        ask: i64,
+    ) -> Option<SpreadOpportunity> {
+        monitor.on_quote(venue, symbol, Decimal::new(bid, 3), Decimal::new(ask, 3))
+    }
+
+    #[test]
+    fn spread_is_computed_in_both_directions() {
+        let mut monitor = new_monitor(5, Duration::from_secs(2));
+        assert!(quote(
+            &mut monitor,
+            Venue::Paradex,
+            "BTC-USD-PERP",
+            100_000,
+            100_100
+        )
+        .is_none());
+
+        // Lighter 买一 100.2 高于 Paradex 卖一 100.1：在 Paradex 买、在 Lighter 卖
+        let opportunity = quote(&mut monitor, Venue::Lighter, "BTC", 100_200, 100_300).unwrap();
+        assert_eq!(opportunity.market, "BTC");
+        assert_eq!(opportunity.buy_venue, Venue::Paradex);
+        assert_eq!(opportunity.sell_venue, Venue::Lighter);
+        assert_eq!(opportunity.buy_price, Decimal::new(1001, 1));
+        assert_eq!(opportunity.sell_price, Decimal::new(1002, 1));
+        assert_eq!(
+            opportunity.spread_bps,
+            Decimal::new(1, 1) / Decimal::new(1001, 1) * Decimal::from(10_000)
+        );
+
+        // 反方向：Paradex 买一 100.5 高于 Lighter 卖一 100.3
+        let opportunity = quote(
+            &mut monitor,
+            Venue::Paradex,
+            "BTC-USD-PERP",
+            100_500,
+            100_600,
+        )
+        .unwrap();
+        assert_eq!(opportunity.buy_venue, Venue::Lighter);
+        assert_eq!(opportunity.sell_venue, Venue::Paradex);
+        assert_eq!(opportunity.buy_price, Decimal::new(1003, 1));
+    }
+
+    #[test]
+    fn only_spreads_above_the_threshold_fire() {
+        let mut monitor = new_monitor(10, Duration::from_secs(2));
+        let fired = Arc::new(Mutex::new(Vec::new()));
+        let sink = fired.clone();
+        monitor.on_opportunity(Box::new(move |o| sink.lock().unwrap().push(o.spread_bps)));
+
+        quote(
+            &mut monitor,
+            Venue::Paradex,
+            "BTC-USD-PERP",
+            99_900,
+            100_000,
+        );
+        // 100.05 vs 100.0：5 bps，低于阈值
+        assert!(quote(&mut monitor, Venue::Lighter, "BTC", 100_050, 100_200).is_none());
+        // 100.125 vs 100.0：12.5 bps，超过阈值
+        assert!(quote(&mut monitor, Venue::Lighter, "BTC", 100_125, 100_200).is_some());
+        assert_eq!(*fired.lock().unwrap(), [Decimal::new(125, 1)]);
+    }
+
+    #[test]
+    fn stale_and_one_sided_quotes_are_ignored() {
+        let mut monitor = new_monitor(1, Duration::from_millis(1));
+        quote(
+            &mut monitor,
+            Venue::Paradex,
+            "BTC-USD-PERP",
+            99_000,
+            100_000,
+        );
+        std::thread::sleep(Duration::from_millis(5));
+        assert!(quote(&mut monitor, Venue::Lighter, "BTC", 101_000, 102_000).is_none());
+
+        let mut monitor = new_monitor(1, Duration::from_secs(2));
+        quote(
+            &mut monitor,
+            Venue::Paradex,
+            "BTC-USD-PERP",
+            99_000,
+            100_000,
+        );
+        // 缺少卖一或买一的报价不参与比较
+        assert!(quote(&mut monitor, Venue::Lighter, "BTC", 101_000, 0).is_none());
+        assert!(quote(&mut monitor, Venue::Lighter, "BTC", 0, 102_000).is_none());
+        // 未映射的符号直接忽略
+        assert!(quote(&mut monitor, Venue::Lighter, "ETH", 101_000, 102_000).is_none());
+    }
+
+    #[test]
+    fn paradex_bbo_is_converted_without_float_noise() {
+        let mut monitor = new_monitor(5, Duration::from_secs(2));
+        quote(&mut monitor, Venue::Lighter, "BTC", 100_200, 100_300);
+        let bbo = BBO {
+            bid: 99.9,
+            bid_size: 1.0,
+            ask: 100.1,
+            ask_size: 1.0,
+            market: "BTC-USD-PERP".to_string(),
+            last_updated_at: 0,
+        };
+        let opportunity = monitor.on_bbo(&bbo).unwrap();
+        assert_eq!(opportunity.buy_price, Decimal::new(1001, 1));
+    }
+
+    #[tokio::test]
+    async fn feed_pushes_external_quotes_into_the_monitor() {
+        let monitor = Arc::new(Mutex::new(new_monitor(5, Duration::from_secs(2))));
+        let fired = Arc::new(Mutex::new(Vec::new()));
+        let sink = fired.clone();
+        monitor
+            .lock()
+            .unwrap()
+            .on_opportunity(Box::new(move |o| sink.lock().unwrap().push(o.sell_venue)));
+
+        let (sender, feed) = mpsc::channel(4);
+        for (venue, symbol, bid, ask) in [
+            (Venue::Paradex, "BTC-USD-PERP", 99_900, 100_000),
+            (Venue::Lighter, "BTC", 100_200, 100_300),
+        ] {
+            sender
+                .send(VenueQuote {
+                    venue,
+                    symbol: symbol.to_string(),
+                    bid: Decimal::new(bid, 3),
+                    ask: Decimal::new(ask, 3),
+                })
+                .await
+                .unwrap();
+        }
+        drop(sender);
+        SpreadMonitor::run_feed(monitor, feed).await;
+        assert_eq!(*fired.lock().unwrap(), [Venue::Lighter]);
+    }
+}
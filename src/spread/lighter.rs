@@ -0,0 +1,273 @@
+//! Lighter 公开订单簿行情：订阅 `order_book/{market_id}`，以快照与增量维护各市场的买卖盘，
+//! 最优买卖价变化时转换为 [`VenueQuote`] 推送给价差监控；断线后自动重连并重新订阅。
+
+use futures_util::{SinkExt, StreamExt};
+use log::{info, warn};
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio_tungstenite::{connect_async, tungstenite::protocol::Message as WsMessage};
+
+use super::{Venue, VenueQuote};
+
+/// Lighter 主网的行情 WebSocket 地址
+pub const MAINNET_URL: &str = "wss://mainnet.zklighter.elliot.ai/stream";
+
+/// 重连等待时间的上限，首次等待 1 秒，此后每次失败翻倍
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Deserialize)]
+struct Frame {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    channel: String,
+    order_book: Option<BookData>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BookData {
+    #[serde(default)]
+    asks: Vec<Level>,
+    #[serde(default)]
+    bids: Vec<Level>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Level {
+    price: Decimal,
+    size: Decimal,
+}
+
+/// 一个市场的本地买卖盘；数量为 0 的档位表示删除
+#[derive(Debug, Default)]
+struct Book {
+    bids: BTreeMap<Decimal, Decimal>,
+    asks: BTreeMap<Decimal, Decimal>,
+    /// 最近一次推送的最优买卖价，未变化时不重复推送
+    last: Option<(Decimal, Decimal)>,
+}
+
+impl Book {
+    fn apply(&mut self, data: BookData, snapshot: bool) {
+        if snapshot {
+            self.bids.clear();
+            self.asks.clear();
+        }
+        for (side, levels) in [(&mut self.bids, data.bids), (&mut self.asks, data.asks)] {
+            for level in levels {
+                if level.size.is_zero() {
+                    side.remove(&level.price);
+                } else {
+                    side.insert(level.price, level.size);
+                }
+            }
+        }
+    }
+
+    /// 最优买卖价变化时返回新的报价；任一侧为空时不返回
+    fn top_changed(&mut self) -> Option<(Decimal, Decimal)> {
+        let bid = *self.bids.keys().next_back()?;
+        let ask = *self.asks.keys().next()?;
+        if self.last == Some((bid, ask)) {
+            return None;
+        }
+        self.last = Some((bid, ask));
+        Some((bid, ask))
+    }
+}
+
+/// Lighter 订单簿行情源
+pub struct LighterFeed {
+    url: String,
+    /// market_id -> Lighter 市场符号
+    markets: BTreeMap<u32, String>,
+    books: HashMap<u32, Book>,
+}
+
+impl LighterFeed {
+    /// 连接 `url`，订阅 `markets` 中 (market_id, 符号) 对应的订单簿
+    pub fn new(url: impl Into<String>, markets: impl IntoIterator<Item = (u32, String)>) -> Self {
+        Self {
+            url: url.into(),
+            markets: markets.into_iter().collect(),
+            books: HashMap::new(),
+        }
+    }
+
+    /// 在后台维护连接，把报价发送到 `sender`；`sender` 的接收端关闭后任务结束
+    pub fn spawn(mut self, sender: mpsc::Sender<VenueQuote>) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut backoff = Duration::from_secs(1);
+            loop {
+                match self.run_connection(&sender).await {
+                    Ok(()) => {
+                        backoff = Duration::from_secs(1);
+                    }
+                    Err(e) => warn!("Lighter order book connection failed: {}", e),
+                }
+                if sender.is_closed() {
+                    break;
+                }
+                warn!("Lighter order book connection closed, reconnecting");
+                tokio::select! {
+                    _ = tokio::time::sleep(backoff) => {}
+                    _ = sender.closed() => break,
+                }
+                backoff = (backoff * 2).min(MAX_BACKOFF);
+            }
+        })
+    }
+
+    /// 单条连接的生命周期：订阅、转发报价，直到连接关闭或接收端关闭
+    async fn run_connection(&mut self, sender: &mpsc::Sender<VenueQuote>) -> Result<(), String> {
+        let (mut connection, _) = connect_async(&self.url).await.map_err(|e| e.to_string())?;
+        // 重连后以新的快照重建买卖盘
+        self.books.clear();
+        for market_id in self.markets.keys() {
+            let subscribe =
+                json!({"type": "subscribe", "channel": format!("order_book/{}", market_id)});
+            connection
+                .send(WsMessage::text(subscribe.to_string()))
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+        info!(
+            "Subscribed to Lighter order books {}",
+            self.markets.values().cloned().collect::<Vec<_>>().join(",")
+        );
+
+        loop {
+            let frame = tokio::select! {
+                frame = connection.next() => frame,
+                _ = sender.closed() => return Ok(()),
+            };
+            let Some(frame) = frame else {
+                return Ok(());
+            };
+            let text = match frame.map_err(|e| e.to_string())? {
+                WsMessage::Text(text) => text,
+                WsMessage::Close(_) => return Ok(()),
+                _ => continue,
+            };
+            if is_ping(&text) {
+                connection
+                    .send(WsMessage::text(json!({"type": "pong"}).to_string()))
+                    .await
+                    .map_err(|e| e.to_string())?;
+                continue;
+            }
+            if let Some(quote) = self.on_frame(&text) {
+                if sender.send(quote).await.is_err() {
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    /// 处理一帧订单簿快照或增量，最优买卖价变化时返回报价；其他帧忽略
+    fn on_frame(&mut self, text: &str) -> Option<VenueQuote> {
+        let frame: Frame = match serde_json::from_str(text) {
+            Ok(frame) => frame,
+            Err(e) => {
+                warn!("Failed to parse Lighter message: {}", e);
+                return None;
+            }
+        };
+        let snapshot = match frame.kind.as_str() {
+            "subscribed/order_book" => true,
+            "update/order_book" => false,
+            _ => return None,
+        };
+        // 频道名形如 `order_book:1`
+        let market_id: u32 = frame.channel.rsplit(':').next()?.parse().ok()?;
+        let symbol = self.markets.get(&market_id)?;
+        let book = self.books.entry(market_id).or_default();
+        book.apply(frame.order_book?, snapshot);
+        let (bid, ask) = book.top_changed()?;
+        Some(VenueQuote {
+            venue: Venue::Lighter,
+            symbol: symbol.clone(),
+            bid,
+            ask,
+        })
+    }
+}
+
+fn is_ping(text: &str) -> bool {
+    serde_json::from_str::<Frame>(text).is_ok_and(|frame| frame.kind == "ping")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    const SNAPSHOT: &str = r#"{"channel":"order_book:1","offset":1,"order_book":{"code":0,"asks":[{"price":"100.3","size":"2"},{"price":"100.4","size":"1"}],"bids":[{"price":"100.1","size":"1"},{"price":"100.0","size":"3"}],"offset":1},"type":"subscribed/order_book"}"#;
+
+    fn feed() -> LighterFeed {
+        LighterFeed::new("ws://unused", [(1, "BTC".to_string())])
+    }
+
+    fn quote(bid: i64, ask: i64) -> VenueQuote {
+        VenueQuote {
+            venue: Venue::Lighter,
+            symbol: "BTC".to_string(),
+            bid: Decimal::new(bid, 1),
+            ask: Decimal::new(ask, 1),
+        }
+    }
+
+    #[test]
+    fn book_updates_move_the_top_of_book() {
+        let mut feed = feed();
+        assert_eq!(feed.on_frame(SNAPSHOT), Some(quote(1001, 1003)));
+
+        // 卖一被吃掉，新的买一挂出
+        let update = r#"{"channel":"order_book:1","order_book":{"asks":[{"price":"100.3","size":"0"}],"bids":[{"price":"100.2","size":"1"}]},"type":"update/order_book"}"#;
+        assert_eq!(feed.on_frame(update), Some(quote(1002, 1004)));
+
+        // 只改变深处档位的增量不推送
+        let deep = r#"{"channel":"order_book:1","order_book":{"asks":[],"bids":[{"price":"99.0","size":"5"}]},"type":"update/order_book"}"#;
+        assert_eq!(feed.on_frame(deep), None);
+
+        // 未订阅的市场与其他类型的帧忽略
+        let other = SNAPSHOT.replace("order_book:1", "order_book:7");
+        assert_eq!(feed.on_frame(&other), None);
+        assert_eq!(feed.on_frame(r#"{"type":"connected"}"#), None);
+    }
+
+    #[tokio::test]
+    async fn feed_subscribes_and_forwards_quotes() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        let (subscribed, mut subscriptions) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut connection = tokio_tungstenite::accept_async(stream).await.unwrap();
+            if let Some(Ok(WsMessage::Text(text))) = connection.next().await {
+                subscribed
+                    .send(serde_json::from_str::<serde_json::Value>(&text).unwrap())
+                    .unwrap();
+            }
+            connection.send(WsMessage::text(SNAPSHOT)).await.unwrap();
+            // 保持连接直到客户端断开
+            while connection.next().await.is_some() {}
+        });
+
+        let (sender, mut quotes) = mpsc::channel(4);
+        let task = LighterFeed::new(url, [(1, "BTC".to_string())]).spawn(sender);
+        assert_eq!(
+            subscriptions.recv().await.unwrap(),
+            json!({"type": "subscribe", "channel": "order_book/1"})
+        );
+        assert_eq!(quotes.recv().await, Some(quote(1001, 1003)));
+
+        drop(quotes);
+        task.await.unwrap();
+    }
+}
@@ -0,0 +1,13 @@
+//! 执行策略：在 [`crate::trading::OrderManager`] 之上按计划拆分与提交订单
+
+mod grid;
+mod mm;
+mod trailing;
+mod twap;
+
+pub use grid::{GridConfig, GridError, GridLevel, GridTrader};
+pub use mm::{MakerConfig, MakerError, SimpleMaker};
+pub use trailing::{
+    TrailDistance, TrailMode, TrailReport, TrailingConfig, TrailingError, TrailingStop,
+};
+pub use twap::{TwapConfig, TwapError, TwapExecutor, TwapOutcome, TwapReport, TwapSlice};
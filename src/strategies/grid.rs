@@ -0,0 +1,735 @@
+//! 网格交易：在中心价上下按固定间隔挂 POST_ONLY 买卖单，任一档成交后在相邻一档挂反向单，维持网格
+//!
+//! 中心价两侧各 `levels` 档，买单在下、卖单在上，中心价所在的一档留空。买单在 p 成交后于 p + 间隔挂卖单，
+//! 卖单在 p 成交后于 p - 间隔挂买单。按持仓上限计算：某一方向的挂单全部成交后持仓会超过上限时，
+//! 暂停该方向尚未挂出的档位并记录错误，持仓回落后恢复；风控拒绝下单同样暂停该方向。
+//! 撤单、被拒绝或断线期间错过的订单在下一次成交或 [`GridTrader::reconcile`] 时按
+//! [`OrderManager::open_orders`] 重新挂出。
+
+use log::{error, info, warn};
+use paradex::structs::{OrderInstruction, OrderType, Side};
+use paradex::ws::Message;
+use rust_decimal::Decimal;
+use std::collections::btree_map::{BTreeMap, Entry};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use thiserror::Error;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::Notify;
+use tokio_util::sync::CancellationToken;
+
+use crate::gateway::GatewayError;
+use crate::market_data::BboCache;
+use crate::orders::{OrderError, OrderSpec};
+use crate::trading::{NewOrder, OrderEvent, OrderManager, OrderManagerError, OrderState};
+
+/// 网格参数无效的原因
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum GridError {
+    #[error("grid spacing must be positive, got {0}")]
+    InvalidSpacing(Decimal),
+    #[error("grid needs at least one level per side")]
+    InvalidLevels,
+    #[error("grid level size must be positive, got {0}")]
+    InvalidSize(Decimal),
+    #[error("no center price for {0}: set grid.center_price or wait for a BBO")]
+    NoCenterPrice(String),
+}
+
+/// 网格参数
+#[derive(Debug, Clone, PartialEq)]
+pub struct GridConfig {
+    pub symbol: String,
+    /// 网格中心价；`None` 时取启动时的 BBO 中间价
+    pub center: Option<Decimal>,
+    /// 相邻两档的价格间隔
+    pub spacing: Decimal,
+    /// 每侧档位数
+    pub levels: u32,
+    /// 每档订单数量
+    pub size: Decimal,
+    /// 市场价格精度：中心价四舍五入对齐，买档向下、卖档向上取整；为 0 时不对齐
+    pub price_tick: Decimal,
+}
+
+impl GridConfig {
+    /// 检查间距、档数与每档数量
+    pub fn validate(&self) -> Result<(), GridError> {
+        if self.spacing <= Decimal::ZERO {
+            return Err(GridError::InvalidSpacing(self.spacing));
+        }
+        if self.levels == 0 {
+            return Err(GridError::InvalidLevels);
+        }
+        if self.size <= Decimal::ZERO {
+            return Err(GridError::InvalidSize(self.size));
+        }
+        Ok(())
+    }
+}
+
+/// 网格的一档
+#[derive(Debug, Clone, PartialEq)]
+pub struct GridLevel {
+    pub price: Decimal,
+    pub side: Side,
+    /// 挂单的交易所订单 ID；尚未挂出（暂停、被拒绝或等待重新挂单）时为 `None`
+    pub order_id: Option<String>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    center: Option<Decimal>,
+    /// 价格 -> 该档的方向与挂单
+    levels: BTreeMap<Decimal, GridLevel>,
+    /// 网格成交累计的持仓，多头为正
+    position: Decimal,
+    buys_filled: u32,
+    sells_filled: u32,
+    buys_paused: bool,
+    sells_paused: bool,
+}
+
+impl State {
+    /// 方向 `side` 已挂出的订单数量合计
+    fn resting(&self, side: Side, size: Decimal) -> Decimal {
+        let count = self
+            .levels
+            .values()
+            .filter(|level| level.side == side && level.order_id.is_some())
+            .count();
+        size * Decimal::from(count)
+    }
+
+    fn paused(&mut self, side: Side) -> &mut bool {
+        match side {
+            Side::BUY => &mut self.buys_paused,
+            Side::SELL => &mut self.sells_paused,
+        }
+    }
+}
+
+/// 网格交易器；克隆后共享同一份网格状态
+#[derive(Clone)]
+pub struct GridTrader {
+    config: GridConfig,
+    quotes: BboCache,
+    max_position: Option<Decimal>,
+    state: Arc<Mutex<State>>,
+    needs_reconcile: Arc<AtomicBool>,
+    reconcile: Arc<Notify>,
+}
+
+impl GridTrader {
+    /// 中心价未指定时按 `quotes` 中的中间价
+    pub fn new(config: GridConfig, quotes: BboCache) -> Result<Self, GridError> {
+        config.validate()?;
+        Ok(Self {
+            config,
+            quotes,
+            max_position: None,
+            state: Arc::default(),
+            needs_reconcile: Arc::default(),
+            reconcile: Arc::default(),
+        })
+    }
+
+    /// 持仓上限（多空取绝对值），通常与风控的 `max_position` 相同；`None` 表示只依赖风控拒绝
+    pub fn with_max_position(mut self, max_position: Option<Decimal>) -> Self {
+        self.max_position = max_position;
+        self
+    }
+
+    /// 启动时已有的持仓，计入持仓上限的检查
+    pub fn with_position(self, position: Decimal) -> Self {
+        self.state.lock().unwrap().position = position;
+        self
+    }
+
+    /// 网格参数
+    pub fn config(&self) -> &GridConfig {
+        &self.config
+    }
+
+    /// 网格各档，按价格从低到高排列
+    pub fn levels(&self) -> Vec<GridLevel> {
+        self.state
+            .lock()
+            .unwrap()
+            .levels
+            .values()
+            .cloned()
+            .collect()
+    }
+
+    /// 网格成交累计的持仓（含启动时的持仓）
+    pub fn position(&self) -> Decimal {
+        self.state.lock().unwrap().position
+    }
+
+    /// 请求在 [`Self::run`] 的下一轮循环中与挂单对账
+    pub fn request_reconcile(&self) {
+        self.reconcile.notify_one();
+    }
+
+    /// 处理私有频道消息；断线后的首次重连请求对账
+    pub fn on_message(&self, message: &Message) {
+        match message {
+            Message::Disconnected => self.needs_reconcile.store(true, Ordering::SeqCst),
+            Message::Connected if self.needs_reconcile.swap(false, Ordering::SeqCst) => {
+                self.request_reconcile()
+            }
+            _ => {}
+        }
+    }
+
+    /// 挂出网格直到 `stop` 被取消，然后撤销全部网格订单
+    pub async fn run(
+        &self,
+        orders: &OrderManager<'_>,
+        stop: &CancellationToken,
+    ) -> Result<(), GridError> {
+        // 先订阅再挂单，避免错过挂单后立即成交的推送
+        let mut events = orders.tracker().subscribe();
+        self.start(orders).await?;
+        loop {
+            tokio::select! {
+                _ = stop.cancelled() => break,
+                _ = self.reconcile.notified() => self.reconcile(orders).await,
+                event = events.recv() => match event {
+                    Ok(event) => self.on_order_event(orders, &event).await,
+                    Err(RecvError::Lagged(skipped)) => {
+                        warn!("Grid missed {} order events, reconciling", skipped);
+                        self.reconcile(orders).await;
+                    }
+                    Err(RecvError::Closed) => break,
+                },
+            }
+        }
+        self.teardown(orders).await;
+        Ok(())
+    }
+
+    /// 确定中心价并挂出两侧各 `levels` 档
+    pub async fn start(&self, orders: &OrderManager<'_>) -> Result<(), GridError> {
+        let config = &self.config;
+        let center = config
+            .center
+            .or_else(|| self.quotes.mid(&config.symbol))
+            .ok_or_else(|| GridError::NoCenterPrice(config.symbol.clone()))?;
+        let center = if config.price_tick.is_zero() {
+            center
+        } else {
+            (center / config.price_tick).round() * config.price_tick
+        };
+        {
+            let mut state = self.state.lock().unwrap();
+            state.center = Some(center);
+            state.levels.clear();
+            for k in 1..=config.levels {
+                let offset = config.spacing * Decimal::from(k);
+                for (price, side) in [(center - offset, Side::BUY), (center + offset, Side::SELL)] {
+                    let price = self.align(side, price);
+                    if price > Decimal::ZERO {
+                        // 间隔小于精度时相邻档位可能对齐到同一价格，只保留一档
+                        state.levels.entry(price).or_insert(GridLevel {
+                            price,
+                            side,
+                            order_id: None,
+                        });
+                    }
+                }
+            }
+        }
+        info!(
+            "Grid on {} around {}: {} levels per side, spacing {}, size {}",
+            config.symbol, center, config.levels, config.spacing, config.size
+        );
+        self.place_pending(orders).await;
+        Ok(())
+    }
+
+    /// 处理订单状态变化：网格订单成交后挂出反向单，撤单或被拒绝的档位等待重新挂单
+    pub async fn on_order_event(&self, orders: &OrderManager<'_>, event: &OrderEvent) {
+        if !event.order.state.is_terminal() || event.previous.is_some_and(OrderState::is_terminal) {
+            return;
+        }
+        if self.settle(
+            &event.order.id,
+            event.order.state,
+            event.order.filled_size(),
+        ) {
+            self.place_pending(orders).await;
+        }
+    }
+
+    /// 与 [`OrderManager::open_orders`] 对账：已结束的网格订单按最终状态处理，然后补挂所有空缺的档位
+    pub async fn reconcile(&self, orders: &OrderManager<'_>) {
+        let open: Vec<_> = orders
+            .open_orders()
+            .into_iter()
+            .map(|order| order.id)
+            .collect();
+        let missing: Vec<_> = self
+            .state
+            .lock()
+            .unwrap()
+            .levels
+            .values()
+            .filter_map(|level| level.order_id.clone())
+            .filter(|id| !open.contains(id))
+            .collect();
+        for id in &missing {
+            match orders.tracker().get(id) {
+                Some(order) => self.settle(id, order.state, order.filled_size()),
+                None => self.settle(id, OrderState::Cancelled, Decimal::ZERO),
+            };
+        }
+        info!(
+            "Grid on {} reconciled: {} orders no longer open",
+            self.config.symbol,
+            missing.len()
+        );
+        self.place_pending(orders).await;
+    }
+
+    /// 撤销全部网格订单
+    pub async fn teardown(&self, orders: &OrderManager<'_>) {
+        let ids: Vec<_> = self
+            .state
+            .lock()
+            .unwrap()
+            .levels
+            .values_mut()
+            .filter_map(|level| level.order_id.take())
+            .collect();
+        for id in ids {
+            if let Err(e) = orders.cancel(&id).await {
+                warn!("Failed to cancel grid order {}: {}", id, e);
+            }
+        }
+        let state = self.state.lock().unwrap();
+        info!(
+            "Grid on {} stopped: {} buys and {} sells filled, position {}",
+            self.config.symbol, state.buys_filled, state.sells_filled, state.position
+        );
+    }
+
+    /// 记录网格订单 `id` 的最终状态并计入成交；成交的档位换成相邻一档的反向单。
+    /// 返回是否为网格订单
+    fn settle(&self, id: &str, state: OrderState, filled: Decimal) -> bool {
+        let spacing = self.config.spacing;
+        let mut grid = self.state.lock().unwrap();
+        let Some(price) = grid
+            .levels
+            .values()
+            .find(|level| level.order_id.as_deref() == Some(id))
+            .map(|level| level.price)
+        else {
+            return false;
+        };
+        let side = grid.levels[&price].side;
+        grid.position += match side {
+            Side::BUY => filled,
+            Side::SELL => -filled,
+        };
+        if state != OrderState::Filled {
+            warn!(
+                "Grid {:?} order {} at {} is {:?}, it will be placed again",
+                side, id, price, state
+            );
+            grid.levels.get_mut(&price).unwrap().order_id = None;
+            return true;
+        }
+
+        let (next, next_side) = match side {
+            Side::BUY => {
+                grid.buys_filled += 1;
+                (self.align(Side::SELL, price + spacing), Side::SELL)
+            }
+            Side::SELL => {
+                grid.sells_filled += 1;
+                (self.align(Side::BUY, price - spacing), Side::BUY)
+            }
+        };
+        info!(
+            "Grid {:?} filled at {}, replacing with {:?} at {} (position {})",
+            side, price, next_side, next, grid.position
+        );
+        grid.levels.remove(&price);
+        if next > Decimal::ZERO {
+            match grid.levels.entry(next) {
+                Entry::Occupied(_) => warn!("Grid level {} is already taken, not replacing", next),
+                Entry::Vacant(entry) => {
+                    entry.insert(GridLevel {
+                        price: next,
+                        side: next_side,
+                        order_id: None,
+                    });
+                }
+            }
+        }
+        true
+    }
+
+    /// 按距中心价由近到远挂出所有空缺的档位；某一方向会超过持仓上限或被风控拒绝时暂停该方向
+    async fn place_pending(&self, orders: &OrderManager<'_>) {
+        for side in [Side::BUY, Side::SELL] {
+            let pending: Vec<_> = {
+                let state = self.state.lock().unwrap();
+                let pending = state
+                    .levels
+                    .values()
+                    .filter(|level| level.side == side && level.order_id.is_none())
+                    .map(|level| level.price);
+                match side {
+                    Side::BUY => pending.rev().collect(),
+                    Side::SELL => pending.collect(),
+                }
+            };
+            let mut blocked = None;
+            for price in pending {
+                if let Some(reason) = self.breach(side) {
+                    blocked = Some(reason);
+                    break;
+                }
+                match self.place(orders, side, price).await {
+                    Ok(id) => {
+                        if let Some(level) = self.state.lock().unwrap().levels.get_mut(&price) {
+                            level.order_id = Some(id);
+                        }
+                    }
+                    Err(ref e @ OrderManagerError::Gateway(GatewayError::Risk(_))) => {
+                        blocked = Some(e.to_string());
+                        break;
+                    }
+                    Err(e @ OrderManagerError::Order(OrderError::PostOnlyWouldCross { .. })) => {
+                        warn!("Grid {:?} at {} not placed: {}", side, price, e);
+                    }
+                    Err(e) => warn!("Failed to place grid {:?} at {}: {}", side, price, e),
+                }
+            }
+            let mut state = self.state.lock().unwrap();
+            let paused = state.paused(side);
+            match blocked {
+                Some(reason) if !*paused => {
+                    *paused = true;
+                    error!(
+                        "Grid {:?} side on {} paused: {}",
+                        side, self.config.symbol, reason
+                    );
+                }
+                None if *paused => {
+                    *paused = false;
+                    info!("Grid {:?} side on {} resumed", side, self.config.symbol);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// 档位价格对齐到市场精度，远离中心价取整：买档向下、卖档向上
+    fn align(&self, side: Side, price: Decimal) -> Decimal {
+        let tick = self.config.price_tick;
+        if tick.is_zero() {
+            return price;
+        }
+        let ticks = price / tick;
+        match side {
+            Side::BUY => ticks.floor() * tick,
+            Side::SELL => ticks.ceil() * tick,
+        }
+    }
+
+    /// 再挂一档 `side` 订单后，该方向挂单全部成交时的持仓超过上限的原因
+    fn breach(&self, side: Side) -> Option<String> {
+        let max = self.max_position?;
+        let size = self.config.size;
+        let state = self.state.lock().unwrap();
+        let exposure = match side {
+            Side::BUY => state.position + state.resting(Side::BUY, size) + size,
+            Side::SELL => state.position - state.resting(Side::SELL, size) - size,
+        };
+        (exposure.abs() > max).then(|| {
+            format!(
+                "position would reach {} if all {:?} orders filled, beyond max_position {}",
+                exposure, side, max
+            )
+        })
+    }
+
+    async fn place(
+        &self,
+        orders: &OrderManager<'_>,
+        side: Side,
+        price: Decimal,
+    ) -> Result<String, OrderManagerError> {
+        let spec = OrderSpec {
+            side,
+            order_type: OrderType::LIMIT,
+            size: self.config.size,
+            price: Some(price),
+            instruction: OrderInstruction::POST_ONLY,
+            client_id: None,
+            reduce_only: false,
+            trigger_price: None,
+        };
+        let order = orders
+            .submit(NewOrder::new(&self.config.symbol, spec))
+            .await?;
+        Ok(order.id().to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::RiskLimits;
+    use crate::paper::test_support::{factory, PaperMarket, MARKET};
+    use crate::risk::RiskGuard;
+    use std::time::Duration;
+
+    fn config() -> GridConfig {
+        GridConfig {
+            symbol: MARKET.to_string(),
+            center: None,
+            spacing: Decimal::ONE,
+            levels: 2,
+            size: Decimal::new(1, 2),
+            price_tick: Decimal::new(1, 1),
+        }
+    }
+
+    /// 中间价 100.01 的宽报价，网格各档都不会穿越盘口
+    fn market() -> PaperMarket {
+        let market = PaperMarket::new();
+        market.bbo(Decimal::new(9752, 2), Decimal::new(10250, 2));
+        market
+    }
+
+    /// 各档的价格、方向以及是否已挂单
+    fn ladder(grid: &GridTrader) -> Vec<(Decimal, Side, bool)> {
+        grid.levels()
+            .into_iter()
+            .map(|level| (level.price, level.side, level.order_id.is_some()))
+            .collect()
+    }
+
+    #[test]
+    fn config_is_validated() {
+        let quotes = BboCache::new();
+        for (config, expected) in [
+            (
+                GridConfig {
+                    spacing: Decimal::ZERO,
+                    ..config()
+                },
+                GridError::InvalidSpacing(Decimal::ZERO),
+            ),
+            (
+                GridConfig {
+                    levels: 0,
+                    ..config()
+                },
+                GridError::InvalidLevels,
+            ),
+            (
+                GridConfig {
+                    size: Decimal::NEGATIVE_ONE,
+                    ..config()
+                },
+                GridError::InvalidSize(Decimal::NEGATIVE_ONE),
+            ),
+        ] {
+            assert_eq!(
+                GridTrader::new(config, quotes.clone()).err(),
+                Some(expected)
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn fills_are_replaced_one_spacing_away_and_teardown_cancels_everything() {
+        let market = market();
+        let orders = market.manager("grid");
+        let grid = GridTrader::new(config(), market.quotes.clone()).unwrap();
+        let stop = CancellationToken::new();
+
+        let script = async {
+            // 中心价按 0.1 的精度对齐到 100.0
+            let initial = [
+                (Side::BUY, Decimal::from(98)),
+                (Side::BUY, Decimal::from(99)),
+                (Side::SELL, Decimal::from(101)),
+                (Side::SELL, Decimal::from(102)),
+            ];
+            market
+                .wait_for("the initial grid", || market.resting() == initial)
+                .await;
+
+            // 买一档成交后在中心价挂卖单
+            market.trade(Decimal::new(985, 1), Decimal::ONE);
+            let replaced = [
+                (Side::BUY, Decimal::from(98)),
+                (Side::SELL, Decimal::from(100)),
+                (Side::SELL, Decimal::from(101)),
+                (Side::SELL, Decimal::from(102)),
+            ];
+            market
+                .wait_for("the replacement sell", || market.resting() == replaced)
+                .await;
+            assert_eq!(grid.position(), Decimal::new(1, 2));
+
+            // 卖单成交后回到初始网格
+            market.trade(Decimal::new(1005, 1), Decimal::ONE);
+            market
+                .wait_for("the initial grid", || market.resting() == initial)
+                .await;
+            assert_eq!(grid.position(), Decimal::ZERO);
+            assert_eq!(
+                market.exchange.position(MARKET).unwrap().size,
+                Decimal::ZERO
+            );
+            stop.cancel();
+        };
+        let (result, ()) = tokio::join!(grid.run(&orders, &stop), script);
+        result.unwrap();
+        assert!(market.exchange.open_orders().is_empty());
+        assert!(ladder(&grid).iter().all(|(_, _, placed)| !placed));
+    }
+
+    #[tokio::test]
+    async fn levels_are_rounded_to_the_tick_away_from_the_center() {
+        let market = market();
+        let orders = market.manager("grid");
+        let grid = GridTrader::new(
+            GridConfig {
+                center: Some(Decimal::new(10003, 2)),
+                spacing: Decimal::new(25, 2),
+                ..config()
+            },
+            market.quotes.clone(),
+        )
+        .unwrap();
+
+        grid.start(&orders).await.unwrap();
+        // 中心价 100.03 对齐到 100.0；99.75 / 100.25 分别向下、向上取整
+        assert_eq!(
+            market.resting(),
+            [
+                (Side::BUY, Decimal::new(995, 1)),
+                (Side::BUY, Decimal::new(997, 1)),
+                (Side::SELL, Decimal::new(1003, 1)),
+                (Side::SELL, Decimal::new(1005, 1)),
+            ]
+        );
+
+        // 99.7 成交后反向卖单 99.95 向上取整到 100.0
+        let mut events = market.tracker.subscribe();
+        market.trade(Decimal::new(996, 1), Decimal::ONE);
+        while let Ok(event) = events.try_recv() {
+            grid.on_order_event(&orders, &event).await;
+        }
+        assert_eq!(
+            market.resting(),
+            [
+                (Side::BUY, Decimal::new(995, 1)),
+                (Side::SELL, Decimal::from(100)),
+                (Side::SELL, Decimal::new(1003, 1)),
+                (Side::SELL, Decimal::new(1005, 1)),
+            ]
+        );
+        grid.teardown(&orders).await;
+    }
+
+    #[tokio::test]
+    async fn sides_that_would_breach_max_position_are_paused() {
+        let market = market();
+        let limits = RiskLimits {
+            max_order_size: Decimal::ONE,
+            max_position: Some(Decimal::new(15, 3)),
+            max_notional: Decimal::from(1_000_000),
+            funding_alert_bps: None,
+            max_slippage_bps: None,
+            min_free_collateral: None,
+        };
+        let exchange = market.exchange.as_ref();
+        let guard = RiskGuard::new(exchange, exchange, limits.clone());
+        let orders = OrderManager::new(&guard, market.tracker.clone(), factory("grid"));
+        let grid = GridTrader::new(config(), market.quotes.clone())
+            .unwrap()
+            .with_max_position(limits.max_position);
+
+        grid.start(&orders).await.unwrap();
+        // 每个方向只挂出最近的一档，第二档会使持仓达到 0.02
+        assert_eq!(
+            ladder(&grid),
+            [
+                (Decimal::from(98), Side::BUY, false),
+                (Decimal::from(99), Side::BUY, true),
+                (Decimal::from(101), Side::SELL, true),
+                (Decimal::from(102), Side::SELL, false),
+            ]
+        );
+
+        let mut events = market.tracker.subscribe();
+        market.trade(Decimal::new(985, 1), Decimal::ONE);
+        while let Ok(event) = events.try_recv() {
+            grid.on_order_event(&orders, &event).await;
+        }
+        // 持仓 0.01：买方向仍暂停，卖方向可以在 100 与 101 各挂一档
+        assert_eq!(
+            ladder(&grid),
+            [
+                (Decimal::from(98), Side::BUY, false),
+                (Decimal::from(100), Side::SELL, true),
+                (Decimal::from(101), Side::SELL, true),
+                (Decimal::from(102), Side::SELL, false),
+            ]
+        );
+        grid.teardown(&orders).await;
+        assert!(exchange.open_orders().is_empty());
+    }
+
+    #[tokio::test]
+    async fn reconcile_replaces_orders_missed_while_disconnected() {
+        let market = market();
+        let orders = market.manager("grid");
+        let grid = GridTrader::new(
+            GridConfig {
+                center: Some(Decimal::from(100)),
+                ..config()
+            },
+            market.quotes.clone(),
+        )
+        .unwrap();
+        grid.start(&orders).await.unwrap();
+
+        // 断线期间：买一档成交、卖二档被撤销，网格没有处理这些推送
+        grid.on_message(&Message::Disconnected);
+        market.trade(Decimal::new(985, 1), Decimal::ONE);
+        let sell = market
+            .exchange
+            .open_orders()
+            .into_iter()
+            .find(|order| order.price == Some(Decimal::from(102)))
+            .unwrap();
+        orders.cancel(&sell.id).await.unwrap();
+        assert_eq!(market.exchange.open_orders().len(), 2);
+
+        grid.on_message(&Message::Connected);
+        let requested = tokio::time::timeout(Duration::from_secs(1), grid.reconcile.notified());
+        assert!(requested.await.is_ok());
+        grid.reconcile(&orders).await;
+        assert_eq!(
+            market.resting(),
+            [
+                (Side::BUY, Decimal::from(98)),
+                (Side::SELL, Decimal::from(100)),
+                (Side::SELL, Decimal::from(101)),
+                (Side::SELL, Decimal::from(102)),
+            ]
+        );
+        assert_eq!(grid.position(), Decimal::new(1, 2));
+    }
+}
@@ -0,0 +1,869 @@
+//! 简单做市：在每个市场的参考价两侧各维持一张 POST_ONLY 买单与卖单
+//!
+//! 参考价取本地订单簿的微价格，订单簿不可用时取 BBO 中间价；报价为参考价 ± `half_spread_bps`，
+//! 按价格精度向远离盘口的方向取整。目标价与挂单价相差超过 `tolerance_ticks` 个价格精度时改价：
+//! 先经 [`OrderManager::amend`] 修改挂单，失败时撤单重挂；同一市场两次改价至少间隔
+//! `min_requote_interval`。行情停滞或订单簿异常时立即撤下该市场的报价，恢复后重新报价。
+//!
+//! 按持仓调整报价：两侧报价整体偏移 `skew_bps_per_unit × 持仓` 个基点，多头时卖价靠近参考价、买价远离，
+//! 空头相反（减仓一侧最多偏移到参考价）。持仓达到 `max_inventory` 时停止报价继续积累持仓的一侧。
+
+use log::{info, warn};
+use paradex::structs::{OrderInstruction, OrderType, Side};
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use std::collections::{BTreeMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use thiserror::Error;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::Notify;
+use tokio::time::{Instant, MissedTickBehavior};
+use tokio_util::sync::CancellationToken;
+
+use crate::config::WsChannel;
+use crate::market_data::{
+    microprice, Anomaly, BboCache, FeedStatus, MarketEvent, OrderBooks, SubscriptionId,
+};
+use crate::metrics::metrics;
+use crate::orders::{passive_price, OrderSpec};
+use crate::positions::PositionCache;
+use crate::trading::{NewOrder, OrderEvent, OrderManager, OrderManagerError, OrderState};
+
+/// 输出各市场持仓与报价偏移的间隔
+const INVENTORY_LOG_INTERVAL: Duration = Duration::from_secs(30);
+
+/// 停滞时撤下报价的行情频道
+const QUOTE_CHANNELS: [WsChannel; 3] = [
+    WsChannel::Bbo,
+    WsChannel::OrderBook,
+    WsChannel::OrderBookDeltas,
+];
+
+/// 做市参数无效的原因
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum MakerError {
+    #[error("market making needs at least one symbol")]
+    NoSymbols,
+    #[error("half spread must be in (0, 10000) bps, got {0}")]
+    InvalidSpread(Decimal),
+    #[error("quote size must be positive, got {0}")]
+    InvalidSize(Decimal),
+    #[error("minimum requote interval must be positive")]
+    InvalidInterval,
+    #[error("inventory skew must not be negative, got {0}")]
+    InvalidSkew(Decimal),
+    #[error("max inventory must be positive, got {0}")]
+    InvalidInventory(Decimal),
+}
+
+/// 做市参数
+#[derive(Debug, Clone, PartialEq)]
+pub struct MakerConfig {
+    pub symbols: Vec<String>,
+    /// 买卖报价相对参考价的偏移（基点）
+    pub half_spread_bps: Decimal,
+    /// 每侧报价数量
+    pub size: Decimal,
+    /// 目标价与挂单价相差超过该数量的价格精度时改价
+    pub tolerance_ticks: u32,
+    /// 各市场的价格精度；未列出的市场不对齐，目标价有任何变化都会改价
+    pub price_ticks: BTreeMap<String, Decimal>,
+    /// 同一市场两次改价的最短间隔
+    pub min_requote_interval: Duration,
+    /// 每单位持仓使报价整体偏移的基点数，0 表示不按持仓调整
+    pub skew_bps_per_unit: Decimal,
+    /// 持仓（多空取绝对值）达到该值时停止报价积累持仓的一侧；`None` 表示不限制
+    pub max_inventory: Option<Decimal>,
+}
+
+impl MakerConfig {
+    /// 检查市场列表、半价差与报价数量
+    pub fn validate(&self) -> Result<(), MakerError> {
+        if self.symbols.is_empty() {
+            return Err(MakerError::NoSymbols);
+        }
+        if self.half_spread_bps <= Decimal::ZERO || self.half_spread_bps >= Decimal::from(10_000) {
+            return Err(MakerError::InvalidSpread(self.half_spread_bps));
+        }
+        if self.size <= Decimal::ZERO {
+            return Err(MakerError::InvalidSize(self.size));
+        }
+        if self.min_requote_interval.is_zero() {
+            return Err(MakerError::InvalidInterval);
+        }
+        if self.skew_bps_per_unit < Decimal::ZERO {
+            return Err(MakerError::InvalidSkew(self.skew_bps_per_unit));
+        }
+        if let Some(max) = self.max_inventory.filter(|max| *max <= Decimal::ZERO) {
+            return Err(MakerError::InvalidInventory(max));
+        }
+        Ok(())
+    }
+
+    fn price_tick(&self, symbol: &str) -> Decimal {
+        self.price_ticks.get(symbol).copied().unwrap_or_default()
+    }
+}
+
+/// 一侧的挂单
+#[derive(Debug, Clone, PartialEq)]
+struct Resting {
+    id: String,
+    price: Decimal,
+}
+
+#[derive(Debug, Default)]
+struct Quotes {
+    bid: Option<Resting>,
+    ask: Option<Resting>,
+    /// 处于停滞状态的行情订阅
+    stale: HashSet<SubscriptionId>,
+    /// 尚未处理的订单簿异常
+    anomaly: Option<String>,
+    /// 报价因行情问题被撤下
+    pulled: bool,
+    /// 因持仓达到上限停止报价的买方向与卖方向
+    bid_capped: bool,
+    ask_capped: bool,
+    /// 最近一次报价时的持仓与偏移（基点）
+    inventory: Decimal,
+    skew_bps: Decimal,
+    last_requote: Option<Instant>,
+}
+
+impl Quotes {
+    fn side(&mut self, side: Side) -> &mut Option<Resting> {
+        match side {
+            Side::BUY => &mut self.bid,
+            Side::SELL => &mut self.ask,
+        }
+    }
+
+    fn capped(&mut self, side: Side) -> &mut bool {
+        match side {
+            Side::BUY => &mut self.bid_capped,
+            Side::SELL => &mut self.ask_capped,
+        }
+    }
+}
+
+fn label(side: Side) -> &'static str {
+    match side {
+        Side::BUY => "bid",
+        Side::SELL => "ask",
+    }
+}
+
+/// 简单做市商；克隆后共享同一份报价状态
+#[derive(Clone)]
+pub struct SimpleMaker {
+    config: MakerConfig,
+    quotes: BboCache,
+    books: Option<OrderBooks>,
+    positions: Option<PositionCache>,
+    state: Arc<Mutex<BTreeMap<String, Quotes>>>,
+    wake: Arc<Notify>,
+}
+
+impl SimpleMaker {
+    /// 参数无效时返回错误；`quotes` 提供各市场的买卖一档
+    pub fn new(config: MakerConfig, quotes: BboCache) -> Result<Self, MakerError> {
+        config.validate()?;
+        let state = config
+            .symbols
+            .iter()
+            .map(|symbol| (symbol.clone(), Quotes::default()))
+            .collect();
+        Ok(Self {
+            config,
+            quotes,
+            books: None,
+            positions: None,
+            state: Arc::new(Mutex::new(state)),
+            wake: Arc::default(),
+        })
+    }
+
+    /// 以订单簿的微价格为参考价；订单簿可疑期间不报价
+    pub fn with_order_books(mut self, books: OrderBooks) -> Self {
+        self.books = Some(books);
+        self
+    }
+
+    /// 按 `positions` 中的持仓调整报价；未设置时视为没有持仓
+    pub fn with_positions(mut self, positions: PositionCache) -> Self {
+        self.positions = Some(positions);
+        self
+    }
+
+    /// 做市参数
+    pub fn config(&self) -> &MakerConfig {
+        &self.config
+    }
+
+    /// `symbol` 当前的买卖挂单价
+    pub fn resting(&self, symbol: &str) -> (Option<Decimal>, Option<Decimal>) {
+        let state = self.state.lock().unwrap();
+        let Some(quotes) = state.get(symbol) else {
+            return (None, None);
+        };
+        let price = |resting: &Option<Resting>| resting.as_ref().map(|r| r.price);
+        (price(&quotes.bid), price(&quotes.ask))
+    }
+
+    /// 处理行情事件：做市市场的 BBO 变化时唤醒 [`Self::run`] 检查是否需要改价
+    pub fn on_event(&self, event: &MarketEvent) {
+        if let MarketEvent::Bbo(bbo) = event {
+            if self.state.lock().unwrap().contains_key(&bbo.symbol) {
+                self.wake.notify_one();
+            }
+        }
+    }
+
+    /// 处理停滞检测的通知：做市市场的 BBO 或订单簿停滞时撤下报价，全部恢复后重新报价
+    pub fn on_feed_status(&self, status: &FeedStatus) {
+        if !QUOTE_CHANNELS.contains(&status.channel) {
+            return;
+        }
+        let Some(market) = &status.market else {
+            return;
+        };
+        let mut state = self.state.lock().unwrap();
+        let Some(quotes) = state.get_mut(market) else {
+            return;
+        };
+        if status.stale {
+            quotes.stale.insert(status.id);
+        } else {
+            quotes.stale.remove(&status.id);
+        }
+        self.wake.notify_one();
+    }
+
+    /// 处理订单簿异常：立即撤下该市场的报价
+    pub fn on_anomaly(&self, anomaly: &Anomaly) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(quotes) = state.get_mut(&anomaly.market) {
+            quotes.anomaly = Some(anomaly.to_string());
+            self.wake.notify_one();
+        }
+    }
+
+    /// 报价直到 `stop` 被取消，然后撤销全部报价
+    pub async fn run(&self, orders: &OrderManager<'_>, stop: &CancellationToken) {
+        // 先订阅再挂单，避免错过挂单后立即成交的推送
+        let mut events = orders.tracker().subscribe();
+        // 被限频推迟的改价在下一次定时检查时执行
+        let mut ticker = tokio::time::interval(self.config.min_requote_interval);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        let mut inventory_log = tokio::time::interval(INVENTORY_LOG_INTERVAL);
+        inventory_log.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        info!(
+            "Making markets on {} at ±{} bps, size {}",
+            self.config.symbols.join(", "),
+            self.config.half_spread_bps,
+            self.config.size
+        );
+        loop {
+            tokio::select! {
+                _ = stop.cancelled() => break,
+                _ = self.wake.notified() => self.refresh(orders).await,
+                _ = ticker.tick() => self.refresh(orders).await,
+                _ = inventory_log.tick() => self.log_inventory(),
+                event = events.recv() => match event {
+                    Ok(event) => self.on_order_event(&event),
+                    Err(RecvError::Lagged(skipped)) => {
+                        warn!("Maker missed {} order events, re-checking quotes", skipped);
+                        self.forget_closed(orders);
+                        self.wake.notify_one();
+                    }
+                    Err(RecvError::Closed) => break,
+                },
+            }
+        }
+        self.teardown(orders).await;
+    }
+
+    /// 检查各市场的报价：行情有问题时撤下，否则补挂缺失的一侧并按容差与限频改价
+    pub async fn refresh(&self, orders: &OrderManager<'_>) {
+        for symbol in &self.config.symbols {
+            self.requote(orders, symbol).await;
+        }
+    }
+
+    /// 处理订单状态变化：报价成交、撤销或被拒绝后该侧在下一次检查时重新挂出
+    pub fn on_order_event(&self, event: &OrderEvent) {
+        if !event.order.state.is_terminal() || event.previous.is_some_and(OrderState::is_terminal) {
+            return;
+        }
+        let order = &event.order;
+        let mut state = self.state.lock().unwrap();
+        let Some(quotes) = state.get_mut(&order.market) else {
+            return;
+        };
+        let side = quotes.side(order.side);
+        if side.as_ref().is_none_or(|resting| resting.id != order.id) {
+            return;
+        }
+        let resting = side.take().unwrap();
+        match order.state {
+            OrderState::Filled => info!(
+                "Maker {} {} filled {} at {}",
+                order.market,
+                label(order.side),
+                order.filled_size(),
+                resting.price
+            ),
+            state => warn!(
+                "Maker {} {} at {} is {:?}, requoting",
+                order.market,
+                label(order.side),
+                resting.price,
+                state
+            ),
+        }
+        self.wake.notify_one();
+    }
+
+    /// 输出各市场最近一次报价时的持仓与偏移
+    pub fn log_inventory(&self) {
+        for (symbol, quotes) in self.state.lock().unwrap().iter() {
+            info!(
+                "Maker {} inventory {}, skew {} bps",
+                symbol, quotes.inventory, quotes.skew_bps
+            );
+        }
+    }
+
+    /// 撤销全部报价
+    pub async fn teardown(&self, orders: &OrderManager<'_>) {
+        for symbol in &self.config.symbols {
+            self.pull(orders, symbol).await;
+        }
+        info!("Maker on {} stopped", self.config.symbols.join(", "));
+    }
+
+    async fn requote(&self, orders: &OrderManager<'_>, symbol: &str) {
+        if let Some(reason) = self.pull_reason(symbol) {
+            let pulled = {
+                let mut state = self.state.lock().unwrap();
+                let quotes = state.get_mut(symbol).unwrap();
+                !std::mem::replace(&mut quotes.pulled, true)
+            };
+            if pulled {
+                warn!("Maker pulling quotes on {}: {}", symbol, reason);
+            }
+            self.pull(orders, symbol).await;
+            return;
+        }
+        let Some(fair) = self.fair_value(symbol) else {
+            return;
+        };
+        let throttled = {
+            let mut state = self.state.lock().unwrap();
+            let quotes = state.get_mut(symbol).unwrap();
+            if std::mem::take(&mut quotes.pulled) {
+                info!("Maker quotes on {} resumed", symbol);
+            }
+            quotes
+                .last_requote
+                .is_some_and(|at| at.elapsed() < self.config.min_requote_interval)
+        };
+        let inventory = self
+            .positions
+            .as_ref()
+            .map_or(Decimal::ZERO, |positions| positions.size(symbol));
+        let skew = self.config.skew_bps_per_unit * inventory;
+        {
+            let mut state = self.state.lock().unwrap();
+            let quotes = state.get_mut(symbol).unwrap();
+            quotes.inventory = inventory;
+            quotes.skew_bps = skew;
+        }
+        metrics().set_maker_skew(symbol, skew.to_f64().unwrap_or_default());
+        let tick = self.config.price_tick(symbol);
+        let tolerance = tick * Decimal::from(self.config.tolerance_ticks);
+        for side in [Side::BUY, Side::SELL] {
+            if self.cap(symbol, side, inventory) {
+                self.cancel_side(orders, symbol, side).await;
+                continue;
+            }
+            // 多头时买价远离、卖价靠近参考价；空头相反
+            let offset = match side {
+                Side::BUY => self.config.half_spread_bps + skew,
+                Side::SELL => self.config.half_spread_bps - skew,
+            };
+            let target = passive_price(side, fair, offset.max(Decimal::ZERO), tick);
+            if target <= Decimal::ZERO {
+                continue;
+            }
+            let current = self
+                .state
+                .lock()
+                .unwrap()
+                .get_mut(symbol)
+                .unwrap()
+                .side(side)
+                .clone();
+            let resting = match current {
+                None => match self.place(orders, symbol, side, target).await {
+                    Ok(id) => {
+                        info!("Maker {} {} placed at {}", symbol, label(side), target);
+                        Resting { id, price: target }
+                    }
+                    Err(e) => {
+                        warn!(
+                            "Failed to place {} {} at {}: {}",
+                            symbol,
+                            label(side),
+                            target,
+                            e
+                        );
+                        continue;
+                    }
+                },
+                Some(resting) if (target - resting.price).abs() > tolerance && !throttled => {
+                    let Some(id) = self.reprice(orders, symbol, side, &resting, target).await
+                    else {
+                        continue;
+                    };
+                    info!(
+                        "Maker {} {} {} -> {}",
+                        symbol,
+                        label(side),
+                        resting.price,
+                        target
+                    );
+                    let mut state = self.state.lock().unwrap();
+                    state.get_mut(symbol).unwrap().last_requote = Some(Instant::now());
+                    Resting { id, price: target }
+                }
+                Some(_) => continue,
+            };
+            *self
+                .state
+                .lock()
+                .unwrap()
+                .get_mut(symbol)
+                .unwrap()
+                .side(side) = Some(resting);
+        }
+    }
+
+    /// 持仓 `inventory` 是否已达到上限、不应再报价 `side` 一侧；状态变化时记录日志
+    fn cap(&self, symbol: &str, side: Side, inventory: Decimal) -> bool {
+        let reached = self.config.max_inventory.is_some_and(|max| match side {
+            Side::BUY => inventory >= max,
+            Side::SELL => inventory <= -max,
+        });
+        let mut state = self.state.lock().unwrap();
+        let capped = state.get_mut(symbol).unwrap().capped(side);
+        if std::mem::replace(capped, reached) != reached {
+            if reached {
+                warn!(
+                    "Maker {} {} paused: position {} reached max_inventory {}",
+                    symbol,
+                    label(side),
+                    inventory,
+                    self.config.max_inventory.unwrap_or_default()
+                );
+            } else {
+                info!(
+                    "Maker {} {} resumed at position {}",
+                    symbol,
+                    label(side),
+                    inventory
+                );
+            }
+        }
+        reached
+    }
+
+    /// 撤下报价的原因：订单簿异常（取走）、订单簿可疑或行情停滞
+    fn pull_reason(&self, symbol: &str) -> Option<String> {
+        let suspect = self
+            .books
+            .as_ref()
+            .and_then(|books| books.get(symbol))
+            .and_then(|book| book.read().unwrap().anomaly().map(ToString::to_string));
+        let mut state = self.state.lock().unwrap();
+        let quotes = state.get_mut(symbol)?;
+        if let Some(anomaly) = quotes.anomaly.take() {
+            return Some(anomaly);
+        }
+        if let Some(suspect) = suspect {
+            return Some(suspect);
+        }
+        (!quotes.stale.is_empty()).then(|| "market data is stale".to_string())
+    }
+
+    /// 订单簿的微价格，订单簿不可用时为 BBO 中间价
+    fn fair_value(&self, symbol: &str) -> Option<Decimal> {
+        self.books
+            .as_ref()
+            .and_then(|books| books.get(symbol))
+            .and_then(|book| microprice(&book.read().unwrap()))
+            .or_else(|| self.quotes.mid(symbol))
+    }
+
+    /// 把挂单改到 `price`：先改单，失败时撤单重挂；返回新的挂单 ID，撤单失败时保留原挂单并返回 `None`
+    async fn reprice(
+        &self,
+        orders: &OrderManager<'_>,
+        symbol: &str,
+        side: Side,
+        resting: &Resting,
+        price: Decimal,
+    ) -> Option<String> {
+        let e = match orders.amend(&resting.id, price, self.config.size).await {
+            Ok(order) => return Some(order.id().to_string()),
+            Err(e) => e,
+        };
+        warn!(
+            "Failed to amend {} {} {} -> {}, replacing: {}",
+            symbol,
+            label(side),
+            resting.price,
+            price,
+            e
+        );
+        if let Err(e) = orders.cancel(&resting.id).await {
+            warn!(
+                "Failed to cancel {} {} {}: {}",
+                symbol,
+                label(side),
+                resting.id,
+                e
+            );
+            return None;
+        }
+        *self
+            .state
+            .lock()
+            .unwrap()
+            .get_mut(symbol)
+            .unwrap()
+            .side(side) = None;
+        match self.place(orders, symbol, side, price).await {
+            Ok(id) => Some(id),
+            Err(e) => {
+                warn!(
+                    "Failed to place {} {} at {}: {}",
+                    symbol,
+                    label(side),
+                    price,
+                    e
+                );
+                None
+            }
+        }
+    }
+
+    /// 撤销 `symbol` 的买卖报价
+    async fn pull(&self, orders: &OrderManager<'_>, symbol: &str) {
+        for side in [Side::BUY, Side::SELL] {
+            self.cancel_side(orders, symbol, side).await;
+        }
+    }
+
+    /// 撤销 `symbol` 在 `side` 一侧的报价
+    async fn cancel_side(&self, orders: &OrderManager<'_>, symbol: &str, side: Side) {
+        let resting = self
+            .state
+            .lock()
+            .unwrap()
+            .get_mut(symbol)
+            .unwrap()
+            .side(side)
+            .take();
+        if let Some(resting) = resting {
+            if let Err(e) = orders.cancel(&resting.id).await {
+                warn!("Failed to cancel quote {} on {}: {}", resting.id, symbol, e);
+            }
+        }
+    }
+
+    /// 漏掉订单事件后，忘记已不在挂单中的报价
+    fn forget_closed(&self, orders: &OrderManager<'_>) {
+        let open: HashSet<_> = orders
+            .open_orders()
+            .into_iter()
+            .map(|order| order.id)
+            .collect();
+        for quotes in self.state.lock().unwrap().values_mut() {
+            for side in [Side::BUY, Side::SELL] {
+                let slot = quotes.side(side);
+                if slot
+                    .as_ref()
+                    .is_some_and(|resting| !open.contains(&resting.id))
+                {
+                    *slot = None;
+                }
+            }
+        }
+    }
+
+    async fn place(
+        &self,
+        orders: &OrderManager<'_>,
+        symbol: &str,
+        side: Side,
+        price: Decimal,
+    ) -> Result<String, OrderManagerError> {
+        let spec = OrderSpec {
+            side,
+            order_type: OrderType::LIMIT,
+            size: self.config.size,
+            price: Some(price),
+            instruction: OrderInstruction::POST_ONLY,
+            client_id: None,
+            reduce_only: false,
+            trigger_price: None,
+        };
+        let order = orders.submit(NewOrder::new(symbol, spec)).await?;
+        Ok(order.id().to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::market_data::AnomalyKind;
+    use crate::paper::test_support::{PaperMarket, MARKET};
+
+    fn config() -> MakerConfig {
+        MakerConfig {
+            symbols: vec![MARKET.to_string()],
+            half_spread_bps: Decimal::from(50),
+            size: Decimal::new(1, 2),
+            tolerance_ticks: 2,
+            price_ticks: BTreeMap::from([(MARKET.to_string(), Decimal::new(1, 1))]),
+            min_requote_interval: Duration::from_secs(1),
+            skew_bps_per_unit: Decimal::ZERO,
+            max_inventory: None,
+        }
+    }
+
+    /// 以 `bid` / `ask`（单位 0.1）更新报价，返回对应的行情事件
+    fn bbo(market: &PaperMarket, bid: i64, ask: i64) -> MarketEvent {
+        market.bbo(Decimal::new(bid, 1), Decimal::new(ask, 1))
+    }
+
+    fn quoted(bid: i64, ask: i64) -> Vec<(Side, Decimal)> {
+        vec![
+            (Side::BUY, Decimal::new(bid, 1)),
+            (Side::SELL, Decimal::new(ask, 1)),
+        ]
+    }
+
+    fn stale(stale: bool) -> FeedStatus {
+        FeedStatus {
+            id: SubscriptionId::Replay(1),
+            channel: WsChannel::Bbo,
+            market: Some(MARKET.to_string()),
+            silent_for: Duration::from_secs(10),
+            stale,
+        }
+    }
+
+    /// 处理积压的订单事件，等待改价间隔后重新报价
+    async fn requote(
+        maker: &SimpleMaker,
+        orders: &OrderManager<'_>,
+        events: &mut tokio::sync::broadcast::Receiver<OrderEvent>,
+    ) {
+        while let Ok(event) = events.try_recv() {
+            maker.on_order_event(&event);
+        }
+        tokio::time::advance(Duration::from_secs(1)).await;
+        maker.refresh(orders).await;
+    }
+
+    #[test]
+    fn config_is_validated() {
+        let quotes = BboCache::new();
+        for (config, expected) in [
+            (
+                MakerConfig {
+                    symbols: Vec::new(),
+                    ..config()
+                },
+                MakerError::NoSymbols,
+            ),
+            (
+                MakerConfig {
+                    half_spread_bps: Decimal::ZERO,
+                    ..config()
+                },
+                MakerError::InvalidSpread(Decimal::ZERO),
+            ),
+            (
+                MakerConfig {
+                    size: Decimal::ZERO,
+                    ..config()
+                },
+                MakerError::InvalidSize(Decimal::ZERO),
+            ),
+            (
+                MakerConfig {
+                    min_requote_interval: Duration::ZERO,
+                    ..config()
+                },
+                MakerError::InvalidInterval,
+            ),
+            (
+                MakerConfig {
+                    skew_bps_per_unit: Decimal::NEGATIVE_ONE,
+                    ..config()
+                },
+                MakerError::InvalidSkew(Decimal::NEGATIVE_ONE),
+            ),
+            (
+                MakerConfig {
+                    max_inventory: Some(Decimal::ZERO),
+                    ..config()
+                },
+                MakerError::InvalidInventory(Decimal::ZERO),
+            ),
+        ] {
+            assert_eq!(
+                SimpleMaker::new(config, quotes.clone()).err(),
+                Some(expected)
+            );
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn quotes_are_amended_only_beyond_the_tolerance_and_at_most_once_per_interval() {
+        let market = PaperMarket::new();
+        let orders = market.manager("mm");
+        let maker = SimpleMaker::new(config(), market.quotes.clone()).unwrap();
+
+        // 中间价 100：100 × (1 ∓ 0.5%) 向远离盘口的方向取整
+        bbo(&market, 999, 1001);
+        maker.refresh(&orders).await;
+        assert_eq!(market.resting(), quoted(995, 1005));
+        let ids: Vec<_> = market
+            .exchange
+            .open_orders()
+            .into_iter()
+            .map(|o| o.id)
+            .collect();
+
+        // 中间价 100.1：卖价目标 100.7，只差两个价格精度，不改价
+        bbo(&market, 1000, 1002);
+        maker.refresh(&orders).await;
+        assert_eq!(market.resting(), quoted(995, 1005));
+
+        // 中间价 100.3：买价目标 99.7 仍在容差内，卖价目标 100.9 超出，原订单改价
+        bbo(&market, 1002, 1004);
+        maker.refresh(&orders).await;
+        assert_eq!(market.resting(), quoted(995, 1009));
+        assert_eq!(
+            maker.resting(MARKET),
+            (Some(Decimal::new(995, 1)), Some(Decimal::new(1009, 1)))
+        );
+        let amended: Vec<_> = market
+            .exchange
+            .open_orders()
+            .into_iter()
+            .map(|o| o.id)
+            .collect();
+        assert_eq!(amended.len(), 2);
+        assert!(amended.iter().all(|id| ids.contains(id)));
+
+        // 中间价 101：一秒内不再改价，间隔过后两侧一起改价
+        bbo(&market, 1009, 1011);
+        maker.refresh(&orders).await;
+        assert_eq!(market.resting(), quoted(995, 1009));
+        tokio::time::advance(Duration::from_secs(1)).await;
+        maker.refresh(&orders).await;
+        assert_eq!(market.resting(), quoted(1004, 1016));
+    }
+
+    #[tokio::test]
+    async fn quotes_are_pulled_while_the_feed_is_stale_or_the_book_is_anomalous() {
+        let market = PaperMarket::new();
+        let orders = market.manager("mm");
+        let maker = SimpleMaker::new(config(), market.quotes.clone()).unwrap();
+        let stop = CancellationToken::new();
+        let quoting = || market.resting() == quoted(995, 1005);
+        let pulled = || market.resting().is_empty();
+
+        let script = async {
+            maker.on_event(&bbo(&market, 999, 1001));
+            market.wait_for("quotes", quoting).await;
+
+            // 停滞通知立即撤下报价，期间的 BBO 不会重新报价
+            maker.on_feed_status(&stale(true));
+            market.wait_for("quotes to be pulled", pulled).await;
+            maker.on_event(&bbo(&market, 999, 1001));
+            market.settle().await;
+            assert!(market.exchange.open_orders().is_empty());
+            assert_eq!(maker.resting(MARKET), (None, None));
+
+            maker.on_feed_status(&stale(false));
+            market.wait_for("quotes", quoting).await;
+
+            // 订单簿异常同样立即撤下报价；没有订单簿可供确认恢复时，下一次 BBO 重新报价
+            maker.on_anomaly(&Anomaly {
+                market: MARKET.to_string(),
+                seq_no: 7,
+                kind: AnomalyKind::Crossed {
+                    bid: Decimal::from(101),
+                    ask: Decimal::from(100),
+                },
+            });
+            market.wait_for("quotes to be pulled", pulled).await;
+            maker.on_event(&bbo(&market, 999, 1001));
+            market.wait_for("quotes", quoting).await;
+            stop.cancel();
+        };
+        tokio::join!(maker.run(&orders, &stop), script);
+        assert!(market.exchange.open_orders().is_empty());
+        assert_eq!(maker.resting(MARKET), (None, None));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn inventory_skews_quotes_and_caps_the_accumulating_side() {
+        let market = PaperMarket::new();
+        let orders = market.manager("mm");
+        // 每 0.01 的持仓偏移 10 个基点，持仓达到 0.02 时停止积累
+        let maker = SimpleMaker::new(
+            MakerConfig {
+                tolerance_ticks: 0,
+                skew_bps_per_unit: Decimal::from(1_000),
+                max_inventory: Some(Decimal::new(2, 2)),
+                ..config()
+            },
+            market.quotes.clone(),
+        )
+        .unwrap()
+        .with_positions(market.positions.clone());
+        let mut events = market.tracker.subscribe();
+        let trade = |price: i64| market.trade(Decimal::new(price, 1), Decimal::ONE);
+
+        bbo(&market, 999, 1001);
+        maker.refresh(&orders).await;
+        assert_eq!(market.resting(), quoted(995, 1005));
+
+        // 买单成交、持仓 0.01：卖价靠近到 100.4，买价远离到 99.4
+        trade(994);
+        requote(&maker, &orders, &mut events).await;
+        assert_eq!(market.resting(), quoted(994, 1004));
+
+        // 再次成交后持仓达到上限：只保留卖价
+        trade(993);
+        requote(&maker, &orders, &mut events).await;
+        assert_eq!(market.resting(), [(Side::SELL, Decimal::new(1003, 1))]);
+        assert_eq!(maker.resting(MARKET), (None, Some(Decimal::new(1003, 1))));
+
+        // 卖单成交、持仓回落到 0.01 后恢复双边报价
+        trade(1004);
+        requote(&maker, &orders, &mut events).await;
+        assert_eq!(market.resting(), quoted(994, 1004));
+        maker.teardown(&orders).await;
+        assert!(market.exchange.open_orders().is_empty());
+    }
+}
@@ -0,0 +1,706 @@
+//! 追踪止损：跟随价格向持仓有利方向移动止损位，价格回撤穿过止损位时只减仓平仓
+//!
+//! 以 BBO 中间价为参考价，记录持仓以来的有利极值（多头为最高价、空头为最低价），止损位为极值向不利方向
+//! 偏移 `distance`（绝对价格或基点），按价格精度向远离极值的方向取整，且只向有利方向移动、不回退。
+//!
+//! 两种执行方式由 [`TrailMode`] 显式指定：
+//! - [`TrailMode::Local`]（默认）：本地监视，中间价穿过止损位时经 [`OrderManager`] 提交只减仓的
+//!   市价单或滑点上限价格的 IOC 限价单；价格跳空穿过止损位时按当时的中间价平仓；
+//! - [`TrailMode::Resting`]：在交易所挂止损市价单（`STOP_LOSS_MARKET`），止损位移动时撤单重挂，
+//!   由交易所触发。Paradex 没有原生的追踪触发单，改单接口也只适用于限价单，因此以撤单重挂代替改单；
+//!   止损位相对已挂触发价的移动不足 `min_step_ticks` 个价格精度时不重挂，避免每个报价都撤单重挂。
+//!   触发价按行情摘要中的标记价格检查方向，没有标记价格时按中间价检查。
+
+use log::{info, warn};
+use paradex::structs::{OrderInstruction, OrderType, Side};
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use thiserror::Error;
+use tokio::sync::Notify;
+use tokio::time::MissedTickBehavior;
+use tokio_util::sync::CancellationToken;
+
+use crate::market_data::{BboCache, MarketEvent};
+use crate::orders::{
+    aggressive_price, capped_price, validate_trigger, AggressiveMode, OrderError, OrderSpec,
+};
+use crate::trading::{ManagedOrder, NewOrder, OrderManager, OrderManagerError, TrackedOrder};
+
+/// 没有 BBO 更新时重新检查止损位的间隔
+const TRAIL_POLL: Duration = Duration::from_millis(250);
+/// 提交平仓单后等待其最终状态的最长时间
+const SETTLE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// 追踪止损参数无效的原因
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum TrailingError {
+    #[error("Trailing stop size must be positive, got {0}")]
+    InvalidSize(Decimal),
+    #[error("Trail distance must be positive, got {0}")]
+    InvalidDistance(Decimal),
+    #[error("Trailing stop slippage cap must be positive, got {0}")]
+    InvalidSlippage(Decimal),
+}
+
+/// 止损位与有利极值的距离
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrailDistance {
+    /// 绝对价格
+    Absolute(Decimal),
+    /// 相对极值的基点
+    Bps(Decimal),
+}
+
+impl TrailDistance {
+    fn value(self) -> Decimal {
+        match self {
+            TrailDistance::Absolute(value) | TrailDistance::Bps(value) => value,
+        }
+    }
+
+    /// 相对 `extreme` 的价格距离
+    fn offset(self, extreme: Decimal) -> Decimal {
+        match self {
+            TrailDistance::Absolute(distance) => distance,
+            TrailDistance::Bps(bps) => extreme * bps / Decimal::from(10_000),
+        }
+    }
+}
+
+/// 止损的执行方式（配置文件中写作 `local` / `resting`）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TrailMode {
+    /// 本地监视，穿过止损位时提交只减仓的平仓单
+    #[default]
+    Local,
+    /// 在交易所挂止损市价单，止损位移动时撤单重挂
+    Resting,
+}
+
+/// 追踪止损参数
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrailingConfig {
+    pub symbol: String,
+    /// 持仓方向：`BUY` 为多头、`SELL` 为空头；平仓方向与之相反
+    pub side: Side,
+    pub size: Decimal,
+    pub distance: TrailDistance,
+    pub mode: TrailMode,
+    /// 本地平仓的方式：市价单，或相对中间价 `max_slippage_bps` 的 IOC 限价单
+    pub close: AggressiveMode,
+    pub max_slippage_bps: Decimal,
+    /// 止损位与平仓限价对齐的价格精度；为 0 时不对齐
+    pub price_tick: Decimal,
+    /// 挂单模式下止损位至少移动该数量的价格精度才撤单重挂；0 表示每次移动都重挂
+    pub min_step_ticks: u32,
+}
+
+impl TrailingConfig {
+    /// 检查数量与追踪距离为正数；限价平仓时滑点上限也须为正数
+    pub fn validate(&self) -> Result<(), TrailingError> {
+        if self.size <= Decimal::ZERO {
+            return Err(TrailingError::InvalidSize(self.size));
+        }
+        if self.distance.value() <= Decimal::ZERO {
+            return Err(TrailingError::InvalidDistance(self.distance.value()));
+        }
+        if self.close == AggressiveMode::Limit && self.max_slippage_bps <= Decimal::ZERO {
+            return Err(TrailingError::InvalidSlippage(self.max_slippage_bps));
+        }
+        Ok(())
+    }
+
+    fn exit_side(&self) -> Side {
+        match self.side {
+            Side::BUY => Side::SELL,
+            Side::SELL => Side::BUY,
+        }
+    }
+}
+
+/// 追踪止损的结果
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrailReport {
+    /// 有利极值与最终的止损位；没有收到行情时为 `None`
+    pub extreme: Option<Decimal>,
+    pub stop: Option<Decimal>,
+    /// 平仓单（本地平仓单或交易所触发的止损单）的最终状态；未触发即退出时为 `None`
+    pub close: Option<TrackedOrder>,
+}
+
+impl fmt::Display for TrailReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let price = |price: Option<Decimal>| price.map_or("-".to_string(), |p| p.to_string());
+        write!(
+            f,
+            "Trailing stop: extreme {}, stop {}",
+            price(self.extreme),
+            price(self.stop)
+        )?;
+        match &self.close {
+            Some(order) => write!(
+                f,
+                ", closed {} {} @ {} ({:?})",
+                order.id,
+                order.filled_size(),
+                price(order.avg_fill_price),
+                order.state
+            ),
+            None => write!(f, ", not triggered"),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct TrailState {
+    extreme: Option<Decimal>,
+    stop: Option<Decimal>,
+}
+
+/// 挂在交易所的止损单及其触发价
+struct Resting {
+    id: String,
+    trigger: Decimal,
+}
+
+/// 追踪止损；可克隆，克隆共享同一状态，以便行情回调唤醒运行中的监视
+#[derive(Clone)]
+pub struct TrailingStop {
+    config: TrailingConfig,
+    quotes: BboCache,
+    state: Arc<Mutex<TrailState>>,
+    wake: Arc<Notify>,
+}
+
+impl TrailingStop {
+    /// 参数无效时返回错误；`quotes` 提供中间价
+    pub fn new(config: TrailingConfig, quotes: BboCache) -> Result<Self, TrailingError> {
+        config.validate()?;
+        Ok(Self {
+            config,
+            quotes,
+            state: Arc::new(Mutex::new(TrailState::default())),
+            wake: Arc::new(Notify::new()),
+        })
+    }
+
+    /// 追踪止损参数
+    pub fn config(&self) -> &TrailingConfig {
+        &self.config
+    }
+
+    /// 当前止损位；尚未收到行情时为 `None`
+    pub fn stop_level(&self) -> Option<Decimal> {
+        self.state.lock().unwrap().stop
+    }
+
+    /// 持仓以来的有利极值
+    pub fn extreme(&self) -> Option<Decimal> {
+        self.state.lock().unwrap().extreme
+    }
+
+    /// 行情事件总线的回调：止损市场的 BBO 更新时唤醒监视
+    pub fn on_event(&self, event: &MarketEvent) {
+        if let MarketEvent::Bbo(bbo) = event {
+            if bbo.symbol == self.config.symbol {
+                self.wake.notify_one();
+            }
+        }
+    }
+
+    /// 以参考价 `price` 更新止损位；价格穿过（含触及）已有的止损位时返回 `true`，止损位不再移动
+    pub fn observe(&self, price: Decimal) -> bool {
+        let long = self.config.side == Side::BUY;
+        let mut state = self.state.lock().unwrap();
+        if let Some(stop) = state.stop {
+            if (long && price <= stop) || (!long && price >= stop) {
+                return true;
+            }
+        }
+        let extreme = match state.extreme {
+            Some(extreme) if long => extreme.max(price),
+            Some(extreme) => extreme.min(price),
+            None => price,
+        };
+        let offset = self.config.distance.offset(extreme);
+        let level = aggressive_price(
+            self.config.exit_side(),
+            if long {
+                extreme - offset
+            } else {
+                extreme + offset
+            },
+            Decimal::ZERO,
+            self.config.price_tick,
+        );
+        // 止损位只向有利方向移动
+        let stop = match state.stop {
+            Some(stop) if long => stop.max(level),
+            Some(stop) => stop.min(level),
+            None => level,
+        };
+        state.extreme = Some(extreme);
+        state.stop = Some(stop);
+        false
+    }
+
+    /// 监视止损直到触发平仓或 `stop` 被取消；取消时撤销挂在交易所的止损单
+    pub async fn run(
+        &self,
+        orders: &OrderManager<'_>,
+        stop: &CancellationToken,
+    ) -> Result<TrailReport, OrderManagerError> {
+        let symbol = &self.config.symbol;
+        let mut ticker = tokio::time::interval(TRAIL_POLL);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        let mut resting: Option<Resting> = None;
+        info!(
+            "Trailing a {:?} stop for {:?} {} on {} at {:?}",
+            self.config.mode, self.config.side, self.config.size, symbol, self.config.distance
+        );
+        let close = loop {
+            tokio::select! {
+                _ = stop.cancelled() => break None,
+                _ = self.wake.notified() => {}
+                _ = ticker.tick() => {}
+            }
+            // 交易所触发的止损单已成交
+            if let Some(order) = resting
+                .as_ref()
+                .and_then(|resting| orders.tracker().get(&resting.id))
+                .filter(|order| order.state.is_terminal())
+            {
+                if !order.filled_size().is_zero() {
+                    info!("Resting trailing stop {} on {} filled", order.id, symbol);
+                    break Some(order);
+                }
+                warn!(
+                    "Resting trailing stop {} on {} closed, re-placing",
+                    order.id, symbol
+                );
+                resting = None;
+            }
+            let Some(mid) = self.quotes.mid(symbol) else {
+                continue;
+            };
+            if self.observe(mid) {
+                // 挂单模式下价格已越过止损位而止损单尚未触发（如跳空），同样在本地平仓
+                if let Some(resting) = resting.take() {
+                    if let Err(e) = orders.cancel(&resting.id).await {
+                        warn!("Failed to cancel trailing stop {}: {}", resting.id, e);
+                    }
+                }
+                let order = self.close(orders, mid).await?;
+                let state = order.await_filled_or_cancelled(SETTLE_TIMEOUT).await;
+                break state.or_else(|| order.state());
+            }
+            if self.config.mode == TrailMode::Resting {
+                self.rest(orders, &mut resting, mid).await?;
+            }
+        };
+        if let Some(resting) = resting.filter(|_| close.is_none()) {
+            if let Err(e) = orders.cancel(&resting.id).await {
+                warn!("Failed to cancel trailing stop {}: {}", resting.id, e);
+            }
+        }
+        Ok(TrailReport {
+            extreme: self.extreme(),
+            stop: self.stop_level(),
+            close,
+        })
+    }
+
+    /// 止损位移动至少 `min_step_ticks` 个价格精度后撤销挂在交易所的止损单，按新的止损位重挂；
+    /// 没有标记价格时按中间价 `mid` 检查触发价
+    async fn rest(
+        &self,
+        orders: &OrderManager<'_>,
+        resting: &mut Option<Resting>,
+        mid: Decimal,
+    ) -> Result<(), OrderManagerError> {
+        let config = &self.config;
+        let Some(level) = self.stop_level() else {
+            return Ok(());
+        };
+        let min_step = config.price_tick * Decimal::from(config.min_step_ticks);
+        if resting
+            .as_ref()
+            .is_some_and(|order| order.trigger == level || (level - order.trigger).abs() < min_step)
+        {
+            return Ok(());
+        }
+        if let Some(previous) = resting.take() {
+            if let Err(e) = orders.cancel(&previous.id).await {
+                warn!("Failed to cancel trailing stop {}: {}", previous.id, e);
+            }
+        }
+        let side = config.exit_side();
+        let order = match orders
+            .submit_stop_loss(&config.symbol, side, config.size, level, None)
+            .await
+        {
+            Err(OrderManagerError::Order(OrderError::NoMarkPrice(_))) => {
+                validate_trigger(OrderType::STOP_LOSS_MARKET, side, level, mid)?;
+                let spec = OrderSpec {
+                    side,
+                    order_type: OrderType::STOP_LOSS_MARKET,
+                    size: config.size,
+                    price: None,
+                    instruction: OrderInstruction::GTC,
+                    client_id: None,
+                    reduce_only: false,
+                    trigger_price: Some(level),
+                };
+                orders.submit(NewOrder::new(&config.symbol, spec)).await
+            }
+            result => result,
+        }?;
+        info!(
+            "Trailing stop on {} moved to {} ({})",
+            config.symbol,
+            level,
+            order.id()
+        );
+        *resting = Some(Resting {
+            id: order.id().to_string(),
+            trigger: level,
+        });
+        Ok(())
+    }
+
+    /// 提交只减仓的平仓单：市价单，或相对中间价 `mid` 滑点上限价格的 IOC 限价单
+    async fn close(
+        &self,
+        orders: &OrderManager<'_>,
+        mid: Decimal,
+    ) -> Result<ManagedOrder, OrderManagerError> {
+        let config = &self.config;
+        let side = config.exit_side();
+        let (order_type, price) = match config.close {
+            AggressiveMode::Market => (OrderType::MARKET, None),
+            AggressiveMode::Limit => (
+                OrderType::LIMIT,
+                Some(capped_price(
+                    side,
+                    mid,
+                    config.max_slippage_bps,
+                    config.price_tick,
+                )),
+            ),
+        };
+        warn!(
+            "{} at {} crossed the trailing stop {:?}, closing {} with {:?} {:?}",
+            config.symbol,
+            mid,
+            self.stop_level(),
+            config.size,
+            side,
+            order_type
+        );
+        let spec = OrderSpec {
+            side,
+            order_type,
+            size: config.size,
+            price,
+            instruction: OrderInstruction::IOC,
+            client_id: None,
+            reduce_only: true,
+            trigger_price: None,
+        };
+        orders.submit(NewOrder::new(&config.symbol, spec)).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gateway::OrderGateway;
+    use crate::paper::test_support::{PaperMarket, MARKET};
+    use crate::trading::OrderState;
+    use paradex::structs::{OrderRequest, OrderUpdate};
+
+    fn config(side: Side, distance: TrailDistance, mode: TrailMode) -> TrailingConfig {
+        TrailingConfig {
+            symbol: MARKET.to_string(),
+            side,
+            size: Decimal::ONE,
+            distance,
+            mode,
+            close: AggressiveMode::Limit,
+            max_slippage_bps: Decimal::from(100),
+            price_tick: Decimal::new(1, 1),
+            min_step_ticks: 0,
+        }
+    }
+
+    /// 以 `bid` / `ask`（单位 0.1）更新报价并唤醒跟踪止损
+    fn quote(market: &PaperMarket, trailing: &TrailingStop, bid: i64, ask: i64) {
+        trailing.on_event(&market.bbo(Decimal::new(bid, 1), Decimal::new(ask, 1)));
+    }
+
+    /// 报价 100.0 / 101.0 下以市价买入 1 建立多头，返回带报价与持仓的订单管理器
+    async fn open_long(market: &PaperMarket) -> OrderManager<'_> {
+        market.bbo(Decimal::from(100), Decimal::from(101));
+        market
+            .exchange
+            .create_order(OrderRequest {
+                instruction: OrderInstruction::IOC,
+                market: MARKET.to_string(),
+                price: None,
+                side: Side::BUY,
+                size: Decimal::ONE,
+                order_type: OrderType::MARKET,
+                client_id: Some("entry".to_string()),
+                flags: vec![],
+                recv_window: None,
+                stp: None,
+                trigger_price: None,
+            })
+            .await
+            .unwrap();
+        market
+            .manager("trail")
+            .with_quotes(market.quotes.clone())
+            .with_positions(market.positions.clone())
+    }
+
+    fn stops(market: &PaperMarket) -> Vec<Decimal> {
+        market
+            .exchange
+            .open_orders()
+            .iter()
+            .filter_map(|order: &OrderUpdate| order.trigger_price)
+            .collect()
+    }
+
+    #[test]
+    fn stop_ratchets_in_the_position_favor_and_never_retreats() {
+        let long = TrailingStop::new(
+            config(
+                Side::BUY,
+                TrailDistance::Absolute(Decimal::from(5)),
+                TrailMode::Local,
+            ),
+            BboCache::new(),
+        )
+        .unwrap();
+        assert!(!long.observe(Decimal::from(100)));
+        assert_eq!(long.stop_level(), Some(Decimal::from(95)));
+        assert!(!long.observe(Decimal::new(1034, 1)));
+        assert_eq!(long.stop_level(), Some(Decimal::new(984, 1)));
+        // 回撤但未触及止损位：止损位不回退
+        assert!(!long.observe(Decimal::from(99)));
+        assert_eq!(long.stop_level(), Some(Decimal::new(984, 1)));
+        assert_eq!(long.extreme(), Some(Decimal::new(1034, 1)));
+        assert!(long.observe(Decimal::new(984, 1)));
+
+        // 空头按基点：止损位在最低价上方 1%，向上取整到价格精度
+        let short = TrailingStop::new(
+            config(
+                Side::SELL,
+                TrailDistance::Bps(Decimal::from(100)),
+                TrailMode::Local,
+            ),
+            BboCache::new(),
+        )
+        .unwrap();
+        assert!(!short.observe(Decimal::from(200)));
+        assert_eq!(short.stop_level(), Some(Decimal::from(202)));
+        assert!(!short.observe(Decimal::new(1905, 1)));
+        assert_eq!(short.stop_level(), Some(Decimal::new(1925, 1)));
+        assert!(!short.observe(Decimal::from(192)));
+        assert_eq!(short.stop_level(), Some(Decimal::new(1925, 1)));
+        assert!(short.observe(Decimal::from(250)));
+
+        assert_eq!(
+            TrailingStop::new(
+                config(
+                    Side::BUY,
+                    TrailDistance::Bps(Decimal::ZERO),
+                    TrailMode::Local
+                ),
+                BboCache::new()
+            )
+            .err(),
+            Some(TrailingError::InvalidDistance(Decimal::ZERO))
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn local_stop_closes_reduce_only_after_a_gap_through_the_stop() {
+        let market = PaperMarket::new();
+        let orders = open_long(&market).await;
+        let trailing = TrailingStop::new(
+            config(
+                Side::BUY,
+                TrailDistance::Absolute(Decimal::from(5)),
+                TrailMode::Local,
+            ),
+            market.quotes.clone(),
+        )
+        .unwrap();
+        let level = |stop: Decimal| {
+            let trailing = &trailing;
+            move || trailing.stop_level() == Some(stop)
+        };
+
+        let stop = CancellationToken::new();
+        let run = trailing.run(&orders, &stop);
+        let script = async {
+            market
+                .wait_for("the initial stop", level(Decimal::new(955, 1)))
+                .await;
+            quote(&market, &trailing, 1090, 1100);
+            market
+                .wait_for("the stop to ratchet", level(Decimal::new(1045, 1)))
+                .await;
+            quote(&market, &trailing, 1060, 1070);
+            market.settle().await;
+            assert_eq!(trailing.stop_level(), Some(Decimal::new(1045, 1)));
+            assert_eq!(market.exchange.position(MARKET).unwrap().size, Decimal::ONE);
+            // 跳空越过止损位：按当时的中间价以滑点上限的 IOC 限价单平仓
+            quote(&market, &trailing, 900, 910);
+        };
+        let (report, ()) = tokio::join!(run, script);
+
+        let report = report.unwrap();
+        assert_eq!(report.extreme, Some(Decimal::new(1095, 1)));
+        assert_eq!(report.stop, Some(Decimal::new(1045, 1)));
+        let close = report.close.unwrap();
+        assert_eq!(close.side, Side::SELL);
+        assert_eq!(close.state, OrderState::Filled);
+        assert_eq!(close.avg_fill_price, Some(Decimal::from(90)));
+        assert_eq!(
+            market.exchange.position(MARKET).unwrap().size,
+            Decimal::ZERO
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn resting_stop_is_replaced_as_it_ratchets_and_fires_on_the_exchange() {
+        let market = PaperMarket::new();
+        let orders = open_long(&market).await;
+        let trailing = TrailingStop::new(
+            config(
+                Side::BUY,
+                TrailDistance::Absolute(Decimal::from(5)),
+                TrailMode::Resting,
+            ),
+            market.quotes.clone(),
+        )
+        .unwrap();
+        let resting = |stop: Decimal| {
+            let market = &market;
+            move || stops(market) == [stop]
+        };
+
+        let stop = CancellationToken::new();
+        let run = trailing.run(&orders, &stop);
+        let script = async {
+            market
+                .wait_for("the initial stop order", resting(Decimal::new(955, 1)))
+                .await;
+            quote(&market, &trailing, 1090, 1100);
+            market
+                .wait_for("the stop order to move", resting(Decimal::new(1045, 1)))
+                .await;
+            quote(&market, &trailing, 1060, 1070);
+            market.settle().await;
+            assert_eq!(stops(&market), [Decimal::new(1045, 1)]);
+            // 成交价跳空越过触发价，交易所按买一成交
+            quote(&market, &trailing, 940, 950);
+            market.trade(Decimal::from(95), Decimal::ONE);
+        };
+        let (report, ()) = tokio::join!(run, script);
+
+        let close = report.unwrap().close.unwrap();
+        assert_eq!(close.state, OrderState::Filled);
+        assert_eq!(close.avg_fill_price, Some(Decimal::from(94)));
+        assert!(market.exchange.open_orders().is_empty());
+        assert_eq!(
+            market.exchange.position(MARKET).unwrap().size,
+            Decimal::ZERO
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn resting_stop_is_only_replaced_after_the_minimum_step() {
+        let market = PaperMarket::new();
+        let orders = open_long(&market).await;
+        let trailing = TrailingStop::new(
+            TrailingConfig {
+                min_step_ticks: 20,
+                ..config(
+                    Side::BUY,
+                    TrailDistance::Absolute(Decimal::from(5)),
+                    TrailMode::Resting,
+                )
+            },
+            market.quotes.clone(),
+        )
+        .unwrap();
+        let resting = |stop: Decimal| {
+            let market = &market;
+            move || stops(market) == [stop]
+        };
+
+        let stop = CancellationToken::new();
+        let run = trailing.run(&orders, &stop);
+        let script = async {
+            market
+                .wait_for("the initial stop order", resting(Decimal::new(955, 1)))
+                .await;
+            // 止损位上移 1.0，不足 2.0 的最小步长：本地止损位更新，交易所的止损单不动
+            quote(&market, &trailing, 1010, 1020);
+            market
+                .wait_for("the stop to ratchet", || {
+                    trailing.stop_level() == Some(Decimal::new(965, 1))
+                })
+                .await;
+            market.settle().await;
+            assert_eq!(stops(&market), [Decimal::new(955, 1)]);
+            // 累计上移 2.0 后重挂
+            quote(&market, &trailing, 1020, 1030);
+            market
+                .wait_for("the stop order to move", resting(Decimal::new(975, 1)))
+                .await;
+            stop.cancel();
+        };
+        let (report, ()) = tokio::join!(run, script);
+
+        assert_eq!(report.unwrap().close, None);
+        assert!(market.exchange.open_orders().is_empty());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn cancelling_the_watch_removes_the_resting_stop() {
+        let market = PaperMarket::new();
+        let orders = open_long(&market).await;
+        let trailing = TrailingStop::new(
+            config(
+                Side::BUY,
+                TrailDistance::Absolute(Decimal::from(5)),
+                TrailMode::Resting,
+            ),
+            market.quotes.clone(),
+        )
+        .unwrap();
+
+        let stop = CancellationToken::new();
+        let run = trailing.run(&orders, &stop);
+        let script = async {
+            market
+                .wait_for("the stop order", || stops(&market).len() == 1)
+                .await;
+            stop.cancel();
+        };
+        let (report, ()) = tokio::join!(run, script);
+
+        assert_eq!(report.unwrap().close, None);
+        assert!(market.exchange.open_orders().is_empty());
+    }
+}
@@ -0,0 +1,624 @@
+//! TWAP（时间加权平均价格）执行：把母单按时间均匀切分为子单逐个提交，逐步建立或平掉持仓
+//!
+//! 第 k 个周期结束时的计划累计数量为 `总数量 × k / 周期数`（向下对齐到数量步长），子单数量为计划累计与
+//! 已成交数量之差，落后于计划的部分在下一个周期自动补上。子单先以己方最优价（买单挂买一、卖单挂卖一）
+//! 挂 POST_ONLY 限价单，等待 `passive_timeout` 后撤销未成交部分；启用追赶时再经
+//! [`OrderManager::submit_market`] 在滑点上限内吃单补齐。全部成交、计划结束或风控拒绝下单时停止，
+//! 结束时输出成交均价与各周期市场 VWAP 的对照。
+
+use log::{info, warn};
+use paradex::structs::{OrderInstruction, OrderType, Side};
+use rust_decimal::Decimal;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use thiserror::Error;
+use tokio::time::Instant;
+
+use crate::gateway::GatewayError;
+use crate::market_data::{BboCache, MarketEvent};
+use crate::orders::{OrderError, OrderSpec};
+use crate::trading::{ManagedOrder, NewOrder, OrderManager, OrderManagerError};
+
+/// 撤销被动子单或提交追赶单后，等待订单最终状态（含撤单前的成交）的最长时间
+const SETTLE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// TWAP 参数无效的原因
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum TwapError {
+    #[error("TWAP total size must be positive, got {0}")]
+    InvalidSize(Decimal),
+    #[error("TWAP interval must be positive and no longer than the duration")]
+    InvalidInterval,
+    #[error("TWAP passive timeout must not exceed the slice interval")]
+    InvalidPassiveTimeout,
+}
+
+/// TWAP 母单与执行参数
+#[derive(Debug, Clone, PartialEq)]
+pub struct TwapConfig {
+    pub symbol: String,
+    pub side: Side,
+    pub total_size: Decimal,
+    pub duration: Duration,
+    /// 子单间隔，每个周期提交一个子单
+    pub interval: Duration,
+    /// 被动子单等待成交的时长（不超过 `interval`）；越短越激进，剩余时间留给追赶
+    pub passive_timeout: Duration,
+    /// 被动子单未全部成交时，以不超过 `max_slippage_bps` 的吃单补齐
+    pub catchup: bool,
+    pub max_slippage_bps: Decimal,
+    /// 子单数量向下对齐的步长；为 0 时不对齐
+    pub size_increment: Decimal,
+}
+
+impl TwapConfig {
+    /// 检查总量、下单间隔与被动挂单超时
+    pub fn validate(&self) -> Result<(), TwapError> {
+        if self.total_size <= Decimal::ZERO {
+            return Err(TwapError::InvalidSize(self.total_size));
+        }
+        if self.interval.is_zero() || self.interval > self.duration {
+            return Err(TwapError::InvalidInterval);
+        }
+        if self.passive_timeout > self.interval {
+            return Err(TwapError::InvalidPassiveTimeout);
+        }
+        Ok(())
+    }
+
+    /// 周期数：时长按间隔向上取整，至少为 1
+    pub fn slices(&self) -> u32 {
+        let interval = self.interval.as_millis().max(1);
+        self.duration.as_millis().div_ceil(interval).max(1) as u32
+    }
+
+    /// 第 `slice` 个周期（从 0 开始）结束时的计划累计数量；最后一个周期为全部数量
+    pub fn scheduled(&self, slice: u32) -> Decimal {
+        let slices = self.slices();
+        if slice + 1 >= slices {
+            return self.total_size;
+        }
+        self.align(self.total_size * Decimal::from(slice + 1) / Decimal::from(slices))
+    }
+
+    fn align(&self, size: Decimal) -> Decimal {
+        if self.size_increment.is_zero() {
+            size
+        } else {
+            (size / self.size_increment).floor() * self.size_increment
+        }
+    }
+}
+
+/// 一个周期的执行结果
+#[derive(Debug, Clone, PartialEq)]
+pub struct TwapSlice {
+    pub index: u32,
+    /// 周期结束时的计划累计数量
+    pub scheduled: Decimal,
+    /// 本周期子单的数量（计划累计与此前已成交数量之差）
+    pub ordered: Decimal,
+    pub filled: Decimal,
+    /// 本周期成交均价；没有成交时为 `None`
+    pub avg_price: Option<Decimal>,
+    /// 其中由追赶吃单成交的数量
+    pub crossed: Decimal,
+    /// 本周期内市场成交的 VWAP；没有市场成交时为 `None`
+    pub market_vwap: Option<Decimal>,
+}
+
+impl TwapSlice {
+    fn new(index: u32, scheduled: Decimal) -> Self {
+        Self {
+            index,
+            scheduled,
+            ordered: Decimal::ZERO,
+            filled: Decimal::ZERO,
+            avg_price: None,
+            crossed: Decimal::ZERO,
+            market_vwap: None,
+        }
+    }
+
+    fn record(&mut self, size: Decimal, price: Option<Decimal>) {
+        let Some(price) = price.filter(|_| size > Decimal::ZERO) else {
+            return;
+        };
+        let notional = self.avg_price.unwrap_or_default() * self.filled + price * size;
+        self.filled += size;
+        self.avg_price = Some(notional / self.filled);
+    }
+}
+
+/// TWAP 结束的原因
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TwapOutcome {
+    /// 全部成交
+    Completed,
+    /// 计划结束时仍有未成交数量
+    Incomplete,
+    /// 风控拒绝子单，提前停止
+    RiskBlocked(String),
+    /// 子单提交失败，提前停止
+    Failed(String),
+}
+
+/// TWAP 执行报告
+#[derive(Debug, Clone, PartialEq)]
+pub struct TwapReport {
+    pub symbol: String,
+    pub side: Side,
+    pub total_size: Decimal,
+    pub slices: Vec<TwapSlice>,
+    pub outcome: TwapOutcome,
+}
+
+impl TwapReport {
+    /// 全部子单的成交数量
+    pub fn filled(&self) -> Decimal {
+        self.slices.iter().map(|slice| slice.filled).sum()
+    }
+
+    /// 全部子单的成交均价；没有成交时为 `None`
+    pub fn avg_price(&self) -> Option<Decimal> {
+        weighted(
+            self.slices
+                .iter()
+                .filter_map(|slice| Some((slice.avg_price?, slice.filled))),
+        )
+    }
+
+    /// 以各周期成交量加权的市场 VWAP，作为成交均价的基准
+    pub fn benchmark_vwap(&self) -> Option<Decimal> {
+        weighted(
+            self.slices
+                .iter()
+                .filter_map(|slice| Some((slice.market_vwap?, slice.filled))),
+        )
+    }
+
+    /// 成交均价相对基准 VWAP 向不利方向偏离的基点数，价格改善时为负
+    pub fn slippage_bps(&self) -> Option<Decimal> {
+        let (avg, benchmark) = (self.avg_price()?, self.benchmark_vwap()?);
+        let diff = match self.side {
+            Side::BUY => avg - benchmark,
+            Side::SELL => benchmark - avg,
+        };
+        Some(diff / benchmark * Decimal::from(10_000))
+    }
+}
+
+/// 按数量加权的均价；数量合计为 0 时为 `None`
+fn weighted(prices: impl Iterator<Item = (Decimal, Decimal)>) -> Option<Decimal> {
+    let (notional, size) = prices.fold(
+        (Decimal::ZERO, Decimal::ZERO),
+        |(notional, total), (price, size)| (notional + price * size, total + size),
+    );
+    (!size.is_zero()).then(|| notional / size)
+}
+
+fn format_price(price: Option<Decimal>) -> String {
+    price.map_or("-".to_string(), |price| price.round_dp(8).to_string())
+}
+
+impl fmt::Display for TwapReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "TWAP {:?} {} on {}: filled {} ({:?}), avg {} vs interval VWAP {} ({} bps)",
+            self.side,
+            self.total_size,
+            self.symbol,
+            self.filled(),
+            self.outcome,
+            format_price(self.avg_price()),
+            format_price(self.benchmark_vwap()),
+            self.slippage_bps()
+                .map_or("-".to_string(), |bps| bps.round_dp(2).to_string())
+        )?;
+        for slice in &self.slices {
+            writeln!(
+                f,
+                "  #{} scheduled {}: ordered {}, filled {} (crossed {}) @ {}, market VWAP {}",
+                slice.index,
+                slice.scheduled,
+                slice.ordered,
+                slice.filled,
+                slice.crossed,
+                format_price(slice.avg_price),
+                format_price(slice.market_vwap)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// TWAP 执行器；克隆后共享同一份市场成交记录，可在行情回调中调用 [`Self::on_event`]
+#[derive(Debug, Clone)]
+pub struct TwapExecutor {
+    config: TwapConfig,
+    quotes: BboCache,
+    /// 当前周期内市场成交的成交额与成交量
+    tape: Arc<Mutex<(Decimal, Decimal)>>,
+}
+
+impl TwapExecutor {
+    /// 被动子单按 `quotes` 中的最优价挂单
+    pub fn new(config: TwapConfig, quotes: BboCache) -> Result<Self, TwapError> {
+        config.validate()?;
+        Ok(Self {
+            config,
+            quotes,
+            tape: Arc::default(),
+        })
+    }
+
+    /// TWAP 参数
+    pub fn config(&self) -> &TwapConfig {
+        &self.config
+    }
+
+    /// 记录执行市场的成交，用于计算各周期的 VWAP；其他事件忽略
+    pub fn on_event(&self, event: &MarketEvent) {
+        if let MarketEvent::Trade(trade) = event {
+            if trade.symbol == self.config.symbol {
+                let mut tape = self.tape.lock().unwrap();
+                tape.0 += trade.price * trade.size;
+                tape.1 += trade.size;
+            }
+        }
+    }
+
+    /// 取出自上次调用以来的市场 VWAP
+    fn take_vwap(&self) -> Option<Decimal> {
+        let (notional, size) = std::mem::take(&mut *self.tape.lock().unwrap());
+        (!size.is_zero()).then(|| notional / size)
+    }
+
+    /// 按计划执行全部周期，返回执行报告
+    pub async fn run(&self, orders: &OrderManager<'_>) -> TwapReport {
+        let config = &self.config;
+        let slices = config.slices();
+        info!(
+            "TWAP {:?} {} on {} over {:?} in {} slices",
+            config.side, config.total_size, config.symbol, config.duration, slices
+        );
+        let start = Instant::now();
+        self.take_vwap();
+        let mut report = TwapReport {
+            symbol: config.symbol.clone(),
+            side: config.side,
+            total_size: config.total_size,
+            slices: Vec::new(),
+            outcome: TwapOutcome::Incomplete,
+        };
+        let mut filled = Decimal::ZERO;
+        for index in 0..slices {
+            let mut slice = TwapSlice::new(index, config.scheduled(index));
+            let child = config.align(slice.scheduled - filled);
+            let stopped = if child > Decimal::ZERO {
+                self.execute(orders, child, &mut slice).await.err()
+            } else {
+                None
+            };
+            filled += slice.filled;
+            if stopped.is_none() {
+                tokio::time::sleep_until(start + config.interval * (index + 1)).await;
+            }
+            slice.market_vwap = self.take_vwap();
+            info!(
+                "TWAP slice {}/{}: filled {} of {} @ {}, {} of {} done",
+                index + 1,
+                slices,
+                slice.filled,
+                slice.ordered,
+                format_price(slice.avg_price),
+                filled,
+                config.total_size
+            );
+            report.slices.push(slice);
+            if let Some(outcome) = stopped {
+                warn!("TWAP on {} stopped early: {:?}", config.symbol, outcome);
+                report.outcome = outcome;
+                return report;
+            }
+            if filled >= config.total_size {
+                report.outcome = TwapOutcome::Completed;
+                break;
+            }
+        }
+        report
+    }
+
+    /// 提交一个周期的子单：先被动挂单，超时后撤销，启用追赶时吃单补齐未成交部分
+    async fn execute(
+        &self,
+        orders: &OrderManager<'_>,
+        size: Decimal,
+        slice: &mut TwapSlice,
+    ) -> Result<(), TwapOutcome> {
+        let config = &self.config;
+        slice.ordered = size;
+        match self.passive_price() {
+            Some(price) => {
+                let spec = OrderSpec {
+                    side: config.side,
+                    order_type: OrderType::LIMIT,
+                    size,
+                    price: Some(price),
+                    instruction: OrderInstruction::POST_ONLY,
+                    client_id: None,
+                    reduce_only: false,
+                    trigger_price: None,
+                };
+                match orders.submit(NewOrder::new(&config.symbol, spec)).await {
+                    Ok(order) => {
+                        self.settle(orders, &order, config.passive_timeout, slice)
+                            .await
+                    }
+                    Err(e) => stop_on(e)?,
+                }
+            }
+            None => warn!(
+                "No BBO for {}, skipping the passive child order",
+                config.symbol
+            ),
+        }
+
+        let remaining = config.align(size - slice.filled);
+        if config.catchup && remaining > Decimal::ZERO {
+            match orders
+                .submit_market(
+                    &config.symbol,
+                    config.side,
+                    remaining,
+                    config.max_slippage_bps,
+                )
+                .await
+            {
+                Ok(taken) => {
+                    let before = slice.filled;
+                    self.settle(orders, &taken.order, SETTLE_TIMEOUT, slice)
+                        .await;
+                    slice.crossed += slice.filled - before;
+                }
+                Err(e) => stop_on(e)?,
+            }
+        }
+        Ok(())
+    }
+
+    /// 己方最优价：买单取买一、卖单取卖一
+    fn passive_price(&self) -> Option<Decimal> {
+        let quote = self.quotes.get(&self.config.symbol)?;
+        let price = match self.config.side {
+            Side::BUY => quote.bid,
+            Side::SELL => quote.ask,
+        };
+        (price > Decimal::ZERO).then_some(price)
+    }
+
+    /// 等待订单结束，超时则撤销；把最终成交计入 `slice`
+    async fn settle(
+        &self,
+        orders: &OrderManager<'_>,
+        order: &ManagedOrder,
+        timeout: Duration,
+        slice: &mut TwapSlice,
+    ) {
+        let state = match order.await_filled_or_cancelled(timeout).await {
+            Some(state) => Some(state),
+            None => {
+                if let Err(e) = orders.cancel(order.id()).await {
+                    warn!("Failed to cancel TWAP child order {}: {}", order.id(), e);
+                }
+                order
+                    .await_filled_or_cancelled(SETTLE_TIMEOUT)
+                    .await
+                    .or_else(|| order.state())
+            }
+        };
+        if let Some(state) = state {
+            slice.record(state.filled_size(), state.avg_fill_price);
+        }
+    }
+}
+
+/// 子单提交失败时是否停止：风控拒绝与下单错误停止执行，POST_ONLY 穿越盘口与吃单滑点超限只跳过本次下单
+fn stop_on(error: OrderManagerError) -> Result<(), TwapOutcome> {
+    match error {
+        OrderManagerError::Gateway(GatewayError::Risk(_)) => {
+            Err(TwapOutcome::RiskBlocked(error.to_string()))
+        }
+        OrderManagerError::Order(OrderError::PostOnlyWouldCross { .. })
+        | OrderManagerError::Risk(_) => {
+            warn!("TWAP child order skipped: {}", error);
+            Ok(())
+        }
+        error => Err(TwapOutcome::Failed(error.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::RiskLimits;
+    use crate::paper::test_support::{factory, PaperMarket, MARKET};
+    use crate::risk::RiskGuard;
+
+    fn config() -> TwapConfig {
+        TwapConfig {
+            symbol: MARKET.to_string(),
+            side: Side::BUY,
+            total_size: Decimal::new(3, 2),
+            duration: Duration::from_secs(30),
+            interval: Duration::from_secs(10),
+            passive_timeout: Duration::from_secs(5),
+            catchup: true,
+            max_slippage_bps: Decimal::from(50),
+            size_increment: Decimal::new(1, 3),
+        }
+    }
+
+    #[test]
+    fn schedule_is_aligned_and_ends_at_the_total() {
+        let config = TwapConfig {
+            total_size: Decimal::new(10, 3),
+            duration: Duration::from_secs(35),
+            ..config()
+        };
+        assert_eq!(config.slices(), 4);
+        let scheduled: Vec<_> = (0..4).map(|slice| config.scheduled(slice)).collect();
+        // 0.0025 / 0.005 / 0.0075 向下对齐到 0.001，最后一个周期为全部数量
+        assert_eq!(
+            scheduled,
+            [
+                Decimal::new(2, 3),
+                Decimal::new(5, 3),
+                Decimal::new(7, 3),
+                Decimal::new(10, 3)
+            ]
+        );
+
+        assert_eq!(
+            TwapConfig {
+                total_size: Decimal::ZERO,
+                ..config.clone()
+            }
+            .validate(),
+            Err(TwapError::InvalidSize(Decimal::ZERO))
+        );
+        assert_eq!(
+            TwapConfig {
+                interval: Duration::from_secs(60),
+                ..config.clone()
+            }
+            .validate(),
+            Err(TwapError::InvalidInterval)
+        );
+        assert_eq!(
+            TwapConfig {
+                passive_timeout: Duration::from_secs(11),
+                ..config
+            }
+            .validate(),
+            Err(TwapError::InvalidPassiveTimeout)
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn passive_children_fill_and_catch_up_against_the_paper_exchange() {
+        let market = PaperMarket::new();
+        let twap = TwapExecutor::new(config(), market.quotes.clone()).unwrap();
+        let orders = market.manager("twap").with_quotes(market.quotes.clone());
+        market.bbo(Decimal::from(100), Decimal::from(101));
+
+        let script = {
+            let (market, twap) = (market.clone(), twap.clone());
+            tokio::spawn(async move {
+                let after = |millis: u64| {
+                    tokio::time::sleep_until(Instant::now() + Duration::from_millis(millis))
+                };
+                // 第 1 个周期：成交价穿过买一挂单，被动子单全部成交
+                after(1_000).await;
+                twap.on_event(&market.trade(Decimal::new(995, 1), Decimal::new(1, 2)));
+                // 第 2 个周期：成交未穿过挂单价，超时后按卖一追赶
+                after(11_000).await;
+                twap.on_event(&market.trade(Decimal::new(1002, 1), Decimal::ONE));
+                // 第 3 个周期：盘口上移，被动子单部分成交，其余追赶
+                after(7_500).await;
+                market.bbo(Decimal::from(101), Decimal::from(102));
+                after(1_500).await;
+                twap.on_event(&market.trade(Decimal::new(1008, 1), Decimal::new(4, 3)));
+            })
+        };
+
+        let report = twap.run(&orders).await;
+        script.await.unwrap();
+        assert_eq!(report.outcome, TwapOutcome::Completed);
+        assert_eq!(report.filled(), Decimal::new(3, 2));
+        let slices: Vec<_> = report
+            .slices
+            .iter()
+            .map(|slice| {
+                (
+                    slice.ordered,
+                    slice.filled,
+                    slice.crossed,
+                    slice.avg_price.unwrap(),
+                    slice.market_vwap.unwrap(),
+                )
+            })
+            .collect();
+        let size = Decimal::new(1, 2);
+        assert_eq!(
+            slices,
+            [
+                (
+                    size,
+                    size,
+                    Decimal::ZERO,
+                    Decimal::from(100),
+                    Decimal::new(995, 1)
+                ),
+                (size, size, size, Decimal::from(101), Decimal::new(1002, 1)),
+                (
+                    size,
+                    size,
+                    Decimal::new(6, 3),
+                    Decimal::new(1016, 1),
+                    Decimal::new(1008, 1)
+                ),
+            ]
+        );
+        // (100 + 101 + 101.6) / 3 对比 (99.5 + 100.2 + 100.8) / 3
+        assert_eq!(
+            report.avg_price().unwrap().round_dp(4),
+            Decimal::new(1008667, 4)
+        );
+        assert_eq!(
+            report.benchmark_vwap().unwrap().round_dp(4),
+            Decimal::new(1001667, 4)
+        );
+        assert_eq!(
+            report.slippage_bps().unwrap().round_dp(2),
+            Decimal::new(6988, 2)
+        );
+        assert_eq!(
+            market.exchange.position(MARKET).unwrap().size,
+            Decimal::new(3, 2)
+        );
+        assert!(report.to_string().starts_with(
+            "TWAP BUY 0.03 on BTC-USD-PERP: filled 0.030 (Completed), avg 100.86666667 \
+             vs interval VWAP 100.16666667 (69.88 bps)"
+        ));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn twap_stops_when_the_risk_guard_trips() {
+        let market = PaperMarket::new();
+        let twap = TwapExecutor::new(config(), market.quotes.clone()).unwrap();
+        let limits = RiskLimits {
+            max_order_size: Decimal::ONE,
+            max_position: Some(Decimal::new(15, 3)),
+            max_notional: Decimal::from(1_000_000),
+            funding_alert_bps: None,
+            max_slippage_bps: None,
+            min_free_collateral: None,
+        };
+        let guard = RiskGuard::new(market.exchange.as_ref(), market.exchange.as_ref(), limits);
+        let orders = OrderManager::new(&guard, market.tracker.clone(), factory("twap"))
+            .with_quotes(market.quotes.clone());
+        market.bbo(Decimal::from(100), Decimal::from(101));
+
+        let report = twap.run(&orders).await;
+        assert!(matches!(report.outcome, TwapOutcome::RiskBlocked(_)));
+        // 第 1 个周期被动子单超时后全部追赶成交，第 2 个子单会使持仓超过 0.015
+        assert_eq!(report.slices.len(), 2);
+        assert_eq!(report.slices[0].crossed, Decimal::new(1, 2));
+        assert_eq!(report.slices[1].filled, Decimal::ZERO);
+        assert_eq!(report.filled(), Decimal::new(1, 2));
+        assert!(market.exchange.open_orders().is_empty());
+    }
+}
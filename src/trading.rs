@@ -0,0 +1,16 @@
+//! 交易状态：由订单频道维护的订单状态与由成交频道维护的成交台账，以及统一的下单入口、括号单与平仓
+
+mod bracket;
+mod close;
+mod fill_ledger;
+mod order_manager;
+mod order_tracker;
+
+pub use bracket::BracketReport;
+pub use close::CloseOptions;
+pub use fill_ledger::{FillLedger, LedgerEntry, MarketFills, RealizedPnl};
+pub use order_manager::{
+    CancelScope, ManagedOrder, MarketOrder, NewOrder, OrderManager, OrderManagerError,
+    TriggerOrder, DEFAULT_POST_ONLY_RETRIES,
+};
+pub use order_tracker::{OrderEvent, OrderState, OrderTracker, TrackedOrder};
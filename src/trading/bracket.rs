@@ -0,0 +1,582 @@
+//! 括号单（bracket）：入场单成交后自动挂出止盈与止损，两者在本地按 OCO（一方成交撤销另一方）联动
+//!
+//! [`OrderManager::submit_bracket`] 提交入场单后按订单状态表跟踪其成交，已成交且尚未平掉的数量由一对
+//! 只减仓的出场单保护：成交均价偏移 `take_profit_offset` 的止盈限价单，与反方向偏移
+//! `stop_loss_offset` 的止损市价单（`STOP_LOSS_MARKET`）。入场单继续成交时按新的均价与数量重挂
+//! 两张出场单；一张出场单部分成交后另一张缩减到剩余数量，全部平掉或持仓归零时撤销另一张。
+//!
+//! 撤单请求到达前另一张出场单可能已经成交或被交易所撤销（OCO 竞争），撤单失败只记录告警；
+//! 只减仓保证两张同时成交时也不会反向开仓，成交数量按订单状态表中两张出场单的实际成交统计。
+
+use log::{info, warn};
+use paradex::structs::{OrderInstruction, OrderType, Side};
+use rust_decimal::Decimal;
+use std::fmt;
+use std::time::Duration;
+
+use super::{NewOrder, OrderManager, OrderManagerError, TrackedOrder};
+use crate::orders::{aggressive_price, passive_price, validate_trigger, OrderError, OrderSpec};
+
+/// 没有订单推送时重新检查持仓与重试出场单的间隔
+const BRACKET_POLL: Duration = Duration::from_millis(250);
+
+/// 出场单的种类
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Exit {
+    TakeProfit,
+    StopLoss,
+}
+
+/// 挂出中的出场单及其目标价格（止盈为限价、止损为触发价）
+struct Leg {
+    id: String,
+    price: Decimal,
+}
+
+/// 括号单的执行结果
+#[derive(Debug, Clone, PartialEq)]
+pub struct BracketReport {
+    pub market: String,
+    pub side: Side,
+    /// 入场单的最终状态
+    pub entry: Option<TrackedOrder>,
+    pub entry_filled: Decimal,
+    pub average_entry: Option<Decimal>,
+    pub take_profit_filled: Decimal,
+    pub stop_loss_filled: Decimal,
+    /// 出场单成交前持仓已归零（如手动平仓），出场单随之撤销
+    pub flattened: bool,
+}
+
+impl fmt::Display for BracketReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Bracket {:?} {} on {}: entry filled {} @ {}, take-profit filled {}, stop-loss filled {}",
+            self.side,
+            self.entry.as_ref().map_or("-", |entry| entry.id.as_str()),
+            self.market,
+            self.entry_filled,
+            self.average_entry
+                .map_or("-".to_string(), |price| price.to_string()),
+            self.take_profit_filled,
+            self.stop_loss_filled
+        )?;
+        if self.flattened {
+            write!(f, " (position closed elsewhere)")?;
+        }
+        Ok(())
+    }
+}
+
+/// 一个括号单的本地状态；成交数量每次都从订单状态表读取，推送只用于唤醒
+struct Bracket<'m, 'a> {
+    orders: &'m OrderManager<'a>,
+    market: String,
+    side: Side,
+    take_profit_offset: Decimal,
+    stop_loss_offset: Decimal,
+    entry_id: String,
+    take_profit: Option<Leg>,
+    stop_loss: Option<Leg>,
+    /// 提交过的全部出场单（含已撤销重挂的），用于统计出场成交
+    exits: Vec<(Exit, String)>,
+    /// 持仓归零时入场单的成交数量，视为已平掉
+    flat_at: Decimal,
+    /// 已在持仓缓存中看到入场方向的持仓；此前持仓为 0 可能只是持仓推送滞后
+    seen_open: bool,
+}
+
+impl Bracket<'_, '_> {
+    fn exit_side(&self) -> Side {
+        match self.side {
+            Side::BUY => Side::SELL,
+            Side::SELL => Side::BUY,
+        }
+    }
+
+    /// 出场单相对成交均价 `average` 的价格，按价格精度向远离入场价的方向取整
+    fn exit_price(&self, exit: Exit, average: Decimal) -> Decimal {
+        let tick = self.orders.price_tick(&self.market);
+        let long = self.side == Side::BUY;
+        match exit {
+            Exit::TakeProfit => {
+                let price = if long {
+                    average + self.take_profit_offset
+                } else {
+                    average - self.take_profit_offset
+                };
+                passive_price(self.exit_side(), price, Decimal::ZERO, tick)
+            }
+            Exit::StopLoss => {
+                let price = if long {
+                    average - self.stop_loss_offset
+                } else {
+                    average + self.stop_loss_offset
+                };
+                aggressive_price(self.exit_side(), price, Decimal::ZERO, tick)
+            }
+        }
+    }
+
+    /// `exit` 种类的出场单累计成交数量
+    fn exited(&self, exit: Exit) -> Decimal {
+        self.exits
+            .iter()
+            .filter(|(kind, _)| *kind == exit)
+            .filter_map(|(_, id)| self.orders.tracker().get(id))
+            .map(|order| order.filled_size())
+            .sum()
+    }
+
+    /// 入场成交 `filled` 中尚未平掉、需要出场单保护的数量
+    fn open_size(&self, filled: Decimal) -> Decimal {
+        let exited = self.exited(Exit::TakeProfit) + self.exited(Exit::StopLoss);
+        (filled - exited.max(self.flat_at)).max(Decimal::ZERO)
+    }
+
+    /// 持仓在出场单成交前归零时，把入场成交全部视为已平掉
+    fn check_flat(&mut self, filled: Decimal) {
+        let Some(position) = self.orders.position(&self.market) else {
+            return;
+        };
+        let held = match self.side {
+            Side::BUY => position,
+            Side::SELL => -position,
+        };
+        if held > Decimal::ZERO {
+            self.seen_open = true;
+        } else if self.seen_open && position.is_zero() && !self.open_size(filled).is_zero() {
+            warn!(
+                "Position on {} went flat outside the bracket, cancelling its exits",
+                self.market
+            );
+            self.flat_at = filled;
+            self.seen_open = false;
+        }
+    }
+
+    fn leg(&mut self, exit: Exit) -> &mut Option<Leg> {
+        match exit {
+            Exit::TakeProfit => &mut self.take_profit,
+            Exit::StopLoss => &mut self.stop_loss,
+        }
+    }
+
+    /// 按当前成交调整出场单；入场单结束且没有需要保护的数量时返回 `true`
+    async fn sync(&mut self) -> Result<bool, OrderManagerError> {
+        let entry = self
+            .orders
+            .tracker()
+            .get(&self.entry_id)
+            .ok_or_else(|| OrderManagerError::UnknownOrder(self.entry_id.clone()))?;
+        let filled = entry.filled_size();
+        self.check_flat(filled);
+        let open = self.open_size(filled);
+        for exit in [Exit::TakeProfit, Exit::StopLoss] {
+            let price = entry
+                .avg_fill_price
+                .map(|average| self.exit_price(exit, average));
+            self.reconcile(exit, open, price).await?;
+        }
+        Ok(entry.state.is_terminal()
+            && open.is_zero()
+            && self.take_profit.is_none()
+            && self.stop_loss.is_none())
+    }
+
+    /// 出场单的剩余数量与价格不符时撤销并按 `size` 与 `price` 重挂；`size` 为 0 时只撤销
+    async fn reconcile(
+        &mut self,
+        exit: Exit,
+        size: Decimal,
+        price: Option<Decimal>,
+    ) -> Result<(), OrderManagerError> {
+        if let Some(leg) = self.leg(exit).take() {
+            let live = self
+                .orders
+                .tracker()
+                .get(&leg.id)
+                .filter(|order| !order.state.is_terminal());
+            match live {
+                Some(order) if order.remaining_size == size && Some(leg.price) == price => {
+                    *self.leg(exit) = Some(leg);
+                    return Ok(());
+                }
+                Some(_) => {
+                    // 撤单前可能已成交或已被交易所撤销，其成交仍按订单状态表统计
+                    if let Err(e) = self.orders.cancel(&leg.id).await {
+                        warn!("Failed to cancel {:?} order {}: {}", exit, leg.id, e);
+                    }
+                }
+                None => {}
+            }
+        }
+        let Some(price) = price.filter(|_| !size.is_zero()) else {
+            return Ok(());
+        };
+        self.place(exit, size, price).await
+    }
+
+    /// 挂出 `size` 的出场单；止损价已被穿过时改为只减仓的市价单立即平仓
+    async fn place(
+        &mut self,
+        exit: Exit,
+        size: Decimal,
+        price: Decimal,
+    ) -> Result<(), OrderManagerError> {
+        let side = self.exit_side();
+        let mut spec = OrderSpec {
+            side,
+            order_type: OrderType::LIMIT,
+            size,
+            price: Some(price),
+            instruction: OrderInstruction::GTC,
+            client_id: None,
+            reduce_only: true,
+            trigger_price: None,
+        };
+        if exit == Exit::StopLoss {
+            spec.order_type = OrderType::STOP_LOSS_MARKET;
+            spec.price = None;
+            spec.trigger_price = Some(price);
+            let mark = self.orders.reference_mark(&self.market);
+            if let Some(Err(e)) =
+                mark.map(|mark| validate_trigger(spec.order_type, side, price, mark))
+            {
+                warn!(
+                    "Closing {} at market, the stop is already breached: {}",
+                    self.market, e
+                );
+                spec.order_type = OrderType::MARKET;
+                spec.instruction = OrderInstruction::IOC;
+                spec.trigger_price = None;
+            }
+        }
+        let order = match self.orders.submit(NewOrder::new(&self.market, spec)).await {
+            Ok(order) => order,
+            // 持仓推送可能晚于入场成交，下一次检查时重试
+            Err(OrderManagerError::Order(
+                e @ (OrderError::NoPositionToReduce(_)
+                | OrderError::ReduceOnlyExceedsPosition { .. }
+                | OrderError::ReduceOnlyWrongSide { .. }),
+            )) => {
+                warn!("Deferring the {:?} order on {}: {}", exit, self.market, e);
+                return Ok(());
+            }
+            Err(e) => return Err(e),
+        };
+        info!(
+            "Placed {:?} {:?} {} on {} at {} as {}",
+            exit,
+            side,
+            size,
+            self.market,
+            price,
+            order.id()
+        );
+        self.exits.push((exit, order.id().to_string()));
+        *self.leg(exit) = Some(Leg {
+            id: order.id().to_string(),
+            price,
+        });
+        Ok(())
+    }
+
+    fn report(&self) -> BracketReport {
+        let entry = self.orders.tracker().get(&self.entry_id);
+        BracketReport {
+            market: self.market.clone(),
+            side: self.side,
+            entry_filled: entry
+                .as_ref()
+                .map_or(Decimal::ZERO, TrackedOrder::filled_size),
+            average_entry: entry.as_ref().and_then(|entry| entry.avg_fill_price),
+            entry,
+            take_profit_filled: self.exited(Exit::TakeProfit),
+            stop_loss_filled: self.exited(Exit::StopLoss),
+            flattened: !self.flat_at.is_zero(),
+        }
+    }
+}
+
+impl OrderManager<'_> {
+    /// 提交入场单 `entry`，并为其成交挂出止盈与止损出场单，直到入场单结束且成交已全部平掉
+    ///
+    /// 止盈价为成交均价向有利方向偏移 `take_profit_offset`，止损触发价向不利方向偏移
+    /// `stop_loss_offset`（均为价格单位）。入场单一直未成交时在其结束后返回；调用方负责超时与退出。
+    pub async fn submit_bracket(
+        &self,
+        entry: NewOrder,
+        take_profit_offset: Decimal,
+        stop_loss_offset: Decimal,
+    ) -> Result<BracketReport, OrderManagerError> {
+        for offset in [take_profit_offset, stop_loss_offset] {
+            if offset <= Decimal::ZERO {
+                return Err(OrderError::InvalidBracketOffset(offset).into());
+            }
+        }
+        // 先订阅再下单，不会错过入场单的推送
+        let mut events = self.tracker().subscribe();
+        let market = entry.market.clone();
+        let side = entry.spec.side;
+        let order = self.submit(entry).await?;
+        info!(
+            "Bracket entry {} on {} submitted, take-profit offset {}, stop-loss offset {}",
+            order.id(),
+            market,
+            take_profit_offset,
+            stop_loss_offset
+        );
+        let mut bracket = Bracket {
+            orders: self,
+            market,
+            side,
+            take_profit_offset,
+            stop_loss_offset,
+            entry_id: order.id().to_string(),
+            take_profit: None,
+            stop_loss: None,
+            exits: Vec::new(),
+            flat_at: Decimal::ZERO,
+            seen_open: false,
+        };
+        let mut poll = tokio::time::interval(BRACKET_POLL);
+        loop {
+            if bracket.sync().await? {
+                return Ok(bracket.report());
+            }
+            // 落后（`Lagged`）也只是唤醒，状态从订单状态表读取
+            tokio::select! {
+                _ = events.recv() => {}
+                _ = poll.tick() => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gateway::OrderGateway;
+    use crate::paper::test_support::{PaperMarket, MARKET};
+    use crate::trading::OrderState;
+    use paradex::structs::{OrderRequest, OrderUpdate};
+
+    /// 带报价与持仓的订单管理器：市价入场按报价估算，出场单按持仓只减仓
+    fn manager(market: &PaperMarket) -> OrderManager<'_> {
+        market
+            .manager("bracket")
+            .with_quotes(market.quotes.clone())
+            .with_positions(market.positions.clone())
+    }
+
+    fn entry(order_type: OrderType, price: Option<i64>, size: Decimal) -> NewOrder {
+        NewOrder::new(
+            MARKET,
+            OrderSpec {
+                side: Side::BUY,
+                order_type,
+                size,
+                price: price.map(Decimal::from),
+                instruction: if price.is_some() {
+                    OrderInstruction::GTC
+                } else {
+                    OrderInstruction::IOC
+                },
+                client_id: None,
+                reduce_only: false,
+                trigger_price: None,
+            },
+        )
+    }
+
+    /// 挂出中的出场单：（类型, 数量, 限价或触发价）
+    fn exits(market: &PaperMarket) -> Vec<(OrderType, Decimal, Decimal)> {
+        let mut exits: Vec<_> = market
+            .exchange
+            .open_orders()
+            .into_iter()
+            .filter(|order| order.side == Side::SELL)
+            .filter_map(|order: OrderUpdate| {
+                let price = order.price.or(order.trigger_price)?;
+                Some((order.order_type, order.remaining_size, price))
+            })
+            .collect();
+        exits.sort_by_key(|(_, _, price)| *price);
+        exits
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn partial_entry_fills_scale_the_exits() {
+        let market = PaperMarket::new();
+        let orders = manager(&market);
+        market.bbo(Decimal::new(1005, 1), Decimal::from(101));
+        let half = Decimal::new(5, 1);
+
+        let bracket = orders.submit_bracket(
+            entry(OrderType::LIMIT, Some(100), Decimal::TWO),
+            Decimal::from(5),
+            Decimal::from(3),
+        );
+        let script = async {
+            let entry = [(Side::BUY, Decimal::from(100))];
+            market
+                .wait_for("the entry order", || market.resting() == entry)
+                .await;
+            assert!(exits(&market).is_empty());
+
+            // 入场单成交 0.5：出场单按 0.5 挂出
+            market.trade(Decimal::new(995, 1), half);
+            let scaled = [
+                (OrderType::STOP_LOSS_MARKET, half, Decimal::from(97)),
+                (OrderType::LIMIT, half, Decimal::from(105)),
+            ];
+            market
+                .wait_for("exits for 0.5", || exits(&market) == scaled)
+                .await;
+
+            // 入场单全部成交：出场单按 2 重挂
+            market.trade(Decimal::from(99), Decimal::from(5));
+            let full = [
+                (OrderType::STOP_LOSS_MARKET, Decimal::TWO, Decimal::from(97)),
+                (OrderType::LIMIT, Decimal::TWO, Decimal::from(105)),
+            ];
+            market
+                .wait_for("exits for 2", || exits(&market) == full)
+                .await;
+
+            // 止盈成交 0.5：止损缩减到剩余的 1.5
+            market.trade(Decimal::new(1055, 1), half);
+            let rest = Decimal::new(15, 1);
+            let remaining = [
+                (OrderType::STOP_LOSS_MARKET, rest, Decimal::from(97)),
+                (OrderType::LIMIT, rest, Decimal::from(105)),
+            ];
+            market
+                .wait_for("exits for 1.5", || exits(&market) == remaining)
+                .await;
+
+            market.trade(Decimal::from(106), Decimal::from(5));
+        };
+        let (report, ()) = tokio::join!(bracket, script);
+
+        let report = report.unwrap();
+        assert_eq!(report.entry_filled, Decimal::TWO);
+        assert_eq!(report.average_entry, Some(Decimal::from(100)));
+        assert_eq!(report.take_profit_filled, Decimal::TWO);
+        assert_eq!(report.stop_loss_filled, Decimal::ZERO);
+        assert!(!report.flattened);
+        assert!(market.exchange.open_orders().is_empty());
+        assert_eq!(
+            market.exchange.position(MARKET).unwrap().size,
+            Decimal::ZERO
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn both_exits_firing_before_the_cancel_never_flip_the_position() {
+        let market = PaperMarket::new();
+        let orders = manager(&market);
+        market.bbo(Decimal::from(100), Decimal::from(101));
+
+        let bracket = orders.submit_bracket(
+            entry(OrderType::MARKET, None, Decimal::ONE),
+            Decimal::from(5),
+            Decimal::from(3),
+        );
+        let script = async {
+            let placed = [
+                (OrderType::STOP_LOSS_MARKET, Decimal::ONE, Decimal::from(98)),
+                (OrderType::LIMIT, Decimal::ONE, Decimal::from(106)),
+            ];
+            market
+                .wait_for("both exits", || exits(&market) == placed)
+                .await;
+            // 括号单处理推送之前，止盈先成交 0.4，随后价格急跌触发止损
+            market.trade(Decimal::new(1065, 1), Decimal::new(4, 1));
+            market.bbo(Decimal::from(96), Decimal::from(97));
+            market.trade(Decimal::from(97), Decimal::ONE);
+        };
+        let (report, ()) = tokio::join!(bracket, script);
+
+        let report = report.unwrap();
+        assert_eq!(report.entry_filled, Decimal::ONE);
+        assert_eq!(report.take_profit_filled, Decimal::new(4, 1));
+        assert_eq!(report.stop_loss_filled, Decimal::new(6, 1));
+        assert!(market.exchange.open_orders().is_empty());
+        assert_eq!(
+            market.exchange.position(MARKET).unwrap().size,
+            Decimal::ZERO
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn exits_are_cancelled_when_the_position_goes_flat() {
+        let market = PaperMarket::new();
+        let orders = manager(&market);
+        market.bbo(Decimal::from(100), Decimal::from(101));
+
+        let bracket = orders.submit_bracket(
+            entry(OrderType::MARKET, None, Decimal::ONE),
+            Decimal::from(5),
+            Decimal::from(3),
+        );
+        let script = async {
+            market
+                .wait_for("both exits", || exits(&market).len() == 2)
+                .await;
+            // 在括号单之外平仓
+            market
+                .exchange
+                .create_order(OrderRequest {
+                    instruction: OrderInstruction::IOC,
+                    market: MARKET.to_string(),
+                    price: None,
+                    side: Side::SELL,
+                    size: Decimal::ONE,
+                    order_type: OrderType::MARKET,
+                    client_id: Some("manual".to_string()),
+                    flags: vec![],
+                    recv_window: None,
+                    stp: None,
+                    trigger_price: None,
+                })
+                .await
+                .unwrap();
+        };
+        let (report, ()) = tokio::join!(bracket, script);
+
+        let report = report.unwrap();
+        assert!(report.flattened);
+        assert_eq!(
+            report.take_profit_filled + report.stop_loss_filled,
+            Decimal::ZERO
+        );
+        assert!(market.exchange.open_orders().is_empty());
+        assert_eq!(
+            report.entry.map(|entry| entry.state),
+            Some(OrderState::Filled)
+        );
+    }
+
+    #[tokio::test]
+    async fn offsets_must_be_positive() {
+        let market = PaperMarket::new();
+        let orders = manager(&market);
+        let result = orders
+            .submit_bracket(
+                entry(OrderType::MARKET, None, Decimal::ONE),
+                Decimal::ZERO,
+                Decimal::ONE,
+            )
+            .await;
+        assert!(matches!(
+            result,
+            Err(OrderManagerError::Order(OrderError::InvalidBracketOffset(
+                _
+            )))
+        ));
+        assert!(market.exchange.open_orders().is_empty());
+    }
+}
@@ -0,0 +1,391 @@
+//! 平仓：提交只减仓的 IOC 订单，部分成交时按剩余持仓重新提交，直到持仓归零或超时
+//!
+//! [`OrderManager::close_position`] 每轮提交后等待一个查询间隔，再从 [`RiskContext`] 读取持仓：
+//! 归零即完成；仍有剩余则按剩余数量（限价模式下按新的对手价）再次提交。只减仓保证重复提交
+//! 不会反向开仓。
+
+use log::{info, warn};
+use paradex::structs::{OrderInstruction, OrderType, Position, Side};
+use rust_decimal::Decimal;
+use std::time::Duration;
+
+use super::{NewOrder, OrderManager, OrderManagerError};
+use crate::orders::{aggressive_price, OrderSpec};
+use crate::positions::{closing_order, CloseOutcome, ClosingOrder};
+use crate::risk::RiskContext;
+
+/// 平仓的下单与等待参数
+#[derive(Debug, Clone)]
+pub struct CloseOptions {
+    /// 限价平仓时相对对手价的滑点（基点）；`None` 时提交市价单
+    pub slippage_bps: Option<Decimal>,
+    /// 等待持仓归零的总时长
+    pub timeout: Duration,
+    /// 每次提交后查询持仓的间隔
+    pub poll_interval: Duration,
+    /// 只记录第一笔平仓请求，不等待也不重试
+    pub dry_run: bool,
+}
+
+impl OrderManager<'_> {
+    /// 平掉 `order` 对应的持仓，持仓与对手价取自 `context`
+    ///
+    /// 第一笔订单被拒绝时返回 [`CloseOutcome::Failed`]；之后的重试失败只记录告警，
+    /// 继续查询持仓直到超时。
+    pub async fn close_position(
+        &self,
+        context: &dyn RiskContext,
+        order: ClosingOrder,
+        options: &CloseOptions,
+    ) -> CloseOutcome {
+        let market = order.market.as_str();
+        let deadline = tokio::time::Instant::now() + options.timeout;
+        let mut remaining = order.size;
+        let mut attempt = 0u32;
+        loop {
+            attempt += 1;
+            if let Err(reason) = self.submit_close(context, &order, remaining, options).await {
+                if attempt == 1 {
+                    return CloseOutcome::Failed(reason);
+                }
+                warn!("Failed to resubmit close order on {}: {}", market, reason);
+            }
+            if options.dry_run {
+                return CloseOutcome::DryRun {
+                    side: order.side,
+                    size: order.size,
+                };
+            }
+
+            tokio::time::sleep(options.poll_interval).await;
+            match context.position(market).await {
+                // 只减仓不会反向开仓；与平仓方向相同的持仓视为已平
+                Ok(position) => {
+                    remaining = match order.side {
+                        Side::BUY => -position,
+                        Side::SELL => position,
+                    }
+                    .max(Decimal::ZERO);
+                    if remaining.is_zero() {
+                        return CloseOutcome::Closed {
+                            side: order.side,
+                            size: order.size,
+                        };
+                    }
+                }
+                Err(e) => warn!("Failed to poll the position on {}: {}", market, e),
+            }
+            if tokio::time::Instant::now() + options.poll_interval > deadline {
+                return CloseOutcome::TimedOut { remaining };
+            }
+            info!(
+                "{} still has {} open after close attempt {}, resubmitting",
+                market, remaining, attempt
+            );
+        }
+    }
+
+    /// 依次平掉 `markets` 的持仓；`positions` 为平仓前的持仓快照，其中没有未平持仓的市场记为
+    /// [`CloseOutcome::AlreadyFlat`]，不下单
+    pub async fn close_positions(
+        &self,
+        context: &dyn RiskContext,
+        positions: &[Position],
+        markets: &[String],
+        options: &CloseOptions,
+    ) -> Vec<(String, CloseOutcome)> {
+        let mut outcomes = Vec::new();
+        for market in markets {
+            let open = positions
+                .iter()
+                .filter(|position| position.market == *market)
+                .find_map(|position| Some((position, closing_order(position)?)));
+            let outcome = match open {
+                Some((position, order)) => {
+                    info!(
+                        "Closing {} {} entered at {}",
+                        position.size, market, position.average_entry_price
+                    );
+                    self.close_position(context, order, options).await
+                }
+                None => CloseOutcome::AlreadyFlat,
+            };
+            info!("{}: {}", market, outcome);
+            outcomes.push((market.clone(), outcome));
+        }
+        outcomes
+    }
+
+    /// 提交 `size` 的只减仓 IOC 平仓单；限价模式下买单参考最优卖价、卖单参考最优买价
+    async fn submit_close(
+        &self,
+        context: &dyn RiskContext,
+        order: &ClosingOrder,
+        size: Decimal,
+        options: &CloseOptions,
+    ) -> Result<(), String> {
+        let price = match options.slippage_bps {
+            None => None,
+            Some(bps) => {
+                let book = context.order_book(&order.market).await;
+                let reference = book.and_then(|book| match order.side {
+                    Side::BUY => book.best_ask(),
+                    Side::SELL => book.best_bid(),
+                });
+                let Some(reference) = reference else {
+                    return Err(format!("failed to fetch BBO for {}", order.market));
+                };
+                Some(aggressive_price(
+                    order.side,
+                    reference,
+                    bps,
+                    self.price_tick(&order.market),
+                ))
+            }
+        };
+        let spec = OrderSpec {
+            side: order.side,
+            order_type: if price.is_some() {
+                OrderType::LIMIT
+            } else {
+                OrderType::MARKET
+            },
+            size,
+            price,
+            instruction: OrderInstruction::IOC,
+            client_id: None,
+            reduce_only: true,
+            trigger_price: None,
+        };
+        match self.submit(NewOrder::new(&order.market, spec)).await {
+            Ok(_) => Ok(()),
+            Err(OrderManagerError::Order(e)) => Err(e.to_string()),
+            Err(e) => Err(format!("order rejected: {}", e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client_id::ClientIdGenerator;
+    use crate::gateway::{DryRun, GatewayError, OrderGateway};
+    use crate::onboarding::ParadexConfig;
+    use crate::orders::OrderFactory;
+    use crate::trading::OrderTracker;
+    use async_trait::async_trait;
+    use paradex::structs::{CancelByMarketResponse, ModifyOrderRequest, OrderRequest, OrderUpdate};
+    use std::sync::Mutex;
+
+    const MARKET: &str = "BTC-USD-PERP";
+
+    /// 每笔订单最多成交 `fill_cap` 的网关，同时充当持仓来源
+    struct PartialFills {
+        inner: DryRun,
+        fill_cap: Decimal,
+        position: Mutex<Decimal>,
+        sizes: Mutex<Vec<Decimal>>,
+    }
+
+    impl PartialFills {
+        fn new(position: Decimal, fill_cap: Decimal) -> Self {
+            Self {
+                inner: DryRun::new("0x1"),
+                fill_cap,
+                position: Mutex::new(position),
+                sizes: Mutex::default(),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl OrderGateway for PartialFills {
+        async fn create_order(&self, request: OrderRequest) -> Result<OrderUpdate, GatewayError> {
+            self.sizes.lock().unwrap().push(request.size);
+            {
+                let mut position = self.position.lock().unwrap();
+                let fill = request.size.min(self.fill_cap).min(position.abs());
+                *position += match request.side {
+                    Side::BUY => fill,
+                    Side::SELL => -fill,
+                };
+            }
+            self.inner.create_order(request).await
+        }
+
+        async fn modify_order(
+            &self,
+            request: ModifyOrderRequest,
+        ) -> Result<OrderUpdate, GatewayError> {
+            self.inner.modify_order(request).await
+        }
+
+        async fn cancel_order(&self, order_id: String) -> Result<(), GatewayError> {
+            self.inner.cancel_order(order_id).await
+        }
+
+        async fn cancel_order_by_client_id(&self, client_id: String) -> Result<(), GatewayError> {
+            self.inner.cancel_order_by_client_id(client_id).await
+        }
+
+        async fn cancel_all_orders_for_market(
+            &self,
+            market: String,
+        ) -> Result<CancelByMarketResponse, GatewayError> {
+            self.inner.cancel_all_orders_for_market(market).await
+        }
+
+        async fn cancel_all_orders(&self) -> Result<Vec<String>, GatewayError> {
+            self.inner.cancel_all_orders().await
+        }
+    }
+
+    #[async_trait]
+    impl RiskContext for PartialFills {
+        async fn position(&self, _market: &str) -> Result<Decimal, String> {
+            Ok(*self.position.lock().unwrap())
+        }
+
+        async fn reference_price(&self, _market: &str) -> Option<Decimal> {
+            Some(Decimal::from(100))
+        }
+    }
+
+    fn manager(gateway: &PartialFills) -> OrderManager<'_> {
+        let factory = OrderFactory::new(ClientIdGenerator::new("close"), &ParadexConfig::testnet());
+        OrderManager::new(gateway, OrderTracker::new(), factory)
+    }
+
+    fn options(timeout: Duration) -> CloseOptions {
+        CloseOptions {
+            slippage_bps: None,
+            timeout,
+            poll_interval: Duration::from_secs(1),
+            dry_run: false,
+        }
+    }
+
+    fn closing(side: Side, size: Decimal) -> ClosingOrder {
+        ClosingOrder {
+            market: MARKET.to_string(),
+            side,
+            size,
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn partial_fills_are_resubmitted_until_flat() {
+        // 多头 0.25，每笔最多成交 0.1
+        let gateway = PartialFills::new(Decimal::new(25, 2), Decimal::new(1, 1));
+        let orders = manager(&gateway);
+        let outcome = orders
+            .close_position(
+                &gateway,
+                closing(Side::SELL, Decimal::new(25, 2)),
+                &options(Duration::from_secs(10)),
+            )
+            .await;
+        assert_eq!(
+            outcome,
+            CloseOutcome::Closed {
+                side: Side::SELL,
+                size: Decimal::new(25, 2),
+            }
+        );
+        assert_eq!(
+            *gateway.sizes.lock().unwrap(),
+            [Decimal::new(25, 2), Decimal::new(15, 2), Decimal::new(5, 2)]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn timeout_reports_the_remaining_size() {
+        // 空头 -1，每笔只成交 0.1，2 秒内提交两次
+        let gateway = PartialFills::new(Decimal::NEGATIVE_ONE, Decimal::new(1, 1));
+        let orders = manager(&gateway);
+        let outcome = orders
+            .close_position(
+                &gateway,
+                closing(Side::BUY, Decimal::ONE),
+                &options(Duration::from_secs(2)),
+            )
+            .await;
+        assert_eq!(
+            outcome,
+            CloseOutcome::TimedOut {
+                remaining: Decimal::new(8, 1),
+            }
+        );
+        assert_eq!(
+            *gateway.sizes.lock().unwrap(),
+            [Decimal::ONE, Decimal::new(9, 1)]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn markets_without_an_open_position_are_already_flat() {
+        let gateway = PartialFills::new(Decimal::new(25, 2), Decimal::ONE);
+        let orders = manager(&gateway);
+        let position: Position = serde_json::from_value(serde_json::json!({
+            "average_entry_price": "95000",
+            "average_entry_price_usd": "95000",
+            "cached_funding_index": "0",
+            "cost": "0",
+            "cost_usd": "0",
+            "id": "1",
+            "last_fill_id": "1",
+            "last_updated_at": 0,
+            "leverage": "",
+            "liquidation_price": "",
+            "market": MARKET,
+            "seq_no": 1,
+            "side": "LONG",
+            "size": "0.25",
+            "status": "OPEN",
+            "unrealized_funding_pnl": "0",
+            "unrealized_pnl": "0"
+        }))
+        .unwrap();
+        let outcomes = orders
+            .close_positions(
+                &gateway,
+                &[position],
+                &["ETH-USD-PERP".to_string(), MARKET.to_string()],
+                &options(Duration::from_secs(10)),
+            )
+            .await;
+        assert_eq!(
+            outcomes,
+            [
+                ("ETH-USD-PERP".to_string(), CloseOutcome::AlreadyFlat),
+                (
+                    MARKET.to_string(),
+                    CloseOutcome::Closed {
+                        side: Side::SELL,
+                        size: Decimal::new(25, 2),
+                    }
+                ),
+            ]
+        );
+        // 只为有持仓的市场下单
+        assert_eq!(*gateway.sizes.lock().unwrap(), [Decimal::new(25, 2)]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn dry_run_submits_once() {
+        let gateway = PartialFills::new(Decimal::ONE, Decimal::ZERO);
+        let orders = manager(&gateway);
+        let outcome = orders
+            .close_position(
+                &gateway,
+                closing(Side::SELL, Decimal::ONE),
+                &CloseOptions {
+                    dry_run: true,
+                    ..options(Duration::from_secs(10))
+                },
+            )
+            .await;
+        assert!(matches!(outcome, CloseOutcome::DryRun { .. }));
+        assert_eq!(gateway.sizes.lock().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,480 @@
+//! 成交台账：记录成交频道推送的每笔成交与资金费支付，按 ID 去重，并按市场汇总买卖量、成交额、手续费、
+//! 资金费与已实现盈亏
+//!
+//! 数值全部使用 `Decimal`。已实现盈亏按平均成本法计算：加仓时更新持仓均价，
+//! 减仓时按成交价与均价之差计入盈亏，再扣除手续费；资金费作为单独的现金流计入，
+//! 与交易盈亏分开报告。交易所撤销（trade bust）的成交不计入汇总。
+
+use paradex::ws::Message;
+use rust_decimal::Decimal;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use crate::fills::{write_csv, FillRecord};
+use crate::funding::FundingPayment;
+use crate::market_data::TradeBust;
+
+/// 台账中的一笔成交
+#[derive(Debug, Clone, PartialEq)]
+pub struct LedgerEntry {
+    pub fill: FillRecord,
+    /// 交易所已撤销（trade bust）该成交
+    pub reversed: bool,
+}
+
+/// 某个市场未被撤销的成交汇总
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MarketFills {
+    pub fills: usize,
+    pub bought: Decimal,
+    pub sold: Decimal,
+    /// 买入与卖出的成交额（价格 × 数量）
+    pub buy_notional: Decimal,
+    pub sell_notional: Decimal,
+    /// 手续费合计，负数为返佣
+    pub fees: Decimal,
+    /// 成交累计的净持仓，多头为正
+    pub position: Decimal,
+    /// 净持仓的平均成本，无持仓时为 0
+    pub avg_cost: Decimal,
+    /// 扣除手续费后的已实现盈亏（不含资金费）
+    pub realized_pnl: Decimal,
+    pub funding_payments: usize,
+    /// 资金费合计，正为收入、负为支出
+    pub funding: Decimal,
+}
+
+/// 已实现盈亏的组成
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RealizedPnl {
+    /// 交易盈亏（未扣手续费）
+    pub trading: Decimal,
+    /// 手续费合计，负数为返佣
+    pub fees: Decimal,
+    /// 资金费合计，正为收入、负为支出
+    pub funding: Decimal,
+}
+
+impl RealizedPnl {
+    /// 交易盈亏扣除手续费后加上资金费
+    pub fn net(&self) -> Decimal {
+        self.trading - self.fees + self.funding
+    }
+}
+
+impl MarketFills {
+    /// 已实现盈亏，交易盈亏已扣除手续费
+    pub fn pnl(&self) -> RealizedPnl {
+        RealizedPnl {
+            trading: self.realized_pnl + self.fees,
+            fees: self.fees,
+            funding: self.funding,
+        }
+    }
+
+    fn apply(&mut self, fill: &FillRecord) {
+        let buy = fill.side == "BUY";
+        let signed = if buy { fill.size } else { -fill.size };
+        self.fills += 1;
+        if buy {
+            self.bought += fill.size;
+            self.buy_notional += fill.price * fill.size;
+        } else {
+            self.sold += fill.size;
+            self.sell_notional += fill.price * fill.size;
+        }
+        self.fees += fill.fee;
+        self.realized_pnl -= fill.fee;
+
+        if self.position.is_zero() || self.position.is_sign_positive() == buy {
+            // 开仓或加仓：按数量加权更新均价
+            let held = self.position.abs();
+            self.avg_cost = (held * self.avg_cost + fill.size * fill.price) / (held + fill.size);
+            self.position += signed;
+            return;
+        }
+        // 减仓，超出持仓的部分按成交价反向开仓
+        let closed = fill.size.min(self.position.abs());
+        let direction = if self.position.is_sign_positive() {
+            Decimal::ONE
+        } else {
+            -Decimal::ONE
+        };
+        self.realized_pnl += closed * (fill.price - self.avg_cost) * direction;
+        self.position += signed;
+        if self.position.is_zero() {
+            self.avg_cost = Decimal::ZERO;
+        } else if fill.size > closed {
+            self.avg_cost = fill.price;
+        }
+    }
+}
+
+#[derive(Default)]
+struct State {
+    fills: HashMap<String, LedgerEntry>,
+    /// 先于对应成交到达的撤销
+    pending_busts: HashSet<String>,
+    /// 资金费支付，按支付 ID 去重
+    funding: HashMap<String, FundingPayment>,
+}
+
+/// 本会话的成交台账，克隆后共享同一份数据
+#[derive(Clone, Default)]
+pub struct FillLedger {
+    state: Arc<Mutex<State>>,
+}
+
+impl FillLedger {
+    /// 空账本
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 处理成交与资金费支付频道的消息，其他消息忽略
+    pub fn on_message(&self, message: &Message) {
+        match message {
+            Message::Fills(fill) => {
+                self.record(FillRecord::from(fill));
+            }
+            Message::FundingPayments(payment) => {
+                self.record_funding(FundingPayment::from(payment));
+            }
+            _ => {}
+        }
+    }
+
+    /// 记录一笔成交；重连后重复推送的成交（ID 已存在）被忽略，返回 `false`
+    pub fn record(&self, fill: FillRecord) -> bool {
+        let mut state = self.state.lock().unwrap();
+        if state.fills.contains_key(&fill.id) {
+            return false;
+        }
+        let reversed = state.pending_busts.remove(&fill.id);
+        state
+            .fills
+            .insert(fill.id.clone(), LedgerEntry { fill, reversed });
+        true
+    }
+
+    /// 记录一笔资金费支付；已记录的支付（重连后的重复推送或启动时补录过的）被忽略，返回 `false`
+    pub fn record_funding(&self, payment: FundingPayment) -> bool {
+        let mut state = self.state.lock().unwrap();
+        if state.funding.contains_key(&payment.id) {
+            return false;
+        }
+        state.funding.insert(payment.id.clone(), payment);
+        true
+    }
+
+    /// 已记录的资金费支付，按时间排列
+    pub fn funding_payments(&self) -> Vec<FundingPayment> {
+        let mut payments: Vec<_> = self
+            .state
+            .lock()
+            .unwrap()
+            .funding
+            .values()
+            .cloned()
+            .collect();
+        payments.sort_by(|a, b| (a.created_at, &a.id).cmp(&(b.created_at, &b.id)));
+        payments
+    }
+
+    /// 标记被撤销的成交；成交尚未到达时返回 `None`，到达后直接记为已冲回
+    pub fn bust(&self, bust: &TradeBust) -> Option<LedgerEntry> {
+        let mut state = self.state.lock().unwrap();
+        match state.fills.get_mut(&bust.busted_fill_id) {
+            Some(entry) => {
+                entry.reversed = true;
+                Some(entry.clone())
+            }
+            None => {
+                state.pending_busts.insert(bust.busted_fill_id.clone());
+                None
+            }
+        }
+    }
+
+    /// 成交 `id`
+    pub fn get(&self, id: &str) -> Option<LedgerEntry> {
+        self.state.lock().unwrap().fills.get(id).cloned()
+    }
+
+    /// 全部成交（含已冲回），按成交时间排列
+    pub fn entries(&self) -> Vec<LedgerEntry> {
+        let mut entries: Vec<_> = self.state.lock().unwrap().fills.values().cloned().collect();
+        entries.sort_by(|a, b| {
+            a.fill
+                .created_at
+                .cmp(&b.fill.created_at)
+                .then_with(|| a.fill.id.cmp(&b.fill.id))
+        });
+        entries
+    }
+
+    /// 已冲回的成交
+    pub fn reversed(&self) -> Vec<LedgerEntry> {
+        self.entries()
+            .into_iter()
+            .filter(|entry| entry.reversed)
+            .collect()
+    }
+
+    /// 记录的成交笔数（含已冲回）
+    pub fn len(&self) -> usize {
+        self.state.lock().unwrap().fills.len()
+    }
+
+    /// 是否没有任何成交
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 按市场汇总未被撤销的成交与资金费；成交按时间顺序计入，乱序到达不影响结果
+    pub fn summaries(&self) -> BTreeMap<String, MarketFills> {
+        let mut summaries: BTreeMap<String, MarketFills> = BTreeMap::new();
+        for entry in self.entries().iter().filter(|entry| !entry.reversed) {
+            summaries
+                .entry(entry.fill.market.clone())
+                .or_default()
+                .apply(&entry.fill);
+        }
+        for payment in self.funding_payments() {
+            let summary = summaries.entry(payment.market.clone()).or_default();
+            summary.funding_payments += 1;
+            summary.funding += payment.payment;
+        }
+        summaries
+    }
+
+    /// `market` 的成交汇总；没有成交时为 `None`
+    pub fn summary(&self, market: &str) -> Option<MarketFills> {
+        self.summaries().remove(market)
+    }
+
+    /// 订单 `order_id` 未被撤销的成交的成交均价；尚无成交时为 `None`
+    pub fn average_price(&self, order_id: &str) -> Option<Decimal> {
+        let (notional, size) = self
+            .state
+            .lock()
+            .unwrap()
+            .fills
+            .values()
+            .filter(|entry| !entry.reversed && entry.fill.order_id == order_id)
+            .fold((Decimal::ZERO, Decimal::ZERO), |(notional, size), entry| {
+                (
+                    notional + entry.fill.price * entry.fill.size,
+                    size + entry.fill.size,
+                )
+            });
+        (!size.is_zero()).then(|| notional / size)
+    }
+
+    /// `market` 的交易盈亏、手续费与资金费；没有成交与资金费时均为 0
+    pub fn realized_pnl(&self, market: &str) -> RealizedPnl {
+        self.summary(market)
+            .map(|summary| summary.pnl())
+            .unwrap_or_default()
+    }
+
+    /// 以成交导出的 CSV 格式写入全部未被撤销的成交
+    pub fn to_csv(&self, path: &Path) -> io::Result<()> {
+        let fills: Vec<FillRecord> = self
+            .entries()
+            .into_iter()
+            .filter(|entry| !entry.reversed)
+            .map(|entry| entry.fill)
+            .collect();
+        write_csv(&fills, BufWriter::new(File::create(path)?))
+    }
+
+    /// 会话报告：每个市场一行汇总，含交易盈亏、资金费、手续费与净盈亏
+    pub fn report(&self) -> String {
+        let summaries = self.summaries();
+        if summaries.is_empty() {
+            return "No fills this session".to_string();
+        }
+        summaries
+            .iter()
+            .map(|(market, s)| {
+                let pnl = s.pnl();
+                format!(
+                    "{}: {} fills, bought {} ({}), sold {} ({}), position {}, trading PnL {}, funding {} ({} payments), fees {}, net PnL {}",
+                    market,
+                    s.fills,
+                    s.bought.normalize(),
+                    s.buy_notional.normalize(),
+                    s.sold.normalize(),
+                    s.sell_notional.normalize(),
+                    s.position.normalize(),
+                    pnl.trading.round_dp(2),
+                    pnl.funding.round_dp(2),
+                    s.funding_payments,
+                    pnl.fees.normalize(),
+                    pnl.net().round_dp(2)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fill(
+        id: &str,
+        created_at: u64,
+        side: &str,
+        price: &str,
+        size: &str,
+        fee: &str,
+    ) -> FillRecord {
+        FillRecord {
+            id: id.to_string(),
+            created_at,
+            market: "BTC-USD-PERP".to_string(),
+            side: side.to_string(),
+            price: price.parse().unwrap(),
+            size: size.parse().unwrap(),
+            fee: fee.parse().unwrap(),
+            fee_currency: "USDC".to_string(),
+            liquidity: "TAKER".to_string(),
+            order_id: format!("order-{id}"),
+            client_id: String::new(),
+        }
+    }
+
+    fn bust(id: &str) -> TradeBust {
+        TradeBust {
+            account: "0x1".to_string(),
+            busted_fill_id: id.to_string(),
+            created_at: 1735689600500,
+        }
+    }
+
+    fn cents(value: &str) -> Decimal {
+        value.parse().unwrap()
+    }
+
+    #[test]
+    fn realized_pnl_uses_average_cost_net_of_fees() {
+        let ledger = FillLedger::new();
+        // 买 0.1 @ 95000，再买 0.3 @ 96000：均价 95750
+        ledger.record(fill("1", 1, "BUY", "95000", "0.1", "1.90"));
+        ledger.record(fill("2", 2, "BUY", "96000", "0.3", "5.76"));
+        // 卖 0.25 @ 97000：(97000 - 95750) × 0.25 = 312.50
+        ledger.record(fill("3", 3, "SELL", "97000", "0.25", "4.85"));
+        // 卖 0.25 @ 94000：平 0.15 亏 262.50，反手空 0.1 @ 94000
+        ledger.record(fill("4", 4, "SELL", "94000", "0.25", "-0.47"));
+        // 重连后重复推送
+        assert!(!ledger.record(fill("3", 3, "SELL", "97000", "0.25", "4.85")));
+
+        let summary = ledger.summary("BTC-USD-PERP").unwrap();
+        assert_eq!(summary.fills, 4);
+        assert_eq!(summary.bought, cents("0.4"));
+        assert_eq!(summary.sold, cents("0.5"));
+        assert_eq!(summary.buy_notional, cents("38300"));
+        assert_eq!(summary.sell_notional, cents("47750"));
+        assert_eq!(summary.fees, cents("12.04"));
+        assert_eq!(summary.position, cents("-0.1"));
+        assert_eq!(summary.avg_cost, cents("94000"));
+        // 312.50 - 262.50 - 12.04
+        assert_eq!(ledger.realized_pnl("BTC-USD-PERP").net(), cents("37.96"));
+        assert_eq!(ledger.realized_pnl("ETH-USD-PERP").net(), Decimal::ZERO);
+
+        // 买回 0.1 @ 93500：空头盈利 50
+        ledger.record(fill("5", 5, "BUY", "93500", "0.1", "1.87"));
+        let summary = ledger.summary("BTC-USD-PERP").unwrap();
+        assert!(summary.position.is_zero());
+        assert!(summary.avg_cost.is_zero());
+        assert_eq!(summary.realized_pnl, cents("86.09"));
+        assert!(
+            ledger.report().contains("net PnL 86.09"),
+            "{}",
+            ledger.report()
+        );
+    }
+
+    #[test]
+    fn busted_fills_are_excluded_from_aggregates() {
+        let ledger = FillLedger::new();
+        let shared = ledger.clone();
+        // 乱序到达：按成交时间计入
+        ledger.record(fill("2", 2, "SELL", "96000", "0.1", "0"));
+        ledger.record(fill("1", 1, "BUY", "95000", "0.1", "0"));
+        assert_eq!(shared.realized_pnl("BTC-USD-PERP").net(), cents("100"));
+
+        let reversed = ledger.bust(&bust("2")).unwrap();
+        assert!(reversed.reversed);
+        assert_eq!(
+            shared.summary("BTC-USD-PERP").unwrap().position,
+            cents("0.1")
+        );
+        assert_eq!(shared.realized_pnl("BTC-USD-PERP").net(), Decimal::ZERO);
+
+        // 撤销先于成交到达
+        assert!(ledger.bust(&bust("3")).is_none());
+        ledger.record(fill("3", 3, "BUY", "97000", "0.1", "0"));
+        assert!(shared.get("3").unwrap().reversed);
+        assert_eq!(shared.reversed().len(), 2);
+        assert_eq!(shared.len(), 3);
+
+        let path = std::env::temp_dir().join(format!("ledger-{}.csv", std::process::id()));
+        ledger.to_csv(&path).unwrap();
+        let csv = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(csv.lines().count(), 2);
+        assert!(csv.lines().nth(1).unwrap().contains(",BUY,95000,0.1,"));
+    }
+
+    fn payment(id: &str, market: &str, amount: &str) -> FundingPayment {
+        FundingPayment {
+            id: id.to_string(),
+            market: market.to_string(),
+            created_at: 1735689600000,
+            payment: amount.parse().unwrap(),
+            index: Decimal::ZERO,
+            fill_id: String::new(),
+        }
+    }
+
+    #[test]
+    fn funding_is_reported_separately_from_trading_pnl() {
+        let ledger = FillLedger::new();
+        ledger.record(fill("1", 1, "BUY", "95000", "0.1", "1.90"));
+        ledger.record(fill("2", 2, "SELL", "96000", "0.1", "1.92"));
+        // 启动时补录的支付与随后频道推送的同一支付只计一次
+        assert!(ledger.record_funding(payment("p1", "BTC-USD-PERP", "-2.5")));
+        assert!(!ledger.record_funding(payment("p1", "BTC-USD-PERP", "-2.5")));
+        ledger.record_funding(payment("p2", "BTC-USD-PERP", "0.75"));
+        // 只有资金费的市场同样出现在汇总中
+        ledger.record_funding(payment("p3", "ETH-USD-PERP", "1.2"));
+
+        let pnl = ledger.realized_pnl("BTC-USD-PERP");
+        assert_eq!(
+            pnl,
+            RealizedPnl {
+                trading: cents("100"),
+                fees: cents("3.82"),
+                funding: cents("-1.75"),
+            }
+        );
+        assert_eq!(pnl.net(), cents("94.43"));
+        assert_eq!(ledger.realized_pnl("ETH-USD-PERP").net(), cents("1.2"));
+        let summary = ledger.summary("ETH-USD-PERP").unwrap();
+        assert_eq!((summary.fills, summary.funding_payments), (0, 1));
+        assert!(
+            ledger.report().contains(
+                "trading PnL 100.00, funding -1.75 (2 payments), fees 3.82, net PnL 94.43"
+            ),
+            "{}",
+            ledger.report()
+        );
+    }
+}
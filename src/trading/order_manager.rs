@@ -0,0 +1,1522 @@
+//! 下单入口：统一完成订单构建、发送、状态跟踪与撤单
+//!
+//! 策略与子命令都通过 [`OrderManager`] 下单，而不是直接调用交易所客户端；风控、计量与
+//! dry-run 都以 [`OrderGateway`] 包装的形式挂在它持有的下单出口上。订单状态来自订单频道
+//! 推送到共享的 [`OrderTracker`]，下单与改单的 REST 响应也会写入其中。指定市场元数据时，
+//! 不符合价格精度、数量步长与下单上下限的订单在发送前被拒绝。
+//!
+//! POST_ONLY 单因行情变化穿越盘口而被拒绝时，按 BBO 缓存中的最新报价重新定价后重试：
+//! 买单挂在最优卖价下方一个价格精度、卖单挂在最优买价上方一个价格精度，每次重试使用新的 client_id。
+//!
+//! 止损与止盈触发单在发送前按行情摘要缓存中的标记价格检查触发价的方向，避免提交后立即触发。
+//! 指定持仓缓存时，只减仓订单（包括止盈与止损单）在发送前检查方向与持仓数量，
+//! 不会增加或反转持仓。
+//!
+//! 吃单通过 [`OrderManager::submit_market`]：先按本地订单簿（未维护时按 BBO）估算成交均价，
+//! 相对中间价的滑点超过上限时不下单。
+
+use log::{info, warn};
+use paradex::{
+    error::Error,
+    structs::{ModifyOrderRequest, OrderInstruction, OrderType, Side},
+};
+use rust_decimal::Decimal;
+use std::time::Duration;
+use thiserror::Error;
+
+use super::{FillLedger, OrderState, OrderTracker, TrackedOrder};
+use crate::gateway::{GatewayError, OrderGateway};
+use crate::market_data::{BboCache, ExecEstimate, LocalOrderBook, MarketSummaryCache, OrderBooks};
+use crate::markets::{MarketRuleError, SharedMarkets};
+use crate::orderbook::{Level, OrderBookSnapshot};
+use crate::orders::{
+    capped_price, check_reduce_only, validate_trigger, AggressiveMode, OrderError, OrderFactory,
+    OrderSpec, TriggerKind, POST_ONLY_WOULD_CROSS,
+};
+use crate::positions::PositionCache;
+use crate::risk::{check_slippage, RiskViolation, MAX_MARK_AGE};
+
+/// 默认 POST_ONLY 单被拒绝后的重试次数
+pub const DEFAULT_POST_ONLY_RETRIES: u32 = 2;
+/// POST_ONLY 重试前的等待时间，第 n 次重试等待 n 倍
+const POST_ONLY_RETRY_BACKOFF: Duration = Duration::from_millis(50);
+
+/// 交易所（或模拟撮合）因 POST_ONLY 单会立即成交而拒绝下单
+fn would_cross(error: &Error) -> bool {
+    matches!(
+        error,
+        Error::ParadexError { error: Some(code), .. } if code == POST_ONLY_WOULD_CROSS
+    )
+}
+
+/// 订单管理器下单或撤单失败的原因
+#[derive(Debug, Error)]
+pub enum OrderManagerError {
+    #[error(transparent)]
+    Order(#[from] OrderError),
+    #[error(transparent)]
+    Market(#[from] MarketRuleError),
+    #[error(transparent)]
+    Gateway(#[from] GatewayError),
+    #[error(transparent)]
+    Risk(#[from] RiskViolation),
+    #[error("Order {0} is not tracked")]
+    UnknownOrder(String),
+}
+
+/// 按类型提交的触发单，见 [`OrderManager::submit_trigger_order`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TriggerOrder {
+    pub kind: TriggerKind,
+    pub side: Side,
+    pub size: Decimal,
+    pub trigger: Decimal,
+    /// 触发后挂限价单的价格；`None` 时以市价成交
+    pub limit_price: Option<Decimal>,
+}
+
+/// 待提交的订单
+#[derive(Debug, Clone, PartialEq)]
+pub struct NewOrder {
+    pub market: String,
+    pub spec: OrderSpec,
+}
+
+impl NewOrder {
+    /// 在 `market` 按 `spec` 下单
+    pub fn new(market: impl Into<String>, spec: OrderSpec) -> Self {
+        Self {
+            market: market.into(),
+            spec,
+        }
+    }
+}
+
+/// 批量撤单的范围
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CancelScope {
+    /// 本会话提交且尚未结束的订单（按 client_id 逐个撤销）
+    Session,
+    /// 指定市场的全部挂单
+    Market(String),
+    /// 账户的全部挂单
+    All,
+}
+
+/// 已提交的订单；状态随订单频道推送更新
+#[derive(Clone)]
+pub struct ManagedOrder {
+    id: String,
+    client_id: String,
+    market: String,
+    tracker: OrderTracker,
+}
+
+impl ManagedOrder {
+    /// 交易所订单 ID
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// 订单的 client_id
+    pub fn client_id(&self) -> &str {
+        &self.client_id
+    }
+
+    /// 订单所在市场
+    pub fn market(&self) -> &str {
+        &self.market
+    }
+
+    /// 当前状态
+    pub fn state(&self) -> Option<TrackedOrder> {
+        self.tracker.get(&self.id)
+    }
+
+    /// 等待交易所确认订单（离开 `New`，包括直接成交或被拒绝）；超时返回 `None`
+    pub async fn await_open(&self, timeout: Duration) -> Option<TrackedOrder> {
+        self.tracker
+            .await_state(&self.id, timeout, |state| state != OrderState::New)
+            .await
+    }
+
+    /// 等待订单成交、撤销或被拒绝；超时返回 `None`
+    pub async fn await_filled_or_cancelled(&self, timeout: Duration) -> Option<TrackedOrder> {
+        self.tracker.await_terminal(&self.id, timeout).await
+    }
+}
+
+/// [`OrderManager::submit_market`] 提交的吃单，附下单前的成交估算以便对照实际滑点
+#[derive(Clone)]
+pub struct MarketOrder {
+    pub order: ManagedOrder,
+    pub side: Side,
+    /// 估算时的中间价
+    pub mid: Decimal,
+    pub estimate: ExecEstimate,
+    /// [`AggressiveMode::Limit`] 时的吃单限价
+    pub limit_price: Option<Decimal>,
+}
+
+impl MarketOrder {
+    /// 估算成交均价相对中间价的滑点（基点）
+    pub fn estimated_slippage_bps(&self) -> Decimal {
+        self.estimate.slippage_bps(self.side, self.mid)
+    }
+
+    /// `ledger` 中该订单的成交均价；尚无成交时为 `None`
+    pub fn realized_price(&self, ledger: &FillLedger) -> Option<Decimal> {
+        ledger.average_price(self.order.id())
+    }
+
+    /// 实际成交均价相对中间价的滑点（基点）
+    pub fn realized_slippage_bps(&self, ledger: &FillLedger) -> Option<Decimal> {
+        let estimate = ExecEstimate {
+            avg_price: self.realized_price(ledger)?,
+            ..self.estimate
+        };
+        Some(estimate.slippage_bps(self.side, self.mid))
+    }
+
+    /// 估算与实际成交均价的对照，用于记录滑点
+    pub fn report(&self, ledger: &FillLedger) -> String {
+        let realized = match (
+            self.realized_price(ledger),
+            self.realized_slippage_bps(ledger),
+        ) {
+            (Some(price), Some(bps)) => format!("{} ({} bps)", price.round_dp(8), bps.round_dp(2)),
+            _ => "no fills".to_string(),
+        };
+        format!(
+            "{:?} {} on {} vs mid {}: estimated {} ({} bps), realized {}",
+            self.side,
+            self.order.id(),
+            self.order.market(),
+            self.mid,
+            self.estimate.avg_price.round_dp(8),
+            self.estimated_slippage_bps().round_dp(2),
+            realized
+        )
+    }
+}
+
+/// 订单管理：持有下单出口、订单状态表与 client_id 生成（经 [`OrderFactory`]）
+pub struct OrderManager<'a> {
+    gateway: &'a dyn OrderGateway,
+    tracker: OrderTracker,
+    factory: OrderFactory,
+    markets: Option<SharedMarkets>,
+    quotes: Option<BboCache>,
+    summaries: Option<MarketSummaryCache>,
+    positions: Option<PositionCache>,
+    clamp_reduce_only: bool,
+    books: Option<OrderBooks>,
+    aggressive_mode: AggressiveMode,
+    post_only_retries: u32,
+}
+
+impl<'a> OrderManager<'a> {
+    /// `tracker` 应与消费订单频道的状态表共享
+    pub fn new(
+        gateway: &'a dyn OrderGateway,
+        tracker: OrderTracker,
+        factory: OrderFactory,
+    ) -> Self {
+        Self {
+            gateway,
+            tracker,
+            factory,
+            markets: None,
+            quotes: None,
+            summaries: None,
+            positions: None,
+            clamp_reduce_only: false,
+            books: None,
+            aggressive_mode: AggressiveMode::default(),
+            post_only_retries: DEFAULT_POST_ONLY_RETRIES,
+        }
+    }
+
+    /// POST_ONLY 单被拒绝后按 `quotes` 中的最新报价重新定价（还需要 [`Self::with_markets`] 提供价格精度）
+    pub fn with_quotes(mut self, quotes: BboCache) -> Self {
+        self.quotes = Some(quotes);
+        self
+    }
+
+    /// POST_ONLY 单穿越盘口被拒绝后最多重试 `retries` 次；0 表示不重试
+    pub fn with_post_only_retries(mut self, retries: u32) -> Self {
+        self.post_only_retries = retries;
+        self
+    }
+
+    /// 下单与改单前按 `markets` 中的市场规则检查价格与数量
+    pub fn with_markets(mut self, markets: SharedMarkets) -> Self {
+        self.markets = Some(markets);
+        self
+    }
+
+    /// 触发单按 `summaries` 中的标记价格（不超过 [`MAX_MARK_AGE`]）检查触发价的方向
+    pub fn with_summaries(mut self, summaries: MarketSummaryCache) -> Self {
+        self.summaries = Some(summaries);
+        self
+    }
+
+    /// 只减仓订单按 `positions` 中的持仓检查：没有持仓、方向与持仓相同或数量超过持仓时拒绝
+    pub fn with_positions(mut self, positions: PositionCache) -> Self {
+        self.positions = Some(positions);
+        self
+    }
+
+    /// 为 `true` 时只减仓订单的数量超过持仓后缩减到持仓数量，而不是拒绝
+    pub fn with_reduce_only_clamp(mut self, clamp: bool) -> Self {
+        self.clamp_reduce_only = clamp;
+        self
+    }
+
+    /// 吃单前优先按 `books` 中已同步的订单簿估算成交价，未同步时按 [`Self::with_quotes`] 的 BBO 估算
+    pub fn with_order_books(mut self, books: OrderBooks) -> Self {
+        self.books = Some(books);
+        self
+    }
+
+    /// [`Self::submit_market`] 以市价单还是滑点上限价格的 IOC 限价单吃单（默认限价单）
+    pub fn with_aggressive_mode(mut self, mode: AggressiveMode) -> Self {
+        self.aggressive_mode = mode;
+        self
+    }
+
+    /// 已提交订单的状态跟踪
+    pub fn tracker(&self) -> &OrderTracker {
+        &self.tracker
+    }
+
+    /// 构建并发送订单；未指定 client_id 时自动生成带策略标识的 id，发送失败时释放其 client_id
+    ///
+    /// POST_ONLY 单穿越盘口被拒绝时重新定价重试，用尽重试次数（或无法重新定价）后返回
+    /// [`OrderError::PostOnlyWouldCross`]。调用方指定的 client_id 在重试时加上 `-r{n}` 后缀。
+    /// `spec.reduce_only` 的订单带 `REDUCE_ONLY`，并按 [`Self::with_positions`] 的持仓检查。
+    pub async fn submit(&self, order: NewOrder) -> Result<ManagedOrder, OrderManagerError> {
+        self.release_finished();
+        let NewOrder { market, mut spec } = order;
+        if let Some(positions) = self.positions.as_ref().filter(|_| spec.is_reduce_only()) {
+            let size = check_reduce_only(
+                &market,
+                spec.side,
+                spec.size,
+                positions.size(&market),
+                self.clamp_reduce_only,
+            )?;
+            if size != spec.size {
+                warn!(
+                    "Reduce-only {:?} order on {} clamped from {} to the open position {}",
+                    spec.side, market, spec.size, size
+                );
+                spec.size = size;
+            }
+        }
+        let client_id = spec.client_id.clone();
+        let mut attempts = 0;
+        loop {
+            attempts += 1;
+            let error = match self.send(&market, &spec).await {
+                Err(OrderManagerError::Gateway(GatewayError::Exchange(e)))
+                    if spec.instruction == OrderInstruction::POST_ONLY && would_cross(&e) =>
+                {
+                    e
+                }
+                result => return result,
+            };
+            let retry = attempts <= self.post_only_retries;
+            if retry {
+                tokio::time::sleep(POST_ONLY_RETRY_BACKOFF * attempts).await;
+            }
+            let Some(price) = retry.then(|| self.reprice(&market, spec.side)).flatten() else {
+                warn!("POST_ONLY order on {} would cross: {}", market, error);
+                return Err(OrderError::PostOnlyWouldCross { market, attempts }.into());
+            };
+            warn!(
+                "POST_ONLY {:?} order on {} at {:?} would cross, retrying at {}",
+                spec.side, market, spec.price, price
+            );
+            spec.price = Some(price);
+            spec.client_id = client_id.as_ref().map(|id| format!("{id}-r{attempts}"));
+        }
+    }
+
+    /// 重新定价：买单为最优卖价下方一个价格精度、卖单为最优买价上方一个价格精度；
+    /// 没有报价或价格精度时返回 `None`
+    fn reprice(&self, market: &str, side: Side) -> Option<Decimal> {
+        let quote = self.quotes.as_ref()?.get(market)?;
+        let tick = self
+            .markets
+            .as_ref()?
+            .with(|registry| registry.price_tick(market));
+        if tick.is_zero() {
+            return None;
+        }
+        let price = match side {
+            Side::BUY => quote.ask - tick,
+            Side::SELL => quote.bid + tick,
+        };
+        (price > Decimal::ZERO && !quote.ask.is_zero() && !quote.bid.is_zero()).then_some(price)
+    }
+
+    /// 吃单：按订单簿估算的成交均价相对中间价的滑点超过 `max_slippage_bps` 或深度不足时不下单，
+    /// 否则按 [`Self::with_aggressive_mode`] 提交市价单或滑点上限价格的 IOC 限价单
+    pub async fn submit_market(
+        &self,
+        symbol: &str,
+        side: Side,
+        size: Decimal,
+        max_slippage_bps: Decimal,
+    ) -> Result<MarketOrder, OrderManagerError> {
+        let book = self.book(symbol)?;
+        let estimate = check_slippage(max_slippage_bps, symbol, side, size, &book)
+            .inspect_err(|violation| warn!("Not taking liquidity on {}: {}", symbol, violation))?;
+        let mid = book
+            .mid()
+            .ok_or_else(|| RiskViolation::MissingBook(symbol.to_string()))?;
+        let (order_type, limit_price) = match self.aggressive_mode {
+            AggressiveMode::Market => (OrderType::MARKET, None),
+            AggressiveMode::Limit => {
+                let tick = self.markets.as_ref().map_or(Decimal::ZERO, |markets| {
+                    markets.with(|registry| registry.price_tick(symbol))
+                });
+                let price = capped_price(side, mid, max_slippage_bps, tick);
+                (OrderType::LIMIT, Some(price))
+            }
+        };
+        let spec = OrderSpec {
+            side,
+            order_type,
+            size,
+            price: limit_price,
+            instruction: OrderInstruction::IOC,
+            client_id: None,
+            reduce_only: false,
+            trigger_price: None,
+        };
+        let order = self.submit(NewOrder::new(symbol, spec)).await?;
+        let order = MarketOrder {
+            order,
+            side,
+            mid,
+            estimate,
+            limit_price,
+        };
+        info!(
+            "Taking {:?} {} on {} at {:?}: estimated avg {} ({} bps, {} levels)",
+            side,
+            size,
+            symbol,
+            limit_price,
+            estimate.avg_price.round_dp(8),
+            order.estimated_slippage_bps().round_dp(2),
+            estimate.levels_consumed
+        );
+        Ok(order)
+    }
+
+    /// 估算成交价的订单簿：已同步的本地订单簿，否则由最新 BBO 构成的单档订单簿
+    fn book(&self, symbol: &str) -> Result<LocalOrderBook, RiskViolation> {
+        let local = self
+            .books
+            .as_ref()
+            .and_then(|books| books.get(symbol))
+            .map(|book| book.read().unwrap().clone())
+            .filter(|book| book.is_synced() && !book.is_suspect());
+        if let Some(book) = local {
+            return Ok(book);
+        }
+        let quote = self
+            .quotes
+            .as_ref()
+            .and_then(|quotes| quotes.get(symbol))
+            .ok_or_else(|| RiskViolation::MissingBook(symbol.to_string()))?;
+        let mut book = LocalOrderBook::new(symbol);
+        book.seed(&OrderBookSnapshot {
+            market: symbol.to_string(),
+            seq_no: None,
+            last_updated_at: None,
+            bids: vec![Level(quote.bid, quote.bid_size)],
+            asks: vec![Level(quote.ask, quote.ask_size)],
+        });
+        Ok(book)
+    }
+
+    /// 止损市价单：买单在标记价格上穿 `trigger` 时、卖单在下穿时以市价成交
+    pub async fn submit_stop_market(
+        &self,
+        symbol: &str,
+        side: Side,
+        size: Decimal,
+        trigger: Decimal,
+    ) -> Result<ManagedOrder, OrderManagerError> {
+        self.submit_trigger(symbol, OrderType::STOP_MARKET, side, size, trigger, None)
+            .await
+    }
+
+    /// 止损限价单：触发后以 `limit_price` 挂限价单
+    pub async fn submit_stop_limit(
+        &self,
+        symbol: &str,
+        side: Side,
+        size: Decimal,
+        trigger: Decimal,
+        limit_price: Decimal,
+    ) -> Result<ManagedOrder, OrderManagerError> {
+        self.submit_trigger(
+            symbol,
+            OrderType::STOP_LIMIT,
+            side,
+            size,
+            trigger,
+            Some(limit_price),
+        )
+        .await
+    }
+
+    /// 只减仓的止盈单：买单（平空）在标记价格下穿 `trigger` 时、卖单（平多）在上穿时触发；
+    /// 指定 `limit_price` 时触发后挂限价单，否则以市价成交
+    pub async fn submit_take_profit(
+        &self,
+        symbol: &str,
+        side: Side,
+        size: Decimal,
+        trigger: Decimal,
+        limit_price: Option<Decimal>,
+    ) -> Result<ManagedOrder, OrderManagerError> {
+        let order_type = match limit_price {
+            Some(_) => OrderType::TAKE_PROFIT_LIMIT,
+            None => OrderType::TAKE_PROFIT_MARKET,
+        };
+        self.submit_trigger(symbol, order_type, side, size, trigger, limit_price)
+            .await
+    }
+
+    /// 只减仓的止损单：买单（平空）在标记价格上穿 `trigger` 时、卖单（平多）在下穿时触发；
+    /// 指定 `limit_price` 时触发后挂限价单，否则以市价成交
+    pub async fn submit_stop_loss(
+        &self,
+        symbol: &str,
+        side: Side,
+        size: Decimal,
+        trigger: Decimal,
+        limit_price: Option<Decimal>,
+    ) -> Result<ManagedOrder, OrderManagerError> {
+        let order_type = match limit_price {
+            Some(_) => OrderType::STOP_LOSS_LIMIT,
+            None => OrderType::STOP_LOSS_MARKET,
+        };
+        self.submit_trigger(symbol, order_type, side, size, trigger, limit_price)
+            .await
+    }
+
+    /// 按 `order.kind` 提交对应的止损、止盈或只减仓止损单
+    pub async fn submit_trigger_order(
+        &self,
+        symbol: &str,
+        order: &TriggerOrder,
+    ) -> Result<ManagedOrder, OrderManagerError> {
+        let TriggerOrder {
+            kind,
+            side,
+            size,
+            trigger,
+            limit_price,
+        } = *order;
+        match (kind, limit_price) {
+            (TriggerKind::Stop, None) => self.submit_stop_market(symbol, side, size, trigger).await,
+            (TriggerKind::Stop, Some(price)) => {
+                self.submit_stop_limit(symbol, side, size, trigger, price)
+                    .await
+            }
+            (TriggerKind::TakeProfit, price) => {
+                self.submit_take_profit(symbol, side, size, trigger, price)
+                    .await
+            }
+            (TriggerKind::StopLoss, price) => {
+                self.submit_stop_loss(symbol, side, size, trigger, price)
+                    .await
+            }
+        }
+    }
+
+    /// 按标记价格检查触发价后提交 GTC 触发单
+    async fn submit_trigger(
+        &self,
+        symbol: &str,
+        order_type: OrderType,
+        side: Side,
+        size: Decimal,
+        trigger: Decimal,
+        price: Option<Decimal>,
+    ) -> Result<ManagedOrder, OrderManagerError> {
+        let mark = self
+            .summaries
+            .as_ref()
+            .and_then(|summaries| summaries.mark_price(symbol, MAX_MARK_AGE))
+            .ok_or_else(|| OrderError::NoMarkPrice(symbol.to_string()))?;
+        validate_trigger(order_type, side, trigger, mark)?;
+        let spec = OrderSpec {
+            side,
+            order_type,
+            size,
+            price,
+            instruction: OrderInstruction::GTC,
+            client_id: None,
+            reduce_only: false,
+            trigger_price: Some(trigger),
+        };
+        info!(
+            "Submitting {:?} {:?} {} on {} triggered at {} (mark {})",
+            side, order_type, size, symbol, trigger, mark
+        );
+        self.submit(NewOrder::new(symbol, spec)).await
+    }
+
+    /// 检查、构建并发送一次订单
+    async fn send(
+        &self,
+        market: &str,
+        spec: &OrderSpec,
+    ) -> Result<ManagedOrder, OrderManagerError> {
+        self.check(market, spec.price, spec.size)?;
+        if let Some(trigger) = spec.trigger_price {
+            self.check(market, Some(trigger), spec.size)?;
+        }
+        let request = self.factory.order(market, spec)?;
+        let client_id = request.client_id.clone().unwrap_or_default();
+
+        info!("Sending order {request:?}");
+        let update = match self.gateway.create_order(request).await {
+            Ok(update) => update,
+            Err(e) => {
+                self.factory.release(&client_id);
+                return Err(e.into());
+            }
+        };
+        info!("Order result {update:?}");
+        // 推送可能先于响应到达，过期的响应由状态表忽略
+        self.tracker.apply(&update);
+        Ok(ManagedOrder {
+            id: update.id,
+            client_id,
+            market: market.to_string(),
+            tracker: self.tracker.clone(),
+        })
+    }
+
+    /// 修改挂单的价格与数量（限价单）；`id` 为交易所订单 ID 或 client_id
+    pub async fn amend(
+        &self,
+        id: &str,
+        price: Decimal,
+        size: Decimal,
+    ) -> Result<ManagedOrder, OrderManagerError> {
+        let order = self
+            .tracker
+            .get(id)
+            .ok_or_else(|| OrderManagerError::UnknownOrder(id.to_string()))?;
+        self.check(&order.market, Some(price), size)?;
+        let request = ModifyOrderRequest {
+            id: order.id.clone(),
+            market: order.market.clone(),
+            price: Some(price),
+            side: order.side,
+            size,
+            order_type: OrderType::LIMIT,
+        };
+
+        info!("Sending modify order {request:?}");
+        let update = self.gateway.modify_order(request).await?;
+        info!("Modify order result {update:?}");
+        self.tracker.apply(&update);
+        Ok(ManagedOrder {
+            id: Some(update.id)
+                .filter(|id| !id.is_empty())
+                .unwrap_or(order.id),
+            client_id: order.client_id,
+            market: order.market,
+            tracker: self.tracker.clone(),
+        })
+    }
+
+    /// 撤销单个订单；`id` 为交易所订单 ID 或本会话的 client_id
+    pub async fn cancel(&self, id: &str) -> Result<(), OrderManagerError> {
+        match self.tracker.get(id) {
+            Some(order) => self.gateway.cancel_order(order.id).await?,
+            None if self
+                .factory
+                .outstanding()
+                .iter()
+                .any(|client_id| client_id == id) =>
+            {
+                self.gateway
+                    .cancel_order_by_client_id(id.to_string())
+                    .await?
+            }
+            None => self.gateway.cancel_order(id.to_string()).await?,
+        }
+        Ok(())
+    }
+
+    /// 撤销 `scope` 内的订单；[`CancelScope::Session`] 逐个撤销，全部尝试后返回第一个错误
+    pub async fn cancel_all(&self, scope: CancelScope) -> Result<(), OrderManagerError> {
+        self.release_finished();
+        let outstanding = self.factory.outstanding();
+        match &scope {
+            CancelScope::Session => {
+                let mut first_error = None;
+                for client_id in outstanding {
+                    match self
+                        .gateway
+                        .cancel_order_by_client_id(client_id.clone())
+                        .await
+                    {
+                        Ok(()) => info!("Cancelled session order {}", client_id),
+                        Err(e) => {
+                            warn!("Failed to cancel session order {}: {}", client_id, e);
+                            first_error.get_or_insert(e);
+                        }
+                    }
+                    self.factory.release(&client_id);
+                }
+                return first_error.map_or(Ok(()), |e| Err(e.into()));
+            }
+            CancelScope::Market(market) => {
+                let response = self
+                    .gateway
+                    .cancel_all_orders_for_market(market.clone())
+                    .await?;
+                info!("Cancel by market orders result {response:?}");
+            }
+            CancelScope::All => {
+                let cancelled = self.gateway.cancel_all_orders().await?;
+                info!("Cancel all orders result {cancelled:?}");
+            }
+        }
+        // 撤销范围内的订单不再需要在退出时逐个撤销
+        for client_id in outstanding {
+            let in_scope = match (&scope, self.tracker.get(&client_id)) {
+                (CancelScope::Market(market), Some(order)) => order.market == *market,
+                (CancelScope::Market(_), None) => false,
+                _ => true,
+            };
+            if in_scope {
+                self.factory.release(&client_id);
+            }
+        }
+        Ok(())
+    }
+
+    /// 尚未进入终态的订单（包括其他会话或客户端提交、经订单频道推送的订单）
+    pub fn open_orders(&self) -> Vec<TrackedOrder> {
+        self.tracker.open_orders()
+    }
+
+    fn check(
+        &self,
+        market: &str,
+        price: Option<Decimal>,
+        size: Decimal,
+    ) -> Result<(), MarketRuleError> {
+        self.markets.as_ref().map_or(Ok(()), |markets| {
+            markets.with(|registry| registry.validate_order(market, price, size))
+        })
+    }
+
+    /// 市场的价格精度；未指定市场元数据时为 0
+    pub(super) fn price_tick(&self, market: &str) -> Decimal {
+        self.markets.as_ref().map_or(Decimal::ZERO, |markets| {
+            markets.with(|registry| registry.price_tick(market))
+        })
+    }
+
+    /// 检查触发价所用的参考价格：行情摘要中的标记价格，其次为 BBO 中间价
+    pub(super) fn reference_mark(&self, market: &str) -> Option<Decimal> {
+        self.summaries
+            .as_ref()
+            .and_then(|summaries| summaries.mark_price(market, MAX_MARK_AGE))
+            .or_else(|| self.quotes.as_ref()?.mid(market))
+    }
+
+    /// 持仓缓存中的持仓（空头为负）；未指定持仓缓存时为 `None`
+    pub(super) fn position(&self, market: &str) -> Option<Decimal> {
+        self.positions
+            .as_ref()
+            .map(|positions| positions.size(market))
+    }
+
+    /// 释放已进入终态的订单的 client_id
+    fn release_finished(&self) {
+        for client_id in self.factory.outstanding() {
+            if self
+                .tracker
+                .get(&client_id)
+                .is_some_and(|order| order.state.is_terminal())
+            {
+                self.factory.release(&client_id);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client_id::ClientIdGenerator;
+    use crate::fills::FillRecord;
+    use crate::gateway::DryRun;
+    use crate::market_data::BboEvent;
+    use crate::markets::{MarketInfo, MarketRegistry, MarketStats};
+    use crate::onboarding::ParadexConfig;
+    use async_trait::async_trait;
+    use chrono::Utc;
+    use paradex::structs::{
+        CancelByMarketResponse, OrderFlags, OrderInstruction, OrderRequest, OrderStatus,
+        OrderUpdate, Position, Side,
+    };
+    use std::collections::VecDeque;
+    use std::sync::Mutex;
+
+    /// 记录请求的下单出口；按顺序以 `rejections` 中的错误拒绝下单，用完后接受
+    #[derive(Default)]
+    struct MockGateway {
+        inner: DryRun,
+        rejections: Mutex<VecDeque<Error>>,
+        /// 全部下单请求，包括被拒绝的
+        submitted: Mutex<Vec<OrderRequest>>,
+    }
+
+    impl MockGateway {
+        fn rejecting(errors: impl IntoIterator<Item = Error>) -> Self {
+            Self {
+                rejections: Mutex::new(errors.into_iter().collect()),
+                ..Self::default()
+            }
+        }
+
+        /// 被接受的请求
+        fn operations(&self) -> Vec<(&'static str, serde_json::Value)> {
+            self.inner.requests()
+        }
+
+        fn submitted(&self) -> Vec<(Option<String>, Option<Decimal>)> {
+            self.submitted
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|request| (request.client_id.clone(), request.price))
+                .collect()
+        }
+    }
+
+    fn would_cross_error() -> Error {
+        Error::ParadexError {
+            status_code: reqwest::StatusCode::BAD_REQUEST,
+            error: Some(POST_ONLY_WOULD_CROSS.to_string()),
+            message: "post only order would cross".to_string(),
+        }
+    }
+
+    #[async_trait]
+    impl OrderGateway for MockGateway {
+        async fn create_order(&self, request: OrderRequest) -> Result<OrderUpdate, GatewayError> {
+            self.submitted.lock().unwrap().push(request.clone());
+            if let Some(error) = self.rejections.lock().unwrap().pop_front() {
+                return Err(error.into());
+            }
+            self.inner.create_order(request).await
+        }
+
+        async fn modify_order(
+            &self,
+            request: ModifyOrderRequest,
+        ) -> Result<OrderUpdate, GatewayError> {
+            self.inner.modify_order(request).await
+        }
+
+        async fn cancel_order(&self, order_id: String) -> Result<(), GatewayError> {
+            self.inner.cancel_order(order_id).await
+        }
+
+        async fn cancel_order_by_client_id(&self, client_id: String) -> Result<(), GatewayError> {
+            self.inner.cancel_order_by_client_id(client_id).await
+        }
+
+        async fn cancel_all_orders_for_market(
+            &self,
+            market: String,
+        ) -> Result<CancelByMarketResponse, GatewayError> {
+            self.inner.cancel_all_orders_for_market(market).await
+        }
+
+        async fn cancel_all_orders(&self) -> Result<Vec<String>, GatewayError> {
+            self.inner.cancel_all_orders().await
+        }
+    }
+
+    fn manager(gateway: &MockGateway) -> OrderManager<'_> {
+        let factory = OrderFactory::new(ClientIdGenerator::new("test"), &ParadexConfig::testnet());
+        OrderManager::new(gateway, OrderTracker::new(), factory)
+    }
+
+    fn limit(market: &str, side: Side, price: i64) -> NewOrder {
+        NewOrder::new(
+            market,
+            OrderSpec {
+                side,
+                order_type: OrderType::LIMIT,
+                size: Decimal::new(1, 2),
+                price: Some(Decimal::from(price)),
+                instruction: OrderInstruction::POST_ONLY,
+                client_id: None,
+                reduce_only: false,
+                trigger_price: None,
+            },
+        )
+    }
+
+    /// 订单频道对 `order` 的推送
+    fn pushed(
+        order: &ManagedOrder,
+        status: OrderStatus,
+        remaining: &str,
+        seq_no: u64,
+    ) -> OrderUpdate {
+        serde_json::from_value(serde_json::json!({
+            "account": "0x1",
+            "cancel_reason": if remaining == "0" { "" } else { "USER_CANCELED" },
+            "client_id": order.client_id(),
+            "created_at": 1735689600000u64,
+            "id": order.id(),
+            "instruction": "POST_ONLY",
+            "last_updated_at": 1735689600000u64 + seq_no,
+            "market": order.market(),
+            "price": "95000",
+            "remaining_size": remaining,
+            "side": "BUY",
+            "size": "0.01",
+            "status": status,
+            "timestamp": 1735689600000u64,
+            "type": "LIMIT",
+            "seq_no": seq_no,
+            "avg_fill_price": "0",
+            "received_at": 0,
+            "published_at": 0,
+            "flags": [],
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn submitted_orders_follow_channel_updates() {
+        let gateway = MockGateway::default();
+        let manager = manager(&gateway);
+        let order = manager
+            .submit(limit("BTC-USD-PERP", Side::BUY, 95000))
+            .await
+            .unwrap();
+        assert_eq!(order.id(), "dry-run-1");
+        assert_eq!(order.state().unwrap().state, OrderState::New);
+        assert_eq!(manager.open_orders().len(), 1);
+        assert!(order.await_open(Duration::from_millis(10)).await.is_none());
+
+        let tracker = manager.tracker().clone();
+        let feed = order.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            tracker.apply(&pushed(&feed, OrderStatus::OPEN, "0.01", 1));
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            tracker.apply(&pushed(&feed, OrderStatus::CLOSED, "0", 2));
+        });
+        let open = order.await_open(Duration::from_secs(5)).await.unwrap();
+        assert_eq!(open.state, OrderState::Open);
+        let closed = order
+            .await_filled_or_cancelled(Duration::from_secs(5))
+            .await
+            .unwrap();
+        assert_eq!(closed.state, OrderState::Filled);
+        assert!(manager.open_orders().is_empty());
+
+        // 已成交的订单不会在退出时被撤销
+        manager.cancel_all(CancelScope::Session).await.unwrap();
+        let operations: Vec<_> = gateway.operations().iter().map(|(op, _)| *op).collect();
+        assert_eq!(operations, ["create_order"]);
+    }
+
+    #[tokio::test]
+    async fn concurrent_submissions_get_distinct_client_ids() {
+        let gateway = MockGateway::default();
+        let manager = manager(&gateway);
+        let submitted = futures_util::future::join_all(
+            (0..32).map(|i| manager.submit(limit("BTC-USD-PERP", Side::BUY, 95000 - i))),
+        )
+        .await;
+        let client_ids: std::collections::HashSet<_> = submitted
+            .iter()
+            .map(|order| order.as_ref().unwrap().client_id().to_string())
+            .collect();
+        assert_eq!(client_ids.len(), 32);
+        assert!(client_ids
+            .iter()
+            .all(|id| crate::client_id::strategy_of(id) == Some("test")));
+        assert_eq!(manager.open_orders().len(), 32);
+        assert!(manager
+            .open_orders()
+            .iter()
+            .all(|order| order.strategy.as_deref() == Some("test")));
+    }
+
+    #[tokio::test]
+    async fn amend_and_cancel_use_tracked_order_details() {
+        let gateway = MockGateway::default();
+        let manager = manager(&gateway);
+        let order = manager
+            .submit(limit("ETH-USD-PERP", Side::SELL, 3300))
+            .await
+            .unwrap();
+
+        let amended = manager
+            .amend(order.client_id(), Decimal::from(3310), Decimal::new(2, 2))
+            .await
+            .unwrap();
+        assert_eq!(amended.id(), order.id());
+        manager.cancel(amended.id()).await.unwrap();
+        assert!(matches!(
+            manager.amend("unknown", Decimal::ONE, Decimal::ONE).await,
+            Err(OrderManagerError::UnknownOrder(id)) if id == "unknown"
+        ));
+
+        let operations = gateway.operations();
+        assert_eq!(operations[1].0, "modify_order");
+        assert_eq!(operations[1].1["market"], "ETH-USD-PERP");
+        assert_eq!(operations[1].1["side"], "SELL");
+        assert_eq!(operations[1].1["price"], "3310");
+        assert_eq!(operations[1].1["size"], "0.02");
+        assert_eq!(operations[2].0, "cancel_order");
+        assert_eq!(operations[2].1["id"], "dry-run-1");
+    }
+
+    /// 价格精度为 1 的 BTC 市场
+    fn btc_market() -> SharedMarkets {
+        SharedMarkets::new(MarketRegistry::new([MarketInfo {
+            symbol: "BTC-USD-PERP".to_string(),
+            base_currency: "BTC".to_string(),
+            quote_currency: "USD".to_string(),
+            price_tick_size: Decimal::ONE,
+            order_size_increment: Decimal::new(1, 3),
+            min_notional: Decimal::from(10),
+            max_order_size: Decimal::from(100),
+        }]))
+    }
+
+    fn quotes(bid: i64, ask: i64) -> BboCache {
+        let quotes = BboCache::new();
+        quotes.store(&BboEvent {
+            symbol: "BTC-USD-PERP".to_string(),
+            bid: Decimal::from(bid),
+            bid_size: Decimal::ONE,
+            ask: Decimal::from(ask),
+            ask_size: Decimal::ONE,
+            exchange_ts: Utc::now(),
+            local_ts: Utc::now(),
+        });
+        quotes
+    }
+
+    #[tokio::test]
+    async fn post_only_rejections_are_repriced_and_retried() {
+        let gateway = MockGateway::rejecting([would_cross_error(), would_cross_error()]);
+        let manager = manager(&gateway)
+            .with_markets(btc_market())
+            .with_quotes(quotes(94990, 95000));
+        let order = manager
+            .submit(limit("BTC-USD-PERP", Side::BUY, 95010))
+            .await
+            .unwrap();
+
+        let submitted = gateway.submitted();
+        let prices: Vec<_> = submitted.iter().map(|(_, price)| *price).collect();
+        assert_eq!(
+            prices,
+            [95010, 94999, 94999].map(|price| Some(Decimal::from(price)))
+        );
+        // 每次重试使用新的 client_id，只有被接受的订单保持占用
+        let client_ids: std::collections::HashSet<_> = submitted
+            .iter()
+            .map(|(id, _)| id.clone().unwrap())
+            .collect();
+        assert_eq!(client_ids.len(), 3);
+        assert_eq!(submitted[2].0.as_deref(), Some(order.client_id()));
+        assert_eq!(manager.factory.outstanding(), [order.client_id()]);
+        assert_eq!(order.state().unwrap().state, OrderState::New);
+    }
+
+    #[tokio::test]
+    async fn post_only_gives_up_after_exhausting_retries() {
+        let gateway = MockGateway::rejecting((0..3).map(|_| would_cross_error()));
+        let orders = manager(&gateway)
+            .with_markets(btc_market())
+            .with_quotes(quotes(94990, 95000));
+        let mut order = limit("BTC-USD-PERP", Side::SELL, 94980);
+        order.spec.client_id = Some("mine".to_string());
+        assert!(matches!(
+            orders.submit(order).await,
+            Err(OrderManagerError::Order(OrderError::PostOnlyWouldCross {
+                attempts: 3,
+                ..
+            }))
+        ));
+        assert_eq!(
+            gateway.submitted(),
+            [
+                (Some("mine".to_string()), Some(Decimal::from(94980))),
+                (Some("mine-r1".to_string()), Some(Decimal::from(94991))),
+                (Some("mine-r2".to_string()), Some(Decimal::from(94991))),
+            ]
+        );
+        assert!(orders.factory.outstanding().is_empty());
+
+        // 其他拒绝原因不重试；只认结构化的错误码，错误信息中的文字不算穿越盘口
+        for rejection in [
+            Error::RestError("insufficient margin".to_string()),
+            Error::RestError("post only order would cross".to_string()),
+        ] {
+            let gateway = MockGateway::rejecting([rejection]);
+            let orders = manager(&gateway)
+                .with_markets(btc_market())
+                .with_quotes(quotes(94990, 95000));
+            assert!(matches!(
+                orders.submit(limit("BTC-USD-PERP", Side::BUY, 94000)).await,
+                Err(OrderManagerError::Gateway(GatewayError::Exchange(_)))
+            ));
+            assert_eq!(gateway.submitted().len(), 1);
+        }
+    }
+
+    #[tokio::test]
+    async fn market_rules_are_checked_before_sending() {
+        let gateway = MockGateway::default();
+        let manager = manager(&gateway).with_markets(btc_market());
+
+        let mut off_tick = limit("BTC-USD-PERP", Side::BUY, 95000);
+        off_tick.spec.price = Some(Decimal::new(950005, 1));
+        assert!(matches!(
+            manager.submit(off_tick).await,
+            Err(OrderManagerError::Market(MarketRuleError::OffTick { .. }))
+        ));
+        let order = manager
+            .submit(limit("BTC-USD-PERP", Side::BUY, 95000))
+            .await
+            .unwrap();
+        assert!(matches!(
+            manager
+                .amend(order.id(), Decimal::from(94000), Decimal::new(5, 4))
+                .await,
+            Err(OrderManagerError::Market(
+                MarketRuleError::SizeBelowMinimum { .. }
+            ))
+        ));
+        // 被拦截的请求不会发送，也不会占用 client_id
+        let operations: Vec<_> = gateway.operations().iter().map(|(op, _)| *op).collect();
+        assert_eq!(operations, ["create_order"]);
+        assert_eq!(manager.factory.outstanding(), [order.client_id()]);
+    }
+
+    #[tokio::test]
+    async fn rejected_orders_release_client_ids_and_scopes_limit_cancels() {
+        let gateway = MockGateway::rejecting([Error::RestError("insufficient margin".to_string())]);
+        let manager = manager(&gateway);
+        assert!(matches!(
+            manager
+                .submit(limit("BTC-USD-PERP", Side::BUY, 95000))
+                .await,
+            Err(OrderManagerError::Gateway(GatewayError::Exchange(_)))
+        ));
+
+        let btc = manager
+            .submit(limit("BTC-USD-PERP", Side::BUY, 95000))
+            .await
+            .unwrap();
+        let eth = manager
+            .submit(limit("ETH-USD-PERP", Side::BUY, 3300))
+            .await
+            .unwrap();
+        manager
+            .cancel_all(CancelScope::Market("BTC-USD-PERP".to_string()))
+            .await
+            .unwrap();
+        // 只有 ETH 订单仍需在退出时撤销；被拒绝的订单从未占用 client_id
+        manager.cancel_all(CancelScope::Session).await.unwrap();
+
+        let operations = gateway.operations();
+        let names: Vec<_> = operations.iter().map(|(op, _)| *op).collect();
+        assert_eq!(
+            names,
+            [
+                "create_order",
+                "create_order",
+                "cancel_all_orders_for_market",
+                "cancel_order_by_client_id"
+            ]
+        );
+        assert_eq!(operations[3].1["client_id"], eth.client_id());
+        assert_ne!(btc.client_id(), eth.client_id());
+    }
+
+    fn marks(mark: i64) -> MarketSummaryCache {
+        let summaries = MarketSummaryCache::new();
+        summaries.seed(&MarketStats {
+            symbol: "BTC-USD-PERP".to_string(),
+            mark_price: Some(Decimal::from(mark)),
+            open_interest: None,
+            funding_rate: None,
+        });
+        summaries
+    }
+
+    #[tokio::test]
+    async fn trigger_orders_are_checked_against_the_mark_price() {
+        let gateway = MockGateway::default();
+        let manager = manager(&gateway)
+            .with_markets(btc_market())
+            .with_summaries(marks(95000));
+        let size = Decimal::new(1, 2);
+        manager
+            .submit_stop_market("BTC-USD-PERP", Side::SELL, size, Decimal::from(94000))
+            .await
+            .unwrap();
+        manager
+            .submit_stop_limit(
+                "BTC-USD-PERP",
+                Side::BUY,
+                size,
+                Decimal::from(96000),
+                Decimal::from(96100),
+            )
+            .await
+            .unwrap();
+        manager
+            .submit_take_profit("BTC-USD-PERP", Side::SELL, size, Decimal::from(97000), None)
+            .await
+            .unwrap();
+
+        let requests = gateway.submitted.lock().unwrap().clone();
+        let fields: Vec<_> = requests
+            .iter()
+            .map(|request| {
+                (
+                    request.order_type,
+                    request.price,
+                    request.trigger_price,
+                    request.instruction.clone(),
+                    request.flags.clone(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            fields,
+            [
+                (
+                    OrderType::STOP_MARKET,
+                    None,
+                    Some(Decimal::from(94000)),
+                    OrderInstruction::GTC,
+                    vec![OrderFlags::STOP_CONDITION_BELOW_TRIGGER],
+                ),
+                (
+                    OrderType::STOP_LIMIT,
+                    Some(Decimal::from(96100)),
+                    Some(Decimal::from(96000)),
+                    OrderInstruction::GTC,
+                    vec![OrderFlags::STOP_CONDITION_ABOVE_TRIGGER],
+                ),
+                (
+                    OrderType::TAKE_PROFIT_MARKET,
+                    None,
+                    Some(Decimal::from(97000)),
+                    OrderInstruction::GTC,
+                    vec![
+                        OrderFlags::REDUCE_ONLY,
+                        OrderFlags::STOP_CONDITION_ABOVE_TRIGGER
+                    ],
+                ),
+            ]
+        );
+
+        // 触发价在错误一侧、不符合价格精度或缺少标记价格时都不发送
+        assert!(matches!(
+            manager
+                .submit_stop_market("BTC-USD-PERP", Side::BUY, size, Decimal::from(94000))
+                .await,
+            Err(OrderManagerError::Order(OrderError::TriggerOnWrongSide {
+                above: true,
+                ..
+            }))
+        ));
+        assert!(matches!(
+            manager
+                .submit_take_profit(
+                    "BTC-USD-PERP",
+                    Side::BUY,
+                    size,
+                    Decimal::from(96000),
+                    Some(Decimal::from(95900))
+                )
+                .await,
+            Err(OrderManagerError::Order(OrderError::TriggerOnWrongSide {
+                above: false,
+                ..
+            }))
+        ));
+        assert!(matches!(
+            manager
+                .submit_stop_market("BTC-USD-PERP", Side::SELL, size, Decimal::new(940005, 1))
+                .await,
+            Err(OrderManagerError::Market(MarketRuleError::OffTick { .. }))
+        ));
+        assert!(matches!(
+            manager
+                .submit_stop_market("ETH-USD-PERP", Side::SELL, size, Decimal::from(3000))
+                .await,
+            Err(OrderManagerError::Order(OrderError::NoMarkPrice(_)))
+        ));
+        assert_eq!(gateway.submitted.lock().unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn trigger_orders_are_dispatched_by_kind() {
+        let gateway = MockGateway::default();
+        let manager = manager(&gateway)
+            .with_markets(btc_market())
+            .with_summaries(marks(95000));
+        let order = |kind, side, trigger, limit_price: Option<i64>| TriggerOrder {
+            kind,
+            side,
+            size: Decimal::new(1, 2),
+            trigger: Decimal::from(trigger),
+            limit_price: limit_price.map(Decimal::from),
+        };
+        for order in [
+            order(TriggerKind::Stop, Side::SELL, 94000, None),
+            order(TriggerKind::Stop, Side::BUY, 96000, Some(96100)),
+            order(TriggerKind::TakeProfit, Side::SELL, 97000, Some(96900)),
+            order(TriggerKind::StopLoss, Side::SELL, 94000, None),
+        ] {
+            manager
+                .submit_trigger_order("BTC-USD-PERP", &order)
+                .await
+                .unwrap();
+        }
+
+        let types: Vec<_> = gateway
+            .submitted
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|request| (request.order_type, request.price))
+            .collect();
+        assert_eq!(
+            types,
+            [
+                (OrderType::STOP_MARKET, None),
+                (OrderType::STOP_LIMIT, Some(Decimal::from(96100))),
+                (OrderType::TAKE_PROFIT_LIMIT, Some(Decimal::from(96900))),
+                (OrderType::STOP_LOSS_MARKET, None),
+            ]
+        );
+    }
+
+    fn long_btc(size: &str) -> PositionCache {
+        let position: Position = serde_json::from_value(serde_json::json!({
+            "average_entry_price": "95000",
+            "average_entry_price_usd": "95000",
+            "cached_funding_index": "0",
+            "cost": "0",
+            "cost_usd": "0",
+            "id": "1",
+            "last_fill_id": "1",
+            "last_updated_at": 0,
+            "leverage": "",
+            "liquidation_price": "",
+            "market": "BTC-USD-PERP",
+            "seq_no": 1,
+            "side": "LONG",
+            "size": size,
+            "status": "OPEN",
+            "unrealized_funding_pnl": "0",
+            "unrealized_pnl": "0"
+        }))
+        .unwrap();
+        PositionCache::from_snapshot(Decimal::ZERO, &[position])
+    }
+
+    #[tokio::test]
+    async fn reduce_only_orders_are_checked_against_the_position() {
+        let reduce = |side, size| {
+            let mut order = limit("BTC-USD-PERP", side, 95000);
+            order.spec.instruction = OrderInstruction::GTC;
+            order.spec.size = size;
+            order.spec.reduce_only = true;
+            order
+        };
+        let gateway = MockGateway::default();
+        let strict = manager(&gateway).with_positions(long_btc("0.004"));
+        assert!(matches!(
+            strict.submit(reduce(Side::BUY, Decimal::new(1, 3))).await,
+            Err(OrderManagerError::Order(
+                OrderError::ReduceOnlyWrongSide { .. }
+            ))
+        ));
+        assert!(matches!(
+            strict.submit(reduce(Side::SELL, Decimal::new(1, 2))).await,
+            Err(OrderManagerError::Order(
+                OrderError::ReduceOnlyExceedsPosition { .. }
+            ))
+        ));
+        // 非只减仓订单不受持仓限制
+        strict
+            .submit(limit("BTC-USD-PERP", Side::BUY, 95000))
+            .await
+            .unwrap();
+
+        let clamped = manager(&gateway)
+            .with_positions(long_btc("0.004"))
+            .with_reduce_only_clamp(true);
+        clamped
+            .submit(reduce(Side::SELL, Decimal::new(1, 2)))
+            .await
+            .unwrap();
+        assert!(matches!(
+            clamped
+                .submit(NewOrder::new(
+                    "ETH-USD-PERP",
+                    reduce(Side::SELL, Decimal::ONE).spec
+                ))
+                .await,
+            Err(OrderManagerError::Order(OrderError::NoPositionToReduce(_)))
+        ));
+
+        let requests = gateway.submitted.lock().unwrap().clone();
+        assert_eq!(requests.len(), 2);
+        assert!(requests[0].flags.is_empty());
+        assert_eq!(requests[1].size, Decimal::new(4, 3));
+        assert_eq!(requests[1].flags, [OrderFlags::REDUCE_ONLY]);
+    }
+
+    fn thin_book() -> OrderBooks {
+        let books = OrderBooks::new(&["BTC-USD-PERP".to_string()]);
+        let level = |price: i64, size: i64| Level(Decimal::from(price), Decimal::new(size, 3));
+        books
+            .get("BTC-USD-PERP")
+            .unwrap()
+            .write()
+            .unwrap()
+            .seed(&OrderBookSnapshot {
+                market: "BTC-USD-PERP".to_string(),
+                seq_no: Some(1),
+                last_updated_at: None,
+                bids: vec![level(94990, 5), level(94900, 20)],
+                asks: vec![level(95010, 5), level(95300, 20)],
+            });
+        books
+    }
+
+    #[tokio::test]
+    async fn market_orders_abort_on_thin_books_and_cap_the_limit_price() {
+        let gateway = MockGateway::default();
+        let orders = manager(&gateway)
+            .with_markets(btc_market())
+            .with_order_books(thin_book());
+        let cap = Decimal::from(20);
+
+        // 吃穿第一档后均价偏离中间价 95000 超过 20 bps
+        assert!(matches!(
+            orders
+                .submit_market("BTC-USD-PERP", Side::BUY, Decimal::new(2, 2), cap)
+                .await,
+            Err(OrderManagerError::Risk(RiskViolation::Slippage { .. }))
+        ));
+        // 超出订单簿全部深度
+        assert!(matches!(
+            orders
+                .submit_market("BTC-USD-PERP", Side::SELL, Decimal::ONE, cap)
+                .await,
+            Err(OrderManagerError::Risk(
+                RiskViolation::InsufficientLiquidity { .. }
+            ))
+        ));
+        // 没有订单簿与 BBO 时不下单
+        assert!(matches!(
+            orders
+                .submit_market("ETH-USD-PERP", Side::BUY, Decimal::ONE, cap)
+                .await,
+            Err(OrderManagerError::Risk(RiskViolation::MissingBook(_)))
+        ));
+        assert!(gateway.submitted().is_empty());
+
+        // 95000 * (1 - 0.002) = 94810
+        let sell = orders
+            .submit_market("BTC-USD-PERP", Side::SELL, Decimal::new(5, 3), cap)
+            .await
+            .unwrap();
+        assert_eq!(sell.limit_price, Some(Decimal::from(94810)));
+        assert_eq!(sell.estimate.avg_price, Decimal::from(94990));
+        assert_eq!(
+            sell.estimated_slippage_bps().round_dp(4),
+            Decimal::new(10526, 4)
+        );
+
+        let ledger = FillLedger::new();
+        assert!(sell.report(&ledger).ends_with("realized no fills"));
+        for (id, price) in [("1", 94990), ("2", 94900)] {
+            ledger.record(FillRecord {
+                id: id.to_string(),
+                created_at: 0,
+                market: "BTC-USD-PERP".to_string(),
+                side: "SELL".to_string(),
+                price: Decimal::from(price),
+                size: Decimal::new(25, 4),
+                fee: Decimal::ZERO,
+                fee_currency: "USDC".to_string(),
+                liquidity: "TAKER".to_string(),
+                order_id: sell.order.id().to_string(),
+                client_id: sell.order.client_id().to_string(),
+            });
+        }
+        assert_eq!(sell.realized_price(&ledger), Some(Decimal::from(94945)));
+        assert!(sell.report(&ledger).ends_with("realized 94945 (5.79 bps)"));
+
+        // 市价单模式不带价格；未维护订单簿时按 BBO 估算
+        let market = manager(&gateway)
+            .with_quotes(quotes(94990, 95010))
+            .with_aggressive_mode(AggressiveMode::Market);
+        let buy = market
+            .submit_market("BTC-USD-PERP", Side::BUY, Decimal::new(1, 3), cap)
+            .await
+            .unwrap();
+        assert_eq!(buy.limit_price, None);
+        assert_eq!(buy.estimate.avg_price, Decimal::from(95010));
+        let requests = gateway.submitted.lock().unwrap().clone();
+        assert_eq!(
+            requests
+                .iter()
+                .map(|request| (
+                    request.order_type,
+                    request.price,
+                    request.instruction.clone()
+                ))
+                .collect::<Vec<_>>(),
+            [
+                (
+                    OrderType::LIMIT,
+                    Some(Decimal::from(94810)),
+                    OrderInstruction::IOC
+                ),
+                (OrderType::MARKET, None, OrderInstruction::IOC),
+            ]
+        );
+    }
+}
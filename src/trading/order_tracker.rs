@@ -0,0 +1,434 @@
+//! 订单状态跟踪：消费订单频道的推送，按交易所订单 ID 与 client_id 维护每个订单的最新状态
+//!
+//! Paradex 只推送 NEW / OPEN / CLOSED 三种状态，这里结合剩余数量与取消原因细分为
+//! [`OrderState`]。推送可能乱序到达：按 `seq_no` 丢弃旧消息，终态不会被非终态覆盖。
+//! 本程序生成的 client_id 带有策略标识，订单与成交推送据此归属到下单的策略。
+
+use paradex::{
+    structs::{OrderStatus, OrderUpdate, Side},
+    ws::Message,
+};
+use rust_decimal::Decimal;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{broadcast, Notify};
+
+use crate::client_id::strategy_of;
+use crate::markets::decimal;
+
+/// 事件通道缓存的事件数，落后更多的订阅者会收到 `Lagged`
+const EVENT_CAPACITY: usize = 256;
+
+/// 视为撤单（而非被拒绝）的取消原因
+const CANCEL_REASONS: [&str; 2] = ["USER_CANCELED", "EXPIRED"];
+
+/// 订单状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrderState {
+    New,
+    Open,
+    PartiallyFilled,
+    Filled,
+    Cancelled,
+    Rejected,
+}
+
+impl OrderState {
+    /// 推送中的交易所状态对应的订单状态
+    pub fn from_update(update: &OrderUpdate) -> Self {
+        let filled = update.remaining_size < update.size;
+        match update.status {
+            OrderStatus::NEW => OrderState::New,
+            OrderStatus::OPEN if filled => OrderState::PartiallyFilled,
+            OrderStatus::OPEN => OrderState::Open,
+            OrderStatus::CLOSED if update.remaining_size.is_zero() => OrderState::Filled,
+            OrderStatus::CLOSED
+                if filled || CANCEL_REASONS.contains(&update.cancel_reason.as_str()) =>
+            {
+                OrderState::Cancelled
+            }
+            OrderStatus::CLOSED => OrderState::Rejected,
+        }
+    }
+
+    /// 成交、撤单与拒绝为终态
+    pub fn is_terminal(self) -> bool {
+        matches!(
+            self,
+            OrderState::Filled | OrderState::Cancelled | OrderState::Rejected
+        )
+    }
+}
+
+/// 跟踪中的订单
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrackedOrder {
+    pub id: String,
+    pub client_id: String,
+    /// client_id 中的策略标识；其他客户端提交的订单为 `None`
+    pub strategy: Option<String>,
+    pub market: String,
+    pub side: Side,
+    pub size: Decimal,
+    pub remaining_size: Decimal,
+    /// 尚未成交时为 `None`
+    pub avg_fill_price: Option<Decimal>,
+    pub state: OrderState,
+    /// 撤单或拒绝的原因
+    pub cancel_reason: Option<String>,
+    /// 创建与最近更新时间（毫秒时间戳）
+    pub created_at: u64,
+    pub last_updated_at: u64,
+    pub seq_no: u64,
+}
+
+impl TrackedOrder {
+    fn from_update(update: &OrderUpdate) -> Self {
+        Self {
+            id: update.id.clone(),
+            client_id: update.client_id.clone(),
+            strategy: strategy_of(&update.client_id).map(str::to_string),
+            market: update.market.clone(),
+            side: update.side,
+            size: update.size,
+            remaining_size: update.remaining_size,
+            avg_fill_price: Some(decimal(update.avg_fill_price)).filter(|price| !price.is_zero()),
+            state: OrderState::from_update(update),
+            cancel_reason: Some(update.cancel_reason.clone()).filter(|reason| !reason.is_empty()),
+            created_at: update.created_at,
+            last_updated_at: update.last_updated_at,
+            seq_no: update.seq_no,
+        }
+    }
+
+    /// 已成交数量
+    pub fn filled_size(&self) -> Decimal {
+        self.size - self.remaining_size
+    }
+}
+
+/// 订单状态变化
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrderEvent {
+    /// 首次收到该订单时为 `None`
+    pub previous: Option<OrderState>,
+    pub order: TrackedOrder,
+}
+
+#[derive(Default)]
+struct State {
+    orders: HashMap<String, TrackedOrder>,
+    /// client_id -> 交易所订单 ID
+    client_ids: HashMap<String, String>,
+}
+
+struct Inner {
+    state: Mutex<State>,
+    updated: Notify,
+    events: broadcast::Sender<OrderEvent>,
+}
+
+/// 订单状态表，克隆后共享同一份数据
+#[derive(Clone)]
+pub struct OrderTracker {
+    inner: Arc<Inner>,
+}
+
+impl Default for OrderTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl OrderTracker {
+    /// 尚未跟踪任何订单
+    pub fn new() -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                state: Mutex::new(State::default()),
+                updated: Notify::new(),
+                events: broadcast::channel(EVENT_CAPACITY).0,
+            }),
+        }
+    }
+
+    /// 处理订单频道的消息，其他消息忽略
+    pub fn on_message(&self, message: &Message) {
+        if let Message::Orders(update) = message {
+            self.apply(update);
+        }
+    }
+
+    /// 应用一条订单推送；过期（`seq_no` 不大于已记录的值）或试图离开终态的推送被忽略，返回 `None`
+    pub fn apply(&self, update: &OrderUpdate) -> Option<OrderEvent> {
+        let order = TrackedOrder::from_update(update);
+        let event = {
+            let mut state = self.inner.state.lock().unwrap();
+            let previous = state.orders.get(&order.id);
+            if let Some(previous) = previous {
+                let stale = order.seq_no <= previous.seq_no;
+                if stale || (previous.state.is_terminal() && !order.state.is_terminal()) {
+                    return None;
+                }
+            }
+            let event = OrderEvent {
+                previous: previous.map(|previous| previous.state),
+                order: order.clone(),
+            };
+            if !order.client_id.is_empty() {
+                state
+                    .client_ids
+                    .insert(order.client_id.clone(), order.id.clone());
+            }
+            state.orders.insert(order.id.clone(), order);
+            event
+        };
+        self.inner.updated.notify_waiters();
+        // 没有订阅者时发送失败，忽略
+        let _ = self.inner.events.send(event.clone());
+        Some(event)
+    }
+
+    /// 按交易所订单 ID 或 client_id 查找
+    pub fn get(&self, id: &str) -> Option<TrackedOrder> {
+        let state = self.inner.state.lock().unwrap();
+        let id = state.client_ids.get(id).map(String::as_str).unwrap_or(id);
+        state.orders.get(id).cloned()
+    }
+
+    /// 订单或成交推送所属的策略：成交按订单 ID 查找跟踪中的订单，找不到时解析其 client_id
+    pub fn strategy(&self, message: &Message) -> Option<String> {
+        match message {
+            Message::Orders(update) => strategy_of(&update.client_id).map(str::to_string),
+            Message::Fills(fill) => self
+                .get(&fill.order_id)
+                .and_then(|order| order.strategy)
+                .or_else(|| strategy_of(&fill.client_id).map(str::to_string)),
+            _ => None,
+        }
+    }
+
+    /// 尚未进入终态的订单，按创建时间排列
+    pub fn open_orders(&self) -> Vec<TrackedOrder> {
+        let mut orders: Vec<_> = self
+            .inner
+            .state
+            .lock()
+            .unwrap()
+            .orders
+            .values()
+            .filter(|order| !order.state.is_terminal())
+            .cloned()
+            .collect();
+        orders.sort_by(|a, b| a.created_at.cmp(&b.created_at).then(a.id.cmp(&b.id)));
+        orders
+    }
+
+    /// 订阅状态变化事件；订阅之前的事件不会补发
+    pub fn subscribe(&self) -> broadcast::Receiver<OrderEvent> {
+        self.inner.events.subscribe()
+    }
+
+    /// 等待订单（交易所订单 ID 或 client_id）进入终态；超时返回 `None`
+    pub async fn await_terminal(&self, id: &str, timeout: Duration) -> Option<TrackedOrder> {
+        self.await_state(id, timeout, OrderState::is_terminal).await
+    }
+
+    /// 等待订单的状态满足 `reached`；超时返回 `None`
+    pub async fn await_state(
+        &self,
+        id: &str,
+        timeout: Duration,
+        reached: impl Fn(OrderState) -> bool,
+    ) -> Option<TrackedOrder> {
+        let wait = async {
+            loop {
+                let updated = self.inner.updated.notified();
+                tokio::pin!(updated);
+                // 先登记等待再检查状态，避免错过检查与等待之间的更新
+                updated.as_mut().enable();
+                if let Some(order) = self.get(id).filter(|order| reached(order.state)) {
+                    return order;
+                }
+                updated.await;
+            }
+        };
+        tokio::time::timeout(timeout, wait).await.ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn update(status: &str, remaining: &str, seq_no: u64, cancel_reason: &str) -> OrderUpdate {
+        serde_json::from_value(serde_json::json!({
+            "account": "0x1",
+            "cancel_reason": cancel_reason,
+            "client_id": "tlp-1",
+            "created_at": 1735689600000u64,
+            "id": "o-1",
+            "instruction": "GTC",
+            "last_updated_at": 1735689600000u64 + seq_no,
+            "market": "BTC-USD-PERP",
+            "price": "95000",
+            "remaining_size": remaining,
+            "side": "BUY",
+            "size": "0.01",
+            "status": status,
+            "timestamp": 1735689600000u64,
+            "type": "LIMIT",
+            "seq_no": seq_no,
+            "avg_fill_price": if remaining == "0.01" { "0" } else { "95000" },
+            "received_at": 0,
+            "published_at": 0,
+            "flags": [],
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn maps_exchange_status_to_order_state() {
+        let state = |status, remaining, reason| {
+            OrderState::from_update(&update(status, remaining, 1, reason))
+        };
+        assert_eq!(state("NEW", "0.01", ""), OrderState::New);
+        assert_eq!(state("OPEN", "0.01", ""), OrderState::Open);
+        assert_eq!(state("OPEN", "0.004", ""), OrderState::PartiallyFilled);
+        assert_eq!(state("CLOSED", "0", ""), OrderState::Filled);
+        assert_eq!(
+            state("CLOSED", "0.01", "USER_CANCELED"),
+            OrderState::Cancelled
+        );
+        assert_eq!(
+            state("CLOSED", "0.004", "NOT_ENOUGH_MARGIN"),
+            OrderState::Cancelled
+        );
+        assert_eq!(
+            state("CLOSED", "0.01", "POST_ONLY_WOULD_CROSS"),
+            OrderState::Rejected
+        );
+    }
+
+    #[test]
+    fn tracks_transitions_and_ignores_out_of_order_updates() {
+        let tracker = OrderTracker::new();
+        let mut events = tracker.subscribe();
+
+        tracker.on_message(&Message::Orders(update("NEW", "0.01", 1, "")));
+        tracker.apply(&update("OPEN", "0.004", 3, "")).unwrap();
+        // 迟到的 OPEN 不会覆盖部分成交
+        assert!(tracker.apply(&update("OPEN", "0.01", 2, "")).is_none());
+
+        let order = tracker.get("tlp-1").unwrap();
+        assert_eq!(order.state, OrderState::PartiallyFilled);
+        assert_eq!(order.filled_size(), Decimal::new(6, 3));
+        assert_eq!(order.avg_fill_price, Some(Decimal::from(95000)));
+        assert_eq!(tracker.open_orders().len(), 1);
+
+        tracker.apply(&update("CLOSED", "0", 5, "")).unwrap();
+        // 终态之后的旧推送被忽略
+        assert!(tracker.apply(&update("OPEN", "0.004", 4, "")).is_none());
+        let order = tracker.get("o-1").unwrap();
+        assert_eq!(order.state, OrderState::Filled);
+        assert_eq!(order.last_updated_at, 1735689600005);
+        assert!(tracker.open_orders().is_empty());
+
+        let states: Vec<_> = std::iter::from_fn(|| events.try_recv().ok())
+            .map(|event| (event.previous, event.order.state))
+            .collect();
+        assert_eq!(
+            states,
+            [
+                (None, OrderState::New),
+                (Some(OrderState::New), OrderState::PartiallyFilled),
+                (Some(OrderState::PartiallyFilled), OrderState::Filled),
+            ]
+        );
+    }
+
+    #[test]
+    fn terminal_update_arriving_first_is_kept() {
+        let tracker = OrderTracker::new();
+        tracker
+            .apply(&update("CLOSED", "0.01", 4, "USER_CANCELED"))
+            .unwrap();
+        assert!(tracker.apply(&update("NEW", "0.01", 1, "")).is_none());
+        assert!(tracker.apply(&update("OPEN", "0.01", 2, "")).is_none());
+        let order = tracker.get("o-1").unwrap();
+        assert_eq!(order.state, OrderState::Cancelled);
+        assert_eq!(order.cancel_reason.as_deref(), Some("USER_CANCELED"));
+        assert_eq!(order.avg_fill_price, None);
+    }
+
+    #[test]
+    fn order_and_fill_messages_are_attributed_to_the_strategy() {
+        let tracker = OrderTracker::new();
+        let mut tagged = update("OPEN", "0.01", 1, "");
+        tagged.client_id = "tl-grid-1735689600000-7".to_string();
+        let message = Message::Orders(tagged.clone());
+        tracker.on_message(&message);
+        assert_eq!(tracker.strategy(&message).as_deref(), Some("grid"));
+        assert_eq!(
+            tracker.get("o-1").unwrap().strategy.as_deref(),
+            Some("grid")
+        );
+
+        let fill = |order_id: &str, client_id: &str| {
+            Message::Fills(
+                serde_json::from_value(serde_json::json!({
+                    "id": "f-1",
+                    "client_id": client_id,
+                    "created_at": 1735689600100u64,
+                    "fee": "0.01",
+                    "fee_currency": "USDC",
+                    "liquidity": "MAKER",
+                    "market": "BTC-USD-PERP",
+                    "order_id": order_id,
+                    "price": "95000",
+                    "side": "BUY",
+                    "size": "0.01",
+                    "remaining_size": "0",
+                    "fill_type": "FILL",
+                    "realized_pnl": "0",
+                }))
+                .unwrap(),
+            )
+        };
+        // 成交推送不一定带 client_id，按订单 ID 找到跟踪中的订单
+        assert_eq!(tracker.strategy(&fill("o-1", "")).as_deref(), Some("grid"));
+        assert_eq!(
+            tracker
+                .strategy(&fill("o-2", "tl-hedge-1735689600000-0"))
+                .as_deref(),
+            Some("hedge")
+        );
+        assert_eq!(tracker.strategy(&fill("o-3", "manual")), None);
+        tagged.client_id = "tlp-1".to_string();
+        assert_eq!(tracker.strategy(&Message::Orders(tagged)), None);
+    }
+
+    #[tokio::test]
+    async fn await_terminal_resolves_on_terminal_state() {
+        let tracker = OrderTracker::new();
+        tracker.apply(&update("OPEN", "0.01", 1, "")).unwrap();
+        assert!(tracker
+            .await_terminal("o-1", Duration::from_millis(20))
+            .await
+            .is_none());
+
+        let feeder = tracker.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            feeder.apply(&update("OPEN", "0.004", 2, ""));
+            feeder.apply(&update("CLOSED", "0.004", 3, "USER_CANCELED"));
+        });
+        let order = tracker
+            .await_terminal("tlp-1", Duration::from_secs(5))
+            .await
+            .unwrap();
+        assert_eq!(order.state, OrderState::Cancelled);
+        assert_eq!(order.remaining_size, Decimal::new(4, 3));
+    }
+}